@@ -0,0 +1,269 @@
+//! Keyboard macro-style command sequences ("actions"): a named list of Ex commands and plugin
+//! function calls which runs as one step, e.g. "format file, save, run tests".
+//!
+//! - Define your own actions in `get_default_actions()`, the optional `key` binds the action to
+//!   a normal mode key.
+//! - `:Action {name}` runs the action, all actions are also listed in the picker registry
+//!   (`<leader>pp` -> "actions").
+//!
+//! Every step shows a progress notification, and the action stops at the first failed step.
+//!
+
+///
+/// A single step of the action
+///
+#[derive(Debug, Clone)]
+pub enum ActionStep {
+    //
+    // Ex command without the leading ':', e.g. "write"
+    //
+    ExCommand(String),
+
+    //
+    // Plugin function call: (description, function), the function returns `false` to stop the
+    // action.
+    //
+    Function(&'static str, fn() -> bool),
+}
+
+impl ActionStep {
+    fn description(&self) -> &str {
+        match self {
+            Self::ExCommand(cmd) => cmd,
+            Self::Function(desc, _) => desc,
+        }
+    }
+}
+
+///
+/// Named action sequence
+///
+#[derive(Debug, Clone)]
+pub struct Action {
+    pub name: String,
+    pub desc: String,
+    pub steps: Vec<ActionStep>,
+
+    //
+    // Normal mode key binding, e.g. "<leader>af"
+    //
+    pub key: Option<String>,
+}
+
+///
+/// Private module-scope state: all actions (in the defining order)
+///
+static ACTIONS_STATE: LazyLock<Mutex<Vec<Action>>> =
+    LazyLock::new(|| Mutex::new(get_default_actions()));
+
+///
+/// Define your actions here
+///
+fn get_default_actions() -> Vec<Action> {
+    vec![
+        Action {
+            name: "format_save_run".to_string(),
+            desc: "Format file, save, run the default project command".to_string(),
+            steps: vec![
+                ActionStep::ExCommand("lua vim.lsp.buf.format({ async = false })".to_string()),
+                ActionStep::ExCommand("write".to_string()),
+                ActionStep::Function(
+                    "run the default project command",
+                    project_command::run_default_command,
+                ),
+            ],
+            key: Some("<leader>af".to_string()),
+        },
+        Action {
+            name: "save_all_run".to_string(),
+            desc: "Save all buffers, run the default project command".to_string(),
+            steps: vec![
+                ActionStep::ExCommand("wall".to_string()),
+                ActionStep::Function(
+                    "run the default project command",
+                    project_command::run_default_command,
+                ),
+            ],
+            key: None,
+        },
+    ]
+}
+
+///
+/// Progress notification
+///
+fn notify_action(message: &str, level: LogLevel) {
+    let _ = notify(message, level, &Dictionary::new());
+}
+
+///
+/// Run the given action by name
+///
+pub fn run_action(name: &str) {
+    //
+    // Copy the action and release the mutex lock immediately, as the steps may run another
+    // action.
+    //
+    let action = ACTIONS_STATE
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|action| action.name == name)
+        .cloned();
+
+    let Some(action) = action else {
+        notify_action(
+            &format!("[ actions ] Action not found: {name}"),
+            LogLevel::Warn,
+        );
+        return;
+    };
+
+    let step_count = action.steps.len();
+    for (index, step) in action.steps.iter().enumerate() {
+        notify_action(
+            &format!(
+                "[ actions ] {} ({}/{step_count}): {}",
+                action.name,
+                index + 1,
+                step.description()
+            ),
+            LogLevel::Info,
+        );
+
+        let step_result = match step {
+            ActionStep::ExCommand(cmd) => command(cmd).map_err(|error| format!("{error}")),
+            ActionStep::Function(desc, f) => match f() {
+                true => Ok(()),
+                false => Err(format!("'{desc}' failed")),
+            },
+        };
+
+        if let Err(error) = step_result {
+            notify_action(
+                &format!(
+                    "[ actions ] {} stopped at step {}/{step_count}: {error}",
+                    action.name,
+                    index + 1
+                ),
+                LogLevel::Error,
+            );
+            return;
+        }
+    }
+
+    notify_action(
+        &format!("[ actions ] {} done.", action.name),
+        LogLevel::Info,
+    );
+}
+
+///
+/// Bind the action to the normal mode key
+///
+fn bind_action_key(name: &str, key: &str, desc: &str) {
+    let action_name = name.to_owned();
+    let _ = set_keymap(
+        Mode::Normal,
+        key,
+        "",
+        &SetKeymapOpts::builder()
+            .desc(&format!("Action: {desc}"))
+            .callback(move |_| {
+                run_action(&action_name);
+                ()
+            })
+            .silent(true)
+            .build(),
+    );
+}
+
+///
+///
+///
+pub fn setup() {
+    //
+    // Key bindings of the default actions
+    //
+    let key_bindings = ACTIONS_STATE
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|action| {
+            action
+                .key
+                .clone()
+                .map(|key| (action.name.clone(), key, action.desc.clone()))
+        })
+        .collect::<Vec<(String, String, String)>>();
+
+    for (name, key, desc) in key_bindings {
+        bind_action_key(&name, &key, &desc);
+    }
+
+    let _ = create_user_command(
+        "Action",
+        |args: CommandArgs| {
+            run_action(args.args.as_deref().unwrap_or_default().trim());
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Run the named action: ':Action {name}'")
+            .nargs(CommandNArgs::One)
+            .build(),
+    );
+
+    //
+    // Contribute the actions to the picker registry (`<leader>pp`), it works as the command
+    // palette.
+    //
+    register_picker_source(
+        "actions",
+        "Actions",
+        || {
+            ACTIONS_STATE
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|action| PickerItem {
+                    columns: vec![
+                        PickerItemColumn::new(&action.name, Some("Function")),
+                        PickerItemColumn::new(&action.desc, Some("Comment")),
+                        PickerItemColumn::new(
+                            action.key.as_deref().unwrap_or_default(),
+                            Some("Special"),
+                        ),
+                    ],
+                    icon: None,
+                    data: action.name.clone(),
+                })
+                .collect::<Vec<PickerItem>>()
+        },
+        |selected_name: String| {
+            //
+            // Run it in the next event loop tick, as the picker windows are still closing at
+            // this moment.
+            //
+            nvim::schedule(move |_| {
+                run_action(&selected_name);
+            });
+        },
+    );
+}
+
+use crate::{
+    picker::{PickerItem, PickerItemColumn, register_picker_source},
+    project_command,
+};
+
+use std::sync::{LazyLock, Mutex};
+
+use nvim_oxi::{
+    self as nvim, Dictionary,
+    api::{
+        command, create_user_command, notify,
+        opts::{CreateCommandOpts, SetKeymapOpts},
+        set_keymap,
+        types::{CommandArgs, CommandNArgs, LogLevel, Mode},
+    },
+};
@@ -0,0 +1,326 @@
+//! ASCII diagram helpers for the documentation comments, e.g. the window diagrams in
+//! `picker.rs`:
+//!
+//! ```text
+//! /-------------\
+//! | Title       | <-- Title window and buffer.
+//! |-------------|
+//! | User input  |
+//! \-------------/
+//! ```
+//!
+//! - `:[range]DiagramBox` (`<leader>db` in `VISUAL` mode): Draw a box around the selected
+//!   lines. If the selection is already a box, it's re-flowed instead, so the new lines inside
+//!   it become part of it.
+//! - `:[range]DiagramReflow` (`<leader>dr`): Re-flow the box borders after editing, the width
+//!   fits the longest content line, and the arrows stay one space after the box.
+//! - `:[range]DiagramArrow {label}` (`<leader>da`): Add (or replace) the `<-- label` arrow on the
+//!   right side of the middle selected line.
+//!
+//! It's comment-aware: the comment leader (from `commentstring`, e.g. `///` or `#`) of the first
+//! line is kept on all lines and the diagram is drawn after it.
+//!
+
+///
+/// The arrow marker on the right side of the box
+///
+const DIAGRAM_ARROW: &'static str = "<--";
+
+///
+/// The classified diagram line (without the comment prefix and the arrow)
+///
+#[derive(Debug, Clone, PartialEq)]
+enum DiagramLine {
+    TopBorder,
+    BottomBorder,
+    Separator,
+    Content(String),
+    Other(String),
+}
+
+///
+/// The diagram line with its arrow (e.g. `<-- Title window`), if any
+///
+#[derive(Debug, Clone)]
+struct ParsedDiagramLine {
+    line: DiagramLine,
+    arrow: Option<String>,
+}
+
+///
+/// Whether the text is `{start}---{end}`
+///
+fn is_border(text: &str, start: char, end: char) -> bool {
+    text.len() >= 3
+        && text.starts_with(start)
+        && text.ends_with(end)
+        && text[1..text.len() - 1].chars().all(|c| c == '-')
+}
+
+///
+/// Classify the line (without the comment prefix)
+///
+fn parse_diagram_line(text: &str) -> ParsedDiagramLine {
+    let (body, arrow) = match text.find(DIAGRAM_ARROW) {
+        Some(pos) if pos > 0 => (
+            text[..pos].trim_end(),
+            Some(text[pos..].trim_end().to_owned()),
+        ),
+        _ => (text.trim_end(), None),
+    };
+
+    let line = if is_border(body, '/', '\\') {
+        DiagramLine::TopBorder
+    } else if is_border(body, '\\', '/') {
+        DiagramLine::BottomBorder
+    } else if is_border(body, '|', '|') {
+        DiagramLine::Separator
+    } else if let Some(content) = body.strip_prefix('|') {
+        //
+        // The right border may be deleted by the edit
+        //
+        let content = content.strip_suffix('|').unwrap_or(content);
+        let content = content.strip_prefix(' ').unwrap_or(content);
+        DiagramLine::Content(content.trim_end().to_owned())
+    } else {
+        DiagramLine::Other(text.to_owned())
+    };
+
+    ParsedDiagramLine { line, arrow }
+}
+
+///
+/// Render the parsed lines with the given content width
+///
+fn render_diagram_lines(prefix: &str, lines: &[ParsedDiagramLine]) -> Vec<String> {
+    let width = lines
+        .iter()
+        .filter_map(|parsed| match &parsed.line {
+            DiagramLine::Content(content) => Some(display_width(content)),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let border = "-".repeat(width + 2);
+    lines
+        .iter()
+        .map(|parsed| {
+            let body = match &parsed.line {
+                DiagramLine::TopBorder => format!("/{border}\\"),
+                DiagramLine::BottomBorder => format!("\\{border}/"),
+                DiagramLine::Separator => format!("|{border}|"),
+                DiagramLine::Content(content) => {
+                    let padding = " ".repeat(width - display_width(content));
+                    format!("| {content}{padding} |")
+                }
+                DiagramLine::Other(text) => return format!("{prefix}{text}"),
+            };
+
+            match &parsed.arrow {
+                Some(arrow) => format!("{prefix}{body} {arrow}"),
+                None => format!("{prefix}{body}"),
+            }
+        })
+        .collect()
+}
+
+///
+/// Get back the comment prefix (of the first line) and the lines (1-based, inclusive) without
+/// the prefix.
+///
+fn get_range_lines(line1: usize, line2: usize) -> Option<(String, Vec<String>)> {
+    let lines = Buffer::current()
+        .get_lines(line1 - 1..line2, false)
+        .ok()?
+        .map(|line| line.to_string())
+        .collect::<Vec<String>>();
+
+    let prefix = get_comment_prefix(lines.first()?);
+    let trimmed_prefix = prefix.trim_end();
+    let lines = lines
+        .iter()
+        .map(|line| {
+            line.strip_prefix(prefix.as_str())
+                .or_else(|| line.strip_prefix(trimmed_prefix))
+                .unwrap_or(line)
+                .to_owned()
+        })
+        .collect();
+
+    Some((prefix, lines))
+}
+
+///
+/// Replace the lines (1-based, inclusive), it's one undo step
+///
+fn set_range_lines(line1: usize, line2: usize, lines: Vec<String>) {
+    let _ = Buffer::current().set_lines(line1 - 1..line2, false, lines);
+}
+
+///
+/// Re-flow the box borders in the range
+///
+fn reflow_box(line1: usize, line2: usize) {
+    let Some((prefix, lines)) = get_range_lines(line1, line2) else {
+        return;
+    };
+
+    let parsed_lines = lines
+        .iter()
+        .map(|line| parse_diagram_line(line))
+        .collect::<Vec<ParsedDiagramLine>>();
+    set_range_lines(line1, line2, render_diagram_lines(&prefix, &parsed_lines));
+}
+
+///
+/// Draw a box around the lines in the range, or re-flow it if it's already a box
+///
+fn draw_box(line1: usize, line2: usize) {
+    let Some((prefix, lines)) = get_range_lines(line1, line2) else {
+        return;
+    };
+
+    let is_box = lines
+        .first()
+        .is_some_and(|line| parse_diagram_line(line).line == DiagramLine::TopBorder);
+    if is_box {
+        reflow_box(line1, line2);
+        return;
+    }
+
+    //
+    // The arrows stay on the right side of the new box
+    //
+    let content_lines = lines.iter().map(|line| {
+        let parsed = parse_diagram_line(line);
+        let content = match &parsed.arrow {
+            Some(_) => line[..line.find(DIAGRAM_ARROW).unwrap_or(line.len())].trim_end(),
+            None => line.trim_end(),
+        };
+        ParsedDiagramLine {
+            line: DiagramLine::Content(content.to_owned()),
+            arrow: parsed.arrow,
+        }
+    });
+
+    let parsed_lines = std::iter::once(ParsedDiagramLine {
+        line: DiagramLine::TopBorder,
+        arrow: None,
+    })
+    .chain(content_lines)
+    .chain(std::iter::once(ParsedDiagramLine {
+        line: DiagramLine::BottomBorder,
+        arrow: None,
+    }))
+    .collect::<Vec<ParsedDiagramLine>>();
+
+    set_range_lines(line1, line2, render_diagram_lines(&prefix, &parsed_lines));
+}
+
+///
+/// Add (or replace) the arrow with the label on the right side of the middle line in the range
+///
+fn draw_arrow(line1: usize, line2: usize, label: &str) {
+    let row = line1 + (line2 - line1) / 2;
+    let Some((prefix, lines)) = get_range_lines(row, row) else {
+        return;
+    };
+    let Some(line) = lines.first() else {
+        return;
+    };
+
+    let body = match line.find(DIAGRAM_ARROW) {
+        Some(pos) => line[..pos].trim_end(),
+        None => line.trim_end(),
+    };
+    let arrow = format!("{DIAGRAM_ARROW} {}", label.trim());
+
+    set_range_lines(row, row, vec![format!("{prefix}{body} {arrow}")]);
+}
+
+///
+///
+///
+pub fn setup() {
+    let range_commands: Vec<(&str, &str, fn(usize, usize))> = vec![
+        (
+            "DiagramBox",
+            "Draw a box around the lines (or re-flow the box)",
+            draw_box,
+        ),
+        (
+            "DiagramReflow",
+            "Re-flow the box borders of the lines",
+            reflow_box,
+        ),
+    ];
+
+    for (name, desc, run) in range_commands {
+        let _ = create_user_command(
+            name,
+            move |args: CommandArgs| {
+                run(args.line1, args.line2);
+                ()
+            },
+            &CreateCommandOpts::builder()
+                .desc(desc)
+                .range(CommandRange::CurrentLine)
+                .build(),
+        );
+    }
+
+    let _ = create_user_command(
+        "DiagramArrow",
+        |args: CommandArgs| {
+            draw_arrow(
+                args.line1,
+                args.line2,
+                args.args.as_deref().unwrap_or_default(),
+            );
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Add the arrow with the label on the right side: ':[range]DiagramArrow {label}'")
+            .range(CommandRange::CurrentLine)
+            .nargs(CommandNArgs::Any)
+            .build(),
+    );
+
+    let key_bindings: Vec<(&str, &str, &str)> = vec![
+        (
+            "<leader>db",
+            ":DiagramBox<CR>",
+            "'<leader>db': Draw a box around the selected lines",
+        ),
+        (
+            "<leader>dr",
+            ":DiagramReflow<CR>",
+            "'<leader>dr': Re-flow the box borders of the selected lines",
+        ),
+        (
+            "<leader>da",
+            ":DiagramArrow ",
+            "'<leader>da': Add the arrow on the right side of the selected lines",
+        ),
+    ];
+
+    for (key, rhs, desc) in key_bindings {
+        let _ = set_keymap(
+            Mode::Visual,
+            key,
+            rhs,
+            &SetKeymapOpts::builder().desc(desc).build(),
+        );
+    }
+}
+
+use crate::utils::{display_width, get_comment_prefix};
+
+use nvim_oxi::api::{
+    Buffer, create_user_command,
+    opts::{CreateCommandOpts, SetKeymapOpts},
+    set_keymap,
+    types::{CommandArgs, CommandNArgs, CommandRange, Mode},
+};
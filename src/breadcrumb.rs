@@ -0,0 +1,361 @@
+//! Per-buffer breadcrumb of the recent significant cursor locations, it's finer-grained than the
+//! global jumplist and scoped to the current file:
+//!
+//! - The cursor location is recorded when it jumps more than `BREADCRUMB_JUMP_LINES` lines
+//!   (both the location it leaves and the location it lands).
+//! - `<leader>jb`: Open a picker of the current buffer breadcrumb (the most recent first).
+//! - `[j` / `]j`: Walk backward/forward through the current buffer breadcrumb.
+//!
+
+///
+/// The cursor movement (in lines) which counts as a significant jump
+///
+const BREADCRUMB_JUMP_LINES: usize = 10;
+
+///
+/// Max locations to keep per buffer
+///
+const BREADCRUMB_MAX_LOCATIONS: usize = 30;
+
+///
+/// Breadcrumb of a single buffer
+///
+#[derive(Debug, Default)]
+struct BufferBreadcrumb {
+    //
+    // (row, col) list, the oldest first, row is 1-based and col is 0-based (the same with
+    // `nvim_win_get_cursor`).
+    //
+    locations: Vec<(usize, usize)>,
+
+    //
+    // The current index when walking through by `[j`/`]j`, `None` means not walking.
+    //
+    walk_index: Option<usize>,
+
+    //
+    // `true` when the cursor is moved by `[j`/`]j` or the picker, the next `CursorMoved` event
+    // should not be recorded.
+    //
+    walking: bool,
+
+    last_cursor: Option<(usize, usize)>,
+}
+
+impl BufferBreadcrumb {
+    ///
+    /// Push the location to the end, the existing location on the same row is moved to the end.
+    ///
+    fn push_location(&mut self, location: (usize, usize)) {
+        self.locations.retain(|(row, _)| *row != location.0);
+        self.locations.push(location);
+
+        if self.locations.len() > BREADCRUMB_MAX_LOCATIONS {
+            self.locations.remove(0);
+        }
+    }
+}
+
+///
+/// Private module-scope state: breadcrumb per buffer (by buffer handle)
+///
+static BREADCRUMB_STATE: LazyLock<Mutex<HashMap<BufHandle, BufferBreadcrumb>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+///
+/// Only track the normal file buffers, ignore the terminal, help, dired, picker buffers, etc.
+///
+fn is_trackable_buffer(buffer: &Buffer) -> bool {
+    let opts = OptionOpts::builder().buffer(buffer.clone()).build();
+    match get_option_value::<NvimString>("buftype", &opts) {
+        Ok(buffer_type) => buffer_type.is_empty(),
+        Err(_) => false,
+    }
+}
+
+///
+/// `CursorMoved` autocmd callback: record the significant jump
+///
+fn on_cursor_moved(buffer: Buffer) {
+    if !is_trackable_buffer(&buffer) {
+        return;
+    }
+
+    let Ok(cursor) = Window::current().get_cursor() else {
+        return;
+    };
+
+    let mut locked_state = BREADCRUMB_STATE.lock().unwrap();
+    let breadcrumb = locked_state.entry(buffer.handle()).or_default();
+
+    if breadcrumb.walking {
+        breadcrumb.walking = false;
+        breadcrumb.last_cursor = Some(cursor);
+        return;
+    }
+
+    if let Some(last_cursor) = breadcrumb.last_cursor
+        && last_cursor.0.abs_diff(cursor.0) > BREADCRUMB_JUMP_LINES
+    {
+        breadcrumb.push_location(last_cursor);
+        breadcrumb.push_location(cursor);
+        breadcrumb.walk_index = None;
+    }
+
+    breadcrumb.last_cursor = Some(cursor);
+}
+
+///
+/// Move the cursor to the given location in the current window, the row is clamped in case the
+/// buffer has been shortened since it was recorded.
+///
+fn jump_to_location(buffer: &Buffer, location: (usize, usize)) {
+    let line_count = buffer.line_count().unwrap_or(1).max(1);
+    let row = location.0.clamp(1, line_count);
+    let _ = Window::current().set_cursor(row, location.1);
+}
+
+///
+/// Walk backward (`[j`) or forward (`]j`) through the current buffer breadcrumb
+///
+fn walk_breadcrumb(backward: bool) {
+    let buffer = Buffer::current();
+    let Ok(cursor) = Window::current().get_cursor() else {
+        return;
+    };
+
+    let location = {
+        let mut locked_state = BREADCRUMB_STATE.lock().unwrap();
+        let Some(breadcrumb) = locked_state.get_mut(&buffer.handle()) else {
+            return;
+        };
+
+        if breadcrumb.locations.is_empty() {
+            return;
+        }
+
+        let last_index = breadcrumb.locations.len() - 1;
+        let next_index = match (breadcrumb.walk_index, backward) {
+            //
+            // Start walking from the most recent location, skip it if the cursor is already
+            // on that row.
+            //
+            (None, true) if breadcrumb.locations[last_index].0 == cursor.0 => {
+                last_index.checked_sub(1)
+            }
+            (None, true) => Some(last_index),
+            (None, false) => None,
+            (Some(index), true) => index.checked_sub(1),
+            (Some(index), false) if index < last_index => Some(index + 1),
+            (Some(_), false) => None,
+        };
+
+        let Some(next_index) = next_index else {
+            nvim::print!("[ breadcrumb ] No more locations.");
+            return;
+        };
+
+        breadcrumb.walk_index = Some(next_index);
+
+        //
+        // The `CursorMoved` event only fires when the cursor really moves
+        //
+        breadcrumb.walking = breadcrumb.locations[next_index] != cursor;
+        breadcrumb.locations[next_index]
+    };
+
+    jump_to_location(&buffer, location);
+}
+
+///
+/// Open the current buffer breadcrumb picker, the most recent first.
+///
+fn open_breadcrumb_picker() {
+    let buffer = Buffer::current();
+    let window_handle = Window::current().handle();
+
+    let locations = BREADCRUMB_STATE
+        .lock()
+        .unwrap()
+        .get(&buffer.handle())
+        .map(|breadcrumb| breadcrumb.locations.clone())
+        .unwrap_or_default();
+
+    if locations.is_empty() {
+        nvim::print!("[ breadcrumb ] No locations recorded in the current buffer.");
+        return;
+    }
+
+    let items = locations
+        .iter()
+        .rev()
+        .map(|(row, col)| {
+            let line_text = buffer
+                .get_lines(row - 1..*row, false)
+                .ok()
+                .and_then(|mut lines| lines.next())
+                .map(|line| line.to_str().unwrap_or_default().trim().to_owned())
+                .unwrap_or_default();
+
+            PickerItem {
+                columns: vec![
+                    PickerItemColumn::new(&format!("{row}:{}", col + 1), Some("LineNr")),
+                    PickerItemColumn::new(&line_text, None),
+                ],
+                icon: None,
+                data: format!("{row}:{col}"),
+            }
+        })
+        .collect::<Vec<PickerItem>>();
+
+    let _ = create_editable_picker_with_options(
+        &mut EditablePickerOptions {
+            title: "Breadcrumb".to_string(),
+            window_opts: PopupWindowOptions {
+                border: WindowBorder::Rounded,
+                window_width_ratio: None,
+                window_height_ratio: None,
+                auto_width: true,
+                auto_height: true,
+                buffer: None,
+                ..Default::default()
+            },
+            list: &items,
+            sort: PickerSortStrategy::None,
+            source: None,
+            sticky_filter: false,
+            show_footer: true,
+            initial_input: None,
+            validator: None,
+            on_delete: None,
+            on_edit: None,
+        },
+        move |selected_text: String| {
+            let Some((row, col)) = selected_text.split_once(':') else {
+                return;
+            };
+
+            let (Ok(row), Ok(col)) = (row.parse::<usize>(), col.parse::<usize>()) else {
+                return;
+            };
+
+            let window = Window::from(window_handle);
+            if !window.is_valid() {
+                return;
+            }
+
+            let _ = set_current_win(&window);
+            if let Some(breadcrumb) = BREADCRUMB_STATE.lock().unwrap().get_mut(&buffer.handle()) {
+                breadcrumb.walking = true;
+            }
+            jump_to_location(&buffer, (row, col));
+        },
+    );
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_autocmd(
+        vec!["CursorMoved"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-breadcrumb-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                on_cursor_moved(args.buffer);
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    //
+    // Forget the breadcrumb of the wiped out buffer, as the buffer handle won't be reused.
+    //
+    let _ = create_autocmd(
+        vec!["BufWipeout"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-breadcrumb-cleanup-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                BREADCRUMB_STATE
+                    .lock()
+                    .unwrap()
+                    .remove(&args.buffer.handle());
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    let key_bindings: Vec<(&str, &str, Box<dyn Fn()>)> = vec![
+        (
+            "<leader>jb",
+            "'<leader>jb': Pick a breadcrumb location in the current buffer.",
+            Box::new(open_breadcrumb_picker),
+        ),
+        (
+            "[j",
+            "'[j': Previous breadcrumb location in the current buffer.",
+            Box::new(|| walk_breadcrumb(true)),
+        ),
+        (
+            "]j",
+            "']j': Next breadcrumb location in the current buffer.",
+            Box::new(|| walk_breadcrumb(false)),
+        ),
+    ];
+
+    for bindings in key_bindings {
+        let _ = set_keymap(
+            Mode::Normal,
+            bindings.0,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(bindings.1)
+                .callback(move |_| {
+                    bindings.2();
+                    ()
+                })
+                .silent(true)
+                .build(),
+        );
+    }
+}
+
+use crate::picker::{
+    EditablePickerOptions, PickerItem, PickerItemColumn, PickerSortStrategy, PopupWindowOptions,
+    create_editable_picker_with_options,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim, BufHandle, String as NvimString,
+    api::{
+        Buffer, Window, create_augroup, create_autocmd, get_option_value,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, SetKeymapOpts},
+        set_current_win, set_keymap,
+        types::{AutocmdCallbackArgs, Mode, WindowBorder},
+    },
+};
@@ -0,0 +1,235 @@
+//! Auto-clean the hidden buffers which are not used for a while:
+//!
+//! - Every `BUFFER_JANITOR_CHECK_INTERVAL`, the hidden (not shown in any window), unmodified
+//!   file buffers which are not visited for `BUFFER_JANITOR_IDLE_MINUTES` are wiped out. The
+//!   pinned buffers (`:BufPin`) and the special buffers (terminal, help, quickfix, etc) are never
+//!   touched.
+//! - `:BufClean`: Run it now, `:BufClean!` ignores the idle time.
+//! - `:BufCleanUndo`: Restore the wiped buffers of the last clean (within
+//!   `BUFFER_JANITOR_UNDO_SECONDS`) to the buffer list, the cursor lines are kept.
+//!
+
+///
+/// The hidden buffers not visited for this long are wiped out
+///
+const BUFFER_JANITOR_IDLE_MINUTES: u64 = 30;
+
+///
+/// The check interval in milliseconds
+///
+const BUFFER_JANITOR_CHECK_INTERVAL: u32 = 5 * 60 * 1000;
+
+///
+/// How long the last clean can be restored by `:BufCleanUndo`
+///
+const BUFFER_JANITOR_UNDO_SECONDS: u64 = 10 * 60;
+
+///
+/// The wiped buffer which can be restored
+///
+#[derive(Debug, Clone)]
+struct CleanedBuffer {
+    file_name: String,
+    line: i64, // 1-based
+}
+
+///
+/// Private module-scope state: the buffers wiped by the last clean and when
+///
+static BUFFER_JANITOR_STATE: LazyLock<Mutex<Option<(Instant, Vec<CleanedBuffer>)>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+///
+/// Get back the hidden, unmodified and unpinned file buffers which are idle longer than
+/// `idle_seconds`.
+///
+fn get_idle_hidden_buffers(idle_seconds: u64) -> Vec<(BufHandle, CleanedBuffer)> {
+    let Ok(buffer_infos) =
+        call_function::<_, Array>("getbufinfo", (Dictionary::from_iter([("buflisted", 1)]),))
+    else {
+        return Vec::new();
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    buffer_infos
+        .into_iter()
+        .filter_map(|info| Dictionary::from_object(info).ok())
+        .filter_map(|info| {
+            let get_integer = |key: &str| {
+                info.get(key)
+                    .and_then(|value| i64::from_object(value.clone()).ok())
+                    .unwrap_or(0)
+            };
+
+            //
+            // `hidden` is only set for the loaded buffers, the unloaded ones don't use memory, and
+            // `lastused` is the Unix time in seconds.
+            //
+            let is_idle = now.saturating_sub(get_integer("lastused") as u64) >= idle_seconds;
+            if get_integer("hidden") != 1 || get_integer("changed") != 0 || !is_idle {
+                return None;
+            }
+
+            let buffer_handle = get_integer("bufnr") as BufHandle;
+            let buffer = Buffer::from(buffer_handle);
+            let opts = OptionOpts::builder().buffer(buffer).build();
+            let buffer_type = get_option_value::<NvimString>("buftype", &opts).unwrap_or_default();
+            if !buffer_type.is_empty() || is_buffer_pinned(buffer_handle) {
+                return None;
+            }
+
+            let file_name = info
+                .get("name")
+                .and_then(|name| NvimString::from_object(name.clone()).ok())
+                .map(|name| name.to_string())
+                .filter(|name| !name.is_empty())?;
+
+            Some((
+                buffer_handle,
+                CleanedBuffer {
+                    file_name,
+                    line: get_integer("lnum").max(1),
+                },
+            ))
+        })
+        .collect()
+}
+
+///
+/// Wipe out the idle hidden buffers, `ignore_idle_time` to wipe all the hidden ones.
+///
+fn clean_hidden_buffers(ignore_idle_time: bool) {
+    const LOGGER_PREFIX: &'static str = "[ buffer_janitor ]";
+
+    let idle_seconds = if ignore_idle_time {
+        0
+    } else {
+        BUFFER_JANITOR_IDLE_MINUTES * 60
+    };
+
+    let mut cleaned_buffers = Vec::new();
+    for (buffer_handle, cleaned_buffer) in get_idle_hidden_buffers(idle_seconds) {
+        if command(&format!("silent! bwipeout {buffer_handle}")).is_ok()
+            && !Buffer::from(buffer_handle).is_valid()
+        {
+            cleaned_buffers.push(cleaned_buffer);
+        }
+    }
+
+    if cleaned_buffers.is_empty() {
+        if ignore_idle_time {
+            nvim::print!("{LOGGER_PREFIX} No hidden buffer to clean.");
+        }
+        return;
+    }
+
+    let names = cleaned_buffers
+        .iter()
+        .map(|cleaned| {
+            call_function::<_, String>("fnamemodify", (cleaned.file_name.as_str(), ":t"))
+                .unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    nvim::print!(
+        "{LOGGER_PREFIX} Wiped {} hidden buffer(s): {names} (`:BufCleanUndo` to restore)",
+        cleaned_buffers.len()
+    );
+
+    *BUFFER_JANITOR_STATE.lock().unwrap() = Some((Instant::now(), cleaned_buffers));
+}
+
+///
+/// Restore the buffers wiped by the last clean
+///
+fn undo_last_clean() {
+    const LOGGER_PREFIX: &'static str = "[ buffer_janitor ]";
+
+    let Some((cleaned_at, cleaned_buffers)) = BUFFER_JANITOR_STATE.lock().unwrap().take() else {
+        nvim::print!("{LOGGER_PREFIX} Nothing to restore.");
+        return;
+    };
+
+    if cleaned_at.elapsed().as_secs() > BUFFER_JANITOR_UNDO_SECONDS {
+        nvim::print!("{LOGGER_PREFIX} The last clean is too old to restore.");
+        return;
+    }
+
+    //
+    // `:badd` only adds the buffers to the list without loading them, the `+{line}` is the
+    // cursor line when the buffer is entered for the first time.
+    //
+    for cleaned_buffer in &cleaned_buffers {
+        let escaped_name =
+            call_function::<_, String>("fnameescape", (cleaned_buffer.file_name.as_str(),))
+                .unwrap_or_default();
+        let _ = command(&format!("badd +{} {escaped_name}", cleaned_buffer.line));
+    }
+
+    nvim::print!(
+        "{LOGGER_PREFIX} Restored {} buffer(s).",
+        cleaned_buffers.len()
+    );
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_user_command(
+        "BufClean",
+        |args: CommandArgs| {
+            clean_hidden_buffers(args.bang);
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .bang(true)
+            .desc("Wipe out the idle hidden buffers ('!' to ignore the idle time)")
+            .build(),
+    );
+
+    let _ = create_user_command(
+        "BufCleanUndo",
+        |_: CommandArgs| {
+            undo_last_clean();
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Restore the buffers wiped by the last clean")
+            .build(),
+    );
+
+    let janitor_callback = Function::<(i64,), ()>::from_fn(|_| {
+        clean_hidden_buffers(false);
+    });
+
+    let _ = call_function::<_, i64>(
+        "timer_start",
+        (
+            BUFFER_JANITOR_CHECK_INTERVAL,
+            janitor_callback,
+            Dictionary::from_iter([("repeat", -1)]),
+        ),
+    );
+}
+
+use crate::tab_buffers::is_buffer_pinned;
+
+use std::{
+    sync::{LazyLock, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use nvim_oxi::{
+    self as nvim, Array, BufHandle, Dictionary, Function, String as NvimString,
+    api::{
+        Buffer, call_function, command, create_user_command, get_option_value,
+        opts::{CreateCommandOpts, OptionOpts},
+        types::CommandArgs,
+    },
+    conversion::FromObject,
+};
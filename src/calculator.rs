@@ -0,0 +1,750 @@
+//! Quick calculation evaluator, the expression is parsed and evaluated in Rust:
+//!
+//! - `<leader>=` (`VISUAL` mode): Evaluate the selected expression and replace the selection with
+//!   the result, e.g. `0x10 << 4` -> `0x100`, the result keeps the base of the first number.
+//! - `<leader>=` (`NORMAL` mode): Evaluate the current line (without the comment leader and the
+//!   trailing `=`), and show the result in all bases in a float, it closes when the cursor moves.
+//! - `:Calc {expr}`: Evaluate the expression and print the result.
+//!
+//! The expression supports:
+//!
+//! - The decimal (`1_000`, `1.5`, `2e3`), hex (`0xFF`), binary (`0b1010`) and octal (`0o17`)
+//!   numbers.
+//! - `+ - * / % **` (power), `& | ^ ~ << >>` (bitwise, the integers only) and the parentheses,
+//!   the precedence is the same as Python.
+//! - The units after the number, they're converted into the base unit: the sizes in bytes
+//!   (`B`, `KB`, `MB`, `GB`, `TB`, `KiB`, `MiB`, `GiB`, `TiB`), and the durations in milliseconds
+//!   (`ms`, `s`, `min`, `h`, `d`), e.g. `1.5GiB / 4KiB` or `2h + 30min`.
+//!
+
+///
+/// The units and their values in the base unit
+///
+const CALC_UNITS: [(&'static str, i64, CalcUnitKind); 14] = [
+    ("B", 1, CalcUnitKind::Size),
+    ("KB", 1_000, CalcUnitKind::Size),
+    ("MB", 1_000_000, CalcUnitKind::Size),
+    ("GB", 1_000_000_000, CalcUnitKind::Size),
+    ("TB", 1_000_000_000_000, CalcUnitKind::Size),
+    ("KiB", 1 << 10, CalcUnitKind::Size),
+    ("MiB", 1 << 20, CalcUnitKind::Size),
+    ("GiB", 1 << 30, CalcUnitKind::Size),
+    ("TiB", 1 << 40, CalcUnitKind::Size),
+    ("ms", 1, CalcUnitKind::Duration),
+    ("s", 1_000, CalcUnitKind::Duration),
+    ("min", 60_000, CalcUnitKind::Duration),
+    ("h", 3_600_000, CalcUnitKind::Duration),
+    ("d", 86_400_000, CalcUnitKind::Duration),
+];
+
+///
+/// The binary operators and their precedence, the bigger one binds tighter. `**` is not here, as
+/// it binds tighter than the unary minus and it's right associative (see `parse_power()`).
+///
+const CALC_BINARY_OPERATORS: [(&'static str, u8); 10] = [
+    ("|", 1),
+    ("^", 2),
+    ("&", 3),
+    ("<<", 4),
+    (">>", 4),
+    ("+", 5),
+    ("-", 5),
+    ("*", 6),
+    ("/", 6),
+    ("%", 6),
+];
+
+///
+/// Private module-scope state: the result float of the `NORMAL` mode evaluation
+///
+static CALCULATOR_RESULT_WINDOW: LazyLock<Mutex<Option<PopupWindow>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CalcUnitKind {
+    Size,
+    Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CalcValue {
+    Int(i64),
+    Float(f64),
+}
+
+impl CalcValue {
+    fn as_float(&self) -> f64 {
+        match self {
+            Self::Int(value) => *value as f64,
+            Self::Float(value) => *value,
+        }
+    }
+
+    ///
+    /// The integer value for the bitwise operators, the float without the fraction is accepted.
+    ///
+    fn as_int(&self) -> Result<i64, String> {
+        match self {
+            Self::Int(value) => Ok(*value),
+            Self::Float(value) if value.fract() == 0.0 && value.abs() < i64::MAX as f64 => {
+                Ok(*value as i64)
+            }
+            Self::Float(value) => Err(format!("The bitwise operand '{value}' is not an integer")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum CalcToken {
+    Number(CalcValue),
+    Operator(&'static str),
+    LeftParen,
+    RightParen,
+}
+
+///
+/// The evaluated result
+///
+#[derive(Debug, Clone, Copy)]
+struct CalcResult {
+    value: CalcValue,
+
+    //
+    // The base of the first number in the expression, the replaced result uses it.
+    //
+    radix: u32,
+
+    //
+    // The kind of the first unit in the expression, the float shows the human-readable form.
+    //
+    unit_kind: Option<CalcUnitKind>,
+}
+
+///
+/// Split the expression into the tokens, return the tokens, the radix of the first number and
+/// the kind of the first unit.
+///
+fn tokenize(expression: &str) -> Result<(Vec<CalcToken>, u32, Option<CalcUnitKind>), String> {
+    let chars = expression.chars().collect::<Vec<char>>();
+    let mut tokens = Vec::new();
+    let mut first_radix = None;
+    let mut unit_kind = None;
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+
+        if c.is_whitespace() {
+            index += 1;
+            continue;
+        }
+
+        if c.is_ascii_digit()
+            || (c == '.' && chars.get(index + 1).is_some_and(char::is_ascii_digit))
+        {
+            let radix = match (c, chars.get(index + 1)) {
+                ('0', Some('x' | 'X')) => 16,
+                ('0', Some('b' | 'B')) => 2,
+                ('0', Some('o' | 'O')) => 8,
+                _ => 10,
+            };
+
+            let value = if radix == 10 {
+                let start = index;
+                while index < chars.len() {
+                    let current = chars[index];
+                    let is_exponent_sign = (current == '+' || current == '-')
+                        && index > start
+                        && matches!(chars[index - 1], 'e' | 'E');
+                    let is_exponent = (current == 'e' || current == 'E')
+                        && chars.get(index + 1).is_some_and(|next| {
+                            next.is_ascii_digit() || *next == '+' || *next == '-'
+                        });
+                    if current.is_ascii_digit()
+                        || current == '_'
+                        || current == '.'
+                        || is_exponent
+                        || is_exponent_sign
+                    {
+                        index += 1;
+                    } else {
+                        break;
+                    }
+                }
+
+                let literal = chars[start..index]
+                    .iter()
+                    .filter(|c| **c != '_')
+                    .collect::<String>();
+                if literal.contains(['.', 'e', 'E']) {
+                    CalcValue::Float(
+                        literal
+                            .parse::<f64>()
+                            .map_err(|_| format!("Invalid number '{literal}'"))?,
+                    )
+                } else {
+                    CalcValue::Int(
+                        literal
+                            .parse::<i64>()
+                            .map_err(|_| format!("Invalid number '{literal}'"))?,
+                    )
+                }
+            } else {
+                index += 2;
+                let start = index;
+                while index < chars.len()
+                    && (chars[index].is_ascii_alphanumeric() || chars[index] == '_')
+                {
+                    index += 1;
+                }
+
+                let digits = chars[start..index]
+                    .iter()
+                    .filter(|c| **c != '_')
+                    .collect::<String>();
+                CalcValue::Int(
+                    i64::from_str_radix(&digits, radix)
+                        .map_err(|_| format!("Invalid base {radix} number '{digits}'"))?,
+                )
+            };
+
+            first_radix = first_radix.or(Some(radix));
+
+            //
+            // The optional unit after the number, e.g. `1.5 GiB`
+            //
+            let mut unit_end = index;
+            while unit_end < chars.len() && chars[unit_end] == ' ' {
+                unit_end += 1;
+            }
+            let unit_start = unit_end;
+            while unit_end < chars.len() && chars[unit_end].is_ascii_alphabetic() {
+                unit_end += 1;
+            }
+
+            let value = if unit_end > unit_start {
+                let unit = chars[unit_start..unit_end].iter().collect::<String>();
+                let Some((_, multiplier, kind)) =
+                    CALC_UNITS.iter().find(|(name, ..)| *name == unit)
+                else {
+                    return Err(format!("Unknown unit '{unit}'"));
+                };
+
+                index = unit_end;
+                unit_kind = unit_kind.or(Some(*kind));
+                apply_binary_operator("*", value, CalcValue::Int(*multiplier))?
+            } else {
+                value
+            };
+
+            tokens.push(CalcToken::Number(value));
+            continue;
+        }
+
+        match c {
+            '(' => tokens.push(CalcToken::LeftParen),
+            ')' => tokens.push(CalcToken::RightParen),
+            _ => {
+                let two_chars = chars[index..chars.len().min(index + 2)]
+                    .iter()
+                    .collect::<String>();
+                let operator = ["**", "<<", ">>"]
+                    .into_iter()
+                    .find(|operator| *operator == two_chars)
+                    .or_else(|| {
+                        ["+", "-", "*", "/", "%", "&", "|", "^", "~"]
+                            .into_iter()
+                            .find(|operator| operator.starts_with(c))
+                    })
+                    .ok_or_else(|| format!("Unexpected '{c}'"))?;
+
+                tokens.push(CalcToken::Operator(operator));
+                index += operator.len();
+                continue;
+            }
+        }
+
+        index += 1;
+    }
+
+    Ok((tokens, first_radix.unwrap_or(10), unit_kind))
+}
+
+///
+/// Apply the binary operator, the integer operation falls back to the float one if the result
+/// isn't an integer (e.g. `7 / 2`).
+///
+fn apply_binary_operator(
+    operator: &str,
+    left: CalcValue,
+    right: CalcValue,
+) -> Result<CalcValue, String> {
+    let overflow = || format!("Overflow in '{operator}'");
+
+    match operator {
+        "&" => Ok(CalcValue::Int(left.as_int()? & right.as_int()?)),
+        "|" => Ok(CalcValue::Int(left.as_int()? | right.as_int()?)),
+        "^" => Ok(CalcValue::Int(left.as_int()? ^ right.as_int()?)),
+        "<<" | ">>" => {
+            let shift = u32::try_from(right.as_int()?)
+                .ok()
+                .filter(|shift| *shift < 64)
+                .ok_or_else(|| format!("Invalid shift '{}'", right.as_float()))?;
+            let value = left.as_int()?;
+            let result = if operator == "<<" {
+                value.checked_shl(shift)
+            } else {
+                value.checked_shr(shift)
+            };
+            result.map(CalcValue::Int).ok_or_else(overflow)
+        }
+        "/" | "%" if right.as_float() == 0.0 => Err("Division by zero".to_string()),
+        _ => match (operator, left, right) {
+            ("+", CalcValue::Int(a), CalcValue::Int(b)) => {
+                a.checked_add(b).map(CalcValue::Int).ok_or_else(overflow)
+            }
+            ("-", CalcValue::Int(a), CalcValue::Int(b)) => {
+                a.checked_sub(b).map(CalcValue::Int).ok_or_else(overflow)
+            }
+            ("*", CalcValue::Int(a), CalcValue::Int(b)) => {
+                a.checked_mul(b).map(CalcValue::Int).ok_or_else(overflow)
+            }
+            ("/", CalcValue::Int(a), CalcValue::Int(b)) if a.checked_rem(b) == Some(0) => {
+                a.checked_div(b).map(CalcValue::Int).ok_or_else(overflow)
+            }
+            ("%", CalcValue::Int(a), CalcValue::Int(b)) => a
+                .checked_rem_euclid(b)
+                .map(CalcValue::Int)
+                .ok_or_else(overflow),
+            ("**", CalcValue::Int(a), CalcValue::Int(b)) if b >= 0 => u32::try_from(b)
+                .ok()
+                .and_then(|b| a.checked_pow(b))
+                .map(CalcValue::Int)
+                .ok_or_else(overflow),
+            (_, left, right) => {
+                let (a, b) = (left.as_float(), right.as_float());
+                let result = match operator {
+                    "+" => a + b,
+                    "-" => a - b,
+                    "*" => a * b,
+                    "/" => a / b,
+                    "%" => a.rem_euclid(b),
+                    "**" => a.powf(b),
+                    _ => return Err(format!("Unknown operator '{operator}'")),
+                };
+                if result.is_finite() {
+                    Ok(CalcValue::Float(result))
+                } else {
+                    Err(overflow())
+                }
+            }
+        },
+    }
+}
+
+///
+/// The recursive descent parser which evaluates while parsing
+///
+struct CalcParser {
+    tokens: Vec<CalcToken>,
+    position: usize,
+}
+
+impl CalcParser {
+    fn peek(&self) -> Option<&CalcToken> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<CalcToken> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    ///
+    /// `binary := unary (operator binary)*`, by the precedence climbing
+    ///
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<CalcValue, String> {
+        let mut left = self.parse_unary()?;
+
+        while let Some(CalcToken::Operator(operator)) = self.peek() {
+            let operator = *operator;
+            let Some((_, precedence)) = CALC_BINARY_OPERATORS
+                .iter()
+                .find(|(name, _)| *name == operator)
+            else {
+                break;
+            };
+            if *precedence < min_precedence {
+                break;
+            }
+
+            self.position += 1;
+            let right = self.parse_binary(precedence + 1)?;
+            left = apply_binary_operator(operator, left, right)?;
+        }
+
+        Ok(left)
+    }
+
+    ///
+    /// `unary := ('-' | '+' | '~') unary | power`
+    ///
+    fn parse_unary(&mut self) -> Result<CalcValue, String> {
+        match self.peek() {
+            Some(CalcToken::Operator("-")) => {
+                self.position += 1;
+                apply_binary_operator("-", CalcValue::Int(0), self.parse_unary()?)
+            }
+            Some(CalcToken::Operator("+")) => {
+                self.position += 1;
+                self.parse_unary()
+            }
+            Some(CalcToken::Operator("~")) => {
+                self.position += 1;
+                Ok(CalcValue::Int(!self.parse_unary()?.as_int()?))
+            }
+            _ => self.parse_power(),
+        }
+    }
+
+    ///
+    /// `power := primary ('**' unary)?`, it's right associative: `2 ** 3 ** 2` is `2 ** 9`
+    ///
+    fn parse_power(&mut self) -> Result<CalcValue, String> {
+        let base = self.parse_primary()?;
+
+        if self.peek() == Some(&CalcToken::Operator("**")) {
+            self.position += 1;
+            let exponent = self.parse_unary()?;
+            return apply_binary_operator("**", base, exponent);
+        }
+
+        Ok(base)
+    }
+
+    ///
+    /// `primary := number | '(' binary ')'`
+    ///
+    fn parse_primary(&mut self) -> Result<CalcValue, String> {
+        match self.next() {
+            Some(CalcToken::Number(value)) => Ok(value),
+            Some(CalcToken::LeftParen) => {
+                let value = self.parse_binary(1)?;
+                match self.next() {
+                    Some(CalcToken::RightParen) => Ok(value),
+                    _ => Err("Missing ')'".to_string()),
+                }
+            }
+            Some(CalcToken::Operator(operator)) => Err(format!("Unexpected '{operator}'")),
+            Some(CalcToken::RightParen) => Err("Unexpected ')'".to_string()),
+            None => Err("Incomplete expression".to_string()),
+        }
+    }
+}
+
+///
+/// Evaluate the expression
+///
+fn evaluate(expression: &str) -> Result<CalcResult, String> {
+    let (tokens, radix, unit_kind) = tokenize(expression)?;
+    if tokens.is_empty() {
+        return Err("Empty expression".to_string());
+    }
+
+    let mut parser = CalcParser {
+        tokens,
+        position: 0,
+    };
+    let value = parser.parse_binary(1)?;
+    if let Some(token) = parser.peek() {
+        return Err(match token {
+            CalcToken::RightParen => "Unexpected ')'".to_string(),
+            _ => "Unexpected trailing input".to_string(),
+        });
+    }
+
+    Ok(CalcResult {
+        value,
+        radix,
+        unit_kind,
+    })
+}
+
+///
+/// Format the value in the base, the float (or the negative number in the non-decimal base) is
+/// always decimal.
+///
+fn format_value(value: CalcValue, radix: u32) -> String {
+    match value {
+        CalcValue::Int(value) => match radix {
+            16 if value >= 0 => format!("{value:#X}").replacen("0X", "0x", 1),
+            2 if value >= 0 => format!("{value:#b}"),
+            8 if value >= 0 => format!("{value:#o}"),
+            _ => value.to_string(),
+        },
+        CalcValue::Float(value) if value.fract() == 0.0 && value.abs() < 1e15 => {
+            format!("{}", value as i64)
+        }
+        CalcValue::Float(value) => {
+            let formatted = format!("{value:.10}");
+            formatted
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_owned()
+        }
+    }
+}
+
+///
+/// The human-readable form of the value in the base unit, e.g. `1.50 GiB`, `2h 30min`
+///
+fn format_unit_value(value: f64, unit_kind: CalcUnitKind) -> String {
+    match unit_kind {
+        CalcUnitKind::Size => {
+            let mut size = value;
+            let mut unit = "B";
+            for next_unit in ["KiB", "MiB", "GiB", "TiB"] {
+                if size.abs() < 1024.0 {
+                    break;
+                }
+                size /= 1024.0;
+                unit = next_unit;
+            }
+            format!("{size:.2} {unit}")
+        }
+        CalcUnitKind::Duration => {
+            let mut remaining = value.abs().round() as u64;
+            let mut parts = Vec::new();
+            for (unit, milliseconds) in [
+                ("d", 86_400_000),
+                ("h", 3_600_000),
+                ("min", 60_000),
+                ("s", 1_000),
+            ] {
+                if remaining >= milliseconds {
+                    parts.push(format!("{}{unit}", remaining / milliseconds));
+                    remaining %= milliseconds;
+                }
+            }
+            if remaining > 0 || parts.is_empty() {
+                parts.push(format!("{remaining}ms"));
+            }
+
+            let sign = if value < 0.0 { "-" } else { "" };
+            format!("{sign}{}", parts.join(" "))
+        }
+    }
+}
+
+///
+/// The float lines of the result: the decimal, hex, binary, octal and the unit form
+///
+fn render_result_lines(result: &CalcResult) -> Vec<String> {
+    let mut lines = vec![format!(" dec: {}", format_value(result.value, 10))];
+
+    if let CalcValue::Int(value) = result.value
+        && value >= 0
+    {
+        lines.push(format!(" hex: {}", format_value(result.value, 16)));
+        lines.push(format!(" bin: {}", format_value(result.value, 2)));
+        lines.push(format!(" oct: {}", format_value(result.value, 8)));
+    }
+
+    if let Some(unit_kind) = result.unit_kind {
+        let label = match unit_kind {
+            CalcUnitKind::Size => "size",
+            CalcUnitKind::Duration => "time",
+        };
+        lines.push(format!(
+            "{label}: {}",
+            format_unit_value(result.value.as_float(), unit_kind)
+        ));
+    }
+
+    lines
+}
+
+///
+/// Close the result float
+///
+fn close_result_window() {
+    if let Some(result_window) = CALCULATOR_RESULT_WINDOW.lock().unwrap().take() {
+        result_window.close();
+    }
+}
+
+///
+/// Show the result in the float which doesn't steal the focus, it closes when the cursor moves.
+///
+fn show_result_window(expression: &str, result: &CalcResult) {
+    close_result_window();
+
+    let Ok(mut result_buffer) = create_buf(false, true) else {
+        return;
+    };
+
+    let opts = OptionOpts::builder().buffer(result_buffer.clone()).build();
+    let _ = set_option_value("bufhidden", "wipe", &opts);
+    let _ = result_buffer.set_lines(.., true, render_result_lines(result));
+    let _ = set_option_value("modifiable", false, &opts);
+
+    let Some(result_window) = create_popup_window(&PopupWindowOptions {
+        border: WindowBorder::Rounded,
+        buffer: Some(result_buffer.handle()),
+        title: Some(format!(" {} ", expression.trim())),
+        max_width: Some(80),
+        zindex: Some(60),
+        focusable: false,
+        ..Default::default()
+    }) else {
+        return;
+    };
+
+    *CALCULATOR_RESULT_WINDOW.lock().unwrap() = Some(result_window);
+
+    let _ = create_autocmd(
+        vec!["CursorMoved", "InsertEnter", "BufLeave"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-calculator-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .buffer(Buffer::current())
+            .callback(|_: AutocmdCallbackArgs| {
+                close_result_window();
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                true
+            })
+            .build(),
+    );
+}
+
+///
+/// `<leader>=` in `VISUAL` mode: replace the selection with the result
+///
+fn evaluate_selection() {
+    let Some(expression) = get_visual_selection() else {
+        return;
+    };
+
+    let result = match evaluate(&expression) {
+        Ok(result) => result,
+        Err(error) => {
+            let _ = command("normal! \x1b");
+            echo_message("calculator", &error, EchoLevel::Error);
+            return;
+        }
+    };
+
+    //
+    // Put the result over the selection by the `z` register (`P` in `VISUAL` mode doesn't yank
+    // the replaced text), and then restore the register.
+    //
+    let saved_register = get_register('z').unwrap_or_else(|| RegisterContent::charwise(""));
+    set_register(
+        'z',
+        &RegisterContent::charwise(&format_value(result.value, result.radix)),
+    );
+    let _ = command(r#"normal! "zP"#);
+    set_register('z', &saved_register);
+}
+
+///
+/// `<leader>=` in `NORMAL` mode: show the result of the current line
+///
+fn evaluate_current_line() {
+    let line = get_current_line()
+        .map(|line| line.to_string())
+        .unwrap_or_default();
+    let prefix = get_comment_prefix(&line);
+    let expression = line
+        .strip_prefix(prefix.as_str())
+        .unwrap_or(line.as_str())
+        .trim()
+        .trim_end_matches('=')
+        .to_owned();
+
+    match evaluate(&expression) {
+        Ok(result) => show_result_window(&expression, &result),
+        Err(error) => echo_message("calculator", &error, EchoLevel::Error),
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = set_keymap(
+        Mode::Visual,
+        "<leader>=",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("Evaluate the selected expression and replace it with the result")
+            .silent(true)
+            .callback(|_| {
+                evaluate_selection();
+                ()
+            })
+            .build(),
+    );
+
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>=",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("Evaluate the current line and show the result")
+            .silent(true)
+            .callback(|_| {
+                evaluate_current_line();
+                ()
+            })
+            .build(),
+    );
+
+    let _ = create_user_command(
+        "Calc",
+        |args: CommandArgs| {
+            let expression = args.args.as_deref().unwrap_or_default().trim().to_owned();
+            match evaluate(&expression) {
+                Ok(result) => echo_message(
+                    "calculator",
+                    &format!(
+                        "{expression} = {}",
+                        format_value(result.value, result.radix)
+                    ),
+                    EchoLevel::Success,
+                ),
+                Err(error) => echo_message("calculator", &error, EchoLevel::Error),
+            }
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Evaluate the arithmetic expression")
+            .nargs(CommandNArgs::OneOrMore)
+            .build(),
+    );
+}
+
+use crate::{
+    extended_api::{EchoLevel, RegisterContent, echo_message, get_register, set_register},
+    picker::popup_window::{PopupWindow, PopupWindowOptions, WindowBorder, create_popup_window},
+    utils::{get_comment_prefix, get_visual_selection},
+};
+
+use std::sync::{LazyLock, Mutex};
+
+use nvim_oxi::api::{
+    Buffer, command, create_augroup, create_autocmd, create_buf, create_user_command,
+    get_current_line,
+    opts::{CreateAugroupOpts, CreateAutocmdOpts, CreateCommandOpts, OptionOpts, SetKeymapOpts},
+    set_keymap, set_option_value,
+    types::{AutocmdCallbackArgs, CommandArgs, CommandNArgs, Mode},
+};
@@ -0,0 +1,173 @@
+//! Command-line hook layer, the context-aware cmdline expansions are registered here:
+//!
+//! - `register_cmdline_expansion(trigger, expansion)`: When typing the `trigger` (e.g. `%%`) in
+//!   the cmdline, the `expansion` gets the current cmdline context (the cmdline type, text,
+//!   cursor and the command name parsed by `nvim_parse_cmd`). The returned text replaces the
+//!   trigger, `None` keeps the trigger as it is.
+//!
+
+///
+/// The current cmdline context
+///
+#[derive(Debug, Clone)]
+pub struct CmdlineContext {
+    //
+    // `:`, `/`, `?`, etc, see `:h getcmdtype()`
+    //
+    pub cmd_type: String,
+    pub line: String,
+
+    //
+    // The cursor byte index (0-based)
+    //
+    pub cursor: usize,
+
+    //
+    // The full command name parsed by `nvim_parse_cmd`, e.g. "edit" for `:e foo`, `None` if the
+    // cmdline is not an Ex command or it can't be parsed yet.
+    //
+    pub command: Option<String>,
+}
+
+///
+/// The cmdline expansion, return the text to replace the trigger.
+///
+pub type CmdlineExpansion = fn(&CmdlineContext) -> Option<String>;
+
+///
+/// Private module-scope state: the registered (trigger, expansion) list
+///
+static CMDLINE_EXPANSIONS: LazyLock<Mutex<Vec<(String, CmdlineExpansion)>>> =
+    LazyLock::new(|| Mutex::new(Vec::new()));
+
+///
+/// Parse the given Ex command line by `nvim_parse_cmd`, return the full command name.
+///
+fn parse_command_name(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    parse_cmd(line, &ParseCmdOpts::default())
+        .ok()
+        .and_then(|cmd_infos| cmd_infos.cmd)
+}
+
+///
+/// Get back the current cmdline context, `None` if it's not in cmdline mode.
+///
+pub fn get_cmdline_context() -> Option<CmdlineContext> {
+    let cmd_type = call_function::<_, String>("getcmdtype", ()).ok()?;
+    if cmd_type.is_empty() {
+        return None;
+    }
+
+    let line = call_function::<_, String>("getcmdline", ()).unwrap_or_default();
+    let cursor = call_function::<_, usize>("getcmdpos", ())
+        .unwrap_or(1)
+        .saturating_sub(1)
+        .min(line.len());
+
+    let command = if cmd_type == ":" {
+        parse_command_name(&line)
+    } else {
+        None
+    };
+
+    Some(CmdlineContext {
+        cmd_type,
+        line,
+        cursor,
+        command,
+    })
+}
+
+///
+/// Insert the text at the cmdline cursor position
+///
+fn insert_into_cmdline(context: &CmdlineContext, text: &str) {
+    let mut cursor = context.cursor;
+    while !context.line.is_char_boundary(cursor) {
+        cursor -= 1;
+    }
+
+    let new_line = format!(
+        "{}{text}{}",
+        &context.line[..cursor],
+        &context.line[cursor..]
+    );
+
+    //
+    // `setcmdline()` cursor position is 1-based
+    //
+    let _ = call_function::<_, i64>("setcmdline", (new_line, cursor + text.len() + 1));
+}
+
+///
+/// The trigger keymap callback: Run the registered expansions of the trigger in order, the
+/// first returned text wins.
+///
+fn expand_cmdline_trigger(trigger: &str) {
+    let Some(context) = get_cmdline_context() else {
+        return;
+    };
+
+    //
+    // Copy the expansion list and release the mutex lock immediately, as the expansion may call
+    // into the other modules.
+    //
+    let expansions = CMDLINE_EXPANSIONS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(expansion_trigger, _)| expansion_trigger == trigger)
+        .map(|(_, expansion)| *expansion)
+        .collect::<Vec<CmdlineExpansion>>();
+
+    let text = expansions
+        .iter()
+        .find_map(|expansion| expansion(&context))
+        .unwrap_or_else(|| trigger.to_owned());
+
+    insert_into_cmdline(&context, &text);
+}
+
+///
+/// Register the cmdline expansion of the given trigger, see the module doc.
+///
+pub fn register_cmdline_expansion(trigger: &str, expansion: CmdlineExpansion) {
+    let is_new_trigger = {
+        let mut locked_expansions = CMDLINE_EXPANSIONS.lock().unwrap();
+        let is_new_trigger = locked_expansions.iter().all(|(t, _)| t != trigger);
+        locked_expansions.push((trigger.to_owned(), expansion));
+        is_new_trigger
+    };
+
+    if !is_new_trigger {
+        return;
+    }
+
+    let trigger_owned = trigger.to_owned();
+    let _ = set_keymap(
+        Mode::CmdLine,
+        trigger,
+        "",
+        &SetKeymapOpts::builder()
+            .desc(format!("Cmdline expansion: '{trigger}'").as_str())
+            .callback(move |_| {
+                expand_cmdline_trigger(&trigger_owned);
+                ()
+            })
+            .build(),
+    );
+}
+
+use std::sync::{LazyLock, Mutex};
+
+use nvim_oxi::api::{
+    call_function,
+    opts::{ParseCmdOpts, SetKeymapOpts},
+    parse_cmd, set_keymap,
+    types::Mode,
+};
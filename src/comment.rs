@@ -0,0 +1,305 @@
+//! The commenting operator, it replaces the builtin `gc`/`gcc`:
+//!
+//! - `gcc`: Toggle the comment of the current line (`3gcc` for 3 lines).
+//! - `gc{motion}`: Toggle the comment of the motion lines, e.g. `gcip`, `gcG`.
+//! - `gc` in `VISUAL` mode: Toggle the comment of the selected lines.
+//! - `:[range]CommentToggle`: The same on the range.
+//!
+//! The lines are commented if any of them isn't (the blank lines are skipped), the leader is at
+//! the smallest indent so the block stays aligned. It's dot-repeatable and one undo step.
+//!
+//! The comment string comes from the language under the first line: the embedded language by
+//! treesitter (e.g. the Lua code in the Vim file, the code block in Markdown), then the
+//! `COMMENT_STRINGS` override, then its `commentstring`.
+//!
+
+///
+/// The comment string overrides of the filetypes (the `%s` is the text)
+///
+const COMMENT_STRINGS: [(&'static str, &'static str); 6] = [
+    ("c", "// %s"),
+    ("cpp", "// %s"),
+    ("jsonc", "// %s"),
+    ("sql", "-- %s"),
+    ("toml", "# %s"),
+    ("zig", "// %s"),
+];
+
+///
+/// The Lua global function name of the `gc` operator (`operatorfunc`)
+///
+const COMMENT_OPERATOR_FUNCTION: &'static str = "MyConfigCommentOperator";
+
+///
+/// Get back the filetype of the embedded language at the line by treesitter, `None` if it's the
+/// same with the buffer one (or there is no parser).
+///
+fn get_embedded_filetype(buffer: &Buffer, row: usize, col: usize) -> Option<String> {
+    let lua_code = r#"local bufnr, row, col = ...
+local parser = vim.treesitter.get_parser(bufnr, nil, { error = false })
+if not parser then
+    return nil
+end
+parser:parse({ row, row + 1 })
+
+local language = parser:language_for_range({ row, col, row, col }):lang()
+if language == parser:lang() then
+    return nil
+end
+return vim.treesitter.language.get_filetypes(language)[1] or language"#;
+
+    exec_lua::<_, String>(lua_code, (buffer.handle() as i64, row as i64, col as i64))
+}
+
+///
+/// Get back the comment leader and trailer of the line, e.g. `("<!--", "-->")`.
+///
+fn get_comment_delimiters(buffer: &Buffer, row: usize, col: usize) -> (String, String) {
+    let embedded_filetype = get_embedded_filetype(buffer, row, col);
+    let filetype = embedded_filetype.clone().unwrap_or_else(|| {
+        get_option_value::<NvimString>(
+            "filetype",
+            &OptionOpts::builder().buffer(buffer.clone()).build(),
+        )
+        .map(|filetype| filetype.to_string())
+        .unwrap_or_default()
+    });
+
+    let comment_string = match COMMENT_STRINGS
+        .iter()
+        .find(|(comment_filetype, _)| *comment_filetype == filetype)
+    {
+        Some((_, comment_string)) => comment_string.to_string(),
+        None if embedded_filetype.is_some() => exec_lua::<_, String>(
+            "return vim.filetype.get_option(..., 'commentstring')",
+            (filetype.as_str(),),
+        )
+        .unwrap_or_default(),
+        None => get_option_value::<NvimString>(
+            "commentstring",
+            &OptionOpts::builder().buffer(buffer.clone()).build(),
+        )
+        .map(|value| value.to_string())
+        .unwrap_or_default(),
+    };
+
+    match comment_string.split_once("%s") {
+        Some((leader, trailer)) => (leader.trim().to_owned(), trailer.trim().to_owned()),
+        None => (String::new(), String::new()),
+    }
+}
+
+///
+/// Strip the leader of the content on the token boundary: the leader has to be followed by the
+/// whitespace, the trailer or nothing, so the `///` doc comment isn't taken as the `//` comment.
+///
+fn strip_leader<'a>(content: &'a str, leader: &str, trailer: &str) -> Option<&'a str> {
+    let rest = content.strip_prefix(leader)?;
+    let is_boundary = rest.is_empty()
+        || rest.starts_with(char::is_whitespace)
+        || (!trailer.is_empty() && rest.starts_with(trailer));
+
+    if is_boundary { Some(rest) } else { None }
+}
+
+///
+/// Whether the line is commented by the leader and trailer
+///
+fn is_commented_line(line: &str, leader: &str, trailer: &str) -> bool {
+    let content = line.trim();
+    strip_leader(content, leader, trailer).is_some()
+        && (trailer.is_empty() || content.ends_with(trailer))
+}
+
+///
+/// Remove the leader and the trailer (and the space next to them) of the commented line
+///
+fn uncomment_line(line: &str, leader: &str, trailer: &str) -> String {
+    let indent_len = line.len() - line.trim_start().len();
+    let (indent, content) = line.split_at(indent_len);
+
+    let mut content = content.trim_end();
+    content = strip_leader(content, leader, trailer).unwrap_or(content);
+    content = content.strip_prefix(' ').unwrap_or(content);
+    if !trailer.is_empty() {
+        content = content.strip_suffix(trailer).unwrap_or(content);
+        content = content.strip_suffix(' ').unwrap_or(content);
+    }
+
+    if content.is_empty() {
+        String::new()
+    } else {
+        format!("{indent}{content}")
+    }
+}
+
+///
+/// Toggle the comment of the lines (0-based, inclusive)
+///
+fn toggle_comment_lines(start_row: usize, end_row: usize) {
+    let mut buffer = Buffer::current();
+    let Ok(lines) = buffer.get_lines(start_row..end_row + 1, false) else {
+        return;
+    };
+    let lines = lines.map(|line| line.to_string()).collect::<Vec<String>>();
+
+    //
+    // The smallest indent of the non-blank lines, it's also where the language is detected.
+    //
+    let min_indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min();
+    let Some(min_indent) = min_indent else {
+        return;
+    };
+
+    let first_row = start_row
+        + lines
+            .iter()
+            .position(|line| !line.trim().is_empty())
+            .unwrap_or(0);
+    let (leader, trailer) = get_comment_delimiters(&buffer, first_row, min_indent);
+    if leader.is_empty() {
+        notify(
+            "comment",
+            "No 'commentstring' for the filetype.",
+            EchoLevel::Warning,
+        );
+        return;
+    }
+
+    let is_all_commented = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| is_commented_line(line, &leader, &trailer));
+
+    let toggled_lines = lines
+        .iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                line.clone()
+            } else if is_all_commented {
+                uncomment_line(line, &leader, &trailer)
+            } else {
+                let (indent, content) = line.split_at(min_indent);
+                if trailer.is_empty() {
+                    format!("{indent}{leader} {content}")
+                } else {
+                    format!("{indent}{leader} {content} {trailer}")
+                }
+            }
+        })
+        .collect::<Vec<String>>();
+
+    let _ = buffer.set_lines(start_row..end_row + 1, false, toggled_lines);
+}
+
+///
+/// `gc{motion}` (the `operatorfunc`) and `gc` in `VISUAL` mode: Toggle the lines between the
+/// marks, the `motion_type` is `char`/`line`/`block` of the operator, or the `visualmode()`.
+///
+fn comment_operator(motion_type: &str) {
+    let (start_mark, end_mark) = match motion_type {
+        "char" | "line" | "block" => ("'[", "']"),
+        _ => ("'<", "'>"),
+    };
+
+    let get_row = |mark: &str| {
+        let position = call_function::<_, Vec<i64>>("getpos", (mark,)).ok()?;
+        Some((*position.get(1)? - 1).max(0) as usize)
+    };
+    let (Some(start_row), Some(end_row)) = (get_row(start_mark), get_row(end_mark)) else {
+        return;
+    };
+
+    toggle_comment_lines(start_row.min(end_row), start_row.max(end_row));
+}
+
+///
+///
+///
+pub fn setup() {
+    register_user_command(
+        "CommentToggle",
+        UserCommandSpec {
+            desc: "Toggle the comment of the range lines",
+            range: true,
+            ..Default::default()
+        },
+        |args: UserCommandArgs| {
+            let (line1, line2) = args.range.unwrap_or_else(|| {
+                let (row, _) = Window::current().get_cursor().unwrap_or((1, 0));
+                (row, row)
+            });
+            toggle_comment_lines(line1.max(1) - 1, line2.max(1) - 1);
+        },
+    );
+
+    //
+    // Expose the operator as the Lua global function, so `operatorfunc` can call it by `v:lua`.
+    // The builtin `gc`/`gcc` are kept if it fails, rather than mapping them to the missing
+    // function (`E117`).
+    //
+    let registered = call_function::<_, Object>(
+        "luaeval",
+        (
+            format!("(function(f) _G.{COMMENT_OPERATOR_FUNCTION} = f end)(_A)"),
+            Function::<(String,), ()>::from_fn(|(motion_type,)| comment_operator(&motion_type)),
+        ),
+    )
+    .context("register the comment operator function");
+    if report("comment", registered).is_none() {
+        return;
+    }
+
+    //
+    // The count of `3gcc` is kept for `g@_`, so it toggles 3 lines.
+    //
+    let comment_keybindings: Vec<(Mode, &str, String, &str)> = vec![
+        (
+            Mode::Normal,
+            "gcc",
+            format!("<Cmd>set operatorfunc=v:lua.{COMMENT_OPERATOR_FUNCTION}<CR>g@_"),
+            "'gcc': Toggle the comment of the line",
+        ),
+        (
+            Mode::Normal,
+            "gc",
+            format!("<Cmd>set operatorfunc=v:lua.{COMMENT_OPERATOR_FUNCTION}<CR>g@"),
+            "'gc{motion}': Toggle the comment of the motion lines",
+        ),
+        (
+            Mode::Visual,
+            "gc",
+            format!("<Esc><Cmd>lua {COMMENT_OPERATOR_FUNCTION}(vim.fn.visualmode())<CR>"),
+            "'gc': Toggle the comment of the selected lines",
+        ),
+    ];
+
+    for (mode, key, rhs, desc) in comment_keybindings {
+        let _ = set_keymap(
+            mode,
+            key,
+            &rhs,
+            &SetKeymapOpts::builder().desc(desc).silent(true).build(),
+        );
+    }
+}
+
+use crate::{
+    error::{ResultContext, report},
+    extended_api::{EchoLevel, UserCommandArgs, UserCommandSpec, exec_lua, register_user_command},
+    notify::notify,
+};
+
+use nvim_oxi::{
+    Function, Object, String as NvimString,
+    api::{
+        Buffer, Window, call_function, get_option_value,
+        opts::{OptionOpts, SetKeymapOpts},
+        set_keymap,
+        types::Mode,
+    },
+};
@@ -0,0 +1,321 @@
+//! Comment-aware `gq` for the doc comments: the comment lines are joined into paragraphs and
+//! re-wrapped by `textwidth` (or `COMMENT_FORMAT_DEFAULT_TEXTWIDTH`), and:
+//!
+//! - The comment leader (`///`, `//!`, `//`, `#`, etc. from `commentstring`) and the indent are
+//!   kept on every line, and the paragraph never joins the lines with another leader.
+//! - The bullet items (`- `, `* `, `+ `, `1. `) start a new paragraph, the wrapped lines are
+//!   indented under the bullet text.
+//! - The empty comment lines (e.g. the decorative `//\n// text\n//` banners), the code fences
+//!   and the lines inside, the separator lines (`----`) and the ASCII diagrams are kept as is.
+//!
+//! It's the `formatexpr` of the `COMMENT_FORMAT_FILETYPES` buffers, the non-comment lines and the
+//! auto-wrap in `INSERT` mode fall back to the builtin formatting. `:[range]CommentFormat` does
+//! the same on any buffer.
+//!
+
+///
+/// The filetypes which use the comment-aware `formatexpr`
+///
+const COMMENT_FORMAT_FILETYPES: [&'static str; 8] =
+    ["rust", "zig", "c", "cpp", "lua", "sh", "fish", "toml"];
+
+///
+/// The wrap width if `textwidth` is `0`, it's the same with the `rustfmt` max width.
+///
+const COMMENT_FORMAT_DEFAULT_TEXTWIDTH: usize = 100;
+
+///
+/// The Lua global function name of the `formatexpr`
+///
+const COMMENT_FORMAT_EXPR_FUNCTION: &'static str = "MyConfigCommentFormatExpr";
+
+///
+/// The paragraph to wrap
+///
+#[derive(Debug, Default)]
+struct CommentParagraph {
+    prefix: String,
+
+    //
+    // The bullet marker with the trailing space (e.g. `- `), and the indent before it
+    //
+    first_lead: String,
+
+    //
+    // The indent of the wrapped lines, it's the width of `first_lead` for the bullet item.
+    //
+    rest_lead: String,
+    words: Vec<String>,
+}
+
+///
+/// Whether the comment content is kept as is: empty, the separator, the ASCII diagram or the
+/// arrow of it, the markdown heading.
+///
+fn is_verbatim_content(content: &str) -> bool {
+    let trimmed = content.trim();
+
+    trimmed.is_empty()
+        || trimmed.contains(" <-- ")
+        || trimmed.starts_with(|c: char| c == '|' || c == '\\' || c == '#')
+        || trimmed.starts_with("/-")
+        || (trimmed.len() >= 3 && trimmed.chars().all(|c| "-=*~_+/\\|".contains(c)))
+}
+
+///
+/// Split the bullet item content into `(lead, text)`, e.g. `"  - item"` -> `("  - ", "item")`
+///
+fn split_bullet(content: &str) -> Option<(String, &str)> {
+    let indent_len = content.len() - content.trim_start().len();
+    let rest = &content[indent_len..];
+
+    let marker_len = if rest.starts_with("- ") || rest.starts_with("* ") || rest.starts_with("+ ") {
+        1
+    } else {
+        let digits_len = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let after_digits = &rest[digits_len..];
+        if digits_len == 0 || !(after_digits.starts_with(". ") || after_digits.starts_with(") ")) {
+            return None;
+        }
+        digits_len + 1
+    };
+
+    let text = rest[marker_len..].trim_start();
+    let lead_len = content.len() - text.len();
+    Some((content[..lead_len].to_owned(), text))
+}
+
+///
+/// Wrap the paragraph into the lines by the width
+///
+fn wrap_paragraph(paragraph: &CommentParagraph, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current_line = format!("{}{}", paragraph.prefix, paragraph.first_lead);
+    let mut current_width = display_width(&current_line);
+    let mut has_word = false;
+
+    for word in &paragraph.words {
+        let word_width = display_width(word);
+
+        //
+        // The long word (e.g. URL) is on its own line
+        //
+        if has_word && current_width + 1 + word_width > width {
+            lines.push(current_line);
+            current_line = format!("{}{}", paragraph.prefix, paragraph.rest_lead);
+            current_width = display_width(&current_line);
+            has_word = false;
+        }
+
+        if has_word {
+            current_line.push(' ');
+            current_width += 1;
+        }
+        current_line.push_str(word);
+        current_width += word_width;
+        has_word = true;
+    }
+
+    lines.push(current_line);
+    lines
+}
+
+///
+/// Format the comment lines, the non-comment lines are kept as is.
+///
+fn format_comment_lines(lines: &[String], width: usize) -> Vec<String> {
+    let mut formatted_lines = Vec::with_capacity(lines.len());
+    let mut paragraph: Option<CommentParagraph> = None;
+    let mut in_code_fence = false;
+
+    let flush = |paragraph: &mut Option<CommentParagraph>, formatted_lines: &mut Vec<String>| {
+        if let Some(paragraph) = paragraph.take() {
+            formatted_lines.extend(wrap_paragraph(&paragraph, width));
+        }
+    };
+
+    for line in lines {
+        let prefix = get_comment_prefix(line);
+        let is_comment = !prefix.trim().is_empty();
+        let content = line
+            .strip_prefix(prefix.as_str())
+            .or_else(|| line.strip_prefix(prefix.trim_end()))
+            .unwrap_or_default();
+
+        let is_fence = content.trim_start().starts_with("```");
+        if !is_comment || in_code_fence || is_fence || is_verbatim_content(content) {
+            flush(&mut paragraph, &mut formatted_lines);
+            if is_comment && is_fence {
+                in_code_fence = !in_code_fence;
+            }
+            formatted_lines.push(line.clone());
+            continue;
+        }
+
+        if let Some((first_lead, text)) = split_bullet(content) {
+            flush(&mut paragraph, &mut formatted_lines);
+            paragraph = Some(CommentParagraph {
+                rest_lead: " ".repeat(display_width(&first_lead)),
+                first_lead,
+                prefix,
+                words: text.split_whitespace().map(|v| v.to_owned()).collect(),
+            });
+            continue;
+        }
+
+        //
+        // The bullet item ends at the line which is not indented under the bullet text
+        //
+        let indent_len = content.len() - content.trim_start().len();
+        match paragraph.as_mut() {
+            Some(current)
+                if current.prefix == prefix
+                    && (current.first_lead.trim().is_empty()
+                        || indent_len >= current.rest_lead.len()) =>
+            {
+                current
+                    .words
+                    .extend(content.split_whitespace().map(|v| v.to_owned()));
+            }
+            _ => {
+                flush(&mut paragraph, &mut formatted_lines);
+                paragraph = Some(CommentParagraph {
+                    prefix,
+                    first_lead: content[..indent_len].to_owned(),
+                    rest_lead: content[..indent_len].to_owned(),
+                    words: content.split_whitespace().map(|v| v.to_owned()).collect(),
+                });
+            }
+        }
+    }
+
+    flush(&mut paragraph, &mut formatted_lines);
+    formatted_lines
+}
+
+///
+/// Format the lines (1-based, inclusive) of the current buffer, the cursor moves to the last
+/// formatted line like `gq`.
+///
+fn format_range(line1: usize, line2: usize) {
+    let mut buffer = Buffer::current();
+    let Ok(lines) = buffer.get_lines(line1 - 1..line2, false) else {
+        return;
+    };
+    let lines = lines.map(|line| line.to_string()).collect::<Vec<String>>();
+
+    let textwidth = get_option_value::<i64>(
+        "textwidth",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    )
+    .unwrap_or(0);
+    let width = if textwidth > 0 {
+        textwidth as usize
+    } else {
+        COMMENT_FORMAT_DEFAULT_TEXTWIDTH
+    };
+
+    let formatted_lines = format_comment_lines(&lines, width);
+    let last_row = line1 + formatted_lines.len().saturating_sub(1);
+
+    if formatted_lines != lines {
+        let _ = buffer.set_lines(line1 - 1..line2, false, formatted_lines);
+    }
+    let _ = Window::current().set_cursor(last_row, 0);
+}
+
+///
+/// The `formatexpr`: return `1` to fall back to the builtin formatting (the auto-wrap in `INSERT`
+/// mode, or the first line is not a comment), otherwise, format the `v:lnum` and `v:count` lines.
+///
+fn format_expr() -> i64 {
+    let mode = call_function::<_, String>("mode", ()).unwrap_or_default();
+    if mode.starts_with('i') || mode.starts_with('R') {
+        return 1;
+    }
+
+    let line1 = get_vvar::<i64>("lnum").unwrap_or(0).max(1) as usize;
+    let count = get_vvar::<i64>("count").unwrap_or(1).max(1) as usize;
+
+    let is_comment = Buffer::current()
+        .get_lines(line1 - 1..line1, false)
+        .ok()
+        .and_then(|mut lines| lines.next())
+        .is_some_and(|line| !get_comment_prefix(&line.to_string()).trim().is_empty());
+    if !is_comment {
+        return 1;
+    }
+
+    format_range(line1, line1 + count - 1);
+    0
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_user_command(
+        "CommentFormat",
+        |args: CommandArgs| {
+            format_range(args.line1, args.line2);
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Comment-aware format (join and wrap) the comment lines")
+            .range(CommandRange::CurrentLine)
+            .build(),
+    );
+
+    //
+    // Expose the `formatexpr` as the Lua global function, so `v:lua` can call it.
+    //
+    let _ = exec_lua::<_, Object>(
+        "local name, f = ...; _G[name] = f",
+        (
+            COMMENT_FORMAT_EXPR_FUNCTION,
+            Function::<(), i64>::from_fn(|()| format_expr()),
+        ),
+    );
+
+    let _ = create_autocmd(
+        vec!["FileType"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-comment-format-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .patterns(COMMENT_FORMAT_FILETYPES.to_vec())
+            .callback(|args: AutocmdCallbackArgs| {
+                let _ = set_option_value(
+                    "formatexpr",
+                    format!("v:lua.{COMMENT_FORMAT_EXPR_FUNCTION}()"),
+                    &OptionOpts::builder().buffer(args.buffer).build(),
+                );
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+use crate::{
+    extended_api::exec_lua,
+    utils::{display_width, get_comment_prefix},
+};
+
+use nvim_oxi::{
+    Function, Object,
+    api::{
+        Buffer, Window, call_function, create_augroup, create_autocmd, create_user_command,
+        get_option_value, get_vvar,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, CreateCommandOpts, OptionOpts},
+        set_option_value,
+        types::{AutocmdCallbackArgs, CommandArgs, CommandRange},
+    },
+};
@@ -0,0 +1,371 @@
+//! Conventional commit message assistant for the `gitcommit` buffers (e.g. `git commit` in the
+//! floating terminal with Neovim as the editor):
+//!
+//! - `<leader>ct`: Pick the type (`feat`, `fix`, `chore`, etc.) and then the scope (completed from
+//!   the recent commits, or type a new one), the subject prefix `type(scope): ` is inserted or
+//!   replaced.
+//! - `<leader>cw`: Wrap the body (the lines between the subject and the `#` comments) by
+//!   `COMMIT_BODY_WIDTH`, it's also the `textwidth` of the buffer.
+//! - Before saving, the lint warns on the long subject line (`COMMIT_SUBJECT_MAX_WIDTH`), the
+//!   non-empty second line and the subject without the type prefix.
+//!
+
+///
+/// The conventional commit types: (type, description)
+///
+const COMMIT_TYPES: [(&'static str, &'static str); 11] = [
+    ("feat", "A new feature"),
+    ("fix", "A bug fix"),
+    ("chore", "Maintenance, no production code change"),
+    ("docs", "Documentation only"),
+    ("refactor", "Neither fixes a bug nor adds a feature"),
+    ("perf", "Performance improvement"),
+    ("test", "Add or fix tests"),
+    ("build", "Build system or dependencies"),
+    ("ci", "CI configuration"),
+    ("style", "Formatting, no code change"),
+    ("revert", "Revert a previous commit"),
+];
+
+///
+/// The subject line longer than it gets the lint warning
+///
+const COMMIT_SUBJECT_MAX_WIDTH: usize = 50;
+
+///
+/// The body wrap width
+///
+const COMMIT_BODY_WIDTH: usize = 72;
+
+///
+/// How many recent commits to collect the scopes from
+///
+const COMMIT_SCOPE_HISTORY_SIZE: usize = 300;
+
+///
+/// The scope picker item which means no scope
+///
+const COMMIT_NO_SCOPE: &'static str = "(no scope)";
+
+///
+/// Private module-scope state: the commit buffer and the picked type, while the scope picker is
+/// open.
+///
+static COMMIT_MESSAGE_STATE: LazyLock<Mutex<Option<(BufHandle, String)>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+///
+/// Split the subject into `(type, scope, description)`, e.g. `fix(picker): typo` ->
+/// `(fix, Some(picker), typo)`. Return `None` if it has no type prefix.
+///
+fn parse_subject(subject: &str) -> Option<(&str, Option<&str>, &str)> {
+    let (prefix, description) = subject.split_once(": ")?;
+    let prefix = prefix.strip_suffix('!').unwrap_or(prefix);
+
+    let (commit_type, scope) = match prefix.split_once('(') {
+        Some((commit_type, scope)) => (commit_type, Some(scope.strip_suffix(')')?)),
+        None => (prefix, None),
+    };
+
+    if commit_type.is_empty() || !commit_type.chars().all(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+
+    Some((commit_type, scope, description))
+}
+
+///
+/// Get back the scopes of the recent commits, the most used one first
+///
+fn get_recent_scopes() -> Vec<String> {
+    let git_dir = call_function::<_, String>("expand", ("%:p:h",)).unwrap_or_default();
+    let max_count = format!("--max-count={COMMIT_SCOPE_HISTORY_SIZE}");
+    let cmd_utils::ExecuteCommandResult::Success { output, .. } = cmd_utils::execute_command(vec![
+        "git",
+        "-C",
+        &git_dir,
+        "log",
+        &max_count,
+        "--format=%s",
+    ]) else {
+        return Vec::new();
+    };
+
+    let mut scope_counts = HashMap::<String, usize>::new();
+    for subject in output.lines() {
+        if let Some((_, Some(scope), _)) = parse_subject(subject)
+            && !scope.is_empty()
+        {
+            *scope_counts.entry(scope.to_owned()).or_default() += 1;
+        }
+    }
+
+    let mut scopes = scope_counts.into_iter().collect::<Vec<(String, usize)>>();
+    scopes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    scopes.into_iter().map(|(scope, _)| scope).collect()
+}
+
+///
+/// Insert or replace the `type(scope): ` prefix of the subject line
+///
+fn set_subject_prefix(buffer_handle: BufHandle, commit_type: &str, scope: &str) {
+    let mut buffer = Buffer::from(buffer_handle);
+    let subject = buffer
+        .get_lines(0..1, false)
+        .ok()
+        .and_then(|mut lines| lines.next())
+        .map(|line| line.to_string())
+        .unwrap_or_default();
+
+    let description = match parse_subject(&subject) {
+        Some((_, _, description)) => description.to_owned(),
+        None => subject.clone(),
+    };
+    let prefix = if scope.is_empty() {
+        format!("{commit_type}: ")
+    } else {
+        format!("{commit_type}({scope}): ")
+    };
+
+    let _ = buffer.set_lines(0..1, false, vec![format!("{prefix}{description}")]);
+
+    //
+    // Continue typing the description
+    //
+    if buffer_handle == Buffer::current().handle() {
+        let _ = Window::current().set_cursor(1, prefix.len() + description.len());
+        let _ = command("startinsert!");
+    }
+}
+
+///
+/// Get back the body range (1-based, inclusive): from the 3rd line to the last non-comment line
+///
+fn get_body_range(buffer: &Buffer) -> Option<(usize, usize)> {
+    let lines = buffer.get_lines(.., false).ok()?;
+    let body_end = lines
+        .map(|line| line.to_string())
+        .enumerate()
+        .skip(2)
+        .take_while(|(_, line)| !line.starts_with('#'))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .last()
+        .map(|(index, _)| index + 1)?;
+
+    Some((3, body_end))
+}
+
+///
+/// `<leader>cw`: Wrap the body by `textwidth` (`COMMIT_BODY_WIDTH`)
+///
+fn wrap_body() {
+    let Some((body_start, body_end)) = get_body_range(&Buffer::current()) else {
+        return;
+    };
+
+    let _ = command(&format!("keepjumps normal! {body_start}Ggq{body_end}G"));
+}
+
+///
+/// Lint the commit message before saving
+///
+fn lint_commit_message(buffer: &Buffer) {
+    let lines = buffer
+        .get_lines(0..2, false)
+        .map(|lines| lines.map(|line| line.to_string()).collect::<Vec<String>>())
+        .unwrap_or_default();
+    let Some(subject) = lines.first().filter(|line| !line.starts_with('#')) else {
+        return;
+    };
+
+    let mut warnings = Vec::new();
+    let subject_width = display_width(subject);
+    if subject_width > COMMIT_SUBJECT_MAX_WIDTH {
+        warnings.push(format!(
+            "The subject is {subject_width} chars (> {COMMIT_SUBJECT_MAX_WIDTH})."
+        ));
+    }
+    if parse_subject(subject).is_none() {
+        warnings.push("The subject has no 'type(scope): ' prefix.".to_string());
+    }
+    if lines
+        .get(1)
+        .is_some_and(|line| !line.is_empty() && !line.starts_with('#'))
+    {
+        warnings.push("The second line should be empty.".to_string());
+    }
+
+    if !warnings.is_empty() {
+        echo_message("commit_message", &warnings.join(" "), EchoLevel::Warning);
+    }
+}
+
+///
+/// Set the buffer options and keybindings of the `gitcommit` buffer
+///
+fn setup_commit_buffer(mut buffer: Buffer, group: u32) {
+    let opts = OptionOpts::builder().buffer(buffer.clone()).build();
+    let _ = set_option_value("textwidth", COMMIT_BODY_WIDTH as i64, &opts);
+
+    let commit_buffer_local_keybindings: Vec<(&str, &str, Box<dyn Fn()>)> = vec![
+        (
+            "<leader>ct",
+            "Commit message: pick the type and scope prefix",
+            Box::new(|| {
+                *COMMIT_MESSAGE_STATE.lock().unwrap() =
+                    Some((Buffer::current().handle(), String::new()));
+                open_registered_picker("commit_types");
+            }),
+        ),
+        (
+            "<leader>cw",
+            "Commit message: wrap the body",
+            Box::new(wrap_body),
+        ),
+    ];
+
+    for bindings in commit_buffer_local_keybindings {
+        let _ = buffer.set_keymap(
+            Mode::Normal,
+            bindings.0,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(bindings.1)
+                .callback(move |_| {
+                    bindings.2();
+                    ()
+                })
+                .silent(true)
+                .build(),
+        );
+    }
+
+    let _ = create_autocmd(
+        vec!["BufWritePre"],
+        &CreateAutocmdOpts::builder()
+            .group(group)
+            .buffer(buffer)
+            .callback(|args: AutocmdCallbackArgs| {
+                lint_commit_message(&args.buffer);
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+///
+///
+///
+pub fn setup() {
+    let group = create_augroup(
+        "custom-commit-message-group",
+        &CreateAugroupOpts::builder().clear(true).build(),
+    )
+    .unwrap();
+
+    let _ = create_autocmd(
+        vec!["FileType"],
+        &CreateAutocmdOpts::builder()
+            .group(group)
+            .patterns(vec!["gitcommit"])
+            .callback(move |args: AutocmdCallbackArgs| {
+                setup_commit_buffer(args.buffer, group);
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    register_picker_source(
+        "commit_types",
+        "Commit type",
+        || {
+            COMMIT_TYPES
+                .iter()
+                .map(|(commit_type, description)| PickerItem {
+                    columns: vec![
+                        PickerItemColumn::new(commit_type, Some("Special")),
+                        PickerItemColumn::new(description, Some("Comment")),
+                    ],
+                    icon: None,
+                    data: commit_type.to_string(),
+                })
+                .collect()
+        },
+        |selected_type: String| {
+            if let Some((_, commit_type)) = COMMIT_MESSAGE_STATE.lock().unwrap().as_mut() {
+                *commit_type = selected_type;
+            }
+
+            //
+            // Open the scope picker in the next event loop tick, as the type picker windows are
+            // still closing at this moment.
+            //
+            nvim::schedule(move |_| {
+                open_registered_picker("commit_scopes");
+            });
+        },
+    );
+
+    register_picker_source(
+        "commit_scopes",
+        "Commit scope (type a new one to add it)",
+        || {
+            std::iter::once(PickerItem::from(COMMIT_NO_SCOPE))
+                .chain(get_recent_scopes().into_iter().map(PickerItem::from))
+                .collect()
+        },
+        |selected_scope: String| {
+            let Some((buffer_handle, commit_type)) = COMMIT_MESSAGE_STATE.lock().unwrap().take()
+            else {
+                return;
+            };
+            if commit_type.is_empty() {
+                return;
+            }
+
+            let scope = match selected_scope.trim() {
+                COMMIT_NO_SCOPE => "",
+                scope => scope,
+            };
+            let scope = scope.to_owned();
+
+            //
+            // Back to the commit buffer after the scope picker windows are closed
+            //
+            nvim::schedule(move |_| {
+                set_subject_prefix(buffer_handle, &commit_type, &scope);
+            });
+        },
+    );
+}
+
+use crate::{
+    extended_api::{EchoLevel, echo_message},
+    picker::{PickerItem, PickerItemColumn, open_registered_picker, register_picker_source},
+    utils::display_width,
+};
+
+use rust_utils::cmd as cmd_utils;
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim, BufHandle,
+    api::{
+        Buffer, Window, call_function, command, create_augroup, create_autocmd,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, SetKeymapOpts},
+        set_option_value,
+        types::{AutocmdCallbackArgs, Mode},
+    },
+};
@@ -0,0 +1,133 @@
+//! The completion by the Neovim built-in popup menu, no completion plugin needed:
+//!
+//! - The buffer which has the LSP client with the completion provider: `vim.lsp.completion` is
+//!   enabled, the popup opens automatically on the trigger characters (see
+//!   `COMPLETION_LSP_AUTOTRIGGER`, or `completion.autotrigger` of the config file).
+//! - `<C-j>`/`<C-k>` in `INSERT` mode: Select the next/prev candidate when the popup is open,
+//!   otherwise `<C-j>` opens it by the `omnifunc` (LSP), or the buffer words if there is no
+//!   `omnifunc`.
+//! - `<C-f>` in `INSERT` mode: Complete the file path (`<C-x><C-f>`).
+//! - `<C-y>` accepts the selected candidate, `<C-e>` closes the popup (the Neovim default).
+//!
+
+///
+/// Set to `false` to only open the LSP completion popup by `<C-j>`, it's the default of
+/// `completion.autotrigger` of the config file.
+///
+const COMPLETION_LSP_AUTOTRIGGER: bool = true;
+
+///
+/// The max candidate count of the popup
+///
+const COMPLETION_POPUP_HEIGHT: i64 = 15;
+
+///
+/// Enable `vim.lsp.completion` for the buffer if the attached client supports it
+///
+fn enable_lsp_completion(buffer: &Buffer, client_id: i64) {
+    let lua_code = r#"local bufnr, client_id, autotrigger = ...
+local client = vim.lsp.get_client_by_id(client_id)
+if client and client:supports_method("textDocument/completion") then
+    vim.lsp.completion.enable(true, client_id, bufnr, { autotrigger = autotrigger })
+end"#;
+
+    let result = try_exec_lua::<_, Object>(
+        lua_code,
+        (
+            buffer.handle() as i64,
+            client_id,
+            get_config_bool("completion.autotrigger", COMPLETION_LSP_AUTOTRIGGER),
+        ),
+    )
+    .context("enable the LSP completion");
+    report("completion", result);
+}
+
+///
+///
+///
+pub fn setup() {
+    let global_opts = OptionOpts::builder().scope(OptionScope::Global).build();
+
+    //
+    // The buffer words come from the current buffer, the other windows, the loaded and the
+    // unloaded buffers (no tags and included files, they're slow).
+    //
+    let _ = set_option_value("complete", ".,w,b,u", &global_opts);
+    let _ = set_option_value("pumheight", COMPLETION_POPUP_HEIGHT, &global_opts);
+
+    let completion_keybindings: Vec<(&str, &str, &str)> = vec![
+        (
+            "<C-j>",
+            r#"pumvisible() ? "\<C-n>" : (&omnifunc != "" ? "\<C-x>\<C-o>" : "\<C-n>")"#,
+            "Next completion candidate or open the completion popup",
+        ),
+        (
+            "<C-k>",
+            r#"pumvisible() ? "\<C-p>" : "\<C-k>""#,
+            "Previous completion candidate",
+        ),
+        ("<C-f>", r#""\<C-x>\<C-f>""#, "Complete the file path"),
+    ];
+
+    for bindings in completion_keybindings {
+        let _ = set_keymap(
+            Mode::Insert,
+            bindings.0,
+            bindings.1,
+            &SetKeymapOpts::builder()
+                .desc(bindings.2)
+                .expr(true)
+                .silent(true)
+                .build(),
+        );
+    }
+
+    let _ = create_autocmd(
+        vec!["LspAttach"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-completion-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                //
+                // `data.client_id` of the `LspAttach` event
+                //
+                let client_id = Dictionary::from_object(args.data)
+                    .ok()
+                    .and_then(|data| data.get("client_id").cloned())
+                    .and_then(|client_id| i64::from_object(client_id).ok());
+
+                if let Some(client_id) = client_id {
+                    enable_lsp_completion(&args.buffer, client_id);
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+use crate::{
+    config::get_config_bool,
+    error::{ResultContext, report},
+    extended_api::try_exec_lua,
+};
+
+use nvim_oxi::{
+    Dictionary, Object,
+    api::{
+        Buffer, create_augroup, create_autocmd,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, OptionScope, SetKeymapOpts},
+        set_keymap, set_option_value,
+        types::{AutocmdCallbackArgs, Mode},
+    },
+    conversion::FromObject,
+};
@@ -0,0 +1,508 @@
+//! The user config file `stdpath("config")/my_neovim_configuration.toml`, it overrides the module
+//! defaults (the `const`s) of the options in `CONFIG_OPTIONS`, e.g.:
+//!
+//! ```toml
+//! [completion]
+//! autotrigger = false
+//!
+//! [diagnostics]
+//! float_on_cursor_hold = false
+//!
+//! [file_delete]
+//! to_trash = false
+//!
+//! [tab_buffers]
+//! scoped_cycling = false
+//! ```
+//!
+//! - The renamed options keep working by `DEPRECATED_OPTIONS` (the old name to the new one), each
+//!   old name in the config file is warned once when it's loaded.
+//! - `:MyConfigMigrate`: Rewrite the config file to the new option names, the original one is
+//!   kept as `my_neovim_configuration.toml.bak`.
+//!
+//! Only the subset of TOML is supported: the `[section]` tables, and the boolean, integer and
+//! one-line string values.
+//!
+
+///
+/// The config file name under `stdpath("config")`
+///
+const CONFIG_FILE_NAME: &'static str = "my_neovim_configuration.toml";
+
+///
+/// The options which can be set in the config file, `section.key`
+///
+const CONFIG_OPTIONS: [&'static str; 4] = [
+    "completion.autotrigger",
+    "diagnostics.float_on_cursor_hold",
+    "file_delete.to_trash",
+    "tab_buffers.scoped_cycling",
+];
+
+///
+/// The renamed options: the old name and the new one, e.g. `("file_delete.trash",
+/// "file_delete.to_trash")`. Add the entry when renaming the option in `CONFIG_OPTIONS`, so the
+/// existing config files keep working and `:MyConfigMigrate` can rewrite them. No option has
+/// been renamed yet.
+///
+const DEPRECATED_OPTIONS: [(&'static str, &'static str); 0] = [];
+
+///
+/// The option value of the config file
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    Bool(bool),
+    Integer(i64),
+    String(String),
+}
+
+///
+/// The `key = value` line of the config file, `name` is `section.key` (or `key` before the first
+/// section).
+///
+#[derive(Debug)]
+struct ConfigEntry {
+    line_index: usize,
+    section: String,
+    name: String,
+    value_text: String,
+}
+
+///
+/// The loaded options, the deprecated names are already resolved to the new ones.
+///
+#[derive(Debug, Default)]
+struct Config {
+    options: HashMap<String, ConfigValue>,
+}
+
+///
+/// Private module-scope state
+///
+static CONFIG_STATE: LazyLock<Mutex<Config>> = LazyLock::new(|| Mutex::new(Config::default()));
+
+///
+/// Get back the config file path
+///
+fn get_config_file() -> Option<PathBuf> {
+    let config_dir = call_function::<_, String>("stdpath", ("config",)).ok()?;
+    Some(PathBuf::from(config_dir).join(CONFIG_FILE_NAME))
+}
+
+///
+/// Parse the value text, the trailing `# comment` is ignored.
+///
+fn parse_config_value(value_text: &str) -> std::result::Result<ConfigValue, String> {
+    for quote in ['"', '\''] {
+        if let Some(rest) = value_text.strip_prefix(quote) {
+            return rest
+                .find(quote)
+                .map(|end| ConfigValue::String(rest[..end].to_string()))
+                .ok_or_else(|| format!("unclosed {quote}"));
+        }
+    }
+
+    let value_text = value_text
+        .split_once('#')
+        .map_or(value_text, |(value, _)| value)
+        .trim();
+    match value_text {
+        "true" => Ok(ConfigValue::Bool(true)),
+        "false" => Ok(ConfigValue::Bool(false)),
+        _ => value_text
+            .replace('_', "")
+            .parse::<i64>()
+            .map(ConfigValue::Integer)
+            .map_err(|_| format!("unsupported value '{value_text}'")),
+    }
+}
+
+///
+/// Get back the `key = value` entries of the config file content
+///
+fn parse_config_entries(content: &str) -> Result<Vec<ConfigEntry>> {
+    let mut entries = Vec::new();
+    let mut section = String::new();
+
+    for (line_index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let Some((name, _)) = header.split_once(']') else {
+                return Err(Error::parse(
+                    &format!("{CONFIG_FILE_NAME} line {}", line_index + 1),
+                    "unclosed section header",
+                ));
+            };
+            section = name.trim().to_string();
+            continue;
+        }
+
+        let Some((key, value_text)) = line.split_once('=') else {
+            return Err(Error::parse(
+                &format!("{CONFIG_FILE_NAME} line {}", line_index + 1),
+                "expected 'key = value'",
+            ));
+        };
+        let key = key.trim();
+        entries.push(ConfigEntry {
+            line_index,
+            name: if section.is_empty() {
+                key.to_string()
+            } else {
+                format!("{section}.{key}")
+            },
+            section: section.clone(),
+            value_text: value_text.trim().to_string(),
+        });
+    }
+
+    Ok(entries)
+}
+
+///
+/// Get back the new name of the deprecated option in the renamed options
+///
+fn get_renamed_option(
+    deprecated_options: &[(&'static str, &'static str)],
+    name: &str,
+) -> Option<&'static str> {
+    deprecated_options
+        .iter()
+        .find(|(old_name, _)| *old_name == name)
+        .map(|(_, new_name)| *new_name)
+}
+
+///
+/// Load the config file into the module state, the deprecated and the unknown options are
+/// warned (once, as it's only loaded by `setup()`).
+///
+fn load_config() -> Result<()> {
+    let Some(config_file) = get_config_file() else {
+        return Ok(());
+    };
+    if !config_file.exists() {
+        return Ok(());
+    }
+
+    let content = std::fs::read_to_string(&config_file).context("read the config file")?;
+    let mut options = HashMap::new();
+    let mut deprecated_names = Vec::new();
+
+    for entry in parse_config_entries(&content)? {
+        let value = parse_config_value(&entry.value_text).map_err(|message| {
+            Error::parse(
+                &format!("{CONFIG_FILE_NAME} line {}", entry.line_index + 1),
+                &message,
+            )
+        })?;
+
+        let name = match get_renamed_option(&DEPRECATED_OPTIONS, &entry.name) {
+            Some(new_name) => {
+                deprecated_names.push(format!("'{}' -> '{new_name}'", entry.name));
+
+                //
+                // The new name wins if both are set
+                //
+                if options.contains_key(new_name) {
+                    continue;
+                }
+                new_name.to_string()
+            }
+            None if CONFIG_OPTIONS.contains(&entry.name.as_str()) => entry.name,
+            None => {
+                notify(
+                    "config",
+                    &format!("Unknown option '{}' in {CONFIG_FILE_NAME}.", entry.name),
+                    EchoLevel::Warning,
+                );
+                continue;
+            }
+        };
+        options.insert(name, value);
+    }
+
+    if !deprecated_names.is_empty() {
+        notify(
+            "config",
+            &format!(
+                "Deprecated options: {}, run ':MyConfigMigrate' to rename them.",
+                deprecated_names.join(", ")
+            ),
+            EchoLevel::Warning,
+        );
+    }
+
+    CONFIG_STATE.lock().unwrap().options = options;
+    Ok(())
+}
+
+///
+/// Get back the option value of the config file, `None` if it's not set.
+///
+pub fn get_config_value(name: &str) -> Option<ConfigValue> {
+    CONFIG_STATE.lock().unwrap().options.get(name).cloned()
+}
+
+///
+/// Get back the boolean option, or the `default` if it's not set (or not a boolean).
+///
+/// ```rust
+/// let to_trash = get_config_bool("file_delete.to_trash", DELETE_FILE_TO_TRASH);
+/// ```
+///
+pub fn get_config_bool(name: &str, default: bool) -> bool {
+    match get_config_value(name) {
+        Some(ConfigValue::Bool(value)) => value,
+        _ => default,
+    }
+}
+
+///
+/// Rewrite the config file content to the new option names of `deprecated_options` (it's
+/// `DEPRECATED_OPTIONS`, the tests pass the sample ones): the deprecated lines are removed,
+/// and their values go to the section of the new name (the section is added if it's not there
+/// yet). The deprecated option is dropped if the new one is already set. Return the new content
+/// and the renamed option count.
+///
+fn migrate_config_content(
+    content: &str,
+    deprecated_options: &[(&'static str, &'static str)],
+) -> Result<(String, usize)> {
+    let entries = parse_config_entries(content)?;
+    let mut lines = content
+        .lines()
+        .map(|line| Some(line.to_string()))
+        .collect::<Vec<Option<String>>>();
+
+    //
+    // The new option and the value text of the deprecated line
+    //
+    let mut renamed_options = Vec::<(&'static str, String)>::new();
+    for entry in entries.iter() {
+        let Some(new_name) = get_renamed_option(deprecated_options, &entry.name) else {
+            continue;
+        };
+        lines[entry.line_index] = None;
+
+        let is_set = entries.iter().any(|entry| entry.name == new_name)
+            || renamed_options.iter().any(|(name, _)| *name == new_name);
+        if !is_set {
+            renamed_options.push((new_name, entry.value_text.clone()));
+        }
+    }
+
+    let renamed_count = entries
+        .iter()
+        .filter(|entry| get_renamed_option(deprecated_options, &entry.name).is_some())
+        .count();
+    let mut appended_lines = Vec::<String>::new();
+
+    for (new_name, value_text) in renamed_options {
+        let (section, key) = new_name.rsplit_once('.').unwrap_or(("", new_name));
+        let new_line = format!("{key} = {value_text}");
+
+        //
+        // After the last entry (or the header) of the section, or at the end of the file with
+        // the new header
+        //
+        let last_section_line = entries
+            .iter()
+            .filter(|entry| entry.section == section && lines[entry.line_index].is_some())
+            .map(|entry| entry.line_index)
+            .last()
+            .or_else(|| {
+                content.lines().position(|line| {
+                    line.trim()
+                        .strip_prefix('[')
+                        .and_then(|header| header.split_once(']'))
+                        .is_some_and(|(name, _)| name.trim() == section)
+                })
+            });
+        match last_section_line {
+            Some(line_index) => {
+                let line = lines[line_index].get_or_insert_default();
+                line.push('\n');
+                line.push_str(&new_line);
+            }
+            None => {
+                let header = format!("[{section}]");
+                match appended_lines.iter().position(|line| *line == header) {
+                    Some(header_index) => appended_lines.insert(header_index + 1, new_line),
+                    None => {
+                        appended_lines.push(String::new());
+                        appended_lines.push(header);
+                        appended_lines.push(new_line);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut new_content = lines.into_iter().flatten().collect::<Vec<String>>();
+    while new_content
+        .last()
+        .is_some_and(|line| line.trim().is_empty())
+    {
+        new_content.pop();
+    }
+    new_content.extend(appended_lines);
+    let leading_blank_count = new_content
+        .iter()
+        .take_while(|line| line.trim().is_empty())
+        .count();
+    new_content.drain(..leading_blank_count);
+
+    Ok((new_content.join("\n") + "\n", renamed_count))
+}
+
+///
+/// `:MyConfigMigrate`: Rewrite the config file to the new option names and reload it
+///
+fn migrate_config_file() -> Result<()> {
+    let Some(config_file) = get_config_file().filter(|file| file.exists()) else {
+        notify(
+            "config",
+            &format!("No {CONFIG_FILE_NAME} to migrate."),
+            EchoLevel::Info,
+        );
+        return Ok(());
+    };
+
+    let content = std::fs::read_to_string(&config_file).context("read the config file")?;
+    let (new_content, renamed_count) = migrate_config_content(&content, &DEPRECATED_OPTIONS)?;
+    if renamed_count == 0 {
+        notify(
+            "config",
+            &format!("{CONFIG_FILE_NAME} is already up to date."),
+            EchoLevel::Info,
+        );
+        return Ok(());
+    }
+
+    let backup_file = config_file.with_extension("toml.bak");
+    std::fs::write(&backup_file, &content).context("back up the config file")?;
+    std::fs::write(&config_file, new_content).context("write the migrated config file")?;
+
+    load_config()?;
+    notify(
+        "config",
+        &format!(
+            "Renamed {renamed_count} option(s), the original file is kept as '{}'.",
+            backup_file.display()
+        ),
+        EchoLevel::Info,
+    );
+
+    Ok(())
+}
+
+///
+///
+///
+pub fn setup() {
+    report("config", load_config());
+
+    register_user_command(
+        "MyConfigMigrate",
+        UserCommandSpec {
+            desc: "Rename the deprecated options of the config file",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| {
+            report("config", migrate_config_file());
+        },
+    );
+}
+
+use crate::{
+    error::{Error, Result, ResultContext, report},
+    extended_api::{EchoLevel, UserCommandArgs, UserCommandSpec, register_user_command},
+    notify::notify,
+};
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::api::call_function;
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigValue, migrate_config_content, parse_config_value};
+
+    ///
+    /// The sample renames, `DEPRECATED_OPTIONS` has no entry yet
+    ///
+    const SAMPLE_RENAMES: [(&'static str, &'static str); 2] =
+        [("old_flag", "first.flag"), ("old_level", "second.level")];
+
+    #[test]
+    fn parse_config_value_of_the_supported_types() {
+        assert_eq!(parse_config_value("true"), Ok(ConfigValue::Bool(true)));
+        assert_eq!(
+            parse_config_value("false # off"),
+            Ok(ConfigValue::Bool(false))
+        );
+        assert_eq!(parse_config_value("1_000"), Ok(ConfigValue::Integer(1000)));
+        assert_eq!(
+            parse_config_value("\"a # b\" # comment"),
+            Ok(ConfigValue::String("a # b".to_string()))
+        );
+        assert!(parse_config_value("'unclosed").is_err());
+        assert!(parse_config_value("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn migrate_config_content_renames_the_deprecated_options() {
+        let content = "# my config
+old_flag = false
+old_level = 3
+
+[second]
+# the level
+";
+        let (new_content, renamed_count) =
+            migrate_config_content(content, &SAMPLE_RENAMES).unwrap();
+        assert_eq!(renamed_count, 2);
+        assert_eq!(
+            new_content,
+            "# my config
+
+[second]
+level = 3
+# the level
+
+[first]
+flag = false
+"
+        );
+    }
+
+    #[test]
+    fn migrate_config_content_keeps_the_new_option() {
+        let content = "old_flag = false
+
+[first]
+flag = true
+";
+        let (new_content, renamed_count) =
+            migrate_config_content(content, &SAMPLE_RENAMES).unwrap();
+        assert_eq!(renamed_count, 1);
+        assert_eq!(new_content, "[first]\nflag = true\n");
+    }
+
+    #[test]
+    fn migrate_config_content_without_the_renames() {
+        let content = "[first]\nflag = true\n";
+        let (new_content, renamed_count) = migrate_config_content(content, &[]).unwrap();
+        assert_eq!(renamed_count, 0);
+        assert_eq!(new_content, content);
+    }
+}
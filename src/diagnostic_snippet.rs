@@ -0,0 +1,259 @@
+//! Copy the diagnostics of the current buffer as a markdown snippet, for pasting into the issue
+//! trackers or chat:
+//!
+//! - `<leader>yd`: Copy all the diagnostics of the current buffer (in `VISUAL` mode, only the
+//!   ones in the selected lines) to the clipboard (`+` register, or the unnamed register if
+//!   there is no clipboard), each one is a list item with the code context:
+//!
+//! ````markdown
+//! ### Diagnostics: `src/main.rs` (1)
+//!
+//! - **ERROR** `src/main.rs:10:18` (rustc E0308): mismatched types
+//!   ```rust
+//!      9 | fn main() {
+//!     10 |     let x: i32 = "a";
+//!     11 | }
+//!   ```
+//! ````
+//!
+
+///
+/// The context lines before and after the diagnostic line
+///
+const DIAGNOSTIC_SNIPPET_CONTEXT_LINES: usize = 1;
+
+///
+/// The diagnostic of `vim.diagnostic.get()`, the lines and columns are 0-based.
+///
+#[derive(Debug, Default)]
+struct BufferDiagnostic {
+    lnum: usize,
+    col: usize,
+    severity: i64,
+    message: String,
+    source: String,
+    code: String,
+}
+
+///
+/// Get back the diagnostics of the current buffer, sorted by position
+///
+fn get_buffer_diagnostics() -> Vec<BufferDiagnostic> {
+    let lua_code = r#"vim.tbl_map(function(d)
+    return {
+        lnum = d.lnum,
+        col = d.col,
+        severity = d.severity,
+        message = d.message,
+        source = d.source or "",
+        code = d.code ~= nil and tostring(d.code) or "",
+    }
+end, vim.diagnostic.get(0))"#;
+
+    let Ok(diagnostics) = call_function::<_, Vec<Dictionary>>("luaeval", (lua_code,)) else {
+        return Vec::new();
+    };
+
+    let mut buffer_diagnostics = diagnostics
+        .into_iter()
+        .map(|diagnostic| {
+            let mut buffer_diagnostic = BufferDiagnostic::default();
+            for (key, value) in diagnostic {
+                match key.to_string().as_str() {
+                    "lnum" => {
+                        buffer_diagnostic.lnum = i64::from_object(value).unwrap_or(0) as usize
+                    }
+                    "col" => buffer_diagnostic.col = i64::from_object(value).unwrap_or(0) as usize,
+                    "severity" => buffer_diagnostic.severity = i64::from_object(value).unwrap_or(0),
+                    "message" => {
+                        buffer_diagnostic.message = NvimString::from_object(value)
+                            .unwrap_or_default()
+                            .to_string()
+                    }
+                    "source" => {
+                        buffer_diagnostic.source = NvimString::from_object(value)
+                            .unwrap_or_default()
+                            .to_string()
+                    }
+                    "code" => {
+                        buffer_diagnostic.code = NvimString::from_object(value)
+                            .unwrap_or_default()
+                            .to_string()
+                    }
+                    _ => {}
+                }
+            }
+            buffer_diagnostic
+        })
+        .collect::<Vec<BufferDiagnostic>>();
+
+    buffer_diagnostics.sort_by_key(|diagnostic| (diagnostic.lnum, diagnostic.col));
+    buffer_diagnostics
+}
+
+///
+/// `vim.diagnostic.severity` name
+///
+fn severity_name(severity: i64) -> &'static str {
+    match severity {
+        1 => "ERROR",
+        2 => "WARN",
+        3 => "INFO",
+        _ => "HINT",
+    }
+}
+
+///
+/// Render the diagnostics into the markdown snippet
+///
+fn render_diagnostics_markdown(
+    diagnostics: &[BufferDiagnostic],
+    lines: &[String],
+    file: &str,
+    filetype: &str,
+) -> String {
+    let mut markdown = vec![
+        format!("### Diagnostics: `{file}` ({})", diagnostics.len()),
+        String::new(),
+    ];
+
+    for diagnostic in diagnostics {
+        let origin = match (diagnostic.source.is_empty(), diagnostic.code.is_empty()) {
+            (true, true) => String::new(),
+            (false, true) => format!(" ({})", diagnostic.source),
+            (true, false) => format!(" ({})", diagnostic.code),
+            (false, false) => format!(" ({} {})", diagnostic.source, diagnostic.code),
+        };
+
+        //
+        // The multi-line message is indented under the list item
+        //
+        let message = diagnostic.message.trim().replace('\n', "\n  ");
+        markdown.push(format!(
+            "- **{}** `{file}:{}:{}`{origin}: {message}",
+            severity_name(diagnostic.severity),
+            diagnostic.lnum + 1,
+            diagnostic.col + 1,
+        ));
+
+        let first_line = diagnostic
+            .lnum
+            .saturating_sub(DIAGNOSTIC_SNIPPET_CONTEXT_LINES);
+        let last_line =
+            (diagnostic.lnum + DIAGNOSTIC_SNIPPET_CONTEXT_LINES).min(lines.len().saturating_sub(1));
+        if first_line > last_line {
+            continue;
+        }
+
+        let number_width = (last_line + 1).to_string().len();
+        markdown.push(format!("  ```{filetype}"));
+        for (index, line) in lines[first_line..=last_line].iter().enumerate() {
+            markdown.push(format!(
+                "  {:>number_width$} | {line}",
+                first_line + index + 1
+            ));
+        }
+        markdown.push("  ```".to_string());
+    }
+
+    markdown.join("\n")
+}
+
+///
+/// Copy the diagnostics in the lines (1-based, inclusive) to the clipboard, all lines if `None`.
+///
+fn copy_diagnostics(line_range: Option<(usize, usize)>) {
+    let diagnostics = get_buffer_diagnostics()
+        .into_iter()
+        .filter(|diagnostic| {
+            line_range.is_none_or(|(line1, line2)| {
+                diagnostic.lnum + 1 >= line1 && diagnostic.lnum < line2
+            })
+        })
+        .collect::<Vec<BufferDiagnostic>>();
+
+    if diagnostics.is_empty() {
+        echo_message("diagnostic_snippet", "No diagnostics.", EchoLevel::Info);
+        return;
+    }
+
+    let buffer = Buffer::current();
+    let lines = buffer
+        .get_lines(.., false)
+        .map(|lines| lines.map(|line| line.to_string()).collect::<Vec<String>>())
+        .unwrap_or_default();
+    let file = call_function::<_, String>("expand", ("%:.",)).unwrap_or_default();
+    let filetype =
+        get_option_value::<NvimString>("filetype", &OptionOpts::builder().buffer(buffer).build())
+            .map(|value| value.to_string())
+            .unwrap_or_default();
+
+    let markdown = render_diagnostics_markdown(&diagnostics, &lines, &file, &filetype);
+
+    let register = get_clipboard_register();
+    if set_register(register, &RegisterContent::charwise(&markdown)) {
+        echo_message(
+            "diagnostic_snippet",
+            &format!(
+                "Copied {} diagnostic(s) to the '{register}' register.",
+                diagnostics.len()
+            ),
+            EchoLevel::Success,
+        );
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>yd",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("Copy the diagnostics of the current buffer as markdown")
+            .silent(true)
+            .callback(|_| {
+                copy_diagnostics(None);
+                ()
+            })
+            .build(),
+    );
+
+    let _ = set_keymap(
+        Mode::Visual,
+        "<leader>yd",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("Copy the diagnostics of the selected lines as markdown")
+            .silent(true)
+            .callback(|_| {
+                //
+                // `line("v")` is the other end of the selection
+                //
+                let start = call_function::<_, i64>("line", ("v",)).unwrap_or(1) as usize;
+                let end = call_function::<_, i64>("line", (".",)).unwrap_or(1) as usize;
+                let _ = command("normal! \x1b");
+                copy_diagnostics(Some((start.min(end), start.max(end))));
+                ()
+            })
+            .build(),
+    );
+}
+
+use crate::{
+    extended_api::{EchoLevel, RegisterContent, echo_message, set_register},
+    utils::get_clipboard_register,
+};
+
+use nvim_oxi::{
+    Dictionary, String as NvimString,
+    api::{
+        Buffer, call_function, command, get_option_value,
+        opts::{OptionOpts, SetKeymapOpts},
+        set_keymap,
+        types::Mode,
+    },
+    conversion::FromObject,
+};
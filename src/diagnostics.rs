@@ -0,0 +1,344 @@
+//! The diagnostics UI, `vim.diagnostic` is configured here (signs, virtual text, severity
+//! sorting and the float window):
+//!
+//! - `]d`/`[d`: Jump to the next/prev diagnostic and show it in the float window.
+//! - `<leader>e`: Show the diagnostics of the current line in the float window.
+//! - The diagnostic under the cursor shows in the float window after `updatetime` (`CursorHold`),
+//!   see `DIAGNOSTIC_FLOAT_ON_CURSOR_HOLD` (or `diagnostics.float_on_cursor_hold` of the config
+//!   file).
+//! - `<leader>pd` or `:Diagnostics`: Pick the diagnostic of all buffers (the errors first) and
+//!   jump to it.
+//!
+
+///
+/// The sign text of each severity: ERROR, WARN, INFO, HINT
+///
+const DIAGNOSTIC_SIGNS: [&'static str; 4] = ["E", "W", "I", "H"];
+
+///
+/// The prefix of the virtual text
+///
+const DIAGNOSTIC_VIRTUAL_TEXT_PREFIX: &'static str = "●";
+
+///
+/// Set to `false` to only show the float window by `<leader>e` or jumping, it's the default of
+/// `diagnostics.float_on_cursor_hold` of the config file.
+///
+const DIAGNOSTIC_FLOAT_ON_CURSOR_HOLD: bool = true;
+
+///
+/// Configure the `vim.diagnostic` display
+///
+fn configure_diagnostics() {
+    //
+    // The Lua list index is the same with `vim.diagnostic.severity` (ERROR is 1)
+    //
+    let lua_code = r#"local signs, prefix = ...
+vim.diagnostic.config({
+    signs = { text = signs },
+    virtual_text = { prefix = prefix, spacing = 2, source = "if_many" },
+    underline = true,
+    severity_sort = true,
+    update_in_insert = false,
+    float = { border = "rounded", source = "if_many", header = "" },
+})"#;
+
+    let result = try_exec_lua::<_, Object>(
+        lua_code,
+        (
+            Array::from_iter(DIAGNOSTIC_SIGNS),
+            DIAGNOSTIC_VIRTUAL_TEXT_PREFIX,
+        ),
+    )
+    .context("configure 'vim.diagnostic'");
+    report("diagnostics", result);
+}
+
+///
+/// Jump to the next (`count` is `1`) or prev (`count` is `-1`) diagnostic
+///
+fn jump_to_diagnostic(count: i64) {
+    let result = try_exec_lua::<_, Object>(
+        "vim.diagnostic.jump({ count = ..., float = true })",
+        (count,),
+    )
+    .context("jump to the diagnostic");
+    report("diagnostics", result);
+}
+
+///
+/// Show the diagnostics in the float window, `scope` is `line` or `cursor`.
+///
+fn open_diagnostic_float(scope: &str, focusable: bool) {
+    let result = try_exec_lua::<_, Object>(
+        r#"local scope, focusable = ...
+vim.diagnostic.open_float({ scope = scope, focusable = focusable })"#,
+        (scope, focusable),
+    )
+    .context("open the diagnostic float window");
+    report("diagnostics", result);
+}
+
+///
+/// The diagnostic of all buffers for the picker, the lines and columns are 0-based.
+///
+#[derive(Debug, Default)]
+struct WorkspaceDiagnostic {
+    buffer_handle: i64,
+    lnum: i64,
+    col: i64,
+    severity: i64,
+    message: String,
+    source: String,
+}
+
+///
+/// Get back the diagnostics of all buffers, sorted by severity (the errors first), file and
+/// position.
+///
+fn get_workspace_diagnostics() -> Vec<WorkspaceDiagnostic> {
+    let lua_code = r#"return vim.tbl_map(function(d)
+    return {
+        buffer_handle = d.bufnr,
+        lnum = d.lnum,
+        col = d.col,
+        severity = d.severity,
+        message = d.message,
+        source = d.source or "",
+    }
+end, vim.diagnostic.get())"#;
+
+    let result = try_exec_lua::<_, Vec<Dictionary>>(lua_code, Array::new())
+        .context("get the workspace diagnostics");
+    let Some(diagnostics) = report("diagnostics", result) else {
+        return Vec::new();
+    };
+
+    let get_integer = |dict: &Dictionary, key: &str| {
+        dict.get(key)
+            .and_then(|value| i64::from_object(value.clone()).ok())
+            .unwrap_or(0)
+    };
+    let get_string = |dict: &Dictionary, key: &str| {
+        dict.get(key)
+            .and_then(|value| NvimString::from_object(value.clone()).ok())
+            .map(|value| value.to_string())
+            .unwrap_or_default()
+    };
+
+    let mut workspace_diagnostics = diagnostics
+        .iter()
+        .map(|diagnostic| WorkspaceDiagnostic {
+            buffer_handle: get_integer(diagnostic, "buffer_handle"),
+            lnum: get_integer(diagnostic, "lnum"),
+            col: get_integer(diagnostic, "col"),
+            severity: get_integer(diagnostic, "severity"),
+            message: get_string(diagnostic, "message"),
+            source: get_string(diagnostic, "source"),
+        })
+        .collect::<Vec<WorkspaceDiagnostic>>();
+
+    workspace_diagnostics.sort_by_key(|diagnostic| {
+        (
+            diagnostic.severity,
+            diagnostic.buffer_handle,
+            diagnostic.lnum,
+            diagnostic.col,
+        )
+    });
+    workspace_diagnostics
+}
+
+///
+/// The severity label and its highlight group
+///
+fn severity_label(severity: i64) -> (&'static str, &'static str) {
+    match severity {
+        1 => ("ERROR", "DiagnosticError"),
+        2 => ("WARN", "DiagnosticWarn"),
+        3 => ("INFO", "DiagnosticInfo"),
+        _ => ("HINT", "DiagnosticHint"),
+    }
+}
+
+///
+/// The picker items of the workspace diagnostics, the item data is `{buffer}:{lnum}:{col}`.
+///
+fn get_diagnostic_picker_items() -> Vec<PickerItem> {
+    get_workspace_diagnostics()
+        .into_iter()
+        .map(|diagnostic| {
+            let name = call_function::<_, String>("bufname", (diagnostic.buffer_handle,))
+                .unwrap_or_default();
+            let display_name =
+                call_function::<_, String>("fnamemodify", (name, ":~:.")).unwrap_or_default();
+            let (label, hl_group) = severity_label(diagnostic.severity);
+
+            //
+            // Only the first line of the multi-line message
+            //
+            let message = diagnostic.message.lines().next().unwrap_or_default();
+            let message = if diagnostic.source.is_empty() {
+                message.to_string()
+            } else {
+                format!("{message} ({})", diagnostic.source)
+            };
+
+            PickerItem {
+                columns: vec![
+                    PickerItemColumn::new(label, Some(hl_group)),
+                    PickerItemColumn::new(
+                        &format!(
+                            "{display_name}:{}:{}",
+                            diagnostic.lnum + 1,
+                            diagnostic.col + 1
+                        ),
+                        Some("Directory"),
+                    ),
+                    PickerItemColumn::new(&message, None),
+                ],
+                icon: None,
+                data: format!(
+                    "{}:{}:{}",
+                    diagnostic.buffer_handle, diagnostic.lnum, diagnostic.col
+                ),
+            }
+        })
+        .collect()
+}
+
+///
+/// Jump to the selected diagnostic in the next event loop tick, as the picker windows are still
+/// closing at this moment.
+///
+fn diagnostic_selected_callback(selected: String) {
+    let mut parts = selected
+        .trim()
+        .splitn(3, ':')
+        .map(|part| part.parse::<i64>());
+    let (Some(Ok(buffer_handle)), Some(Ok(lnum)), Some(Ok(col))) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        return;
+    };
+
+    nvim::schedule(move |_| {
+        if command(&format!("buffer {buffer_handle}")).is_ok() {
+            let _ = Window::current().set_cursor(lnum as usize + 1, col as usize);
+            let _ = command("normal! zz");
+        }
+        ()
+    });
+}
+
+///
+///
+///
+pub fn setup() {
+    configure_diagnostics();
+
+    let diagnostic_keybindings: Vec<(&str, &str, Box<dyn Fn()>)> = vec![
+        (
+            "]d",
+            "']d': Jump to the next diagnostic",
+            Box::new(|| jump_to_diagnostic(1)),
+        ),
+        (
+            "[d",
+            "'[d': Jump to the prev diagnostic",
+            Box::new(|| jump_to_diagnostic(-1)),
+        ),
+        (
+            "<leader>e",
+            "'<leader>e': Show the line diagnostics in the float window",
+            Box::new(|| open_diagnostic_float("line", true)),
+        ),
+        (
+            "<leader>pd",
+            "'<leader>pd': Pick the diagnostic of all buffers",
+            Box::new(|| open_registered_picker("diagnostics")),
+        ),
+    ];
+
+    for (key, desc, callback) in diagnostic_keybindings {
+        let _ = set_keymap(
+            Mode::Normal,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(desc)
+                .silent(true)
+                .callback(move |_| {
+                    callback();
+                    ()
+                })
+                .build(),
+        );
+    }
+
+    if get_config_bool(
+        "diagnostics.float_on_cursor_hold",
+        DIAGNOSTIC_FLOAT_ON_CURSOR_HOLD,
+    ) {
+        let _ = create_autocmd(
+            vec!["CursorHold"],
+            &CreateAutocmdOpts::builder()
+                .group(
+                    create_augroup(
+                        "custom-diagnostics-group",
+                        &CreateAugroupOpts::builder().clear(true).build(),
+                    )
+                    .unwrap(),
+                )
+                .callback(|_| {
+                    //
+                    // Don't steal the focus, the float window closes on the cursor moving
+                    //
+                    open_diagnostic_float("cursor", false);
+
+                    //
+                    // Return `true` to delete the autocommand (means only run once)!!!
+                    //
+                    false
+                })
+                .build(),
+        );
+    }
+
+    register_user_command(
+        "Diagnostics",
+        UserCommandSpec {
+            desc: "Pick the diagnostic of all buffers",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| open_registered_picker("diagnostics"),
+    );
+
+    //
+    // Contribute the workspace diagnostics to the picker registry (`<leader>pp`)
+    //
+    register_picker_source(
+        "diagnostics",
+        "Workspace diagnostics",
+        get_diagnostic_picker_items,
+        diagnostic_selected_callback,
+    );
+}
+
+use crate::{
+    config::get_config_bool,
+    error::{ResultContext, report},
+    extended_api::{UserCommandArgs, UserCommandSpec, register_user_command, try_exec_lua},
+    picker::{PickerItem, PickerItemColumn, open_registered_picker, register_picker_source},
+};
+
+use nvim_oxi::{
+    self as nvim, Array, Dictionary, Object, String as NvimString,
+    api::{
+        Window, call_function, command, create_augroup, create_autocmd,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, SetKeymapOpts},
+        set_keymap,
+        types::Mode,
+    },
+    conversion::FromObject,
+};
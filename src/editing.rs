@@ -0,0 +1,659 @@
+//! The editing helpers:
+//!
+//! - Autopairs in `INSERT` mode (only in the normal file buffer):
+//!
+//!   `(`/`[`/`{` and the quotes insert the pair, unless the next char is the word char (or the
+//!   char before the quote is the word char).
+//!   Typing the closing char (or the quote) right before the same one only moves over it.
+//!   `<BS>` between the empty pair deletes both.
+//!
+//! - Surround in `NORMAL`/`VISUAL` mode (like `vim-surround`):
+//!
+//!   `ys{motion}{char}`: Surround the motion text, e.g. `ysiw)` -> `(word)`
+//!   `yss{char}`: Surround the current line (without the indent)
+//!   `S{char}` in `VISUAL` mode: Surround the selection
+//!   `ds{char}`: Delete the surrounding pair, e.g. `ds"`
+//!   `cs{old}{new}`: Change the surrounding pair, e.g. `cs"'`
+//!
+//!   The opening bracket (`(`, `[`, `{`) adds the inner space (or trims it in `ds`/`cs`), the
+//!   closing one (or the alias `b`, `r`, `B`, `a`) doesn't. `t` asks for the tag (only in `ys`
+//!   and `S`), any other punctuation is the same char at both sides.
+//!
+
+///
+/// The autopairs: (open, close)
+///
+const AUTOPAIRS: [(char, char); 6] = [
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('"', '"'),
+    ('\'', '\''),
+    ('`', '`'),
+];
+
+///
+/// The quote which is NOT paired in the filetype, e.g. the Rust lifetime and the Vim comment.
+///
+const AUTOPAIRS_UNPAIRED_QUOTES: [(&'static str, char); 4] = [
+    ("rust", '\''),
+    ("vim", '"'),
+    ("lisp", '\''),
+    ("scheme", '\''),
+];
+
+///
+/// The surround pairs: (keys, open, close), the opening bracket key has the inner space.
+///
+const SURROUND_PAIRS: [(&'static str, &'static str, &'static str); 7] = [
+    ("(", "( ", " )"),
+    (")b", "(", ")"),
+    ("[", "[ ", " ]"),
+    ("]r", "[", "]"),
+    ("{", "{ ", " }"),
+    ("}B", "{", "}"),
+    ("<>a", "<", ">"),
+];
+
+///
+/// How many lines around the cursor to search for the surrounding brackets
+///
+const SURROUND_SEARCH_LINES: usize = 200;
+
+///
+/// The Lua global function name of the `ys` operator (`operatorfunc`)
+///
+const SURROUND_OPERATOR_FUNCTION: &'static str = "MyConfigSurroundOperator";
+
+///
+/// The buffer position: `(row, col)`, 0-based and the col is the byte index.
+///
+type TextPosition = (usize, usize);
+
+///
+/// The surrounding pair found around the cursor
+///
+#[derive(Debug, Clone, Copy)]
+struct SurroundingPair {
+    open_position: TextPosition,
+    close_position: TextPosition,
+    open_char: char,
+    close_char: char,
+    is_trim_space: bool,
+}
+
+///
+/// Get back the line of the buffer
+///
+fn get_line(buffer: &Buffer, row: usize) -> Option<String> {
+    buffer
+        .get_lines(row..row + 1, true)
+        .ok()
+        .and_then(|mut lines| lines.next())
+        .map(|line| line.to_string())
+}
+
+///
+/// Replace the text between the positions (the end is exclusive)
+///
+fn replace_text(buffer: &Buffer, start: TextPosition, end: TextPosition, text: &str) {
+    let _ = exec_lua::<_, Object>(
+        r#"local bufnr, start_row, start_col, end_row, end_col, lines = ...
+vim.api.nvim_buf_set_text(bufnr, start_row, start_col, end_row, end_col, lines)"#,
+        (
+            buffer.handle() as i64,
+            start.0 as i64,
+            start.1 as i64,
+            end.0 as i64,
+            end.1 as i64,
+            Array::from_iter(text.split('\n')),
+        ),
+    );
+}
+
+///
+/// Get back the chars before and after the cursor in `INSERT` mode, `None` if the autopairs
+/// doesn't work in the buffer (e.g. the picker input or the terminal).
+///
+fn get_chars_around_cursor() -> Option<(Option<char>, Option<char>)> {
+    let buffer = Buffer::current();
+    let buftype = get_option_value::<NvimString>(
+        "buftype",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    )
+    .map(|buftype| buftype.to_string())
+    .unwrap_or_default();
+    if !buftype.is_empty() {
+        return None;
+    }
+
+    let (row, col) = Window::current().get_cursor().ok()?;
+    let line = get_line(&buffer, row - 1)?;
+    let col = col.min(line.len());
+
+    Some((
+        line.get(..col)
+            .and_then(|before| before.chars().next_back()),
+        line.get(col..).and_then(|after| after.chars().next()),
+    ))
+}
+
+///
+/// The word char stops the pairing, e.g. typing `(` before `foo`
+///
+fn is_word_char(c: Option<char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+///
+/// The keys to insert the pair and put the cursor between them, `<C-g>U` keeps the undo and `.`
+/// working.
+///
+fn insert_pair(open: char, close: char) -> String {
+    format!("{open}{close}<C-g>U<Left>")
+}
+
+///
+/// The opening bracket is typed
+///
+fn on_open_bracket(open: char, close: char) -> String {
+    match get_chars_around_cursor() {
+        Some((_, next)) if !is_word_char(next) => insert_pair(open, close),
+        _ => open.to_string(),
+    }
+}
+
+///
+/// The closing bracket is typed, move over the existing one.
+///
+fn on_close_bracket(close: char) -> String {
+    match get_chars_around_cursor() {
+        Some((_, Some(next))) if next == close => String::from("<C-g>U<Right>"),
+        _ => close.to_string(),
+    }
+}
+
+///
+/// The quote is typed, move over the existing one or insert the pair.
+///
+fn on_quote(quote: char) -> String {
+    let Some((prev, next)) = get_chars_around_cursor() else {
+        return quote.to_string();
+    };
+
+    if next == Some(quote) {
+        return String::from("<C-g>U<Right>");
+    }
+
+    let filetype = get_option_value::<NvimString>(
+        "filetype",
+        &OptionOpts::builder().buffer(Buffer::current()).build(),
+    )
+    .map(|filetype| filetype.to_string())
+    .unwrap_or_default();
+    let is_unpaired_quote =
+        AUTOPAIRS_UNPAIRED_QUOTES
+            .iter()
+            .any(|(unpaired_filetype, unpaired_quote)| {
+                *unpaired_filetype == filetype && *unpaired_quote == quote
+            });
+
+    if is_unpaired_quote || is_word_char(prev) || is_word_char(next) {
+        quote.to_string()
+    } else {
+        insert_pair(quote, quote)
+    }
+}
+
+///
+/// `<BS>`: Delete the empty pair
+///
+fn on_backspace() -> String {
+    let is_in_empty_pair = get_chars_around_cursor().is_some_and(|(prev, next)| {
+        AUTOPAIRS
+            .iter()
+            .any(|(open, close)| prev == Some(*open) && next == Some(*close))
+    });
+
+    if is_in_empty_pair {
+        String::from("<BS><Del>")
+    } else {
+        String::from("<BS>")
+    }
+}
+
+///
+/// Set the autopairs keymaps in `INSERT` mode, they're the `expr` keymaps which return the keys
+/// to insert, so the pair is inserted in the typed order (e.g. in the macro or `:normal`).
+///
+fn setup_autopairs() {
+    let mut autopairs_keybindings: Vec<(String, String, Box<dyn Fn() -> String>)> = Vec::new();
+    for (open, close) in AUTOPAIRS {
+        if open == close {
+            autopairs_keybindings.push((
+                open.to_string(),
+                format!("'{open}': Insert the quote pair or move over it"),
+                Box::new(move || on_quote(open)),
+            ));
+        } else {
+            autopairs_keybindings.push((
+                open.to_string(),
+                format!("'{open}': Insert the bracket pair"),
+                Box::new(move || on_open_bracket(open, close)),
+            ));
+            autopairs_keybindings.push((
+                close.to_string(),
+                format!("'{close}': Move over the closing bracket"),
+                Box::new(move || on_close_bracket(close)),
+            ));
+        }
+    }
+    autopairs_keybindings.push((
+        String::from("<BS>"),
+        String::from("'<BS>': Delete the empty pair"),
+        Box::new(on_backspace),
+    ));
+
+    for (key, desc, callback) in autopairs_keybindings {
+        set_expr_keymap(Mode::Insert, &key, &desc, callback);
+    }
+}
+
+///
+/// Read the surround key, `None` if it's cancelled by `<Esc>` or `<C-c>`.
+///
+fn read_surround_key() -> Option<char> {
+    let key = call_function::<_, String>("getcharstr", ()).ok()?;
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_control() => Some(c),
+        _ => None,
+    }
+}
+
+///
+/// Get back the (open, close) text of the surround key, `t` asks for the tag.
+///
+fn get_surround_pair(key: char) -> Option<(String, String)> {
+    if let Some((_, open, close)) = SURROUND_PAIRS
+        .iter()
+        .find(|(keys, _, _)| keys.contains(key))
+    {
+        return Some((open.to_string(), close.to_string()));
+    }
+
+    if key == 't' {
+        let tag = prompt("Tag: <", "", None)?;
+        let tag = tag.trim().trim_start_matches('<').trim_end_matches('>');
+        let tag_name = tag.split_whitespace().next()?;
+        return Some((format!("<{tag}>"), format!("</{tag_name}>")));
+    }
+
+    if key.is_alphanumeric() {
+        return None;
+    }
+
+    Some((key.to_string(), key.to_string()))
+}
+
+///
+/// `ys{motion}` (the `operatorfunc`) and `S` in `VISUAL` mode: Surround the text between the
+/// marks, the `motion_type` is `char`/`line`/`block` of the operator, or the `visualmode()`.
+///
+fn surround_add(motion_type: &str) {
+    let (start_mark, end_mark) = match motion_type {
+        "char" | "line" | "block" => ("'[", "']"),
+        _ => ("'<", "'>"),
+    };
+    let is_linewise = motion_type == "line" || motion_type == "V";
+
+    let get_position = |mark: &str| {
+        let position = call_function::<_, Vec<i64>>("getpos", (mark,)).ok()?;
+        Some((
+            (*position.get(1)? - 1).max(0) as usize,
+            (*position.get(2)? - 1).max(0) as usize,
+        ))
+    };
+    let (Some(start), Some(end)) = (get_position(start_mark), get_position(end_mark)) else {
+        return;
+    };
+
+    let Some(key) = read_surround_key() else {
+        return;
+    };
+    let Some((open, close)) = get_surround_pair(key) else {
+        return;
+    };
+
+    let buffer = Buffer::current();
+    let (Some(start_line), Some(end_line)) = (get_line(&buffer, start.0), get_line(&buffer, end.0))
+    else {
+        return;
+    };
+
+    //
+    // The linewise text is without the indent and the trailing spaces, the charwise end is the
+    // last char (including the multi-byte one).
+    //
+    let (start_col, end_col) = if is_linewise {
+        (
+            start_line.len() - start_line.trim_start().len(),
+            end_line.trim_end().len(),
+        )
+    } else {
+        let end_col = end_line
+            .get(end.1.min(end_line.len())..)
+            .and_then(|rest| rest.chars().next())
+            .map_or(end_line.len(), |c| end.1 + c.len_utf8());
+        (start.1.min(start_line.len()), end_col)
+    };
+
+    //
+    // Insert the close first, so the start position doesn't move.
+    //
+    replace_text(&buffer, (end.0, end_col), (end.0, end_col), &close);
+    replace_text(&buffer, (start.0, start_col), (start.0, start_col), &open);
+    let _ = Window::current().set_cursor(start.0 + 1, start_col);
+}
+
+///
+/// Find the surrounding bracket pair of the cursor (the nested pairs are skipped), return the
+/// positions of the open and the close chars.
+///
+fn find_surrounding_brackets(
+    buffer: &Buffer,
+    cursor: TextPosition,
+    open: char,
+    close: char,
+) -> Option<(TextPosition, TextPosition)> {
+    let first_row = cursor.0.saturating_sub(SURROUND_SEARCH_LINES);
+    let chars = buffer
+        .get_lines(first_row..cursor.0 + SURROUND_SEARCH_LINES + 1, false)
+        .ok()?
+        .enumerate()
+        .flat_map(|(index, line)| {
+            line.to_string()
+                .char_indices()
+                .map(|(col, c)| (first_row + index, col, c))
+                .collect::<Vec<(usize, usize, char)>>()
+        })
+        .collect::<Vec<(usize, usize, char)>>();
+
+    //
+    // The cursor on the close char belongs to it, the cursor on the open char is the open one.
+    //
+    let cursor_index = chars.partition_point(|(row, col, _)| (*row, *col) < cursor);
+    let is_on_close = chars.get(cursor_index).is_some_and(|(_, _, c)| *c == close);
+    let backward_end = if is_on_close {
+        cursor_index
+    } else {
+        (cursor_index + 1).min(chars.len())
+    };
+
+    let mut depth = 0usize;
+    let open_index = chars[..backward_end].iter().rposition(|(_, _, c)| {
+        if *c == close {
+            depth += 1;
+        } else if *c == open {
+            if depth == 0 {
+                return true;
+            }
+            depth -= 1;
+        }
+        false
+    })?;
+
+    let mut depth = 0usize;
+    let close_index = chars[open_index + 1..].iter().position(|(_, _, c)| {
+        if *c == open {
+            depth += 1;
+        } else if *c == close {
+            if depth == 0 {
+                return true;
+            }
+            depth -= 1;
+        }
+        false
+    })? + open_index
+        + 1;
+
+    let (open_row, open_col, _) = chars[open_index];
+    let (close_row, close_col, _) = chars[close_index];
+    Some(((open_row, open_col), (close_row, close_col)))
+}
+
+///
+/// Find the surrounding quote pair in the cursor line (the escaped quote is skipped), or the
+/// first pair after the cursor like `ci"`.
+///
+fn find_surrounding_quotes(
+    buffer: &Buffer,
+    cursor: TextPosition,
+    quote: char,
+) -> Option<(TextPosition, TextPosition)> {
+    let line = get_line(buffer, cursor.0)?;
+
+    let mut quote_cols = Vec::new();
+    let mut is_escaped = false;
+    for (col, c) in line.char_indices() {
+        if c == quote && !is_escaped {
+            quote_cols.push(col);
+        }
+        is_escaped = c == '\\' && !is_escaped;
+    }
+
+    let (open_col, close_col) = quote_cols
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|(open_col, close_col)| {
+            (*open_col <= cursor.1 && cursor.1 <= *close_col) || *open_col > cursor.1
+        })?;
+
+    Some(((cursor.0, open_col), (cursor.0, close_col)))
+}
+
+///
+/// Find the surrounding pair of the key around the cursor
+///
+fn find_surrounding_pair(key: char) -> Option<SurroundingPair> {
+    if key == 't' {
+        notify(
+            "editing",
+            "The tag isn't supported by 'ds'/'cs'.",
+            EchoLevel::Info,
+        );
+        return None;
+    }
+
+    let (open, close) = get_surround_pair(key)?;
+    let open_char = open.chars().next()?;
+    let close_char = close.chars().next_back()?;
+    let is_trim_space = open.len() > open_char.len_utf8();
+
+    let buffer = Buffer::current();
+    let (row, col) = Window::current().get_cursor().ok()?;
+    let cursor = (row - 1, col);
+
+    let (open_position, close_position) = if open_char == close_char {
+        find_surrounding_quotes(&buffer, cursor, open_char)?
+    } else {
+        find_surrounding_brackets(&buffer, cursor, open_char, close_char)?
+    };
+
+    Some(SurroundingPair {
+        open_position,
+        close_position,
+        open_char,
+        close_char,
+        is_trim_space,
+    })
+}
+
+///
+/// Replace the surrounding pair with the new open and close text (empty to delete)
+///
+fn replace_surrounding_pair(old_key: char, new_open: &str, new_close: &str) {
+    let Some(SurroundingPair {
+        open_position,
+        close_position,
+        open_char,
+        close_char,
+        is_trim_space,
+    }) = find_surrounding_pair(old_key)
+    else {
+        notify(
+            "editing",
+            &format!("No surrounding '{old_key}' found."),
+            EchoLevel::Info,
+        );
+        return;
+    };
+
+    let buffer = Buffer::current();
+    let mut open_end = (open_position.0, open_position.1 + open_char.len_utf8());
+    let mut close_start = close_position;
+    let close_end = (close_position.0, close_position.1 + close_char.len_utf8());
+
+    //
+    // The inner space of `( text )`, only when it's not the same space.
+    //
+    if is_trim_space {
+        let is_space_at = |position: TextPosition| {
+            get_line(&buffer, position.0)
+                .and_then(|line| line.get(position.1..).and_then(|rest| rest.chars().next()))
+                .is_some_and(|c| c == ' ')
+        };
+
+        if close_start.1 > 0 && is_space_at((close_start.0, close_start.1 - 1)) {
+            close_start.1 -= 1;
+        }
+        if is_space_at(open_end) && open_end < close_start {
+            open_end.1 += 1;
+        }
+    }
+
+    replace_text(&buffer, close_start, close_end, new_close);
+    replace_text(&buffer, open_position, open_end, new_open);
+    let _ = Window::current().set_cursor(open_position.0 + 1, open_position.1);
+}
+
+///
+/// `ds{char}`: Delete the surrounding pair
+///
+fn delete_surround() {
+    if let Some(key) = read_surround_key() {
+        replace_surrounding_pair(key, "", "");
+    }
+}
+
+///
+/// `cs{old}{new}`: Change the surrounding pair
+///
+fn change_surround() {
+    let Some(old_key) = read_surround_key() else {
+        return;
+    };
+    let Some((new_open, new_close)) = read_surround_key().and_then(get_surround_pair) else {
+        return;
+    };
+
+    replace_surrounding_pair(old_key, &new_open, &new_close);
+}
+
+///
+/// Set the surround keymaps, `ys` is the operator so it works with any motion.
+///
+fn setup_surround() {
+    //
+    // Expose the operator as the Lua global function, so `operatorfunc` can call it by `v:lua`.
+    //
+    let _ = exec_lua::<_, Object>(
+        "local name, f = ...; _G[name] = f",
+        (
+            SURROUND_OPERATOR_FUNCTION,
+            Function::<(String,), ()>::from_fn(|(motion_type,)| surround_add(&motion_type)),
+        ),
+    );
+
+    let surround_operator_keybindings: Vec<(Mode, &str, String, &str)> = vec![
+        (
+            Mode::Normal,
+            "ys",
+            format!("<Cmd>set operatorfunc=v:lua.{SURROUND_OPERATOR_FUNCTION}<CR>g@"),
+            "'ys{motion}{char}': Surround the motion text",
+        ),
+        (
+            Mode::Normal,
+            "yss",
+            format!("<Cmd>set operatorfunc=v:lua.{SURROUND_OPERATOR_FUNCTION}<CR>g@_"),
+            "'yss{char}': Surround the current line",
+        ),
+        (
+            Mode::Visual,
+            "S",
+            format!("<Esc><Cmd>lua {SURROUND_OPERATOR_FUNCTION}(vim.fn.visualmode())<CR>"),
+            "'S{char}': Surround the selection",
+        ),
+    ];
+
+    for (mode, key, rhs, desc) in surround_operator_keybindings {
+        let _ = set_keymap(
+            mode,
+            key,
+            &rhs,
+            &SetKeymapOpts::builder().desc(desc).silent(true).build(),
+        );
+    }
+
+    let surround_keybindings: Vec<(&str, &str, fn())> = vec![
+        (
+            "ds",
+            "'ds{char}': Delete the surrounding pair",
+            delete_surround,
+        ),
+        (
+            "cs",
+            "'cs{old}{new}': Change the surrounding pair",
+            change_surround,
+        ),
+    ];
+
+    for (key, desc, callback) in surround_keybindings {
+        let _ = set_keymap(
+            Mode::Normal,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(desc)
+                .silent(true)
+                .callback(move |_| {
+                    callback();
+                    ()
+                })
+                .build(),
+        );
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    setup_autopairs();
+    setup_surround();
+}
+
+use crate::{
+    extended_api::{EchoLevel, exec_lua, prompt, set_expr_keymap},
+    notify::notify,
+};
+
+use nvim_oxi::{
+    Array, Function, Object, String as NvimString,
+    api::{
+        Buffer, Window, call_function, get_option_value,
+        opts::{OptionOpts, SetKeymapOpts},
+        set_keymap,
+        types::Mode,
+    },
+};
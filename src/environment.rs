@@ -0,0 +1,199 @@
+//! A module to view and edit the environment variables of the Neovim process.
+//!
+//! - `:EnvList`: Open a picker to show all environment variables (fuzzy search), select one to
+//!               fill the `:EnvSet NAME=value` command line, so you can edit it.
+//!
+//! - `:EnvSet NAME=value`: Set the environment variable, it affects all subsequently spawned
+//!                         jobs (project commands, terminals, etc.). `:EnvSet NAME=` removes it.
+//!
+//! It's useful for toggling `RUST_LOG` and `PATH` tweaks without restarting Neovim.
+//!
+
+///
+/// The long value (e.g. `PATH`) is truncated in the picker list
+///
+const ENV_VALUE_MAX_DISPLAY_CHARS: usize = 80;
+
+///
+/// Get back all environment variables as picker items, sorted by name.
+///
+fn get_env_picker_items() -> Vec<PickerItem> {
+    let mut env_list = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect::<Vec<(String, String)>>();
+    env_list.sort_by(|a, b| a.0.cmp(&b.0));
+
+    env_list
+        .into_iter()
+        .map(|(name, value)| {
+            //
+            // The multi-line value (e.g. the exported bash function `BASH_FUNC_which%%`) is shown
+            // in one line, as the buffer line can't contain the newline.
+            //
+            let value = value.replace('\n', "⏎");
+            let display_value = if value.chars().count() > ENV_VALUE_MAX_DISPLAY_CHARS {
+                format!(
+                    "{}...",
+                    value
+                        .chars()
+                        .take(ENV_VALUE_MAX_DISPLAY_CHARS)
+                        .collect::<String>()
+                )
+            } else {
+                value.clone()
+            };
+
+            PickerItem {
+                columns: vec![
+                    PickerItemColumn::new(&name, Some("Identifier")),
+                    PickerItemColumn::new(&display_value, Some("String")),
+                ],
+                icon: None,
+                data: name,
+            }
+        })
+        .collect()
+}
+
+///
+/// Fill the command line with `:EnvSet NAME=value` for the selected variable name, so you can
+/// edit it before pressing ENTER. The multi-line value can't be edited in the command line.
+///
+fn env_picker_selected_callback(selected_name: String) {
+    const LOGGER_PREFIX: &'static str = "[ environment - env_picker_selected_callback ]";
+
+    if selected_name.is_empty() {
+        return;
+    }
+
+    let value = std::env::var_os(&selected_name)
+        .and_then(|value| value.into_string().ok())
+        .unwrap_or_default();
+    if value.contains('\n') {
+        nvim::print!("{LOGGER_PREFIX} '{selected_name}' has a multi-line value, can't edit it.");
+        return;
+    }
+
+    let _ = call_function::<_, i64>(
+        "feedkeys",
+        (format!(":EnvSet {selected_name}={value}"), "n"),
+    );
+}
+
+///
+/// Open the environment variable picker
+///
+fn env_list() {
+    let items = get_env_picker_items();
+
+    let _ = create_editable_picker_with_options(
+        &mut EditablePickerOptions {
+            title: "Environment variables ('Enter' to edit)".to_string(),
+            window_opts: PopupWindowOptions {
+                border: WindowBorder::Rounded,
+                window_width_ratio: None,
+                window_height_ratio: None,
+                auto_width: true,
+                auto_height: true,
+                buffer: None,
+                ..Default::default()
+            },
+            list: &items,
+            sort: PickerSortStrategy::None,
+            source: Some("environment".to_string()),
+            sticky_filter: true,
+            show_footer: true,
+            initial_input: None,
+            validator: None,
+            on_delete: None,
+            on_edit: None,
+        },
+        env_picker_selected_callback,
+    );
+}
+
+///
+/// Set (or remove if the value is empty) the environment variable by the given `NAME=value`.
+///
+/// It calls the Vimscript `setenv()`, as the Neovim process and this plugin share the same
+/// environment, all the subsequently spawned jobs (from both sides) inherit the change.
+///
+fn env_set(assignment: &str) {
+    const LOGGER_PREFIX: &'static str = "[ environment - env_set ]";
+
+    let Some((name, value)) = assignment.split_once('=') else {
+        nvim::print!("{LOGGER_PREFIX} Usage: ':EnvSet NAME=value'");
+        return;
+    };
+
+    let name = name.trim();
+    if name.is_empty() {
+        nvim::print!("{LOGGER_PREFIX} Usage: ':EnvSet NAME=value'");
+        return;
+    }
+
+    let set_result = if value.is_empty() {
+        call_function::<_, Object>("setenv", (name, Object::nil()))
+    } else {
+        call_function::<_, Object>("setenv", (name, value))
+    };
+
+    match set_result {
+        Ok(_) if value.is_empty() => nvim::print!("{LOGGER_PREFIX} Removed '{name}'"),
+        Ok(_) => nvim::print!("{LOGGER_PREFIX} {name}={value}"),
+        Err(error) => nvim::print!("{LOGGER_PREFIX} Failed to set '{name}': {error:?}"),
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_user_command(
+        "EnvList",
+        |_: CommandArgs| {
+            env_list();
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Show the environment variables")
+            .build(),
+    );
+
+    let _ = create_user_command(
+        "EnvSet",
+        |args: CommandArgs| {
+            env_set(args.args.as_deref().unwrap_or_default());
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Set the environment variable: ':EnvSet NAME=value'")
+            .nargs(CommandNArgs::OneOrMore)
+            .complete(CommandComplete::Environment)
+            .build(),
+    );
+
+    //
+    // Contribute the environment variables to the picker registry (`<leader>pp`)
+    //
+    register_picker_source(
+        "environment",
+        "Environment variables ('Enter' to edit)",
+        get_env_picker_items,
+        env_picker_selected_callback,
+    );
+}
+
+use crate::picker::{
+    EditablePickerOptions, PickerItem, PickerItemColumn, PickerSortStrategy, PopupWindowOptions,
+    create_editable_picker_with_options, register_picker_source,
+};
+
+use nvim_oxi::{
+    self as nvim, Object,
+    api::{
+        call_function, create_user_command,
+        opts::CreateCommandOpts,
+        types::{CommandArgs, CommandComplete, CommandNArgs, WindowBorder},
+    },
+};
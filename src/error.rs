@@ -0,0 +1,183 @@
+//! The crate-wide error type, the module functions which can fail return `Result<T>` instead of
+//! swallowing the error with `let _ = ...`, and the error is surfaced in one place (the user
+//! facing entry, e.g. the keymap callback):
+//!
+//! ```rust
+//! fn refresh(buffer: &mut Buffer) -> Result<()> {
+//!     let output = run_ls(dir)?;
+//!     buffer.set_lines(.., true, output).context("set the dired buffer lines")?;
+//!     Ok(())
+//! }
+//!
+//! fn on_key_pressed() {
+//!     report("my_dired", refresh(&mut buffer));
+//! }
+//! ```
+//!
+
+///
+/// The error of the module functions, the context describes what it was doing, e.g.
+/// `[ my_dired ] Failed to set the dired buffer lines: ...`.
+///
+#[derive(Debug)]
+pub enum Error {
+    ///
+    /// The Neovim API call failed
+    ///
+    Nvim {
+        context: String,
+        source: nvim::Error,
+    },
+
+    ///
+    /// The file system operation failed
+    ///
+    Io {
+        context: String,
+        source: std::io::Error,
+    },
+
+    ///
+    /// The content can't be parsed, e.g. the state file or the command output
+    ///
+    Parse { context: String, message: String },
+
+    ///
+    /// The external tool (e.g. `ls`, `git`) can't be started or exited with failure
+    ///
+    ExternalTool { tool: String, message: String },
+}
+
+///
+/// The result of the module functions
+///
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn parse(context: &str, message: &str) -> Self {
+        Self::Parse {
+            context: context.to_owned(),
+            message: message.to_owned(),
+        }
+    }
+
+    pub fn external_tool(tool: &str, message: &str) -> Self {
+        Self::ExternalTool {
+            tool: tool.to_owned(),
+            message: message.to_owned(),
+        }
+    }
+
+    ///
+    /// Replace the context, `ExternalTool` keeps the tool name as the context.
+    ///
+    fn with_context(self, new_context: String) -> Self {
+        match self {
+            Self::Nvim { source, .. } => Self::Nvim {
+                context: new_context,
+                source,
+            },
+            Self::Io { source, .. } => Self::Io {
+                context: new_context,
+                source,
+            },
+            Self::Parse { message, .. } => Self::Parse {
+                context: new_context,
+                message,
+            },
+            Self::ExternalTool { .. } => self,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Nvim { context, source } => write!(f, "Failed to {context}: {source}"),
+            Self::Io { context, source } => write!(f, "Failed to {context}: {source}"),
+            Self::Parse { context, message } => write!(f, "Failed to parse {context}: {message}"),
+            Self::ExternalTool { tool, message } => write!(f, "'{tool}' failed: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Nvim { source, .. } => Some(source),
+            Self::Io { source, .. } => Some(source),
+            Self::Parse { .. } | Self::ExternalTool { .. } => None,
+        }
+    }
+}
+
+///
+/// The bare `?` on the Neovim API result, the context is filled by `.context()` if any.
+///
+impl From<nvim::Error> for Error {
+    fn from(source: nvim::Error) -> Self {
+        Self::Nvim {
+            context: String::from("call the Neovim API"),
+            source,
+        }
+    }
+}
+
+impl From<nvim::api::Error> for Error {
+    fn from(source: nvim::api::Error) -> Self {
+        Error::from(nvim::Error::from(source))
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(source: std::io::Error) -> Self {
+        Self::Io {
+            context: String::from("access the file"),
+            source,
+        }
+    }
+}
+
+///
+/// Add the context to the result, it reads as `Failed to {context}: {error}`:
+///
+/// ```rust
+/// std::fs::write(&state_file, content).context("save the project command state")?;
+/// ```
+///
+pub trait ResultContext<T> {
+    fn context(self, context: &str) -> Result<T>;
+}
+
+impl<T, E> ResultContext<T> for std::result::Result<T, E>
+where
+    E: Into<Error>,
+{
+    fn context(self, context: &str) -> Result<T> {
+        self.map_err(|error| error.into().with_context(context.to_owned()))
+    }
+}
+
+///
+/// Show the error message in the notification popup, it's kept in the `:Notifications` history.
+///
+pub fn report_error(module: &str, error: &Error) {
+    notify(module, &error.to_string(), EchoLevel::Error);
+}
+
+///
+/// The one place to surface the module result: report the error and get back the value if any.
+///
+pub fn report<T>(module: &str, result: Result<T>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(error) => {
+            report_error(module, &error);
+            None
+        }
+    }
+}
+
+use crate::{extended_api::EchoLevel, notify::notify};
+
+use nvim_oxi as nvim;
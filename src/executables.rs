@@ -0,0 +1,226 @@
+//! A picker over the executables found on `$PATH`, handy for composing commands:
+//!
+//! - `:Executables` or `<leader>px`: The selected executable name goes into the project command
+//!                                  picker input, so you can finish the command there.
+//! - `:ExecutablesRun`: Run the selected executable in a floating terminal.
+//! - `:ExecutablesRefresh`: Re-scan `$PATH`, the scan result is cached after the first time.
+//!
+
+///
+/// Private module-scope state: the cached executable names (sorted and unique), `None` means
+/// `$PATH` hasn't been scanned yet.
+///
+static EXECUTABLES_CACHE: LazyLock<Mutex<Option<Vec<String>>>> = LazyLock::new(|| Mutex::new(None));
+
+///
+/// What to do with the selected executable
+///
+#[derive(Debug, Clone, Copy)]
+enum ExecutableAction {
+    InsertIntoProjectCommand,
+    RunInTerminal,
+}
+
+///
+/// Scan all directories in `$PATH`, the same name in multiple directories only appears once.
+///
+fn scan_path_executables() -> Vec<String> {
+    let Some(path_value) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+
+    let mut executables = BTreeSet::new();
+    for dir in std::env::split_paths(&path_value) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            //
+            // `std::fs::metadata` follows the symlink, most of the executables are symlinks,
+            // e.g. the ones installed by `homebrew`.
+            //
+            let is_executable = std::fs::metadata(entry.path())
+                .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+                .unwrap_or(false);
+
+            if is_executable && let Ok(name) = entry.file_name().into_string() {
+                executables.insert(name);
+            }
+        }
+    }
+
+    executables.into_iter().collect()
+}
+
+///
+/// Get back the cached executable names, scan `$PATH` if it's not cached yet or `refresh` is
+/// `true`.
+///
+fn get_executables(refresh: bool) -> Vec<String> {
+    let mut locked_cache = EXECUTABLES_CACHE.lock().unwrap();
+    if refresh || locked_cache.is_none() {
+        *locked_cache = Some(scan_path_executables());
+    }
+
+    locked_cache.clone().unwrap_or_default()
+}
+
+///
+///
+///
+fn get_executable_picker_items() -> Vec<PickerItem> {
+    get_executables(false)
+        .iter()
+        .map(|name| PickerItem::from(name.as_str()))
+        .collect()
+}
+
+///
+///
+///
+fn executable_selected_callback(action: ExecutableAction, selected_name: String) {
+    let selected_name = selected_name.trim().to_owned();
+    if selected_name.is_empty() {
+        return;
+    }
+
+    match action {
+        //
+        // Open the project command picker in the next event loop tick, as the executable picker
+        // windows are still closing (and leaving `INSERT` mode) at this moment.
+        //
+        ExecutableAction::InsertIntoProjectCommand => nvim::schedule(move |_| {
+            project_command::open_with_input(&format!("{selected_name} "));
+        }),
+        ExecutableAction::RunInTerminal => run_command_in_floating_terminal(&selected_name),
+    }
+}
+
+///
+/// Open the executable picker
+///
+fn open(action: ExecutableAction) {
+    let items = get_executable_picker_items();
+
+    let title = match action {
+        ExecutableAction::InsertIntoProjectCommand => "Executables (insert into project command)",
+        ExecutableAction::RunInTerminal => "Executables (run in terminal)",
+    };
+
+    let _ = create_editable_picker_with_options(
+        &mut EditablePickerOptions {
+            title: title.to_string(),
+            window_opts: PopupWindowOptions {
+                border: WindowBorder::Rounded,
+                window_width_ratio: None,
+                window_height_ratio: None,
+                auto_width: true,
+                auto_height: true,
+                buffer: None,
+                max_height: Some(20),
+                ..Default::default()
+            },
+            list: &items,
+            sort: PickerSortStrategy::Frecency,
+            source: Some("executables".to_string()),
+            sticky_filter: false,
+            show_footer: true,
+            initial_input: None,
+            validator: None,
+            on_delete: None,
+            on_edit: None,
+        },
+        move |selected_name: String| {
+            executable_selected_callback(action, selected_name);
+        },
+    );
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>px",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("Pick an executable on $PATH for the project command")
+            .silent(true)
+            .callback(|_| {
+                open(ExecutableAction::InsertIntoProjectCommand);
+                ()
+            })
+            .build(),
+    );
+
+    let user_commands: Vec<(&str, &str, Box<dyn Fn()>)> = vec![
+        (
+            "Executables",
+            "Pick an executable on $PATH for the project command",
+            Box::new(|| open(ExecutableAction::InsertIntoProjectCommand)),
+        ),
+        (
+            "ExecutablesRun",
+            "Pick an executable on $PATH and run it in a floating terminal",
+            Box::new(|| open(ExecutableAction::RunInTerminal)),
+        ),
+        (
+            "ExecutablesRefresh",
+            "Re-scan the executables on $PATH",
+            Box::new(|| {
+                let count = get_executables(true).len();
+                nvim::print!("[ executables ] Found {count} executables on $PATH.");
+            }),
+        ),
+    ];
+
+    for command in user_commands {
+        let _ = create_user_command(
+            command.0,
+            move |_: CommandArgs| {
+                command.2();
+                ()
+            },
+            &CreateCommandOpts::builder().desc(command.1).build(),
+        );
+    }
+
+    //
+    // Contribute the executables to the picker registry (`<leader>pp`)
+    //
+    register_picker_source(
+        "executables",
+        "Executables (insert into project command)",
+        get_executable_picker_items,
+        |selected_name: String| {
+            executable_selected_callback(ExecutableAction::InsertIntoProjectCommand, selected_name);
+        },
+    );
+}
+
+use crate::{
+    floating_terminal::run_command_in_floating_terminal,
+    picker::{
+        EditablePickerOptions, PickerItem, PickerSortStrategy, PopupWindowOptions,
+        create_editable_picker_with_options, register_picker_source,
+    },
+    project_command,
+};
+
+use std::{
+    collections::BTreeSet,
+    os::unix::fs::PermissionsExt,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim,
+    api::{
+        create_user_command,
+        opts::{CreateCommandOpts, SetKeymapOpts},
+        set_keymap,
+        types::{CommandArgs, Mode, WindowBorder},
+    },
+};
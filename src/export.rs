@@ -0,0 +1,249 @@
+//! Export the buffer (or the line range) with the current theme colors:
+//!
+//! - `:[range]Export [html|pdf]`: Render the lines to the styled HTML by the builtin Lua
+//!   `tohtml` (it uses the highlight colors of the current colorscheme), and then write it to
+//!   the prompted output path. The PDF is converted from the HTML by the first available
+//!   converter in `PDF_CONVERTERS`. It's the whole buffer without range, default is `html`.
+//! - `<leader>th`: Export the buffer (or the visual selection) to HTML.
+//!
+
+///
+/// The external HTML to PDF converters (in the preferred order), `{input}` and `{output}` are
+/// replaced with the HTML and PDF file paths.
+///
+const PDF_CONVERTERS: [&'static [&'static str]; 3] = [
+    &["wkhtmltopdf", "--quiet", "{input}", "{output}"],
+    &["weasyprint", "{input}", "{output}"],
+    &[
+        "chromium",
+        "--headless",
+        "--no-pdf-header-footer",
+        "--print-to-pdf={output}",
+        "{input}",
+    ],
+];
+
+///
+/// Export format
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Html,
+    Pdf,
+}
+
+impl ExportFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "" | "html" => Some(Self::Html),
+            "pdf" => Some(Self::Pdf),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
+///
+/// Render the lines (1-based, inclusive) of the current window buffer to the HTML lines
+///
+fn render_html(line1: usize, line2: usize) -> Option<Vec<String>> {
+    exec_lua_with_module::<_, Vec<String>>(
+        "tohtml",
+        "tohtml",
+        (
+            0,
+            Dictionary::from_iter([("range", Array::from_iter([line1 as i64, line2 as i64]))]),
+        ),
+    )
+    .filter(|lines| !lines.is_empty())
+}
+
+///
+/// Prompt the output path, the default one is the current file name with the format
+/// extension. Return `None` if it's cancelled, or the existing file is not allowed to overwrite.
+///
+fn prompt_output_path(format: ExportFormat) -> Option<String> {
+    let default_path = call_function::<_, String>("expand", ("%:p:r",))
+        .ok()
+        .filter(|path| !path.is_empty())
+        .map(|path| format!("{path}.{}", format.extension()))
+        .unwrap_or_else(|| format!("export.{}", format.extension()));
+
+    let output_path = prompt("Export to: ", &default_path, Some("file"))
+        .map(|path| path.trim().to_owned())
+        .filter(|path| !path.is_empty())?;
+
+    let output_path =
+        call_function::<_, String>("expand", (output_path.as_str(),)).unwrap_or(output_path);
+
+    if Path::new(&output_path).exists()
+        && confirm(
+            "Export",
+            &[format!("'{output_path}' exists, overwrite it?")],
+            &["&Yes", "&No"],
+            1,
+        ) != Some(0)
+    {
+        return None;
+    }
+
+    Some(output_path)
+}
+
+///
+/// Get back the command of the first available PDF converter
+///
+fn get_pdf_converter_command(input: &str, output: &str) -> Option<Vec<String>> {
+    let converter = PDF_CONVERTERS.iter().find(|converter| {
+        call_function::<_, i64>("executable", (converter[0],)).unwrap_or(0) == 1
+    })?;
+
+    Some(
+        converter
+            .iter()
+            .map(|arg| arg.replace("{input}", input).replace("{output}", output))
+            .collect(),
+    )
+}
+
+///
+/// Export the lines (1-based, inclusive) of the current buffer to the given format
+///
+fn export_buffer(format: ExportFormat, line1: usize, line2: usize) {
+    const LOGGER_PREFIX: &'static str = "[ export ]";
+
+    let Some(html_lines) = render_html(line1, line2) else {
+        nvim::print!("{LOGGER_PREFIX} Failed to render the HTML.");
+        return;
+    };
+
+    let Some(output_path) = prompt_output_path(format) else {
+        return;
+    };
+
+    let html_path = match format {
+        ExportFormat::Html => output_path.clone(),
+        ExportFormat::Pdf => {
+            let temp_path = call_function::<_, String>("tempname", ()).unwrap_or_default();
+            format!("{temp_path}.html")
+        }
+    };
+
+    if let Err(error) = std::fs::write(&html_path, html_lines.join("\n")) {
+        nvim::print!("{LOGGER_PREFIX} Failed to write '{html_path}': {error}");
+        return;
+    }
+
+    if format == ExportFormat::Html {
+        nvim::print!("{LOGGER_PREFIX} Exported to '{output_path}'.");
+        return;
+    }
+
+    let Some(converter_command) = get_pdf_converter_command(&html_path, &output_path) else {
+        nvim::print!(
+            "{LOGGER_PREFIX} No PDF converter found (wkhtmltopdf, weasyprint or chromium), the HTML is at '{html_path}'."
+        );
+        return;
+    };
+
+    let error_lines = Rc::new(RefCell::new(Vec::<String>::new()));
+    let on_output = {
+        let error_lines = error_lines.clone();
+        move |lines: Vec<String>| error_lines.borrow_mut().extend(lines)
+    };
+    let on_exit = move |exit_code: i64| {
+        let _ = std::fs::remove_file(&html_path);
+
+        if exit_code == 0 {
+            nvim::print!("{LOGGER_PREFIX} Exported to '{output_path}'.");
+        } else {
+            nvim::print!(
+                "{LOGGER_PREFIX} PDF converter failed (exit code: {exit_code}): {}",
+                error_lines.borrow().join(" ").trim()
+            );
+        }
+    };
+
+    if !run_async(converter_command, on_output, on_exit) {
+        nvim::print!("{LOGGER_PREFIX} Failed to run the PDF converter.");
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_user_command(
+        "Export",
+        |args: CommandArgs| {
+            let format_name = args.args.as_deref().unwrap_or_default().trim().to_lowercase();
+            match ExportFormat::from_name(&format_name) {
+                Some(format) => export_buffer(format, args.line1, args.line2),
+                None => {
+                    nvim::print!("[ export ] Unknown format: '{format_name}', use 'html' or 'pdf'.")
+                }
+            }
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Export the buffer (or the range) with the theme colors: ':[range]Export [html|pdf]'")
+            .nargs(CommandNArgs::ZeroOrOne)
+            .range(CommandRange::WholeFile)
+            .complete(CommandComplete::CustomList(Function::from_fn(
+                |(arg_lead, _, _): (String, String, usize)| {
+                    ["html", "pdf"]
+                        .iter()
+                        .filter(|format| format.starts_with(&arg_lead))
+                        .map(|format| format.to_string())
+                        .collect::<Vec<String>>()
+                },
+            )))
+            .build(),
+    );
+
+    let key_bindings: Vec<(Mode, &str, &str)> = vec![
+        (
+            Mode::Normal,
+            ":Export html<CR>",
+            "'<leader>th': Export the buffer to HTML",
+        ),
+        (
+            Mode::Visual,
+            ":Export html<CR>",
+            "'<leader>th': Export the selection to HTML",
+        ),
+    ];
+
+    for (mode, rhs, desc) in key_bindings {
+        let _ = set_keymap(
+            mode,
+            "<leader>th",
+            rhs,
+            &SetKeymapOpts::builder().desc(desc).silent(true).build(),
+        );
+    }
+}
+
+use crate::{
+    extended_api::{exec_lua_with_module, prompt},
+    picker::confirm,
+    utils::run_async,
+};
+
+use std::{cell::RefCell, path::Path, rc::Rc};
+
+use nvim_oxi::{
+    self as nvim, Array, Dictionary, Function,
+    api::{
+        call_function, create_user_command,
+        opts::{CreateCommandOpts, SetKeymapOpts},
+        set_keymap,
+        types::{CommandArgs, CommandComplete, CommandNArgs, CommandRange, Mode},
+    },
+};
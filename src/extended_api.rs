@@ -0,0 +1,813 @@
+//! Safe wrappers of the Neovim API which need the extra care to be used from Rust:
+//!
+//! - `buf_attach()`/`buf_detach()`: Wrap `nvim_buf_attach`, so the modules can react to the
+//!   buffer edits without polling (e.g. the picker's live filter). The `on_lines` events are
+//!   delivered to the Rust callback on the next event loop tick (by `vim.schedule`), as the
+//!   `on_lines` callback runs under the textlock, it's not allowed to change any buffer there.
+//! - `echo_chunks()`/`echo_message()`: Wrap `nvim_echo`, print the multi-chunk highlighted
+//!   message (e.g. the red errors, the green success), and keep it in `:messages` if `history`
+//!   is `true`.
+//! - `open_terminal()`/`terminal_send()`: Wrap `nvim_open_term` and `nvim_chan_send`, render the
+//!   output (with the ANSI escape sequences) by the builtin terminal emulator in a scratch
+//!   buffer, without running a process like `:terminal`.
+//! - `list_uis()`: Wrap `nvim_list_uis`, the typed info of the attached UIs (grid size,
+//!   multigrid, RGB colors). `nvim_ui_attach` is only for the RPC clients, so it's not wrapped.
+//! - `exec_lua()`/`try_exec_lua()`/`exec_lua_with_module()`: Run the Lua chunk by `luaeval()`
+//!   (`nvim_exec_lua` is only for the RPC clients, it can't be called by `call_function()`), the
+//!   arguments (any tuple of the `Object` convertible values, e.g. `String`, `i64`, `Dictionary`,
+//!   `Function`) are passed by the magic global `_A` and unpacked as the Lua varargs `...`, so the
+//!   Lua code never embeds the values by `format!()`, and the result is converted back to the Rust
+//!   type.
+//! - `set_expr_keymap()`: Set the `expr` keymap with the Rust callback which returns the keys
+//!   (`nvim_set_keymap` only takes the callback without the return value). The returned keys
+//!   run right away, while `nvim_feedkeys()` appends them after the pending typeahead, which
+//!   breaks the order in the macro, `:normal` and the fast typing.
+//! - `set_decoration_provider()`: Wrap `nvim_set_decoration_provider`, the `DecorationProvider`
+//!   draws the ephemeral extmarks (`set_ephemeral_highlight()`) of the visible lines during the
+//!   redraw, instead of setting the extmarks of all lines eagerly (e.g. the picker item
+//!   highlights of the thousands of items).
+//! - `register_user_command()`: Wrap `nvim_create_user_command` with the declarative
+//!   `UserCommandSpec` (nargs, range, bang, completion), the callback gets the parsed
+//!   `UserCommandArgs` instead of the raw `CommandArgs`.
+//! - `prompt()`: Wrap `vim.fn.input` with the `default` and `completion`, it returns `None` if
+//!   it's cancelled (`<Esc>` or `<C-c>`), which is different from entering the empty string.
+//!   `vim.ui.input` isn't used, as it's asynchronous (and may be replaced by the UI plugins),
+//!   while the callers need the input right away.
+//! - `get_register()`/`set_register()`: Wrap `getreg()`/`getregtype()` and `setreg()`, the
+//!   register content is the list of lines with the typed `RegisterType` (charwise, linewise or
+//!   blockwise), so the multi-line and the blockwise content round trip without the string
+//!   escaping.
+//!
+
+///
+/// The `on_lines` event of the attached buffer
+///
+#[derive(Debug, Clone, Copy)]
+pub struct BufLinesEvent {
+    pub buffer: BufHandle,
+
+    //
+    // The `b:changedtick` after the change, the event is stale if it's not the current one, as
+    // the events are delivered on the next event loop tick.
+    //
+    pub changedtick: u32,
+
+    //
+    // The first changed line (0-based)
+    //
+    pub first_line: usize,
+}
+
+///
+/// Private module-scope state
+///
+#[derive(Debug, Default)]
+struct BufAttachState {
+    next_attach_id: u64,
+
+    //
+    // The detached attachments, their `on_lines` returns `true` to detach from the buffer on the
+    // next event, as there is no API to detach the Lua callback directly.
+    //
+    detached_ids: HashSet<u64>,
+}
+
+static BUF_ATTACH_STATE: LazyLock<Mutex<BufAttachState>> =
+    LazyLock::new(|| Mutex::new(BufAttachState::default()));
+
+///
+/// Whether the attachment is detached
+///
+fn is_buf_detached(attach_id: u64) -> bool {
+    BUF_ATTACH_STATE
+        .lock()
+        .unwrap()
+        .detached_ids
+        .contains(&attach_id)
+}
+
+///
+/// Attach to the buffer and call the `callback` after the lines of the buffer changed, it stops
+/// when `buf_detach()` is called, or the buffer is unloaded. Return the attach id, or `None` if
+/// it fails to attach.
+///
+/// ```rust
+/// let attach_id = buf_attach(&input_buffer, move |event: BufLinesEvent| {
+///     let query = Buffer::from(event.buffer).get_lines(0..1, true);
+///     // ...
+/// });
+/// ```
+///
+pub fn buf_attach<F>(buffer: &Buffer, callback: F) -> Option<u64>
+where
+    F: FnMut(BufLinesEvent) + 'static,
+{
+    let attach_id = {
+        let mut locked_state = BUF_ATTACH_STATE.lock().unwrap();
+        locked_state.next_attach_id += 1;
+        locked_state.next_attach_id
+    };
+
+    let callback = Rc::new(RefCell::new(callback));
+    let opts = BufAttachOpts::builder()
+        .on_lines(move |args: OnLinesArgs| {
+            if is_buf_detached(attach_id) {
+                BUF_ATTACH_STATE
+                    .lock()
+                    .unwrap()
+                    .detached_ids
+                    .remove(&attach_id);
+
+                //
+                // Return `true` to detach from the buffer
+                //
+                return true;
+            }
+
+            let (_, buffer, changedtick, first_line, ..) = args;
+            let event = BufLinesEvent {
+                buffer: buffer.handle(),
+                changedtick,
+                first_line,
+            };
+
+            //
+            // Run it outside the textlock
+            //
+            let callback = callback.clone();
+            nvim::schedule(move |_| {
+                if !is_buf_detached(attach_id) {
+                    (callback.borrow_mut())(event);
+                }
+                ()
+            });
+
+            false
+        })
+        .build();
+
+    buffer.attach(false, &opts).ok().map(|_| attach_id)
+}
+
+///
+/// Stop calling the callback of the attachment, it detaches from the buffer on the next change.
+///
+pub fn buf_detach(attach_id: u64) {
+    BUF_ATTACH_STATE
+        .lock()
+        .unwrap()
+        .detached_ids
+        .insert(attach_id);
+}
+
+///
+/// The level of the message, it decides the highlight group of the message text
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EchoLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl EchoLevel {
+    fn hl_group(&self) -> Option<&'static str> {
+        match self {
+            Self::Info => None,
+            Self::Success => Some("DiagnosticOk"),
+            Self::Warning => Some("WarningMsg"),
+            Self::Error => Some("ErrorMsg"),
+        }
+    }
+}
+
+///
+/// Print the message chunks: `(text, highlight group)`, the chunk without the highlight group
+/// uses the `Normal` one. It goes to `:messages` if `history` is `true`.
+///
+/// ```rust
+/// echo_chunks(
+///     &[("[ my_dired ] ", Some("Title")), ("Failed to delete: ", Some("ErrorMsg")), ("a.txt", None)],
+///     true,
+/// );
+/// ```
+///
+pub fn echo_chunks(chunks: &[(&str, Option<&str>)], history: bool) {
+    let _ = echo(
+        chunks.iter().map(|(text, hl_group)| (*text, *hl_group)),
+        history,
+        &EchoOpts::default(),
+    );
+}
+
+///
+/// Print the module message with the level highlight, e.g. `[ project_command ] Passed: ...`,
+/// it's kept in `:messages`.
+///
+pub fn echo_message(module: &str, message: &str, level: EchoLevel) {
+    echo_chunks(
+        &[
+            (&format!("[ {module} ] "), Some("Title")),
+            (message, level.hl_group()),
+        ],
+        true,
+    );
+}
+
+///
+/// Open the embedded terminal (without the process) in the buffer, the data sent by
+/// `terminal_send()` is rendered by the terminal emulator, so the ANSI escape sequences (colors,
+/// cursor movements) work natively. Return the channel id.
+///
+pub fn open_terminal(buffer: &Buffer) -> Option<u32> {
+    open_term(buffer, &OpenTermOpts::default()).ok()
+}
+
+///
+/// Send the data to the embedded terminal, the line ending has to be `\r\n` as there is no pty
+/// to translate `\n`.
+///
+pub fn terminal_send(channel_id: u32, data: &str) -> bool {
+    chan_send(channel_id, data).is_ok()
+}
+
+///
+/// The attached UI info of `nvim_list_uis`
+///
+#[derive(Debug, Clone, Default)]
+pub struct UiInfo {
+    pub width: u32,
+    pub height: u32,
+
+    //
+    // The UI uses the RGB colors (`termguicolors` for the TUI)
+    //
+    pub rgb: bool,
+
+    //
+    // The UI draws each window on its own grid (e.g. Neovide)
+    //
+    pub multigrid: bool,
+
+    //
+    // The builtin TUI (or `--remote-ui`) runs in a terminal, it's `false` for the GUI clients.
+    //
+    pub is_tty: bool,
+}
+
+///
+/// Get back the attached UIs, it's empty in the headless mode.
+///
+pub fn list_uis() -> Vec<UiInfo> {
+    let Ok(uis) = call_function::<_, Vec<Dictionary>>("nvim_list_uis", ()) else {
+        return Vec::new();
+    };
+
+    uis.into_iter()
+        .map(|ui| {
+            let mut ui_info = UiInfo::default();
+            for (key, value) in ui {
+                match key.to_string().as_str() {
+                    "width" => ui_info.width = i64::from_object(value).unwrap_or(0) as u32,
+                    "height" => ui_info.height = i64::from_object(value).unwrap_or(0) as u32,
+                    "rgb" => ui_info.rgb = bool::from_object(value).unwrap_or(false),
+                    "ext_multigrid" => {
+                        ui_info.multigrid = bool::from_object(value).unwrap_or(false)
+                    }
+                    "stdout_tty" => ui_info.is_tty = bool::from_object(value).unwrap_or(false),
+                    _ => {}
+                }
+            }
+            ui_info
+        })
+        .collect()
+}
+
+///
+/// Run the Lua code with the arguments (`...` in the code), and convert the returned value to
+/// `R`. Return `None` if the code fails or the result can't be converted.
+///
+/// ```rust
+/// let lines = exec_lua::<_, Vec<String>>(
+///     "local line1, line2 = ...; return vim.api.nvim_buf_get_lines(0, line1 - 1, line2, false)",
+///     (line1 as i64, line2 as i64),
+/// );
+/// ```
+///
+pub fn exec_lua<A, R>(code: &str, args: A) -> Option<R>
+where
+    A: Into<Array>,
+    R: FromObject,
+{
+    try_exec_lua(code, args).ok()
+}
+
+///
+/// The same with `exec_lua()`, but get back the error (e.g. the Lua syntax error, the error
+/// raised by the code), so the caller can report it instead of ignoring it. `Ok` doesn't mean
+/// there is the value, the code may return `nil`.
+///
+/// The chunk is compiled by `loadstring()` and called with the unpacked `_A[2]`, as `luaeval()`
+/// only evaluates the expression. The arg count is passed as `_A[3]` and `vim.NIL` is turned
+/// back to `nil`, so the `Object::nil()` arg is `nil` in the code and the args after it are kept.
+///
+pub fn try_exec_lua<A, R>(code: &str, args: A) -> Result<R, nvim::api::Error>
+where
+    A: Into<Array>,
+    R: FromObject,
+{
+    const EXEC_LUA_EXPRESSION: &'static str = r#"(function(c, a, n)
+    local args = {}
+    for i = 1, n do
+        if a[i] ~= vim.NIL then
+            args[i] = a[i]
+        end
+    end
+    return assert(loadstring(c))(unpack(args, 1, n))
+end)(_A[1], _A[2], _A[3])"#;
+
+    let args = args.into();
+    let arg_count = args.len() as i64;
+
+    call_function::<_, R>(
+        "luaeval",
+        (
+            EXEC_LUA_EXPRESSION,
+            Array::from_iter([
+                Object::from(code),
+                Object::from(args),
+                Object::from(arg_count),
+            ]),
+        ),
+    )
+}
+
+///
+/// Call the function of the Lua module, `require(module)[function](args...)`, and convert the
+/// returned value to `R`.
+///
+/// ```rust
+/// let html_lines = exec_lua_with_module::<_, Vec<String>>(
+///     "tohtml",
+///     "tohtml",
+///     (0, Dictionary::from_iter([("range", Array::from_iter([1, 10]))])),
+/// );
+/// ```
+///
+pub fn exec_lua_with_module<A, R>(module: &str, function: &str, args: A) -> Option<R>
+where
+    A: Into<Array>,
+    R: FromObject,
+{
+    //
+    // The module and function names are the first 2 varargs, the rest are the function args.
+    //
+    let code = r#"local module, func = ...
+return require(module)[func](select(3, ...))"#;
+
+    let args = Array::from_iter(
+        [Object::from(module), Object::from(function)]
+            .into_iter()
+            .chain(args.into()),
+    );
+
+    exec_lua(code, args)
+}
+
+///
+/// Set the global `expr` keymap, the callback returns the keys to run (the keycodes like `<Tab>`
+/// are replaced, and they're not remapped).
+///
+/// The callback runs under the textlock: it can read the buffer and the cursor, but the change
+/// has to be done by the returned keys (e.g. `<Cmd>...<CR>`).
+///
+/// ```rust
+/// set_expr_keymap(Mode::Insert, "(", "'(': Insert the pair", || "()<C-g>U<Left>".to_string());
+/// ```
+///
+pub fn set_expr_keymap<F>(mode: Mode, lhs: &str, desc: &str, callback: F) -> bool
+where
+    F: Fn() -> String + 'static,
+{
+    let lua_code = r#"local mode, lhs, callback, desc = ...
+vim.keymap.set(mode, lhs, function()
+    return callback()
+end, { expr = true, replace_keycodes = true, silent = true, desc = desc })"#;
+
+    try_exec_lua::<_, Object>(
+        lua_code,
+        (
+            NvimString::from(mode),
+            lhs,
+            Function::<(), String>::from_fn(move |()| callback()),
+            desc,
+        ),
+    )
+    .is_ok()
+}
+
+///
+/// The redraw callbacks of the decoration provider, they run while Neovim is drawing the screen,
+/// so only the ephemeral extmarks are allowed to set in them (no buffer/window changes).
+///
+pub trait DecorationProvider {
+    ///
+    /// The window is about to be redrawn, `top_row` and `bottom_row` (0-based) are the visible
+    /// lines of the buffer. Return `false` to skip the `on_line()` of this window.
+    ///
+    fn on_win(
+        &mut self,
+        window: &Window,
+        buffer: &Buffer,
+        top_row: usize,
+        bottom_row: usize,
+    ) -> bool;
+
+    ///
+    /// The line (0-based) of the window is about to be drawn
+    ///
+    fn on_line(&mut self, window: &Window, buffer: &mut Buffer, row: usize, namespace_id: u32);
+}
+
+///
+/// Set the decoration provider of the namespace, it replaces the previous one of the same
+/// namespace.
+///
+/// The callbacks go through the trampolines: the provider is skipped (instead of panicking) if
+/// it's still borrowed by the nested redraw, and the panic in the provider is caught, as the
+/// unwinding across the redraw aborts Neovim.
+///
+pub fn set_decoration_provider<P>(namespace_id: u32, provider: P) -> bool
+where
+    P: DecorationProvider + 'static,
+{
+    let provider = Rc::new(RefCell::new(provider));
+    let on_win_provider = provider.clone();
+
+    let opts = DecorationProviderOpts::builder()
+        .on_win(move |args: OnWinArgs| {
+            let (_, window, buffer, top_row, bottom_row) = args;
+            let Ok(mut provider) = on_win_provider.try_borrow_mut() else {
+                return false;
+            };
+
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                provider.on_win(&window, &buffer, top_row as usize, bottom_row as usize)
+            }))
+            .unwrap_or(false)
+        })
+        .on_line(move |args: OnLineArgs| {
+            let (_, window, mut buffer, row) = args;
+            let Ok(mut provider) = provider.try_borrow_mut() else {
+                return;
+            };
+
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+                provider.on_line(&window, &mut buffer, row, namespace_id)
+            }));
+        })
+        .build();
+
+    set_decoration_provider_of_namespace(namespace_id, &opts).is_ok()
+}
+
+///
+/// Highlight the columns (byte offsets, `end_col` is exclusive) of the row in the
+/// `DecorationProvider::on_line()`, it only lives for the current redraw.
+///
+pub fn set_ephemeral_highlight(
+    buffer: &mut Buffer,
+    namespace_id: u32,
+    row: usize,
+    start_col: usize,
+    end_col: usize,
+    hl_group: &str,
+) {
+    let _ = buffer.set_extmark(
+        namespace_id,
+        row,
+        start_col,
+        &SetExtmarkOpts::builder()
+            .end_line(row)
+            .end_col(end_col)
+            .hl_group(hl_group)
+            .ephemeral(true)
+            .build(),
+    );
+}
+
+///
+/// The argument completion of the user command
+///
+#[derive(Debug, Clone, Copy)]
+pub enum UserCommandComplete {
+    None,
+    Directory,
+    File,
+
+    //
+    // The candidates, they're filtered by the typed argument prefix.
+    //
+    List(fn() -> Vec<String>),
+}
+
+///
+/// The declarative spec of the user command
+///
+#[derive(Debug, Clone)]
+pub struct UserCommandSpec {
+    pub desc: &'static str,
+    pub nargs: CommandNArgs,
+
+    //
+    // Accept the line range, the default is the current line.
+    //
+    pub range: bool,
+    pub bang: bool,
+    pub complete: UserCommandComplete,
+}
+
+impl Default for UserCommandSpec {
+    fn default() -> Self {
+        Self {
+            desc: "",
+            nargs: CommandNArgs::Zero,
+            range: false,
+            bang: false,
+            complete: UserCommandComplete::None,
+        }
+    }
+}
+
+///
+/// The parsed arguments of the user command
+///
+#[derive(Debug, Clone, Default)]
+pub struct UserCommandArgs {
+    //
+    // The whitespace-separated arguments (`<f-args>`)
+    //
+    pub args: Vec<String>,
+    pub bang: bool,
+
+    //
+    // The line range (1-based, inclusive), `None` if the range isn't given.
+    //
+    pub range: Option<(usize, usize)>,
+}
+
+impl UserCommandArgs {
+    ///
+    /// The argument by the index
+    ///
+    pub fn arg(&self, index: usize) -> Option<&str> {
+        self.args.get(index).map(|arg| arg.as_str())
+    }
+
+    ///
+    /// All arguments joined by the space, e.g. the shell command
+    ///
+    pub fn joined(&self) -> String {
+        self.args.join(" ")
+    }
+}
+
+///
+/// Create the user command by the spec, the callback gets the parsed arguments.
+///
+/// ```rust
+/// register_user_command(
+///     "Dired",
+///     UserCommandSpec {
+///         desc: "Open my dired with the directory",
+///         nargs: CommandNArgs::ZeroOrOne,
+///         complete: UserCommandComplete::Directory,
+///         ..Default::default()
+///     },
+///     |args: UserCommandArgs| open_directory(args.arg(0).unwrap_or(".")),
+/// );
+/// ```
+///
+pub fn register_user_command<F>(name: &str, spec: UserCommandSpec, callback: F)
+where
+    F: Fn(UserCommandArgs) + 'static,
+{
+    let mut builder = CreateCommandOpts::builder();
+    builder.desc(spec.desc).nargs(spec.nargs).bang(spec.bang);
+    if spec.range {
+        builder.range(CommandRange::CurrentLine);
+    }
+    match spec.complete {
+        UserCommandComplete::None => {}
+        UserCommandComplete::Directory => {
+            builder.complete(CommandComplete::Dir);
+        }
+        UserCommandComplete::File => {
+            builder.complete(CommandComplete::File);
+        }
+        UserCommandComplete::List(candidates) => {
+            builder.complete(CommandComplete::CustomList(Function::from_fn(
+                move |(arg_lead, _, _): (String, String, usize)| {
+                    candidates()
+                        .into_iter()
+                        .filter(|candidate| candidate.starts_with(&arg_lead))
+                        .collect::<Vec<String>>()
+                },
+            )));
+        }
+    }
+
+    let _ = create_user_command(
+        name,
+        move |args: CommandArgs| {
+            callback(UserCommandArgs {
+                args: args.fargs,
+                bang: args.bang,
+                range: if args.range > 0 {
+                    Some((args.line1, args.line2))
+                } else {
+                    None
+                },
+            });
+            ()
+        },
+        &builder.build(),
+    );
+}
+
+///
+/// Prompt for the user input in the cmdline, `completion` is the `:command-complete` type (e.g.
+/// `file`, `shellcmd`). Return `None` if it's cancelled, or `Some("")` if the empty string is
+/// entered.
+///
+/// ```rust
+/// let Some(new_path) = prompt("Rename to: ", &current_path, Some("file")) else {
+///     return;
+/// };
+/// ```
+///
+pub fn prompt(prompt: &str, default: &str, completion: Option<&str>) -> Option<String> {
+    //
+    // `cancelreturn` distinguishes `<Esc>` from the empty input, and `<C-c>` raises the
+    // `Keyboard interrupt` error which is caught by `pcall`.
+    //
+    const CANCEL_RETURN: &'static str = "\u{1b}";
+
+    //
+    // The `input()` options are passed by the magic global `_A` of `luaeval()`, so the prompt
+    // and the default text never need to be escaped.
+    //
+    let mut opts = Dictionary::from_iter([
+        ("prompt", prompt),
+        ("default", default),
+        ("cancelreturn", CANCEL_RETURN),
+    ]);
+    if let Some(completion) = completion {
+        opts.insert("completion", completion);
+    }
+
+    let input = call_function::<_, String>(
+        "luaeval",
+        (
+            r#"(function(opts)
+    local ok, input = pcall(vim.fn.input, opts)
+    return ok and input or opts.cancelreturn
+end)(_A)"#,
+            opts,
+        ),
+    )
+    .ok()?;
+
+    if input == CANCEL_RETURN {
+        None
+    } else {
+        Some(input)
+    }
+}
+
+///
+/// The register type of `getregtype()`, the blockwise one has the block width.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegisterType {
+    Charwise,
+    Linewise,
+    Blockwise(Option<usize>),
+}
+
+impl RegisterType {
+    ///
+    /// `getregtype()` returns `v`, `V` or `<C-v>{width}`, `None` for the empty register.
+    ///
+    fn from_regtype(regtype: &str) -> Option<Self> {
+        match regtype {
+            "v" => Some(Self::Charwise),
+            "V" => Some(Self::Linewise),
+            _ => regtype
+                .strip_prefix('\u{16}')
+                .map(|width| Self::Blockwise(width.parse::<usize>().ok())),
+        }
+    }
+
+    ///
+    /// The `options` of `setreg()`, the blockwise one without the width uses the longest line.
+    ///
+    fn as_setreg_options(&self) -> String {
+        match self {
+            Self::Charwise => "c".to_string(),
+            Self::Linewise => "l".to_string(),
+            Self::Blockwise(Some(width)) => format!("b{width}"),
+            Self::Blockwise(None) => "b".to_string(),
+        }
+    }
+}
+
+///
+/// The register content
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegisterContent {
+    pub lines: Vec<String>,
+    pub regtype: RegisterType,
+}
+
+impl RegisterContent {
+    ///
+    /// The charwise content of the text, e.g. the file path to paste inside the line
+    ///
+    pub fn charwise(text: &str) -> Self {
+        Self {
+            lines: text.split('\n').map(|line| line.to_owned()).collect(),
+            regtype: RegisterType::Charwise,
+        }
+    }
+
+    ///
+    /// The linewise content, it's pasted as the whole lines
+    ///
+    pub fn linewise(lines: Vec<String>) -> Self {
+        Self {
+            lines,
+            regtype: RegisterType::Linewise,
+        }
+    }
+
+    ///
+    /// The content as a single string, the lines are joined by `\n`
+    ///
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+}
+
+///
+/// Get back the register content, `None` if the register is empty or invalid. The `=`
+/// register returns the last expression result, it's NOT evaluated again.
+///
+pub fn get_register(name: char) -> Option<RegisterContent> {
+    let name = name.to_string();
+    let regtype = call_function::<_, String>("getregtype", (name.as_str(),)).ok()?;
+    let regtype = RegisterType::from_regtype(&regtype)?;
+    let lines = call_function::<_, Vec<String>>("getreg", (name.as_str(), 1, 1)).ok()?;
+
+    Some(RegisterContent { lines, regtype })
+}
+
+///
+/// Set the register content, return `false` if the register isn't writable (e.g. `.` or `%`).
+///
+/// ```rust
+/// set_register('+', &RegisterContent::charwise("src/main.rs"));
+/// ```
+///
+pub fn set_register(name: char, content: &RegisterContent) -> bool {
+    let lines = Array::from_iter(content.lines.iter().map(|line| line.as_str()));
+    call_function::<_, i64>(
+        "setreg",
+        (name.to_string(), lines, content.regtype.as_setreg_options()),
+    )
+    .is_ok_and(|result| result == 0)
+}
+
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim, Array, BufHandle, Dictionary, Function, Object, String as NvimString,
+    api::{
+        Buffer, Window, call_function, chan_send, create_user_command, echo, open_term,
+        opts::{
+            BufAttachOpts, CreateCommandOpts, DecorationProviderOpts, EchoOpts, OnLineArgs,
+            OnLinesArgs, OnWinArgs, OpenTermOpts, SetExtmarkOpts,
+        },
+        set_decoration_provider as set_decoration_provider_of_namespace,
+        types::{CommandArgs, CommandComplete, CommandNArgs, CommandRange, Mode},
+    },
+    conversion::FromObject,
+};
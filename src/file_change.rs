@@ -0,0 +1,191 @@
+//! File-change conflict detector for the command outputs.
+//!
+//! Some commands modify the source files (`cargo fmt`, code generators, etc.), it takes a
+//! snapshot of the open file buffers before running the command, and then shows a single
+//! confirm dialog of the changed files after the command finishes (instead of the per-buffer
+//! `W11` warnings):
+//!
+//! - `Reload`: Reload the changed buffers without unsaved changes.
+//! - `Reload all`: Reload all changed buffers (the unsaved changes are discarded).
+//! - `Close` (or `<ESC>`): Close without reloading.
+//!
+
+///
+/// The open file buffer and its file modified time
+///
+#[derive(Debug, Clone)]
+struct OpenFile {
+    buffer_handle: BufHandle,
+    path: PathBuf,
+    modified_time: Option<SystemTime>,
+}
+
+///
+/// Snapshot of all open file buffers, take it before running the command
+///
+#[derive(Debug, Default)]
+pub struct OpenFilesSnapshot {
+    files: Vec<OpenFile>,
+}
+
+///
+/// The open file which has been changed on disk
+///
+#[derive(Debug, Clone)]
+struct ChangedFile {
+    buffer_handle: BufHandle,
+    path: PathBuf,
+
+    //
+    // The buffer has unsaved changes
+    //
+    has_unsaved_changes: bool,
+}
+
+///
+/// Get back the file modified time
+///
+fn get_modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+///
+/// Take the snapshot of all loaded normal file buffers
+///
+pub fn take_open_files_snapshot() -> OpenFilesSnapshot {
+    let files = list_bufs()
+        .filter(|buffer| buffer.is_loaded())
+        .filter_map(|buffer| {
+            let opts = OptionOpts::builder().buffer(buffer.clone()).build();
+            let buffer_type = get_option_value::<NvimString>("buftype", &opts).ok()?;
+            if !buffer_type.is_empty() {
+                return None;
+            }
+
+            let path = buffer.get_name().ok()?;
+            if !path.is_file() {
+                return None;
+            }
+
+            Some(OpenFile {
+                buffer_handle: buffer.handle(),
+                modified_time: get_modified_time(&path),
+                path,
+            })
+        })
+        .collect();
+
+    OpenFilesSnapshot { files }
+}
+
+///
+/// Compare with the snapshot to get back the files changed on disk
+///
+fn get_changed_files(snapshot: &OpenFilesSnapshot) -> Vec<ChangedFile> {
+    snapshot
+        .files
+        .iter()
+        .filter(|file| Buffer::from(file.buffer_handle).is_loaded())
+        .filter(|file| {
+            file.modified_time.is_some() && get_modified_time(&file.path) != file.modified_time
+        })
+        .map(|file| {
+            let opts = OptionOpts::builder()
+                .buffer(Buffer::from(file.buffer_handle))
+                .build();
+
+            ChangedFile {
+                buffer_handle: file.buffer_handle,
+                path: file.path.clone(),
+                has_unsaved_changes: get_option_value::<bool>("modified", &opts).unwrap_or(false),
+            }
+        })
+        .collect()
+}
+
+///
+/// Reload the changed buffers, return the reloaded buffer count.
+///
+fn reload_changed_files(changed_files: &[ChangedFile], discard_unsaved_changes: bool) -> usize {
+    let mut reloaded_count = 0usize;
+
+    for file in changed_files {
+        if file.has_unsaved_changes && !discard_unsaved_changes {
+            continue;
+        }
+
+        let buffer = Buffer::from(file.buffer_handle);
+        if !buffer.is_loaded() {
+            continue;
+        }
+
+        let reload_result = buffer.call(|_| {
+            let _ = command("silent! edit!");
+        });
+
+        if reload_result.is_ok() {
+            reloaded_count += 1;
+        }
+    }
+
+    reloaded_count
+}
+
+///
+/// Show the confirm dialog of the files changed since the snapshot was taken, do nothing if
+/// there is no changed file.
+///
+pub fn check_changed_files(snapshot: &OpenFilesSnapshot, project_dir: &str) {
+    let changed_files = get_changed_files(snapshot);
+    if changed_files.is_empty() {
+        return;
+    }
+
+    let lines = changed_files
+        .iter()
+        .map(|file| {
+            let display_path = file
+                .path
+                .strip_prefix(project_dir)
+                .unwrap_or(&file.path)
+                .display()
+                .to_string();
+
+            if file.has_unsaved_changes {
+                format!("{display_path}  [unsaved changes]")
+            } else {
+                display_path
+            }
+        })
+        .collect::<Vec<String>>();
+
+    let choice = confirm(
+        &format!("{} file(s) changed on disk", changed_files.len()),
+        &lines,
+        &["&Reload", "Reload &all (discard unsaved)", "&Close"],
+        0,
+    );
+
+    let discard_unsaved_changes = match choice {
+        Some(0) => false,
+        Some(1) => true,
+        _ => return,
+    };
+
+    let reloaded_count = reload_changed_files(&changed_files, discard_unsaved_changes);
+    nvim::print!("[ file_change ] Reloaded {reloaded_count} buffer(s).");
+}
+
+use crate::picker::confirm;
+
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use nvim_oxi::{
+    self as nvim, BufHandle, String as NvimString,
+    api::{Buffer, command, get_option_value, list_bufs, opts::OptionOpts},
+};
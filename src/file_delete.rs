@@ -0,0 +1,142 @@
+//! Delete the current file without going through dired:
+//!
+//! - `<leader>df`: Confirm by a single keypress (`y`), delete the file backing the current
+//!   buffer, wipe the buffer and switch the window to the previous buffer.
+//!
+//! When `DELETE_FILE_TO_TRASH` (or `file_delete.to_trash` of the config file) is `true`, the
+//! file is moved to the dired trash directory instead, so it can still be restored from there.
+//!
+
+///
+/// Move the deleted file to the dired trash directory instead of removing it, it's the default
+/// of `file_delete.to_trash` of the config file.
+///
+const DELETE_FILE_TO_TRASH: bool = true;
+
+///
+/// Whether the deleted file goes to the trash
+///
+fn is_delete_to_trash() -> bool {
+    get_config_bool("file_delete.to_trash", DELETE_FILE_TO_TRASH)
+}
+
+///
+/// Delete (or move to the trash) the given file, return the trash path if it's moved to trash.
+///
+fn delete_file(file_path: &Path) -> std::io::Result<Option<String>> {
+    if is_delete_to_trash()
+        && let Some(trash_path) = file_path.to_str().and_then(get_dired_trash_path)
+    {
+        std::fs::rename(file_path, &trash_path)?;
+        return Ok(Some(trash_path));
+    }
+
+    std::fs::remove_file(file_path)?;
+    Ok(None)
+}
+
+///
+/// Switch the current window to the previous buffer (the alternate one, or the next listed
+/// one), or an empty buffer if the given buffer is the only one.
+///
+fn switch_away_from_buffer(buffer: &Buffer) {
+    let alternate_buffer_handle = call_function::<_, i32>("bufnr", ("#",)).unwrap_or(-1);
+    let is_alternate_listed = alternate_buffer_handle > 0
+        && alternate_buffer_handle != buffer.handle()
+        && call_function::<_, i64>("buflisted", (alternate_buffer_handle,)).unwrap_or(0) == 1;
+
+    if is_alternate_listed {
+        let _ = command(&format!("buffer {alternate_buffer_handle}"));
+        return;
+    }
+
+    let _ = command("silent! bnext");
+    if Buffer::current() == *buffer {
+        let _ = command("enew");
+    }
+}
+
+///
+/// Confirm and delete the current file
+///
+fn delete_current_file() {
+    const LOGGER_PREFIX: &'static str = "[ file_delete ]";
+
+    let buffer = Buffer::current();
+    let opts = OptionOpts::builder().buffer(buffer.clone()).build();
+
+    let buffer_type = get_option_value::<NvimString>("buftype", &opts).unwrap_or_default();
+    let file_path = match buffer.get_name() {
+        Ok(path) if buffer_type.is_empty() && path.is_file() => path,
+        _ => {
+            nvim::print!("{LOGGER_PREFIX} The current buffer is not a file.");
+            return;
+        }
+    };
+
+    let display_path = call_function::<_, String>("expand", ("%:~:.",)).unwrap_or_default();
+    let prompt = format!(
+        "Delete{} '{display_path}'?",
+        if is_delete_to_trash() {
+            " (to trash)"
+        } else {
+            ""
+        }
+    );
+
+    //
+    // `confirm()` accepts the single keypress of the choice shortcut, 2 (No) is the default one
+    //
+    let choice = call_function::<_, i64>("confirm", (prompt, "&Yes\n&No", 2)).unwrap_or(0);
+    if choice != 1 {
+        return;
+    }
+
+    match delete_file(&file_path) {
+        Ok(trash_path) => {
+            switch_away_from_buffer(&buffer);
+            let _ = command(&format!("silent! bwipeout! {}", buffer.handle()));
+
+            match trash_path {
+                Some(trash_path) => {
+                    nvim::print!("{LOGGER_PREFIX} Moved '{display_path}' to: {trash_path}")
+                }
+                None => nvim::print!("{LOGGER_PREFIX} Deleted: {display_path}"),
+            }
+        }
+        Err(error) => nvim::print!("{LOGGER_PREFIX} Failed to delete '{display_path}': {error}"),
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>df",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("'<leader>df': Delete the current file")
+            .silent(true)
+            .callback(|_| {
+                delete_current_file();
+                ()
+            })
+            .build(),
+    );
+}
+
+use crate::{config::get_config_bool, my_dired::get_dired_trash_path};
+
+use std::path::Path;
+
+use nvim_oxi::{
+    self as nvim, String as NvimString,
+    api::{
+        Buffer, call_function, command, get_option_value,
+        opts::{OptionOpts, SetKeymapOpts},
+        set_keymap,
+        types::Mode,
+    },
+};
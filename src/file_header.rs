@@ -0,0 +1,205 @@
+//! Insert the license/author header at the top of the file:
+//!
+//! - `:InsertHeader`: Insert the `FILE_HEADER_TEMPLATE` lines (commented by `commentstring`) at
+//!   the top of the current buffer, it's skipped if the buffer already has the header.
+//! - The header is inserted automatically into the new files (`BufNewFile`) of the
+//!   `FILE_HEADER_AUTO_FILETYPES`.
+//!
+//! The template placeholders:
+//!
+//! - `{filename}`: The file name, e.g. `main.rs`
+//! - `{date}`/`{year}`: The current date (`%Y-%m-%d`) and year
+//! - `{project}`: The project directory name
+//! - `{author}`: `git config user.name`, or `$USER` if it's not set
+//! - `{license}`: `FILE_HEADER_LICENSE`
+//!
+
+///
+/// The header lines (without the comment leader)
+///
+const FILE_HEADER_TEMPLATE: [&'static str; 5] = [
+    "{filename} - {project}",
+    "",
+    "Copyright (c) {year} {author}",
+    "SPDX-License-Identifier: {license}",
+    "Created: {date}",
+];
+
+///
+/// The license in the header
+///
+const FILE_HEADER_LICENSE: &'static str = "MIT";
+
+///
+/// The filetypes of the new files which get the header automatically, empty to disable it.
+///
+const FILE_HEADER_AUTO_FILETYPES: [&'static str; 4] = ["rust", "zig", "c", "cpp"];
+
+///
+/// Get back the author: `git config user.name`, or `$USER`
+///
+fn get_author() -> String {
+    if let cmd_utils::ExecuteCommandResult::Success { output, .. } =
+        cmd_utils::execute_command(vec!["git", "config", "user.name"])
+        && !output.trim().is_empty()
+    {
+        return output.trim().to_owned();
+    }
+
+    std::env::var("USER").unwrap_or_default()
+}
+
+///
+/// Render the template lines with the comment leader of the buffer `commentstring`
+///
+fn render_header_lines(buffer: &Buffer) -> Vec<String> {
+    let comment_string = get_option_value::<NvimString>(
+        "commentstring",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    )
+    .map(|value| value.to_string())
+    .ok()
+    .filter(|value| value.contains("%s"))
+    .unwrap_or_else(|| "# %s".to_string());
+
+    let filename = call_function::<_, String>("expand", ("%:t",)).unwrap_or_default();
+    let date = call_function::<_, String>("strftime", ("%Y-%m-%d",)).unwrap_or_default();
+    let year = call_function::<_, String>("strftime", ("%Y",)).unwrap_or_default();
+    let project = Path::new(&get_project_dir())
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let author = get_author();
+
+    FILE_HEADER_TEMPLATE
+        .iter()
+        .map(|line| {
+            let text = line
+                .replace("{filename}", &filename)
+                .replace("{date}", &date)
+                .replace("{year}", &year)
+                .replace("{project}", &project)
+                .replace("{author}", &author)
+                .replace("{license}", FILE_HEADER_LICENSE);
+
+            //
+            // No trailing whitespace for the empty line, e.g. "//" instead of "// "
+            //
+            comment_string
+                .replacen("%s", &text, 1)
+                .trim_end()
+                .to_owned()
+        })
+        .collect()
+}
+
+///
+/// Insert the header at the top of the buffer, it returns `false` if the buffer already has it.
+///
+fn insert_header(mut buffer: Buffer) -> bool {
+    let header_lines = render_header_lines(&buffer);
+    let Some(first_header_line) = header_lines.first() else {
+        return false;
+    };
+
+    let has_header = buffer
+        .get_lines(0..1, false)
+        .ok()
+        .and_then(|mut lines| lines.next())
+        .is_some_and(|line| line.to_string() == *first_header_line);
+    if has_header {
+        return false;
+    }
+
+    //
+    // Keep the empty line between the header and the content, the new file only has one empty
+    // line.
+    //
+    let is_empty_buffer = buffer.line_count().unwrap_or(0) <= 1
+        && buffer
+            .get_lines(0..1, false)
+            .ok()
+            .and_then(|mut lines| lines.next())
+            .is_none_or(|line| line.to_string().is_empty());
+
+    let mut lines = header_lines;
+    lines.push(String::new());
+    let replaced_range = if is_empty_buffer { 0..1 } else { 0..0 };
+    let _ = buffer.set_lines(replaced_range, false, lines);
+
+    true
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_user_command(
+        "InsertHeader",
+        |_: CommandArgs| {
+            if !insert_header(Buffer::current()) {
+                nvim::print!("[ file_header ] The header already exists.");
+            }
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Insert the license/author header at the top of the file")
+            .build(),
+    );
+
+    if FILE_HEADER_AUTO_FILETYPES.is_empty() {
+        return;
+    }
+
+    let _ = create_autocmd(
+        vec!["BufNewFile"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-file-header-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                //
+                // The `filetype` option may be not set yet, as this autocmd can run before the
+                // runtime `filetypedetect` one, detect it by the file name.
+                //
+                let filetype = call_function::<_, String>(
+                    "luaeval",
+                    (
+                        "vim.filetype.match({ buf = _A }) or ''",
+                        args.buffer.handle(),
+                    ),
+                )
+                .unwrap_or_default();
+
+                if FILE_HEADER_AUTO_FILETYPES.contains(&filetype.as_str()) {
+                    let _ = insert_header(args.buffer);
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+use crate::project_command::get_project_dir;
+
+use rust_utils::cmd as cmd_utils;
+
+use std::path::Path;
+
+use nvim_oxi::{
+    self as nvim, String as NvimString,
+    api::{
+        Buffer, call_function, create_augroup, create_autocmd, create_user_command,
+        get_option_value,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, CreateCommandOpts, OptionOpts},
+        types::{AutocmdCallbackArgs, CommandArgs},
+    },
+};
@@ -0,0 +1,151 @@
+//! Copy the current file path to the clipboard, the buffer-side counterpart of the dired `w` and
+//! `W`:
+//!
+//! - `<leader>yp`: The path relative to the project directory, e.g. `src/picker/picker.rs`.
+//! - `<leader>yP`: The absolute path.
+//! - `<leader>yl`: The `path:line` reference of the cursor line, e.g. `src/lib.rs:42`, or the
+//!   `path:start-end` of the `VISUAL` lines.
+//!
+//! The file outside of the project directory falls back to the path relative to the current
+//! working directory (or the home directory).
+//!
+
+///
+/// The path format to copy
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FilePathCopyKind {
+    ProjectRelative,
+    Absolute,
+    LineReference,
+}
+
+///
+/// Get back the path of the current buffer, `None` if the buffer doesn't have a file.
+///
+fn get_file_path(is_absolute: bool) -> Option<String> {
+    let file_path = Buffer::current().get_name().ok()?;
+    if file_path.as_os_str().is_empty() {
+        return None;
+    }
+    if is_absolute {
+        return Some(file_path.display().to_string());
+    }
+
+    let project_dir = get_project_dir();
+    if !project_dir.is_empty()
+        && let Ok(relative_path) = file_path.strip_prefix(Path::new(&project_dir))
+        && !relative_path.as_os_str().is_empty()
+    {
+        return Some(relative_path.display().to_string());
+    }
+
+    call_function::<_, String>("fnamemodify", (file_path.display().to_string(), ":~:.")).ok()
+}
+
+///
+/// Get back the line range of the `VISUAL` selection, or the cursor line.
+///
+fn get_line_range() -> (usize, usize) {
+    let cursor_line = call_function::<_, usize>("line", (".",)).unwrap_or(1);
+    let mode = call_function::<_, String>("mode", ()).unwrap_or_default();
+    if !matches!(mode.as_str(), "v" | "V" | "\x16") {
+        return (cursor_line, cursor_line);
+    }
+
+    //
+    // The `'<` and `'>` marks are only updated after leaving the `VISUAL` mode, `line("v")` is
+    // the other end of the selection.
+    //
+    let visual_line = call_function::<_, usize>("line", ("v",)).unwrap_or(cursor_line);
+    (visual_line.min(cursor_line), visual_line.max(cursor_line))
+}
+
+///
+/// Copy the path to the clipboard register and then show what's copied.
+///
+fn copy_file_path(kind: FilePathCopyKind) {
+    let Some(file_path) = get_file_path(kind == FilePathCopyKind::Absolute) else {
+        echo_message(
+            "file_path_copy",
+            "No file in the current buffer.",
+            EchoLevel::Info,
+        );
+        return;
+    };
+
+    let text = match kind {
+        FilePathCopyKind::LineReference => match get_line_range() {
+            (start, end) if start == end => format!("{file_path}:{start}"),
+            (start, end) => format!("{file_path}:{start}-{end}"),
+        },
+        _ => file_path,
+    };
+
+    let register = get_clipboard_register();
+    if set_register(register, &RegisterContent::charwise(&text)) {
+        echo_message(
+            "file_path_copy",
+            &format!("Copied to the '{register}' register: {text}"),
+            EchoLevel::Success,
+        );
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    let keymaps: Vec<(Mode, &str, &str, FilePathCopyKind)> = vec![
+        (
+            Mode::Normal,
+            "<leader>yp",
+            "'<leader>yp': Copy the project-relative file path",
+            FilePathCopyKind::ProjectRelative,
+        ),
+        (
+            Mode::Normal,
+            "<leader>yP",
+            "'<leader>yP': Copy the absolute file path",
+            FilePathCopyKind::Absolute,
+        ),
+        (
+            Mode::Normal,
+            "<leader>yl",
+            "'<leader>yl': Copy the 'path:line' reference",
+            FilePathCopyKind::LineReference,
+        ),
+        (
+            Mode::Visual,
+            "<leader>yl",
+            "'<leader>yl': Copy the 'path:start-end' reference",
+            FilePathCopyKind::LineReference,
+        ),
+    ];
+
+    for (mode, key, desc, kind) in keymaps {
+        let _ = set_keymap(
+            mode,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(desc)
+                .silent(true)
+                .callback(move |_| {
+                    copy_file_path(kind);
+                    ()
+                })
+                .build(),
+        );
+    }
+}
+
+use crate::{
+    extended_api::{EchoLevel, RegisterContent, echo_message, set_register},
+    project_command::get_project_dir,
+    utils::get_clipboard_register,
+};
+
+use std::path::Path;
+
+use nvim_oxi::api::{Buffer, call_function, opts::SetKeymapOpts, set_keymap, types::Mode};
@@ -0,0 +1,189 @@
+//! Rename/move the current file without going through dired:
+//!
+//! - `<leader>rf`: Prompt (with path completion) for the new path of the current file, rename
+//!   it on disk (the missing parent directories are created), and then update the buffer name.
+//!
+//! The old file name doesn't stay as the alternate file (`#`), and the LSP clients which support
+//! the file operations get `workspace/willRenameFiles` (the returned edits are applied) and
+//! `workspace/didRenameFiles`.
+//!
+
+///
+/// Set to `false` to skip the LSP `willRenameFiles`/`didRenameFiles` requests
+///
+const NOTIFY_LSP_ON_RENAME: bool = true;
+
+///
+/// Send `workspace/willRenameFiles` (before renaming) or `workspace/didRenameFiles` (after
+/// renaming) to the LSP clients which support it.
+///
+fn notify_lsp_file_rename(old_path: &str, new_path: &str, is_will_rename: bool) {
+    let lua_code = r#"(function(old_path, new_path, is_will_rename)
+    local params = {
+        files = { { oldUri = vim.uri_from_fname(old_path), newUri = vim.uri_from_fname(new_path) } },
+    }
+    local capability = is_will_rename and "willRename" or "didRename"
+    for _, client in ipairs(vim.lsp.get_clients()) do
+        if vim.tbl_get(client.server_capabilities, "workspace", "fileOperations", capability) then
+            if is_will_rename then
+                local response = client:request_sync("workspace/willRenameFiles", params, 1000, 0)
+                if response and response.result then
+                    vim.lsp.util.apply_workspace_edit(response.result, client.offset_encoding)
+                end
+            else
+                client:notify("workspace/didRenameFiles", params)
+            end
+        end
+    end
+end)(_A[1], _A[2], _A[3])"#;
+
+    let _ = call_function::<_, Object>(
+        "luaeval",
+        (
+            lua_code,
+            Array::from_iter([
+                Object::from(old_path),
+                Object::from(new_path),
+                Object::from(is_will_rename),
+            ]),
+        ),
+    );
+}
+
+///
+/// Rename the current file to the given path
+///
+fn rename_current_file(new_path: &str) {
+    const LOGGER_PREFIX: &'static str = "[ file_rename ]";
+
+    let mut buffer = Buffer::current();
+    let Ok(old_path) = buffer.get_name() else {
+        return;
+    };
+
+    let new_path = match call_function::<_, String>("fnamemodify", (new_path, ":p")) {
+        Ok(full_path) if !full_path.is_empty() => PathBuf::from(full_path),
+        _ => return,
+    };
+
+    if new_path == old_path {
+        return;
+    }
+
+    if new_path.exists() {
+        nvim::print!("{LOGGER_PREFIX} Already exists: {}", new_path.display());
+        return;
+    }
+
+    if let Some(parent_dir) = new_path.parent() {
+        if let Err(error) = std::fs::create_dir_all(parent_dir) {
+            nvim::print!(
+                "{LOGGER_PREFIX} Failed to create '{}': {error}",
+                parent_dir.display()
+            );
+            return;
+        }
+    }
+
+    let (old_path_str, new_path_str) = (
+        old_path.to_string_lossy().to_string(),
+        new_path.to_string_lossy().to_string(),
+    );
+
+    if NOTIFY_LSP_ON_RENAME {
+        notify_lsp_file_rename(&old_path_str, &new_path_str, true);
+    }
+
+    if let Err(error) = std::fs::rename(&old_path, &new_path) {
+        nvim::print!("{LOGGER_PREFIX} Failed to rename: {error}");
+        return;
+    }
+
+    //
+    // Renaming the buffer creates the unlisted buffer of the old name as the alternate file,
+    // wipe it out, as that file doesn't exist anymore.
+    //
+    let _ = buffer.set_name(&new_path);
+    if let Ok(old_buffer_handle) = call_function::<_, i32>("bufnr", (old_path_str.as_str(),)) {
+        if old_buffer_handle > 0 && old_buffer_handle != buffer.handle() {
+            let _ = command(&format!("silent! bwipeout {old_buffer_handle}"));
+        }
+    }
+
+    //
+    // Write it to the new file, otherwise the buffer is marked as "not edited" and the next
+    // `:w` fails.
+    //
+    let _ = command("silent! write!");
+
+    if NOTIFY_LSP_ON_RENAME {
+        notify_lsp_file_rename(&old_path_str, &new_path_str, false);
+    }
+
+    nvim::print!("{LOGGER_PREFIX} Renamed to: {new_path_str}");
+}
+
+///
+/// Prompt for the new path of the current file
+///
+fn prompt_file_rename() {
+    const LOGGER_PREFIX: &'static str = "[ file_rename ]";
+
+    let buffer = Buffer::current();
+    let opts = OptionOpts::builder().buffer(buffer.clone()).build();
+
+    let buffer_type = get_option_value::<NvimString>("buftype", &opts).unwrap_or_default();
+    let is_file = buffer.get_name().is_ok_and(|path| path.is_file());
+    if !buffer_type.is_empty() || !is_file {
+        nvim::print!("{LOGGER_PREFIX} The current buffer is not a file.");
+        return;
+    }
+
+    if get_option_value::<bool>("modified", &opts).unwrap_or(false) {
+        nvim::print!("{LOGGER_PREFIX} Save the changes before renaming.");
+        return;
+    }
+
+    let current_path = call_function::<_, String>("expand", ("%:~:.",)).unwrap_or_default();
+    let new_path = prompt("Rename to: ", &current_path, Some("file")).unwrap_or_default();
+
+    let new_path = new_path.trim();
+    if new_path.is_empty() || new_path == current_path {
+        return;
+    }
+
+    rename_current_file(new_path);
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>rf",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("'<leader>rf': Rename/move the current file")
+            .silent(true)
+            .callback(|_| {
+                prompt_file_rename();
+                ()
+            })
+            .build(),
+    );
+}
+
+use crate::extended_api::prompt;
+
+use std::path::PathBuf;
+
+use nvim_oxi::{
+    self as nvim, Array, Object, String as NvimString,
+    api::{
+        Buffer, call_function, command, get_option_value,
+        opts::{OptionOpts, SetKeymapOpts},
+        set_keymap,
+        types::Mode,
+    },
+};
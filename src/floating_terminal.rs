@@ -0,0 +1,281 @@
+//! Floating terminal manager:
+//!
+//! - `<leader>ft`: Show/hide the default floating terminal (instance 1).
+//! - `<leader>f1..f3`: Show/hide the numbered floating terminal instance.
+//!
+//! The terminal buffer stays alive when the window is hidden, so the next toggle shows the same
+//! shell session (with its scrollback) instead of spawning a new one. Each instance remembers the
+//! working directory it was created in, it's used to respawn the shell after it exits.
+//!
+//! Each tab page has its own instances (see `tab_scope`), the instances of the closed tab page
+//! are wiped out.
+//!
+//! In `TERMINAL` mode, press `<C-\><C-n>` first and then the toggle key to hide it.
+//!
+
+///
+/// Floating terminal instance
+///
+#[derive(Debug, Default, Clone)]
+struct FloatingTerminal {
+    buffer_handle: Option<BufHandle>,
+    window: Option<PopupWindow>,
+
+    //
+    // The working directory when the terminal was created
+    //
+    cwd: Option<String>,
+}
+
+///
+/// Private module-scope state: all floating terminal instances (by tab scope and instance
+/// number)
+///
+static FLOATING_TERMINAL_STATE: LazyLock<Mutex<HashMap<(i32, u32), FloatingTerminal>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+///
+/// The floating terminal popup window options
+///
+fn get_floating_terminal_window_options(
+    buffer: Option<BufHandle>,
+    title: &str,
+) -> PopupWindowOptions {
+    PopupWindowOptions {
+        border: WindowBorder::Rounded,
+        window_width_ratio: Some(0.7),
+        window_height_ratio: Some(0.7),
+        auto_width: false,
+        auto_height: false,
+        buffer,
+        title: Some(title.to_string()),
+        ..Default::default()
+    }
+}
+
+///
+/// Run the `:terminal` command inside the current (popup) window
+///
+fn run_terminal_command(cmd: Option<&str>) {
+    let command = "terminal";
+    let infos = match cmd {
+        Some(cmd) => CmdInfos::builder().cmd(command).args([cmd]).build(),
+        None => CmdInfos::builder().cmd(command).build(),
+    };
+    let opts = CmdOpts::builder().output(false).build();
+    let _ = vim_cmd(&infos, &opts);
+}
+
+///
+/// Hide all visible floating terminal windows, the terminal buffers keep running.
+///
+fn hide_all_floating_terminals() {
+    for terminal in FLOATING_TERMINAL_STATE.lock().unwrap().values_mut() {
+        if let Some(window) = terminal.window.take() {
+            window.close();
+        }
+    }
+}
+
+///
+/// Show/hide the given floating terminal instance, create it if it doesn't exist yet (or the
+/// shell has exited and the buffer has been deleted).
+///
+pub fn toggle_floating_terminal(instance: u32) {
+    #[cfg(feature = "enable_floating_terminal_debug_print")]
+    const LOGGER_PREFIX: &'static str = "[ floating_terminal - toggle_floating_terminal ]";
+
+    let key = (current_tab_scope(), instance);
+    let terminal = FLOATING_TERMINAL_STATE
+        .lock()
+        .unwrap()
+        .get(&key)
+        .cloned()
+        .unwrap_or_default();
+
+    //
+    // It's visible, hide it
+    //
+    if let Some(window) = terminal.window
+        && window.is_valid()
+    {
+        window.close();
+        if let Some(terminal) = FLOATING_TERMINAL_STATE.lock().unwrap().get_mut(&key) {
+            terminal.window = None;
+        }
+        return;
+    }
+
+    //
+    // Only one floating terminal is visible at a time
+    //
+    hide_all_floating_terminals();
+
+    let alive_buffer_handle = terminal
+        .buffer_handle
+        .filter(|handle| Buffer::from(*handle).is_loaded());
+
+    let cwd = terminal
+        .cwd
+        .clone()
+        .or_else(|| call_function::<_, String>("getcwd", ()).ok());
+
+    let title = format!(
+        " Terminal {instance}: {} ",
+        cwd.as_deref().unwrap_or_default()
+    );
+
+    let Some(window) = create_popup_window(&get_floating_terminal_window_options(
+        alive_buffer_handle,
+        &title,
+    )) else {
+        return;
+    };
+
+    let buffer_handle = match alive_buffer_handle {
+        Some(handle) => handle,
+
+        //
+        // Spawn the shell in the remembered working directory (window-local, so it doesn't
+        // affect other windows).
+        //
+        None => {
+            if let Some(cwd) = &cwd {
+                let infos = CmdInfos::builder().cmd("lcd").args([cwd.as_str()]).build();
+                let _ = vim_cmd(&infos, &CmdOpts::builder().output(false).build());
+            }
+
+            run_terminal_command(None);
+
+            //
+            // Keep the terminal buffer (and the shell process) alive when the window is closed
+            //
+            let terminal_buffer = Buffer::current();
+            let opts = OptionOpts::builder()
+                .buffer(terminal_buffer.clone())
+                .build();
+            let _ = set_option_value("bufhidden", "hide", &opts);
+
+            terminal_buffer.handle()
+        }
+    };
+
+    #[cfg(feature = "enable_floating_terminal_debug_print")]
+    nvim::print!(
+        "\n>>> {LOGGER_PREFIX} instance: {instance}, buffer: {buffer_handle}, cwd: {cwd:?}"
+    );
+
+    FLOATING_TERMINAL_STATE.lock().unwrap().insert(
+        key,
+        FloatingTerminal {
+            buffer_handle: Some(buffer_handle),
+            window: Some(window),
+            cwd,
+        },
+    );
+
+    let _ = command("startinsert");
+}
+
+///
+/// Wipe out the floating terminals (and their shell processes) of the closed tab pages
+///
+pub fn wipe_closed_tab_floating_terminals() {
+    let closed_terminals = {
+        let mut locked_state = FLOATING_TERMINAL_STATE.lock().unwrap();
+        let closed_keys = locked_state
+            .keys()
+            .filter(|(scope, _)| !is_tab_scope_alive(*scope))
+            .copied()
+            .collect::<Vec<(i32, u32)>>();
+
+        closed_keys
+            .iter()
+            .filter_map(|key| locked_state.remove(key))
+            .collect::<Vec<FloatingTerminal>>()
+    };
+
+    for terminal in closed_terminals {
+        if let Some(handle) = terminal.buffer_handle
+            && Buffer::from(handle).is_valid()
+        {
+            let _ = command(&format!("silent! bwipeout! {handle}"));
+        }
+    }
+}
+
+///
+/// Run the given command in a centred floating terminal window, it's a one-off terminal which
+/// doesn't belong to any instance.
+///
+pub fn run_command_in_floating_terminal(cmd: &str) {
+    let _ = create_popup_window(&get_floating_terminal_window_options(None, " Terminal "));
+    run_terminal_command(Some(cmd));
+}
+
+///
+///
+///
+pub fn setup() {
+    let key_bindings: Vec<(&str, &str, Box<dyn Fn()>)> = vec![
+        (
+            "<leader>ft",
+            "'<leader>ft': Toggle the floating terminal.",
+            Box::new(|| toggle_floating_terminal(1)),
+        ),
+        (
+            "<leader>f1",
+            "'<leader>f1': Toggle the floating terminal 1.",
+            Box::new(|| toggle_floating_terminal(1)),
+        ),
+        (
+            "<leader>f2",
+            "'<leader>f2': Toggle the floating terminal 2.",
+            Box::new(|| toggle_floating_terminal(2)),
+        ),
+        (
+            "<leader>f3",
+            "'<leader>f3': Toggle the floating terminal 3.",
+            Box::new(|| toggle_floating_terminal(3)),
+        ),
+    ];
+
+    for bindings in key_bindings {
+        let _ = set_keymap(
+            Mode::Normal,
+            bindings.0,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(bindings.1)
+                .callback(move |_| {
+                    bindings.2();
+                    ()
+                })
+                .silent(true)
+                .build(),
+        );
+    }
+}
+
+use crate::{
+    picker::{PopupWindow, PopupWindowOptions, create_popup_window},
+    tab_scope::{current_tab_scope, is_tab_scope_alive},
+};
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+#[cfg(feature = "enable_floating_terminal_debug_print")]
+use nvim_oxi as nvim;
+
+use nvim_oxi::{
+    BufHandle,
+    api::{
+        Buffer, call_function, cmd as vim_cmd, command,
+        opts::{CmdOpts, OptionOpts, SetKeymapOpts},
+        set_keymap, set_option_value,
+        types::{CmdInfos, Mode, WindowBorder},
+    },
+};
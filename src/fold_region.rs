@@ -0,0 +1,353 @@
+//! Comment-based folding regions, the same markers across all filetypes:
+//!
+//! ```rust
+//! // #region Keybindings
+//! ...
+//! // #endregion
+//! ```
+//!
+//! - The marker is the comment of the buffer `commentstring` with `#region [name]` and
+//!   `#endregion` (the `#` is optional), e.g. `-- #region`, `<!-- #region -->`, and the bare
+//!   `#region` of C#. The regions can be nested.
+//! - The window of the buffer with the markers uses the Rust `foldexpr` automatically (if it
+//!   doesn't fold by another `foldmethod`), only the regions are folded.
+//! - `:FoldRegions`: Toggle the region folding of the current window.
+//! - `:[range]FoldRegionWrap [name]`: Wrap the lines (e.g. the `VISUAL` selection) in a new
+//!   region, the name is prompted if it's not given.
+//!
+//! The fold levels of the whole buffer are computed once per `b:changedtick`, the `foldexpr` of
+//! each line only looks them up.
+//!
+
+///
+/// The Lua global function name of the `foldexpr`
+///
+const FOLD_REGION_EXPR_FUNCTION: &'static str = "MyConfigFoldRegionExpr";
+
+///
+/// The region marker of the line
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FoldRegionMarker {
+    Start,
+    End,
+}
+
+///
+/// Private module-scope state: the fold levels (`foldexpr` results) of the buffer and the
+/// `b:changedtick` when they're computed.
+///
+static FOLD_REGION_LEVELS: LazyLock<Mutex<HashMap<BufHandle, (usize, Vec<String>)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+///
+/// Get back the comment leader and trailer of the buffer `commentstring`, e.g. `("<!--", "-->")`
+///
+fn get_comment_delimiters(buffer: &Buffer) -> (String, String) {
+    let comment_string = get_option_value::<NvimString>(
+        "commentstring",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    )
+    .map(|value| value.to_string())
+    .unwrap_or_default();
+
+    match comment_string.split_once("%s") {
+        Some((leader, trailer)) => (leader.trim().to_owned(), trailer.trim().to_owned()),
+        None => (String::new(), String::new()),
+    }
+}
+
+///
+/// Parse the region marker of the line, the comment leader is optional, so the bare `#region` of
+/// C# (or the one in the buffer without `commentstring`) works as well.
+///
+fn parse_region_marker(line: &str, leader: &str, trailer: &str) -> Option<FoldRegionMarker> {
+    let mut content = line.trim();
+    if !leader.is_empty()
+        && let Some(rest) = content.strip_prefix(leader)
+    {
+        content = rest.trim_start_matches(|c: char| leader.contains(c) || c == '!');
+    }
+    if !trailer.is_empty()
+        && let Some(rest) = content.strip_suffix(trailer)
+    {
+        content = rest;
+    }
+
+    let content = content.trim();
+    let content = content.strip_prefix('#').unwrap_or(content);
+    let (keyword, rest) = content
+        .split_once(char::is_whitespace)
+        .unwrap_or((content, ""));
+
+    //
+    // `region` must be followed by the name (or nothing), so the comment like
+    // `// region-based allocator` isn't a marker.
+    //
+    match keyword {
+        "region" => Some(FoldRegionMarker::Start),
+        "endregion" if rest.trim().is_empty() => Some(FoldRegionMarker::End),
+        _ => None,
+    }
+}
+
+///
+/// Compute the `foldexpr` results of the lines, the unmatched `#endregion` is ignored.
+///
+fn compute_fold_levels(buffer: &Buffer) -> Vec<String> {
+    let (leader, trailer) = get_comment_delimiters(buffer);
+    let Ok(lines) = buffer.get_lines(.., false) else {
+        return Vec::new();
+    };
+
+    let mut depth = 0usize;
+    lines
+        .map(
+            |line| match parse_region_marker(&line.to_string(), &leader, &trailer) {
+                Some(FoldRegionMarker::Start) => {
+                    depth += 1;
+                    format!(">{depth}")
+                }
+                Some(FoldRegionMarker::End) if depth > 0 => {
+                    depth -= 1;
+                    format!("<{}", depth + 1)
+                }
+                _ => depth.to_string(),
+            },
+        )
+        .collect()
+}
+
+///
+/// The `foldexpr`: the fold level of the line (1-based) in the current buffer
+///
+fn fold_expr(lnum: usize) -> String {
+    let buffer = Buffer::current();
+    let changedtick = buffer.get_changedtick().unwrap_or(0);
+
+    let mut locked_levels = FOLD_REGION_LEVELS.lock().unwrap();
+    let is_stale = locked_levels
+        .get(&buffer.handle())
+        .is_none_or(|(computed_tick, _)| *computed_tick != changedtick);
+    if is_stale {
+        let levels = compute_fold_levels(&buffer);
+        locked_levels.insert(buffer.handle(), (changedtick, levels));
+    }
+
+    locked_levels
+        .get(&buffer.handle())
+        .and_then(|(_, levels)| levels.get(lnum.saturating_sub(1)).cloned())
+        .unwrap_or_else(|| "0".to_string())
+}
+
+///
+/// Whether the buffer has any region marker
+///
+fn has_region_markers(buffer: &Buffer) -> bool {
+    compute_fold_levels(buffer)
+        .iter()
+        .any(|level| level.starts_with('>'))
+}
+
+///
+/// Whether the window folds by the region `foldexpr`
+///
+fn is_fold_region_enabled(window: &Window) -> bool {
+    let opts = OptionOpts::builder().win(window.clone()).build();
+    let fold_method = get_option_value::<NvimString>("foldmethod", &opts).unwrap_or_default();
+    let fold_expr = get_option_value::<NvimString>("foldexpr", &opts).unwrap_or_default();
+
+    fold_method == "expr" && fold_expr.to_string().contains(FOLD_REGION_EXPR_FUNCTION)
+}
+
+///
+/// Fold the window by the regions (`enable`), or go back to the manual folding.
+///
+fn set_fold_region(window: &Window, enable: bool) {
+    let opts = OptionOpts::builder()
+        .win(window.clone())
+        .scope(OptionScope::Local)
+        .build();
+
+    if enable {
+        let _ = set_option_value(
+            "foldexpr",
+            format!("v:lua.{FOLD_REGION_EXPR_FUNCTION}(v:lnum)"),
+            &opts,
+        );
+        let _ = set_option_value("foldmethod", "expr", &opts);
+    } else {
+        let _ = set_option_value("foldmethod", "manual", &opts);
+        let _ = command("normal! zE");
+    }
+}
+
+///
+/// `:FoldRegions`: Toggle the region folding of the current window
+///
+fn toggle_fold_region() {
+    let window = Window::current();
+    let enable = !is_fold_region_enabled(&window);
+    set_fold_region(&window, enable);
+
+    echo_message(
+        "fold_region",
+        if enable { "On." } else { "Off." },
+        EchoLevel::Info,
+    );
+}
+
+///
+/// `:[range]FoldRegionWrap [name]`: Wrap the lines in the new region, the markers have the same
+/// indent as the first line.
+///
+fn wrap_in_region(args: UserCommandArgs) {
+    let Some((line1, line2)) = args.range else {
+        return;
+    };
+
+    let name = match args.joined().trim() {
+        "" => match prompt("Region name: ", "", None) {
+            Some(name) => name.trim().to_owned(),
+            None => return,
+        },
+        name => name.to_owned(),
+    };
+
+    let mut buffer = Buffer::current();
+    let indent = buffer
+        .get_lines(line1 - 1..line1, false)
+        .ok()
+        .and_then(|mut lines| lines.next())
+        .map(|line| {
+            let line = line.to_string();
+            line[..line.len() - line.trim_start().len()].to_owned()
+        })
+        .unwrap_or_default();
+
+    let (leader, trailer) = get_comment_delimiters(&buffer);
+    let render_marker = |marker: &str| {
+        let mut text = format!("{indent}{leader}");
+        if !leader.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(marker);
+        if !trailer.is_empty() {
+            text.push(' ');
+            text.push_str(&trailer);
+        }
+        text
+    };
+    let start_marker = if name.is_empty() {
+        render_marker("#region")
+    } else {
+        render_marker(&format!("#region {name}"))
+    };
+    let end_marker = render_marker("#endregion");
+
+    //
+    // Insert the end marker first, so the start line number is still valid.
+    //
+    let _ = buffer.set_lines(line2..line2, true, [end_marker.as_str()]);
+    let _ = buffer.set_lines(line1 - 1..line1 - 1, true, [start_marker.as_str()]);
+
+    let window = Window::current();
+    if !is_fold_region_enabled(&window) {
+        set_fold_region(&window, true);
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    //
+    // Expose the `foldexpr` as the Lua global function, so `v:lua` can call it.
+    //
+    let _ = exec_lua::<_, Object>(
+        "local name, f = ...; _G[name] = f",
+        (
+            FOLD_REGION_EXPR_FUNCTION,
+            Function::<i64, String>::from_fn(|lnum: i64| fold_expr(lnum.max(1) as usize)),
+        ),
+    );
+
+    let _ = create_autocmd(
+        vec!["BufWinEnter", "BufWipeout"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-fold-region-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                if args.event == "BufWipeout" {
+                    FOLD_REGION_LEVELS
+                        .lock()
+                        .unwrap()
+                        .remove(&args.buffer.handle());
+                } else {
+                    //
+                    // Don't override the other folding (e.g. `foldmethod=marker` by the modeline)
+                    //
+                    let window = Window::current();
+                    let fold_method = get_option_value::<NvimString>(
+                        "foldmethod",
+                        &OptionOpts::builder().win(window.clone()).build(),
+                    )
+                    .unwrap_or_default();
+                    if fold_method == "manual" && has_region_markers(&args.buffer) {
+                        set_fold_region(&window, true);
+                    }
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    register_user_command(
+        "FoldRegions",
+        UserCommandSpec {
+            desc: "Toggle the comment-based region folding of the current window",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| toggle_fold_region(),
+    );
+
+    register_user_command(
+        "FoldRegionWrap",
+        UserCommandSpec {
+            desc: "Wrap the lines in a new named fold region",
+            nargs: CommandNArgs::Any,
+            range: true,
+            ..Default::default()
+        },
+        wrap_in_region,
+    );
+}
+
+use crate::extended_api::{
+    EchoLevel, UserCommandArgs, UserCommandSpec, echo_message, exec_lua, prompt,
+    register_user_command,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    BufHandle, Function, Object, String as NvimString,
+    api::{
+        Buffer, Window, command, create_augroup, create_autocmd, get_option_value,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, OptionScope},
+        set_option_value,
+        types::{AutocmdCallbackArgs, CommandNArgs},
+    },
+};
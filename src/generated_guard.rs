@@ -0,0 +1,216 @@
+//! Guard rails for the generated files, e.g. `target/debug/build/.../out/bindings.rs` opened by
+//! jumping from the compiler error:
+//!
+//! - The file under any of `GENERATED_DIRS` is `readonly` and not `modifiable`, with the warning
+//!   banner above the first line.
+//! - `<leader>ge` (only in the guarded buffer) or `:GeneratedEdit`: Edit it anyway, the override
+//!   stays with the buffer (e.g. after `:edit`).
+//!
+//! For the file under the project directory, only the path inside the project is checked, so
+//! the project itself can live in the `build` directory.
+//!
+
+///
+/// Define your generated directory names here, the file is guarded if any directory of its path
+/// is one of them.
+///
+const GENERATED_DIRS: [&'static str; 10] = [
+    "target",
+    "node_modules",
+    "dist",
+    "build",
+    "zig-out",
+    ".zig-cache",
+    "__pycache__",
+    ".venv",
+    ".next",
+    ".cargo",
+];
+
+///
+/// The namespace of the banner
+///
+const GENERATED_GUARD_NAMESPACE: &'static str = "generated_guard";
+
+///
+/// The buffer variable of the override
+///
+const GENERATED_GUARD_OVERRIDE_FLAG: &'static str = "generated_guard_override";
+
+///
+/// Get back the generated directory of the file, `None` if it isn't a generated file.
+///
+fn get_generated_dir(file_path: &Path) -> Option<&'static str> {
+    let project_dir = get_project_dir();
+    let checked_path = if project_dir.is_empty() {
+        file_path
+    } else {
+        file_path
+            .strip_prefix(Path::new(&project_dir))
+            .unwrap_or(file_path)
+    };
+
+    //
+    // The last component is the file name itself
+    //
+    let dir_count = checked_path.components().count().saturating_sub(1);
+    checked_path
+        .components()
+        .take(dir_count)
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .find_map(|name| GENERATED_DIRS.iter().find(|dir| **dir == name).copied())
+}
+
+///
+/// Show the banner as the virtual line above the first line
+///
+fn show_generated_banner(buffer: &mut Buffer, generated_dir: &str) {
+    let namespace_id = create_namespace(GENERATED_GUARD_NAMESPACE);
+    let _ = buffer.clear_namespace(namespace_id, ..);
+
+    let message = format!(
+        " Generated file (under '{generated_dir}/'), it's readonly. Press <leader>ge to edit it anyway. "
+    );
+    let _ = buffer.set_extmark(
+        namespace_id,
+        0,
+        0,
+        &SetExtmarkOpts::builder()
+            .virt_lines([[(message.as_str(), "WarningMsg")]])
+            .virt_lines_above(true)
+            .build(),
+    );
+}
+
+///
+/// `<leader>ge`: Make the guarded buffer editable
+///
+fn override_generated_guard() {
+    let mut buffer = Buffer::current();
+    let _ = buffer.set_var(GENERATED_GUARD_OVERRIDE_FLAG, true);
+
+    let opts = OptionOpts::builder().buffer(buffer.clone()).build();
+    let _ = set_option_value("readonly", false, &opts);
+    let _ = set_option_value("modifiable", true, &opts);
+    let _ = buffer.clear_namespace(create_namespace(GENERATED_GUARD_NAMESPACE), ..);
+
+    echo_message(
+        "generated_guard",
+        "The generated file is editable now.",
+        EchoLevel::Warning,
+    );
+}
+
+///
+/// Get back the generated directory of the buffer file, `None` if the buffer isn't a generated
+/// file or it's overridden.
+///
+fn get_guarded_dir(buffer: &Buffer) -> Option<&'static str> {
+    let buffer_type = get_option_value::<NvimString>(
+        "buftype",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    )
+    .unwrap_or_default();
+    if !buffer_type.is_empty()
+        || buffer
+            .get_var::<bool>(GENERATED_GUARD_OVERRIDE_FLAG)
+            .is_ok()
+    {
+        return None;
+    }
+
+    get_generated_dir(&buffer.get_name().ok()?)
+}
+
+///
+/// Guard the buffer if it's a generated file and not overridden
+///
+fn guard_generated_buffer(buffer: &mut Buffer) {
+    let Some(generated_dir) = get_guarded_dir(buffer) else {
+        return;
+    };
+
+    let opts = OptionOpts::builder().buffer(buffer.clone()).build();
+    let _ = set_option_value("readonly", true, &opts);
+    let _ = set_option_value("modifiable", false, &opts);
+    show_generated_banner(buffer, generated_dir);
+
+    let _ = buffer.set_keymap(
+        Mode::Normal,
+        "<leader>ge",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("'<leader>ge': Edit the generated file anyway")
+            .callback(|_| {
+                override_generated_guard();
+                ()
+            })
+            .silent(true)
+            .build(),
+    );
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_autocmd(
+        vec!["BufReadPost", "BufWinEnter"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-generated-guard-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                let mut buffer = args.buffer;
+                if args.event == "BufReadPost" {
+                    guard_generated_buffer(&mut buffer);
+                } else if get_guarded_dir(&buffer).is_some() {
+                    //
+                    // The banner above the first line is only visible with the `topfill`
+                    //
+                    let _ = command("silent! call winrestview({ 'topfill': 1 })");
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    register_user_command(
+        "GeneratedEdit",
+        UserCommandSpec {
+            desc: "Edit the guarded generated file anyway",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| override_generated_guard(),
+    );
+}
+
+use crate::{
+    extended_api::{
+        EchoLevel, UserCommandArgs, UserCommandSpec, echo_message, register_user_command,
+    },
+    project_command::get_project_dir,
+};
+
+use std::path::{Component, Path};
+
+use nvim_oxi::{
+    String as NvimString,
+    api::{
+        Buffer, command, create_augroup, create_autocmd, create_namespace, get_option_value,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, SetExtmarkOpts, SetKeymapOpts},
+        set_option_value,
+        types::{AutocmdCallbackArgs, Mode},
+    },
+};
@@ -0,0 +1,692 @@
+//! Git integration of the file buffers:
+//!
+//! - Signs: The added/changed/deleted lines of `git diff --unified=0` (the working file against
+//!   the index), it runs asynchronously when the file is read or written, and when Neovim gets
+//!   the focus back.
+//! - `]h`/`[h`: Jump to the next/previous hunk.
+//! - `<leader>hs`: Stage the hunk under the cursor by applying its patch to the index
+//!   (`git apply --cached`).
+//! - `<leader>hr`: Reset the hunk under the cursor to the index version (in the buffer, it's not
+//!   saved).
+//! - `<leader>gb`: Toggle the inline blame (virtual text) of the cursor line.
+//!
+//! The diff is of the saved file, so staging/resetting the hunk of a modified buffer asks to save
+//! it first (the line numbers may not match).
+//!
+
+///
+/// The namespaces of the signs and the inline blame
+///
+const GIT_SIGNS_NAMESPACE: &'static str = "git_signs";
+const GIT_BLAME_NAMESPACE: &'static str = "git_blame";
+
+///
+/// The sign text and highlight group of the hunk kinds
+///
+const GIT_SIGN_ADD: (&'static str, &'static str) = ("▎", "Added");
+const GIT_SIGN_CHANGE: (&'static str, &'static str) = ("▎", "Changed");
+const GIT_SIGN_DELETE: (&'static str, &'static str) = ("▁", "Removed");
+
+///
+/// The hunk kind
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GitHunkKind {
+    Add,
+    Change,
+    Delete,
+}
+
+///
+/// The hunk of `git diff --unified=0`
+///
+#[derive(Debug, Clone)]
+struct GitHunk {
+    kind: GitHunkKind,
+
+    //
+    // The `+{new_start},{new_count}` of the hunk header, for the deleted lines, `new_start` is
+    // the line before them (`0` means the top of the file).
+    //
+    new_start: usize,
+    new_count: usize,
+
+    //
+    // The index version of the lines (the `-` lines)
+    //
+    old_lines: Vec<String>,
+
+    //
+    // The hunk header and body, it's the patch with the file header
+    //
+    patch_lines: Vec<String>,
+}
+
+impl GitHunk {
+    ///
+    /// The (1-based) line range of the signs in the working file
+    ///
+    fn line_range(&self) -> (usize, usize) {
+        match self.kind {
+            GitHunkKind::Delete => (self.new_start.max(1), self.new_start.max(1)),
+            _ => (self.new_start, self.new_start + self.new_count - 1),
+        }
+    }
+}
+
+///
+/// The diff of the buffer
+///
+#[derive(Debug, Clone, Default)]
+struct GitBufferDiff {
+    git_root: PathBuf,
+    file_header: Vec<String>,
+    hunks: Vec<GitHunk>,
+}
+
+///
+/// Private module-scope state: buffer handle <--> the diff of the buffer file
+///
+static GIT_BUFFER_DIFFS: LazyLock<Mutex<HashMap<BufHandle, GitBufferDiff>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+///
+/// Private module-scope state: whether the inline blame is on
+///
+static GIT_BLAME_ENABLED: LazyLock<Mutex<bool>> = LazyLock::new(|| Mutex::new(false));
+
+///
+/// Find the repository root (the directory with `.git`, it's a file in the worktree or the
+/// submodule) of the file or directory
+///
+pub fn find_git_root(file_path: &Path) -> Option<PathBuf> {
+    file_path
+        .ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .map(|ancestor| ancestor.to_path_buf())
+}
+
+///
+/// Get back the repository root and the file path relative to it, `None` if the buffer isn't a
+/// file in the git repository.
+///
+fn get_git_file(buffer: &Buffer) -> Option<(PathBuf, String)> {
+    let buffer_type = get_option_value::<NvimString>(
+        "buftype",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    )
+    .unwrap_or_default();
+    if !buffer_type.is_empty() {
+        return None;
+    }
+
+    let file_path = buffer.get_name().ok()?;
+    if !file_path.is_absolute() {
+        return None;
+    }
+    let git_root = find_git_root(&file_path)?;
+    let relative_path = file_path
+        .strip_prefix(&git_root)
+        .ok()?
+        .display()
+        .to_string();
+
+    Some((git_root, relative_path))
+}
+
+///
+/// Parse the range of the hunk header, e.g. `-12,3` or `+7` (the count is `1` if omitted)
+///
+fn parse_hunk_range(range: &str) -> Option<(usize, usize)> {
+    let range = range.get(1..)?;
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+///
+/// Parse the output of `git diff --unified=0` into the file header and the hunks, the lines
+/// before `diff --git` (e.g. the warnings in stderr) are ignored.
+///
+fn parse_diff_hunks(lines: &[String]) -> (Vec<String>, Vec<GitHunk>) {
+    let mut file_header = Vec::new();
+    let mut hunks: Vec<GitHunk> = Vec::new();
+
+    let Some(diff_start) = lines.iter().position(|line| line.starts_with("diff --git")) else {
+        return (file_header, hunks);
+    };
+
+    for line in &lines[diff_start..] {
+        if line.starts_with("@@") {
+            let mut ranges = line.split_whitespace().skip(1);
+            let (Some((_, old_count)), Some((new_start, new_count))) = (
+                ranges.next().and_then(parse_hunk_range),
+                ranges.next().and_then(parse_hunk_range),
+            ) else {
+                continue;
+            };
+
+            let kind = if old_count == 0 {
+                GitHunkKind::Add
+            } else if new_count == 0 {
+                GitHunkKind::Delete
+            } else {
+                GitHunkKind::Change
+            };
+
+            hunks.push(GitHunk {
+                kind,
+                new_start,
+                new_count,
+                old_lines: Vec::new(),
+                patch_lines: vec![line.clone()],
+            });
+            continue;
+        }
+
+        match hunks.last_mut() {
+            Some(hunk) => {
+                if let Some(old_line) = line.strip_prefix('-') {
+                    hunk.old_lines.push(old_line.to_owned());
+                }
+                hunk.patch_lines.push(line.clone());
+            }
+            None => file_header.push(line.clone()),
+        }
+    }
+
+    (file_header, hunks)
+}
+
+///
+/// Place the signs of the hunks
+///
+fn place_git_signs(buffer: &mut Buffer, hunks: &[GitHunk]) {
+    let namespace_id = create_namespace(GIT_SIGNS_NAMESPACE);
+    let _ = buffer.clear_namespace(namespace_id, ..);
+    let line_count = buffer.line_count().unwrap_or(0);
+
+    for hunk in hunks {
+        let (sign_text, hl_group) = match hunk.kind {
+            GitHunkKind::Add => GIT_SIGN_ADD,
+            GitHunkKind::Change => GIT_SIGN_CHANGE,
+            GitHunkKind::Delete => GIT_SIGN_DELETE,
+        };
+
+        let (start_line, end_line) = hunk.line_range();
+        for line in start_line..=end_line.min(line_count) {
+            let _ = buffer.set_extmark(
+                namespace_id,
+                line - 1,
+                0,
+                &SetExtmarkOpts::builder()
+                    .sign_text(sign_text)
+                    .sign_hl_group(hl_group)
+                    .build(),
+            );
+        }
+    }
+}
+
+///
+/// Run `git diff` of the buffer file asynchronously, and then update the signs.
+///
+fn refresh_git_signs(buffer: Buffer) {
+    let Some((git_root, relative_path)) = get_git_file(&buffer) else {
+        return;
+    };
+
+    let output_lines = Rc::new(RefCell::new(Vec::<String>::new()));
+    let collected_lines = output_lines.clone();
+    let buffer_handle = buffer.handle();
+    let cwd = git_root.display().to_string();
+
+    let _ = run_async_in(
+        vec![
+            "git".to_string(),
+            "--no-pager".to_string(),
+            "diff".to_string(),
+            "--no-color".to_string(),
+            "--no-ext-diff".to_string(),
+            "--unified=0".to_string(),
+            "--".to_string(),
+            relative_path,
+        ],
+        Some(&cwd),
+        &[],
+        move |lines| collected_lines.borrow_mut().extend(lines),
+        move |exit_code| {
+            let mut buffer = Buffer::from(buffer_handle);
+            if exit_code != 0 || !buffer.is_valid() {
+                return;
+            }
+
+            let (file_header, hunks) = parse_diff_hunks(&output_lines.borrow());
+            place_git_signs(&mut buffer, &hunks);
+            GIT_BUFFER_DIFFS.lock().unwrap().insert(
+                buffer_handle,
+                GitBufferDiff {
+                    git_root,
+                    file_header,
+                    hunks,
+                },
+            );
+        },
+    );
+}
+
+///
+/// `]h`/`[h`: Jump to the next/previous hunk
+///
+fn jump_to_hunk(forward: bool) {
+    let buffer = Buffer::current();
+    let hunk_lines = GIT_BUFFER_DIFFS
+        .lock()
+        .unwrap()
+        .get(&buffer.handle())
+        .map(|diff| {
+            diff.hunks
+                .iter()
+                .map(|hunk| hunk.line_range().0)
+                .collect::<Vec<usize>>()
+        })
+        .unwrap_or_default();
+
+    let mut window = Window::current();
+    let Ok((cursor_line, _)) = window.get_cursor() else {
+        return;
+    };
+
+    let target_line = if forward {
+        hunk_lines.iter().find(|line| **line > cursor_line)
+    } else {
+        hunk_lines.iter().rev().find(|line| **line < cursor_line)
+    };
+
+    match target_line {
+        Some(line) => {
+            let _ = window.set_cursor(*line, 0);
+        }
+        None => notify(
+            "git",
+            if forward {
+                "No more hunks below."
+            } else {
+                "No more hunks above."
+            },
+            EchoLevel::Info,
+        ),
+    }
+}
+
+///
+/// Get back the diff and the hunk under the cursor, the modified buffer is refused as its line
+/// numbers may not match the diff.
+///
+fn get_hunk_under_cursor() -> Option<(GitBufferDiff, GitHunk)> {
+    let buffer = Buffer::current();
+    let is_modified = get_option_value::<bool>(
+        "modified",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    )
+    .unwrap_or(false);
+    if is_modified {
+        notify("git", "Save the buffer first.", EchoLevel::Info);
+        return None;
+    }
+
+    let (cursor_line, _) = Window::current().get_cursor().ok()?;
+    let diff = GIT_BUFFER_DIFFS
+        .lock()
+        .unwrap()
+        .get(&buffer.handle())
+        .cloned()?;
+    let Some(hunk) = diff
+        .hunks
+        .iter()
+        .find(|hunk| {
+            let (start_line, end_line) = hunk.line_range();
+            (start_line..=end_line).contains(&cursor_line)
+        })
+        .cloned()
+    else {
+        notify("git", "No hunk under the cursor.", EchoLevel::Info);
+        return None;
+    };
+
+    Some((diff, hunk))
+}
+
+///
+/// `<leader>hs`: Stage the hunk under the cursor, the patch is written into a temp file for
+/// `git apply --cached --unidiff-zero`.
+///
+fn stage_hunk() {
+    let Some((diff, hunk)) = get_hunk_under_cursor() else {
+        return;
+    };
+
+    let patch_path = call_function::<_, String>("tempname", ()).unwrap_or_default();
+    let mut patch = diff.file_header.join("\n");
+    patch.push('\n');
+    patch.push_str(&hunk.patch_lines.join("\n"));
+    patch.push('\n');
+    if let Err(error) = std::fs::write(&patch_path, patch) {
+        notify(
+            "git",
+            &format!("Failed to write the patch: {error}"),
+            EchoLevel::Error,
+        );
+        return;
+    }
+
+    let output_lines = Rc::new(RefCell::new(Vec::<String>::new()));
+    let collected_lines = output_lines.clone();
+    let buffer_handle = Buffer::current().handle();
+
+    let _ = run_async_in(
+        vec![
+            "git".to_string(),
+            "apply".to_string(),
+            "--cached".to_string(),
+            "--unidiff-zero".to_string(),
+            patch_path.clone(),
+        ],
+        Some(&diff.git_root.display().to_string()),
+        &[],
+        move |lines| collected_lines.borrow_mut().extend(lines),
+        move |exit_code| {
+            let _ = std::fs::remove_file(&patch_path);
+
+            if exit_code == 0 {
+                notify("git", "Staged the hunk.", EchoLevel::Success);
+                refresh_git_signs(Buffer::from(buffer_handle));
+            } else {
+                notify(
+                    "git",
+                    &format!(
+                        "Failed to stage the hunk: {}",
+                        output_lines.borrow().join(" ")
+                    ),
+                    EchoLevel::Error,
+                );
+            }
+        },
+    );
+}
+
+///
+/// `<leader>hr`: Replace the hunk lines with the index version in the buffer
+///
+fn reset_hunk() {
+    let Some((_, hunk)) = get_hunk_under_cursor() else {
+        return;
+    };
+
+    let mut buffer = Buffer::current();
+    let old_lines = hunk.old_lines.iter().map(|line| line.as_str());
+    let _ = match hunk.kind {
+        //
+        // The deleted lines go back after `new_start`
+        //
+        GitHunkKind::Delete => buffer.set_lines(hunk.new_start..hunk.new_start, true, old_lines),
+        _ => buffer.set_lines(
+            hunk.new_start - 1..hunk.new_start - 1 + hunk.new_count,
+            true,
+            old_lines,
+        ),
+    };
+}
+
+///
+/// Clear the inline blame of all buffers
+///
+fn clear_inline_blame() {
+    let namespace_id = create_namespace(GIT_BLAME_NAMESPACE);
+    for mut buffer in list_bufs() {
+        let _ = buffer.clear_namespace(namespace_id, ..);
+    }
+}
+
+///
+/// Show the blame of the cursor line as the virtual text, the `git blame` runs asynchronously and
+/// the result is dropped if the cursor has moved to another line.
+///
+fn show_inline_blame() {
+    let buffer = Buffer::current();
+    let Some((git_root, relative_path)) = get_git_file(&buffer) else {
+        return;
+    };
+    let Ok((cursor_line, _)) = Window::current().get_cursor() else {
+        return;
+    };
+
+    let output_lines = Rc::new(RefCell::new(Vec::<String>::new()));
+    let collected_lines = output_lines.clone();
+    let buffer_handle = buffer.handle();
+
+    let _ = run_async_in(
+        vec![
+            "git".to_string(),
+            "blame".to_string(),
+            "--porcelain".to_string(),
+            "-L".to_string(),
+            format!("{cursor_line},{cursor_line}"),
+            "--".to_string(),
+            relative_path,
+        ],
+        Some(&git_root.display().to_string()),
+        &[],
+        move |lines| collected_lines.borrow_mut().extend(lines),
+        move |exit_code| {
+            let mut buffer = Buffer::current();
+            let is_same_line = Window::current()
+                .get_cursor()
+                .is_ok_and(|(line, _)| line == cursor_line);
+            if exit_code != 0
+                || buffer.handle() != buffer_handle
+                || !is_same_line
+                || !*GIT_BLAME_ENABLED.lock().unwrap()
+            {
+                return;
+            }
+
+            let output_lines = output_lines.borrow();
+            let get_field = |key: &str| {
+                output_lines
+                    .iter()
+                    .find_map(|line| line.strip_prefix(key))
+                    .unwrap_or_default()
+                    .to_owned()
+            };
+
+            let is_uncommitted = output_lines
+                .first()
+                .is_some_and(|line| line.starts_with("0000000"));
+            let blame_text = if is_uncommitted {
+                "    Not committed yet".to_string()
+            } else {
+                let author_time = get_field("author-time ").parse::<i64>().unwrap_or(0);
+                let date = call_function::<_, String>("strftime", ("%Y-%m-%d", author_time))
+                    .unwrap_or_default();
+                format!(
+                    "    {}, {date} • {}",
+                    get_field("author "),
+                    get_field("summary ")
+                )
+            };
+
+            let namespace_id = create_namespace(GIT_BLAME_NAMESPACE);
+            let _ = buffer.clear_namespace(namespace_id, ..);
+            let _ = buffer.set_extmark(
+                namespace_id,
+                cursor_line - 1,
+                0,
+                &SetExtmarkOpts::builder()
+                    .virt_text([(blame_text.as_str(), "Comment")])
+                    .virt_text_pos(ExtmarkVirtTextPosition::Eol)
+                    .build(),
+            );
+        },
+    );
+}
+
+///
+/// `<leader>gb`: Toggle the inline blame
+///
+fn toggle_inline_blame() {
+    let is_enabled = {
+        let mut locked_enabled = GIT_BLAME_ENABLED.lock().unwrap();
+        *locked_enabled = !*locked_enabled;
+        *locked_enabled
+    };
+
+    if is_enabled {
+        show_inline_blame();
+    } else {
+        clear_inline_blame();
+    }
+
+    notify(
+        "git",
+        if is_enabled {
+            "Inline blame on."
+        } else {
+            "Inline blame off."
+        },
+        EchoLevel::Info,
+    );
+}
+
+///
+///
+///
+pub fn setup() {
+    let Ok(git_group) = create_augroup(
+        "custom-git-group",
+        &CreateAugroupOpts::builder().clear(true).build(),
+    ) else {
+        return;
+    };
+
+    let _ = create_autocmd(
+        vec!["BufReadPost", "BufWritePost", "FocusGained", "BufWipeout"],
+        &CreateAutocmdOpts::builder()
+            .group(git_group)
+            .callback(|args: AutocmdCallbackArgs| {
+                match args.event.as_str() {
+                    "BufWipeout" => {
+                        GIT_BUFFER_DIFFS
+                            .lock()
+                            .unwrap()
+                            .remove(&args.buffer.handle());
+                    }
+
+                    //
+                    // The files may be changed by the git commands outside, refresh the buffers
+                    // shown in the windows.
+                    //
+                    "FocusGained" => {
+                        for window in list_wins() {
+                            if let Ok(buffer) = window.get_buf() {
+                                refresh_git_signs(buffer);
+                            }
+                        }
+                    }
+                    _ => refresh_git_signs(args.buffer),
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    let _ = create_autocmd(
+        vec!["CursorHold", "CursorMoved", "InsertEnter"],
+        &CreateAutocmdOpts::builder()
+            .group(git_group)
+            .callback(|args: AutocmdCallbackArgs| {
+                if *GIT_BLAME_ENABLED.lock().unwrap() {
+                    if args.event == "CursorHold" {
+                        show_inline_blame();
+                    } else {
+                        let _ = args
+                            .buffer
+                            .clone()
+                            .clear_namespace(create_namespace(GIT_BLAME_NAMESPACE), ..);
+                    }
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    let git_keybindings: Vec<(&str, &str, fn())> = vec![
+        ("]h", "']h': Jump to the next git hunk", || {
+            jump_to_hunk(true)
+        }),
+        ("[h", "'[h': Jump to the previous git hunk", || {
+            jump_to_hunk(false)
+        }),
+        (
+            "<leader>hs",
+            "'<leader>hs': Stage the git hunk under the cursor",
+            stage_hunk,
+        ),
+        (
+            "<leader>hr",
+            "'<leader>hr': Reset the git hunk under the cursor",
+            reset_hunk,
+        ),
+        (
+            "<leader>gb",
+            "'<leader>gb': Toggle the inline git blame",
+            toggle_inline_blame,
+        ),
+    ];
+
+    for (key, desc, callback) in git_keybindings {
+        let _ = set_keymap(
+            Mode::Normal,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(desc)
+                .silent(true)
+                .callback(move |_| {
+                    callback();
+                    ()
+                })
+                .build(),
+        );
+    }
+}
+
+use crate::{extended_api::EchoLevel, notify::notify, utils::run_async_in};
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    BufHandle, String as NvimString,
+    api::{
+        Buffer, Window, call_function, create_augroup, create_autocmd, create_namespace,
+        get_option_value, list_bufs, list_wins,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, SetExtmarkOpts, SetKeymapOpts},
+        set_keymap,
+        types::{AutocmdCallbackArgs, ExtmarkVirtTextPosition, Mode},
+    },
+};
@@ -0,0 +1,691 @@
+//! `<leader>gc`: The git commit/push popup of the repository of the current file (or the current
+//! working directory):
+//!
+//! ```text
+//! ╭──────────────────── Git: my-project ─────────────────────╮
+//! │ M  src/lib.rs                                            │
+//! │  M src/git.rs                                            │
+//! │ ?? src/git_commit.rs                                     │
+//! ╰─ s: stage  u: unstage  <Space>: toggle  c: commit  ... ──╯
+//! ```
+//!
+//! - The list is `git status --porcelain`, the first column is the index (staged) status and the
+//!   second one is the working tree status.
+//! - `s`/`u`/`<Space>`: Stage/Unstage/Toggle the file under the cursor, `a`: Stage all.
+//! - `c`: Write the commit message in the message window (`#` lines are ignored), `<C-s>` (or
+//!   `<CR>` in the normal mode) commits the staged files.
+//! - `p`: Push, `r`: Refresh, `q`/`<ESC>`: Close.
+//!
+//! The git commands (including the commit and the push) run asynchronously, the results are
+//! reported as the messages.
+//!
+
+///
+/// The namespace to highlight the status list
+///
+const GIT_COMMIT_NAMESPACE: &'static str = "git_commit_status";
+
+///
+/// The file entry of `git status --porcelain`
+///
+#[derive(Debug, Clone)]
+struct GitStatusEntry {
+    index_status: char,
+    worktree_status: char,
+    path: String,
+}
+
+impl GitStatusEntry {
+    ///
+    /// Whether the file has any staged change
+    ///
+    fn is_staged(&self) -> bool {
+        self.index_status != ' ' && self.index_status != '?'
+    }
+
+    ///
+    /// Whether the file has any unstaged change (including the untracked file)
+    ///
+    fn is_unstaged(&self) -> bool {
+        self.worktree_status != ' '
+    }
+}
+
+///
+/// The open popup
+///
+#[derive(Debug, Clone)]
+struct GitCommitPopup {
+    git_root: PathBuf,
+    status_window: PopupWindow,
+    message_window: Option<PopupWindow>,
+    entries: Vec<GitStatusEntry>,
+}
+
+///
+/// Private module-scope state: the open popup, only one at a time
+///
+static GIT_COMMIT_STATE: LazyLock<Mutex<Option<GitCommitPopup>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+///
+/// Parse the lines of `git status --porcelain`, the renamed file (`R  old -> new`) is the new
+/// path.
+///
+fn parse_status_lines(lines: &[String]) -> Vec<GitStatusEntry> {
+    lines
+        .iter()
+        .filter_map(|line| {
+            let mut chars = line.chars();
+            let index_status = chars.next()?;
+            let worktree_status = chars.next()?;
+            let path = line.get(3..)?;
+            let path = path.rsplit(" -> ").next().unwrap_or(path);
+
+            Some(GitStatusEntry {
+                index_status,
+                worktree_status,
+                path: path.trim_matches('"').to_owned(),
+            })
+        })
+        .collect()
+}
+
+///
+/// Render the status list into the status window
+///
+fn render_status_list(status_window: &PopupWindow, entries: &[GitStatusEntry]) {
+    let lines = if entries.is_empty() {
+        vec![" Nothing to commit, working tree clean".to_string()]
+    } else {
+        entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    " {}{} {}",
+                    entry.index_status, entry.worktree_status, entry.path
+                )
+            })
+            .collect::<Vec<String>>()
+    };
+    let _ = status_window.set_lines(lines.iter().map(|line| line.as_str()));
+
+    let mut buffer = status_window.buffer();
+    let namespace_id = create_namespace(GIT_COMMIT_NAMESPACE);
+    let _ = buffer.clear_namespace(namespace_id, ..);
+
+    for (index, entry) in entries.iter().enumerate() {
+        let (index_hl_group, worktree_hl_group) = if entry.index_status == '?' {
+            ("Comment", "Comment")
+        } else {
+            ("Added", "Changed")
+        };
+
+        for (col, hl_group) in [(1, index_hl_group), (2, worktree_hl_group)] {
+            let _ = buffer.set_extmark(
+                namespace_id,
+                index,
+                col,
+                &SetExtmarkOpts::builder()
+                    .end_col(col + 1)
+                    .hl_group(hl_group)
+                    .build(),
+            );
+        }
+    }
+}
+
+///
+/// Run the git command in the repository root asynchronously, `on_exit` gets the exit code and
+/// the output lines.
+///
+fn run_git<E>(git_root: &Path, args: &[&str], on_exit: E)
+where
+    E: FnOnce(i64, Vec<String>) + 'static,
+{
+    let output_lines = Rc::new(RefCell::new(Vec::<String>::new()));
+    let collected_lines = output_lines.clone();
+
+    let mut cmd = vec!["git".to_string()];
+    cmd.extend(args.iter().map(|arg| arg.to_string()));
+
+    let is_started = run_async_in(
+        cmd,
+        Some(&git_root.display().to_string()),
+        &[],
+        move |lines| collected_lines.borrow_mut().extend(lines),
+        move |exit_code| on_exit(exit_code, output_lines.take()),
+    );
+    if !is_started {
+        notify("git_commit", "Failed to run 'git'.", EchoLevel::Error);
+    }
+}
+
+///
+/// Refresh the status list of the open popup
+///
+fn refresh_status() {
+    let Some(git_root) = GIT_COMMIT_STATE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|popup| popup.git_root.clone())
+    else {
+        return;
+    };
+
+    run_git(
+        &git_root,
+        &["status", "--porcelain=v1", "--untracked-files=all"],
+        |exit_code, lines| {
+            if exit_code != 0 {
+                notify("git_commit", &lines.join(" "), EchoLevel::Error);
+                return;
+            }
+
+            let entries = parse_status_lines(&lines);
+            let mut locked_state = GIT_COMMIT_STATE.lock().unwrap();
+            let Some(popup) = locked_state.as_mut() else {
+                return;
+            };
+            if !popup.status_window.is_valid() {
+                return;
+            }
+
+            render_status_list(&popup.status_window, &entries);
+            popup.entries = entries;
+        },
+    );
+}
+
+///
+/// Get back the repository root and the status entry under the cursor
+///
+fn get_entry_under_cursor() -> Option<(PathBuf, GitStatusEntry)> {
+    let locked_state = GIT_COMMIT_STATE.lock().unwrap();
+    let popup = locked_state.as_ref()?;
+    let (cursor_line, _) = popup.status_window.window().get_cursor().ok()?;
+    let entry = popup.entries.get(cursor_line - 1)?.clone();
+
+    Some((popup.git_root.clone(), entry))
+}
+
+///
+/// `s`/`u`/`<Space>`: Stage (`Some(true)`), unstage (`Some(false)`) or toggle (`None`) the file
+/// under the cursor, toggling stages the file if it has any unstaged change.
+///
+fn stage_entry(stage: Option<bool>) {
+    let Some((git_root, entry)) = get_entry_under_cursor() else {
+        return;
+    };
+
+    let stage = stage.unwrap_or(entry.is_unstaged() || !entry.is_staged());
+    let args = if stage {
+        vec!["add", "--", entry.path.as_str()]
+    } else {
+        vec!["restore", "--staged", "--", entry.path.as_str()]
+    };
+
+    run_git(&git_root, &args, |exit_code, lines| {
+        if exit_code != 0 {
+            notify("git_commit", &lines.join(" "), EchoLevel::Error);
+        }
+        refresh_status();
+    });
+}
+
+///
+/// `a`: Stage all files
+///
+fn stage_all() {
+    let Some(git_root) = GIT_COMMIT_STATE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|popup| popup.git_root.clone())
+    else {
+        return;
+    };
+
+    run_git(&git_root, &["add", "--all"], |exit_code, lines| {
+        if exit_code != 0 {
+            notify("git_commit", &lines.join(" "), EchoLevel::Error);
+        }
+        refresh_status();
+    });
+}
+
+///
+/// `<C-s>` in the message window: Commit the staged files with the message
+///
+fn commit() {
+    let (git_root, message_window, has_staged) = {
+        let locked_state = GIT_COMMIT_STATE.lock().unwrap();
+        let Some(popup) = locked_state.as_ref() else {
+            return;
+        };
+        let Some(message_window) = popup.message_window else {
+            return;
+        };
+        (
+            popup.git_root.clone(),
+            message_window,
+            popup.entries.iter().any(|entry| entry.is_staged()),
+        )
+    };
+
+    if !has_staged {
+        notify("git_commit", "Nothing staged.", EchoLevel::Info);
+        return;
+    }
+
+    let message_lines = message_window
+        .buffer()
+        .get_lines(.., false)
+        .map(|lines| {
+            lines
+                .map(|line| line.to_string())
+                .filter(|line| !line.starts_with('#'))
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+    let message = message_lines.join("\n").trim().to_owned();
+    if message.is_empty() {
+        notify(
+            "git_commit",
+            "The commit message is empty.",
+            EchoLevel::Info,
+        );
+        return;
+    }
+
+    //
+    // `git commit -F` keeps the multi-line message as it is
+    //
+    let message_path = call_function::<_, String>("tempname", ()).unwrap_or_default();
+    if let Err(error) = std::fs::write(&message_path, format!("{message}\n")) {
+        notify(
+            "git_commit",
+            &format!("Failed to write the commit message: {error}"),
+            EchoLevel::Error,
+        );
+        return;
+    }
+
+    notify("git_commit", "Committing...", EchoLevel::Info);
+    run_git(
+        &git_root,
+        &["commit", "--file", message_path.as_str()],
+        move |exit_code, lines| {
+            let _ = std::fs::remove_file(&message_path);
+
+            if exit_code != 0 {
+                notify(
+                    "git_commit",
+                    &format!("Failed to commit: {}", lines.join(" ")),
+                    EchoLevel::Error,
+                );
+                return;
+            }
+
+            notify(
+                "git_commit",
+                lines
+                    .first()
+                    .map(|line| line.as_str())
+                    .unwrap_or("Committed."),
+                EchoLevel::Success,
+            );
+            close_message_window();
+            refresh_status();
+        },
+    );
+}
+
+///
+/// `p`: Push the current branch
+///
+fn push() {
+    let Some(git_root) = GIT_COMMIT_STATE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|popup| popup.git_root.clone())
+    else {
+        return;
+    };
+
+    notify("git_commit", "Pushing...", EchoLevel::Info);
+    run_git(&git_root, &["push"], |exit_code, lines| {
+        //
+        // The progress goes into stderr, the last line is the summary
+        //
+        let summary = lines
+            .iter()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .cloned()
+            .unwrap_or_default();
+
+        if exit_code == 0 {
+            notify(
+                "git_commit",
+                &format!("Pushed. {summary}"),
+                EchoLevel::Success,
+            );
+        } else {
+            notify(
+                "git_commit",
+                &format!("Failed to push: {summary}"),
+                EchoLevel::Error,
+            );
+        }
+    });
+}
+
+///
+/// Close the message window and go back to the status window
+///
+fn close_message_window() {
+    //
+    // Release the lock before closing, as the `WinClosed` autocommand locks the state as well
+    //
+    let Some((status_window, message_window)) = GIT_COMMIT_STATE
+        .lock()
+        .unwrap()
+        .as_mut()
+        .map(|popup| (popup.status_window, popup.message_window.take()))
+    else {
+        return;
+    };
+
+    if let Some(message_window) = message_window {
+        message_window.close();
+    }
+    if status_window.is_valid() {
+        let _ = set_current_win(&status_window.window());
+    }
+}
+
+///
+/// `q`/`<ESC>`: Close the popup
+///
+fn close_popup() {
+    let Some(popup) = GIT_COMMIT_STATE.lock().unwrap().take() else {
+        return;
+    };
+
+    if let Some(message_window) = popup.message_window {
+        message_window.close();
+    }
+    popup.status_window.close();
+}
+
+///
+/// `c`: Open the message window above the status window
+///
+fn open_message_window() {
+    if GIT_COMMIT_STATE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_none_or(|popup| popup.message_window.is_some_and(|window| window.is_valid()))
+    {
+        return;
+    }
+
+    let Ok(mut message_buffer) = create_buf(false, true) else {
+        return;
+    };
+    let opts = OptionOpts::builder().buffer(message_buffer.clone()).build();
+    let _ = set_option_value("bufhidden", "wipe", &opts);
+    let _ = message_buffer.set_lines(
+        ..,
+        true,
+        [
+            "",
+            "",
+            "# <C-s> (or <CR> in the normal mode): commit, <ESC> in the normal mode: back",
+        ],
+    );
+
+    let Some(message_window) = create_popup_window(&PopupWindowOptions {
+        buffer: Some(message_buffer.handle()),
+        window_width_ratio: Some(0.5),
+        title: Some(" Commit message ".to_string()),
+        min_height: Some(8),
+        zindex: Some(60),
+        ..Default::default()
+    }) else {
+        return;
+    };
+
+    //
+    // The `gitcommit` filetype gets the syntax and the commit message assistant keybindings
+    //
+    let _ = set_option_value("filetype", "gitcommit", &opts);
+
+    let message_keybindings: Vec<(Mode, &str, &str, fn())> = vec![
+        (Mode::Insert, "<C-s>", "Git commit: commit", commit),
+        (Mode::Normal, "<C-s>", "Git commit: commit", commit),
+        (Mode::Normal, "<CR>", "Git commit: commit", commit),
+        (
+            Mode::Normal,
+            "<Esc>",
+            "Git commit: back to the status",
+            close_message_window,
+        ),
+    ];
+
+    for (mode, key, desc, callback) in message_keybindings {
+        let _ = message_buffer.set_keymap(
+            mode,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(desc)
+                .callback(move |_| {
+                    callback();
+                    ()
+                })
+                .silent(true)
+                .build(),
+        );
+    }
+
+    if let Some(popup) = GIT_COMMIT_STATE.lock().unwrap().as_mut() {
+        popup.message_window = Some(message_window);
+    }
+
+    let _ = set_current_win(&message_window.window());
+    let _ = command("startinsert");
+}
+
+///
+/// `<leader>gc`: Open the popup of the repository
+///
+fn open_git_commit_popup() {
+    if GIT_COMMIT_STATE.lock().unwrap().is_some() {
+        close_popup();
+    }
+
+    let start_path = Buffer::current()
+        .get_name()
+        .ok()
+        .filter(|name| name.is_absolute())
+        .unwrap_or_else(|| PathBuf::from(get_project_dir()));
+    let Some(git_root) = find_git_root(&start_path) else {
+        notify("git_commit", "Not in a git repository.", EchoLevel::Info);
+        return;
+    };
+
+    let Ok(mut status_buffer) = create_buf(false, true) else {
+        return;
+    };
+    let opts = OptionOpts::builder().buffer(status_buffer.clone()).build();
+    let _ = set_option_value("bufhidden", "wipe", &opts);
+    let _ = set_option_value("modifiable", false, &opts);
+
+    let repository_name = git_root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let Some(status_window) = create_popup_window(&PopupWindowOptions {
+        buffer: Some(status_buffer.handle()),
+        window_width_ratio: Some(0.5),
+        window_height_ratio: Some(0.5),
+        title: Some(format!(" Git: {repository_name} ")),
+        footer: Some(
+            " s: stage  u: unstage  <Space>: toggle  a: all  c: commit  p: push  q: close "
+                .to_string(),
+        ),
+        ..Default::default()
+    }) else {
+        return;
+    };
+    let _ = set_option_value(
+        "cursorline",
+        true,
+        &OptionOpts::builder().win(status_window.window()).build(),
+    );
+
+    let status_keybindings: Vec<(&str, &str, fn())> = vec![
+        ("s", "Git commit: stage the file", || {
+            stage_entry(Some(true))
+        }),
+        ("u", "Git commit: unstage the file", || {
+            stage_entry(Some(false))
+        }),
+        ("<Space>", "Git commit: toggle staging the file", || {
+            stage_entry(None)
+        }),
+        ("a", "Git commit: stage all files", stage_all),
+        (
+            "c",
+            "Git commit: write the commit message",
+            open_message_window,
+        ),
+        ("p", "Git commit: push", push),
+        ("r", "Git commit: refresh", refresh_status),
+        ("q", "Git commit: close", close_popup),
+        ("<Esc>", "Git commit: close", close_popup),
+    ];
+
+    for (key, desc, callback) in status_keybindings {
+        let _ = status_buffer.set_keymap(
+            Mode::Normal,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(desc)
+                .callback(move |_| {
+                    callback();
+                    ()
+                })
+                .silent(true)
+                .nowait(true)
+                .build(),
+        );
+    }
+
+    *GIT_COMMIT_STATE.lock().unwrap() = Some(GitCommitPopup {
+        git_root,
+        status_window,
+        message_window: None,
+        entries: Vec::new(),
+    });
+
+    refresh_status();
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>gc",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("'<leader>gc': Git commit/push popup")
+            .silent(true)
+            .callback(|_| {
+                open_git_commit_popup();
+                ()
+            })
+            .build(),
+    );
+
+    //
+    // The popup may be closed by `:q` or `<C-w>c`, drop the state then
+    //
+    let _ = create_autocmd(
+        vec!["WinClosed"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-git-commit-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                let closed_handle = args.r#match.parse::<WinHandle>().unwrap_or(-1);
+                let mut locked_state = GIT_COMMIT_STATE.lock().unwrap();
+                let is_status_closed = locked_state
+                    .as_ref()
+                    .is_some_and(|popup| popup.status_window.window_handle() == closed_handle);
+
+                if is_status_closed && let Some(popup) = locked_state.take() {
+                    if let Some(message_window) = popup.message_window {
+                        //
+                        // Can't close the window inside `WinClosed`
+                        //
+                        nvim::schedule(move |_| {
+                            message_window.close();
+                            ()
+                        });
+                    }
+                } else if let Some(popup) = locked_state.as_mut()
+                    && popup
+                        .message_window
+                        .is_some_and(|window| window.window_handle() == closed_handle)
+                {
+                    popup.message_window = None;
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+use crate::{
+    extended_api::EchoLevel,
+    git::find_git_root,
+    notify::notify,
+    picker::{PopupWindow, PopupWindowOptions, create_popup_window},
+    project_command::get_project_dir,
+    utils::run_async_in,
+};
+
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim, WinHandle,
+    api::{
+        Buffer, call_function, command, create_augroup, create_autocmd, create_buf,
+        create_namespace,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, SetExtmarkOpts, SetKeymapOpts},
+        set_current_win, set_keymap, set_option_value,
+        types::{AutocmdCallbackArgs, Mode},
+    },
+};
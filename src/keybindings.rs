@@ -51,40 +51,12 @@ pub fn setup() {
         (Mode::Normal, "W", ":w<CR>", "Save current buffer"),
         (Mode::Normal, "Q", ":q<CR>", "Quit current buffer"),
         (Mode::Insert, "jj", "<ESC>", "'jj': Escape from insert mode"),
-        (
-            Mode::Normal,
-            "<Tab>",
-            ":bn<CR>",
-            "'Tab': Switch to next buffer",
-        ),
-        (
-            Mode::Normal,
-            "<S-Tab>",
-            ":bp<CR>",
-            "'Shift + Tab': Switch to prev buffer",
-        ),
         (
             Mode::Normal,
             "<Space><Space>",
             "<c-^>",
             "'<leader><leader>': Toggles between buffers",
         ),
-        (
-            Mode::Normal,
-            "<Space>th",
-            ":%TOhtml<CR>",
-            "'<leader>th': To HTML",
-        ),
-        // ------------------------------------------------------------------------------------
-        // ctrl+s: replace all words under cursor.
-        // <c-r><c-w> to grab the word under cursor
-        // ------------------------------------------------------------------------------------
-        (
-            Mode::Normal,
-            "<c-s>",
-            ":%s/<C-r><C-w>//g<left><left>",
-            "'<C-s>': Replace all words under the cursor",
-        ),
         // ------------------------------------------------------------------------------------
         // Split & window movement
         // ------------------------------------------------------------------------------------
@@ -104,18 +76,6 @@ pub fn setup() {
             ":wincmd k<CR>",
             "Move to up window",
         ),
-        (
-            Mode::Normal,
-            "<C-h>",
-            ":wincmd h<CR>",
-            "Move to left window",
-        ),
-        (
-            Mode::Normal,
-            "<C-l>",
-            ":wincmd l<CR>",
-            "Move to right window",
-        ),
         // Resize windows
         (
             Mode::Normal,
@@ -170,9 +130,7 @@ pub fn setup() {
         (Mode::Normal, "<leader>n", ":nohl<CR>", "No highlight"),
         (Mode::Normal, "n", "nzz", "Jump to next matching and center"),
         (Mode::Normal, "N", "Nzz", "Jump to prev matching and center"),
-        // Snippets (suggestion list) select item up and down
-        (Mode::Insert, "<c-j>", "<c-n>", ""),
-        (Mode::Insert, "<c-k>", "<c-p>", ""),
+        // The completion popup `<c-j>`/`<c-k>` are in `completion`
         (Mode::Normal, "<leader>oq", ":copen<CR>", "Open quick list"),
         (
             Mode::Normal,
@@ -192,9 +150,6 @@ pub fn setup() {
             ":lclose<CR>",
             "Close location list",
         ),
-        // Cycle through the quick fix list and center the current result line
-        (Mode::Normal, "<c-j>", ":cnext<CR>zz", ""),
-        (Mode::Normal, "<c-k>", ":cNext<CR>zz", ""),
         // ------------------------------------------------------------------------------------
         // Handy book marks
         // ------------------------------------------------------------------------------------
@@ -263,11 +218,6 @@ pub fn setup() {
             "Terminal: Press `<C-h>` to go back to the left window",
         ),
         // ------------------------------------------------------------------------------------
-        // Command line related
-        // ------------------------------------------------------------------------------------
-        (Mode::CmdLine, "<C-j>", "<Down>", "Next history command"),
-        (Mode::CmdLine, "<C-k>", "<Up>", "Previous history command"),
-        // ------------------------------------------------------------------------------------
         // Evacuate/run the selected lua code
         // ------------------------------------------------------------------------------------
         (
@@ -290,12 +240,38 @@ pub fn setup() {
         );
     }
 
+    //
+    // Command line: `<C-j>/<C-k>` select the next/prev completion candidate when the completion
+    // popup is open (the same with the insert mode), otherwise, go through the command history.
+    //
+    let cmdline_keybindings: Vec<(&str, &str, &str)> = vec![
+        (
+            "<C-j>",
+            r#"wildmenumode() ? "\<C-n>" : "\<Down>""#,
+            "Next completion candidate or history command",
+        ),
+        (
+            "<C-k>",
+            r#"wildmenumode() ? "\<C-p>" : "\<Up>""#,
+            "Previous completion candidate or history command",
+        ),
+    ];
+
+    for bindings in cmdline_keybindings {
+        let _ = set_keymap(
+            Mode::CmdLine,
+            bindings.0,
+            bindings.1,
+            &SetKeymapOpts::builder().desc(bindings.2).expr(true).build(),
+        );
+    }
+
     // let my_keybindings_with_callback: Vec<(Mode, &str, &str, Box<dyn Fn()>)> = vec![
     let my_keybindings_with_callback: Vec<(Mode, &str, &str, &dyn Fn())> = vec![
         (
             Mode::Normal,
-            "<leader>1",
-            "'<leader>1': Kill other windows.",
+            "<leader>wo",
+            "'<leader>wo': Kill other windows.",
             &kill_other_windows,
         ),
         (
@@ -304,11 +280,32 @@ pub fn setup() {
             "'<leader>sc': Toggle spell checking.",
             &toggle_spell_checking,
         ),
+        //
+        // ctrl+s: replace all words under the cursor (or the selected text)
+        //
+        (
+            Mode::Normal,
+            "<c-s>",
+            "'<C-s>': Replace all words under the cursor",
+            &replace_word_under_cursor,
+        ),
+        (
+            Mode::Visual,
+            "<c-s>",
+            "'<C-s>': Replace all selected text",
+            &replace_visual_selection,
+        ),
+        (
+            Mode::Normal,
+            "<leader>tl",
+            "'<leader>tl': Cycle the listchars/fillchars presets.",
+            &cycle_list_chars_preset,
+        ),
         (
             Mode::Normal,
-            "<leader>ft",
-            "'<leader>ft': Open a floating terminal.",
-            &open_centred_floating_terminal_window,
+            "gf",
+            "'gf': Open the file path (`path:line:col`) under the cursor",
+            &open_path_under_cursor,
         ),
     ];
 
@@ -328,6 +325,102 @@ pub fn setup() {
         );
     }
 
+    //
+    // Option toggles: `<leader>u` prefix, the new value shows in the notification
+    //
+    let option_toggle_keybindings: Vec<(&str, &str, Box<dyn Fn()>)> = vec![
+        (
+            "<leader>ur",
+            "'<leader>ur': Toggle relative number",
+            Box::new(|| {
+                toggle_option("relativenumber", ToggleOptionScope::Window);
+            }),
+        ),
+        (
+            "<leader>uw",
+            "'<leader>uw': Toggle wrap",
+            Box::new(|| {
+                toggle_option("wrap", ToggleOptionScope::Window);
+            }),
+        ),
+        (
+            "<leader>ul",
+            "'<leader>ul': Toggle list chars",
+            Box::new(|| {
+                toggle_option("list", ToggleOptionScope::Window);
+            }),
+        ),
+        (
+            "<leader>ud",
+            "'<leader>ud': Toggle diagnostics virtual text",
+            Box::new(toggle_diagnostics_virtual_text),
+        ),
+        (
+            "<leader>uc",
+            "'<leader>uc': Toggle color column",
+            Box::new(|| {
+                toggle_string_option("colorcolumn", ToggleOptionScope::Window, "80");
+            }),
+        ),
+        (
+            "<leader>us",
+            "'<leader>us': Toggle spell checking",
+            Box::new(toggle_spell_checking),
+        ),
+    ];
+
+    for bindings in option_toggle_keybindings {
+        let _ = set_keymap(
+            Mode::Normal,
+            bindings.0,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(bindings.1)
+                .callback(move |_| {
+                    bindings.2();
+                    ()
+                })
+                .silent(true)
+                .build(),
+        );
+    }
+
+    //
+    // Smart window movement: `<C-h>/<C-j>/<C-k>/<C-l>` create a new split when moving beyond the
+    // last window. `<C-j>/<C-k>` cycle through the quick fix list (and center the current result
+    // line) instead when the quick fix window is open.
+    //
+    let smart_window_keybindings: Vec<(&str, SmartWindowDirection, &str)> = vec![
+        ("<C-h>", SmartWindowDirection::Left, "Move to left window"),
+        (
+            "<C-j>",
+            SmartWindowDirection::Down,
+            "Move to down window or next quick fix item",
+        ),
+        (
+            "<C-k>",
+            SmartWindowDirection::Up,
+            "Move to up window or prev quick fix item",
+        ),
+        ("<C-l>", SmartWindowDirection::Right, "Move to right window"),
+    ];
+
+    for (lhs, direction, desc) in smart_window_keybindings {
+        let _ = set_keymap(
+            Mode::Normal,
+            lhs,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(desc)
+                .callback(move |_| {
+                    smart_window_move(direction);
+                    ()
+                })
+                .silent(true)
+                .build(),
+        );
+    }
+
     // Temporary testing purpose
     let _ = set_keymap(
         Mode::Normal,
@@ -345,8 +438,12 @@ pub fn setup() {
     );
 }
 
+use crate::settings::cycle_list_chars_preset;
+
 use crate::utils::{
-    kill_other_windows, open_centred_floating_terminal_window, toggle_spell_checking, get_split_window
+    SmartWindowDirection, ToggleOptionScope, get_split_window, kill_other_windows,
+    open_path_under_cursor, replace_visual_selection, replace_word_under_cursor, smart_window_move,
+    toggle_diagnostics_virtual_text, toggle_option, toggle_spell_checking, toggle_string_option,
 };
 
 use nvim_oxi::api::{opts::SetKeymapOpts, set_keymap, set_var, types::Mode};
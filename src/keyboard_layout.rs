@@ -0,0 +1,179 @@
+//! Second keyboard layout awareness, so the normal mode navigation keys still work when the OS
+//! keyboard layout is switched to a non-Latin one:
+//!
+//! - The `langmap` option is generated from the layout definition (`KEYBOARD_LAYOUTS`), it
+//!   maps the non-Latin characters back to the Latin keys on the same physical keyboard position
+//!   in normal, visual and operator-pending modes.
+//!
+//! - The `keymap` option is set to the Neovim builtin keymap of the layout, so you can type the
+//!   non-Latin characters in insert mode by `<C-^>` without switching the OS layout.
+//!
+//! - `:KeyboardLayout {name}`: Switch to another layout definition, `:KeyboardLayout none` to
+//!   disable it.
+//!
+
+///
+/// The keyboard layout definition, the characters are listed by the physical key positions,
+/// `layout_chars` and `latin_chars` MUST have the same character count.
+///
+#[derive(Debug, Clone, Copy)]
+struct KeyboardLayout {
+    name: &'static str,
+
+    //
+    // The Neovim builtin keymap name (`:h keymap`), e.g. "russian-jcukenwin"
+    //
+    keymap: Option<&'static str>,
+    layout_chars: &'static str,
+    latin_chars: &'static str,
+}
+
+///
+/// Define your keyboard layouts here
+///
+const KEYBOARD_LAYOUTS: [KeyboardLayout; 2] = [
+    KeyboardLayout {
+        name: "russian",
+        keymap: Some("russian-jcukenwin"),
+        layout_chars: "ёйцукенгшщзхъфывапролджэячсмитьбюЁЙЦУКЕНГШЩЗХЪФЫВАПРОЛДЖЭЯЧСМИТЬБЮ",
+        latin_chars: "`qwertyuiop[]asdfghjkl;'zxcvbnm,.~QWERTYUIOP{}ASDFGHJKL:\"ZXCVBNM<>",
+    },
+    KeyboardLayout {
+        name: "greek",
+        keymap: Some("greek_utf-8"),
+        layout_chars: "ςερτυθιοπασδφγηξκλζχψωβνμΕΡΤΥΘΙΟΠΑΣΔΦΓΗΞΚΛΖΧΨΩΒΝΜ",
+        latin_chars: "wertyuiopasdfghjklzxcvbnmERTYUIOPASDFGHJKLZXCVBNM",
+    },
+];
+
+///
+/// The layout enabled on startup, `None` to disable it.
+///
+const ENABLED_KEYBOARD_LAYOUT: Option<&'static str> = Some("russian");
+
+///
+/// Escape the special characters of the `langmap` option value, see `:h langmap`
+///
+fn escape_langmap_char(c: char) -> String {
+    match c {
+        ',' | ';' | '\\' | '"' | '|' => format!("\\{c}"),
+        _ => c.to_string(),
+    }
+}
+
+///
+/// Generate the `langmap` option value from the layout definition, e.g. "йq,цw,уe"
+///
+fn get_langmap_value(layout: &KeyboardLayout) -> Option<String> {
+    const LOGGER_PREFIX: &'static str = "[ keyboard_layout - get_langmap_value ]";
+
+    if layout.layout_chars.chars().count() != layout.latin_chars.chars().count() {
+        nvim::print!(
+            "{LOGGER_PREFIX} '{}': 'layout_chars' and 'latin_chars' have different lengths.",
+            layout.name
+        );
+        return None;
+    }
+
+    let pairs = layout
+        .layout_chars
+        .chars()
+        .zip(layout.latin_chars.chars())
+        .map(|(from, to)| format!("{}{}", escape_langmap_char(from), escape_langmap_char(to)))
+        .collect::<Vec<String>>();
+
+    Some(pairs.join(","))
+}
+
+///
+/// Apply the given layout, `None` to reset the related options.
+///
+fn apply_keyboard_layout(layout: Option<&KeyboardLayout>) -> bool {
+    let opts = OptionOpts::builder().scope(OptionScope::Global).build();
+
+    let Some(layout) = layout else {
+        let _ = set_option_value("langmap", "", &opts);
+        let _ = set_option_value("keymap", "", &opts);
+        return true;
+    };
+
+    let Some(langmap) = get_langmap_value(layout) else {
+        return false;
+    };
+
+    let _ = set_option_value("langmap", langmap.as_str(), &opts);
+
+    //
+    // Don't apply `langmap` to the characters resulting from a mapping
+    //
+    let _ = set_option_value("langremap", false, &opts);
+
+    if let Some(keymap) = layout.keymap {
+        let _ = set_option_value("keymap", keymap, &opts);
+
+        //
+        // Start with the Latin input in insert mode and search, `<C-^>` to toggle
+        //
+        let _ = set_option_value("iminsert", 0, &opts);
+        let _ = set_option_value("imsearch", -1, &opts);
+    }
+
+    true
+}
+
+///
+///
+///
+pub fn setup() {
+    if let Some(name) = ENABLED_KEYBOARD_LAYOUT {
+        apply_keyboard_layout(KEYBOARD_LAYOUTS.iter().find(|layout| layout.name == name));
+    }
+
+    let _ = create_user_command(
+        "KeyboardLayout",
+        |args: CommandArgs| {
+            let name = args.args.as_deref().unwrap_or_default().trim();
+
+            if name == "none" {
+                apply_keyboard_layout(None);
+                nvim::print!("[ keyboard_layout ] Disabled.");
+                return;
+            }
+
+            let Some(layout) = KEYBOARD_LAYOUTS.iter().find(|layout| layout.name == name) else {
+                nvim::print!("[ keyboard_layout ] Unknown layout: '{name}'");
+                return;
+            };
+
+            if apply_keyboard_layout(Some(layout)) {
+                nvim::print!("[ keyboard_layout ] Switched to: {}", layout.name);
+            }
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Switch the keyboard layout: ':KeyboardLayout {name|none}'")
+            .nargs(CommandNArgs::One)
+            .complete(CommandComplete::CustomList(Function::from_fn(
+                |(arg_lead, _, _): (String, String, usize)| {
+                    KEYBOARD_LAYOUTS
+                        .iter()
+                        .map(|layout| layout.name)
+                        .chain(std::iter::once("none"))
+                        .filter(|name| name.starts_with(&arg_lead))
+                        .map(|name| name.to_string())
+                        .collect::<Vec<String>>()
+                },
+            )))
+            .build(),
+    );
+}
+
+use nvim_oxi::{
+    self as nvim, Function,
+    api::{
+        create_user_command,
+        opts::{CreateCommandOpts, OptionOpts, OptionScope},
+        set_option_value,
+        types::{CommandArgs, CommandComplete, CommandNArgs},
+    },
+};
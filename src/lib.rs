@@ -47,12 +47,69 @@
 ///
 #[nvim_oxi::plugin]
 fn my_neovim_configuration() -> bool {
+    //
+    // The main thread bridge goes first, so the background threads spawned by any `setup()`
+    // can use `main_thread::run()`. Then the config file, the modules read their options from it.
+    // The usage stats goes before the keybindings, it skips the ones which exist before it.
+    //
+    main_thread::setup();
+    config::setup();
+    usage_stats::setup();
     settings::setup();
+    true_color::setup();
+    os_theme::setup();
+    remote_session::setup();
     keybindings::setup();
+    keyboard_layout::setup();
     my_dired::setup();
     auto_groups::setup();
-    // picker::setup();
+    picker::setup();
     project_command::setup();
+    environment::setup();
+    executables::setup();
+    floating_terminal::setup();
+    breadcrumb::setup();
+    actions::setup();
+    rpc_server::setup();
+    workspace_rename::setup();
+    scratch::setup();
+    path_expansion::setup();
+    file_rename::setup();
+    file_delete::setup();
+    quickfix_peek::setup();
+    scroll_sync::setup();
+    tab_buffers::setup();
+    buffer_janitor::setup();
+    tab_scope::setup();
+    export::setup();
+    ascii_diagram::setup();
+    file_header::setup();
+    comment_format::setup();
+    commit_message::setup();
+    diagnostic_snippet::setup();
+    calculator::setup();
+    regex_playground::setup();
+    screencast::setup();
+    project_spell::setup();
+    fold_region::setup();
+    statusline::setup();
+    structured_paste::setup();
+    winbar::setup();
+    file_path_copy::setup();
+    smart_quit::setup();
+    tabline::setup();
+    git::setup();
+    generated_guard::setup();
+    git_commit::setup();
+    lsp::setup();
+    diagnostics::setup();
+    treesitter::setup();
+    completion::setup();
+    notify::setup();
+    registers::setup();
+    snippets::setup();
+    editing::setup();
+    comment::setup();
 
     #[cfg(feature = "enable_plugin_debug_print")]
     nvim::print!("\n>>> My Neovim Configuration has loaded successfully.");
@@ -63,10 +120,66 @@ fn my_neovim_configuration() -> bool {
 #[cfg(feature = "enable_plugin_debug_print")]
 use nvim_oxi as nvim;
 
+mod actions;
+mod ascii_diagram;
 mod auto_groups;
+mod breadcrumb;
+mod buffer_janitor;
+mod calculator;
+mod cmdline;
+mod comment;
+mod comment_format;
+mod commit_message;
+mod completion;
+mod config;
+mod diagnostic_snippet;
+mod diagnostics;
+mod editing;
+mod environment;
+mod error;
+mod executables;
+mod export;
+mod extended_api;
+mod file_change;
+mod file_delete;
+mod file_header;
+mod file_path_copy;
+mod file_rename;
+mod floating_terminal;
+mod fold_region;
+mod generated_guard;
+mod git;
+mod git_commit;
 mod keybindings;
+mod keyboard_layout;
+mod lsp;
+mod main_thread;
 mod my_dired;
+mod notify;
+mod os_theme;
+mod path_expansion;
 mod picker;
 mod project_command;
+mod project_spell;
+mod quickfix_peek;
+mod regex_playground;
+mod registers;
+mod remote_session;
+mod rpc_server;
+mod scratch;
+mod screencast;
+mod scroll_sync;
 mod settings;
+mod smart_quit;
+mod snippets;
+mod statusline;
+mod structured_paste;
+mod tab_buffers;
+mod tab_scope;
+mod tabline;
+mod treesitter;
+mod true_color;
+mod usage_stats;
 mod utils;
+mod winbar;
+mod workspace_rename;
@@ -0,0 +1,282 @@
+//! The language servers bootstrap, no `nvim-lspconfig` needed:
+//!
+//! - The server in `LSP_SERVERS` is started by `vim.lsp.start` when the buffer of its filetype
+//!   is opened, the running client of the same root directory is reused.
+//! - The root directory is the nearest ancestor which has any of the root markers, or the
+//!   current working directory.
+//! - After the server attaches to the buffer (`LspAttach`), the buffer-local keymaps are set:
+//!
+//!   `gd`: Go to definition
+//!   `gr`: List references
+//!   `K`: Hover
+//!   `<leader>rn`: Rename the symbol
+//!   `<leader>ca`: Code action (also in `VISUAL` mode)
+//!
+
+///
+/// The language server and its settings
+///
+struct LspServer {
+    name: &'static str,
+    cmd: &'static [&'static str],
+    filetypes: &'static [&'static str],
+    root_markers: &'static [&'static str],
+
+    ///
+    /// The `settings` (JSON) sent by `workspace/configuration`, `None` means no settings.
+    ///
+    settings: Option<&'static str>,
+}
+
+///
+/// Define your language servers here, the server is skipped if its executable isn't in `$PATH`.
+///
+const LSP_SERVERS: [LspServer; 4] = [
+    LspServer {
+        name: "rust-analyzer",
+        cmd: &["rust-analyzer"],
+        filetypes: &["rust"],
+        root_markers: &["Cargo.toml", ".git"],
+        settings: Some(
+            r#"{ "rust-analyzer": { "check": { "command": "clippy" }, "cargo": { "allFeatures": true } } }"#,
+        ),
+    },
+    LspServer {
+        name: "clangd",
+        cmd: &["clangd", "--background-index", "--clang-tidy"],
+        filetypes: &["c", "cpp", "objc", "objcpp"],
+        root_markers: &[
+            "compile_commands.json",
+            "compile_flags.txt",
+            ".clangd",
+            ".git",
+        ],
+        settings: None,
+    },
+    LspServer {
+        name: "zls",
+        cmd: &["zls"],
+        filetypes: &["zig", "zir"],
+        root_markers: &["build.zig", "build.zig.zon", ".git"],
+        settings: Some(r#"{ "zls": { "enable_build_on_save": true } }"#),
+    },
+    LspServer {
+        name: "lua-language-server",
+        cmd: &["lua-language-server"],
+        filetypes: &["lua"],
+        root_markers: &[".luarc.json", ".stylua.toml", ".git"],
+        settings: Some(
+            r#"{ "Lua": { "runtime": { "version": "LuaJIT" }, "diagnostics": { "globals": ["vim"] }, "workspace": { "checkThirdParty": false } } }"#,
+        ),
+    },
+];
+
+///
+/// The client capabilities (JSON) merged into the default ones, the file operations are used by
+/// `file_rename`.
+///
+const LSP_EXTRA_CAPABILITIES: &'static str =
+    r#"{ "workspace": { "fileOperations": { "willRename": true, "didRename": true } } }"#;
+
+///
+/// Private module-scope state: the servers which are already reported as missing, so it's only
+/// reported once.
+///
+static MISSING_LSP_SERVERS: LazyLock<Mutex<HashSet<&'static str>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+///
+/// Start (or reuse) the language server for the buffer
+///
+fn start_lsp_server(server: &LspServer, buffer: &Buffer) {
+    let is_executable = call_function::<_, i64>("executable", (server.cmd[0],)).unwrap_or(0) == 1;
+    if !is_executable {
+        if MISSING_LSP_SERVERS.lock().unwrap().insert(server.name) {
+            echo_message(
+                "lsp",
+                &format!("'{}' not found in $PATH, skipped.", server.cmd[0]),
+                EchoLevel::Warning,
+            );
+        }
+        return;
+    }
+
+    let lua_code = r#"local bufnr, name, cmd, root_markers, settings, capabilities = ...
+return vim.lsp.start({
+    name = name,
+    cmd = cmd,
+    root_dir = vim.fs.root(bufnr, root_markers) or vim.fn.getcwd(),
+    settings = settings and vim.json.decode(settings) or nil,
+    capabilities = vim.tbl_deep_extend(
+        "force",
+        vim.lsp.protocol.make_client_capabilities(),
+        vim.json.decode(capabilities)
+    ),
+}, { bufnr = bufnr })"#;
+
+    let client_id = try_exec_lua::<_, Object>(
+        lua_code,
+        Array::from_iter([
+            Object::from(buffer.handle() as i64),
+            Object::from(server.name),
+            Object::from(Array::from_iter(server.cmd.iter().copied())),
+            Object::from(Array::from_iter(server.root_markers.iter().copied())),
+            server.settings.map_or(Object::nil(), Object::from),
+            Object::from(LSP_EXTRA_CAPABILITIES),
+        ]),
+    )
+    .context(&format!("start the language server '{}'", server.name));
+
+    //
+    // The error is reported, `nil` means `vim.lsp.start()` refused to start it.
+    //
+    if report("lsp", client_id).is_some_and(|id| id.is_nil()) {
+        echo_message(
+            "lsp",
+            &format!("Failed to start '{}'.", server.name),
+            EchoLevel::Error,
+        );
+    }
+}
+
+///
+/// Set the buffer-local keymaps after the language server attaches to the buffer
+///
+fn set_lsp_buffer_keymaps(buffer: &mut Buffer) {
+    let lsp_keymaps: Vec<(Vec<Mode>, &str, &'static str, &str)> = vec![
+        (
+            vec![Mode::Normal],
+            "gd",
+            "vim.lsp.buf.definition()",
+            "'gd': Go to definition",
+        ),
+        (
+            vec![Mode::Normal],
+            "gr",
+            "vim.lsp.buf.references()",
+            "'gr': List references",
+        ),
+        (vec![Mode::Normal], "K", "vim.lsp.buf.hover()", "'K': Hover"),
+        (
+            vec![Mode::Normal],
+            "<leader>rn",
+            "vim.lsp.buf.rename()",
+            "'<leader>rn': Rename the symbol",
+        ),
+        (
+            vec![Mode::Normal, Mode::Visual],
+            "<leader>ca",
+            "vim.lsp.buf.code_action()",
+            "'<leader>ca': Code action",
+        ),
+    ];
+
+    for (modes, lhs, lua_code, desc) in lsp_keymaps {
+        for mode in modes {
+            let _ = buffer.set_keymap(
+                mode,
+                lhs,
+                "",
+                &SetKeymapOpts::builder()
+                    .desc(desc)
+                    //
+                    // Don't wait for the global `grn`, `gra`, `grr` etc.
+                    //
+                    .nowait(true)
+                    .silent(true)
+                    .callback(move |_| {
+                        report(
+                            "lsp",
+                            try_exec_lua::<_, Object>(lua_code, Array::new())
+                                .context(&format!("run '{lua_code}'")),
+                        );
+                        ()
+                    })
+                    .build(),
+            );
+        }
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    let group = create_augroup(
+        "custom-lsp-group",
+        &CreateAugroupOpts::builder().clear(true).build(),
+    )
+    .unwrap();
+
+    let filetypes = LSP_SERVERS
+        .iter()
+        .flat_map(|server| server.filetypes.iter().copied())
+        .collect::<Vec<&str>>();
+
+    let _ = create_autocmd(
+        vec!["FileType"],
+        &CreateAutocmdOpts::builder()
+            .group(group)
+            .patterns(filetypes)
+            .callback(|args: AutocmdCallbackArgs| {
+                //
+                // Only the file buffer, not the `nofile`, `help` buffers
+                //
+                let buffer_type = get_option_value::<NvimString>(
+                    "buftype",
+                    &OptionOpts::builder().buffer(args.buffer.clone()).build(),
+                )
+                .unwrap_or_default();
+
+                if buffer_type.is_empty() {
+                    if let Some(server) = LSP_SERVERS
+                        .iter()
+                        .find(|server| server.filetypes.contains(&args.r#match.as_str()))
+                    {
+                        start_lsp_server(server, &args.buffer);
+                    }
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    let _ = create_autocmd(
+        vec!["LspAttach"],
+        &CreateAutocmdOpts::builder()
+            .group(group)
+            .callback(|args: AutocmdCallbackArgs| {
+                let mut buffer = args.buffer;
+                set_lsp_buffer_keymaps(&mut buffer);
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+use crate::{
+    error::{ResultContext, report},
+    extended_api::{EchoLevel, echo_message, try_exec_lua},
+};
+
+use std::{
+    collections::HashSet,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    Array, Object, String as NvimString,
+    api::{
+        Buffer, call_function, create_augroup, create_autocmd, get_option_value,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, SetKeymapOpts},
+        types::{AutocmdCallbackArgs, Mode},
+    },
+};
@@ -0,0 +1,111 @@
+//! The bridge to run the closures on the Neovim main thread, the Neovim API can only be called
+//! on the main thread, so the background threads (e.g. the `rg` scan of `workspace_rename`) send
+//! the closures which touch the API here:
+//!
+//! ```rust
+//! std::thread::spawn(|| {
+//!     let result = scan_something_slow();
+//!
+//!     main_thread::run(move || {
+//!         echo_message("scan", &format!("Done: {result}"), EchoLevel::Success);
+//!     });
+//! });
+//! ```
+//!
+//! - The closures are queued in the channel, and the libuv `AsyncHandle` wakes up the main loop
+//!   to run them (in the sending order) by `nvim_oxi::schedule`, as the `AsyncHandle` callback
+//!   itself still can't call the API.
+//! - Several `run()` calls may be woken up only once (libuv coalesces the `uv_async_send`), all the
+//!   queued closures are drained every time.
+//! - `setup()` must be called on the main thread (in the plugin entry point), before any
+//!   background thread calls `run()`.
+//!
+//! The `jobstart` callbacks (`utils::run_async`) and the timers are already on the main thread,
+//! they don't need it.
+//!
+
+///
+/// The closure to run on the main thread
+///
+type MainThreadTask = Box<dyn FnOnce() + Send + 'static>;
+
+///
+/// The sender of the queue and the handle to wake up the main loop. `AsyncHandle` is `Send` but
+/// not `Sync`, the `Mutex` makes the bridge shareable in the `static`.
+///
+struct MainThreadBridge {
+    sender: Sender<MainThreadTask>,
+    handle: Mutex<AsyncHandle>,
+}
+
+///
+/// Private module-scope state: it's only set once by `setup()`
+///
+static MAIN_THREAD_BRIDGE: OnceLock<MainThreadBridge> = OnceLock::new();
+
+///
+/// Run the closure on the Neovim main thread, it's safe to call from any thread (including the
+/// main thread, the closure runs in the next event loop tick then).
+///
+/// Return `false` if the bridge isn't set up or the main loop is gone (e.g. Neovim is exiting),
+/// the closure is dropped without running.
+///
+pub fn run<F>(task: F) -> bool
+where
+    F: FnOnce() + Send + 'static,
+{
+    let Some(bridge) = MAIN_THREAD_BRIDGE.get() else {
+        return false;
+    };
+
+    bridge.sender.send(Box::new(task)).is_ok()
+        && bridge
+            .handle
+            .lock()
+            .is_ok_and(|handle| handle.send().is_ok())
+}
+
+///
+///
+///
+pub fn setup() {
+    if MAIN_THREAD_BRIDGE.get().is_some() {
+        return;
+    }
+
+    let (sender, receiver) = channel::<MainThreadTask>();
+
+    let handle = AsyncHandle::new(move || {
+        //
+        // Drain the queue, the closures run in the sending order in the scheduled callback.
+        //
+        let tasks = receiver.try_iter().collect::<Vec<MainThreadTask>>();
+        if !tasks.is_empty() {
+            nvim::schedule(move |_| {
+                for task in tasks {
+                    task();
+                }
+                ()
+            });
+        }
+
+        Ok::<_, Infallible>(())
+    });
+
+    if let Ok(handle) = handle {
+        let _ = MAIN_THREAD_BRIDGE.set(MainThreadBridge {
+            sender,
+            handle: Mutex::new(handle),
+        });
+    }
+}
+
+use std::{
+    convert::Infallible,
+    sync::{
+        Mutex, OnceLock,
+        mpsc::{Sender, channel},
+    },
+};
+
+use nvim_oxi::{self as nvim, libuv::AsyncHandle};
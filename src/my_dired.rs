@@ -5,6 +5,8 @@
 //!     - delete
 //!     - rename
 //!     - copy
+//!     - undo the last file operation (`u`), `:DiredOps` shows the operation journal
+//!     - copy the name (`w`) or the absolute path (`W`) of the item to the clipboard
 //!     - ...
 //!
 //!
@@ -24,17 +26,96 @@
 //!  );
 //! ```
 
+///
+/// The line classification of the dired buffer, it's generated on every render. Only the `Entry`
+/// lines can be parsed as file or directory.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DiredLineKind {
+    Header,
+    Summary, // The "total 123" line of the `ls` output
+    Entry,
+    Empty,
+}
+
 #[derive(Debug, Default)]
 struct MyDiredState {
     last_dired_buffer_dir: String,
+
+    //
+    // One item per dired buffer line (index = row - 1)
+    //
+    line_kinds: Vec<DiredLineKind>,
+
+    //
+    // The visited directories, the latest one on top
+    //
+    visited_dirs: Vec<String>,
+
+    //
+    // All executed file operations, the latest one at the end
+    //
+    operation_journal: Vec<DiredOperation>,
 }
 
+///
+/// The executed file operation with the command list to invert it (if possible)
+///
+#[derive(Debug, Clone)]
+struct DiredOperation {
+    action: MyDiredItemAction,
+    cmd_list: Vec<String>,
+
+    //
+    // `None` means it can't be undone, e.g. the existing file was overwritten.
+    //
+    undo_cmd_list: Option<Vec<String>>,
+
+    time: u64, // Seconds since UNIX EPOCH
+    undone: bool,
+}
+
+///
+/// The sub directory of `get_data_dir()` to store the deleted items, so they can be restored
+/// by undo.
+///
+const DIRED_TRASH_SUB_DIR: &'static str = "dired_trash";
+
+///
+/// The max count of the `MyDiredState.visited_dirs`
+///
+const DIRED_HISTORY_MAX_COUNT: usize = 50;
+
+///
+/// The max count of the `MyDiredState.operation_journal`, the trash item of the evicted delete
+/// operation is removed permanently, as it can't be restored by undo anymore.
+///
+const DIRED_JOURNAL_MAX_COUNT: usize = 100;
+
 ///
 /// Private module-scope state
 ///
 static MY_DIRED_STATE: LazyLock<Mutex<MyDiredState>> =
     LazyLock::new(|| Mutex::new(MyDiredState::default()));
 
+///
+/// The first 2 lines of the dired buffer are the header, the cursor should never stay on them:
+///
+/// # [ Dired buffer ]
+/// /Users/wison/rust:
+///
+const DIRED_BUFFER_HEADER_LINE_COUNT: usize = 2;
+
+///
+/// Cursor movement inside the dired buffer
+///
+enum DiredCursorMovement {
+    Down,
+    Up,
+    First,
+    Last,
+}
+
 ///
 /// Fast goto directory
 ///
@@ -189,6 +270,41 @@ fn get_dired_buffer(create_new_one_if_not_exists: bool) -> i32 {
                 "Dired buffer: Rename file or directory",
                 Box::new(|| rename()),
             ),
+            (
+                "w",
+                "Dired buffer: Copy the file or directory name",
+                Box::new(|| copy_item_path(false)),
+            ),
+            (
+                "W",
+                "Dired buffer: Copy the absolute path of the file or directory",
+                Box::new(|| copy_item_path(true)),
+            ),
+            (
+                "u",
+                "Dired buffer: Undo the last file operation",
+                Box::new(|| undo_last_operation()),
+            ),
+            (
+                "j",
+                "Dired buffer: Move down ([count]j)",
+                Box::new(|| move_cursor(DiredCursorMovement::Down)),
+            ),
+            (
+                "k",
+                "Dired buffer: Move up ([count]k)",
+                Box::new(|| move_cursor(DiredCursorMovement::Up)),
+            ),
+            (
+                "gg",
+                "Dired buffer: Go to the first entry ([count]gg goes to line [count])",
+                Box::new(|| move_cursor(DiredCursorMovement::First)),
+            ),
+            (
+                "G",
+                "Dired buffer: Go to the last entry ([count]G goes to line [count])",
+                Box::new(|| move_cursor(DiredCursorMovement::Last)),
+            ),
             (
                 "gh",
                 "Dired buffer: Go home",
@@ -246,6 +362,24 @@ fn get_dired_buffer(create_new_one_if_not_exists: bool) -> i32 {
             );
         }
 
+        //
+        // Clamp the cursor for all other movements (mouse click, search, etc.)
+        //
+        let _ = create_autocmd(
+            vec!["CursorMoved"],
+            &CreateAutocmdOpts::builder()
+                .buffer(dired_buffer.clone())
+                .callback(|_| {
+                    clamp_cursor_to_entries();
+
+                    //
+                    // Return `true` to delete the autocommand (means only run once)!!!
+                    //
+                    false
+                })
+                .build(),
+        );
+
         //
         // Return the newly created dired buffer handle.
         //
@@ -256,10 +390,31 @@ fn get_dired_buffer(create_new_one_if_not_exists: bool) -> i32 {
     dired_buffer_handle
 }
 
+///
+/// Get back the (entry count, total file size) of the given directory, the sub-directory
+/// sizes are not included.
+///
+fn get_directory_summary(dir: &str) -> Option<(usize, u64)> {
+    let entries = std::fs::read_dir(dir).ok()?;
+
+    let (entry_count, total_size) = entries
+        .filter_map(|entry| entry.ok()?.metadata().ok())
+        .fold((0usize, 0u64), |(count, size), metadata| {
+            let file_size = if metadata.is_file() {
+                metadata.len()
+            } else {
+                0
+            };
+            (count + 1, size + file_size)
+        });
+
+    Some((entry_count, total_size))
+}
+
 ///
 /// Run ls command and fill the dired buffer and switch it in current window
 ///
-fn list_directories_into_dired_buffer(dired_buffer_handle: i32, dir: &str) {
+fn list_directories_into_dired_buffer(dired_buffer_handle: i32, dir: &str) -> Result<()> {
     #[cfg(feature = "enable_my_dired_debug_print")]
     const LOGGER_PREFIX: &'static str = "[ my_dired - list_directories_into_dired_buffer ]";
 
@@ -273,7 +428,7 @@ fn list_directories_into_dired_buffer(dired_buffer_handle: i32, dir: &str) {
 
     // Allow to modify before finishing the command
     let opts = OptionOpts::builder().buffer(dired_buffer.clone()).build();
-    let _ = set_option_value("modifiable", true, &opts);
+    set_option_value("modifiable", true, &opts).context("make the dired buffer modifiable")?;
 
     match cmd_utils::execute_command(vec!["ls", "-lhta", dir]) {
         cmd_utils::ExecuteCommandResult::Success {
@@ -290,8 +445,15 @@ fn list_directories_into_dired_buffer(dired_buffer_handle: i32, dir: &str) {
             //
             // Set dired buffer content
             //
+            let header_line = match get_directory_summary(dir) {
+                Some((entry_count, total_size)) => format!(
+                    "# [ Dired buffer ]  {entry_count} entries, {}",
+                    format_size(total_size)
+                ),
+                None => "# [ Dired buffer ]".to_string(),
+            };
             let dir_title_line = format!("{dir}:");
-            let mut dired_buffer_content = vec!["# [ Dired buffer ]", &dir_title_line];
+            let mut dired_buffer_content = vec![header_line.as_str(), &dir_title_line];
             dired_buffer_content.reserve(100);
 
             dired_buffer_content.extend(output.split('\n'));
@@ -310,18 +472,35 @@ fn list_directories_into_dired_buffer(dired_buffer_handle: i32, dir: &str) {
             // 0..  - The range of first line to the last line
             // ..   - The range of all lines
             //
-            let _ = dired_buffer.set_lines(.., true, dired_buffer_content);
+            let line_kinds = classify_dired_lines(&dired_buffer_content);
+            let set_lines_result = dired_buffer.set_lines(.., true, dired_buffer_content);
 
             //
-            // Not allow to modify anymore
+            // Not allow to modify anymore, even if it failed to set the lines
             //
-            let _ = set_option_value("modifiable", false, &opts);
+            set_option_value("modifiable", false, &opts)
+                .context("make the dired buffer unmodifiable")?;
+            set_lines_result.context("set the dired buffer lines")?;
+
+            //
+            // The line kinds go with the buffer lines right away, so the cursor clamp below (and
+            // the later `CursorMoved` ones) never use the previous directory's rows, even if the
+            // `lcd` fails.
+            //
+            {
+                let mut locked_state = MY_DIRED_STATE.lock().unwrap();
+                locked_state.last_dired_buffer_dir = dir.to_owned();
+                locked_state.line_kinds = line_kinds;
+            }
 
             //
             // Switch to current window and disable spell checking
             //
-            let _ = set_current_buf(&dired_buffer);
-            let _ = set_option_value("spell", false, &opts);
+            set_current_buf(&dired_buffer).context("switch to the dired buffer")?;
+            set_option_value("spell", false, &opts).context("disable the dired buffer spell")?;
+
+            // Never let the cursor sit on the header lines after refreshing
+            clamp_cursor_to_entries();
 
             //
             // Change working directory to `dir`, so you're able to manipulate files
@@ -331,23 +510,141 @@ fn list_directories_into_dired_buffer(dired_buffer_handle: i32, dir: &str) {
             let lcd_cmd_info = CmdInfos::builder().cmd(lcd_command).args([dir]).build();
             let lcd_command_opts = CmdOpts::builder().output(false).build();
             let lcd_cmd_result = vim_cmd(&lcd_cmd_info, &lcd_command_opts);
-            let _ = &lcd_cmd_result;
             #[cfg(feature = "enable_my_dired_debug_print")]
             nvim::print!("\n>>> {LOGGER_PREFIX} lcd_cmd_result: {:?}", lcd_cmd_result);
+            lcd_cmd_result.context(&format!("change the working directory to '{dir}'"))?;
 
             //
-            // Update internal state
+            // Update the visited history
             //
-            MY_DIRED_STATE.lock().unwrap().last_dired_buffer_dir = dir.to_owned();
+            {
+                let mut locked_state = MY_DIRED_STATE.lock().unwrap();
+                locked_state.visited_dirs.retain(|d| d != dir);
+                locked_state.visited_dirs.insert(0, dir.to_owned());
+                locked_state.visited_dirs.truncate(DIRED_HISTORY_MAX_COUNT);
+            }
+
+            Ok(())
         }
         cmd_utils::ExecuteCommandResult::Fail { error_message } => {
-            let _ = &error_message;
             #[cfg(feature = "enable_my_dired_debug_print")]
             nvim::print!("\n>>> {LOGGER_PREFIX} error: {}", error_message);
+
+            //
+            // Keep the buffer unmodifiable as before
+            //
+            let _ = set_option_value("modifiable", false, &opts);
+            Err(Error::external_tool("ls", &error_message))
+        }
+    }
+}
+
+///
+/// Classify the given dired buffer lines, the first `DIRED_BUFFER_HEADER_LINE_COUNT` lines are
+/// always the header.
+///
+fn classify_dired_lines(lines: &[&str]) -> Vec<DiredLineKind> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            if index < DIRED_BUFFER_HEADER_LINE_COUNT {
+                DiredLineKind::Header
+            } else if line.trim().is_empty() {
+                DiredLineKind::Empty
+            } else if line.starts_with("total ") {
+                DiredLineKind::Summary
+            } else {
+                DiredLineKind::Entry
+            }
+        })
+        .collect()
+}
+
+///
+/// Get back the line kind of the given row (1-based), `None` if it's out of range.
+///
+fn get_dired_line_kind(row: usize) -> Option<DiredLineKind> {
+    if row == 0 {
+        return None;
+    }
+
+    MY_DIRED_STATE
+        .lock()
+        .unwrap()
+        .line_kinds
+        .get(row - 1)
+        .copied()
+}
+
+///
+/// Get back the (first, last) row (1-based) of the entry lines in the dired buffer by the
+/// `DiredLineKind` of the last render, so the header, the `total` summary line and the trailing
+/// empty lines are excluded.
+///
+fn get_dired_entry_rows(dired_buffer: &Buffer) -> Option<(usize, usize)> {
+    let line_count = dired_buffer.line_count().ok()?;
+
+    let locked_state = MY_DIRED_STATE.lock().unwrap();
+    let line_kinds = &locked_state.line_kinds[..line_count.min(locked_state.line_kinds.len())];
+    let is_entry = |kind: &DiredLineKind| *kind == DiredLineKind::Entry;
+
+    let first_row = line_kinds.iter().position(is_entry)? + 1;
+    let last_row = line_kinds.iter().rposition(is_entry)? + 1;
+
+    Some((first_row, last_row))
+}
+
+///
+/// Move the cursor back to the entry lines if it's on the header or the summary lines
+///
+fn clamp_cursor_to_entries() {
+    let mut current_window = Window::current();
+    let current_buffer = Buffer::current();
+
+    if let (Ok((row, col)), Some((first_row, last_row))) = (
+        current_window.get_cursor(),
+        get_dired_entry_rows(&current_buffer),
+    ) {
+        let clamped_row = row.clamp(first_row, last_row);
+        if clamped_row != row {
+            let _ = current_window.set_cursor(clamped_row, col);
         }
     }
 }
 
+///
+/// Move the cursor with the `[count]` prefix and clamp it inside the entry lines
+///
+fn move_cursor(movement: DiredCursorMovement) {
+    let mut current_window = Window::current();
+    let current_buffer = Buffer::current();
+
+    let (Ok((row, col)), Some((first_row, last_row))) = (
+        current_window.get_cursor(),
+        get_dired_entry_rows(&current_buffer),
+    ) else {
+        return;
+    };
+
+    //
+    // `v:count` is `0` when no count is typed, `v:count1` is `1` in that case.
+    //
+    let count = get_vvar::<usize>("count").unwrap_or(0);
+    let count1 = get_vvar::<usize>("count1").unwrap_or(1);
+
+    let target_row = match movement {
+        DiredCursorMovement::Down => row.saturating_add(count1),
+        DiredCursorMovement::Up => row.saturating_sub(count1),
+        DiredCursorMovement::First if count > 0 => count,
+        DiredCursorMovement::First => first_row,
+        DiredCursorMovement::Last if count > 0 => count,
+        DiredCursorMovement::Last => last_row,
+    };
+
+    let _ = current_window.set_cursor(target_row.clamp(first_row, last_row), col);
+}
+
 ///
 /// Open the dired buffer based on the current buffer filename
 ///
@@ -403,13 +700,16 @@ fn open() {
     //
     // List and update `dired_buffer`
     //
-    list_directories_into_dired_buffer(
-        dired_buffer_handle,
-        if dir == "" {
-            current_dir.to_str().unwrap()
-        } else {
-            dir
-        },
+    report(
+        "my_dired",
+        list_directories_into_dired_buffer(
+            dired_buffer_handle,
+            if dir == "" {
+                current_dir.to_str().unwrap()
+            } else {
+                dir
+            },
+        ),
     );
 }
 
@@ -477,7 +777,10 @@ fn go_parent_directory() {
         #[cfg(feature = "enable_my_dired_debug_print")]
         nvim::print!("\n>>> {LOGGER_PREFIX} dir: {dir}",);
 
-        list_directories_into_dired_buffer(dired_buffer_handle, &dir);
+        report(
+            "my_dired",
+            list_directories_into_dired_buffer(dired_buffer_handle, &dir),
+        );
     }
 }
 
@@ -516,6 +819,25 @@ fn get_current_dired_buffer_item(escape_filename: bool) -> Option<CurrentDiredBu
         return None;
     }
 
+    //
+    // Only the entry lines can be parsed, ignore the header, summary and empty lines.
+    //
+    let cursor_row = Window::current()
+        .get_cursor()
+        .map(|(row, _)| row)
+        .unwrap_or(0);
+    match get_dired_line_kind(cursor_row) {
+        Some(DiredLineKind::Entry) => {}
+        _ => {
+            notify(
+                "my_dired",
+                "Not a file or directory line, ignored.",
+                EchoLevel::Info,
+            );
+            return None;
+        }
+    }
+
     //
     // Get the current cursor line from the dired_buffer and get the last column
     //
@@ -667,7 +989,10 @@ fn open_directory_or_file() {
                 );
 
                 if let Some(dir) = parent_dir.to_str() {
-                    list_directories_into_dired_buffer(item.dired_buffer_handle, &dir);
+                    report(
+                        "my_dired",
+                        list_directories_into_dired_buffer(item.dired_buffer_handle, &dir),
+                    );
                 }
             }
         }
@@ -704,7 +1029,10 @@ fn open_directory_or_file() {
                     dir
                 );
 
-                list_directories_into_dired_buffer(item.dired_buffer_handle, &dir);
+                report(
+                    "my_dired",
+                    list_directories_into_dired_buffer(item.dired_buffer_handle, &dir),
+                );
             }
         }
     }
@@ -765,7 +1093,7 @@ fn open_directory_or_file() {
 ///
 ///
 ///
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum MyDiredItemAction {
     Copy,
     Create,
@@ -777,48 +1105,20 @@ enum MyDiredItemAction {
 ///
 ///
 fn prompt_user_to_fill_cmd_list(
-    prompt: &str,
+    prompt_text: &str,
     cmd_list: &mut Vec<String>,
     commands: &[String],
 ) -> bool {
-    //
-    // You can call `nvim_oxi::api::call_function()` to call Vimscript function (NOT lua
-    // function!!!).
-    //
-    // You need to provide the generic type when call this function.
-    //
-    // call_function::<ArgumentsGenericType, ReturnGenericType>(FunctionNameHere, ArgsHere);
-    //
-    // `ArgsHere` need to match the following syntax:
-    // - One param: (A,)
-    // - Twe param: (A, B,)
-    // - Three param: (A, B, C,)
-    // ...
-    //
-    let eval_result = call_function::<_, String>(
-        "luaeval",
-        //
-        // The magic global "_A" is the placeholder, it use the second argument!!!
-        //
-        // Example:
-        //     :luaeval('_A[1] + _A[2]', [40, 2])
-        //   ->: luaeval('40 + 2')
-        //
-        //     :luaeval('string.match(_A, "[a-z]+")', 'XYXfoo123')
-        //   ->:luaeval('string.match( 'XYXfoo123', "[a-z]+")')
-        //
-        (r#"vim.fn.input({ prompt =  _A })"#, prompt),
-    );
-
-    if let Ok(user_input) = eval_result {
-        if user_input == "" {
-            return false;
-        }
-
-        cmd_list.extend_from_slice(commands);
-        cmd_list.push(user_input);
+    let Some(user_input) = prompt(prompt_text, "", None) else {
+        return false;
+    };
+    if user_input == "" {
+        return false;
     }
 
+    cmd_list.extend_from_slice(commands);
+    cmd_list.push(user_input);
+
     return true;
 }
 
@@ -835,16 +1135,12 @@ fn init_cmd_list_by_action(
 
     match action {
         MyDiredItemAction::Create => {
-            let prompt = "Create file or directory (end with '/')";
-            let eval_result = call_function::<_, String>(
-                "luaeval",
-                (r#"vim.fn.input({ prompt =  _A })"#, prompt),
-            );
+            let eval_result = prompt("Create file or directory (end with '/')", "", None);
 
             #[cfg(feature = "enable_my_dired_debug_print")]
             nvim::print!("\n>>> {LOGGER_PREFIX} eval_result: {eval_result:?}");
 
-            if let Ok(new_item) = eval_result {
+            if let Some(new_item) = eval_result {
                 if new_item == "" {
                     return false;
                 }
@@ -909,26 +1205,35 @@ fn init_cmd_list_by_action(
             let item = current_item.unwrap();
             let action_prompt = if item.is_diretory {
                 format!(
-                    "Are you sure to delete '{}' and all its contents? (y/n)",
+                    "Are you sure to delete '{}' and all its contents?",
                     item.name
                 )
             } else {
-                format!("Are you sure to delete '{}'? (y/n)", item.name)
+                format!("Are you sure to delete '{}'?", item.name)
             };
 
-            let eval_result = call_function::<_, String>(
-                "luaeval",
-                (r#"vim.fn.input({ prompt =  _A })"#, action_prompt),
-            );
+            //
+            // `No` is the default choice
+            //
+            if confirm("Delete", &[action_prompt], &["&Yes", "&No"], 1) != Some(0) {
+                return false;
+            }
 
-            if let Ok(delete_confirm) = eval_result {
-                if delete_confirm != "y" && delete_confirm != "Y" {
-                    return false;
+            //
+            // Move it into the trash directory, so it can be restored by undo. Only remove
+            // it permanently when the trash directory isn't available.
+            //
+            match get_dired_trash_path(&item.name) {
+                Some(trash_path) => {
+                    cmd_list.push("mv".to_string());
+                    cmd_list.push(item.name.clone());
+                    cmd_list.push(trash_path);
+                }
+                None => {
+                    cmd_list.push("rm".to_string());
+                    cmd_list.push("-rf".to_string());
+                    cmd_list.push(item.name.clone());
                 }
-
-                cmd_list.push("rm".to_string());
-                cmd_list.push("-rf".to_string());
-                cmd_list.push(item.name.clone());
             }
         } // _ => {
           //     nvim::print!("\n>>> {LOGGER_PREFIX} unsupported action: {action:?}");
@@ -941,7 +1246,7 @@ fn init_cmd_list_by_action(
 ///
 ///
 ///
-fn run_action_on_dired_buffer_item(action: MyDiredItemAction) {
+fn run_action_on_dired_buffer_item(action: MyDiredItemAction) -> Result<()> {
     #[cfg(feature = "enable_my_dired_debug_print")]
     const LOGGER_PREFIX: &'static str = "run_action_on_dired_buffer_item";
 
@@ -958,7 +1263,7 @@ fn run_action_on_dired_buffer_item(action: MyDiredItemAction) {
         MyDiredItemAction::Copy | MyDiredItemAction::Delete | MyDiredItemAction::Rename => {
             current_item = get_current_dired_buffer_item(false);
             if current_item.is_none() {
-                return;
+                return Ok(());
             }
 
             //
@@ -969,7 +1274,7 @@ fn run_action_on_dired_buffer_item(action: MyDiredItemAction) {
                 #[cfg(feature = "enable_my_dired_debug_print")]
                 nvim::print!("\n>>> {LOGGER_PREFIX} doesn't handle '.' or '..' directory");
 
-                return;
+                return Ok(());
             }
 
             dired_buffer_handle = item.dired_buffer_handle;
@@ -980,14 +1285,14 @@ fn run_action_on_dired_buffer_item(action: MyDiredItemAction) {
                 #[cfg(feature = "enable_my_dired_debug_print")]
                 nvim::print!("\n>>> {LOGGER_PREFIX} 'get_dired_buffer(false)' return '-1'.");
 
-                return;
+                return Ok(());
             }
 
             if dired_buffer_handle != Buffer::current().handle() {
                 #[cfg(feature = "enable_my_dired_debug_print")]
                 nvim::print!("\n>>> {LOGGER_PREFIX} dired_buffer is NOT the current buffer, abort");
 
-                return;
+                return Ok(());
             }
         }
     }
@@ -1006,109 +1311,528 @@ fn run_action_on_dired_buffer_item(action: MyDiredItemAction) {
     //
     let mut cmd_vec = Vec::<String>::with_capacity(5);
     if !init_cmd_list_by_action(action, current_item, &mut cmd_vec) {
-        return;
+        return Ok(());
     }
 
+    //
+    // The undo command list has to be created before running the command, as it checks whether
+    // the destination exists or not.
+    //
+    let undo_cmd_vec = get_undo_cmd_list(action, &latest_dir, &cmd_vec);
+
     //
     // Run command
     //
     #[cfg(feature = "enable_my_dired_debug_print")]
     nvim::print!("\n>>> {LOGGER_PREFIX} cmd_vec: {cmd_vec:?}");
 
-    let temp_cmd_list = cmd_vec.iter().map(|v| v.as_str()).collect();
-    match cmd_utils::execute_command(temp_cmd_list) {
-        cmd_utils::ExecuteCommandResult::Success {
-            cmd_desc,
-            exit_code,
-            output,
-        } => {
-            let _ = cmd_desc;
-            let _ = exit_code;
-            let _ = output;
+    //
+    // Run it asynchronously (e.g. copying a big directory), the journal and the dired buffer are
+    // updated after the command exits.
+    //
+    let output_lines = Rc::new(RefCell::new(Vec::<String>::new()));
+
+    let on_output = {
+        let output_lines = output_lines.clone();
+        move |lines: Vec<String>| output_lines.borrow_mut().extend(lines)
+    };
+
+    let on_exit = {
+        let cmd_list = cmd_vec.clone();
+        move |exit_code: i64| {
+            if exit_code != 0 {
+                report_error(
+                    "my_dired",
+                    &Error::external_tool(
+                        &cmd_list.join(" "),
+                        output_lines.borrow().join(" ").trim(),
+                    ),
+                );
+                return;
+            }
+
+            push_operation_journal(DiredOperation {
+                action,
+                cmd_list,
+                undo_cmd_list: undo_cmd_vec,
+                time: now_in_seconds(),
+                undone: false,
+            });
 
             if dired_buffer_handle != -1 {
-                list_directories_into_dired_buffer(dired_buffer_handle, &latest_dir);
+                report(
+                    "my_dired",
+                    list_directories_into_dired_buffer(dired_buffer_handle, &latest_dir),
+                );
             }
         }
-        cmd_utils::ExecuteCommandResult::Fail { error_message } => {
-            let _ = &error_message;
-            #[cfg(feature = "enable_my_dired_debug_print")]
-            nvim::print!("\n>>> {LOGGER_PREFIX} error: {}", error_message);
-        }
+    };
+
+    let cmd_desc = cmd_vec.join(" ");
+    if !run_async(cmd_vec, on_output, on_exit) {
+        #[cfg(feature = "enable_my_dired_debug_print")]
+        nvim::print!("\n>>> {LOGGER_PREFIX} failed to run the command.");
+
+        return Err(Error::external_tool(
+            &cmd_desc,
+            "failed to start the command",
+        ));
     }
+
+    Ok(())
 }
 
 ///
 /// Delete
 ///
 fn delete() {
-    run_action_on_dired_buffer_item(MyDiredItemAction::Delete);
+    report(
+        "my_dired",
+        run_action_on_dired_buffer_item(MyDiredItemAction::Delete),
+    );
 }
 
 //
 // Create file or directory
 //
 fn create() {
-    run_action_on_dired_buffer_item(MyDiredItemAction::Create);
+    report(
+        "my_dired",
+        run_action_on_dired_buffer_item(MyDiredItemAction::Create),
+    );
 }
 
 ///
 /// Copy
 ///
 fn copy() {
-    run_action_on_dired_buffer_item(MyDiredItemAction::Copy);
+    report(
+        "my_dired",
+        run_action_on_dired_buffer_item(MyDiredItemAction::Copy),
+    );
 }
 
 ///
 /// Rename
 ///
 fn rename() {
-    run_action_on_dired_buffer_item(MyDiredItemAction::Rename);
+    report(
+        "my_dired",
+        run_action_on_dired_buffer_item(MyDiredItemAction::Rename),
+    );
 }
 
 ///
-/// Go to the given directory
+/// Copy the name (or the absolute path) of the item under the cursor to the clipboard register
 ///
-fn go_to_directory(dir_type: FastGotoDirectory) {
-    let dired_buffer_handle = get_dired_buffer(true);
+fn copy_item_path(is_absolute: bool) {
+    let Some(item) = get_current_dired_buffer_item(false) else {
+        return;
+    };
+    if item.is_diretory && item.name == ".." {
+        return;
+    }
 
-    if dired_buffer_handle == -1 {
+    let path = if is_absolute {
+        let dired_dir = {
+            let locked_state = MY_DIRED_STATE.lock();
+            let state = locked_state.as_ref().unwrap();
+            state.last_dired_buffer_dir.to_owned()
+        };
+
+        if item.name == "." {
+            dired_dir
+        } else {
+            Path::new(&dired_dir).join(&item.name).display().to_string()
+        }
+    } else {
+        item.name
+    };
+
+    let register = get_clipboard_register();
+    if set_register(register, &RegisterContent::charwise(&path)) {
+        notify(
+            "my_dired",
+            &format!("Copied to the '{register}' register: {path}"),
+            EchoLevel::Success,
+        );
+    }
+}
+
+#[inline]
+fn now_in_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+///
+/// Get back the unique trash path for the given item:
+/// `{trash_dir}/{seconds}_{nanoseconds}[_{counter}]_{item_name}`
+///
+/// The counter is only added when the path already exists, otherwise the `mv` overwrites that
+/// trash item (or moves into it if it's a directory).
+///
+pub fn get_dired_trash_path(item_name: &str) -> Option<String> {
+    let file_name = Path::new(item_name).file_name()?.to_str()?;
+    let trash_dir = get_data_dir(DIRED_TRASH_SUB_DIR)?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let timestamp = format!("{}_{:09}", now.as_secs(), now.subsec_nanos());
+
+    let mut trash_path = trash_dir.join(format!("{timestamp}_{file_name}"));
+    let mut counter = 1;
+    while trash_path.symlink_metadata().is_ok() {
+        trash_path = trash_dir.join(format!("{timestamp}_{counter}_{file_name}"));
+        counter += 1;
+    }
+
+    trash_path.to_str().map(|p| p.to_owned())
+}
+
+///
+/// Append the operation to the journal, and evict the oldest ones if it's full. The trash item
+/// of the evicted delete operation (if it's not undone yet) is removed permanently.
+///
+fn push_operation_journal(operation: DiredOperation) {
+    //
+    // Release the lock before touching the file system
+    //
+    let evicted_operations = {
+        let mut locked_state = MY_DIRED_STATE.lock().unwrap();
+        locked_state.operation_journal.push(operation);
+
+        let evicted_count = locked_state
+            .operation_journal
+            .len()
+            .saturating_sub(DIRED_JOURNAL_MAX_COUNT);
+        locked_state
+            .operation_journal
+            .drain(..evicted_count)
+            .collect::<Vec<DiredOperation>>()
+    };
+
+    let Some(trash_dir) = get_data_dir(DIRED_TRASH_SUB_DIR) else {
         return;
+    };
+
+    for operation in evicted_operations {
+        //
+        // Delete: `mv name trash_path`, only the path under the trash directory is removed.
+        //
+        let trash_path = match (operation.action, operation.undone) {
+            (MyDiredItemAction::Delete, false)
+                if operation.cmd_list.first().is_some_and(|cmd| cmd == "mv") =>
+            {
+                operation.cmd_list.get(2).map(PathBuf::from)
+            }
+            _ => None,
+        };
+        let Some(trash_path) = trash_path.filter(|path| path.starts_with(&trash_dir)) else {
+            continue;
+        };
+
+        let result = if trash_path.is_dir() {
+            std::fs::remove_dir_all(&trash_path)
+        } else {
+            std::fs::remove_file(&trash_path)
+        };
+        report(
+            "my_dired",
+            result.context(&format!("prune the trash item '{}'", trash_path.display())),
+        );
     }
+}
 
-    if let Ok(home_dir) = std::env::var("HOME") {
-        match dir_type {
-            FastGotoDirectory::Home => {
-                list_directories_into_dired_buffer(dired_buffer_handle, &home_dir)
+///
+/// Get back the real destination path of `cp`/`mv`: it's `dest/src_name` if `dest` is an
+/// existing directory.
+///
+fn resolve_destination_path(dir: &str, src: &str, dest: &str) -> PathBuf {
+    let dest_path = Path::new(dir).join(dest);
+
+    if dest_path.is_dir()
+        && let Some(src_name) = Path::new(src).file_name()
+    {
+        return dest_path.join(src_name);
+    }
+
+    dest_path
+}
+
+///
+/// Get back the command list (with absolute paths) to invert the given operation, `None` if it
+/// can't be undone.
+///
+/// - Create: `mkdir name` / `touch name` <--> `rmdir /dir/name` / `rm /dir/name`
+/// - Copy:   `cp -rf src dest`           <--> `rm -rf /dir/dest`
+/// - Rename: `mv src dest`               <--> `mv /dir/dest /dir/src`
+/// - Delete: `mv name trash_path`        <--> `mv trash_path /dir/name`
+///
+/// The `touch` on an existing file, and `cp`/`mv` overwrites an existing file can't be undone.
+///
+fn get_undo_cmd_list(
+    action: MyDiredItemAction,
+    dir: &str,
+    cmd_list: &[String],
+) -> Option<Vec<String>> {
+    let to_absolute_path = |path: &str| Path::new(dir).join(path).to_str().map(|p| p.to_owned());
+
+    match action {
+        MyDiredItemAction::Create => {
+            let target = to_absolute_path(cmd_list.last()?)?;
+            if Path::new(&target).exists() {
+                return None;
             }
-            FastGotoDirectory::EmacsConfig => list_directories_into_dired_buffer(
-                dired_buffer_handle,
-                &format!("{home_dir}/.config/emacs"),
-            ),
-            FastGotoDirectory::NeovimConfig => list_directories_into_dired_buffer(
-                dired_buffer_handle,
-                &format!("{home_dir}/.config/nvim"),
-            ),
-            FastGotoDirectory::C => {
-                list_directories_into_dired_buffer(dired_buffer_handle, &format!("{home_dir}/c"))
+
+            match cmd_list.first()?.as_str() {
+                "mkdir" => Some(vec!["rmdir".to_string(), target]),
+                _ => Some(vec!["rm".to_string(), target]),
             }
-            FastGotoDirectory::Odin => {
-                list_directories_into_dired_buffer(dired_buffer_handle, &format!("{home_dir}/odin"))
+        }
+        MyDiredItemAction::Copy => {
+            let target = resolve_destination_path(dir, cmd_list.get(2)?, cmd_list.get(3)?);
+            if target.exists() {
+                return None;
             }
-            FastGotoDirectory::Rust => {
-                list_directories_into_dired_buffer(dired_buffer_handle, &format!("{home_dir}/rust"))
+
+            Some(vec![
+                "rm".to_string(),
+                "-rf".to_string(),
+                target.to_str()?.to_owned(),
+            ])
+        }
+        MyDiredItemAction::Rename => {
+            let src = cmd_list.get(1)?;
+            let target = resolve_destination_path(dir, src, cmd_list.get(2)?);
+            if target.exists() {
+                return None;
             }
-            FastGotoDirectory::Temp => {
-                list_directories_into_dired_buffer(dired_buffer_handle, &format!("{home_dir}/temp"))
+
+            Some(vec![
+                "mv".to_string(),
+                target.to_str()?.to_owned(),
+                to_absolute_path(src)?,
+            ])
+        }
+        MyDiredItemAction::Delete => {
+            // `rm -rf` can't be undone
+            if cmd_list.first()? != "mv" {
+                return None;
             }
-            FastGotoDirectory::Download => list_directories_into_dired_buffer(
-                dired_buffer_handle,
-                &format!("{home_dir}/Downloads"),
-            ),
+
+            Some(vec![
+                "mv".to_string(),
+                cmd_list.get(2)?.to_owned(),
+                to_absolute_path(cmd_list.get(1)?)?,
+            ])
+        }
+    }
+}
+
+///
+/// Undo the last file operation (which hasn't been undone yet)
+///
+fn undo_last_operation() {
+    //
+    // Copy the last operation and release the mutex lock immediately.
+    //
+    let last_operation = {
+        let locked_state = MY_DIRED_STATE.lock().unwrap();
+        locked_state
+            .operation_journal
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, op)| !op.undone)
+            .map(|(index, op)| (index, op.clone()))
+    };
+
+    let Some((operation_index, operation)) = last_operation else {
+        notify("my_dired", "No file operation to undo.", EchoLevel::Warning);
+        return;
+    };
+
+    let Some(undo_cmd_list) = operation.undo_cmd_list else {
+        notify(
+            "my_dired",
+            &format!("Can't undo '{}'.", operation.cmd_list.join(" ")),
+            EchoLevel::Warning,
+        );
+        return;
+    };
+
+    //
+    // `No` is the default choice
+    //
+    let message_lines = [
+        format!("Undo '{}'?", operation.cmd_list.join(" ")),
+        format!("It runs '{}'.", undo_cmd_list.join(" ")),
+    ];
+    if confirm("Undo", &message_lines, &["&Yes", "&No"], 1) != Some(0) {
+        return;
+    }
+
+    let temp_cmd_list = undo_cmd_list.iter().map(|v| v.as_str()).collect();
+    match cmd_utils::execute_command(temp_cmd_list) {
+        cmd_utils::ExecuteCommandResult::Success {
+            cmd_desc,
+            exit_code,
+            output,
+        } => {
+            let _ = cmd_desc;
+            let _ = exit_code;
+            let _ = output;
+
+            #[allow(unused_assignments)]
+            let mut latest_dir = String::from("");
+            {
+                let mut locked_state = MY_DIRED_STATE.lock().unwrap();
+                if let Some(op) = locked_state.operation_journal.get_mut(operation_index) {
+                    op.undone = true;
+                }
+                latest_dir = locked_state.last_dired_buffer_dir.clone();
+            }
+
+            let dired_buffer_handle = get_dired_buffer(false);
+            if dired_buffer_handle != -1 && dired_buffer_handle == Buffer::current().handle() {
+                report(
+                    "my_dired",
+                    list_directories_into_dired_buffer(dired_buffer_handle, &latest_dir),
+                );
+            }
+        }
+        cmd_utils::ExecuteCommandResult::Fail { error_message } => {
+            notify(
+                "my_dired",
+                &format!("Undo failed: {error_message}"),
+                EchoLevel::Error,
+            );
+        }
+    }
+}
+
+///
+/// Show the operation journal in a floating window (`:DiredOps`), press `q` to close it.
+///
+fn show_operation_journal() {
+    let lines = {
+        let locked_state = MY_DIRED_STATE.lock().unwrap();
+        if locked_state.operation_journal.is_empty() {
+            vec!["No file operation yet.".to_string()]
+        } else {
+            locked_state
+                .operation_journal
+                .iter()
+                .rev()
+                .map(|op| {
+                    let time = call_function::<_, String>("strftime", ("%H:%M:%S", op.time))
+                        .unwrap_or_default();
+                    let ago = relative_time(UNIX_EPOCH + Duration::from_secs(op.time));
+                    let status = match (op.undone, &op.undo_cmd_list) {
+                        (true, _) => "undone",
+                        (false, Some(_)) => "",
+                        (false, None) => "no undo",
+                    };
+                    format!(
+                        "{time} {ago:>9}  {:<6}  {:<7}  {}",
+                        format!("{:?}", op.action),
+                        status,
+                        op.cmd_list.join(" ")
+                    )
+                })
+                .collect::<Vec<String>>()
         }
+    };
+
+    let Ok(mut journal_buffer) = create_buf(false, true) else {
+        return;
+    };
+
+    let opts = OptionOpts::builder().buffer(journal_buffer.clone()).build();
+    let _ = set_option_value("bufhidden", "wipe", &opts);
+    let _ = journal_buffer.set_lines(.., true, lines.iter().map(|v| v.as_str()));
+    let _ = set_option_value("modifiable", false, &opts);
+
+    let Some(journal_window) = create_popup_window(&PopupWindowOptions {
+        border: WindowBorder::Rounded,
+        window_width_ratio: None,
+        window_height_ratio: None,
+        auto_width: true,
+        auto_height: true,
+        buffer: Some(journal_buffer.handle()),
+        title: Some(" Dired operations ".to_string()),
+        footer: Some(" q: close ".to_string()),
+        ..Default::default()
+    }) else {
+        return;
+    };
+
+    for key in ["q", "<ESC>"] {
+        let _ = journal_buffer.set_keymap(
+            Mode::Normal,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc("Dired operation journal: Close")
+                .callback(move |_| {
+                    journal_window.close();
+                    ()
+                })
+                .silent(true)
+                .build(),
+        );
+    }
+}
+
+///
+/// Go to the given directory
+///
+fn go_to_directory(dir_type: FastGotoDirectory) {
+    let dired_buffer_handle = get_dired_buffer(true);
+
+    if dired_buffer_handle == -1 {
+        return;
+    }
+
+    if let Ok(home_dir) = std::env::var("HOME") {
+        let dir = match dir_type {
+            FastGotoDirectory::Home => home_dir,
+            FastGotoDirectory::EmacsConfig => format!("{home_dir}/.config/emacs"),
+            FastGotoDirectory::NeovimConfig => format!("{home_dir}/.config/nvim"),
+            FastGotoDirectory::C => format!("{home_dir}/c"),
+            FastGotoDirectory::Odin => format!("{home_dir}/odin"),
+            FastGotoDirectory::Rust => format!("{home_dir}/rust"),
+            FastGotoDirectory::Temp => format!("{home_dir}/temp"),
+            FastGotoDirectory::Download => format!("{home_dir}/Downloads"),
+        };
+
+        report(
+            "my_dired",
+            list_directories_into_dired_buffer(dired_buffer_handle, &dir),
+        );
     }
 }
 
+///
+/// Open the dired buffer with the given directory, e.g. from the RPC server.
+///
+pub fn open_directory(dir: &str) {
+    let dired_buffer_handle = get_dired_buffer(true);
+
+    if dired_buffer_handle == -1 {
+        return;
+    }
+
+    report(
+        "my_dired",
+        list_directories_into_dired_buffer(dired_buffer_handle, dir),
+    );
+}
+
 ///
 ///
 ///
@@ -1126,19 +1850,111 @@ pub fn setup() {
             })
             .build(),
     );
+
+    let _ = create_user_command(
+        "DiredOps",
+        |_: CommandArgs| {
+            show_operation_journal();
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Show the dired file operation journal")
+            .build(),
+    );
+
+    register_user_command(
+        "Dired",
+        UserCommandSpec {
+            desc: "Open my dired with the directory (default: the current buffer directory)",
+            nargs: CommandNArgs::ZeroOrOne,
+            complete: UserCommandComplete::Directory,
+            ..Default::default()
+        },
+        |args: UserCommandArgs| {
+            let Some(dir) = args.arg(0) else {
+                open();
+                return;
+            };
+
+            let full_path = call_function::<_, String>("expand", (dir,))
+                .and_then(|dir| call_function::<_, String>("fnamemodify", (dir, ":p")))
+                .unwrap_or_default();
+            let full_path = match full_path.trim_end_matches('/') {
+                "" => "/",
+                trimmed => trimmed,
+            };
+
+            if Path::new(full_path).is_dir() {
+                open_directory(full_path);
+            } else {
+                notify(
+                    "my_dired",
+                    &format!("Not a directory: {dir}"),
+                    EchoLevel::Error,
+                );
+            }
+        },
+    );
+
+    //
+    // Contribute the visited directories to the picker registry (`<leader>pp`)
+    //
+    register_picker_source(
+        "dired_history",
+        "Dired history",
+        || {
+            MY_DIRED_STATE
+                .lock()
+                .unwrap()
+                .visited_dirs
+                .iter()
+                .map(|dir| PickerItem::from(dir.as_str()))
+                .collect::<Vec<PickerItem>>()
+        },
+        |selected_dir: String| {
+            if selected_dir.is_empty() || !std::path::Path::new(&selected_dir).is_dir() {
+                return;
+            }
+
+            let dired_buffer_handle = get_dired_buffer(true);
+            if dired_buffer_handle != -1 {
+                report(
+                    "my_dired",
+                    list_directories_into_dired_buffer(dired_buffer_handle, &selected_dir),
+                );
+            }
+        },
+    );
 }
 
+use crate::{
+    error::{Error, Result, ResultContext, report, report_error},
+    extended_api::{
+        EchoLevel, RegisterContent, UserCommandArgs, UserCommandComplete, UserCommandSpec, prompt,
+        register_user_command, set_register,
+    },
+    notify::notify,
+    picker::{
+        PickerItem, PopupWindowOptions, confirm, create_popup_window, register_picker_source,
+    },
+    utils::{format_size, get_clipboard_register, get_data_dir, relative_time, run_async},
+};
+
 use nvim::{
     String as NvimString,
     api::{
-        Buffer, call_function, cmd as vim_cmd, create_buf, get_current_line, get_option_value,
-        list_bufs,
-        opts::{CmdOpts, OptionOpts, SetKeymapOpts},
+        Buffer, Window, call_function, cmd as vim_cmd, create_autocmd, create_buf,
+        create_user_command, get_current_line, get_option_value, get_vvar, list_bufs,
+        opts::{CmdOpts, CreateAutocmdOpts, CreateCommandOpts, OptionOpts, SetKeymapOpts},
         set_current_buf, set_keymap, set_option_value,
-        types::{CmdInfos, Mode},
+        types::{CmdInfos, CommandArgs, CommandNArgs, Mode, WindowBorder},
     },
 };
 use nvim_oxi::{self as nvim};
 use rust_utils::cmd as cmd_utils;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::LazyLock;
 use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -0,0 +1,374 @@
+//! The notification center, the module messages show in the floating popups which stack in the
+//! top-right corner and dismiss automatically:
+//!
+//! ```text
+//!                    ╭─ project_command ──────────────────╮
+//!                    │ Passed: cargo test (3.2s)          │
+//!                    ╰────────────────────────────────────╯
+//!                    ╭─ git ──────────────────────────────╮
+//!                    │ Hunk staged.                       │
+//!                    ╰────────────────────────────────────╯
+//! ```
+//!
+//! - The border color and the display time follow the level, the error stays the longest.
+//! - `:Notifications`: Pick the notification from the history (the newest first), the selected
+//!   one is printed in full and kept in `:messages`.
+//! - `:NotificationsDismiss`: Close all popups.
+//!
+//! It falls back to `echo_message()` when the popup can't be opened (e.g. in the textlock).
+//!
+
+///
+/// The popup width (without the border)
+///
+const NOTIFY_WIDTH: usize = 50;
+
+///
+/// The max popup count on the screen, the oldest one is dismissed first.
+///
+const NOTIFY_MAX_POPUPS: usize = 5;
+
+///
+/// The max notification count of the history
+///
+const NOTIFY_HISTORY_MAX_COUNT: usize = 200;
+
+///
+/// The notification in the history
+///
+#[derive(Debug, Clone)]
+struct Notification {
+    module: String,
+    message: String,
+    level: EchoLevel,
+    time: SystemTime,
+}
+
+///
+/// The popup on the screen, the height doesn't include the border.
+///
+#[derive(Debug, Clone, Copy)]
+struct NotificationPopup {
+    window_handle: WinHandle,
+    height: u32,
+}
+
+///
+/// Private module-scope state
+///
+#[derive(Debug, Default)]
+struct NotifyState {
+    history: VecDeque<Notification>,
+
+    //
+    // The oldest popup is the first one (at the top)
+    //
+    popups: Vec<NotificationPopup>,
+}
+
+static NOTIFY_STATE: LazyLock<Mutex<NotifyState>> =
+    LazyLock::new(|| Mutex::new(NotifyState::default()));
+
+///
+/// The border highlight group and the display time (in milliseconds) of the level
+///
+fn level_style(level: EchoLevel) -> (&'static str, i64) {
+    match level {
+        EchoLevel::Info => ("DiagnosticInfo", 3000),
+        EchoLevel::Success => ("DiagnosticOk", 3000),
+        EchoLevel::Warning => ("DiagnosticWarn", 5000),
+        EchoLevel::Error => ("DiagnosticError", 8000),
+    }
+}
+
+///
+/// The popup height of the lines, the long line is wrapped by the window.
+///
+fn get_popup_height(lines: &[&str]) -> u32 {
+    lines
+        .iter()
+        .map(|line| display_width(line).div_ceil(NOTIFY_WIDTH).max(1) as u32)
+        .sum::<u32>()
+        .max(1)
+}
+
+///
+/// The popup config at the given row of the top-right corner
+///
+fn get_popup_window_config(module: &str, row: u32, height: u32) -> WindowConfig {
+    WindowConfig::builder()
+        .relative(WindowRelativeTo::Editor)
+        .anchor(WindowAnchor::NorthEast)
+        .width(NOTIFY_WIDTH as u32)
+        .height(height)
+        .row(row)
+        .col(get_screen_size().width)
+        .border(WindowBorder::Rounded)
+        .title(WindowTitle::SimpleString(format!(" {module} ").into()))
+        .title_pos(WindowTitlePosition::Left)
+        .focusable(false)
+        .zindex(200)
+        .build()
+}
+
+///
+/// Move the popups up to fill the gap of the dismissed one, the first one is below the tabline.
+///
+fn restack_popups(popups: &[NotificationPopup]) {
+    let screen_width = get_screen_size().width;
+    let mut row = 1u32;
+    for popup in popups {
+        //
+        // The position keys have to be set together
+        //
+        let config = WindowConfig::builder()
+            .relative(WindowRelativeTo::Editor)
+            .anchor(WindowAnchor::NorthEast)
+            .row(row)
+            .col(screen_width)
+            .build();
+        let _ = Window::from(popup.window_handle).set_config(&config);
+
+        //
+        // The border takes 2 lines
+        //
+        row += popup.height + 2;
+    }
+}
+
+///
+/// Close the popup and move the rest up
+///
+fn dismiss_popup(window_handle: WinHandle) {
+    let popups = {
+        let mut locked_state = NOTIFY_STATE.lock().unwrap();
+        locked_state
+            .popups
+            .retain(|popup| popup.window_handle != window_handle);
+        locked_state.popups.clone()
+    };
+
+    let window = Window::from(window_handle);
+    if window.is_valid() {
+        let _ = window.close(true);
+    }
+
+    restack_popups(&popups);
+}
+
+///
+/// `:NotificationsDismiss`: Close all popups
+///
+fn dismiss_all_popups() {
+    let popups = std::mem::take(&mut NOTIFY_STATE.lock().unwrap().popups);
+    for popup in popups {
+        let window = Window::from(popup.window_handle);
+        if window.is_valid() {
+            let _ = window.close(true);
+        }
+    }
+}
+
+///
+/// Open the popup below the existing ones, return the window handle.
+///
+fn open_popup(module: &str, message: &str, level: EchoLevel) -> Option<WinHandle> {
+    let lines = message.lines().collect::<Vec<&str>>();
+    let height = get_popup_height(&lines);
+
+    let mut buffer = create_buf(false, true).ok()?;
+    let _ = set_option_value(
+        "bufhidden",
+        "wipe",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    );
+    buffer.set_lines(.., false, lines).ok()?;
+
+    let row = {
+        let locked_state = NOTIFY_STATE.lock().unwrap();
+        1 + locked_state
+            .popups
+            .iter()
+            .map(|popup| popup.height + 2)
+            .sum::<u32>()
+    };
+
+    let window = open_win(
+        &buffer,
+        false,
+        &get_popup_window_config(module, row, height),
+    )
+    .ok()?;
+
+    let (hl_group, _) = level_style(level);
+    let window_opts = OptionOpts::builder().win(window.clone()).build();
+    let _ = set_option_value(
+        "winhighlight",
+        format!("NormalFloat:Normal,FloatBorder:{hl_group},FloatTitle:{hl_group}"),
+        &window_opts,
+    );
+    let _ = set_option_value("wrap", true, &window_opts);
+
+    NOTIFY_STATE.lock().unwrap().popups.push(NotificationPopup {
+        window_handle: window.handle(),
+        height,
+    });
+
+    Some(window.handle())
+}
+
+///
+/// Show the module message in the popup and keep it in the history
+///
+/// ```rust
+/// notify("git", "Hunk staged.", EchoLevel::Success);
+/// ```
+///
+pub fn notify(module: &str, message: &str, level: EchoLevel) {
+    let oldest_popup = {
+        let mut locked_state = NOTIFY_STATE.lock().unwrap();
+        locked_state.history.push_back(Notification {
+            module: module.to_owned(),
+            message: message.to_owned(),
+            level,
+            time: SystemTime::now(),
+        });
+        while locked_state.history.len() > NOTIFY_HISTORY_MAX_COUNT {
+            locked_state.history.pop_front();
+        }
+
+        if locked_state.popups.len() >= NOTIFY_MAX_POPUPS {
+            locked_state.popups.first().map(|popup| popup.window_handle)
+        } else {
+            None
+        }
+    };
+
+    if let Some(window_handle) = oldest_popup {
+        dismiss_popup(window_handle);
+    }
+
+    let Some(window_handle) = open_popup(module, message, level) else {
+        echo_message(module, message, level);
+        return;
+    };
+
+    let (_, display_time) = level_style(level);
+    let _ = call_function::<_, i64>(
+        "timer_start",
+        (
+            display_time,
+            Function::<(i64,), ()>::from_fn(move |_| dismiss_popup(window_handle)),
+        ),
+    );
+}
+
+///
+/// The picker items of the history (the newest first), the item data is the history index.
+///
+fn get_notification_picker_items() -> Vec<PickerItem> {
+    let locked_state = NOTIFY_STATE.lock().unwrap();
+    locked_state
+        .history
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(index, notification)| {
+            let (hl_group, _) = level_style(notification.level);
+            PickerItem {
+                columns: vec![
+                    PickerItemColumn::new(&relative_time(notification.time), Some("Comment")),
+                    PickerItemColumn::new(&notification.module, Some(hl_group)),
+                    PickerItemColumn::new(
+                        notification.message.lines().next().unwrap_or_default(),
+                        None,
+                    ),
+                ],
+                icon: None,
+                data: index.to_string(),
+            }
+        })
+        .collect()
+}
+
+///
+/// Print the selected notification in full, so it's kept in `:messages`.
+///
+fn notification_selected_callback(selected_index: String) {
+    let notification = selected_index
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|index| NOTIFY_STATE.lock().unwrap().history.get(index).cloned());
+
+    if let Some(notification) = notification {
+        echo_message(
+            &notification.module,
+            &notification.message,
+            notification.level,
+        );
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    register_user_command(
+        "Notifications",
+        UserCommandSpec {
+            desc: "Pick the notification from the history",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| open_registered_picker("notifications"),
+    );
+
+    register_user_command(
+        "NotificationsDismiss",
+        UserCommandSpec {
+            desc: "Close all notification popups",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| dismiss_all_popups(),
+    );
+
+    //
+    // Contribute the notification history to the picker registry (`<leader>pp`)
+    //
+    register_picker_source(
+        "notifications",
+        "Notifications",
+        get_notification_picker_items,
+        notification_selected_callback,
+    );
+}
+
+use crate::{
+    extended_api::{
+        EchoLevel, UserCommandArgs, UserCommandSpec, echo_message, register_user_command,
+    },
+    picker::{
+        PickerItem, PickerItemColumn, get_screen_size, open_registered_picker,
+        register_picker_source,
+    },
+    utils::{display_width, relative_time},
+};
+
+use std::{
+    collections::VecDeque,
+    sync::{LazyLock, Mutex},
+    time::SystemTime,
+};
+
+use nvim_oxi::{
+    Function, WinHandle,
+    api::{
+        Window, call_function, create_buf, open_win,
+        opts::OptionOpts,
+        set_option_value,
+        types::{
+            WindowAnchor, WindowBorder, WindowConfig, WindowRelativeTo, WindowTitle,
+            WindowTitlePosition,
+        },
+    },
+};
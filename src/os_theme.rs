@@ -0,0 +1,285 @@
+//! Follow the OS appearance (light/dark) automatically:
+//!
+//! - macOS: `defaults read -g AppleInterfaceStyle`
+//! - Linux: The XDG desktop portal `org.freedesktop.appearance color-scheme` (by `gdbus`)
+//!
+//! The OS appearance is polled by the async command runner (`utils::run_async`), so it never
+//! blocks the UI. When it changes, the `background` option (and the colorscheme configured in
+//! `OS_THEME_COLORSCHEMES`) is switched.
+//!
+//! - `:OsTheme {auto|light|dark}`: `light` or `dark` stops following the OS appearance, `auto`
+//!   follows it again.
+//!
+
+///
+/// The OS appearance polling interval in milliseconds
+///
+const OS_THEME_POLL_INTERVAL: u32 = 5000;
+
+///
+/// The (light, dark) colorscheme names, `None` to only switch the `background` option.
+///
+const OS_THEME_COLORSCHEMES: Option<(&'static str, &'static str)> = None;
+
+///
+/// OS appearance
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Appearance {
+    Light,
+    Dark,
+}
+
+impl Appearance {
+    ///
+    /// The `background` option value
+    ///
+    fn as_background(&self) -> &'static str {
+        match self {
+            Appearance::Light => "light",
+            Appearance::Dark => "dark",
+        }
+    }
+}
+
+///
+/// `Auto` follows the OS appearance, `Manual` is set by `:OsTheme light|dark`.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OsThemeMode {
+    Auto,
+    Manual(Appearance),
+}
+
+///
+///
+///
+#[derive(Debug)]
+struct ModuleState {
+    mode: OsThemeMode,
+    applied_appearance: Option<Appearance>,
+
+    //
+    // Whether the OS appearance query command is still running or not
+    //
+    is_querying: bool,
+}
+
+///
+/// Private module-scope state
+///
+static OS_THEME_STATE: LazyLock<Mutex<ModuleState>> = LazyLock::new(|| {
+    Mutex::new(ModuleState {
+        mode: OsThemeMode::Auto,
+        applied_appearance: None,
+        is_querying: false,
+    })
+});
+
+///
+/// Get back the command to query the OS appearance of the current platform
+///
+fn get_appearance_query_command() -> Option<Vec<String>> {
+    let cmd: &[&str] = if cfg!(target_os = "macos") {
+        &["defaults", "read", "-g", "AppleInterfaceStyle"]
+    } else if cfg!(target_os = "linux") {
+        &[
+            "gdbus",
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.portal.Desktop",
+            "--object-path",
+            "/org/freedesktop/portal/desktop",
+            "--method",
+            "org.freedesktop.portal.Settings.Read",
+            "org.freedesktop.appearance",
+            "color-scheme",
+        ]
+    } else {
+        return None;
+    };
+
+    Some(cmd.iter().map(|v| v.to_string()).collect())
+}
+
+///
+/// Parse the query command result, `None` means unknown (e.g. the portal is not available).
+///
+/// - macOS: It prints `Dark` in dark mode, and fails (the key doesn't exist) in light mode.
+/// - Linux: It prints `(<<uint32 1>>,)`, 1 is dark, 2 is light, 0 is no preference.
+///
+fn parse_appearance(exit_code: i64, output: &str) -> Option<Appearance> {
+    if cfg!(target_os = "macos") {
+        return Some(if exit_code == 0 && output.trim() == "Dark" {
+            Appearance::Dark
+        } else {
+            Appearance::Light
+        });
+    }
+
+    if exit_code != 0 {
+        return None;
+    }
+
+    match output.trim().trim_end_matches(">>,)").chars().last() {
+        Some('1') => Some(Appearance::Dark),
+        Some('0') | Some('2') => Some(Appearance::Light),
+        _ => None,
+    }
+}
+
+///
+/// Switch `background` and the colorscheme to the given appearance, do nothing if it's already
+/// applied.
+///
+fn apply_appearance(appearance: Appearance) {
+    {
+        let mut locked_state = OS_THEME_STATE.lock().unwrap();
+        if locked_state.applied_appearance == Some(appearance) {
+            return;
+        }
+        locked_state.applied_appearance = Some(appearance);
+    }
+
+    let opts = OptionOpts::builder().scope(OptionScope::Global).build();
+    let _ = set_option_value("background", appearance.as_background(), &opts);
+
+    if let Some((light_colorscheme, dark_colorscheme)) = OS_THEME_COLORSCHEMES {
+        let colorscheme = match appearance {
+            Appearance::Light => light_colorscheme,
+            Appearance::Dark => dark_colorscheme,
+        };
+        let _ = command(&format!("silent! colorscheme {colorscheme}"));
+    }
+}
+
+///
+/// Query the OS appearance asynchronously, and apply it in `Auto` mode.
+///
+fn poll_os_appearance() {
+    let Some(query_command) = get_appearance_query_command() else {
+        return;
+    };
+
+    {
+        let mut locked_state = OS_THEME_STATE.lock().unwrap();
+        if locked_state.mode != OsThemeMode::Auto || locked_state.is_querying {
+            return;
+        }
+        locked_state.is_querying = true;
+    }
+
+    let output_lines = Rc::new(RefCell::new(Vec::<String>::new()));
+
+    let on_output = {
+        let output_lines = output_lines.clone();
+        move |lines: Vec<String>| output_lines.borrow_mut().extend(lines)
+    };
+
+    let on_exit = move |exit_code: i64| {
+        let is_auto_mode = {
+            let mut locked_state = OS_THEME_STATE.lock().unwrap();
+            locked_state.is_querying = false;
+            locked_state.mode == OsThemeMode::Auto
+        };
+
+        if !is_auto_mode {
+            return;
+        }
+
+        if let Some(appearance) = parse_appearance(exit_code, &output_lines.borrow().join("\n")) {
+            apply_appearance(appearance);
+        }
+    };
+
+    if !run_async(query_command, on_output, on_exit) {
+        OS_THEME_STATE.lock().unwrap().is_querying = false;
+    }
+}
+
+///
+/// `:OsTheme {auto|light|dark}`
+///
+fn set_os_theme_mode(mode_name: &str) {
+    let mode = match mode_name {
+        "auto" => OsThemeMode::Auto,
+        "light" => OsThemeMode::Manual(Appearance::Light),
+        "dark" => OsThemeMode::Manual(Appearance::Dark),
+        _ => {
+            nvim::print!(
+                "[ os_theme ] Unknown mode: '{mode_name}', use 'auto', 'light' or 'dark'."
+            );
+            return;
+        }
+    };
+
+    OS_THEME_STATE.lock().unwrap().mode = mode;
+
+    match mode {
+        OsThemeMode::Auto => poll_os_appearance(),
+        OsThemeMode::Manual(appearance) => apply_appearance(appearance),
+    }
+
+    nvim::print!("[ os_theme ] Mode: {mode_name}");
+}
+
+///
+///
+///
+pub fn setup() {
+    if get_appearance_query_command().is_some() {
+        poll_os_appearance();
+
+        let poll_callback = Function::<(i64,), ()>::from_fn(|_| {
+            poll_os_appearance();
+        });
+        let _ = call_function::<_, i64>(
+            "timer_start",
+            (
+                OS_THEME_POLL_INTERVAL,
+                poll_callback,
+                Dictionary::from_iter([("repeat", -1)]),
+            ),
+        );
+    }
+
+    let _ = create_user_command(
+        "OsTheme",
+        |args: CommandArgs| {
+            set_os_theme_mode(args.args.as_deref().unwrap_or_default().trim());
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Set the theme mode: ':OsTheme {auto|light|dark}'")
+            .nargs(CommandNArgs::One)
+            .complete(CommandComplete::CustomList(Function::from_fn(
+                |(arg_lead, _, _): (String, String, usize)| {
+                    ["auto", "light", "dark"]
+                        .iter()
+                        .filter(|mode| mode.starts_with(&arg_lead))
+                        .map(|mode| mode.to_string())
+                        .collect::<Vec<String>>()
+                },
+            )))
+            .build(),
+    );
+}
+
+use crate::utils::run_async;
+
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim, Dictionary, Function,
+    api::{
+        call_function, command, create_user_command,
+        opts::{CreateCommandOpts, OptionOpts, OptionScope},
+        set_option_value,
+        types::{CommandArgs, CommandComplete, CommandNArgs},
+    },
+};
@@ -0,0 +1,91 @@
+//! `%%`-style path expansions in the cmdline (by the `cmdline` hook layer), so `:e %%/sibling.rs`
+//! works without typing the full path:
+//!
+//! - `%%`: The current file's directory.
+//! - `##`: The project root directory.
+//!
+//! They only expand in the file-path-taking Ex commands (`PATH_EXPANSION_COMMANDS`), otherwise
+//! (e.g. `:s/%%/x/` or a search) the trigger is typed as it is.
+//!
+
+///
+/// The commands (full names by `nvim_parse_cmd`) that expand the path triggers
+///
+const PATH_EXPANSION_COMMANDS: [&'static str; 18] = [
+    "edit",
+    "split",
+    "vsplit",
+    "new",
+    "vnew",
+    "tabedit",
+    "tabnew",
+    "find",
+    "sfind",
+    "read",
+    "write",
+    "saveas",
+    "badd",
+    "cd",
+    "lcd",
+    "tcd",
+    "source",
+    "diffsplit",
+];
+
+///
+/// Whether the current cmdline is a path-taking command or not
+///
+fn is_path_command(context: &CmdlineContext) -> bool {
+    context.cmd_type == ":"
+        && context
+            .command
+            .as_deref()
+            .is_some_and(|command| PATH_EXPANSION_COMMANDS.contains(&command))
+}
+
+///
+/// `%%`: The current file's directory
+///
+fn expand_current_file_dir(context: &CmdlineContext) -> Option<String> {
+    if !is_path_command(context) {
+        return None;
+    }
+
+    let dir = call_function::<_, String>("expand", ("%:~:.:h",)).ok()?;
+    if dir.is_empty() {
+        return None;
+    }
+
+    Some(format!("{dir}/"))
+}
+
+///
+/// `##`: The project root directory
+///
+fn expand_project_dir(context: &CmdlineContext) -> Option<String> {
+    if !is_path_command(context) {
+        return None;
+    }
+
+    let project_dir = get_project_dir();
+    if project_dir.is_empty() {
+        return None;
+    }
+
+    Some(format!("{}/", project_dir.trim_end_matches('/')))
+}
+
+///
+///
+///
+pub fn setup() {
+    register_cmdline_expansion("%%", expand_current_file_dir);
+    register_cmdline_expansion("##", expand_project_dir);
+}
+
+use crate::{
+    cmdline::{CmdlineContext, register_cmdline_expansion},
+    project_command::get_project_dir,
+};
+
+use nvim_oxi::api::call_function;
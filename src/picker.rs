@@ -1,6 +1,16 @@
 pub mod popup_window;
 pub mod picker;
+pub mod picker_item;
+pub mod sorting;
+pub mod filter;
+pub mod registry;
+pub mod confirm_dialog;
 mod keybindings;
 
 pub use popup_window::*;
 pub use picker::*;
+pub use picker_item::*;
+pub use sorting::*;
+pub use filter::*;
+pub use registry::*;
+pub use confirm_dialog::*;
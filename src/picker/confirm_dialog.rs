@@ -0,0 +1,216 @@
+///
+/// The namespace to highlight the buttons of the confirm dialog
+///
+const CONFIRM_DIALOG_NAMESPACE: &'static str = "picker_confirm_dialog";
+
+///
+/// The button of the confirm dialog
+///
+#[derive(Debug, Clone)]
+struct ConfirmButton {
+    label: String,
+
+    //
+    // The shortcut key (lowercase), it's the letter after `&` in the choice, or the initial
+    // letter if there is no `&`.
+    //
+    shortcut: Option<char>,
+
+    //
+    // The byte range of the button in the buttons line
+    //
+    start_col: usize,
+    end_col: usize,
+}
+
+///
+/// Parse the choices like the `confirm()` ones, e.g. `["&Yes", "&No", "Reload &all"]`
+///
+fn parse_confirm_buttons(choices: &[&str]) -> (String, Vec<ConfirmButton>) {
+    let mut buttons_line = String::new();
+    let mut buttons = Vec::with_capacity(choices.len());
+
+    for (index, choice) in choices.iter().enumerate() {
+        let shortcut = match choice.find('&') {
+            Some(pos) => choice[pos + 1..].chars().next(),
+            None => choice.chars().next(),
+        }
+        .map(|c| c.to_ascii_lowercase());
+
+        let label = format!("[ {} ]", choice.replacen('&', "", 1));
+
+        if index > 0 {
+            buttons_line.push_str("  ");
+        }
+        let start_col = buttons_line.len();
+        buttons_line.push_str(&label);
+
+        buttons.push(ConfirmButton {
+            label,
+            shortcut,
+            start_col,
+            end_col: buttons_line.len(),
+        });
+    }
+
+    (buttons_line, buttons)
+}
+
+///
+/// Highlight the selected button and the shortcut letters of the buttons line
+///
+fn highlight_confirm_buttons(
+    buffer: &mut Buffer,
+    buttons_row: usize,
+    buttons: &[ConfirmButton],
+    selected_index: usize,
+) {
+    let namespace_id = create_namespace(CONFIRM_DIALOG_NAMESPACE);
+    let _ = buffer.clear_namespace(namespace_id, ..);
+
+    for (index, button) in buttons.iter().enumerate() {
+        if index == selected_index {
+            let _ = buffer.set_extmark(
+                namespace_id,
+                buttons_row,
+                button.start_col,
+                &SetExtmarkOpts::builder()
+                    .end_col(button.end_col)
+                    .hl_group("PmenuSel")
+                    .build(),
+            );
+        }
+
+        if let Some(shortcut) = button.shortcut
+            && let Some(pos) = button.label[2..]
+                .char_indices()
+                .find(|(_, c)| c.to_ascii_lowercase() == shortcut)
+                .map(|(pos, _)| pos + 2)
+        {
+            let _ = buffer.set_extmark(
+                namespace_id,
+                buttons_row,
+                button.start_col + pos,
+                &SetExtmarkOpts::builder()
+                    .end_col(button.start_col + pos + shortcut.len_utf8())
+                    .hl_group("Special")
+                    .build(),
+            );
+        }
+    }
+}
+
+///
+/// Show the confirm dialog in the popup window and wait for the choice, it works like the
+/// builtin `confirm()`:
+///
+/// - `h`/`l` (or `<Left>`/`<Right>`, `<S-Tab>`/`<Tab>`): Select the previous/next button.
+/// - `<CR>`: Accept the selected button.
+/// - The shortcut letter (after `&` in the choice, or the initial letter): Accept that button.
+/// - `q` (if it's not a shortcut) or `<ESC>`: Cancel.
+///
+/// Return the index of the chosen button, or `None` if it's cancelled.
+///
+/// ```rust
+/// let choice = confirm(
+///     "Delete file",
+///     &["Are you sure to delete 'main.rs'?".to_string()],
+///     &["&Yes", "&No"],
+///     1,
+/// );
+/// ```
+///
+pub fn confirm(
+    title: &str,
+    message_lines: &[String],
+    choices: &[&str],
+    default_index: usize,
+) -> Option<usize> {
+    if choices.is_empty() {
+        return None;
+    }
+
+    let (buttons_line, buttons) = parse_confirm_buttons(choices);
+
+    let mut lines = message_lines.to_vec();
+    if !lines.is_empty() {
+        lines.push(String::new());
+    }
+    let buttons_row = lines.len();
+    lines.push(buttons_line);
+
+    let Ok(mut dialog_buffer) = create_buf(false, true) else {
+        return None;
+    };
+    let opts = OptionOpts::builder().buffer(dialog_buffer.clone()).build();
+    let _ = set_option_value("bufhidden", "wipe", &opts);
+    let _ = dialog_buffer.set_lines(.., true, lines.iter().map(|v| v.as_str()));
+    let _ = set_option_value("modifiable", false, &opts);
+
+    let dialog_window = create_popup_window(&PopupWindowOptions {
+        border: WindowBorder::Rounded,
+        window_width_ratio: None,
+        window_height_ratio: None,
+        auto_width: true,
+        auto_height: true,
+        buffer: Some(dialog_buffer.handle()),
+        title: Some(format!(" {title} ")),
+        footer: Some(" h/l: select  <CR>: accept  <ESC>: cancel ".to_string()),
+        min_width: Some(40),
+        zindex: Some(100),
+        focusable: false,
+        ..Default::default()
+    })?;
+
+    let mut selected_index = default_index.min(buttons.len() - 1);
+
+    //
+    // Block and read the keys like the builtin `confirm()`, `getcharstr()` fails on `<C-c>`.
+    //
+    let choice = loop {
+        highlight_confirm_buttons(&mut dialog_buffer, buttons_row, &buttons, selected_index);
+        let _ = command("redraw");
+
+        let Ok(key) = call_function::<_, String>("getcharstr", ()) else {
+            break None;
+        };
+        let key = call_function::<_, String>("keytrans", (key,)).unwrap_or_default();
+
+        match key.as_str() {
+            "h" | "<Left>" | "<S-Tab>" => {
+                selected_index = selected_index.saturating_sub(1);
+            }
+            "l" | "<Right>" | "<Tab>" => {
+                selected_index = (selected_index + 1).min(buttons.len() - 1);
+            }
+            "<CR>" => break Some(selected_index),
+            "<Esc>" => break None,
+            _ => {
+                let mut chars = key.chars();
+                if let (Some(c), None) = (chars.next(), chars.next()) {
+                    let c = c.to_ascii_lowercase();
+                    if let Some(index) = buttons.iter().position(|b| b.shortcut == Some(c)) {
+                        break Some(index);
+                    }
+                    if c == 'q' {
+                        break None;
+                    }
+                }
+            }
+        }
+    };
+
+    dialog_window.close();
+    let _ = command("redraw");
+
+    choice
+}
+
+use crate::picker::{PopupWindowOptions, create_popup_window};
+
+use nvim_oxi::api::{
+    Buffer, call_function, command, create_buf, create_namespace,
+    opts::{OptionOpts, SetExtmarkOpts},
+    set_option_value,
+    types::WindowBorder,
+};
@@ -0,0 +1,44 @@
+///
+/// Case-insensitive fuzzy match: all query characters (whitespace ignored) appear in the
+/// haystack in the same order, e.g. "bdsh" matches "./build.sh".
+///
+fn fuzzy_match(haystack: &str, query: &str) -> bool {
+    let mut haystack_chars = haystack.chars().flat_map(|c| c.to_lowercase());
+
+    query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(|c| c.to_lowercase())
+        .all(|query_char| haystack_chars.any(|c| c == query_char))
+}
+
+///
+/// Filter the items by the given query, the items keep their order (that's why the sorting
+/// strategy result still applies after filtering).
+///
+/// The query matches against the `data` and all the display columns of the item.
+///
+pub fn filter_picker_items(items: &[PickerItem], query: &str) -> Vec<PickerItem> {
+    if query.trim().is_empty() {
+        return items.to_vec();
+    }
+
+    items
+        .iter()
+        .filter(|item| {
+            fuzzy_match(&item.data, query)
+                || fuzzy_match(
+                    &item
+                        .columns
+                        .iter()
+                        .map(|column| column.text.as_str())
+                        .collect::<Vec<&str>>()
+                        .join(" "),
+                    query,
+                )
+        })
+        .cloned()
+        .collect()
+}
+
+use crate::picker::PickerItem;
@@ -1,10 +1,123 @@
+///
+/// The shared state between the input buffer keybindings and the live filter
+///
+#[derive(Debug, Default)]
+pub struct PickerInputState {
+    //
+    // The `PickerItem.data` list of the visible (filtered) items, it has the same order with the
+    // list buffer.
+    //
+    pub visible_data_list: Vec<String>,
+
+    //
+    // The input text filled by <c-j>/<c-k>/<Tab> (NOT typed by the user), it should NOT trigger
+    // the live filter.
+    //
+    pub filled_text: Option<String>,
+
+    //
+    // All items (the visible ones are filtered from it), `<c-d>` and `<c-r>` update it.
+    //
+    pub all_items: Vec<PickerItem>,
+
+    //
+    // The item data which is being edited by `<c-r>`, the next `<CR>` saves the edit instead of
+    // selecting.
+    //
+    pub editing_data: Option<String>,
+
+    pub show_footer: bool,
+}
+
+///
+/// The optional input handlers of the editable picker, see `EditablePickerOptions`.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct PickerInputHandlers {
+    pub validator: Option<PickerInputValidator>,
+    pub on_delete: Option<PickerItemDeleteHandler>,
+    pub on_edit: Option<PickerItemEditHandler>,
+}
+
+///
+/// The namespace to show the input validation error in the list buffer
+///
+const PICKER_VALIDATION_NAMESPACE: &'static str = "picker_validation";
+
+///
+/// Show the validation error as the red virtual line above the first list line, so it's right
+/// under the input window.
+///
+fn show_validation_error(list_window_handle: i32, message: &str) {
+    show_list_message(
+        list_window_handle,
+        &format!("✗ {message}"),
+        "DiagnosticError",
+    );
+}
+
+///
+/// Show the message as the virtual line above the first list line
+///
+fn show_list_message(list_window_handle: i32, message: &str, hl_group: &str) {
+    let list_window = Window::from(list_window_handle);
+    let Ok(mut list_buffer) = list_window.get_buf() else {
+        return;
+    };
+
+    let namespace_id = create_namespace(PICKER_VALIDATION_NAMESPACE);
+    let _ = list_buffer.clear_namespace(namespace_id, ..);
+    let _ = list_buffer.set_extmark(
+        namespace_id,
+        0,
+        0,
+        &SetExtmarkOpts::builder()
+            .virt_lines([[(message, hl_group)]])
+            .virt_lines_above(true)
+            .build(),
+    );
+
+    //
+    // The virtual lines above the first buffer line are only visible with the `topfill`
+    //
+    let _ = call_function::<_, String>(
+        "win_execute",
+        (
+            list_window_handle,
+            "call winrestview({ 'topline': 1, 'topfill': 1 })",
+        ),
+    );
+}
+
+///
+/// Clear the validation error in the list buffer
+///
+pub fn clear_validation_error(list_buffer_handle: i32) {
+    let namespace_id = create_namespace(PICKER_VALIDATION_NAMESPACE);
+    let _ = Buffer::from(list_buffer_handle).clear_namespace(namespace_id, ..);
+}
+
+///
+/// Fill the input buffer with the given item data and mark it as `filled_text`
+///
+fn fill_input_buffer(
+    input_buffer_ref: &mut Buffer,
+    input_state: &RefCell<PickerInputState>,
+    item_data: String,
+) {
+    let _ = input_buffer_ref.set_lines(.., true, vec![item_data.as_str()]);
+    input_state.borrow_mut().filled_text = Some(item_data);
+}
+
 ///
 /// <c-j>/<c-k>: Move the cursor up and down in the list buffer and set the input buffer text
+/// to the item data (NOT the rendered line, as it may have icon and multiple columns).
 ///
 fn ctrl_jk_callback(
     list_win_ref: &mut Window,
     is_ctrl_j: bool,
     input_buffer_ref: &mut Buffer,
+    input_state: &RefCell<PickerInputState>,
 ) {
     if let Ok(cursor_pos) = &list_win_ref.get_cursor() {
         let mut row = cursor_pos.0;
@@ -14,17 +127,19 @@ fn ctrl_jk_callback(
             return;
         }
 
-        // Set the input window text to current line from list window
+        // Set the input window text to the item data of the next/prev line from list window
         if let Ok(list_buffer) = list_win_ref.get_buf() {
-            let mut read_line_range: std::ops::Range<usize> = row..row + 1;
+            let mut item_index = row;
             if !is_ctrl_j && row >= 2 {
-                read_line_range = row - 2..row - 1;
+                item_index = row - 2;
             }
-            if let Ok(mut lines) = list_buffer.get_lines(read_line_range.clone(), true) {
-                if let Some(first_line) = lines.next() {
-                    let _ =
-                        input_buffer_ref.set_lines(.., true, vec![first_line.to_str().unwrap()]);
-                }
+            let item_data = input_state
+                .borrow()
+                .visible_data_list
+                .get(item_index)
+                .cloned();
+            if let Some(item_data) = item_data {
+                fill_input_buffer(input_buffer_ref, input_state, item_data);
             }
 
             // Update list window cursor
@@ -54,12 +169,188 @@ fn ctrl_jk_callback(
 }
 
 ///
-/// <CR>: Add input into the list buffer IF it doesn't exists, and then trigger callback.
+/// <Tab>: Complete the input buffer text with the item under the list window cursor.
+///
+fn tab_callback(
+    list_win_ref: &Window,
+    input_win_ref: &mut Window,
+    input_state: &RefCell<PickerInputState>,
+) {
+    let Ok((row, _)) = list_win_ref.get_cursor() else {
+        return;
+    };
+
+    let item_data = input_state.borrow().visible_data_list.get(row - 1).cloned();
+
+    if let Some(item_data) = item_data
+        && let Ok(mut input_buffer) = input_win_ref.get_buf()
+    {
+        let cursor_col = item_data.len();
+        fill_input_buffer(&mut input_buffer, input_state, item_data);
+
+        // Move the cursor to the end of the input
+        let _ = input_win_ref.set_cursor(1, cursor_col);
+    }
+}
+
+///
+/// Re-filter the list by the current input text and re-render it (and the footer)
+///
+fn refresh_list(
+    input_window_handle: i32,
+    list_window_handle: i32,
+    input_state: &RefCell<PickerInputState>,
+) {
+    let query = Window::from(input_window_handle)
+        .get_buf()
+        .ok()
+        .and_then(|buffer| buffer.get_lines(0..1, true).ok()?.next())
+        .map(|line| line.to_string())
+        .unwrap_or_default();
+
+    let (filtered_items, total_count, show_footer) = {
+        let mut locked_input_state = input_state.borrow_mut();
+        let filtered_items = filter_picker_items(&locked_input_state.all_items, &query);
+        locked_input_state.visible_data_list = filtered_items
+            .iter()
+            .map(|item| item.data.clone())
+            .collect();
+        (
+            filtered_items,
+            locked_input_state.all_items.len(),
+            locked_input_state.show_footer,
+        )
+    };
+
+    let Ok(list_buffer) = Window::from(list_window_handle).get_buf() else {
+        return;
+    };
+    render_filtered_items(list_buffer.handle(), list_window_handle, &filtered_items);
+
+    if show_footer && let Ok(mut input_buffer) = Window::from(input_window_handle).get_buf() {
+        update_picker_footer(&mut input_buffer, filtered_items.len(), total_count);
+    }
+}
+
+///
+/// <c-d>: Delete the item under the list window cursor if the `on_delete` handler accepts it.
+///
+fn ctrl_d_callback(
+    input_window_handle: i32,
+    list_window_handle: i32,
+    input_state: &RefCell<PickerInputState>,
+    on_delete: PickerItemDeleteHandler,
+) {
+    let Ok((row, _)) = Window::from(list_window_handle).get_cursor() else {
+        return;
+    };
+
+    let Some(item_data) = input_state.borrow().visible_data_list.get(row - 1).cloned() else {
+        return;
+    };
+
+    if !on_delete(&item_data) {
+        return;
+    }
+
+    input_state
+        .borrow_mut()
+        .all_items
+        .retain(|item| item.data != item_data);
+    refresh_list(input_window_handle, list_window_handle, input_state);
+
+    //
+    // Keep the cursor at the same position (or the new last line)
+    //
+    let mut list_window = Window::from(list_window_handle);
+    if let Ok(line_count) = list_window.get_buf().and_then(|buffer| buffer.line_count()) {
+        let _ = list_window.set_cursor(row.min(line_count.max(1)), 0);
+    }
+}
+
+///
+/// <c-r>: Fill the input buffer with the item under the list window cursor to edit it, the next
+/// <CR> saves the edit by the `on_edit` handler.
+///
+fn ctrl_r_callback(
+    input_window_handle: i32,
+    list_window_handle: i32,
+    input_state: &RefCell<PickerInputState>,
+) {
+    let Ok((row, _)) = Window::from(list_window_handle).get_cursor() else {
+        return;
+    };
+
+    let Some(item_data) = input_state.borrow().visible_data_list.get(row - 1).cloned() else {
+        return;
+    };
+
+    let mut input_window = Window::from(input_window_handle);
+    if let Ok(mut input_buffer) = input_window.get_buf() {
+        let cursor_col = item_data.len();
+        fill_input_buffer(&mut input_buffer, input_state, item_data.clone());
+        let _ = input_window.set_cursor(1, cursor_col);
+    }
+
+    show_list_message(
+        list_window_handle,
+        &format!("Editing: {item_data}  (<CR> to save)"),
+        "DiagnosticInfo",
+    );
+    input_state.borrow_mut().editing_data = Some(item_data);
+}
+
+///
+/// <CR> in the edit mode: Replace the edited item (its data and the column with the same text)
+/// if the `on_edit` handler accepts the input, and then back to the normal mode.
+///
+fn save_edited_item(
+    input_window_handle: i32,
+    list_window_handle: i32,
+    input_state: &RefCell<PickerInputState>,
+    on_edit: PickerItemEditHandler,
+    old_data: &str,
+    new_data: &str,
+) {
+    if let Err(message) = on_edit(old_data, new_data) {
+        show_validation_error(list_window_handle, &message);
+        return;
+    }
+
+    {
+        let mut locked_input_state = input_state.borrow_mut();
+        locked_input_state.editing_data = None;
+        for item in locked_input_state
+            .all_items
+            .iter_mut()
+            .filter(|item| item.data == old_data)
+        {
+            item.data = new_data.to_owned();
+            for column in item.columns.iter_mut().filter(|c| c.text == old_data) {
+                column.text = new_data.to_owned();
+            }
+        }
+    }
+
+    if let Ok(mut input_buffer) = Window::from(input_window_handle).get_buf() {
+        fill_input_buffer(&mut input_buffer, input_state, String::new());
+    }
+    if let Ok(list_buffer) = Window::from(list_window_handle).get_buf() {
+        clear_validation_error(list_buffer.handle());
+    }
+    refresh_list(input_window_handle, list_window_handle, input_state);
+}
+
+///
+/// <CR>: Add input into the list buffer IF it doesn't exists, and then trigger callback. The
+/// picker stays open if the `validator` rejects the input. In the edit mode (`<c-r>`), it saves
+/// the edit instead.
 ///
 fn enter_callback<F>(
-    title_window_handle: i32,
     input_window_handle: i32,
     list_window_handle: i32,
+    input_state: &RefCell<PickerInputState>,
+    handlers: PickerInputHandlers,
     mut selected_callback: F,
 ) where
     F: FnMut(String) + Clone + 'static,
@@ -67,7 +358,6 @@ fn enter_callback<F>(
     #[cfg(feature = "enable_picker_debug_print")]
     const LOGGER_PREFIX: &'static str = "[ picker keybindings - enter_callback ]";
 
-    let title_window = Window::from(title_window_handle);
     let input_window = Window::from(input_window_handle);
     let list_window = Window::from(list_window_handle);
 
@@ -93,6 +383,28 @@ fn enter_callback<F>(
         }
     }
 
+    if let Some(validator) = handlers.validator
+        && let Err(message) = validator(&selected_text)
+    {
+        show_validation_error(list_window_handle, &message);
+        return;
+    }
+
+    let editing_data = input_state.borrow().editing_data.clone();
+    if let Some(old_data) = editing_data
+        && let Some(on_edit) = handlers.on_edit
+    {
+        save_edited_item(
+            input_window_handle,
+            list_window_handle,
+            input_state,
+            on_edit,
+            &old_data,
+            &selected_text,
+        );
+        return;
+    }
+
     // Back to normal mode
     let command = "stopinsert";
     let infos = CmdInfos::builder().cmd(command).build();
@@ -100,7 +412,6 @@ fn enter_callback<F>(
     let _ = vim_cmd(&infos, &opts);
 
     // Close all windows
-    let _ = title_window.close(true);
     let _ = input_window.close(true);
     let _ = list_window.close(true);
 
@@ -111,7 +422,7 @@ fn enter_callback<F>(
 ///
 /// <c-e> or <ESC>: Quit the picker without trigger the `selected_callback`.
 ///
-fn close_the_picker(title_window_handle: i32, input_window_handle: i32, list_window_handle: i32) {
+fn close_the_picker(input_window_handle: i32, list_window_handle: i32) {
     // Back to normal mode
     let command = "stopinsert";
     let infos = CmdInfos::builder().cmd(command).build();
@@ -119,7 +430,6 @@ fn close_the_picker(title_window_handle: i32, input_window_handle: i32, list_win
     let _ = vim_cmd(&infos, &opts);
 
     // Close all windows
-    let _ = Window::from(title_window_handle).close(true);
     let _ = Window::from(input_window_handle).close(true);
     let _ = Window::from(list_window_handle).close(true);
 }
@@ -128,18 +438,24 @@ fn close_the_picker(title_window_handle: i32, input_window_handle: i32, list_win
 /// Set the following keybindings for the input buffer:
 ///
 /// - <c-j>/<c-k>: Move the cursor up and down in the list buffer and set the input buffer text
-/// - <CR>: Add input into the list buffer IF it doesn't exists, and then trigger callback.
+/// - <Tab>: Complete the input buffer text with the item under the list window cursor.
+/// - <CR>: Add input into the list buffer IF it doesn't exists, and then trigger callback (if
+///   the `validator` accepts the input).
+/// - <c-d>: Delete the item under cursor (only if the `on_delete` handler is provided).
+/// - <c-r>: Edit the item under cursor in the input (only if the `on_edit` handler is provided).
 /// - <c-e>: Quit the picker without trigger the `selected_callback`.
+/// - <CR>/<c-j>/<c-k> in `SELECT` mode: Keep the selected sticky query and do the same.
 ///
 pub fn set_input_buffer_keybindings<F>(
-    title_window_handle: i32,
     input_window_handle: i32,
     list_window_handle: i32,
+    input_state: Rc<RefCell<PickerInputState>>,
+    handlers: PickerInputHandlers,
     selected_callback: F,
 ) where
     F: FnMut(String) + Clone + 'static,
 {
-    if title_window_handle == -1 || input_window_handle == -1 || list_window_handle == -1 {
+    if input_window_handle == -1 || list_window_handle == -1 {
         return;
     }
 
@@ -149,16 +465,24 @@ pub fn set_input_buffer_keybindings<F>(
     let input_buffer_handle = input_buffer.handle();
 
     let selected_callback_cloned = selected_callback.clone();
-    let my_keybindings_with_callback: Vec<(Mode, &str, &str, Box<dyn Fn()>)> = vec![
+    let input_state_1 = input_state.clone();
+    let input_state_2 = input_state.clone();
+    let input_state_3 = input_state.clone();
+    let input_state_4 = input_state.clone();
+    let input_state_5 = input_state.clone();
+    let input_state_6 = input_state.clone();
+    let input_state_7 = input_state.clone();
+    let mut my_keybindings_with_callback: Vec<(Mode, &str, &str, Box<dyn Fn()>)> = vec![
         (
             Mode::Insert,
             "<CR>",
             "Press ENTER to select",
             Box::new(move || {
                 enter_callback(
-                    title_window_handle,
                     input_window_handle,
                     list_window_handle,
+                    &input_state_6,
+                    handlers,
                     selected_callback_cloned.clone(),
                 )
             }),
@@ -169,9 +493,10 @@ pub fn set_input_buffer_keybindings<F>(
             "Press ENTER to select",
             Box::new(move || {
                 enter_callback(
-                    title_window_handle,
                     input_window_handle,
                     list_window_handle,
+                    &input_state_7,
+                    handlers,
                     selected_callback.clone(),
                 )
             }),
@@ -181,10 +506,11 @@ pub fn set_input_buffer_keybindings<F>(
             "<c-j>",
             "'<c-j>' to move down",
             Box::new(move || {
-                ctrl_jk_callback.clone()(
+                ctrl_jk_callback(
                     &mut Window::from(list_window_handle),
                     true,
                     &mut Buffer::from(input_buffer_handle),
+                    &input_state_1,
                 );
             }),
         ),
@@ -193,10 +519,11 @@ pub fn set_input_buffer_keybindings<F>(
             "<c-j>",
             "'<c-j>' to move down",
             Box::new(move || {
-                ctrl_jk_callback.clone()(
+                ctrl_jk_callback(
                     &mut Window::from(list_window_handle),
                     true,
                     &mut Buffer::from(input_buffer_handle),
+                    &input_state_2,
                 );
             }),
         ),
@@ -209,6 +536,7 @@ pub fn set_input_buffer_keybindings<F>(
                     &mut Window::from(list_window_handle),
                     false,
                     &mut Buffer::from(input_buffer_handle),
+                    &input_state_3,
                 );
             }),
         ),
@@ -221,6 +549,19 @@ pub fn set_input_buffer_keybindings<F>(
                     &mut Window::from(list_window_handle),
                     false,
                     &mut Buffer::from(input_buffer_handle),
+                    &input_state_4,
+                );
+            }),
+        ),
+        (
+            Mode::Insert,
+            "<Tab>",
+            "'<Tab>' to complete the input with the list item under cursor",
+            Box::new(move || {
+                tab_callback(
+                    &Window::from(list_window_handle),
+                    &mut Window::from(input_window_handle),
+                    &input_state_5,
                 );
             }),
         ),
@@ -229,7 +570,7 @@ pub fn set_input_buffer_keybindings<F>(
             "<c-e>",
             "'<c-e>' to close the picker",
             Box::new(move || {
-                close_the_picker(title_window_handle, input_window_handle, list_window_handle);
+                close_the_picker(input_window_handle, list_window_handle);
             }),
         ),
         (
@@ -237,7 +578,7 @@ pub fn set_input_buffer_keybindings<F>(
             "<c-e>",
             "'<c-e>' to close the picker",
             Box::new(move || {
-                close_the_picker(title_window_handle, input_window_handle, list_window_handle);
+                close_the_picker(input_window_handle, list_window_handle);
             }),
         ),
         (
@@ -245,11 +586,44 @@ pub fn set_input_buffer_keybindings<F>(
             "<ESC>",
             "'<ESC>' to close the picker",
             Box::new(move || {
-                close_the_picker(title_window_handle, input_window_handle, list_window_handle);
+                close_the_picker(input_window_handle, list_window_handle);
             }),
         ),
     ];
 
+    if let Some(on_delete) = handlers.on_delete {
+        for mode in [Mode::Insert, Mode::Normal] {
+            let input_state = input_state.clone();
+            my_keybindings_with_callback.push((
+                mode,
+                "<c-d>",
+                "'<c-d>' to delete the item under cursor",
+                Box::new(move || {
+                    ctrl_d_callback(
+                        input_window_handle,
+                        list_window_handle,
+                        &input_state,
+                        on_delete,
+                    );
+                }),
+            ));
+        }
+    }
+
+    if handlers.on_edit.is_some() {
+        for mode in [Mode::Insert, Mode::Normal] {
+            let input_state = input_state.clone();
+            my_keybindings_with_callback.push((
+                mode,
+                "<c-r>",
+                "'<c-r>' to edit the item under cursor",
+                Box::new(move || {
+                    ctrl_r_callback(input_window_handle, list_window_handle, &input_state);
+                }),
+            ));
+        }
+    }
+
     for bindings in my_keybindings_with_callback {
         let _ = input_buffer.set_keymap(
             bindings.0,
@@ -265,11 +639,36 @@ pub fn set_input_buffer_keybindings<F>(
                 .build(),
         );
     }
+
+    //
+    // The sticky query is selected in `SELECT` mode when the picker opens, typing replaces it,
+    // while `<CR>`/`<c-j>`/`<c-k>` go back to `INSERT` mode at the end of the query and run the
+    // `INSERT` mode ones above (so it's remapped), instead of replacing it with the newline.
+    //
+    for key in ["<CR>", "<c-j>", "<c-k>"] {
+        let _ = input_buffer.set_keymap(
+            Mode::Select,
+            key,
+            &format!("<Esc>A{key}"),
+            &SetKeymapOpts::builder()
+                .desc(&format!("'{key}' to keep using the selected query"))
+                .silent(true)
+                .build(),
+        );
+    }
 }
 
+use crate::picker::{
+    PickerInputValidator, PickerItem, PickerItemDeleteHandler, PickerItemEditHandler,
+    filter_picker_items,
+    picker::{render_filtered_items, update_picker_footer},
+};
+
+use std::{cell::RefCell, rc::Rc};
+
 use nvim_oxi::api::{
-    Buffer, Window, cmd as vim_cmd,
-    opts::{CmdOpts, SetKeymapOpts},
+    Buffer, Window, call_function, cmd as vim_cmd, create_namespace,
+    opts::{CmdOpts, SetExtmarkOpts, SetKeymapOpts},
     types::{CmdInfos, Mode},
 };
 
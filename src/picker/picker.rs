@@ -4,9 +4,28 @@
 #[derive(Debug)]
 pub struct PickerOptions {
     window_opts: PopupWindowOptions,
-    list: Vec<String>,
+    list: Vec<PickerItem>,
+    sort: PickerSortStrategy,
+    source: Option<String>,
 }
 
+///
+/// The highlight namespace for rendering the picker item columns
+///
+const PICKER_HIGHLIGHT_NAMESPACE: &'static str = "picker_highlight";
+
+///
+/// The namespace for rendering the editable picker footer
+///
+const PICKER_FOOTER_NAMESPACE: &'static str = "picker_footer";
+
+///
+/// Private module-scope state: picker source name <--> the last input text (query), it's used by
+/// the `EditablePickerOptions.sticky_filter` option.
+///
+static PICKER_STICKY_FILTER_STATE: LazyLock<Mutex<HashMap<String, String>>> =
+    LazyLock::new(|| Mutex::new(HashMap::with_capacity(10)));
+
 ///
 ///
 ///
@@ -85,8 +104,14 @@ where
     // 0..  - The range of first line to the last line
     // ..   - The range of all lines
     //
-    let content = opts.list.iter().map(|v| v.as_str()).collect::<Vec<&str>>();
-    let _ = picker_buffer.set_lines(.., true, content)?;
+    sort_picker_items(&mut opts.list, &opts.sort, opts.source.as_deref());
+    let rendered_items = render_picker_items(&opts.list);
+    let _ = picker_buffer.set_lines(.., true, rendered_items.lines.iter().map(|v| v.as_str()))?;
+    apply_picker_item_highlights(
+        &mut picker_buffer,
+        create_namespace(PICKER_HIGHLIGHT_NAMESPACE),
+        &rendered_items.highlights,
+    );
 
     //
     // Not allow to modify after adding content
@@ -136,8 +161,8 @@ where
     // Open the picker window
     //
     opts.window_opts.buffer = Some(picker_buffer_id);
-    if let Some(win_handle) = create_popup_window(&opts.window_opts) {
-        let current_window = Window::from(win_handle);
+    if let Some(popup_window) = create_popup_window(&opts.window_opts) {
+        let current_window = popup_window.window();
         //
         // Disable default window option:
         //
@@ -162,6 +187,81 @@ where
     Ok(())
 }
 
+///
+/// The footer text looks like this: "3/10  <CR> select  <c-e> close  <Tab> complete"
+///
+fn get_picker_footer_text(matched_count: usize, total_count: usize) -> String {
+    const PICKER_FOOTER_HINTS: &'static str = "<CR> select  <c-e> close  <Tab> complete";
+    format!("{matched_count}/{total_count}  {PICKER_FOOTER_HINTS}")
+}
+
+///
+/// Render the footer as the right-aligned virtual text of the input line
+///
+pub(crate) fn update_picker_footer(
+    input_buffer: &mut Buffer,
+    matched_count: usize,
+    total_count: usize,
+) {
+    let namespace_id = create_namespace(PICKER_FOOTER_NAMESPACE);
+    let _ = input_buffer.clear_namespace(namespace_id, ..);
+
+    let footer_text = get_picker_footer_text(matched_count, total_count);
+    let _ = input_buffer.set_extmark(
+        namespace_id,
+        0,
+        0,
+        &SetExtmarkOpts::builder()
+            .virt_text([(footer_text.as_str(), "Comment")])
+            .virt_text_pos(ExtmarkVirtTextPosition::RightAlign)
+            .build(),
+    );
+}
+
+///
+/// Re-render the list buffer with the filtered items and reset the list cursor to the first line
+///
+pub(crate) fn render_filtered_items(
+    list_buffer_handle: i32,
+    list_window_handle: i32,
+    filtered_items: &[PickerItem],
+) {
+    let mut list_buffer = Buffer::from(list_buffer_handle);
+    let list_buffer_opts = OptionOpts::builder().buffer(list_buffer.clone()).build();
+
+    let rendered_items = render_picker_items(filtered_items);
+
+    let _ = set_option_value("modifiable", true, &list_buffer_opts);
+    let _ = list_buffer.set_lines(.., true, rendered_items.lines.iter().map(|v| v.as_str()));
+    let _ = set_option_value("modifiable", false, &list_buffer_opts);
+
+    apply_picker_item_highlights(
+        &mut list_buffer,
+        create_namespace(PICKER_HIGHLIGHT_NAMESPACE),
+        &rendered_items.highlights,
+    );
+
+    let _ = Window::from(list_window_handle).set_cursor(1, 0);
+}
+
+///
+/// Validate the input text before calling the `selected_callback`, the `Err` message is shown
+/// under the input window and the picker stays open.
+///
+pub type PickerInputValidator = fn(&str) -> Result<(), String>;
+
+///
+/// `<c-d>`: Delete the stored entry of the item data under the list cursor, return `true` to
+/// remove the item from the list.
+///
+pub type PickerItemDeleteHandler = fn(&str) -> bool;
+
+///
+/// `<c-r>` and then `<CR>`: Replace the stored entry (the old item data) with the edited input,
+/// the `Err` message is shown under the input window and it stays in the edit mode.
+///
+pub type PickerItemEditHandler = fn(&str, &str) -> Result<(), String>;
+
 ///
 /// Editable picker options
 ///
@@ -169,7 +269,46 @@ where
 pub struct EditablePickerOptions<'epo> {
     pub title: String,
     pub window_opts: PopupWindowOptions,
-    pub list: &'epo Vec<String>,
+    pub list: &'epo Vec<PickerItem>,
+
+    //
+    // How to sort the `list` before rendering, `Recency` and `Frecency` require the `source`.
+    //
+    pub sort: PickerSortStrategy,
+
+    //
+    // The unique picker source name (e.g. "project_commands"), the selection history is tracked
+    // by this name.
+    //
+    pub source: Option<String>,
+
+    //
+    // Pre-fill the input with the last query of the same `source` (selected for quick overwrite),
+    // it's useful for the iterative refinement workflows. Only works when `source` is provided.
+    //
+    pub sticky_filter: bool,
+
+    //
+    // Show the "matched/total" counts and keybinding hints on the right side of the input.
+    //
+    pub show_footer: bool,
+
+    //
+    // Pre-fill the input and stay at the end of it in `INSERT` mode (e.g. "cargo " to compose a
+    // new command), it wins over the `sticky_filter`.
+    //
+    pub initial_input: Option<String>,
+
+    //
+    // Reject the invalid input on `<CR>`, e.g. the project command with the unclosed quote
+    //
+    pub validator: Option<PickerInputValidator>,
+
+    //
+    // Manage the stored entries in the list, e.g. delete or fix the typo'd project command
+    //
+    pub on_delete: Option<PickerItemDeleteHandler>,
+    pub on_edit: Option<PickerItemEditHandler>,
 }
 
 ///
@@ -177,18 +316,15 @@ pub struct EditablePickerOptions<'epo> {
 ///
 #[derive(Debug)]
 pub struct EditablePickerOpenResult {
-    pub title_window_handle: i32,
     input_window_handle: i32,
     list_window_handle: i32,
 }
 
 ///
-/// Create an editor picker from the given list, split across three windows with their own buffers
+/// Create an editor picker from the given list, split across two windows with their own buffers
 /// like this:
 ///
-/// /-------------------------------------\
-/// | Title                               | <-- Title window and buffer.
-/// |-------------------------------------|
+/// /-------------- Title ----------------\ <-- The native title of the input window.
 /// | User input                          | <-- Input window and buffer.
 /// |-------------------------------------|
 /// | List line 0                         |
@@ -197,12 +333,15 @@ pub struct EditablePickerOpenResult {
 /// | List line ...                       |
 /// \-------------------------------------/
 ///
-/// After creating three buffers and three windows, set the second window as the current window
+/// After creating two buffers and two windows, set the input window as the current window
 /// (i.e., give it focus and input). Also, set the following keybindings for the input buffer:
 ///
 /// - <c-j>/<c-k>: Move the cursor up and down in the list buffer.
+/// - <Tab>: Complete the input with the list item under cursor.
 /// - <CR>: Add input into the list buffer IF it doesn't exists, and then trigger callback.
 ///
+/// Typing in the input buffer filters the list (fuzzy match) on the fly.
+///
 pub fn create_editable_picker_with_options<F>(
     opts: &mut EditablePickerOptions,
     selected_callback: F,
@@ -221,27 +360,30 @@ where
     //
     // Create buffers
     //
-    let mut title_buffer = create_popup_buffer()?;
-    let input_buffer = create_popup_buffer()?;
+    let mut input_buffer = create_popup_buffer()?;
     let mut list_buffer = create_popup_buffer()?;
 
-    // Fill list buffer
-    let list_content = opts.list.iter().map(|v| v.as_str()).collect::<Vec<&str>>();
-    let _ = list_buffer.set_lines(.., true, list_content)?;
-
-    // Fill title buffer and set highlight colors
-    let _ = title_buffer.set_lines(.., true, vec![opts.title.clone()])?;
+    // Sort a copy of the given list, as the caller still owns it.
+    let mut sorted_items = opts.list.clone();
+    sort_picker_items(&mut sorted_items, &opts.sort, opts.source.as_deref());
+
+    // Fill list buffer with the aligned columns and apply the column highlights
+    let rendered_items = render_picker_items(&sorted_items);
+    let _ = list_buffer.set_lines(.., true, rendered_items.lines.iter().map(|v| v.as_str()))?;
+    apply_picker_item_highlights(
+        &mut list_buffer,
+        create_namespace(PICKER_HIGHLIGHT_NAMESPACE),
+        &rendered_items.highlights,
+    );
 
     //
     // Not allow to modify after adding content
     //
-    let title_buffer_opts = OptionOpts::builder().buffer(title_buffer.clone()).build();
     let list_buffer_opts = OptionOpts::builder().buffer(list_buffer.clone()).build();
-    let _ = set_option_value("modifiable", false, &title_buffer_opts);
     let _ = set_option_value("modifiable", false, &list_buffer_opts);
 
     //
-    // Calculate the outter virtual window size to hold all 3 inner windows
+    // Calculate the outter virtual window size to hold both inner windows
     //
     let screen_size = get_screen_size();
 
@@ -258,32 +400,51 @@ where
 
     // Auto width logic
     if opts.window_opts.auto_width && opts.window_opts.window_width_ratio.is_none() {
-        // Loop through all lines in all buffers to find the longest one
-        let mut max_cols = opts.title.len();
-
-        for line in opts.list.iter() {
-            if line.len() > max_cols {
-                max_cols = line.len();
-            }
+        // Find the longest one between the title (padded on the border), the footer (on the
+        // input line) and all rendered lines
+        let mut title_cols = display_width(&opts.title) + 2;
+        if opts.show_footer {
+            let total_count = sorted_items.len();
+            title_cols = title_cols
+                .max(display_width(&get_picker_footer_text(total_count, total_count)) + 2);
+        }
+        let max_cols = std::cmp::max(title_cols, rendered_items.max_line_width());
 
-            // #[cfg(feature = "enable_picker_debug_print")]
-            // nvim::print!("\n>>> {LOGGER_PREFIX} max_cols (without paddings): {max_cols}");
+        // #[cfg(feature = "enable_picker_debug_print")]
+        // nvim::print!("\n>>> {LOGGER_PREFIX} max_cols (without paddings): {max_cols}");
 
-            if max_cols > 0 {
-                let both_padding = (POPUP_WINDOW_AUTO_WIDTH_PADDING_EACH_SIDE * 2) as f32;
-                width = (max_cols as f32 + both_padding) as f32;
-            }
+        if max_cols > 0 {
+            let both_padding = (POPUP_WINDOW_AUTO_WIDTH_PADDING_EACH_SIDE * 2) as f32;
+            width = (max_cols as f32 + both_padding) as f32;
         }
     }
 
     // Auto height logic
     if opts.window_opts.auto_height && opts.window_opts.window_height_ratio.is_none() {
-        height = opts.list.len() as f32 + 2.0f32; // 1 line title, 1 line empty input
+        height = sorted_items.len() as f32 + 1.0f32; // 1 line empty input
 
         // #[cfg(feature = "enable_picker_debug_print")]
         // nvim::print!("\n>>> {LOGGER_PREFIX} max_rows: {height}");
     }
 
+    //
+    // Clamp into the size constraints, the height includes 1 line input, and there are 3
+    // borders in vertical.
+    //
+    let (border_width, border_height) = if opts.window_opts.border == WindowBorder::None {
+        (0.0f32, 0.0f32)
+    } else {
+        (2.0f32, 3.0f32)
+    };
+    (width, height) = clamp_popup_window_size(
+        &opts.window_opts,
+        &screen_size,
+        width,
+        height,
+        border_width,
+        border_height,
+    );
+
     // #[cfg(feature = "enable_picker_debug_print")]
     // nvim::print!("\n>>> {LOGGER_PREFIX} width: {width}, height: {height}");
 
@@ -296,78 +457,54 @@ where
     let cal_height = if opts.window_opts.border == WindowBorder::None {
         height
     } else {
-        height + 4.0f32 // 4 borders!!!
+        height + 3.0f32 // 3 borders!!!
     };
     let left = (((screen_size.width as f32 - cal_width) / 2f32).floor()) as u32;
-    let mut top = (((screen_size.height as f32 - cal_height) / 2f32).floor()) as u32;
+    let top = (((screen_size.height as f32 - cal_height) / 2f32).floor()) as u32;
 
     // #[cfg(feature = "enable_picker_debug_print")]
     // nvim::print!("\n>>> {LOGGER_PREFIX} cal_width: {cal_width}, cal_height: {cal_height}");
 
     //
-    // Title window
+    // Input window
     //
-    let mut title_window_handle = -1;
+    let mut input_window_handle = -1;
 
-    let title_win_popup_border = WindowBorder::Anal(
+    let input_win_popup_border = WindowBorder::Anal(
         WindowBorderChar::Char(Some('╭')), // Left-top corner
         WindowBorderChar::Char(Some('─')), // Top
         WindowBorderChar::Char(Some('╮')), // Right-top corner
         WindowBorderChar::Char(Some('│')), // Right-vertical
-        WindowBorderChar::Char(None),      // Right-bottom corner
-        WindowBorderChar::Char(None),      // bottom
-        WindowBorderChar::Char(None),      // Left-bottom corner
+        WindowBorderChar::Char(Some('│')), // Right-bottom corner
+        WindowBorderChar::Char(Some('─')), // bottom
+        WindowBorderChar::Char(Some('│')), // Left-bottom corner
         WindowBorderChar::Char(Some('│')), // Left-vertical
     );
 
-    let title_window_config = WindowConfig::builder()
+    let mut input_window_config_builder = WindowConfig::builder();
+    input_window_config_builder
         .relative(WindowRelativeTo::Editor)
         .width(width as u32)
         .height(1)
         .row(top)
         .col(left)
-        .border(title_win_popup_border)
-        .build();
-
-    // #[cfg(feature = "enable_picker_debug_print")]
-    // nvim::print!("\n>>> {LOGGER_PREFIX} title_window_config: {title_window_config:#?}");
-
-    if let Ok(title_window) = open_win(&title_buffer, false, &title_window_config) {
-        title_window_handle = title_window.handle();
-
-        // Add window left padding
-        let _ = set_option_value(
-            "foldcolumn",
-            POPUP_WINDOW_AUTO_WIDTH_PADDING_EACH_SIDE.to_string(),
-            &OptionOpts::builder().win(title_window.clone()).build(),
-        );
+        .border(input_win_popup_border);
+    if let Some(zindex) = opts.window_opts.zindex {
+        input_window_config_builder.zindex(zindex);
     }
 
     //
-    // Input window
+    // The title goes to the native float config (the top border of the input window)
     //
-    let mut input_window_handle = -1;
-
-    let input_win_popup_border = WindowBorder::Anal(
-        WindowBorderChar::Char(Some('│')), // Left-top corner
-        WindowBorderChar::Char(Some('─')), // Top
-        WindowBorderChar::Char(Some('│')), // Right-top corner
-        WindowBorderChar::Char(Some('│')), // Right-vertical
-        WindowBorderChar::Char(Some('│')), // Right-bottom corner
-        WindowBorderChar::Char(Some('─')), // bottom
-        WindowBorderChar::Char(Some('│')), // Left-bottom corner
-        WindowBorderChar::Char(Some('│')), // Left-vertical
-    );
+    if !opts.title.is_empty() {
+        input_window_config_builder
+            .title(WindowTitle::SimpleString(
+                format!(" {} ", opts.title).as_str().into(),
+            ))
+            .title_pos(opts.window_opts.title_pos.clone());
+    }
 
-    top += 2;
-    let input_window_config = WindowConfig::builder()
-        .relative(WindowRelativeTo::Editor)
-        .width(width as u32)
-        .height(1)
-        .row(top)
-        .col(left)
-        .border(input_win_popup_border)
-        .build();
+    let input_window_config = input_window_config_builder.build();
 
     // #[cfg(feature = "enable_picker_debug_print")]
     // nvim::print!("\n>>> {LOGGER_PREFIX} input_window_config: {input_window_config:#?}");
@@ -399,16 +536,37 @@ where
         WindowBorderChar::Char(Some('│')), // Left-vertical
     );
 
-    top += 3; // title_win height: 1, input_win height: 1
-    let list_len = opts.list.len() as u32;
-    let list_window_config = WindowConfig::builder()
+    let list_top = top + 3; // input_win top border, height: 1, bottom border
+    let list_len = sorted_items.len() as u32;
+    let list_max_height = (height as u32).saturating_sub(1).max(1);
+    let list_height =
+        if opts.window_opts.auto_height && opts.window_opts.window_height_ratio.is_none() {
+            list_max_height
+        } else {
+            list_len.clamp(1, list_max_height)
+        };
+    let mut list_window_config_builder = WindowConfig::builder();
+    list_window_config_builder
         .relative(WindowRelativeTo::Editor)
         .width(width as u32)
-        .height(if list_len > 0 { list_len } else { 1 })
-        .row(top)
+        .height(list_height)
+        .row(list_top)
         .col(left)
-        .border(list_win_popup_border)
-        .build();
+        .border(list_win_popup_border);
+    if let Some(zindex) = opts.window_opts.zindex {
+        list_window_config_builder.zindex(zindex);
+    }
+
+    //
+    // The footer goes to the native float config (the bottom border of the list window)
+    //
+    if let Some(footer) = &opts.window_opts.footer {
+        list_window_config_builder
+            .footer(WindowFooter::SimpleString(footer.as_str().into()))
+            .footer_pos(opts.window_opts.footer_pos.clone());
+    }
+
+    let list_window_config = list_window_config_builder.build();
 
     // #[cfg(feature = "enable_picker_debug_print")]
     // nvim::print!("\n>>> {LOGGER_PREFIX} list_window_config: {list_window_config:#?}");
@@ -437,27 +595,187 @@ where
     // Add left padding to all windows
     //
 
+    //
+    // Record the selection history for the `Recency` and `Frecency` sorting strategy before
+    // calling the `selected_callback`
+    //
+    let source = opts.source.clone();
+    let mut selected_callback = selected_callback;
+    let selected_callback_with_history = move |selected_text: String| {
+        if let Some(source_name) = &source {
+            record_picker_selection(source_name, &selected_text);
+        }
+        selected_callback(selected_text);
+    };
+
     //
     // Inupt buffer keybindings:
     //
+    let input_state = Rc::new(RefCell::new(PickerInputState {
+        visible_data_list: sorted_items
+            .iter()
+            .map(|item| item.data.clone())
+            .collect::<Vec<String>>(),
+        filled_text: None,
+        all_items: sorted_items.clone(),
+        editing_data: None,
+        show_footer: opts.show_footer,
+    }));
     let _ = set_input_buffer_keybindings(
-        title_window_handle,
         input_window_handle,
         list_window_handle,
-        selected_callback,
+        input_state.clone(),
+        PickerInputHandlers {
+            validator: opts.validator,
+            on_delete: opts.on_delete,
+            on_edit: opts.on_edit,
+        },
+        selected_callback_with_history,
     );
 
+    //
+    // Footer: "matched/total" counts and keybinding hints on the right side of the input
+    //
+    let total_count = sorted_items.len();
+    if opts.show_footer {
+        update_picker_footer(&mut input_buffer, total_count, total_count);
+    }
+
+    //
+    // Sticky filter: get back the last query
+    //
+    let mut last_query: Option<String> = None;
+    let sticky_source = if opts.sticky_filter {
+        opts.source.clone()
+    } else {
+        None
+    };
+    if let Some(source_name) = &sticky_source {
+        last_query = PICKER_STICKY_FILTER_STATE
+            .lock()
+            .unwrap()
+            .get(source_name)
+            .filter(|query| !query.is_empty())
+            .cloned();
+    }
+
+    //
+    // Live filter: re-render the list buffer and the footer on every input change (the
+    // `on_lines` events of the input buffer), and remember the query for the sticky filter. It's
+    // detached automatically when the input buffer is wiped out.
+    //
+    let show_footer = opts.show_footer;
+    let list_buffer_handle = list_buffer.handle();
+    let live_filter_attach_id = Rc::new(Cell::new(None::<u64>));
+    let attach_id = buf_attach(&input_buffer, {
+        let live_filter_attach_id = live_filter_attach_id.clone();
+        move |event: BufLinesEvent| {
+            //
+            // The picker is closed before the event is delivered
+            //
+            if !Buffer::from(list_buffer_handle).is_valid() {
+                if let Some(attach_id) = live_filter_attach_id.get() {
+                    buf_detach(attach_id);
+                }
+                return;
+            }
+
+            //
+            // Only the first line is the query, and skip the stale event as a newer one is
+            // on the way.
+            //
+            let input_buffer = Buffer::from(event.buffer);
+            if event.first_line > 0
+                || input_buffer.get_changedtick().ok() != Some(event.changedtick)
+            {
+                return;
+            }
+
+            let mut query = String::from("");
+            if let Ok(mut lines) = input_buffer.get_lines(0..1, true)
+                && let Some(first_line) = lines.next()
+            {
+                query = first_line.to_str().unwrap_or_default().to_owned();
+            }
+
+            //
+            // Ignore the text filled by <c-j>/<c-k>/<Tab>, otherwise, the list only has the
+            // selected item left.
+            //
+            if input_state.borrow().filled_text.as_deref() == Some(query.as_str()) {
+                return;
+            }
+            input_state.borrow_mut().filled_text = None;
+            clear_validation_error(list_buffer_handle);
+
+            if let Some(source_name) = &sticky_source {
+                PICKER_STICKY_FILTER_STATE
+                    .lock()
+                    .unwrap()
+                    .insert(source_name.clone(), query.clone());
+            }
+
+            let (filtered_items, total_count) = {
+                let locked_input_state = input_state.borrow();
+                (
+                    filter_picker_items(&locked_input_state.all_items, &query),
+                    locked_input_state.all_items.len(),
+                )
+            };
+            input_state.borrow_mut().visible_data_list = filtered_items
+                .iter()
+                .map(|item| item.data.clone())
+                .collect::<Vec<String>>();
+
+            render_filtered_items(list_buffer_handle, list_window_handle, &filtered_items);
+
+            if show_footer {
+                update_picker_footer(
+                    &mut Buffer::from(event.buffer),
+                    filtered_items.len(),
+                    total_count,
+                );
+            }
+        }
+    });
+    live_filter_attach_id.set(attach_id);
+
     //
     // Reset the input window as current window to get focus and input, and go into `INSERT` mode.
     //
     let _ = set_current_win(&Window::from(input_window_handle));
-    let command = "startinsert";
-    let infos = CmdInfos::builder().cmd(command).build();
-    let opts = CmdOpts::builder().output(false).build();
-    let _ = vim_cmd(&infos, &opts);
+    if let Some(initial_input) = &opts.initial_input {
+        let _ = input_buffer.set_lines(.., true, vec![initial_input.as_str()]);
+
+        // `startinsert!` works like `A`: append at the end of the line
+        let command = "startinsert";
+        let infos = CmdInfos::builder().cmd(command).bang(true).build();
+        let opts = CmdOpts::builder().output(false).build();
+        let _ = vim_cmd(&infos, &opts);
+    } else {
+        match last_query {
+            //
+            // Fill the last query and select it in `SELECT` mode: typing anything overwrites it, or
+            // press <c-j>/<c-k>/<CR> to keep using it (the `SELECT` mode keybindings of the input
+            // buffer).
+            //
+            // "0vg_" selects the entire line in `VISUAL` mode, then "<c-g>" ("\x07") switches to
+            // `SELECT` mode.
+            //
+            Some(query) => {
+                let _ = input_buffer.set_lines(.., true, vec![query.as_str()]);
+                let _ = call_function::<_, i64>("feedkeys", ("0vg_\x07", "n"));
+            }
+            None => {
+                let command = "startinsert";
+                let infos = CmdInfos::builder().cmd(command).build();
+                let opts = CmdOpts::builder().output(false).build();
+                let _ = vim_cmd(&infos, &opts);
+            }
+        }
+    }
 
     Ok(EditablePickerOpenResult {
-        title_window_handle,
         input_window_handle,
         list_window_handle,
     })
@@ -481,11 +799,14 @@ fn run_test_picker() {
                 auto_width: true,
                 auto_height: true,
                 buffer: None,
+                ..Default::default()
             },
             list: vec![
-                String::from("./build.sh"),
-                String::from("./build_release.sh"),
+                PickerItem::from("./build.sh"),
+                PickerItem::from("./build_release.sh"),
             ],
+            sort: PickerSortStrategy::Alphabetical,
+            source: None,
         },
         |picker_buffer_id: BufHandle, picker_window_id: WinHandle| {
             if let Ok(selected_line) = get_current_line() {
@@ -504,44 +825,24 @@ fn run_test_picker() {
 }
 
 ///
+/// The files under the current working directory (respect `.gitignore`) for the "files" source,
+/// `git ls-files` is the fallback if `rg` isn't installed.
 ///
-///
-fn run_test_picker_2() {
-    #[cfg(feature = "enable_picker_debug_print")]
-    const LOGGER_PREFIX: &'static str = "[ picker - run_test_picker_2 ]";
-
-    let result = create_editable_picker_with_options(
-        &mut EditablePickerOptions {
-            title: "Project Command ('Ctrl+d' to delete item, 'tab' to fill input)".to_string(),
-            window_opts: PopupWindowOptions {
-                border: WindowBorder::Rounded,
-                // window_width_ratio: Some(0.7),
-                // window_height_ratio: Some(0.7),
-                window_width_ratio: None,
-                window_height_ratio: None,
-                auto_width: true,
-                auto_height: true,
-                buffer: None,
-            },
-            list: &vec![
-                String::from("11111"),
-                String::from("22222"),
-                String::from("33333"),
-                String::from("44444"),
-                String::from("./build.sh"),
-                String::from("./build_release.sh"),
-            ],
-        },
-        |selected_text: String| {
-            #[cfg(feature = "enable_picker_debug_print")]
-            nvim::print!("\n>>> {LOGGER_PREFIX} Pressed ENTER, selected_text: {selected_text}",);
+fn get_file_picker_items() -> Vec<PickerItem> {
+    let output = match cmd_utils::execute_command(vec!["rg", "--files", "--color", "never"]) {
+        cmd_utils::ExecuteCommandResult::Success { output, .. } => output,
+        _ => match cmd_utils::execute_command(vec!["git", "ls-files"]) {
+            cmd_utils::ExecuteCommandResult::Success { output, .. } => output,
+            _ => return Vec::new(),
         },
-    );
-
-    let _ = result;
+    };
 
-    #[cfg(feature = "enable_picker_debug_print")]
-    nvim::print!("\n>>> {LOGGER_PREFIX} result: {:?}", result);
+    let mut files = output
+        .lines()
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>();
+    files.sort_unstable();
+    files.into_iter().map(PickerItem::from).collect()
 }
 
 ///
@@ -550,45 +851,102 @@ fn run_test_picker_2() {
 pub fn setup() {
     let picker_keybindings_with_callback: Vec<(Mode, &str, &str, Box<dyn Fn()>)> = vec![(
         Mode::Normal,
-        "<leader>tp",
-        "'<leader>tp': Test picker.",
+        "<leader>pp",
+        "'<leader>pp': List all registered pickers.",
         Box::new(|| {
-            // run_test_picker();
-            run_test_picker_2();
+            open_picker_registry();
         }),
     )];
 
     for bindings in picker_keybindings_with_callback {
-        let _ = set_keymap(
-            bindings.0,
-            bindings.1,
-            "",
-            &SetKeymapOpts::builder()
-                .desc(bindings.2)
-                .callback(move |_| {
-                    bindings.3();
-                    ()
-                })
-                .silent(true)
-                .build(),
+        let keymap = bindings.1;
+        report(
+            "picker",
+            set_keymap(
+                bindings.0,
+                keymap,
+                "",
+                &SetKeymapOpts::builder()
+                    .desc(bindings.2)
+                    .callback(move |_| {
+                        bindings.3();
+                        ()
+                    })
+                    .silent(true)
+                    .build(),
+            )
+            .context(&format!("set the keymap '{keymap}'")),
         );
     }
+
+    register_picker_source(
+        "files",
+        "Files",
+        get_file_picker_items,
+        |selected_file: String| {
+            if selected_file.is_empty() {
+                return;
+            }
+
+            let escaped_file = call_function::<_, String>("fnameescape", (selected_file.as_str(),))
+                .unwrap_or(selected_file);
+            report(
+                "picker",
+                command(&format!("edit {escaped_file}")).context(&format!("open '{escaped_file}'")),
+            );
+        },
+    );
+
+    register_user_command(
+        "PickerFiles",
+        UserCommandSpec {
+            desc: "Pick the file under the current working directory",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| open_registered_picker("files"),
+    );
 }
 
+use crate::error::{ResultContext, report};
+
+use crate::extended_api::{
+    BufLinesEvent, UserCommandArgs, UserCommandSpec, buf_attach, buf_detach, register_user_command,
+};
+
 use crate::picker::{
-    PopupWindowOptions, create_popup_window, get_screen_size,
-    keybindings::set_input_buffer_keybindings,
+    PickerItem, PickerItemColumn, PickerSortStrategy, PopupWindowOptions,
+    apply_picker_item_highlights, clamp_popup_window_size, create_popup_window,
+    filter_picker_items, get_screen_size,
+    keybindings::{
+        PickerInputHandlers, PickerInputState, clear_validation_error, set_input_buffer_keybindings,
+    },
+    open_picker_registry, open_registered_picker, record_picker_selection, register_picker_source,
+    render_picker_items, sort_picker_items,
+};
+
+use crate::utils::display_width;
+
+use rust_utils::cmd as cmd_utils;
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    sync::{LazyLock, Mutex},
 };
 
 use nvim_oxi::{
     BufHandle, WinHandle,
     api::{
-        Buffer, Error as NvimError, Window, cmd as vim_cmd, create_buf, get_current_line, open_win,
-        opts::{CmdOpts, OptionOpts, SetKeymapOpts},
+        Buffer, Error as NvimError, Window, call_function, cmd as vim_cmd, command, create_buf,
+        create_namespace, get_current_line, open_win,
+        opts::{CmdOpts, OptionOpts, SetExtmarkOpts, SetKeymapOpts},
         set_current_win, set_keymap, set_option_value,
-        types::{CmdInfos, Mode, WindowBorder, WindowBorderChar, WindowConfig, WindowRelativeTo},
+        types::{
+            CmdInfos, ExtmarkVirtTextPosition, Mode, WindowBorder, WindowBorderChar, WindowConfig,
+            WindowFooter, WindowRelativeTo, WindowTitle,
+        },
     },
 };
 
-#[cfg(feature = "enable_picker_debug_print")]
 use nvim_oxi as nvim;
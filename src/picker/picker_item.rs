@@ -0,0 +1,298 @@
+const PICKER_ITEM_COLUMN_SEPARATOR: &'static str = "  ";
+
+///
+/// A single display column of the picker item, `hl_group` is optional.
+///
+#[derive(Debug, Clone, Default)]
+pub struct PickerItemColumn {
+    pub text: String,
+    pub hl_group: Option<String>,
+}
+
+impl PickerItemColumn {
+    pub fn new(text: &str, hl_group: Option<&str>) -> Self {
+        Self {
+            text: text.to_owned(),
+            hl_group: hl_group.map(|hl| hl.to_owned()),
+        }
+    }
+}
+
+///
+/// Picker item:
+///
+/// - `columns`: All display columns, they will be aligned across all items when rendering.
+/// - `icon`: Optional icon on the left side of the first column.
+/// - `data`: The value passes to the `selected_callback` (and fills the input buffer) when the
+///           item is selected.
+///
+#[derive(Debug, Clone, Default)]
+pub struct PickerItem {
+    pub columns: Vec<PickerItemColumn>,
+    pub icon: Option<PickerItemColumn>,
+    pub data: String,
+}
+
+///
+/// Plain text item: single column without highlight, `data` is the same with the text.
+///
+impl From<&str> for PickerItem {
+    fn from(text: &str) -> Self {
+        Self {
+            columns: vec![PickerItemColumn::new(text, None)],
+            icon: None,
+            data: text.to_owned(),
+        }
+    }
+}
+
+impl From<String> for PickerItem {
+    fn from(text: String) -> Self {
+        PickerItem::from(text.as_str())
+    }
+}
+
+///
+/// Highlight range in the rendered line, `start_col` and `end_col` are byte offsets.
+///
+#[derive(Debug, Clone)]
+pub struct PickerItemHighlight {
+    pub line: usize,
+    pub start_col: usize,
+    pub end_col: usize,
+    pub hl_group: String,
+}
+
+///
+/// Rendered picker items which are ready to be set into the list buffer
+///
+#[derive(Debug, Default)]
+pub struct RenderedPickerItems {
+    pub lines: Vec<String>,
+    pub highlights: Vec<PickerItemHighlight>,
+}
+
+impl RenderedPickerItems {
+    ///
+    /// The longest line in display cells, used by the auto width logic.
+    ///
+    pub fn max_line_width(&self) -> usize {
+        self.lines
+            .iter()
+            .map(|line| display_width(line))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+///
+/// Render items into aligned lines like this:
+///
+/// <icon> column_1      column_2  column_3
+/// <icon> column_1_123  column_2  column_3
+///
+/// The column width is the longest text (in display cells, so the CJK, emoji and double-width
+/// icons still align) of that column across all items, and the last column never gets padded.
+///
+pub fn render_picker_items(items: &[PickerItem]) -> RenderedPickerItems {
+    let has_icon = items.iter().any(|item| item.icon.is_some());
+    let icon_widths = items
+        .iter()
+        .map(|item| {
+            item.icon
+                .as_ref()
+                .map_or(0, |icon| display_width(&icon.text))
+        })
+        .collect::<Vec<usize>>();
+    let icon_width = icon_widths.iter().copied().max().unwrap_or(0);
+
+    let column_count = items
+        .iter()
+        .map(|item| item.columns.len())
+        .max()
+        .unwrap_or(0);
+    //
+    // Each text width is calculated once, it's reused by the padding below
+    //
+    let item_column_widths = items
+        .iter()
+        .map(|item| {
+            item.columns
+                .iter()
+                .map(|column| display_width(&column.text))
+                .collect::<Vec<usize>>()
+        })
+        .collect::<Vec<Vec<usize>>>();
+    let mut column_widths = vec![0usize; column_count];
+    for widths in &item_column_widths {
+        for (index, width) in widths.iter().enumerate() {
+            if *width > column_widths[index] {
+                column_widths[index] = *width;
+            }
+        }
+    }
+
+    let mut rendered = RenderedPickerItems {
+        lines: Vec::with_capacity(items.len()),
+        highlights: Vec::with_capacity(items.len()),
+    };
+
+    for (line_index, item) in items.iter().enumerate() {
+        let mut line = String::with_capacity(64);
+
+        //
+        // Icon
+        //
+        if has_icon {
+            let (icon_text, icon_hl) = match &item.icon {
+                Some(icon) => (icon.text.as_str(), icon.hl_group.as_ref()),
+                None => ("", None),
+            };
+
+            let start_col = line.len();
+            line.push_str(icon_text);
+            if let Some(hl_group) = icon_hl {
+                rendered.highlights.push(PickerItemHighlight {
+                    line: line_index,
+                    start_col,
+                    end_col: line.len(),
+                    hl_group: hl_group.clone(),
+                });
+            }
+
+            let padding = icon_width - icon_widths[line_index];
+            line.push_str(&" ".repeat(padding + 1));
+        }
+
+        //
+        // Columns
+        //
+        for (index, column) in item.columns.iter().enumerate() {
+            if index > 0 {
+                line.push_str(PICKER_ITEM_COLUMN_SEPARATOR);
+            }
+
+            let start_col = line.len();
+            line.push_str(&column.text);
+            if let Some(hl_group) = &column.hl_group {
+                rendered.highlights.push(PickerItemHighlight {
+                    line: line_index,
+                    start_col,
+                    end_col: line.len(),
+                    hl_group: hl_group.clone(),
+                });
+            }
+
+            let is_last_column = index == item.columns.len() - 1;
+            if !is_last_column {
+                let padding = column_widths[index] - item_column_widths[line_index][index];
+                line.push_str(&" ".repeat(padding));
+            }
+        }
+
+        rendered.lines.push(line);
+    }
+
+    rendered
+}
+
+///
+/// Private module-scope state: the highlights of the picker buffers (by line), and whether the
+/// decoration provider is set.
+///
+#[derive(Debug, Default)]
+struct PickerItemDecorationState {
+    is_provider_set: bool,
+    highlights: HashMap<BufHandle, Vec<Vec<PickerItemHighlight>>>,
+}
+
+static PICKER_ITEM_DECORATION_STATE: LazyLock<Mutex<PickerItemDecorationState>> =
+    LazyLock::new(|| Mutex::new(PickerItemDecorationState::default()));
+
+///
+/// Draw the highlights of the visible picker lines during the redraw
+///
+struct PickerItemDecorationProvider;
+
+impl DecorationProvider for PickerItemDecorationProvider {
+    fn on_win(&mut self, _: &Window, buffer: &Buffer, _: usize, _: usize) -> bool {
+        PICKER_ITEM_DECORATION_STATE
+            .lock()
+            .unwrap()
+            .highlights
+            .contains_key(&buffer.handle())
+    }
+
+    fn on_line(&mut self, _: &Window, buffer: &mut Buffer, row: usize, namespace_id: u32) {
+        let locked_state = PICKER_ITEM_DECORATION_STATE.lock().unwrap();
+        let Some(line_highlights) = locked_state
+            .highlights
+            .get(&buffer.handle())
+            .and_then(|lines| lines.get(row))
+        else {
+            return;
+        };
+
+        for hl in line_highlights {
+            set_ephemeral_highlight(
+                buffer,
+                namespace_id,
+                hl.line,
+                hl.start_col,
+                hl.end_col,
+                &hl.hl_group,
+            );
+        }
+    }
+}
+
+///
+/// Apply the rendered highlights to the given buffer, they're drawn by the decoration provider
+/// of the namespace for the visible lines only, so the big list doesn't set thousands of
+/// extmarks.
+///
+pub fn apply_picker_item_highlights(
+    buffer: &mut Buffer,
+    namespace_id: u32,
+    highlights: &[PickerItemHighlight],
+) {
+    let mut locked_state = PICKER_ITEM_DECORATION_STATE.lock().unwrap();
+    if !locked_state.is_provider_set {
+        locked_state.is_provider_set =
+            set_decoration_provider(namespace_id, PickerItemDecorationProvider);
+    }
+
+    //
+    // Drop the closed pickers
+    //
+    locked_state
+        .highlights
+        .retain(|handle, _| Buffer::from(*handle).is_valid());
+
+    let line_count = highlights.iter().map(|hl| hl.line + 1).max().unwrap_or(0);
+    let mut line_highlights = (0..line_count)
+        .map(|_| Vec::new())
+        .collect::<Vec<Vec<PickerItemHighlight>>>();
+    for hl in highlights {
+        line_highlights[hl.line].push(hl.clone());
+    }
+
+    locked_state
+        .highlights
+        .insert(buffer.handle(), line_highlights);
+}
+
+use crate::{
+    extended_api::{DecorationProvider, set_decoration_provider, set_ephemeral_highlight},
+    utils::display_width,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    BufHandle,
+    api::{Buffer, Window},
+};
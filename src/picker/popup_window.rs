@@ -12,6 +12,152 @@ pub struct PopupWindowOptions {
     pub auto_width: bool,                 // Only works when `window_width_ratio` is `None`
     pub auto_height: bool,                // Only works when `window_height_ratio` is `None`
     pub buffer: Option<BufHandle>,
+
+    //
+    // The native float title (on the top border) and footer (on the bottom border), they only
+    // work when the `border` is NOT `WindowBorder::None`.
+    //
+    pub title: Option<String>,
+    pub title_pos: WindowTitlePosition,
+    pub footer: Option<String>,
+    pub footer_pos: WindowFooterPosition,
+
+    //
+    // The computed size (including the auto size) is clamped into these constraints (without
+    // borders), and never exceeds the screen.
+    //
+    pub min_width: Option<u32>,
+    pub max_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub max_height: Option<u32>,
+
+    //
+    // Which corner of the popup window is placed at the computed position (the popup window
+    // is always centered), it's the corner which stays in place when resizing.
+    //
+    pub anchor: WindowAnchor,
+
+    //
+    // The stacking order, the bigger one is on top (Neovim's default is `50`). e.g. the preview
+    // and notification popups can sit above the pickers.
+    //
+    pub zindex: Option<u32>,
+
+    //
+    // - `focusable: false`: The informational popup which never steals the focus (it's NOT
+    //   entered after opening, and `<c-w>w` skips it).
+    // - `focusable: true`: The modal popup which gets the focus after opening.
+    //
+    pub focusable: bool,
+}
+
+impl Default for PopupWindowOptions {
+    fn default() -> Self {
+        Self {
+            border: WindowBorder::Rounded,
+            window_width_ratio: None,
+            window_height_ratio: None,
+            auto_width: true,
+            auto_height: true,
+            buffer: None,
+            title: None,
+            title_pos: WindowTitlePosition::Center,
+            footer: None,
+            footer_pos: WindowFooterPosition::Right,
+            min_width: None,
+            max_width: None,
+            min_height: None,
+            max_height: None,
+            anchor: WindowAnchor::NorthWest,
+            zindex: None,
+            focusable: true,
+        }
+    }
+}
+
+///
+/// The popup window handle which owns the window and buffer handles.
+///
+/// Dropping the handle never closes the window, as the popup usually outlives the function
+/// which creates it. Call `close()` explicitly instead, it's safe to call more than once or
+/// after the window has been closed by the user.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PopupWindow {
+    window_handle: WinHandle,
+    buffer_handle: BufHandle,
+}
+
+impl PopupWindow {
+    pub fn window_handle(&self) -> WinHandle {
+        self.window_handle
+    }
+
+    pub fn buffer_handle(&self) -> BufHandle {
+        self.buffer_handle
+    }
+
+    pub fn window(&self) -> Window {
+        Window::from(self.window_handle)
+    }
+
+    pub fn buffer(&self) -> Buffer {
+        Buffer::from(self.buffer_handle)
+    }
+
+    ///
+    /// Whether the popup window is still open or not
+    ///
+    pub fn is_valid(&self) -> bool {
+        self.window().is_valid()
+    }
+
+    ///
+    /// Close the popup window if it's still open
+    ///
+    pub fn close(&self) {
+        if self.is_valid() {
+            let _ = self.window().close(true);
+        }
+    }
+
+    ///
+    /// Set the native float title (on the top border), it only works when the border is NOT
+    /// `WindowBorder::None`.
+    ///
+    pub fn set_title(&self, title: &str) -> Result<(), NvimError> {
+        let config = WindowConfig::builder()
+            .title(WindowTitle::SimpleString(title.into()))
+            .build();
+        self.window().set_config(&config)
+    }
+
+    ///
+    /// Resize the popup window, the position (top-left corner) doesn't change
+    ///
+    pub fn resize(&self, width: u32, height: u32) -> Result<(), NvimError> {
+        let config = WindowConfig::builder().width(width).height(height).build();
+        self.window().set_config(&config)
+    }
+
+    ///
+    /// Replace all lines of the popup buffer, it works even if the buffer is NOT `modifiable`.
+    ///
+    pub fn set_lines<Line, Lines>(&self, lines: Lines) -> Result<(), NvimError>
+    where
+        Line: Into<NvimString>,
+        Lines: IntoIterator<Item = Line>,
+    {
+        let mut buffer = self.buffer();
+        let buffer_opts = OptionOpts::builder().buffer(buffer.clone()).build();
+        let modifiable = get_option_value::<bool>("modifiable", &buffer_opts).unwrap_or(true);
+
+        let _ = set_option_value("modifiable", true, &buffer_opts);
+        let result = buffer.set_lines(.., true, lines);
+        let _ = set_option_value("modifiable", modifiable, &buffer_opts);
+
+        result
+    }
 }
 
 ///
@@ -23,21 +169,65 @@ pub struct ScreenSize {
 }
 
 ///
+/// Get back the screen size: the smallest grid of the attached UIs (Neovim sizes the grid to fit
+/// all of them), or the `columns`/`lines` options in the headless mode.
 ///
-///
-#[inline]
 pub fn get_screen_size() -> ScreenSize {
+    let uis = list_uis();
+    if let (Some(width), Some(height)) = (
+        uis.iter().map(|ui| ui.width).filter(|w| *w > 0).min(),
+        uis.iter().map(|ui| ui.height).filter(|h| *h > 0).min(),
+    ) {
+        return ScreenSize { width, height };
+    }
+
     let _opts = OptionOpts::builder().scope(OptionScope::Local).build();
     ScreenSize {
-        width: get_option_value::<u32>("columns", &_opts).unwrap(),
-        height: get_option_value::<u32>("lines", &_opts).unwrap(),
+        width: get_option_value::<u32>("columns", &_opts).unwrap_or(80),
+        height: get_option_value::<u32>("lines", &_opts).unwrap_or(24),
     }
 }
 
+///
+/// Clamp the given popup window size (without borders) into the `min_xxx`/`max_xxx` options,
+/// the max size is never bigger than the screen size (minus the borders).
+///
+pub fn clamp_popup_window_size(
+    opts: &PopupWindowOptions,
+    screen_size: &ScreenSize,
+    width: f32,
+    height: f32,
+    border_width: f32,
+    border_height: f32,
+) -> (f32, f32) {
+    let screen_max_width = (screen_size.width as f32 - border_width).max(1.0f32);
+    let screen_max_height = (screen_size.height as f32 - border_height).max(1.0f32);
+
+    let max_width = opts
+        .max_width
+        .map_or(screen_max_width, |w| (w as f32).min(screen_max_width));
+    let max_height = opts
+        .max_height
+        .map_or(screen_max_height, |h| (h as f32).min(screen_max_height));
+
+    //
+    // The `max_xxx` wins when the `min_xxx` is bigger than it
+    //
+    let min_width = opts.min_width.map_or(1.0f32, |w| (w as f32).min(max_width));
+    let min_height = opts
+        .min_height
+        .map_or(1.0f32, |h| (h as f32).min(max_height));
+
+    (
+        width.clamp(min_width, max_width),
+        height.clamp(min_height, max_height),
+    )
+}
+
 ///
 /// Create a popup window witht the given buffer
 ///
-pub fn create_popup_window(opts: &PopupWindowOptions) -> Option<i32> {
+pub fn create_popup_window(opts: &PopupWindowOptions) -> Option<PopupWindow> {
     #[cfg(feature = "enable_picker_debug_print")]
     const LOGGER_PREFIX: &'static str = "[ picker - create_popup_window ]";
 
@@ -117,14 +307,35 @@ pub fn create_popup_window(opts: &PopupWindowOptions) -> Option<i32> {
         }
     }
 
+    let border_size = if opts.border == WindowBorder::None {
+        0.0f32
+    } else {
+        2.0f32
+    };
+    (width, height) =
+        clamp_popup_window_size(opts, &screen_size, width, height, border_size, border_size);
+
     #[cfg(feature = "enable_picker_debug_print")]
     nvim::print!("\n>>> {LOGGER_PREFIX} width: {width}, height: {height}");
 
     // Center window in `editor` area by calculating the (left, top)
     let cal_width = if opts.border == WindowBorder::None { width } else { width + 2.0f32 };
     let cal_height = if opts.border == WindowBorder::None { height } else { height + 2.0f32 };
-    let cols = (((screen_size.width as f32 - cal_width) / 2f32).floor()) as u32;
-    let rows = (((screen_size.height as f32 - cal_height) / 2f32).floor()) as u32;
+    let mut cols = (((screen_size.width as f32 - cal_width) / 2f32).floor()) as u32;
+    let mut rows = (((screen_size.height as f32 - cal_height) / 2f32).floor()) as u32;
+
+    //
+    // Move the position to the anchor corner, the popup window stays centered
+    //
+    match opts.anchor {
+        WindowAnchor::NorthWest => {}
+        WindowAnchor::NorthEast => cols += cal_width as u32,
+        WindowAnchor::SouthWest => rows += cal_height as u32,
+        WindowAnchor::SouthEast => {
+            cols += cal_width as u32;
+            rows += cal_height as u32;
+        }
+    }
 
     // // Debug print
     // nvim::print!(
@@ -147,15 +358,37 @@ pub fn create_popup_window(opts: &PopupWindowOptions) -> Option<i32> {
     // );
 
     // Open popup window with current buffer
-    let enter_into_window = true;
-    let open_win_config = WindowConfig::builder()
+    let enter_into_window = opts.focusable;
+    let mut open_win_config_builder = WindowConfig::builder();
+    open_win_config_builder
         .relative(WindowRelativeTo::Editor)
+        .anchor(opts.anchor.clone())
         .width(width as u32)
         .height(height as u32)
         .row(rows)
         .col(cols)
         .border(opts.border.clone())
-        .build();
+        .focusable(opts.focusable);
+
+    if let Some(zindex) = opts.zindex {
+        open_win_config_builder.zindex(zindex);
+    }
+
+    if opts.border != WindowBorder::None {
+        if let Some(title) = &opts.title {
+            open_win_config_builder
+                .title(WindowTitle::SimpleString(title.as_str().into()))
+                .title_pos(opts.title_pos.clone());
+        }
+
+        if let Some(footer) = &opts.footer {
+            open_win_config_builder
+                .footer(WindowFooter::SimpleString(footer.as_str().into()))
+                .footer_pos(opts.footer_pos.clone());
+        }
+    }
+
+    let open_win_config = open_win_config_builder.build();
 
     let window_buffer = match opts.buffer {
         Some(handle) => &Buffer::from(handle),
@@ -174,22 +407,30 @@ pub fn create_popup_window(opts: &PopupWindowOptions) -> Option<i32> {
                 POPUP_WINDOW_AUTO_WIDTH_PADDING_EACH_SIDE.to_string(),
                 &popup_win_opts,
             );
-            return Some(win.handle());
+            return Some(PopupWindow {
+                window_handle: win.handle(),
+                buffer_handle: window_buffer.handle(),
+            });
         }
 
         Err(_) => return None,
     }
 }
 
+use crate::extended_api::list_uis;
+
 #[cfg(feature = "enable_picker_debug_print")]
 use nvim_oxi as nvim;
 
 use nvim_oxi::{
-    BufHandle,
+    BufHandle, String as NvimString, WinHandle,
     api::{
-        Buffer, get_option_value, open_win,
+        Buffer, Error as NvimError, Window, get_option_value, open_win,
         opts::{OptionOpts, OptionScope},
         set_option_value,
-        types::{WindowBorder, WindowConfig, WindowRelativeTo},
+        types::{
+            WindowAnchor, WindowBorder, WindowConfig, WindowFooter, WindowFooterPosition,
+            WindowRelativeTo, WindowTitle, WindowTitlePosition,
+        },
     },
 };
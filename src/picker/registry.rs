@@ -0,0 +1,192 @@
+///
+/// Produce the picker items when the source picker opens
+///
+pub type PickerSourceProducer = Arc<dyn Fn() -> Vec<PickerItem> + Send + Sync>;
+
+///
+/// Handle the selected item data (or the input text if it's not in the list)
+///
+pub type PickerSourceSelectHandler = Arc<dyn Fn(String) + Send + Sync>;
+
+///
+/// A named picker source contributed by any module, e.g. "dired_history", "project_commands".
+///
+#[derive(Clone)]
+pub struct PickerSource {
+    pub name: String,
+    pub title: String,
+    pub producer: PickerSourceProducer,
+    pub on_select: PickerSourceSelectHandler,
+}
+
+impl std::fmt::Debug for PickerSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PickerSource")
+            .field("name", &self.name)
+            .field("title", &self.title)
+            .finish()
+    }
+}
+
+///
+/// Private module-scope state: all registered sources (in the registration order)
+///
+static PICKER_REGISTRY: LazyLock<Mutex<Vec<PickerSource>>> =
+    LazyLock::new(|| Mutex::new(Vec::with_capacity(10)));
+
+///
+/// The source name of the meta-picker which lists all registered sources
+///
+const PICKER_REGISTRY_SOURCE_NAME: &'static str = "picker_registry";
+
+///
+/// Register a named source, the existing one with the same name will be replaced.
+///
+pub fn register_picker_source<P, S>(name: &str, title: &str, producer: P, on_select: S)
+where
+    P: Fn() -> Vec<PickerItem> + Send + Sync + 'static,
+    S: Fn(String) + Send + Sync + 'static,
+{
+    let source = PickerSource {
+        name: name.to_owned(),
+        title: title.to_owned(),
+        producer: Arc::new(producer),
+        on_select: Arc::new(on_select),
+    };
+
+    let mut locked_registry = PICKER_REGISTRY.lock().unwrap();
+    match locked_registry.iter_mut().find(|s| s.name == name) {
+        Some(existing_source) => *existing_source = source,
+        None => locked_registry.push(source),
+    }
+}
+
+///
+/// Get back the registered source by name
+///
+fn get_picker_source(name: &str) -> Option<PickerSource> {
+    PICKER_REGISTRY
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|s| s.name == name)
+        .cloned()
+}
+
+///
+/// Open the picker of the given registered source.
+///
+/// The source name is used as the `EditablePickerOptions.source`, so the items are sorted by
+/// frecency and the filter text is sticky between invocations.
+///
+pub fn open_registered_picker(name: &str) {
+    #[cfg(feature = "enable_picker_debug_print")]
+    const LOGGER_PREFIX: &'static str = "[ picker registry - open_registered_picker ]";
+
+    //
+    // Clone the source out, so the mutex lock is NOT held when running the producer or the
+    // `on_select` handler (they may register or open another source).
+    //
+    let Some(source) = get_picker_source(name) else {
+        #[cfg(feature = "enable_picker_debug_print")]
+        nvim::print!("\n>>> {LOGGER_PREFIX} source not found: {name}");
+
+        return;
+    };
+
+    let items = (source.producer)();
+    let on_select = source.on_select.clone();
+
+    let result = create_editable_picker_with_options(
+        &mut EditablePickerOptions {
+            title: source.title.clone(),
+            window_opts: PopupWindowOptions {
+                border: WindowBorder::Rounded,
+                window_width_ratio: None,
+                window_height_ratio: None,
+                auto_width: true,
+                auto_height: true,
+                buffer: None,
+                ..Default::default()
+            },
+            list: &items,
+            sort: PickerSortStrategy::Frecency,
+            source: Some(source.name.clone()),
+            sticky_filter: true,
+            show_footer: true,
+            initial_input: None,
+            validator: None,
+            on_delete: None,
+            on_edit: None,
+        },
+        move |selected_text: String| {
+            on_select(selected_text);
+        },
+    );
+
+    let _ = result;
+
+    #[cfg(feature = "enable_picker_debug_print")]
+    nvim::print!("\n>>> {LOGGER_PREFIX} result: {:?}", result);
+}
+
+///
+/// Open the meta-picker which lists all registered sources, select one to open its picker.
+///
+pub fn open_picker_registry() {
+    let items = PICKER_REGISTRY
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|s| PickerItem {
+            columns: vec![
+                PickerItemColumn::new(&s.name, Some("Question")),
+                PickerItemColumn::new(&s.title, Some("Comment")),
+            ],
+            icon: None,
+            data: s.name.clone(),
+        })
+        .collect::<Vec<PickerItem>>();
+
+    let _ = create_editable_picker_with_options(
+        &mut EditablePickerOptions {
+            title: "Pickers".to_string(),
+            window_opts: PopupWindowOptions {
+                border: WindowBorder::Rounded,
+                window_width_ratio: None,
+                window_height_ratio: None,
+                auto_width: true,
+                auto_height: true,
+                buffer: None,
+                ..Default::default()
+            },
+            list: &items,
+            sort: PickerSortStrategy::Frecency,
+            source: Some(PICKER_REGISTRY_SOURCE_NAME.to_string()),
+            sticky_filter: false,
+            show_footer: true,
+            initial_input: None,
+            validator: None,
+            on_delete: None,
+            on_edit: None,
+        },
+        |selected_name: String| {
+            //
+            // Open the selected source picker in the next event loop tick, as the meta-picker
+            // windows are still closing (and leaving `INSERT` mode) at this moment.
+            //
+            nvim::schedule(move |_| {
+                open_registered_picker(&selected_name);
+            });
+        },
+    );
+}
+
+use crate::picker::{
+    EditablePickerOptions, PickerItem, PickerItemColumn, PickerSortStrategy, PopupWindowOptions,
+    create_editable_picker_with_options,
+};
+
+use std::sync::{Arc, LazyLock, Mutex};
+
+use nvim_oxi::{self as nvim, api::types::WindowBorder};
@@ -0,0 +1,237 @@
+///
+/// Picker item sorting strategy, it applies to the items before rendering into the list buffer.
+///
+/// - `None`: Keep the given order.
+/// - `Alphabetical`: Sort by `PickerItem.data`.
+/// - `Recency`: The most recently selected items (of the picker source) on top.
+/// - `Frecency`: Frequently and recently selected items (of the picker source) on top.
+/// - `Custom`: Your own comparator.
+///
+/// `Recency` and `Frecency` only work when the picker options have the `source` name, as the
+/// selection history is tracked per source. The items that never be selected keep their original
+/// order after the selected ones (stable sort).
+///
+#[derive(Clone, Default)]
+pub enum PickerSortStrategy {
+    #[default]
+    None,
+    Alphabetical,
+    Recency,
+    Frecency,
+    Custom(Rc<dyn Fn(&PickerItem, &PickerItem) -> Ordering>),
+}
+
+impl std::fmt::Debug for PickerSortStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "None"),
+            Self::Alphabetical => write!(f, "Alphabetical"),
+            Self::Recency => write!(f, "Recency"),
+            Self::Frecency => write!(f, "Frecency"),
+            Self::Custom(_) => write!(f, "Custom(<comparator>)"),
+        }
+    }
+}
+
+///
+/// Selection record of a single item
+///
+#[derive(Debug, Clone, Copy)]
+struct FrecencyEntry {
+    count: u32,
+    last_used: u64, // Seconds since UNIX EPOCH
+}
+
+impl FrecencyEntry {
+    ///
+    /// Selection count weighted by how long ago the item was selected
+    ///
+    fn score(&self, now: u64) -> f64 {
+        const HOUR: u64 = 60 * 60;
+        const DAY: u64 = 24 * HOUR;
+        const WEEK: u64 = 7 * DAY;
+
+        let age = now.saturating_sub(self.last_used);
+        let weight = if age < HOUR {
+            4.0f64
+        } else if age < DAY {
+            2.0f64
+        } else if age < WEEK {
+            1.0f64
+        } else {
+            0.5f64
+        };
+
+        self.count as f64 * weight
+    }
+}
+
+#[derive(Debug, Default)]
+struct FrecencyState {
+    //
+    // source name <--> (item data <--> selection record)
+    //
+    sources: HashMap<String, HashMap<String, FrecencyEntry>>,
+}
+
+///
+/// Private module-scope state
+///
+static PICKER_FRECENCY_STATE: LazyLock<Mutex<FrecencyState>> =
+    LazyLock::new(|| Mutex::new(FrecencyState::default()));
+
+const FRECENCY_DATA_SUB_DIR: &'static str = "picker_frecency";
+
+#[inline]
+fn now_in_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+///
+/// Source name to filename, only keep the safe characters.
+///
+fn get_frecency_file(source: &str) -> Option<PathBuf> {
+    let safe_name = source
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    let mut file = get_data_dir(FRECENCY_DATA_SUB_DIR)?;
+    file.push(format!("{safe_name}.txt"));
+    Some(file)
+}
+
+///
+/// Load the source records from disk, each line looks like this:
+///
+/// {count}\t{last_used}\t{item_data}
+///
+fn load_frecency_entries(source: &str) -> HashMap<String, FrecencyEntry> {
+    let mut entries = HashMap::with_capacity(20);
+
+    let Some(file) = get_frecency_file(source) else {
+        return entries;
+    };
+
+    if let Ok(content) = std::fs::read_to_string(file) {
+        for line in content.lines() {
+            let mut fields = line.splitn(3, '\t');
+            if let (Some(count), Some(last_used), Some(data)) =
+                (fields.next(), fields.next(), fields.next())
+                && let (Ok(count), Ok(last_used)) = (count.parse::<u32>(), last_used.parse::<u64>())
+            {
+                entries.insert(data.to_owned(), FrecencyEntry { count, last_used });
+            }
+        }
+    }
+
+    entries
+}
+
+fn save_frecency_entries(source: &str, entries: &HashMap<String, FrecencyEntry>) {
+    let Some(file) = get_frecency_file(source) else {
+        return;
+    };
+
+    let content = entries
+        .iter()
+        .map(|(data, entry)| format!("{}\t{}\t{}", entry.count, entry.last_used, data))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let _ = std::fs::write(file, content);
+}
+
+///
+/// Run the given closure with the (lazy loaded) source records
+///
+fn with_source_entries<R>(
+    source: &str,
+    f: impl FnOnce(&mut HashMap<String, FrecencyEntry>) -> R,
+) -> R {
+    let mut locked_state = PICKER_FRECENCY_STATE.lock().unwrap();
+    let entries = locked_state
+        .sources
+        .entry(source.to_owned())
+        .or_insert_with(|| load_frecency_entries(source));
+
+    f(entries)
+}
+
+///
+/// Record the selected item data for the given source and save it to disk
+///
+pub fn record_picker_selection(source: &str, item_data: &str) {
+    if item_data.is_empty() {
+        return;
+    }
+
+    with_source_entries(source, |entries| {
+        let entry = entries
+            .entry(item_data.to_owned())
+            .or_insert(FrecencyEntry {
+                count: 0,
+                last_used: 0,
+            });
+        entry.count += 1;
+        entry.last_used = now_in_seconds();
+
+        save_frecency_entries(source, entries);
+    });
+}
+
+///
+/// Sort the items in place by the given strategy
+///
+pub fn sort_picker_items(
+    items: &mut Vec<PickerItem>,
+    strategy: &PickerSortStrategy,
+    source: Option<&str>,
+) {
+    match strategy {
+        PickerSortStrategy::None => {}
+        PickerSortStrategy::Alphabetical => items.sort_by(|a, b| a.data.cmp(&b.data)),
+        PickerSortStrategy::Custom(comparator) => items.sort_by(|a, b| comparator(a, b)),
+        PickerSortStrategy::Recency | PickerSortStrategy::Frecency => {
+            let Some(source) = source else {
+                return;
+            };
+
+            let now = now_in_seconds();
+            let is_recency = matches!(strategy, PickerSortStrategy::Recency);
+
+            with_source_entries(source, |entries| {
+                let rank = |item: &PickerItem| -> f64 {
+                    match entries.get(&item.data) {
+                        Some(entry) if is_recency => entry.last_used as f64,
+                        Some(entry) => entry.score(now),
+                        None => -1.0f64,
+                    }
+                };
+
+                // Higher rank on top
+                items.sort_by(|a, b| rank(b).partial_cmp(&rank(a)).unwrap_or(Ordering::Equal));
+            });
+        }
+    }
+}
+
+use crate::{picker::PickerItem, utils::get_data_dir};
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    path::PathBuf,
+    rc::Rc,
+    sync::{LazyLock, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
@@ -4,6 +4,28 @@
 //! If compilation fails, pressing ENTER on the error line will bring you to the
 //! source code.
 //!
+//! Each command can have its own working directory and environment variables (`<leader>pe`),
+//! or the inline `KEY=VAL` prefix, e.g. `RUST_LOG=debug cargo run`. The command is split into
+//! the shell-like tokens (quotes and escapes are supported), or it runs by `$SHELL -c` if it's
+//! set in `<leader>pe`, e.g. `cargo build && ./target/debug/app`. The command can also run in a
+//! `:terminal` job (set in `<leader>pe`) to keep the ANSI colors and the interaction.
+//!
+//! The first 9 commands of the project (in the adding order) are in the slots, `<leader>p1`..
+//! `<leader>p9` run them directly, and the picker shows the slot number of each command.
+//!
+//! `<leader>pw` toggles the watch mode of the project: the default command runs after saving any
+//! file under the project directory (debounced by `PROJECT_COMMAND_WATCH_DEBOUNCE`).
+//!
+//! `<leader>pt` runs the Rust/Zig test which encloses the cursor (`cargo test <name>` or
+//! `zig test <file> --test-filter <name>`), and the failures are set to the quickfix list.
+//!
+//! In the picker, `<c-d>` deletes the command under cursor and `<c-r>` edits it in the input
+//! line (`<CR>` to save).
+//!
+//! Every run has its own result buffer (`Command result #k: <cmd>`), the last
+//! `COMMAND_RESULT_HISTORY_SIZE` ones are kept, `<leader>ph` picks one to reopen its output. `a` in
+//! the result buffer shows the output with the ANSI colors (rendered by the embedded terminal).
+//!
 //! ```rust
 //!  let _ = set_keymap(
 //!      Mode::Normal,
@@ -27,6 +49,54 @@ struct ProjectCommandState {
     // The default selection index from the `cmd_list`
     //
     default_cmd_index: Option<usize>,
+
+    //
+    // cmd <--> run statistics
+    //
+    cmd_stats: HashMap<String, ProjectCommandStats>,
+
+    //
+    // The last executed command, it may not be the default one (`<leader>pl`)
+    //
+    last_cmd: Option<String>,
+
+    //
+    // cmd <--> working directory and environment variable overrides (`<leader>pe`)
+    //
+    cmd_envs: HashMap<String, ProjectCommandEnv>,
+}
+
+///
+/// The working directory and the environment variable overrides of the project command, the
+/// command inherits the Neovim ones if they're not set.
+///
+#[derive(Debug, Default, Clone)]
+struct ProjectCommandEnv {
+    //
+    // The relative path is resolved from the project directory
+    //
+    cwd: Option<String>,
+    env: Vec<(String, String)>,
+
+    //
+    // Run the command by `$SHELL -c`, so the shell syntax works, e.g. `cargo build && ./run`
+    //
+    use_shell: bool,
+
+    //
+    // Run the command in a `:terminal` job (ANSI colors and interactive, e.g. the test
+    // watchers) instead of the `nowrite` command buffer.
+    //
+    use_terminal: bool,
+}
+
+///
+/// The run statistics of the project command
+///
+#[derive(Debug, Default, Clone, Copy)]
+struct ProjectCommandStats {
+    run_count: u32,
+    last_run: u64, // Unix time in seconds
 }
 
 #[derive(Debug, Default)]
@@ -40,6 +110,33 @@ struct ModuleState {
     // custom highlight namespace, you don't need to destroy it manually, it's cheap, just an integer!!!
     //
     custom_highlight: Option<u32>,
+
+    //
+    // command buffer <--> the source locations found in its output
+    //
+    output_locations: HashMap<BufHandle, CommandOutputLocations>,
+
+    //
+    // The command result buffers (the oldest first), only the last
+    // `COMMAND_RESULT_HISTORY_SIZE` ones are kept.
+    //
+    command_results: VecDeque<CommandResult>,
+
+    //
+    // The `#k` of the next command result buffer
+    //
+    next_command_result_index: usize,
+
+    //
+    // The projects in the watch mode (`<leader>pw`), the default command runs after saving any
+    // file under the project directory.
+    //
+    watched_projects: HashSet<String>,
+
+    //
+    // The pending debounce timer of the watch mode
+    //
+    watch_timer: Option<i64>,
 }
 
 impl ModuleState {
@@ -47,10 +144,122 @@ impl ModuleState {
         Self {
             cmd_map: HashMap::with_capacity(10),
             custom_highlight: Some(create_namespace("project_command_highlight")),
+            output_locations: HashMap::with_capacity(COMMAND_RESULT_HISTORY_SIZE),
+            command_results: VecDeque::with_capacity(COMMAND_RESULT_HISTORY_SIZE),
+            next_command_result_index: 1,
+            watched_projects: HashSet::new(),
+            watch_timer: None,
         }
     }
 }
 
+///
+/// The command result buffer of the run
+///
+#[derive(Debug, Clone)]
+struct CommandResult {
+    index: usize, // The `#k` in the buffer name
+    buffer_handle: BufHandle,
+    cmd: String,
+    start_time: String,
+}
+
+///
+/// The source locations found in the command buffer output, they're pre-computed as the output
+/// arrives, so the jumps don't need to re-parse the line (and work while the command runs).
+///
+#[derive(Debug, Default)]
+struct CommandOutputLocations {
+    //
+    // Sorted by row
+    //
+    locations: Vec<CommandOutputLocation>,
+
+    //
+    // command buffer row (1-based) <--> index of `locations`
+    //
+    rows: HashMap<usize, usize>,
+}
+
+///
+/// The source location (`file:line:col`) found in the command output
+///
+#[derive(Debug, Clone)]
+struct CommandOutputLocation {
+    row: usize, // The command buffer row (1-based)
+    file: String,
+    line: usize,
+    col: usize,
+}
+
+///
+/// The namespace to underline the source locations in the command buffer
+///
+const OUTPUT_LOCATION_NAMESPACE: &'static str = "project_command_output_location";
+
+///
+/// How many command result buffers are kept, the oldest one is wiped out when running more.
+///
+const COMMAND_RESULT_HISTORY_SIZE: usize = 10;
+
+///
+/// The first commands of the project command list (in the adding order) are bound to
+/// `<leader>p1`..`<leader>p9`
+///
+const PROJECT_COMMAND_SLOT_COUNT: usize = 9;
+
+///
+/// The watch mode waits for this long (in milliseconds) after the last save before running the
+/// default command, so the rapid saves only trigger one build.
+///
+const PROJECT_COMMAND_WATCH_DEBOUNCE: u32 = 500;
+
+///
+/// The header lines before the command output: "Command: xxx", "-----", ""
+///
+const COMMAND_BUFFER_HEADER_LINE_COUNT: usize = 3;
+
+///
+/// The project marker file and its default commands, they're seeded into the command list
+/// when `ProjectCommandOptions.enable_project_type_commands` is `true`.
+///
+const PROJECT_TYPE_COMMANDS: [(&'static str, &'static [&'static str]); 8] = [
+    (
+        "Cargo.toml",
+        &["cargo build", "cargo test", "cargo clippy", "cargo run"],
+    ),
+    (
+        "package.json",
+        &["npm run build", "npm test", "npm run dev"],
+    ),
+    (
+        "build.zig",
+        &["zig build", "zig build test", "zig build run"],
+    ),
+    ("CMakeLists.txt", &["cmake -B build", "cmake --build build"]),
+    ("Makefile", &["make", "make clean"]),
+    ("go.mod", &["go build ./...", "go test ./..."]),
+    ("pyproject.toml", &["python -m pytest"]),
+    ("mix.exs", &["mix compile", "mix test"]),
+];
+
+///
+/// The sub directory of `get_data_dir()` to persist the project command state (JSON file per
+/// project)
+///
+const PROJECT_COMMAND_DATA_SUB_DIR: &'static str = "project_command";
+
+///
+/// The spinner frames in the first line of the command buffer while the command is running
+///
+const COMMAND_SPINNER_FRAMES: [&'static str; 10] =
+    ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+///
+/// The spinner refresh interval in milliseconds
+///
+const COMMAND_SPINNER_INTERVAL: u32 = 100;
+
 // impl Drop for ModuleState {
 //     fn drop(&mut self) {
 //         // const LOGGER_PREFIX: &'static str = "[ project_command - ModuleState.drop ]";
@@ -91,144 +300,101 @@ fn get_project_script_files(project_dir: &str) -> std::io::Result<Vec<String>> {
 }
 
 ///
-/// Get command buffer, create it if it's not exists yet
+/// Get back the default commands of the detected project types (by the marker files in the
+/// project root directory)
+///
+fn get_project_type_commands(project_dir: &str) -> Vec<String> {
+    let project_path = Path::new(project_dir);
+
+    PROJECT_TYPE_COMMANDS
+        .iter()
+        .filter(|(marker_file, _)| project_path.join(marker_file).exists())
+        .flat_map(|(_, commands)| commands.iter().map(|cmd| cmd.to_string()))
+        .collect()
+}
+
 ///
-/// If `open_on_most_left_win` is `true`, open source code on most left window.
+/// Create the command result buffer of the new run, it's named `Command result #k: <cmd>`. The
+/// oldest command result buffer is wiped out if there are more than
+/// `COMMAND_RESULT_HISTORY_SIZE`.
 ///
-fn get_command_buffer(open_on_most_left_win: bool) -> Option<Buffer> {
+fn create_command_buffer(
+    open_on_most_left_win: bool,
+    cmd: &str,
+    start_time: &str,
+) -> error::Result<Buffer> {
     #[cfg(feature = "enable_project_command_debug_print")]
-    const LOGGER_PREFIX: &'static str = "[ project_command - get_command_buffer ]";
+    const LOGGER_PREFIX: &'static str = "[ project_command - create_command_buffer ]";
 
     const COMMAND_BUFFER_NAME: &'static str = "Command result";
 
-    let mut command_buffer: Option<Buffer> = None;
-
-    //
-    // Find the existing command buffer
-    //
-    let buffer_list = list_bufs().collect::<Vec<Buffer>>();
-    for buffer in buffer_list.iter() {
-        let opts = OptionOpts::builder().buffer(buffer.clone()).build();
-        let buffer_name = buffer.get_name();
-        let buffer_type = get_option_value::<NvimString>("buftype", &opts);
-        let buffer_file_type = get_option_value::<NvimString>("filetype", &opts);
-        let buffer_is_hidden = get_option_value::<NvimString>("bufhidden", &opts);
-        let buffer_has_swapfile = get_option_value::<bool>("swapfile", &opts);
-
-        match (
-            buffer_name,
-            buffer_type,
-            buffer_file_type,
-            buffer_is_hidden,
-            buffer_has_swapfile,
-        ) {
-            (Ok(name), Ok(b_type), Ok(file_type), Ok(is_hidden), Ok(has_swapfile)) => {
-                #[cfg(feature = "enable_project_command_debug_print")]
-                nvim::print!(
-                    concat!(
-                        "{} {{",
-                        "\n\tbuffer_name: {:?}",
-                        "\n\tbuffer_type: {:?}",
-                        "\n\tbuffer_file_type: {:?}",
-                        "\n\tbuffer_has_swapfile: {:?}",
-                        "\n\tbuffer_name: {:?}",
-                        "\n}}"
-                    ),
-                    LOGGER_PREFIX,
-                    &name,
-                    &b_type,
-                    &file_type,
-                    &is_hidden,
-                    &has_swapfile,
-                );
-
-                if name.to_str().unwrap().ends_with(COMMAND_BUFFER_NAME)
-                    && b_type == "nowrite"
-                    && file_type == "fish"
-                    && is_hidden == "hide"
-                    && !has_swapfile
-                {
-                    command_buffer = Some(Buffer::from(buffer.handle()));
-
-                    #[cfg(feature = "enable_project_command_debug_print")]
-                    if let Some(temp_buffer) = &command_buffer {
-                        nvim::print!(
-                            "{LOGGER_PREFIX} Found command buffer: {}",
-                            temp_buffer.handle()
-                        );
-                    }
+    let mut new_buffer = create_buf(true, false).context("create the command buffer")?;
 
-                    return command_buffer;
-                }
-            }
-            _ => {}
-        }
-    }
+    #[cfg(feature = "enable_project_command_debug_print")]
+    nvim::print!(
+        "\n>>> {LOGGER_PREFIX} Created new command buffer: {}",
+        new_buffer.handle()
+    );
 
     //
-    // Create the new one if not exists yet
+    // Set related options
     //
-    if command_buffer.is_none()
-        && let Ok(mut new_buffer) = create_buf(true, false)
-    {
-        #[cfg(feature = "enable_project_command_debug_print")]
-        nvim::print!(
-            "\n>>> {LOGGER_PREFIX} Created new command buffer: {}",
-            new_buffer.handle()
-        );
-
-        //
-        // Set related options
-        //
-        let opts = OptionOpts::builder().buffer(new_buffer.clone()).build();
-
-        let _ = set_option_value("buftype", "nowrite", &opts);
-        let _ = set_option_value("bufhidden", "hide", &opts);
-        let _ = set_option_value("swapfile", false, &opts);
+    let opts = OptionOpts::builder().buffer(new_buffer.clone()).build();
 
+    set_option_value("buftype", "nowrite", &opts)
+        .and_then(|_| set_option_value("bufhidden", "hide", &opts))
+        .and_then(|_| set_option_value("swapfile", false, &opts))
         // Allow to modify before finishing the command
-        let _ = set_option_value("modifiable", true, &opts);
-
+        .and_then(|_| set_option_value("modifiable", true, &opts))
         // This enables the shell syntax color
-        let _ = set_option_value("filetype", "fish", &opts);
+        .and_then(|_| set_option_value("filetype", "fish", &opts))
+        .context("set the command buffer options")?;
 
-        // Set the name
-        let _ = new_buffer.set_name(COMMAND_BUFFER_NAME);
+    set_command_buffer_keybindings(&mut new_buffer, open_on_most_left_win);
 
-        //
-        // Setup local buffer keybindings
-        //
-        let _ = new_buffer.set_keymap(
-            Mode::Normal,
-            "<CR>",
-            "",
-            &SetKeymapOpts::builder()
-                .desc("Command result: open error/warning under cursor")
-                .callback(move |_| {
-                    // go_to_error_or_warning_under_cursor();
-                    ()
-                })
-                .build(),
-        );
+    //
+    // Record the run, and drop the oldest one if it's full
+    //
+    let dropped_results = {
+        let mut locked_state = MY_PROJECT_COMMAND_STATE.lock().unwrap();
+        let index = locked_state.next_command_result_index;
+        locked_state.next_command_result_index += 1;
 
-        command_buffer = Some(new_buffer);
+        // Set the name
+        let _ = new_buffer.set_name(format!("{COMMAND_BUFFER_NAME} #{index}: {cmd}"));
+
+        locked_state.command_results.push_back(CommandResult {
+            index,
+            buffer_handle: new_buffer.handle(),
+            cmd: cmd.to_owned(),
+            start_time: start_time.to_owned(),
+        });
+
+        let mut dropped_results = Vec::new();
+        while locked_state.command_results.len() > COMMAND_RESULT_HISTORY_SIZE {
+            if let Some(dropped) = locked_state.command_results.pop_front() {
+                locked_state.output_locations.remove(&dropped.buffer_handle);
+                dropped_results.push(dropped);
+            }
+        }
+        dropped_results
+    };
+
+    for dropped in dropped_results {
+        let _ = command(&format!("silent! bwipeout! {}", dropped.buffer_handle));
     }
 
-    command_buffer
+    Ok(new_buffer)
 }
 
 ///
-/// Execute the command and write the result back to the `command buffer`
+/// Show the command buffer in the output window of the current tab page (the right split
+/// window, created if it doesn't exist), so the other tab pages are not touched.
 ///
-fn execute_command(project_dir: &str, cmd: &str) {
-    #[cfg(feature = "enable_project_command_debug_print")]
-    const LOGGER_PREFIX: &'static str = "[ project_command - execute_command ]";
-
-    let mut command_buffer = get_command_buffer(true).unwrap();
-
-    let command_window = match get_split_window(true) {
+fn show_command_buffer(command_buffer: &Buffer) -> Option<Window> {
+    let command_window = match get_tab_output_window() {
         Some(mut split_win) => {
-            let _ = split_win.set_buf(&command_buffer);
+            let _ = split_win.set_buf(command_buffer);
             split_win
         }
         None => {
@@ -238,115 +404,1036 @@ fn execute_command(project_dir: &str, cmd: &str) {
             let command_window_config =
                 WindowConfig::builder().split(SplitDirection::Right).build();
 
-            open_win(&command_buffer, true, &command_window_config).unwrap()
+            open_win(command_buffer, true, &command_window_config).ok()?
         }
     };
 
-    //
-    // Set related options
-    //
-    let buffer_opts = OptionOpts::builder().buffer(command_buffer.clone()).build();
+    set_tab_output_window(&command_window);
+    Some(command_window)
+}
 
-    // Allow to modify before finishing the command
-    let _ = set_option_value("modifiable", true, &buffer_opts);
+///
+/// Setup the local buffer keybindings of the command buffer (or the terminal buffer)
+///
+fn set_command_buffer_keybindings(buffer: &mut Buffer, open_on_most_left_win: bool) {
+    let command_buffer_local_keybindings: Vec<(&str, &str, Box<dyn Fn()>)> = vec![
+        (
+            "<CR>",
+            "Command result: open error/warning under cursor",
+            Box::new(move || go_to_location_under_cursor(open_on_most_left_win)),
+        ),
+        (
+            "]e",
+            "Command result: go to the next error/warning line",
+            Box::new(|| move_to_location_line(true)),
+        ),
+        (
+            "[e",
+            "Command result: go to the previous error/warning line",
+            Box::new(|| move_to_location_line(false)),
+        ),
+        (
+            "a",
+            "Command result: show the output with the ANSI colors",
+            Box::new(show_ansi_output),
+        ),
+    ];
+
+    for bindings in command_buffer_local_keybindings {
+        let _ = buffer.set_keymap(
+            Mode::Normal,
+            bindings.0,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(bindings.1)
+                .callback(move |_| {
+                    bindings.2();
+                    ()
+                })
+                .build(),
+        );
+    }
+}
 
-    //
-    // Replace the command buffer content to running command and force to redraw
-    // to see the buffer change
-    //
-    let _ = command_buffer.set_lines(.., true, vec![format!("Running command: {cmd}")]);
-    let _ = command_window.call(|_| {
-        let redraw_command = "redraw";
-        let redraw_cmd_info = CmdInfos::builder().cmd(redraw_command).build();
-        let redraw_command_opts = CmdOpts::builder().output(false).build();
-        let _ = vim_cmd(&redraw_cmd_info, &redraw_command_opts);
-    });
+///
+/// Parse the source location from the given output line, it supports the following formats:
+///
+/// - `src/main.rs:10:5` (rustc, gcc, clang, etc.)
+/// - `src/main.rs:10`
+/// - `/path/to/main.odin(10:5)` (odin)
+///
+/// It returns `(start_col, end_col, file, line, col)`, the `start_col` and `end_col` are the
+/// byte range of the location text in the line. Only the existing file is treated as location,
+/// so the time string like "12:24" never matches.
+///
+fn parse_source_location(
+    project_dir: &str,
+    output_line: &str,
+) -> Option<(usize, usize, String, usize, usize)> {
+    let mut token_start = 0;
+    for token in output_line.split(' ') {
+        let start_col = token_start;
+        token_start += token.len() + 1;
+
+        let token = token.trim_end_matches(|c| c == ':' || c == ',');
+        if token.is_empty() {
+            continue;
+        }
 
-    //
-    // Create `cmd_list`: the first element is the biniary name, and then all args follow
-    //
-    let cmd_list = cmd.split(" ").collect::<Vec<&str>>();
-    match cmd_utils::execute_command(cmd_list) {
-        cmd_utils::ExecuteCommandResult::Success {
-            cmd_desc,
-            exit_code,
-            output,
-        } => {
-            let _ = cmd_desc;
-            let _ = exit_code;
-            let _ = output;
+        //
+        // Split into (file, "line:col")
+        //
+        let (file, position) = match token.find('(') {
+            Some(index) if token.ends_with(')') => {
+                (&token[..index], &token[index + 1..token.len() - 1])
+            }
+            _ => match token.find(':') {
+                Some(index) => (&token[..index], &token[index + 1..]),
+                None => continue,
+            },
+        };
 
-            #[cfg(feature = "enable_project_command_debug_print")]
-            nvim::print!("\n>>> {LOGGER_PREFIX} cmd output: {output}");
+        let mut position_parts = position.split(':');
+        let Some(Ok(line)) = position_parts.next().map(|v| v.parse::<usize>()) else {
+            continue;
+        };
+        let col = position_parts
+            .next()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(1);
+
+        let file_path = Path::new(project_dir).join(file);
+        if file.is_empty() || !file_path.is_file() {
+            continue;
+        }
 
-            //
-            // You have to split on `\n` before inserting to the command buffer!!!
-            //
-            let output_lines = output.split("\n").collect::<Vec<&str>>();
-            let mut result_list = Vec::with_capacity(output_lines.len() + 3);
-            let first_line = format!("Command: {cmd}");
-            result_list.push(first_line.as_str());
-            result_list.push("-------------------------------------------------------");
-            result_list.push("");
-            result_list.extend(output_lines);
-
-            let set_lines_result = command_buffer.set_lines(.., true, result_list);
-            let _ = set_lines_result;
-
-            // #[cfg(feature = "enable_project_command_debug_print")]
-            // nvim::print!("\n>>> {LOGGER_PREFIX} set_lines_result: {set_lines_result:?}");
+        if let Some(file_path_str) = file_path.to_str() {
+            return Some((
+                start_col,
+                start_col + token.len(),
+                file_path_str.to_owned(),
+                line,
+                col,
+            ));
         }
-        cmd_utils::ExecuteCommandResult::Fail { error_message } => {
-            let _ = command_buffer.set_lines(.., true, vec![error_message]);
+    }
+
+    None
+}
+
+///
+/// Find the source locations in the output lines, underline them and append them into the module
+/// state. `first_row` (1-based) is the command buffer row of the first output line, the lines
+/// are appended in order, so the locations stay sorted by row.
+///
+fn append_output_locations(
+    command_buffer: &mut Buffer,
+    project_dir: &str,
+    output_lines: &[String],
+    first_row: usize,
+) {
+    let namespace_id = create_namespace(OUTPUT_LOCATION_NAMESPACE);
+
+    let mut new_locations = Vec::new();
+    for (index, output_line) in output_lines.iter().enumerate() {
+        if let Some((start_col, end_col, file, line, col)) =
+            parse_source_location(project_dir, output_line)
+        {
+            let row = first_row + index;
+            let _ = command_buffer.set_extmark(
+                namespace_id,
+                row - 1,
+                start_col,
+                &SetExtmarkOpts::builder()
+                    .end_line(row - 1)
+                    .end_col(end_col)
+                    .hl_group("Underlined")
+                    .build(),
+            );
+
+            new_locations.push(CommandOutputLocation {
+                row,
+                file,
+                line,
+                col,
+            });
         }
     }
 
-    // Not allow to modify after finishing the command
-    let _ = set_option_value("modifiable", false, &buffer_opts);
+    if new_locations.is_empty() {
+        return;
+    }
+
+    let mut locked_state = MY_PROJECT_COMMAND_STATE.lock();
+    let module_state = locked_state.as_mut().unwrap();
+    let buffer_locations = module_state
+        .output_locations
+        .entry(command_buffer.handle())
+        .or_default();
+    for location in new_locations {
+        buffer_locations
+            .rows
+            .insert(location.row, buffer_locations.locations.len());
+        buffer_locations.locations.push(location);
+    }
 }
 
 ///
+/// Remove the source locations of the command buffer, e.g. before re-running the command in it.
+///
+fn clear_output_locations(command_buffer: &mut Buffer) {
+    let namespace_id = create_namespace(OUTPUT_LOCATION_NAMESPACE);
+    let _ = command_buffer.clear_namespace(namespace_id, ..);
+
+    let mut locked_state = MY_PROJECT_COMMAND_STATE.lock();
+    locked_state
+        .as_mut()
+        .unwrap()
+        .output_locations
+        .remove(&command_buffer.handle());
+}
+
 ///
+/// Find all source locations in the whole command output, underline them and save them into the
+/// module state.
 ///
-fn picker_selected_callback(project_dir: &str, selected_cmd: String) {
-    #[cfg(feature = "enable_project_command_debug_print")]
-    const LOGGER_PREFIX: &'static str = "[ project_command - picker_selected_callback ]";
+fn update_output_locations(
+    command_buffer: &mut Buffer,
+    project_dir: &str,
+    output: &str,
+    header_line_count: usize,
+) {
+    clear_output_locations(command_buffer);
+
+    let output_lines = output
+        .split("\n")
+        .map(|line| line.to_owned())
+        .collect::<Vec<String>>();
+    append_output_locations(
+        command_buffer,
+        project_dir,
+        &output_lines,
+        header_line_count + 1,
+    );
+}
+
+///
+/// <CR>: Open the source location of the current command buffer line.
+///
+fn go_to_location_under_cursor(open_on_most_left_win: bool) {
+    let command_window = Window::current();
+    let Ok((row, _)) = command_window.get_cursor() else {
+        return;
+    };
 
     //
-    // Lock the state and get back the cmd list
+    // Copy the location and release the mutex lock immediately.
     //
-    let mut locked_state = MY_PROJECT_COMMAND_STATE.lock();
-    let module_state = locked_state.as_mut().unwrap();
+    let location = {
+        let locked_state = MY_PROJECT_COMMAND_STATE.lock();
+        locked_state
+            .as_ref()
+            .unwrap()
+            .output_locations
+            .get(&Buffer::current().handle())
+            .and_then(|buffer_locations| {
+                buffer_locations
+                    .rows
+                    .get(&row)
+                    .and_then(|index| buffer_locations.locations.get(*index))
+            })
+            .cloned()
+    };
 
-    let get_state_result = module_state.cmd_map.get_mut(project_dir);
-    if get_state_result.is_none() {
+    let Some(location) = location else {
         return;
-    }
-
-    let state = get_state_result.unwrap();
-    let mut cmd = selected_cmd;
+    };
 
     //
-    // Pick the first line from the `state.cmd_list` if `cmd` is empty, otherwise, exit
+    // Open the source on the most left window (if it's not the command window), otherwise,
+    // open it in the command window.
     //
-    if cmd.len() == 0 {
-        if state.cmd_list.len() == 0 {
+    if open_on_most_left_win
+        && let Some(source_window) = get_tab_split_window(false)
+        && source_window.handle() != command_window.handle()
+    {
+        let _ = set_current_win(&source_window);
+    }
+
+    let edit_command = "edit";
+    let edit_cmd_info = CmdInfos::builder()
+        .cmd(edit_command)
+        .args([location.file.as_str()])
+        .build();
+    let edit_command_opts = CmdOpts::builder().output(false).build();
+    if vim_cmd(&edit_cmd_info, &edit_command_opts).is_ok() {
+        let _ = Window::current().set_cursor(location.line, location.col.saturating_sub(1));
+    }
+}
+
+///
+/// ]e/[e: Move the cursor to the next/previous source location line in the command buffer.
+///
+fn move_to_location_line(is_next: bool) {
+    let mut command_window = Window::current();
+    let Ok((row, _)) = command_window.get_cursor() else {
+        return;
+    };
+
+    let target_row = {
+        let locked_state = MY_PROJECT_COMMAND_STATE.lock();
+        let Some(buffer_locations) = locked_state
+            .as_ref()
+            .unwrap()
+            .output_locations
+            .get(&Buffer::current().handle())
+        else {
             return;
-        }
+        };
+        let locations = &buffer_locations.locations;
 
-        if let Some(cmd_index) = state.default_cmd_index {
-            cmd = state.cmd_list[cmd_index].clone();
+        //
+        // `locations` is sorted by row
+        //
+        if is_next {
+            let index = locations.partition_point(|location| location.row <= row);
+            locations.get(index).map(|location| location.row)
         } else {
-            cmd = state.cmd_list[0].clone();
+            let index = locations.partition_point(|location| location.row < row);
+            index
+                .checked_sub(1)
+                .and_then(|i| locations.get(i))
+                .map(|location| location.row)
         }
+    };
+
+    if let Some(target_row) = target_row {
+        let _ = command_window.set_cursor(target_row, 0);
     }
+}
 
-    #[cfg(feature = "enable_project_command_debug_print")]
-    nvim::print!("{LOGGER_PREFIX} called with '{cmd}'.");
+///
+/// `a`: Show the current command buffer lines in the embedded terminal popup, so the ANSI escape
+/// sequences of the command output (e.g. the colored test result) are rendered.
+///
+fn show_ansi_output() {
+    let Ok(lines) = Buffer::current().get_lines(.., false) else {
+        return;
+    };
+    let output = lines
+        .map(|line| line.to_string())
+        .collect::<Vec<String>>()
+        .join("\r\n");
+
+    let Ok(mut terminal_buffer) = create_buf(false, true) else {
+        return;
+    };
+    let _ = set_option_value(
+        "bufhidden",
+        "wipe",
+        &OptionOpts::builder()
+            .buffer(terminal_buffer.clone())
+            .build(),
+    );
+
+    let Some(channel_id) = open_terminal(&terminal_buffer) else {
+        return;
+    };
+
+    let Some(terminal_window) = create_popup_window(&PopupWindowOptions {
+        border: WindowBorder::Rounded,
+        window_width_ratio: Some(0.8),
+        window_height_ratio: Some(0.8),
+        auto_width: false,
+        auto_height: false,
+        buffer: Some(terminal_buffer.handle()),
+        title: Some(" Command output (ANSI) ".to_string()),
+        footer: Some(" q: close ".to_string()),
+        ..Default::default()
+    }) else {
+        return;
+    };
+
+    terminal_send(channel_id, &output);
+
+    let _ = terminal_buffer.set_keymap(
+        Mode::Normal,
+        "q",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("Command output (ANSI): Close")
+            .callback(move |_| {
+                terminal_window.close();
+                ()
+            })
+            .silent(true)
+            .build(),
+    );
+}
+
+///
+/// Execute the command and write the result back to the `command buffer`, the source locations
+/// of the failed command output are set to the quickfix list if `populate_quickfix` is `true`.
+///
+fn execute_command(
+    project_dir: &str,
+    cmd: &str,
+    cmd_env: &ProjectCommandEnv,
+    populate_quickfix: bool,
+) {
+    #[cfg(feature = "enable_project_command_debug_print")]
+    const LOGGER_PREFIX: &'static str = "[ project_command - execute_command ]";
+
+    if cmd_env.use_terminal {
+        execute_command_in_terminal(project_dir, cmd, cmd_env);
+        return;
+    }
+
+    let start_time = call_function::<_, String>("strftime", ("%H:%M:%S",)).unwrap_or_default();
+    let Some(mut command_buffer) = report(
+        "project_command",
+        create_command_buffer(true, cmd, &start_time),
+    ) else {
+        return;
+    };
+    let Some(command_window) = show_command_buffer(&command_buffer) else {
+        return;
+    };
+
+    //
+    // Set related options
+    //
+    let buffer_opts = OptionOpts::builder().buffer(command_buffer.clone()).build();
+
+    // Allow to modify before finishing the command
+    let _ = set_option_value("modifiable", true, &buffer_opts);
+
+    //
+    // Replace the command buffer content to running command and force to redraw
+    // to see the buffer change
+    //
+    let _ = command_buffer.set_lines(
+        ..,
+        true,
+        vec![
+            format!("{} Running command: {cmd}", COMMAND_SPINNER_FRAMES[0]),
+            "-------------------------------------------------------".to_string(),
+            String::new(),
+        ],
+    );
+    let _ = command_window.call(|_| {
+        let redraw_command = "redraw";
+        let redraw_cmd_info = CmdInfos::builder().cmd(redraw_command).build();
+        let redraw_command_opts = CmdOpts::builder().output(false).build();
+        let _ = vim_cmd(&redraw_cmd_info, &redraw_command_opts);
+    });
+
+    //
+    // The command may change the open files (e.g. `cargo fmt`), take the snapshot before
+    // running it.
+    //
+    let open_files_snapshot = take_open_files_snapshot();
+
+    let (cmd_list, cwd, env) = build_command_job(project_dir, cmd, cmd_env);
+
+    //
+    // Run it asynchronously, the stdout/stderr lines are appended to the command buffer as they
+    // arrive, and the exit code is appended when the command exits.
+    //
+    let started_at = Instant::now();
+    let output_lines = Rc::new(RefCell::new(Vec::<String>::new()));
+    let spinner_timer = start_command_spinner(&command_buffer, cmd, &start_time, started_at);
+
+    //
+    // The source locations are parsed chunk by chunk, so `<CR>`, `]e` and `[e` work while the
+    // output is still streaming.
+    //
+    clear_output_locations(&mut command_buffer);
+    let on_output = {
+        let output_lines = output_lines.clone();
+        let project_dir = project_dir.to_owned();
+        let mut command_buffer = command_buffer.clone();
+        move |lines: Vec<String>| {
+            if !command_buffer.is_valid() {
+                return;
+            }
+
+            let first_row = COMMAND_BUFFER_HEADER_LINE_COUNT + output_lines.borrow().len() + 1;
+            append_command_output_lines(&mut command_buffer, &lines);
+            append_output_locations(&mut command_buffer, &project_dir, &lines, first_row);
+            output_lines.borrow_mut().extend(lines);
+        }
+    };
+
+    let on_exit = {
+        let project_dir = project_dir.to_owned();
+        let cmd = cmd.to_owned();
+        let mut command_buffer = command_buffer.clone();
+        move |exit_code: i64| {
+            if let Some(timer_id) = spinner_timer {
+                let _ = call_function::<_, i64>("timer_stop", (timer_id,));
+            }
+
+            if !command_buffer.is_valid() {
+                return;
+            }
+
+            let output = output_lines.borrow().join("\n");
+
+            #[cfg(feature = "enable_project_command_debug_print")]
+            nvim::print!("\n>>> {LOGGER_PREFIX} exit code: {exit_code}, cmd output: {output}");
+
+            let elapsed = format_duration(started_at.elapsed().as_millis());
+            set_command_status_line(&mut command_buffer, &cmd, &start_time, &elapsed, exit_code);
+            append_command_output_lines(
+                &mut command_buffer,
+                &[String::new(), format!("[ Exit code: {exit_code} ]")],
+            );
+
+            if populate_quickfix {
+                set_failures_quickfix(&project_dir, &cmd, &output, exit_code);
+            }
+
+            // Not allow to modify after finishing the command
+            let buffer_opts = OptionOpts::builder().buffer(command_buffer.clone()).build();
+            let _ = set_option_value("modifiable", false, &buffer_opts);
+
+            check_changed_files(&open_files_snapshot, &project_dir);
+        }
+    };
+
+    if !run_async_in(cmd_list, cwd.as_deref(), &env, on_output, on_exit) {
+        if let Some(timer_id) = spinner_timer {
+            let _ = call_function::<_, i64>("timer_stop", (timer_id,));
+        }
+
+        let _ = command_buffer.set_lines(.., true, vec![format!("Failed to run command: {cmd}")]);
+        clear_output_locations(&mut command_buffer);
+
+        // Not allow to modify after finishing the command
+        let _ = set_option_value("modifiable", false, &buffer_opts);
+    }
+}
+
+///
+/// Set the source locations of the failed command output to the quickfix list and open it,
+/// the quickfix list is cleared if the command succeeded.
+///
+fn set_failures_quickfix(project_dir: &str, cmd: &str, output: &str, exit_code: i64) {
+    let items = if exit_code == 0 {
+        Array::new()
+    } else {
+        output
+            .split("\n")
+            .filter_map(|output_line| {
+                let (_, _, file, line, col) = parse_source_location(project_dir, output_line)?;
+                Some(Dictionary::from_iter([
+                    ("filename", Object::from(file)),
+                    ("lnum", Object::from(line as i64)),
+                    ("col", Object::from(col as i64)),
+                    ("text", Object::from(output_line.trim())),
+                ]))
+            })
+            .collect::<Array>()
+    };
+    let has_items = !items.is_empty();
+
+    let what = Dictionary::from_iter([
+        ("title", Object::from(format!("Failures: {cmd}"))),
+        ("items", Object::from(items)),
+    ]);
+    let _ = call_function::<_, i64>("setqflist", (Array::new(), " ", what));
+
+    if exit_code == 0 {
+        notify(
+            "project_command",
+            &format!("Passed: {cmd}"),
+            EchoLevel::Success,
+        );
+    } else if has_items {
+        let _ = command("copen | wincmd p");
+    } else {
+        notify(
+            "project_command",
+            &format!("Failed (exit code: {exit_code}), no failure location found."),
+            EchoLevel::Error,
+        );
+    }
+}
+
+///
+/// Split the command into the shell-like tokens: the whitespace separates the tokens, except
+/// in the single quotes (literal) or the double quotes (`\"` and `\\` are escaped), and the
+/// backslash escapes the next character outside the quotes, e.g.
+///
+/// `rg -g '*.rs' "fn main" my\ dir` -> `[rg, -g, *.rs, fn main, my dir]`
+///
+fn tokenize_command(cmd: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut has_token = false;
+    let mut chars = cmd.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                has_token = true;
+                token.extend(chars.by_ref().take_while(|c| *c != '\''));
+            }
+            '"' => {
+                has_token = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some(escaped @ ('"' | '\\')) => token.push(escaped),
+                            Some(other) => {
+                                token.push('\\');
+                                token.push(other);
+                            }
+                            None => token.push('\\'),
+                        },
+                        _ => token.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                has_token = true;
+                if let Some(escaped) = chars.next() {
+                    token.push(escaped);
+                }
+            }
+            c if c.is_whitespace() => {
+                if has_token {
+                    tokens.push(std::mem::take(&mut token));
+                    has_token = false;
+                }
+            }
+            _ => {
+                has_token = true;
+                token.push(c);
+            }
+        }
+    }
+
+    if has_token {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+///
+/// Split the leading `KEY=VAL` tokens from the command tokens, e.g.
+/// `RUST_LOG=debug PORT=8080 cargo run` -> (`[(RUST_LOG, debug), (PORT, 8080)]`,
+/// `[cargo, run]`).
+///
+fn split_inline_env(tokens: Vec<String>) -> (Vec<(String, String)>, Vec<String>) {
+    let mut env = Vec::new();
+    let mut tokens = tokens.into_iter().peekable();
+
+    //
+    // Keep the last token as the program, even if it looks like `KEY=VAL`
+    //
+    while let Some(token) = tokens.next_if(|token| token.contains('=')) {
+        match parse_env_pair(&token) {
+            Some(pair) if tokens.peek().is_some() => env.push(pair),
+            _ => return (env, std::iter::once(token).chain(tokens).collect()),
+        }
+    }
+
+    (env, tokens.collect())
+}
+
+///
+/// Whether the command has the unclosed single or double quote
+///
+fn has_unclosed_quote(cmd: &str) -> bool {
+    let mut quote: Option<char> = None;
+    let mut chars = cmd.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None | Some('"'), '\\') => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+
+    quote.is_some()
+}
+
+///
+/// Validate the project command picker input, reject the obviously invalid commands instead of
+/// running them. The empty input means the default command, and the stored commands always
+/// pass (they may run by `$SHELL -c`).
+///
+fn validate_project_command(input: &str) -> Result<(), String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(());
+    }
+
+    let is_stored_cmd = MY_PROJECT_COMMAND_STATE
+        .lock()
+        .unwrap()
+        .cmd_map
+        .get(&get_project_dir())
+        .is_some_and(|state| state.cmd_list.iter().any(|cmd| cmd == input));
+    if is_stored_cmd {
+        return Ok(());
+    }
+
+    if has_unclosed_quote(input) {
+        return Err("Unclosed quote in the command".to_string());
+    }
+
+    let (_, cmd_list) = split_inline_env(tokenize_command(input));
+    let Some(program) = cmd_list.first() else {
+        return Err("Missing the program to run".to_string());
+    };
+
+    if parse_env_pair(program).is_some() {
+        return Err("Missing the program after the `KEY=VAL` environment variables".to_string());
+    }
+
+    if call_function::<_, i64>("executable", (program.as_str(),)).unwrap_or(0) != 1 {
+        return Err(format!("'{program}' is not an executable"));
+    }
+
+    Ok(())
+}
+
+///
+/// Parse the `KEY=VAL` pair, the key should be a valid environment variable name.
+///
+fn parse_env_pair(token: &str) -> Option<(String, String)> {
+    let (key, value) = token.split_once('=')?;
+    let is_valid_key = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if !is_valid_key {
+        return None;
+    }
+
+    Some((key.to_owned(), value.to_owned()))
+}
+
+///
+/// Build the job of the command: `(cmd_list, cwd, env)`.
+///
+fn build_command_job(
+    project_dir: &str,
+    cmd: &str,
+    cmd_env: &ProjectCommandEnv,
+) -> (Vec<String>, Option<String>, Vec<(String, String)>) {
+    //
+    // Create `cmd_list`: the first element is the biniary name, and then all args follow. The
+    // inline `KEY=VAL` prefix (e.g. `RUST_LOG=debug cargo run`) overrides the stored environment
+    // variables of the command, the shell (`use_shell`) handles that prefix by itself.
+    //
+    let (inline_env, cmd_list) = if cmd_env.use_shell {
+        let shell = std::env::var("SHELL").unwrap_or("sh".to_string());
+        (Vec::new(), vec![shell, "-c".to_string(), cmd.to_owned()])
+    } else {
+        split_inline_env(tokenize_command(cmd))
+    };
+
+    let mut env = cmd_env.env.clone();
+    for (key, value) in inline_env {
+        env.retain(|(existing_key, _)| *existing_key != key);
+        env.push((key, value));
+    }
+
+    let cwd = cmd_env.cwd.as_ref().map(|cwd| {
+        let cwd_path = Path::new(cwd);
+        if cwd_path.is_absolute() {
+            cwd.to_owned()
+        } else {
+            Path::new(project_dir)
+                .join(cwd_path)
+                .to_string_lossy()
+                .to_string()
+        }
+    });
+
+    (cmd_list, cwd, env)
+}
+
+///
+/// Execute the command in a `:terminal` job in the right split window, the ANSI colors and
+/// the interaction are preserved. The terminal lines are parsed for the source locations after
+/// the command exits, so `<CR>`, `]e` and `[e` work the same with the command buffer.
+///
+fn execute_command_in_terminal(project_dir: &str, cmd: &str, cmd_env: &ProjectCommandEnv) {
+    let (cmd_list, cwd, env) = build_command_job(project_dir, cmd, cmd_env);
+    if cmd_list.is_empty() {
+        return;
+    }
+
+    //
+    // The terminal job needs a fresh buffer, the previous terminal buffer is wiped out when
+    // it's replaced in the window.
+    //
+    let Ok(mut terminal_buffer) = create_buf(false, true) else {
+        return;
+    };
+    let buffer_opts = OptionOpts::builder()
+        .buffer(terminal_buffer.clone())
+        .build();
+    let _ = set_option_value("bufhidden", "wipe", &buffer_opts);
+    set_command_buffer_keybindings(&mut terminal_buffer, true);
+
+    let Some(terminal_window) = show_command_buffer(&terminal_buffer) else {
+        return;
+    };
+
+    //
+    // `jobstart()` with `term` runs in the current buffer
+    //
+    let _ = set_current_win(&terminal_window);
+
+    let open_files_snapshot = take_open_files_snapshot();
+    let started_at = Instant::now();
+
+    let on_exit = {
+        let project_dir = project_dir.to_owned();
+        let cmd = cmd.to_owned();
+        let mut terminal_buffer = terminal_buffer.clone();
+        Function::<(i64, i64, NvimString), ()>::from_fn_once(move |(_, exit_code, _)| {
+            if !terminal_buffer.is_valid() {
+                return;
+            }
+
+            //
+            // The terminal buffer has the rendered lines (without the ANSI escape sequences),
+            // the trailing empty lines are the unused terminal rows.
+            //
+            let mut output_lines = terminal_buffer
+                .get_lines(.., false)
+                .map(|lines| lines.map(|line| line.to_string()).collect::<Vec<String>>())
+                .unwrap_or_default();
+            while output_lines
+                .last()
+                .is_some_and(|line| line.trim().is_empty())
+            {
+                output_lines.pop();
+            }
+
+            update_output_locations(
+                &mut terminal_buffer,
+                &project_dir,
+                &output_lines.join("\n"),
+                0,
+            );
+            check_changed_files(&open_files_snapshot, &project_dir);
+
+            let elapsed = format_duration(started_at.elapsed().as_millis());
+            notify(
+                "project_command",
+                &format!("'{cmd}' finished in {elapsed}, exit code: {exit_code}"),
+                if exit_code == 0 {
+                    EchoLevel::Success
+                } else {
+                    EchoLevel::Error
+                },
+            );
+        })
+    };
+
+    let mut job_opts = vec![
+        ("term", Object::from(true)),
+        ("on_exit", Object::from(on_exit)),
+    ];
+    if let Some(cwd) = cwd {
+        job_opts.push(("cwd", Object::from(cwd)));
+    }
+    if !env.is_empty() {
+        job_opts.push((
+            "env",
+            Object::from(Dictionary::from_iter(
+                env.iter()
+                    .map(|(key, value)| (key.as_str(), Object::from(value.as_str()))),
+            )),
+        ));
+    }
+
+    let job_id = call_function::<_, i64>(
+        "jobstart",
+        (Array::from_iter(cmd_list), Dictionary::from_iter(job_opts)),
+    )
+    .unwrap_or(0);
+    if job_id <= 0 {
+        notify(
+            "project_command",
+            &format!("Failed to run command: {cmd}"),
+            EchoLevel::Error,
+        );
+    }
+}
+
+///
+/// Replace the first line of the command buffer with the status line, e.g.
+/// `[ PASS ] Command: cargo build  (started at 10:20:30, finished in 1.2s, exit code: 0)`, the
+/// `PASS`/`FAIL` status is colored by the exit code.
+///
+fn set_command_status_line(
+    command_buffer: &mut Buffer,
+    cmd: &str,
+    start_time: &str,
+    elapsed: &str,
+    exit_code: i64,
+) {
+    let (status, status_hl_group) = if exit_code == 0 {
+        ("[ PASS ]", "DiagnosticOk")
+    } else {
+        ("[ FAIL ]", "DiagnosticError")
+    };
+
+    let _ = command_buffer.set_lines(
+        0..1,
+        true,
+        vec![format!(
+            "{status} Command: {cmd}  (started at {start_time}, finished in {elapsed}, exit code: {exit_code})"
+        )],
+    );
+
+    let Some(custom_highlight_id) = MY_PROJECT_COMMAND_STATE.lock().unwrap().custom_highlight
+    else {
+        return;
+    };
+
+    let _ = command_buffer.clear_namespace(custom_highlight_id, 0..1);
+    let _ = command_buffer.set_extmark(
+        custom_highlight_id,
+        0,
+        0,
+        &SetExtmarkOpts::builder()
+            .end_line(0)
+            .end_col(status.len())
+            .hl_group(status_hl_group)
+            .build(),
+    );
+}
+
+///
+/// Start the timer to animate the spinner in the first line of the command buffer, return the
+/// timer ID.
+///
+fn start_command_spinner(
+    command_buffer: &Buffer,
+    cmd: &str,
+    start_time: &str,
+    started_at: Instant,
+) -> Option<i64> {
+    let mut command_buffer = command_buffer.clone();
+    let cmd = cmd.to_owned();
+    let start_time = start_time.to_owned();
+    let mut frame_index = 0usize;
+
+    let spinner_callback = Function::<(i64,), ()>::from_fn_mut(move |(timer_id,)| {
+        if !command_buffer.is_valid() {
+            let _ = call_function::<_, i64>("timer_stop", (timer_id,));
+            return;
+        }
+
+        frame_index = (frame_index + 1) % COMMAND_SPINNER_FRAMES.len();
+        let elapsed = format_duration(started_at.elapsed().as_millis());
+        let _ = command_buffer.set_lines(
+            0..1,
+            true,
+            vec![format!(
+                "{} Running command: {cmd}  (started at {start_time}, {elapsed})",
+                COMMAND_SPINNER_FRAMES[frame_index]
+            )],
+        );
+    });
+
+    call_function::<_, i64>(
+        "timer_start",
+        (
+            COMMAND_SPINNER_INTERVAL,
+            spinner_callback,
+            Dictionary::from_iter([("repeat", -1)]),
+        ),
+    )
+    .ok()
+}
+
+///
+/// Append the lines to the end of the command buffer, the windows which cursor is at the last
+/// line keep following the output (auto-scrolling).
+///
+fn append_command_output_lines(command_buffer: &mut Buffer, lines: &[String]) {
+    let Ok(line_count) = command_buffer.line_count() else {
+        return;
+    };
+
+    let following_windows = list_wins()
+        .filter(|win| win.get_buf().is_ok_and(|buffer| buffer == *command_buffer))
+        .filter(|win| win.get_cursor().is_ok_and(|(row, _)| row == line_count))
+        .collect::<Vec<Window>>();
+
+    let _ = command_buffer.set_lines(
+        line_count..line_count,
+        true,
+        lines.iter().map(|v| v.as_str()),
+    );
+
+    let new_line_count = line_count + lines.len();
+    for mut win in following_windows {
+        let _ = win.set_cursor(new_line_count, 0);
+    }
+}
+
+///
+///
+///
+fn picker_selected_callback(project_dir: &str, selected_cmd: String) {
+    #[cfg(feature = "enable_project_command_debug_print")]
+    const LOGGER_PREFIX: &'static str = "[ project_command - picker_selected_callback ]";
+
+    //
+    // Lock the state and get back the cmd list
+    //
+    let mut locked_state = MY_PROJECT_COMMAND_STATE.lock();
+    let module_state = locked_state.as_mut().unwrap();
+
+    let get_state_result = module_state.cmd_map.get_mut(project_dir);
+    if get_state_result.is_none() {
+        return;
+    }
+
+    let state = get_state_result.unwrap();
+    let mut cmd = selected_cmd;
+
+    //
+    // Pick the first line from the `state.cmd_list` if `cmd` is empty, otherwise, exit
+    //
+    if cmd.len() == 0 {
+        if state.cmd_list.len() == 0 {
+            return;
+        }
+
+        if let Some(cmd_index) = state.default_cmd_index {
+            cmd = state.cmd_list[cmd_index].clone();
+        } else {
+            cmd = state.cmd_list[0].clone();
+        }
+    }
+
+    #[cfg(feature = "enable_project_command_debug_print")]
+    nvim::print!("{LOGGER_PREFIX} called with '{cmd}'.");
 
     //
-    // Update the cmd list if the cmd doesn't exists
-    //
+    // Update the cmd list if the cmd doesn't exists
+    //
     if state
         .cmd_list
         .iter()
@@ -360,174 +1447,1079 @@ fn picker_selected_callback(project_dir: &str, selected_cmd: String) {
         //
         // Remove the empty placeholder line (used for rendering the empty list window) if exists.
         //
-        state.cmd_list.retain(|line| !line.is_empty());
+        state.cmd_list.retain(|line| !line.is_empty());
+    }
+
+    //
+    // Update the `default_cmd_index`
+    //
+    for index in 0..state.cmd_list.len() {
+        if state.cmd_list[index].cmp(&cmd) == core::cmp::Ordering::Equal {
+            state.default_cmd_index = Some(index);
+
+            #[cfg(feature = "enable_project_command_debug_print")]
+            nvim::print!("{LOGGER_PREFIX} update 'default_cmd_index' to: {index}");
+
+            break;
+        }
+    }
+
+    record_command_run(project_dir, state, &cmd);
+    let cmd_env = state.cmd_envs.get(&cmd).cloned().unwrap_or_default();
+
+    //
+    // Release the mutex lock before running the command, as it updates the module state.
+    //
+    drop(locked_state);
+    execute_command(project_dir, &cmd, &cmd_env, false);
+}
+
+///
+/// Options
+///
+struct ProjectCommandOptions {
+    enable_script_files: bool,
+
+    //
+    // Seed the default commands of the detected project type, see `PROJECT_TYPE_COMMANDS`
+    //
+    enable_project_type_commands: bool,
+    open_source_on_left_split_win: bool,
+
+    //
+    // Pre-fill the picker input, e.g. the executable name to compose a new command
+    //
+    initial_input: Option<String>,
+}
+
+///
+/// Get back the current project directory
+///
+pub fn get_project_dir() -> String {
+    //
+    // TODO:
+    //
+    // 'project_dir' should be the '.git' folder searching start from the current opened file!!!
+    // 'project_dir' should be the '.git' folder searching start from the current opened file!!!
+    // 'project_dir' should be the '.git' folder searching start from the current opened file!!!
+    //
+    match std::env::var("PWD") {
+        Ok(current_pwd) => current_pwd,
+        Err(_) => "".to_string(),
+    }
+}
+
+///
+/// Update the run statistics and the last executed command, and then persist the state.
+///
+fn record_command_run(project_dir: &str, state: &mut ProjectCommandState, cmd: &str) {
+    let stats = state.cmd_stats.entry(cmd.to_owned()).or_default();
+    stats.run_count += 1;
+    stats.last_run = now_in_seconds();
+    state.last_cmd = Some(cmd.to_owned());
+
+    report(
+        "project_command",
+        save_project_command_state(project_dir, state),
+    );
+}
+
+#[inline]
+fn now_in_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+///
+/// Get back the state file of the given project directory, e.g.
+/// `/home/wison/my-project` -> `project_command/%home%wison%my-project.json`
+///
+fn get_project_command_state_file(project_dir: &str) -> Option<PathBuf> {
+    let mut state_file = get_data_dir(PROJECT_COMMAND_DATA_SUB_DIR)?;
+    state_file.push(format!(
+        "{}.json",
+        project_dir.replace(std::path::MAIN_SEPARATOR, "%")
+    ));
+    Some(state_file)
+}
+
+///
+/// Load the persisted project command state, the JSON file looks like this:
+///
+/// ```json
+/// {
+///   "project_dir": "/home/wison/my-project",
+///   "default_cmd_index": 0,
+///   "commands": [
+///     {
+///       "cmd": "cargo build",
+///       "run_count": 3,
+///       "last_run": 1700000000,
+///       "cwd": "backend",
+///       "env": { "RUST_LOG": "debug" },
+///       "shell": false,
+///       "terminal": false
+///     }
+///   ],
+///   "last_cmd": "cargo build"
+/// }
+/// ```
+///
+/// Return `Ok(None)` if the project doesn't have the persisted state yet.
+///
+fn load_project_command_state(project_dir: &str) -> error::Result<Option<ProjectCommandState>> {
+    let Some(state_file) = get_project_command_state_file(project_dir) else {
+        return Ok(None);
+    };
+    if !state_file.exists() {
+        return Ok(None);
+    }
+
+    let state_file_desc = format!("the project command state '{}'", state_file.display());
+    let content =
+        std::fs::read_to_string(&state_file).context(&format!("read {state_file_desc}"))?;
+    let state_dict = call_function::<_, Dictionary>("json_decode", (content,))
+        .map_err(|error| Error::parse(&state_file_desc, &error.to_string()))?;
+
+    let commands = state_dict
+        .get("commands")
+        .and_then(|commands| Array::from_object(commands.clone()).ok())
+        .ok_or_else(|| Error::parse(&state_file_desc, "no 'commands' list"))?;
+
+    let get_integer = |dict: &Dictionary, key: &str| {
+        dict.get(key)
+            .and_then(|value| i64::from_object(value.clone()).ok())
+            .unwrap_or(0)
+    };
+
+    let mut cmd_list = Vec::with_capacity(commands.len());
+    let mut cmd_stats = HashMap::with_capacity(commands.len());
+    let mut cmd_envs = HashMap::new();
+    for command_dict in commands
+        .into_iter()
+        .filter_map(|command| Dictionary::from_object(command).ok())
+    {
+        let Some(cmd) = command_dict
+            .get("cmd")
+            .and_then(|cmd| NvimString::from_object(cmd.clone()).ok())
+            .map(|cmd| cmd.to_string())
+            .filter(|cmd| !cmd.is_empty())
+        else {
+            continue;
+        };
+
+        cmd_stats.insert(
+            cmd.clone(),
+            ProjectCommandStats {
+                run_count: get_integer(&command_dict, "run_count") as u32,
+                last_run: get_integer(&command_dict, "last_run") as u64,
+            },
+        );
+
+        let cwd = command_dict
+            .get("cwd")
+            .and_then(|cwd| NvimString::from_object(cwd.clone()).ok())
+            .map(|cwd| cwd.to_string())
+            .filter(|cwd| !cwd.is_empty());
+        let env = command_dict
+            .get("env")
+            .and_then(|env| Dictionary::from_object(env.clone()).ok())
+            .map(|env| {
+                env.into_iter()
+                    .filter_map(|(key, value)| {
+                        NvimString::from_object(value)
+                            .ok()
+                            .map(|value| (key.to_string(), value.to_string()))
+                    })
+                    .collect::<Vec<(String, String)>>()
+            })
+            .unwrap_or_default();
+
+        let get_bool = |key: &str| {
+            command_dict
+                .get(key)
+                .and_then(|value| bool::from_object(value.clone()).ok())
+                .unwrap_or(false)
+        };
+        let (use_shell, use_terminal) = (get_bool("shell"), get_bool("terminal"));
+
+        if cwd.is_some() || !env.is_empty() || use_shell || use_terminal {
+            cmd_envs.insert(
+                cmd.clone(),
+                ProjectCommandEnv {
+                    cwd,
+                    env,
+                    use_shell,
+                    use_terminal,
+                },
+            );
+        }
+
+        cmd_list.push(cmd);
+    }
+
+    let default_cmd_index = state_dict
+        .get("default_cmd_index")
+        .and_then(|index| i64::from_object(index.clone()).ok())
+        .filter(|index| *index >= 0 && (*index as usize) < cmd_list.len())
+        .map(|index| index as usize);
+
+    let last_cmd = state_dict
+        .get("last_cmd")
+        .and_then(|cmd| NvimString::from_object(cmd.clone()).ok())
+        .map(|cmd| cmd.to_string())
+        .filter(|cmd| !cmd.is_empty());
+
+    Ok(Some(ProjectCommandState {
+        cmd_list,
+        default_cmd_index,
+        cmd_stats,
+        last_cmd,
+        cmd_envs,
+    }))
+}
+
+///
+/// Save the project command state to the JSON file, see `load_project_command_state()`.
+///
+fn save_project_command_state(project_dir: &str, state: &ProjectCommandState) -> error::Result<()> {
+    //
+    // Nothing to persist without the data directory
+    //
+    let Some(state_file) = get_project_command_state_file(project_dir) else {
+        return Ok(());
+    };
+
+    let commands = state.cmd_list.iter().map(|cmd| {
+        let stats = state.cmd_stats.get(cmd).copied().unwrap_or_default();
+        let cmd_env = state.cmd_envs.get(cmd).cloned().unwrap_or_default();
+        Object::from(Dictionary::from_iter([
+            ("cmd", Object::from(cmd.as_str())),
+            ("run_count", Object::from(stats.run_count as i64)),
+            ("last_run", Object::from(stats.last_run as i64)),
+            (
+                "cwd",
+                Object::from(cmd_env.cwd.as_deref().unwrap_or_default()),
+            ),
+            (
+                "env",
+                Object::from(Dictionary::from_iter(
+                    cmd_env
+                        .env
+                        .iter()
+                        .map(|(key, value)| (key.as_str(), Object::from(value.as_str()))),
+                )),
+            ),
+            ("shell", Object::from(cmd_env.use_shell)),
+            ("terminal", Object::from(cmd_env.use_terminal)),
+        ]))
+    });
+
+    let state_dict = Dictionary::from_iter([
+        ("project_dir", Object::from(project_dir)),
+        (
+            "default_cmd_index",
+            Object::from(state.default_cmd_index.map_or(-1, |index| index as i64)),
+        ),
+        ("commands", Object::from(Array::from_iter(commands))),
+        (
+            "last_cmd",
+            Object::from(state.last_cmd.as_deref().unwrap_or_default()),
+        ),
+    ]);
+
+    let content = call_function::<_, String>("json_encode", (state_dict,))
+        .context("encode the project command state")?;
+    std::fs::write(&state_file, content).context(&format!(
+        "save the project command state to '{}'",
+        state_file.display()
+    ))
+}
+
+///
+/// Init the `project_dir` cmd list when it doesn't exists, the persisted state (if any) is
+/// loaded first.
+///
+fn init_project_cmd_list(project_dir: &str, options: &ProjectCommandOptions) {
+    #[cfg(feature = "enable_project_command_debug_print")]
+    const LOGGER_PREFIX: &'static str = "[ project_command - init_project_cmd_list ]";
+
+    let mut locked_state = MY_PROJECT_COMMAND_STATE.lock();
+    let module_state = locked_state.as_mut().unwrap();
+    //
+    // Only init the cmd list when it doesn't exists.
+    //
+    if module_state.cmd_map.get(project_dir).is_none() {
+        if let Some(persisted_state) =
+            report("project_command", load_project_command_state(project_dir)).flatten()
+        {
+            module_state
+                .cmd_map
+                .insert(project_dir.to_string(), persisted_state);
+        } else {
+            let mut cmd_list = Vec::with_capacity(10);
+
+            if options.enable_project_type_commands {
+                cmd_list.extend(get_project_type_commands(project_dir));
+            }
+
+            if options.enable_script_files
+                && let Ok(script_file_list) = get_project_script_files(project_dir)
+            {
+                cmd_list.extend(script_file_list);
+            }
+
+            module_state.cmd_map.insert(
+                project_dir.to_string(),
+                ProjectCommandState {
+                    cmd_list,
+                    default_cmd_index: None,
+                    cmd_stats: HashMap::new(),
+                    last_cmd: None,
+                    cmd_envs: HashMap::new(),
+                },
+            );
+        }
+
+        #[cfg(feature = "enable_project_command_debug_print")]
+        nvim::print!("{LOGGER_PREFIX} state: {module_state:#?}");
+    }
+}
+
+///
+/// Get back the `project_dir` cmd list as picker items, the default cmd (if any) is on top.
+///
+fn get_project_picker_items(project_dir: &str) -> Option<Vec<PickerItem>> {
+    let locked_state = MY_PROJECT_COMMAND_STATE.lock();
+    let module_state = locked_state.as_ref().unwrap();
+
+    let state = module_state.cmd_map.get(project_dir)?;
+    let cmd_list_len = state.cmd_list.len();
+    let mut temp_cmd_list =
+        Vec::<String>::with_capacity(if cmd_list_len > 0 { cmd_list_len } else { 1 });
+
+    //
+    // If `state.default_cmd_index != None` then put the `default_cmd` on top then follow by the rest
+    //
+    let display_cmd_list: &Vec<String> = if state.default_cmd_index.is_none() {
+        &state.cmd_list
+    } else {
+        // Put the default cmd on top
+        let top_line = state.cmd_list[state.default_cmd_index.unwrap()].clone();
+        temp_cmd_list.push(top_line.clone());
+
+        // Copy the reset
+        for line in &state.cmd_list {
+            if line != &top_line {
+                temp_cmd_list.push(line.clone());
+            }
+        }
+
+        // return the re-ordered cmd list
+        &temp_cmd_list
+    };
+
+    //
+    // The slot number (`<leader>p1`..`<leader>p9`) is the position in `state.cmd_list`
+    //
+    Some(
+        display_cmd_list
+            .iter()
+            .map(|cmd| {
+                let slot = state
+                    .cmd_list
+                    .iter()
+                    .position(|item| item == cmd)
+                    .filter(|index| *index < PROJECT_COMMAND_SLOT_COUNT && !cmd.is_empty())
+                    .map(|index| (index + 1).to_string())
+                    .unwrap_or_default();
+
+                PickerItem {
+                    columns: vec![
+                        PickerItemColumn::new(&slot, Some("Special")),
+                        PickerItemColumn::new(cmd, None),
+                    ],
+                    icon: None,
+                    data: cmd.to_owned(),
+                }
+            })
+            .collect::<Vec<PickerItem>>(),
+    )
+}
+
+///
+/// `<c-d>` in the picker: Delete the command (and its run statistics and environment) from the
+/// current project, the `default_cmd_index` keeps pointing to the same default command.
+///
+fn delete_project_command(cmd: &str) -> bool {
+    let project_dir = get_project_dir();
+    let mut locked_state = MY_PROJECT_COMMAND_STATE.lock().unwrap();
+    let Some(state) = locked_state.cmd_map.get_mut(&project_dir) else {
+        return false;
+    };
+    let Some(index) = state.cmd_list.iter().position(|item| item == cmd) else {
+        return false;
+    };
+
+    state.cmd_list.remove(index);
+    state.default_cmd_index = match state.default_cmd_index {
+        Some(default_index) if default_index == index => None,
+        Some(default_index) if default_index > index => Some(default_index - 1),
+        default_index => default_index,
+    };
+    state.cmd_stats.remove(cmd);
+    state.cmd_envs.remove(cmd);
+    if state.last_cmd.as_deref() == Some(cmd) {
+        state.last_cmd = None;
+    }
+
+    report(
+        "project_command",
+        save_project_command_state(&project_dir, state),
+    );
+    true
+}
+
+///
+/// `<c-r>` in the picker: Replace the command in place, so its slot and the
+/// `default_cmd_index` don't change, the run statistics and environment are kept.
+///
+fn edit_project_command(old_cmd: &str, new_cmd: &str) -> Result<(), String> {
+    if new_cmd.trim().is_empty() {
+        return Err("The command can't be empty".to_string());
+    }
+
+    let project_dir = get_project_dir();
+    let mut locked_state = MY_PROJECT_COMMAND_STATE.lock().unwrap();
+    let Some(state) = locked_state.cmd_map.get_mut(&project_dir) else {
+        return Err("No project command state".to_string());
+    };
+    if new_cmd != old_cmd && state.cmd_list.iter().any(|item| item == new_cmd) {
+        return Err(format!("'{new_cmd}' already exists"));
+    }
+    let Some(index) = state.cmd_list.iter().position(|item| item == old_cmd) else {
+        return Err(format!("'{old_cmd}' doesn't exist"));
+    };
+
+    state.cmd_list[index] = new_cmd.to_owned();
+    if let Some(stats) = state.cmd_stats.remove(old_cmd) {
+        state.cmd_stats.insert(new_cmd.to_owned(), stats);
+    }
+    if let Some(cmd_env) = state.cmd_envs.remove(old_cmd) {
+        state.cmd_envs.insert(new_cmd.to_owned(), cmd_env);
+    }
+    if state.last_cmd.as_deref() == Some(old_cmd) {
+        state.last_cmd = Some(new_cmd.to_owned());
+    }
+
+    report(
+        "project_command",
+        save_project_command_state(&project_dir, state),
+    );
+    Ok(())
+}
+
+///
+/// Open the project command picker
+///
+fn open(options: ProjectCommandOptions) {
+    #[cfg(feature = "enable_project_command_debug_print")]
+    const LOGGER_PREFIX: &'static str = "[ project_command - open ]";
+
+    let project_dir = get_project_dir();
+
+    #[cfg(feature = "enable_project_command_debug_print")]
+    nvim::print!("{LOGGER_PREFIX} project_dir: {project_dir}");
+
+    init_project_cmd_list(&project_dir, &options);
+
+    //
+    // Get back the `project_dir` cmd list
+    //
+    if let Some(picker_items) = get_project_picker_items(&project_dir) {
+        //
+        // Open the picker
+        //
+        let _ = create_editable_picker_with_options(
+            &mut EditablePickerOptions {
+                title: "Project Command ('Ctrl+e' to close picker)".to_string(),
+                window_opts: PopupWindowOptions {
+                    border: WindowBorder::Rounded,
+                    window_width_ratio: None,
+                    window_height_ratio: None,
+                    auto_width: true,
+                    auto_height: true,
+                    buffer: None,
+                    ..Default::default()
+                },
+                list: &picker_items,
+                //
+                // The `get_project_picker_items` already puts the default command on top
+                //
+                sort: PickerSortStrategy::None,
+                source: Some("project_commands".to_string()),
+                sticky_filter: false,
+                show_footer: true,
+                initial_input: options.initial_input.clone(),
+                validator: Some(validate_project_command),
+                on_delete: Some(delete_project_command),
+                on_edit: Some(edit_project_command),
+            },
+            move |selected_text: String| {
+                picker_selected_callback(&project_dir, selected_text);
+            },
+        );
+    };
+}
+
+///
+/// Run the default command (the last selected one, or the first one) of the current project
+/// without opening the picker, it returns `false` if there is no command to run.
+///
+pub fn run_default_command() -> bool {
+    run_command("")
+}
+
+///
+/// Run the given command in the current project without opening the picker, the command is
+/// added to the project command list. The empty command means the default one, it returns
+/// `false` if there is no command to run.
+///
+pub fn run_command(cmd: &str) -> bool {
+    let project_dir = get_project_dir();
+    init_project_cmd_list(
+        &project_dir,
+        &ProjectCommandOptions {
+            enable_script_files: true,
+            enable_project_type_commands: true,
+            open_source_on_left_split_win: false,
+            initial_input: None,
+        },
+    );
+
+    let has_cmd = MY_PROJECT_COMMAND_STATE
+        .lock()
+        .unwrap()
+        .cmd_map
+        .get(&project_dir)
+        .is_some_and(|state| !state.cmd_list.is_empty());
+    if cmd.is_empty() && !has_cmd {
+        return false;
+    }
+
+    picker_selected_callback(&project_dir, cmd.to_owned());
+    true
+}
+
+///
+/// Run the command in the given slot (1-based) of the current project without opening the
+/// picker, it returns `false` if the slot is empty.
+///
+pub fn run_slot_command(slot: usize) -> bool {
+    let project_dir = get_project_dir();
+    init_project_cmd_list(
+        &project_dir,
+        &ProjectCommandOptions {
+            enable_script_files: true,
+            enable_project_type_commands: true,
+            open_source_on_left_split_win: false,
+            initial_input: None,
+        },
+    );
+
+    let slot_cmd = MY_PROJECT_COMMAND_STATE
+        .lock()
+        .unwrap()
+        .cmd_map
+        .get(&project_dir)
+        .and_then(|state| state.cmd_list.get(slot.wrapping_sub(1)).cloned())
+        .filter(|cmd| !cmd.is_empty() && slot <= PROJECT_COMMAND_SLOT_COUNT);
+
+    match slot_cmd {
+        Some(cmd) => run_command(&cmd),
+        None => false,
+    }
+}
+
+///
+/// Re-run the last executed command of the current project (even if it's not the default one)
+/// without opening the picker, it returns `false` if there is no command executed yet.
+///
+pub fn run_last_command() -> bool {
+    let project_dir = get_project_dir();
+    init_project_cmd_list(
+        &project_dir,
+        &ProjectCommandOptions {
+            enable_script_files: true,
+            enable_project_type_commands: true,
+            open_source_on_left_split_win: false,
+            initial_input: None,
+        },
+    );
+
+    let (last_cmd, cmd_env) = {
+        let mut locked_state = MY_PROJECT_COMMAND_STATE.lock().unwrap();
+        let Some(state) = locked_state.cmd_map.get_mut(&project_dir) else {
+            return false;
+        };
+        let Some(last_cmd) = state.last_cmd.clone() else {
+            return false;
+        };
+
+        record_command_run(&project_dir, state, &last_cmd);
+        let cmd_env = state.cmd_envs.get(&last_cmd).cloned().unwrap_or_default();
+        (last_cmd, cmd_env)
+    };
+
+    execute_command(&project_dir, &last_cmd, &cmd_env, false);
+    true
+}
+
+///
+/// Get back the name of the Rust test function which encloses the cursor row (0-based): the
+/// nearest `fn` above the cursor, which has the test attribute (e.g. `#[test]`,
+/// `#[tokio::test]`) above it.
+///
+fn find_rust_test_name(lines: &[String], cursor_row: usize) -> Option<String> {
+    const FN_MODIFIERS: [&'static str; 5] = ["pub", "pub(crate)", "async", "const", "unsafe"];
+
+    for row in (0..=cursor_row.min(lines.len().checked_sub(1)?)).rev() {
+        let mut tokens = lines[row].split_whitespace();
+        let is_fn_line = tokens
+            .by_ref()
+            .find(|token| !FN_MODIFIERS.contains(token))
+            .is_some_and(|token| token == "fn");
+        if !is_fn_line {
+            continue;
+        }
+
+        let name = tokens
+            .next()?
+            .split(|c: char| c == '(' || c == '<')
+            .next()
+            .unwrap_or_default()
+            .to_owned();
+
+        //
+        // The attributes and doc comments above the `fn` line
+        //
+        let is_test = lines[..row]
+            .iter()
+            .rev()
+            .map(|line| line.trim())
+            .take_while(|line| line.starts_with("#[") || line.starts_with("///"))
+            .any(|line| line.starts_with("#[") && line.contains("test"));
+
+        return (is_test && !name.is_empty()).then_some(name);
     }
 
-    //
-    // Update the `default_cmd_index`
-    //
-    for index in 0..state.cmd_list.len() {
-        if state.cmd_list[index].cmp(&cmd) == core::cmp::Ordering::Equal {
-            state.default_cmd_index = Some(index);
+    None
+}
 
-            #[cfg(feature = "enable_project_command_debug_print")]
-            nvim::print!("{LOGGER_PREFIX} update 'default_cmd_index' to: {index}");
+///
+/// Get back the name of the Zig test block which encloses the cursor row (0-based), e.g.
+/// `test "parse empty input" {`. It's `Some("")` for the unnamed `test {` block.
+///
+fn find_zig_test_name(lines: &[String], cursor_row: usize) -> Option<String> {
+    lines
+        .iter()
+        .take(cursor_row + 1)
+        .rev()
+        .map(|line| line.trim())
+        .find(|line| line.starts_with("test ") && line.ends_with('{'))
+        .map(|line| {
+            let mut parts = line.splitn(3, '"');
+            match (parts.next(), parts.next(), parts.next()) {
+                (_, Some(name), Some(_)) => name.to_owned(),
+                _ => String::new(),
+            }
+        })
+}
 
-            break;
-        }
+///
+/// Quote the command argument if needed, see `tokenize_command()`
+///
+fn quote_command_arg(arg: &str) -> String {
+    if !arg.is_empty()
+        && !arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '\\')
+    {
+        return arg.to_owned();
     }
 
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+///
+/// Run the test which encloses the cursor in the current project, the failures are set to the
+/// quickfix list:
+///
+/// - Rust: `cargo test <test_fn_name>`
+/// - Zig: `zig test <file> --test-filter <test_name>`, or the whole file if there is no named
+///   test block above the cursor.
+///
+fn run_test_under_cursor() {
+    let buffer = Buffer::current();
+    let filetype = get_option_value::<NvimString>(
+        "filetype",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    )
+    .map(|value| value.to_string())
+    .unwrap_or_default();
+
+    let Ok((cursor_row, _)) = Window::current().get_cursor() else {
+        return;
+    };
+    let lines = buffer
+        .get_lines(..cursor_row, false)
+        .map(|lines| lines.map(|line| line.to_string()).collect::<Vec<String>>())
+        .unwrap_or_default();
+
+    let test_cmd = match filetype.as_str() {
+        "rust" => find_rust_test_name(&lines, cursor_row.saturating_sub(1))
+            .map(|name| format!("cargo test {}", quote_command_arg(&name))),
+        "zig" => {
+            let file = call_function::<_, String>("expand", ("%:p",)).unwrap_or_default();
+            match find_zig_test_name(&lines, cursor_row.saturating_sub(1)) {
+                Some(name) if !name.is_empty() => Some(format!(
+                    "zig test {} --test-filter {}",
+                    quote_command_arg(&file),
+                    quote_command_arg(&name)
+                )),
+                _ => Some(format!("zig test {}", quote_command_arg(&file))),
+            }
+        }
+        _ => {
+            notify(
+                "project_command",
+                "The test runner only supports Rust and Zig.",
+                EchoLevel::Warning,
+            );
+            return;
+        }
+    };
+
+    let Some(test_cmd) = test_cmd else {
+        notify(
+            "project_command",
+            "No test function under cursor.",
+            EchoLevel::Warning,
+        );
+        return;
+    };
+
     //
+    // Save the buffer before running the test, the test command isn't added to the command list.
     //
-    //
-    execute_command(project_dir, &cmd);
+    let _ = command("silent! update");
+
+    let project_dir = get_project_dir();
+    let cmd_env = MY_PROJECT_COMMAND_STATE
+        .lock()
+        .unwrap()
+        .cmd_map
+        .get(&project_dir)
+        .and_then(|state| state.cmd_envs.get(&test_cmd).cloned())
+        .unwrap_or_default();
+
+    execute_command(&project_dir, &test_cmd, &cmd_env, true);
 }
 
 ///
-/// Options
+/// Toggle the watch mode of the current project
 ///
-struct ProjectCommandOptions {
-    enable_script_files: bool,
-    open_source_on_left_split_win: bool,
+fn toggle_watch_mode() {
+    let project_dir = get_project_dir();
+    let is_watching = {
+        let mut locked_state = MY_PROJECT_COMMAND_STATE.lock().unwrap();
+        if locked_state.watched_projects.remove(&project_dir) {
+            false
+        } else {
+            locked_state.watched_projects.insert(project_dir.clone());
+            true
+        }
+    };
+
+    if is_watching {
+        notify(
+            "project_command",
+            "Watch mode on: run the default command after saving.",
+            EchoLevel::Info,
+        );
+    } else {
+        notify("project_command", "Watch mode off.", EchoLevel::Info);
+    }
 }
 
 ///
-/// Open the project command picker
+/// `BufWritePost`: Run the default command of the watched project which the saved file belongs
+/// to, the previous pending run (within `PROJECT_COMMAND_WATCH_DEBOUNCE`) is cancelled.
 ///
-fn open(options: ProjectCommandOptions) {
-    #[cfg(feature = "enable_project_command_debug_print")]
-    const LOGGER_PREFIX: &'static str = "[ project_command - open ]";
-
-    //
-    // TODO:
-    //
-    // 'project_dir' should be the '.git' folder searching start from the current opened file!!!
-    // 'project_dir' should be the '.git' folder searching start from the current opened file!!!
-    // 'project_dir' should be the '.git' folder searching start from the current opened file!!!
-    //
-    let project_dir = match std::env::var("PWD") {
-        Ok(current_pwd) => current_pwd,
-        Err(_) => "".to_string(),
+fn on_file_saved(file: &str) {
+    let mut locked_state = MY_PROJECT_COMMAND_STATE.lock().unwrap();
+    let Some(project_dir) = locked_state
+        .watched_projects
+        .iter()
+        .find(|project_dir| Path::new(file).starts_with(project_dir.as_str()))
+        .cloned()
+    else {
+        return;
     };
 
-    #[cfg(feature = "enable_project_command_debug_print")]
-    nvim::print!("{LOGGER_PREFIX} project_dir: {project_dir}");
+    if let Some(timer_id) = locked_state.watch_timer.take() {
+        let _ = call_function::<_, i64>("timer_stop", (timer_id,));
+    }
 
-    //
-    // Use the temporary scope to create a small mutex lock range!!!
-    //
-    {
-        let mut locked_state = MY_PROJECT_COMMAND_STATE.lock();
-        let module_state = locked_state.as_mut().unwrap();
-        //
-        // Only init the cmd list when it doesn't exists.
-        //
-        if module_state.cmd_map.get(&project_dir).is_none() {
-            if options.enable_script_files {
-                if let Ok(script_file_list) = get_project_script_files(&project_dir) {
-                    let mut cmd_list = Vec::with_capacity(script_file_list.len());
-                    for script_file in script_file_list {
-                        cmd_list.push(script_file);
-                    }
+    let watch_callback = Function::<(i64,), ()>::from_fn_once(move |_| {
+        MY_PROJECT_COMMAND_STATE.lock().unwrap().watch_timer = None;
 
-                    module_state.cmd_map.insert(
-                        project_dir.to_string(),
-                        ProjectCommandState {
-                            cmd_list,
-                            default_cmd_index: None,
-                        },
-                    );
-                }
-            } else {
-                module_state.cmd_map.insert(
-                    project_dir.to_string(),
-                    ProjectCommandState {
-                        cmd_list: Vec::with_capacity(5),
-                        default_cmd_index: None,
-                    },
-                );
-            }
+        init_project_cmd_list(
+            &project_dir,
+            &ProjectCommandOptions {
+                enable_script_files: true,
+                enable_project_type_commands: true,
+                open_source_on_left_split_win: false,
+                initial_input: None,
+            },
+        );
+        picker_selected_callback(&project_dir, String::new());
+    });
 
-            #[cfg(feature = "enable_project_command_debug_print")]
-            nvim::print!("{LOGGER_PREFIX} state: {module_state:#?}");
+    locked_state.watch_timer = call_function::<_, i64>(
+        "timer_start",
+        (PROJECT_COMMAND_WATCH_DEBOUNCE, watch_callback),
+    )
+    .ok();
+}
+
+///
+/// Edit the working directory, the environment variables, the shell and the terminal mode of
+/// the project command (the last executed one is pre-filled) by the secondary prompts.
+///
+fn edit_command_env() {
+    let project_dir = get_project_dir();
+    init_project_cmd_list(
+        &project_dir,
+        &ProjectCommandOptions {
+            enable_script_files: true,
+            enable_project_type_commands: true,
+            open_source_on_left_split_win: false,
+            initial_input: None,
+        },
+    );
+
+    let (default_cmd, cmd_list) = {
+        let locked_state = MY_PROJECT_COMMAND_STATE.lock().unwrap();
+        match locked_state.cmd_map.get(&project_dir) {
+            Some(state) => (
+                state
+                    .last_cmd
+                    .clone()
+                    .or_else(|| state.default_cmd_index.map(|i| state.cmd_list[i].clone()))
+                    .unwrap_or_default(),
+                state.cmd_list.clone(),
+            ),
+            None => return,
         }
+    };
+
+    let Some(cmd) =
+        prompt("Command: ", &default_cmd, Some("shellcmd")).map(|input| input.trim().to_owned())
+    else {
+        return;
+    };
+    if !cmd_list.contains(&cmd) {
+        notify(
+            "project_command",
+            &format!("Not a project command: {cmd}"),
+            EchoLevel::Warning,
+        );
+        return;
     }
 
+    let current_env = MY_PROJECT_COMMAND_STATE
+        .lock()
+        .unwrap()
+        .cmd_map
+        .get(&project_dir)
+        .and_then(|state| state.cmd_envs.get(&cmd).cloned())
+        .unwrap_or_default();
+
+    let Some(cwd) = prompt(
+        "Working directory (empty to inherit): ",
+        current_env.cwd.as_deref().unwrap_or_default(),
+        Some("dir"),
+    )
+    .map(|input| input.trim().to_owned()) else {
+        return;
+    };
+
+    let current_env_input = current_env
+        .env
+        .iter()
+        .map(|(key, value)| {
+            if value.contains(char::is_whitespace) {
+                format!("{key}='{value}'")
+            } else {
+                format!("{key}={value}")
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+    let Some(env_input) = prompt(
+        "Environment (KEY=VAL ..., empty to inherit): ",
+        &current_env_input,
+        Some("environment"),
+    )
+    .map(|input| input.trim().to_owned()) else {
+        return;
+    };
+
     //
-    // Lock the state
+    // `confirm()` accepts the single keypress of the choice shortcut
     //
+    let shell_choice = call_function::<_, i64>(
+        "confirm",
+        (
+            "Run by '$SHELL -c' (for the shell syntax, e.g. '&&', pipes)?",
+            "&Yes\n&No",
+            if current_env.use_shell { 1 } else { 2 },
+        ),
+    )
+    .unwrap_or(0);
+    if shell_choice == 0 {
+        return;
+    }
+
+    let terminal_choice = call_function::<_, i64>(
+        "confirm",
+        (
+            "Run in a terminal (ANSI colors, interactive)?",
+            "&Yes\n&No",
+            if current_env.use_terminal { 1 } else { 2 },
+        ),
+    )
+    .unwrap_or(0);
+    if terminal_choice == 0 {
+        return;
+    }
+
+    let cmd_env = ProjectCommandEnv {
+        cwd: if cwd.is_empty() { None } else { Some(cwd) },
+        env: tokenize_command(&env_input)
+            .iter()
+            .filter_map(|token| parse_env_pair(token))
+            .collect::<Vec<(String, String)>>(),
+        use_shell: shell_choice == 1,
+        use_terminal: terminal_choice == 1,
+    };
+
+    let mut locked_state = MY_PROJECT_COMMAND_STATE.lock().unwrap();
+    let Some(state) = locked_state.cmd_map.get_mut(&project_dir) else {
+        return;
+    };
+
+    if cmd_env.cwd.is_none()
+        && cmd_env.env.is_empty()
+        && !cmd_env.use_shell
+        && !cmd_env.use_terminal
+    {
+        state.cmd_envs.remove(&cmd);
+    } else {
+        state.cmd_envs.insert(cmd.clone(), cmd_env);
+    }
+    report(
+        "project_command",
+        save_project_command_state(&project_dir, state),
+    );
+
+    notify(
+        "project_command",
+        &format!("Updated: {cmd}"),
+        EchoLevel::Success,
+    );
+}
+
+///
+/// Open the project command picker with the given input, e.g. "cargo " to compose a new command.
+///
+pub fn open_with_input(input: &str) {
+    open(ProjectCommandOptions {
+        enable_script_files: true,
+        enable_project_type_commands: true,
+        open_source_on_left_split_win: false,
+        initial_input: Some(input.to_owned()),
+    });
+}
+
+///
+/// The command list of the current project, e.g. the `:ProjectCommand` completion
+///
+fn get_project_command_list() -> Vec<String> {
+    let project_dir = get_project_dir();
+    init_project_cmd_list(
+        &project_dir,
+        &ProjectCommandOptions {
+            enable_script_files: true,
+            enable_project_type_commands: true,
+            open_source_on_left_split_win: false,
+            initial_input: None,
+        },
+    );
+
+    MY_PROJECT_COMMAND_STATE
+        .lock()
+        .unwrap()
+        .cmd_map
+        .get(&project_dir)
+        .map(|state| state.cmd_list.clone())
+        .unwrap_or_default()
+}
+
+///
+/// The command result history (the newest first) for the picker
+///
+fn get_command_result_picker_items() -> Vec<PickerItem> {
     let mut locked_state = MY_PROJECT_COMMAND_STATE.lock();
     let module_state = locked_state.as_mut().unwrap();
 
     //
-    // Get back the `project_dir` cmd list
+    // Drop the buffers which are wiped by the user
     //
-    if let Some(state) = module_state.cmd_map.get(&project_dir) {
-        let cmd_list_len = state.cmd_list.len();
-        let mut temp_cmd_list =
-            Vec::<String>::with_capacity(if cmd_list_len > 0 { cmd_list_len } else { 1 });
+    module_state
+        .command_results
+        .retain(|result| Buffer::from(result.buffer_handle).is_valid());
 
-        //
-        // If `state.default_cmd_index != None` then put the `default_cmd` on top then follow by the rest
-        //
-        let display_cmd_list: &Vec<String> = if state.default_cmd_index.is_none() {
-            &state.cmd_list
-        } else {
-            // Put the default cmd on top
-            let top_line = state.cmd_list[state.default_cmd_index.unwrap()].clone();
-            temp_cmd_list.push(top_line.clone());
-
-            // Copy the reset
-            for line in &state.cmd_list {
-                if line != &top_line {
-                    temp_cmd_list.push(line.clone());
-                }
-            }
-
-            // return the re-ordered cmd list
-            &temp_cmd_list
-        };
+    module_state
+        .command_results
+        .iter()
+        .rev()
+        .map(|result| PickerItem {
+            columns: vec![
+                PickerItemColumn::new(&format!("#{}", result.index), Some("Special")),
+                PickerItemColumn::new(&result.start_time, Some("Comment")),
+                PickerItemColumn::new(&result.cmd, None),
+            ],
+            icon: None,
+            data: result.buffer_handle.to_string(),
+        })
+        .collect()
+}
 
-        //
-        // Open the picker
-        //
-        if let Ok(open_result) = create_editable_picker_with_options(
-            &mut EditablePickerOptions {
-                title: "Project Command ('Ctrl+e' to close picker)".to_string(),
-                window_opts: PopupWindowOptions {
-                    border: WindowBorder::Rounded,
-                    window_width_ratio: None,
-                    window_height_ratio: None,
-                    auto_width: true,
-                    auto_height: true,
-                    buffer: None,
-                },
-                list: &display_cmd_list,
-            },
-            move |selected_text: String| {
-                picker_selected_callback(&project_dir, selected_text);
-            },
-        ) {
-            let custom_highlight_id = module_state.custom_highlight.unwrap();
-            if let Ok(mut title_buffer) = Window::from(open_result.title_window_handle).get_buf() {
-                let _ = title_buffer.set_extmark(
-                    custom_highlight_id, // namespace ID
-                    0,                   // start line/row
-                    18,                  // start col
-                    &SetExtmarkOpts::builder()
-                        .end_line(0)
-                        .end_col(24)
-                        //
-                        // You can run `:h highlight-groups` in Neovim to show all supported
-                        // highlight group values.
-                        //
-                        // .hl_group("TermCursor")
-                        .hl_group("Question")
-                        .build(),
-                );
+///
+/// Show the selected command result buffer in the next event loop tick, as the picker windows
+/// are still closing at this moment.
+///
+fn command_result_selected_callback(selected_handle: String) {
+    nvim::schedule(move |_| {
+        if let Ok(handle) = selected_handle.trim().parse::<BufHandle>() {
+            let command_buffer = Buffer::from(handle);
+            if command_buffer.is_valid() {
+                let _ = show_command_buffer(&command_buffer);
             }
-        };
-    };
+        }
+    });
 }
 
 ///
@@ -544,36 +2536,250 @@ pub fn setup() {
             .callback(|_| {
                 open(ProjectCommandOptions {
                     enable_script_files: true,
+                    enable_project_type_commands: true,
                     open_source_on_left_split_win: false,
+                    initial_input: None,
                 });
                 ()
             })
             .build(),
     );
+
+    let rerun_keybindings: Vec<(&str, &str, fn() -> bool)> = vec![
+        (
+            "<leader>pr",
+            "Project command: Re-run the default command",
+            run_default_command,
+        ),
+        (
+            "<leader>pl",
+            "Project command: Re-run the last executed command",
+            run_last_command,
+        ),
+    ];
+
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>pe",
+        "",
+        &SetKeymapOpts::builder()
+            .desc(
+                "Project command: Edit the working directory, environment, shell and terminal mode of the command",
+            )
+            .silent(true)
+            .callback(|_| {
+                edit_command_env();
+                ()
+            })
+            .build(),
+    );
+
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>pw",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("Project command: Toggle the watch mode (run the default command on save)")
+            .silent(true)
+            .callback(|_| {
+                toggle_watch_mode();
+                ()
+            })
+            .build(),
+    );
+
+    let _ = create_autocmd(
+        vec!["BufWritePost"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-project-command-watch-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                let file = call_function::<_, String>(
+                    "fnamemodify",
+                    (args.file.to_string_lossy().to_string(), ":p"),
+                )
+                .unwrap_or_default();
+                on_file_saved(&file);
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>pt",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("Project command: Run the test under cursor (Rust and Zig)")
+            .silent(true)
+            .callback(|_| {
+                run_test_under_cursor();
+                ()
+            })
+            .build(),
+    );
+
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>ph",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("Project command: Reopen the output of a previous run")
+            .silent(true)
+            .callback(|_| {
+                open_registered_picker("command_results");
+                ()
+            })
+            .build(),
+    );
+
+    for slot in 1..=PROJECT_COMMAND_SLOT_COUNT {
+        let _ = set_keymap(
+            Mode::Normal,
+            &format!("<leader>p{slot}"),
+            "",
+            &SetKeymapOpts::builder()
+                .desc(&format!("Project command: Run the command in slot {slot}"))
+                .silent(true)
+                .callback(move |_| {
+                    if !run_slot_command(slot) {
+                        notify(
+                            "project_command",
+                            &format!("No command in slot {slot}."),
+                            EchoLevel::Warning,
+                        );
+                    }
+                    ()
+                })
+                .build(),
+        );
+    }
+
+    for (key, desc, run) in rerun_keybindings {
+        let _ = set_keymap(
+            Mode::Normal,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(desc)
+                .silent(true)
+                .callback(move |_| {
+                    if !run() {
+                        notify(
+                            "project_command",
+                            "No project command to run.",
+                            EchoLevel::Warning,
+                        );
+                    }
+                    ()
+                })
+                .build(),
+        );
+    }
+
+    //
+    // Contribute the project commands to the picker registry (`<leader>pp`)
+    //
+    register_picker_source(
+        "project_commands",
+        "Project Command ('Ctrl+e' to close picker)",
+        || {
+            let project_dir = get_project_dir();
+            init_project_cmd_list(
+                &project_dir,
+                &ProjectCommandOptions {
+                    enable_script_files: true,
+                    enable_project_type_commands: true,
+                    open_source_on_left_split_win: false,
+                    initial_input: None,
+                },
+            );
+            get_project_picker_items(&project_dir).unwrap_or_default()
+        },
+        |selected_text: String| {
+            picker_selected_callback(&get_project_dir(), selected_text);
+        },
+    );
+
+    register_picker_source(
+        "command_results",
+        "Command result history",
+        get_command_result_picker_items,
+        command_result_selected_callback,
+    );
+
+    register_user_command(
+        "ProjectCommand",
+        UserCommandSpec {
+            desc: "Project command: open the picker with the command, or run it directly with '!'",
+            nargs: CommandNArgs::Any,
+            bang: true,
+            complete: UserCommandComplete::List(get_project_command_list),
+            ..Default::default()
+        },
+        |args: UserCommandArgs| {
+            let cmd = args.joined();
+            if !args.bang {
+                open_with_input(&cmd);
+            } else if !run_command(&cmd) {
+                notify("project_command", "No command to run.", EchoLevel::Warning);
+            }
+        },
+    );
 }
 
 use crate::{
-    picker::{EditablePickerOptions, PopupWindowOptions, create_editable_picker_with_options},
-    utils::get_split_window,
+    error::{self, Error, ResultContext, report},
+    extended_api::{
+        EchoLevel, UserCommandArgs, UserCommandComplete, UserCommandSpec, open_terminal, prompt,
+        register_user_command, terminal_send,
+    },
+    file_change::{check_changed_files, take_open_files_snapshot},
+    notify::notify,
+    picker::{
+        EditablePickerOptions, PickerItem, PickerItemColumn, PickerSortStrategy,
+        PopupWindowOptions, create_editable_picker_with_options, create_popup_window,
+        open_registered_picker, register_picker_source,
+    },
+    tab_scope::{get_tab_output_window, get_tab_split_window, set_tab_output_window},
+    utils::{format_duration, get_data_dir, run_async_in},
 };
 
-use rust_utils::cmd as cmd_utils;
+use std::path::{Path, PathBuf};
 
 use std::{
-    collections::HashMap,
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    rc::Rc,
     sync::{LazyLock, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
 use nvim_oxi::{
-    String as NvimString,
+    Array, BufHandle, Dictionary, Function, Object, String as NvimString,
     api::{
-        Buffer, Window, cmd as vim_cmd, create_buf, create_namespace, get_option_value, list_bufs,
-        open_win,
-        opts::{CmdOpts, OptionOpts, SetExtmarkOpts, SetKeymapOpts},
-        set_keymap, set_option_value,
-        types::{CmdInfos, Mode, SplitDirection, WindowBorder, WindowConfig},
+        Buffer, Window, call_function, cmd as vim_cmd, command, create_augroup, create_autocmd,
+        create_buf, create_namespace, get_option_value, list_wins, open_win,
+        opts::{
+            CmdOpts, CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, SetExtmarkOpts,
+            SetKeymapOpts,
+        },
+        set_current_win, set_keymap, set_option_value,
+        types::{
+            AutocmdCallbackArgs, CmdInfos, CommandNArgs, Mode, SplitDirection, WindowBorder,
+            WindowConfig,
+        },
     },
+    conversion::FromObject,
 };
 
-#[cfg(feature = "enable_project_command_debug_print")]
 use nvim_oxi as nvim;
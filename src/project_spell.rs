@@ -0,0 +1,341 @@
+//! Per-project spell file, the words added by `zg` (or marked bad by `zw`) stay with the project
+//! instead of going into the global spell file:
+//!
+//! - The buffers under the project directory get the local `spellfile` option, the project one
+//!   is the first, so `zg` writes into it and `2zg` still writes into the global one.
+//! - `<leader>sw` or `:ProjectSpell`: Review the added words in the picker, `<c-d>` removes the
+//!   word, `<c-r>` fixes the typo'd word, `<CR>` searches the word in the current buffer.
+//!
+//! The spell file directory is only created after turning on the spell checking, so the project
+//! without spell checking doesn't get an extra folder.
+//!
+
+///
+/// The spell file under the project directory, the name has to end with `.{encoding}.add`.
+///
+const PROJECT_SPELL_DIR: &'static str = ".spell";
+const PROJECT_SPELL_FILE: &'static str = "words.utf-8.add";
+
+///
+/// Get back the project spell file, `None` if there is no project directory or the path can't
+/// be in the comma separated `spellfile` option.
+///
+fn get_project_spellfile() -> Option<PathBuf> {
+    let project_dir = get_project_dir();
+    if project_dir.is_empty() || project_dir.contains(',') {
+        return None;
+    }
+
+    Some(
+        Path::new(&project_dir)
+            .join(PROJECT_SPELL_DIR)
+            .join(PROJECT_SPELL_FILE),
+    )
+}
+
+///
+/// Only the normal file buffers under the project directory use the project spell file.
+///
+fn is_project_buffer(buffer: &Buffer) -> bool {
+    let opts = OptionOpts::builder().buffer(buffer.clone()).build();
+    let buffer_type = get_option_value::<NvimString>("buftype", &opts).unwrap_or_default();
+    if !buffer_type.is_empty() {
+        return false;
+    }
+
+    let project_dir = get_project_dir();
+    !project_dir.is_empty()
+        && buffer
+            .get_name()
+            .is_ok_and(|name| name.starts_with(Path::new(&project_dir)))
+}
+
+///
+/// Set the project spell file as the first one of the buffer local `spellfile`, and then create
+/// its directory if the spell checking is on (`zg` fails if the directory doesn't exist).
+///
+pub fn use_project_spellfile(buffer: &Buffer) {
+    if !is_project_buffer(buffer) {
+        return;
+    }
+    let Some(spellfile) = get_project_spellfile() else {
+        return;
+    };
+
+    //
+    // The global value is kept after the project one, it's always computed from the global value,
+    // so entering the buffer again doesn't prepend twice.
+    //
+    let global_spellfile = get_option_value::<NvimString>(
+        "spellfile",
+        &OptionOpts::builder().scope(OptionScope::Global).build(),
+    )
+    .map(|value| value.to_string())
+    .unwrap_or_default();
+    let spellfile_value = if global_spellfile.is_empty() {
+        spellfile.display().to_string()
+    } else {
+        format!("{},{global_spellfile}", spellfile.display())
+    };
+    let _ = set_option_value(
+        "spellfile",
+        spellfile_value,
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    );
+
+    let is_spell_on = get_option_value::<bool>(
+        "spell",
+        &OptionOpts::builder().win(Window::current()).build(),
+    )
+    .unwrap_or(false);
+    if is_spell_on && let Some(spell_dir) = spellfile.parent() {
+        let _ = std::fs::create_dir_all(spell_dir);
+    }
+}
+
+///
+/// Get back the lines of the project spell file, the commented out words (by `zug`) and the empty
+/// lines are skipped.
+///
+fn get_project_spell_words() -> Vec<String> {
+    let Some(spellfile) = get_project_spellfile() else {
+        return Vec::new();
+    };
+    let Ok(content) = std::fs::read_to_string(&spellfile) else {
+        return Vec::new();
+    };
+
+    content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_owned())
+        .collect()
+}
+
+///
+/// Rewrite the project spell file with the words, and then regenerate the `.spl` file, so the
+/// change works immediately in all the buffers.
+///
+fn save_project_spell_words(words: &[String]) -> bool {
+    let Some(spellfile) = get_project_spellfile() else {
+        return false;
+    };
+
+    let mut content = words.join("\n");
+    content.push('\n');
+    if std::fs::write(&spellfile, content).is_err() {
+        return false;
+    }
+
+    let escaped_spellfile =
+        call_function::<_, String>("fnameescape", (spellfile.display().to_string(),))
+            .unwrap_or_default();
+    let _ = command(&format!("silent! mkspell! {escaped_spellfile}"));
+    true
+}
+
+///
+/// The picker item of the spell file line, the `zw` word (e.g. `teh/!`) is shown as "bad".
+///
+fn get_project_spell_picker_items() -> Vec<PickerItem> {
+    get_project_spell_words()
+        .into_iter()
+        .map(|line| {
+            let (word, is_bad_word) = match line.strip_suffix("/!") {
+                Some(word) => (word.to_owned(), true),
+                None => (line.clone(), false),
+            };
+
+            let mut columns = vec![PickerItemColumn::new(&word, None)];
+            if is_bad_word {
+                columns.push(PickerItemColumn::new("(bad)", Some("Comment")));
+            }
+
+            PickerItem {
+                columns,
+                icon: None,
+                data: line,
+            }
+        })
+        .collect()
+}
+
+///
+/// `<c-d>` in the picker: Remove the word from the project spell file
+///
+fn delete_project_spell_word(line: &str) -> bool {
+    let mut words = get_project_spell_words();
+    let Some(index) = words.iter().position(|word| word == line) else {
+        return false;
+    };
+
+    words.remove(index);
+    save_project_spell_words(&words)
+}
+
+///
+/// `<c-r>` in the picker: Replace the typo'd word in place
+///
+fn edit_project_spell_word(old_line: &str, new_line: &str) -> Result<(), String> {
+    let new_line = new_line.trim();
+    if new_line.is_empty() || new_line.contains(char::is_whitespace) {
+        return Err("The word can't be empty or contain spaces".to_string());
+    }
+
+    let mut words = get_project_spell_words();
+    if new_line != old_line && words.iter().any(|word| word == new_line) {
+        return Err(format!("'{new_line}' already exists"));
+    }
+    let Some(index) = words.iter().position(|word| word == old_line) else {
+        return Err(format!("'{old_line}' doesn't exist"));
+    };
+
+    words[index] = new_line.to_owned();
+    if !save_project_spell_words(&words) {
+        return Err("Failed to save the project spell file".to_string());
+    }
+
+    Ok(())
+}
+
+///
+/// `<CR>` in the picker: Search the whole word in the current buffer
+///
+fn project_spell_selected_callback(selected_line: String) {
+    let word = selected_line.trim();
+    let word = word.split('/').next().unwrap_or(word).to_owned();
+    if word.is_empty() {
+        return;
+    }
+
+    nvim::schedule(move |_| {
+        let pattern = format!("\\V\\<{}\\>", word.replace('\\', "\\\\"));
+        set_register('/', &RegisterContent::charwise(&pattern));
+        let _ = command("silent! normal! n");
+        ()
+    });
+}
+
+///
+/// Open the picker of the project spell words
+///
+fn open_project_spell_picker() {
+    let items = get_project_spell_picker_items();
+    if items.is_empty() {
+        echo_message(
+            "project_spell",
+            "No words in the project spell file, add one by 'zg'.",
+            EchoLevel::Info,
+        );
+        return;
+    }
+
+    let _ = create_editable_picker_with_options(
+        &mut EditablePickerOptions {
+            title: "Project spell words".to_string(),
+            window_opts: PopupWindowOptions {
+                border: WindowBorder::Rounded,
+                window_width_ratio: None,
+                window_height_ratio: None,
+                auto_width: true,
+                auto_height: true,
+                buffer: None,
+                ..Default::default()
+            },
+            list: &items,
+            sort: PickerSortStrategy::None,
+            source: Some("project_spell".to_string()),
+            sticky_filter: false,
+            show_footer: true,
+            initial_input: None,
+            validator: None,
+            on_delete: Some(delete_project_spell_word),
+            on_edit: Some(edit_project_spell_word),
+        },
+        project_spell_selected_callback,
+    );
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_autocmd(
+        vec!["BufEnter"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-project-spell-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                use_project_spellfile(&args.buffer);
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>sw",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("'<leader>sw': Review the project spell words")
+            .silent(true)
+            .callback(|_| {
+                open_project_spell_picker();
+                ()
+            })
+            .build(),
+    );
+
+    register_user_command(
+        "ProjectSpell",
+        UserCommandSpec {
+            desc: "Review the words in the project spell file",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| open_project_spell_picker(),
+    );
+
+    //
+    // Contribute the project spell words to the picker registry (`<leader>pp`)
+    //
+    register_picker_source(
+        "project_spell",
+        "Project spell words",
+        get_project_spell_picker_items,
+        project_spell_selected_callback,
+    );
+}
+
+use crate::{
+    extended_api::{
+        EchoLevel, RegisterContent, UserCommandArgs, UserCommandSpec, echo_message,
+        register_user_command, set_register,
+    },
+    picker::{
+        EditablePickerOptions, PickerItem, PickerItemColumn, PickerSortStrategy,
+        PopupWindowOptions, create_editable_picker_with_options, register_picker_source,
+    },
+    project_command::get_project_dir,
+};
+
+use std::path::{Path, PathBuf};
+
+use nvim_oxi::{
+    self as nvim, String as NvimString,
+    api::{
+        Buffer, Window, call_function, command, create_augroup, create_autocmd, get_option_value,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, OptionScope, SetKeymapOpts},
+        set_keymap, set_option_value,
+        types::{AutocmdCallbackArgs, Mode, WindowBorder},
+    },
+};
@@ -0,0 +1,319 @@
+//! Readonly "peek" mode for the quickfix/location list (e.g. the `:grep` results):
+//!
+//! - `<C-j>`/`<C-k>` in the quickfix window: Move to the next/previous entry and show its
+//!   location in a transient preview float. It doesn't jump, so the jumplist stays untouched,
+//!   and the file is read from disk (or the loaded buffer) without being opened in a window.
+//! - `<CR>`: Close the preview and accept the entry (the default quickfix jump).
+//!
+//! The preview is closed when the cursor moves by the other keys or leaves the quickfix window.
+//!
+
+///
+/// The line count to show before and after the entry line
+///
+const PEEK_CONTEXT_LINES: usize = 10;
+
+///
+/// The namespace to highlight the entry line in the preview
+///
+const QUICKFIX_PEEK_NAMESPACE: &'static str = "quickfix_peek";
+
+///
+/// The peeked quickfix entry
+///
+#[derive(Debug, Clone)]
+struct PeekEntry {
+    buffer_handle: i32,
+    line: usize, // 1-based
+    text: String,
+}
+
+///
+/// Private module-scope state: the preview popup and the quickfix window line (1-based) it
+/// belongs to.
+///
+static QUICKFIX_PEEK_STATE: LazyLock<Mutex<Option<(PopupWindow, usize)>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+///
+/// Close the preview popup if it's open
+///
+fn close_peek_preview() {
+    if let Some((preview_window, _)) = QUICKFIX_PEEK_STATE.lock().unwrap().take() {
+        preview_window.close();
+    }
+}
+
+///
+/// Get back the quickfix (or location list) entry of the given line in the current quickfix
+/// window
+///
+fn get_peek_entry(qf_line: usize) -> Option<PeekEntry> {
+    let entry = call_function::<_, Dictionary>(
+        "luaeval",
+        (
+            r#"(function(index)
+    local win_info = vim.fn.getwininfo(vim.fn.win_getid())[1]
+    local list = win_info.loclist == 1 and vim.fn.getloclist(0) or vim.fn.getqflist()
+    return list[index] or vim.empty_dict()
+end)(_A)"#,
+            qf_line as i64,
+        ),
+    )
+    .ok()?;
+
+    let get_integer = |key: &str| {
+        entry
+            .get(key)
+            .and_then(|value| i64::from_object(value.clone()).ok())
+            .unwrap_or(0)
+    };
+
+    let buffer_handle = get_integer("bufnr") as i32;
+    if buffer_handle <= 0 {
+        return None;
+    }
+
+    Some(PeekEntry {
+        buffer_handle,
+        line: get_integer("lnum").max(1) as usize,
+        text: entry
+            .get("text")
+            .and_then(|text| NvimString::from_object(text.clone()).ok())
+            .map(|text| text.to_string())
+            .unwrap_or_default(),
+    })
+}
+
+///
+/// Get back the lines of the entry buffer, the loaded buffer content is preferred (it may have
+/// unsaved changes), otherwise, it's read from disk without loading the buffer.
+///
+fn get_entry_lines(entry: &PeekEntry) -> Option<(String, Vec<String>)> {
+    let buffer = Buffer::from(entry.buffer_handle);
+    let file_name = buffer.get_name().ok()?.to_string_lossy().to_string();
+
+    if buffer.is_loaded() {
+        let lines = buffer
+            .get_lines(.., false)
+            .ok()?
+            .map(|line| line.to_string())
+            .collect();
+        return Some((file_name, lines));
+    }
+
+    let content = std::fs::read_to_string(&file_name).ok()?;
+    Some((
+        file_name,
+        content.lines().map(|line| line.to_owned()).collect(),
+    ))
+}
+
+///
+/// Show the entry of the given quickfix window line in the preview popup
+///
+fn peek_entry(qf_line: usize) {
+    close_peek_preview();
+
+    let Some(entry) = get_peek_entry(qf_line) else {
+        return;
+    };
+
+    let Some((file_name, lines)) = get_entry_lines(&entry) else {
+        return;
+    };
+
+    let target_index = entry.line.min(lines.len().max(1)) - 1;
+    let start_index = target_index.saturating_sub(PEEK_CONTEXT_LINES);
+    let end_index = (target_index + PEEK_CONTEXT_LINES + 1).min(lines.len());
+
+    let Ok(mut preview_buffer) = create_buf(false, true) else {
+        return;
+    };
+
+    let opts = OptionOpts::builder().buffer(preview_buffer.clone()).build();
+    let _ = set_option_value("bufhidden", "wipe", &opts);
+    let _ = preview_buffer.set_lines(
+        ..,
+        true,
+        lines[start_index..end_index].iter().map(|v| v.as_str()),
+    );
+    let _ = set_option_value("modifiable", false, &opts);
+
+    if let Ok(filetype) = call_function::<_, String>(
+        "luaeval",
+        (
+            r#"vim.filetype.match({ filename = _A }) or """#,
+            file_name.as_str(),
+        ),
+    ) {
+        let _ = set_option_value("filetype", filetype.as_str(), &opts);
+    }
+
+    //
+    // Highlight the entry line
+    //
+    let namespace_id = create_namespace(QUICKFIX_PEEK_NAMESPACE);
+    let _ = preview_buffer.set_extmark(
+        namespace_id,
+        target_index - start_index,
+        0,
+        &SetExtmarkOpts::builder().line_hl_group("Visual").build(),
+    );
+
+    let display_name = call_function::<_, String>("fnamemodify", (file_name.as_str(), ":~:."))
+        .unwrap_or(file_name);
+
+    let Some(preview_window) = create_popup_window(&PopupWindowOptions {
+        border: WindowBorder::Rounded,
+        window_width_ratio: Some(0.7),
+        window_height_ratio: None,
+        auto_width: false,
+        auto_height: true,
+        buffer: Some(preview_buffer.handle()),
+        title: Some(format!(" {display_name}:{} ", entry.line)),
+        footer: Some(format!(" {} ", entry.text.trim())),
+        zindex: Some(60),
+        focusable: false,
+        ..Default::default()
+    }) else {
+        return;
+    };
+
+    //
+    // Move the cursor to the entry line, so it's always visible in the preview
+    //
+    let _ = preview_window
+        .window()
+        .set_cursor(target_index - start_index + 1, 0);
+
+    *QUICKFIX_PEEK_STATE.lock().unwrap() = Some((preview_window, qf_line));
+}
+
+///
+/// Move the cursor in the quickfix window by the given offset, and then peek the entry.
+///
+fn move_and_peek(offset: isize) {
+    let mut qf_window = Window::current();
+    let Ok((row, _)) = qf_window.get_cursor() else {
+        return;
+    };
+    let Ok(line_count) = Buffer::current().line_count() else {
+        return;
+    };
+
+    let new_row = (row as isize + offset).clamp(1, line_count.max(1) as isize) as usize;
+    let _ = qf_window.set_cursor(new_row, 0);
+
+    peek_entry(new_row);
+}
+
+///
+/// Setup the buffer-local keybindings and autocmds of the quickfix buffer
+///
+fn setup_quickfix_buffer(mut qf_buffer: Buffer) {
+    let key_bindings: Vec<(&str, &str, Box<dyn Fn()>)> = vec![
+        (
+            "<C-j>",
+            "Quickfix peek: Next entry",
+            Box::new(|| move_and_peek(1)),
+        ),
+        (
+            "<C-k>",
+            "Quickfix peek: Previous entry",
+            Box::new(|| move_and_peek(-1)),
+        ),
+        (
+            "<CR>",
+            "Quickfix peek: Accept the entry",
+            Box::new(|| {
+                close_peek_preview();
+                let _ = command(r#"execute "normal! \<CR>""#);
+            }),
+        ),
+    ];
+
+    for b in key_bindings {
+        let _ = qf_buffer.set_keymap(
+            Mode::Normal,
+            b.0,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(b.1)
+                .callback(move |_| {
+                    b.2();
+                    ()
+                })
+                .silent(true)
+                .build(),
+        );
+    }
+
+    let _ = create_autocmd(
+        vec!["CursorMoved", "WinLeave", "BufLeave"],
+        &CreateAutocmdOpts::builder()
+            .buffer(qf_buffer)
+            .callback(|args: AutocmdCallbackArgs| {
+                let peeked_line = QUICKFIX_PEEK_STATE
+                    .lock()
+                    .unwrap()
+                    .map(|(_, peeked_line)| peeked_line);
+
+                if let Some(peeked_line) = peeked_line {
+                    let current_line = Window::current().get_cursor().map(|(row, _)| row);
+                    if args.event != "CursorMoved" || current_line != Ok(peeked_line) {
+                        close_peek_preview();
+                    }
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_autocmd(
+        vec!["FileType"],
+        &CreateAutocmdOpts::builder()
+            .patterns(vec!["qf"])
+            .group(
+                create_augroup(
+                    "custom-quickfix-peek-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                setup_quickfix_buffer(args.buffer);
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+use crate::picker::{PopupWindow, PopupWindowOptions, create_popup_window};
+
+use std::sync::{LazyLock, Mutex};
+
+use nvim_oxi::{
+    Dictionary, String as NvimString,
+    api::{
+        Buffer, Window, call_function, command, create_augroup, create_autocmd, create_buf,
+        create_namespace,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, SetExtmarkOpts, SetKeymapOpts},
+        set_option_value,
+        types::{AutocmdCallbackArgs, Mode, WindowBorder},
+    },
+    conversion::FromObject,
+};
@@ -0,0 +1,698 @@
+//! Regex playground for crafting the search/replace patterns before applying them:
+//!
+//! - `:[range]RegexTest [pattern]`: Open the 3 stacked floats: the pattern input, the sample
+//!   text (the range lines, or the visible lines of the current window, it's editable) and the
+//!   match list. The matches are highlighted in the sample text as you type.
+//!
+//! Keybindings (in all 3 floats):
+//!
+//! - `<C-t>`: Toggle the engine between the Vim regex (`matchstrpos()`, line by line) and the
+//!   Rust regex (the `regex` crate, the same syntax as `ripgrep`, so `:WorkspaceRename` uses it).
+//! - `<Tab>`/`<S-Tab>`: Focus the next/previous float.
+//! - `<CR>` (pattern input): Apply the pattern, the Vim one goes to the search register (`@/`),
+//!   the Rust one starts `:WorkspaceRename` with it.
+//! - `q` or `<ESC>`: Close.
+//!
+
+///
+/// The namespace of the match highlights in the sample text
+///
+const REGEX_PLAYGROUND_NAMESPACE: &'static str = "regex_playground";
+
+///
+/// The matches more than it are not listed and highlighted
+///
+const REGEX_PLAYGROUND_MAX_MATCHES: usize = 1000;
+
+///
+/// The Lua code to find the Vim regex matches: `{ { row, start_col, end_col }, ... }` (0-based,
+/// the end is exclusive).
+///
+const REGEX_PLAYGROUND_VIM_MATCH_LUA: &'static str = r#"local pattern, lines, max_matches = ...
+local matches = {}
+for row, line in ipairs(lines) do
+    local start = 0
+    while start <= #line and #matches < max_matches do
+        local found = vim.fn.matchstrpos(line, pattern, start)
+        if found[2] < 0 then
+            break
+        end
+        table.insert(matches, { row - 1, found[2], found[3] })
+        start = found[3] > found[2] and found[3] or found[3] + 1
+    end
+end
+return matches"#;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RegexEngine {
+    Vim,
+    Rust,
+}
+
+impl RegexEngine {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Vim => "Vim",
+            Self::Rust => "Rust",
+        }
+    }
+}
+
+///
+/// The match in the sample text, the positions are `(row, col)` (0-based, byte offset), the
+/// end is exclusive.
+///
+#[derive(Debug, Clone, Default)]
+struct RegexMatch {
+    start: (usize, usize),
+    end: (usize, usize),
+    text: String,
+
+    //
+    // The capture groups (`$1`, `$2`, ...), only the Rust regex has them.
+    //
+    captures: Vec<Option<String>>,
+}
+
+///
+/// Private module-scope state
+///
+#[derive(Debug)]
+struct RegexPlaygroundState {
+    engine: RegexEngine,
+    pattern_buffer: BufHandle,
+    sample_buffer: BufHandle,
+    matches_buffer: BufHandle,
+
+    //
+    // The pattern, sample and matches windows, it's the `<Tab>` focus order.
+    //
+    windows: Vec<WinHandle>,
+    attach_ids: Vec<u64>,
+}
+
+static REGEX_PLAYGROUND_STATE: LazyLock<Mutex<Option<RegexPlaygroundState>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+///
+/// Find the Vim regex matches line by line, the Vim regex never matches across the lines.
+///
+fn find_vim_matches(pattern: &str, lines: &[String]) -> Result<Vec<RegexMatch>, String> {
+    let found_list = exec_lua::<_, Vec<Vec<i64>>>(
+        REGEX_PLAYGROUND_VIM_MATCH_LUA,
+        (
+            pattern,
+            Array::from_iter(lines.iter().map(|line| line.as_str())),
+            REGEX_PLAYGROUND_MAX_MATCHES as i64,
+        ),
+    )
+    .ok_or_else(|| format!("Invalid Vim pattern: {pattern}"))?;
+
+    Ok(found_list
+        .into_iter()
+        .filter_map(|found| {
+            let [row, start_col, end_col] = found[..] else {
+                return None;
+            };
+            let (row, start_col, end_col) = (row as usize, start_col as usize, end_col as usize);
+            let text = lines.get(row)?.get(start_col..end_col)?.to_owned();
+
+            Some(RegexMatch {
+                start: (row, start_col),
+                end: (row, end_col),
+                text,
+                captures: Vec::new(),
+            })
+        })
+        .collect())
+}
+
+///
+/// Find the Rust regex matches in the whole sample text, so the pattern can match across the
+/// lines (e.g. `(?s)`, `\n`).
+///
+fn find_rust_matches(pattern: &str, lines: &[String]) -> Result<Vec<RegexMatch>, String> {
+    //
+    // The parse error is multi-line with the caret under the pattern, the last line is the
+    // reason.
+    //
+    let regex = Regex::new(pattern).map_err(|error| {
+        error
+            .to_string()
+            .lines()
+            .last()
+            .unwrap_or("Invalid Rust regex")
+            .trim()
+            .to_owned()
+    })?;
+
+    let text = lines.join("\n");
+    let mut line_starts = Vec::with_capacity(lines.len());
+    let mut offset = 0;
+    for line in lines {
+        line_starts.push(offset);
+        offset += line.len() + 1;
+    }
+
+    let to_position = |offset: usize| {
+        let row = line_starts
+            .partition_point(|start| *start <= offset)
+            .saturating_sub(1);
+        (row, offset - line_starts.get(row).copied().unwrap_or(0))
+    };
+
+    Ok(regex
+        .captures_iter(&text)
+        .take(REGEX_PLAYGROUND_MAX_MATCHES)
+        .filter_map(|captures| {
+            let whole = captures.get(0)?;
+            Some(RegexMatch {
+                start: to_position(whole.start()),
+                end: to_position(whole.end()),
+                text: whole.as_str().to_owned(),
+                captures: (1..captures.len())
+                    .map(|index| captures.get(index).map(|group| group.as_str().to_owned()))
+                    .collect(),
+            })
+        })
+        .collect())
+}
+
+///
+/// The match list line, e.g. `  12:5   "foo_bar"  $1="foo"  $2="bar"`
+///
+fn render_match_line(regex_match: &RegexMatch) -> String {
+    let mut line = format!(
+        "{:>4}:{:<4} {:?}",
+        regex_match.start.0 + 1,
+        regex_match.start.1 + 1,
+        regex_match.text
+    );
+
+    for (index, capture) in regex_match.captures.iter().enumerate() {
+        match capture {
+            Some(capture) => line.push_str(&format!("  ${}={capture:?}", index + 1)),
+            None => line.push_str(&format!("  ${}=<none>", index + 1)),
+        }
+    }
+
+    line
+}
+
+///
+/// Set the float title
+///
+fn set_window_title(window_handle: WinHandle, title: &str) {
+    let config = WindowConfig::builder()
+        .title(WindowTitle::SimpleString(title.into()))
+        .title_pos(WindowTitlePosition::Center)
+        .build();
+    let _ = Window::from(window_handle).set_config(&config);
+}
+
+///
+/// Find the matches of the current pattern, and then update the highlights and the match list
+///
+fn update_playground() {
+    let (engine, pattern_buffer, sample_buffer, matches_buffer, matches_window) = {
+        let locked_state = REGEX_PLAYGROUND_STATE.lock().unwrap();
+        let Some(state) = locked_state.as_ref() else {
+            return;
+        };
+        (
+            state.engine,
+            state.pattern_buffer,
+            state.sample_buffer,
+            state.matches_buffer,
+            state.windows[2],
+        )
+    };
+
+    let pattern = Buffer::from(pattern_buffer)
+        .get_lines(0..1, false)
+        .ok()
+        .and_then(|mut lines| lines.next())
+        .map(|line| line.to_string())
+        .unwrap_or_default();
+
+    let mut sample_buffer = Buffer::from(sample_buffer);
+    let lines = sample_buffer
+        .get_lines(.., false)
+        .map(|lines| lines.map(|line| line.to_string()).collect::<Vec<String>>())
+        .unwrap_or_default();
+
+    let namespace_id = create_namespace(REGEX_PLAYGROUND_NAMESPACE);
+    let _ = sample_buffer.clear_namespace(namespace_id, ..);
+
+    let result = if pattern.is_empty() {
+        Ok(Vec::new())
+    } else {
+        match engine {
+            RegexEngine::Vim => find_vim_matches(&pattern, &lines),
+            RegexEngine::Rust => find_rust_matches(&pattern, &lines),
+        }
+    };
+
+    let (title, match_lines) = match result {
+        Ok(matches) => {
+            for regex_match in matches.iter().filter(|m| m.start != m.end) {
+                let _ = sample_buffer.set_extmark(
+                    namespace_id,
+                    regex_match.start.0,
+                    regex_match.start.1,
+                    &SetExtmarkOpts::builder()
+                        .end_line(regex_match.end.0)
+                        .end_col(regex_match.end.1)
+                        .hl_group("Search")
+                        .build(),
+                );
+            }
+
+            let more = if matches.len() >= REGEX_PLAYGROUND_MAX_MATCHES {
+                "+"
+            } else {
+                ""
+            };
+            (
+                format!(" Matches: {}{more} ", matches.len()),
+                matches
+                    .iter()
+                    .map(render_match_line)
+                    .collect::<Vec<String>>(),
+            )
+        }
+        Err(error) => (" Invalid pattern ".to_string(), vec![error]),
+    };
+
+    let mut matches_buffer = Buffer::from(matches_buffer);
+    let opts = OptionOpts::builder().buffer(matches_buffer.clone()).build();
+    let _ = set_option_value("modifiable", true, &opts);
+    let _ = matches_buffer.set_lines(.., true, match_lines);
+    let _ = set_option_value("modifiable", false, &opts);
+
+    set_window_title(matches_window, &title);
+}
+
+///
+/// Close all floats of the playground
+///
+fn close_playground() {
+    let Some(state) = REGEX_PLAYGROUND_STATE.lock().unwrap().take() else {
+        return;
+    };
+
+    for attach_id in state.attach_ids {
+        buf_detach(attach_id);
+    }
+
+    for window_handle in state.windows {
+        let window = Window::from(window_handle);
+        if window.is_valid() {
+            let _ = window.close(true);
+        }
+    }
+}
+
+///
+/// Toggle the engine between the Vim and Rust regex
+///
+fn toggle_engine() {
+    let (engine, pattern_window) = {
+        let mut locked_state = REGEX_PLAYGROUND_STATE.lock().unwrap();
+        let Some(state) = locked_state.as_mut() else {
+            return;
+        };
+        state.engine = match state.engine {
+            RegexEngine::Vim => RegexEngine::Rust,
+            RegexEngine::Rust => RegexEngine::Vim,
+        };
+        (state.engine, state.windows[0])
+    };
+
+    set_window_title(pattern_window, &format!(" Pattern ({}) ", engine.name()));
+    update_playground();
+}
+
+///
+/// Focus the next (or previous if `step` is `-1`) float
+///
+fn focus_next_window(step: isize) {
+    let windows = match REGEX_PLAYGROUND_STATE.lock().unwrap().as_ref() {
+        Some(state) => state.windows.clone(),
+        None => return,
+    };
+
+    let current = Window::current().handle();
+    let index = windows
+        .iter()
+        .position(|handle| *handle == current)
+        .unwrap_or(0);
+    let next = (index as isize + step).rem_euclid(windows.len() as isize) as usize;
+
+    let _ = set_current_win(&Window::from(windows[next]));
+}
+
+///
+/// `<CR>` in the pattern input: apply the pattern by the engine
+///
+fn apply_pattern() {
+    let (engine, pattern_buffer) = match REGEX_PLAYGROUND_STATE.lock().unwrap().as_ref() {
+        Some(state) => (state.engine, state.pattern_buffer),
+        None => return,
+    };
+
+    let pattern = Buffer::from(pattern_buffer)
+        .get_lines(0..1, false)
+        .ok()
+        .and_then(|mut lines| lines.next())
+        .map(|line| line.to_string())
+        .unwrap_or_default();
+
+    close_playground();
+    let _ = command("stopinsert");
+
+    if pattern.is_empty() {
+        return;
+    }
+
+    match engine {
+        RegexEngine::Vim => {
+            set_register('/', &RegisterContent::charwise(&pattern));
+            let _ = set_option_value("hlsearch", true, &OptionOpts::builder().build());
+            echo_message(
+                "regex_playground",
+                &format!("Search pattern: {pattern}, ':%s//{{replacement}}/g' to replace."),
+                EchoLevel::Success,
+            );
+        }
+        RegexEngine::Rust => {
+            //
+            // Prompt for the replacement after the floats are closed
+            //
+            nvim::schedule(move |_| {
+                workspace_rename_pattern(&pattern, false);
+            });
+        }
+    }
+}
+
+///
+/// Set the buffer-local keybindings of the playground buffer
+///
+fn set_playground_keybindings(buffer: &mut Buffer, is_pattern_buffer: bool) {
+    let mut key_bindings: Vec<(Mode, &str, &str, Box<dyn Fn()>)> = vec![
+        (
+            Mode::Normal,
+            "<C-t>",
+            "Regex playground: Toggle the Vim/Rust regex",
+            Box::new(toggle_engine),
+        ),
+        (
+            Mode::Normal,
+            "<Tab>",
+            "Regex playground: Focus the next float",
+            Box::new(|| focus_next_window(1)),
+        ),
+        (
+            Mode::Normal,
+            "<S-Tab>",
+            "Regex playground: Focus the previous float",
+            Box::new(|| focus_next_window(-1)),
+        ),
+        (
+            Mode::Normal,
+            "q",
+            "Regex playground: Close",
+            Box::new(close_playground),
+        ),
+        (
+            Mode::Normal,
+            "<ESC>",
+            "Regex playground: Close",
+            Box::new(close_playground),
+        ),
+    ];
+
+    if is_pattern_buffer {
+        let pattern_key_bindings: Vec<(Mode, &str, &str, Box<dyn Fn()>)> = vec![
+            (
+                Mode::Insert,
+                "<C-t>",
+                "Regex playground: Toggle the Vim/Rust regex",
+                Box::new(toggle_engine),
+            ),
+            (
+                Mode::Normal,
+                "<CR>",
+                "Regex playground: Apply the pattern",
+                Box::new(apply_pattern),
+            ),
+            (
+                Mode::Insert,
+                "<CR>",
+                "Regex playground: Apply the pattern",
+                Box::new(apply_pattern),
+            ),
+        ];
+        key_bindings.extend(pattern_key_bindings);
+    }
+
+    for bindings in key_bindings {
+        let _ = buffer.set_keymap(
+            bindings.0,
+            bindings.1,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(bindings.2)
+                .callback(move |_| {
+                    bindings.3();
+                    ()
+                })
+                .silent(true)
+                .build(),
+        );
+    }
+}
+
+///
+/// Open the float of the playground buffer
+///
+fn open_playground_window(
+    buffer: &Buffer,
+    title: &str,
+    (row, col): (u32, u32),
+    (width, height): (u32, u32),
+) -> Option<WinHandle> {
+    let config = WindowConfig::builder()
+        .relative(WindowRelativeTo::Editor)
+        .width(width)
+        .height(height)
+        .row(row)
+        .col(col)
+        .border(WindowBorder::Rounded)
+        .title(WindowTitle::SimpleString(title.into()))
+        .title_pos(WindowTitlePosition::Center)
+        .build();
+
+    open_win(buffer, false, &config)
+        .ok()
+        .map(|window| window.handle())
+}
+
+///
+/// Open the playground with the sample lines and the initial pattern
+///
+fn open_playground(sample_lines: Vec<String>, pattern: &str) {
+    close_playground();
+
+    let mut buffers = Vec::with_capacity(3);
+    for _ in 0..3 {
+        let Ok(buffer) = create_buf(false, true) else {
+            return;
+        };
+        let _ = set_option_value(
+            "bufhidden",
+            "wipe",
+            &OptionOpts::builder().buffer(buffer.clone()).build(),
+        );
+        buffers.push(buffer);
+    }
+    let (mut pattern_buffer, mut sample_buffer, mut matches_buffer) =
+        (buffers[0].clone(), buffers[1].clone(), buffers[2].clone());
+
+    let _ = pattern_buffer.set_lines(.., true, vec![pattern]);
+    let _ = sample_buffer.set_lines(.., true, sample_lines);
+    let _ = set_option_value(
+        "modifiable",
+        false,
+        &OptionOpts::builder().buffer(matches_buffer.clone()).build(),
+    );
+
+    //
+    // Stack the floats in the center: 1 line pattern, the sample and the match list, each one
+    // has 2 border lines.
+    //
+    let screen_size = get_screen_size();
+    let width = ((screen_size.width as f32 * 0.7).floor() as u32).max(20);
+    let total_height = ((screen_size.height as f32 * 0.7).floor() as u32).max(12);
+    let matches_height = (total_height / 3).max(3);
+    let sample_height = total_height.saturating_sub(matches_height + 1).max(3);
+    let col = screen_size.width.saturating_sub(width + 2) / 2;
+    let mut row = screen_size.height.saturating_sub(total_height + 6) / 2;
+
+    let mut windows = Vec::with_capacity(3);
+    for (buffer, title, height) in [
+        (&pattern_buffer, " Pattern (Vim) ".to_string(), 1),
+        (&sample_buffer, " Sample text ".to_string(), sample_height),
+        (&matches_buffer, " Matches ".to_string(), matches_height),
+    ] {
+        match open_playground_window(buffer, &title, (row, col), (width, height)) {
+            Some(window_handle) => windows.push(window_handle),
+            None => {
+                for window_handle in windows {
+                    let _ = Window::from(window_handle).close(true);
+                }
+                return;
+            }
+        }
+        row += height + 2;
+    }
+
+    //
+    // Update on every change of the pattern and the sample text
+    //
+    let attach_ids = [&pattern_buffer, &sample_buffer]
+        .into_iter()
+        .filter_map(|buffer| buf_attach(buffer, |_: BufLinesEvent| update_playground()))
+        .collect::<Vec<u64>>();
+
+    *REGEX_PLAYGROUND_STATE.lock().unwrap() = Some(RegexPlaygroundState {
+        engine: RegexEngine::Vim,
+        pattern_buffer: pattern_buffer.handle(),
+        sample_buffer: sample_buffer.handle(),
+        matches_buffer: matches_buffer.handle(),
+        windows: windows.clone(),
+        attach_ids,
+    });
+
+    set_playground_keybindings(&mut pattern_buffer, true);
+    set_playground_keybindings(&mut sample_buffer, false);
+    set_playground_keybindings(&mut matches_buffer, false);
+
+    //
+    // Close all floats if any of them is closed by `:q` or `<C-w>c`
+    //
+    let group = create_augroup(
+        "custom-regex-playground-group",
+        &CreateAugroupOpts::builder().clear(true).build(),
+    )
+    .unwrap();
+    for buffer in [&pattern_buffer, &sample_buffer, &matches_buffer] {
+        let _ =
+            create_autocmd(
+                vec!["BufWipeout"],
+                &CreateAutocmdOpts::builder()
+                    .group(group)
+                    .buffer(buffer.clone())
+                    .callback(|args: AutocmdCallbackArgs| {
+                        //
+                        // Not allowed to close the windows in the `BufWipeout` autocmd. Skip it if
+                        // the playground is already closed (or reopened) at that moment.
+                        //
+                        let buffer_handle = args.buffer.handle();
+                        nvim::schedule(move |_| {
+                            let is_open =
+                                REGEX_PLAYGROUND_STATE.lock().unwrap().as_ref().is_some_and(
+                                    |state| {
+                                        [
+                                            state.pattern_buffer,
+                                            state.sample_buffer,
+                                            state.matches_buffer,
+                                        ]
+                                        .contains(&buffer_handle)
+                                    },
+                                );
+                            if is_open {
+                                close_playground();
+                            }
+                        });
+
+                        //
+                        // Return `true` to delete the autocommand (means only run once)!!!
+                        //
+                        true
+                    })
+                    .build(),
+            );
+    }
+
+    update_playground();
+
+    let _ = set_current_win(&Window::from(windows[0]));
+    let _ = command("startinsert!");
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_user_command(
+        "RegexTest",
+        |args: CommandArgs| {
+            //
+            // The range lines, or the visible lines of the current window
+            //
+            let (line1, line2) = if args.range > 0 {
+                (args.line1, args.line2)
+            } else {
+                (
+                    call_function::<_, i64>("line", ("w0",)).unwrap_or(1) as usize,
+                    call_function::<_, i64>("line", ("w$",)).unwrap_or(1) as usize,
+                )
+            };
+
+            let sample_lines = Buffer::current()
+                .get_lines(line1.saturating_sub(1)..line2, false)
+                .map(|lines| lines.map(|line| line.to_string()).collect::<Vec<String>>())
+                .unwrap_or_default();
+
+            open_playground(sample_lines, args.args.as_deref().unwrap_or_default());
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Regex playground with the live match highlights (Vim/Rust regex)")
+            .range(CommandRange::CurrentLine)
+            .nargs(CommandNArgs::Any)
+            .build(),
+    );
+}
+
+use crate::{
+    extended_api::{
+        BufLinesEvent, EchoLevel, RegisterContent, buf_attach, buf_detach, echo_message, exec_lua,
+        set_register,
+    },
+    picker::get_screen_size,
+    workspace_rename::workspace_rename_pattern,
+};
+
+use regex::Regex;
+
+use std::sync::{LazyLock, Mutex};
+
+use nvim_oxi::{
+    self as nvim, Array, BufHandle, WinHandle,
+    api::{
+        Buffer, Window, call_function, command, create_augroup, create_autocmd, create_buf,
+        create_namespace, create_user_command, open_win,
+        opts::{
+            CreateAugroupOpts, CreateAutocmdOpts, CreateCommandOpts, OptionOpts, SetExtmarkOpts,
+            SetKeymapOpts,
+        },
+        set_current_win, set_option_value,
+        types::{
+            AutocmdCallbackArgs, CommandArgs, CommandNArgs, CommandRange, Mode, WindowBorder,
+            WindowConfig, WindowRelativeTo, WindowTitle, WindowTitlePosition,
+        },
+    },
+};
@@ -0,0 +1,388 @@
+//! The register viewer:
+//!
+//! - `<leader>"` or `:PickerRegisters`: Pick the non-empty register (with the type and the
+//!   preview of its content), the selected one is pasted after the cursor.
+//! - `<leader>'` or `:PickerRegistersEdit`: Pick the register and edit its content in the popup
+//!   first:
+//!
+//!   `<C-s>` (or `<CR>` in `NORMAL` mode): Save the content back to the register and paste it
+//!   `<Esc>`/`q` in `NORMAL` mode: Cancel
+//!
+//! The read-only register (e.g. `.`, `:`) can't be changed, the edited content goes to the
+//! unnamed register instead.
+//!
+
+///
+/// The registers to show: the unnamed, the yank/delete history, the named ones, and then the
+/// special ones.
+///
+const REGISTER_NAMES: &'static str = "\"0123456789abcdefghijklmnopqrstuvwxyz-+*/:.";
+
+///
+/// The max char count of the preview, the longer content is truncated with `…`
+///
+const REGISTER_PREVIEW_MAX_CHARS: usize = 80;
+
+///
+/// The register being edited in the popup and the window to paste into
+///
+#[derive(Debug, Clone, Copy)]
+struct RegisterEdit {
+    name: char,
+    regtype: RegisterType,
+    popup: PopupWindow,
+    target_window: WinHandle,
+}
+
+///
+/// Private module-scope state
+///
+static REGISTER_EDIT_STATE: LazyLock<Mutex<Option<RegisterEdit>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+///
+/// The register type label, e.g. `block 12` for the blockwise register with the width
+///
+fn regtype_label(regtype: RegisterType) -> String {
+    match regtype {
+        RegisterType::Charwise => "char".to_string(),
+        RegisterType::Linewise => "line".to_string(),
+        RegisterType::Blockwise(Some(width)) => format!("block {width}"),
+        RegisterType::Blockwise(None) => "block".to_string(),
+    }
+}
+
+///
+/// Show the multi-line content in one line (the `\n` is shown as `↵`), the long content is
+/// truncated with the line count.
+///
+fn get_register_preview(content: &RegisterContent) -> String {
+    let text = content.text();
+    let one_line_text = text
+        .trim_end_matches('\n')
+        .replace('\n', "↵")
+        .replace('\t', "  ");
+
+    if one_line_text.chars().count() <= REGISTER_PREVIEW_MAX_CHARS {
+        return one_line_text;
+    }
+
+    let truncated = one_line_text
+        .chars()
+        .take(REGISTER_PREVIEW_MAX_CHARS)
+        .collect::<String>();
+    if content.lines.len() > 1 {
+        format!("{truncated}… ({} lines)", content.lines.len())
+    } else {
+        format!("{truncated}… ({} chars)", text.chars().count())
+    }
+}
+
+///
+/// The non-empty registers for the picker, the register name is the item data.
+///
+fn get_register_picker_items() -> Vec<PickerItem> {
+    REGISTER_NAMES
+        .chars()
+        .filter_map(|name| {
+            let content = get_register(name)?;
+            if content.text().trim().is_empty() {
+                return None;
+            }
+
+            Some(PickerItem {
+                columns: vec![
+                    PickerItemColumn::new(&format!("\"{name}"), Some("Special")),
+                    PickerItemColumn::new(&regtype_label(content.regtype), Some("Comment")),
+                    PickerItemColumn::new(&get_register_preview(&content), None),
+                ],
+                icon: None,
+                data: name.to_string(),
+            })
+        })
+        .collect()
+}
+
+///
+/// Paste the register after the cursor
+///
+fn paste_register(name: char) {
+    let _ = command(&format!("normal! \"{name}p"));
+}
+
+///
+/// Paste the selected register after the picker is gone and back to `NORMAL` mode
+///
+fn register_selected_callback(selected_register: String) {
+    if let Some(name) = selected_register.chars().next() {
+        nvim::schedule(move |_| {
+            paste_register(name);
+            ()
+        });
+    }
+}
+
+///
+/// Open the popup to edit the selected register after the picker is gone
+///
+fn register_edit_selected_callback(selected_register: String) {
+    if let Some(name) = selected_register.chars().next() {
+        nvim::schedule(move |_| {
+            open_register_edit_popup(name);
+            ()
+        });
+    }
+}
+
+///
+/// `<Esc>`/`q`: Close the popup and back to the target window
+///
+fn close_register_edit_popup() -> Option<RegisterEdit> {
+    //
+    // Release the lock before closing the window
+    //
+    let register_edit = REGISTER_EDIT_STATE.lock().unwrap().take()?;
+    register_edit.popup.close();
+
+    let target_window = Window::from(register_edit.target_window);
+    if target_window.is_valid() {
+        let _ = set_current_win(&target_window);
+    }
+
+    Some(register_edit)
+}
+
+///
+/// `<C-s>`: Save the edited content back to the register and paste it
+///
+fn save_register_edit() {
+    let lines = REGISTER_EDIT_STATE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|register_edit| {
+            register_edit
+                .popup
+                .buffer()
+                .get_lines(.., false)
+                .ok()
+                .map(|lines| lines.map(|line| line.to_string()).collect::<Vec<String>>())
+        });
+    let Some(lines) = lines else {
+        return;
+    };
+
+    let _ = command("stopinsert");
+    let Some(register_edit) = close_register_edit_popup() else {
+        return;
+    };
+
+    let content = RegisterContent {
+        lines,
+        regtype: register_edit.regtype,
+    };
+
+    let name = if set_register(register_edit.name, &content) {
+        register_edit.name
+    } else {
+        notify(
+            "registers",
+            &format!(
+                "Register '{}' is read-only, the content goes to the unnamed register.",
+                register_edit.name
+            ),
+            EchoLevel::Warning,
+        );
+        set_register('"', &content);
+        '"'
+    };
+
+    paste_register(name);
+}
+
+///
+/// Open the popup with the register content
+///
+fn open_register_edit_popup(name: char) {
+    let Some(content) = get_register(name) else {
+        notify(
+            "registers",
+            &format!("Register '{name}' is empty."),
+            EchoLevel::Info,
+        );
+        return;
+    };
+
+    if REGISTER_EDIT_STATE.lock().unwrap().is_some() {
+        close_register_edit_popup();
+    }
+    let target_window = Window::current().handle();
+
+    let Ok(mut buffer) = create_buf(false, true) else {
+        return;
+    };
+    let _ = set_option_value(
+        "bufhidden",
+        "wipe",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    );
+    let _ = buffer.set_lines(.., true, content.lines.iter().map(|line| line.as_str()));
+
+    let Some(popup) = create_popup_window(&PopupWindowOptions {
+        buffer: Some(buffer.handle()),
+        window_width_ratio: Some(0.5),
+        title: Some(format!(
+            " Register \"{name} ({}) ",
+            regtype_label(content.regtype)
+        )),
+        footer: Some(" <C-s>: save and paste, <Esc>: cancel ".to_string()),
+        min_height: Some(5),
+        ..Default::default()
+    }) else {
+        return;
+    };
+
+    *REGISTER_EDIT_STATE.lock().unwrap() = Some(RegisterEdit {
+        name,
+        regtype: content.regtype,
+        popup,
+        target_window,
+    });
+
+    let edit_keybindings: Vec<(Mode, &str, &str, fn())> = vec![
+        (
+            Mode::Insert,
+            "<C-s>",
+            "Register: save and paste",
+            save_register_edit,
+        ),
+        (
+            Mode::Normal,
+            "<C-s>",
+            "Register: save and paste",
+            save_register_edit,
+        ),
+        (
+            Mode::Normal,
+            "<CR>",
+            "Register: save and paste",
+            save_register_edit,
+        ),
+        (Mode::Normal, "<Esc>", "Register: cancel", || {
+            close_register_edit_popup();
+        }),
+        (Mode::Normal, "q", "Register: cancel", || {
+            close_register_edit_popup();
+        }),
+    ];
+
+    for (mode, key, desc, callback) in edit_keybindings {
+        let _ = buffer.set_keymap(
+            mode,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(desc)
+                .callback(move |_| {
+                    callback();
+                    ()
+                })
+                .silent(true)
+                .build(),
+        );
+    }
+
+    let _ = set_current_win(&popup.window());
+}
+
+///
+///
+///
+pub fn setup() {
+    let register_keybindings: Vec<(&str, &str, &str)> = vec![
+        (
+            "<leader>\"",
+            "'<leader>\"': Pick the register to paste",
+            "registers",
+        ),
+        (
+            "<leader>'",
+            "'<leader>'': Pick the register to edit and paste",
+            "registers_edit",
+        ),
+    ];
+
+    for (key, desc, source) in register_keybindings {
+        let _ = set_keymap(
+            Mode::Normal,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(desc)
+                .silent(true)
+                .callback(move |_| {
+                    open_registered_picker(source);
+                    ()
+                })
+                .build(),
+        );
+    }
+
+    register_user_command(
+        "PickerRegisters",
+        UserCommandSpec {
+            desc: "Pick the register to paste",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| open_registered_picker("registers"),
+    );
+
+    register_user_command(
+        "PickerRegistersEdit",
+        UserCommandSpec {
+            desc: "Pick the register to edit and paste",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| open_registered_picker("registers_edit"),
+    );
+
+    //
+    // Contribute the registers to the picker registry (`<leader>pp`)
+    //
+    register_picker_source(
+        "registers",
+        "Registers",
+        get_register_picker_items,
+        register_selected_callback,
+    );
+    register_picker_source(
+        "registers_edit",
+        "Registers (edit before pasting)",
+        get_register_picker_items,
+        register_edit_selected_callback,
+    );
+}
+
+use crate::{
+    extended_api::{
+        EchoLevel, RegisterContent, RegisterType, UserCommandArgs, UserCommandSpec, get_register,
+        register_user_command, set_register,
+    },
+    notify::notify,
+    picker::{
+        PickerItem, PickerItemColumn, PopupWindow, PopupWindowOptions, create_popup_window,
+        open_registered_picker, register_picker_source,
+    },
+};
+
+use std::sync::{LazyLock, Mutex};
+
+use nvim_oxi::{
+    self as nvim, WinHandle,
+    api::{
+        Window, command, create_buf,
+        opts::{OptionOpts, SetKeymapOpts},
+        set_current_win, set_keymap, set_option_value,
+        types::Mode,
+    },
+};
@@ -0,0 +1,237 @@
+//! Protection against opening Neovim twice in the same project (which causes the swapfile
+//! conflicts):
+//!
+//! - Every instance registers its server address (`v:servername`) for the project (the current
+//!   working directory) under `stdpath("data")/my_neovim_configuration/remote_sessions`.
+//!
+//! - On the second launch in the same project, if the registered server is still alive, it
+//!   offers to open the given files in the existing instance (`nvim --server {addr} --remote`)
+//!   and quit the current one. It's skipped in the headless mode (no UI attached), and when any
+//!   given file is outside the project or in the `.git` directory (e.g. `git commit` opens
+//!   `COMMIT_EDITMSG` by `$EDITOR`, git waits for this instance to exit).
+//!
+//! - The registration is removed when the registered instance exits.
+//!
+
+///
+/// Private module-scope state: the registered session file of the current instance, `None`
+/// means the current instance doesn't own the project session.
+///
+static REMOTE_SESSION_STATE: LazyLock<Mutex<Option<PathBuf>>> = LazyLock::new(|| Mutex::new(None));
+
+///
+/// Get back the session file path of the given project directory, e.g.
+/// `/home/wison/my-project` -> `remote_sessions/%home%wison%my-project`
+///
+fn get_session_file(project_dir: &str) -> Option<PathBuf> {
+    let mut session_file = get_data_dir("remote_sessions")?;
+    session_file.push(project_dir.replace(std::path::MAIN_SEPARATOR, "%"));
+    Some(session_file)
+}
+
+///
+/// Get back the server address of the current instance
+///
+fn get_server_name() -> Option<String> {
+    match get_vvar::<String>("servername") {
+        Ok(name) if !name.is_empty() => Some(name),
+        _ => None,
+    }
+}
+
+///
+/// Whether the given server address is still accepting connections or not, it supports both
+/// the unix domain socket (the default) and the TCP address (`--listen 127.0.0.1:6666`).
+///
+fn is_server_alive(server_name: &str) -> bool {
+    if Path::new(server_name).is_absolute() {
+        UnixStream::connect(server_name).is_ok()
+    } else {
+        TcpStream::connect(server_name).is_ok()
+    }
+}
+
+///
+/// Open the given files in the remote instance by `nvim --server {addr} --remote {files}`
+///
+fn open_files_in_remote(server_name: &str, files: &[String]) -> bool {
+    let mut remote_command = std::process::Command::new("nvim");
+    remote_command.args(["--server", server_name, "--remote"]);
+    remote_command.args(files);
+
+    match remote_command.status() {
+        Ok(status) => status.success(),
+        Err(_) => false,
+    }
+}
+
+///
+/// Get back the full path of the files given in the command line
+///
+fn get_argument_files() -> Vec<String> {
+    let Ok(arg_list) = call_function::<_, Array>("argv", ()) else {
+        return Vec::new();
+    };
+
+    arg_list
+        .into_iter()
+        .filter_map(|arg| NvimString::from_object(arg).ok())
+        .filter_map(|arg| call_function::<_, String>("fnamemodify", (arg, ":p")).ok())
+        .collect()
+}
+
+///
+/// Whether the files can be opened in the other instance of the project: all of them are in the
+/// project, and none of them is the git temp file (it's in the `.git` directory).
+///
+fn is_project_files(project_dir: &str, files: &[String]) -> bool {
+    files.iter().all(|file| {
+        let file_path = Path::new(file);
+        file_path.starts_with(project_dir)
+            && !file_path
+                .components()
+                .any(|component| component.as_os_str() == ".git")
+    })
+}
+
+///
+/// Register the current instance as the project session
+///
+fn register_session(session_file: PathBuf, server_name: &str) {
+    if std::fs::write(&session_file, server_name).is_ok() {
+        *REMOTE_SESSION_STATE.lock().unwrap() = Some(session_file);
+    }
+}
+
+///
+/// Remove the session file if it's registered by the current instance
+///
+fn unregister_session() {
+    let Some(session_file) = REMOTE_SESSION_STATE.lock().unwrap().take() else {
+        return;
+    };
+
+    let registered_server = std::fs::read_to_string(&session_file).unwrap_or_default();
+    if Some(registered_server.trim().to_owned()) == get_server_name() {
+        let _ = std::fs::remove_file(&session_file);
+    }
+}
+
+///
+/// Detect the existing instance of the current project. Return `true` if the files have been
+/// opened in the existing instance (the current one should quit).
+///
+fn check_existing_session() -> bool {
+    const LOGGER_PREFIX: &'static str = "[ remote_session ]";
+
+    //
+    // The headless instance (e.g. the script) neither asks nor owns the project session.
+    //
+    if list_uis().is_empty() {
+        return false;
+    }
+
+    let Some(server_name) = get_server_name() else {
+        return false;
+    };
+
+    let Ok(project_dir) = call_function::<_, String>("getcwd", ()) else {
+        return false;
+    };
+
+    let Some(session_file) = get_session_file(&project_dir) else {
+        return false;
+    };
+
+    let registered_server = std::fs::read_to_string(&session_file)
+        .unwrap_or_default()
+        .trim()
+        .to_owned();
+
+    if registered_server.is_empty()
+        || registered_server == server_name
+        || !is_server_alive(&registered_server)
+    {
+        register_session(session_file, &server_name);
+        return false;
+    }
+
+    let files = get_argument_files();
+    if files.is_empty() {
+        nvim::print!(
+            "{LOGGER_PREFIX} This project is already opened in another Neovim: {registered_server}"
+        );
+        return false;
+    }
+
+    if !is_project_files(&project_dir, &files) {
+        return false;
+    }
+
+    let prompt_text = format!(
+        "{LOGGER_PREFIX} This project is already opened in another Neovim, open the file(s) there? (y/n): "
+    );
+
+    match prompt(&prompt_text, "", None) {
+        Some(confirm) if confirm == "y" || confirm == "Y" => {
+            open_files_in_remote(&registered_server, &files)
+        }
+        _ => false,
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    //
+    // It runs when the plugin is loaded (before the argument files are loaded into buffers), so
+    // the swapfile conflict doesn't happen at all.
+    //
+    if check_existing_session() {
+        let _ = command("qall!");
+        return;
+    }
+
+    let _ = create_autocmd(
+        vec!["VimLeavePre"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-remote-session-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|_| {
+                unregister_session();
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+use crate::{
+    extended_api::{list_uis, prompt},
+    utils::get_data_dir,
+};
+
+use std::{
+    net::TcpStream,
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim, Array, String as NvimString,
+    api::{
+        call_function, command, create_augroup, create_autocmd, get_vvar,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts},
+    },
+    conversion::FromObject,
+};
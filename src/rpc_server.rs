@@ -0,0 +1,173 @@
+//! Opt-in RPC server for the editor automation from the shell or external scripts.
+//!
+//! - `:RpcServerStart [address]`: Listen on the given named pipe or TCP address (e.g.
+//!   `127.0.0.1:6666`), the default one is `stdpath("run")/my_neovim_configuration.{pid}.sock`.
+//!   Set the `MY_NVIM_RPC_SERVER` environment variable (to the address) to start it automatically.
+//! - `:RpcServerStop`: Stop the server started by `:RpcServerStart`.
+//!
+//! It's the Neovim builtin msgpack-rpc server, the plugin functionalities are exposed by the
+//! `:Rpc {method} [args]` command, call it by the `nvim_command` RPC request, e.g.:
+//!
+//! ```fish
+//! nvim --server /tmp/nvim.sock --remote-send '<C-\><C-n>:Rpc dired ~/temp<CR>'
+//! ```
+//!
+//! Methods:
+//!
+//! - `dired {path}`: Open dired at the given path.
+//! - `project_command [cmd]`: Run the given command (or the default one) in the current project.
+//! - `picker {source}`: Open the registered picker source, e.g. `picker project_commands`.
+//! - `action {name}`: Run the named action.
+//!
+
+///
+/// The environment variable to start the RPC server automatically
+///
+const RPC_SERVER_ENV_NAME: &'static str = "MY_NVIM_RPC_SERVER";
+
+///
+/// Private module-scope state: the address of the started RPC server
+///
+static RPC_SERVER_STATE: LazyLock<Mutex<Option<String>>> = LazyLock::new(|| Mutex::new(None));
+
+///
+/// Get back the default server address
+///
+fn get_default_server_address() -> Option<String> {
+    let run_dir = call_function::<_, String>("stdpath", ("run",)).ok()?;
+    Some(format!(
+        "{run_dir}/my_neovim_configuration.{}.sock",
+        std::process::id()
+    ))
+}
+
+///
+/// Start the RPC server on the given address (or the default one)
+///
+fn start_rpc_server(address: Option<&str>) {
+    const LOGGER_PREFIX: &'static str = "[ rpc_server - start_rpc_server ]";
+
+    let mut locked_state = RPC_SERVER_STATE.lock().unwrap();
+    if let Some(started_address) = locked_state.as_deref() {
+        nvim::print!("{LOGGER_PREFIX} Already listening on: {started_address}");
+        return;
+    }
+
+    let Some(address) = address
+        .map(|addr| addr.to_owned())
+        .or_else(get_default_server_address)
+    else {
+        return;
+    };
+
+    match call_function::<_, String>("serverstart", (address.as_str(),)) {
+        Ok(started_address) => {
+            nvim::print!("{LOGGER_PREFIX} Listening on: {started_address}");
+            *locked_state = Some(started_address);
+        }
+        Err(error) => nvim::print!("{LOGGER_PREFIX} Failed to listen on '{address}': {error:?}"),
+    }
+}
+
+///
+/// Stop the RPC server started by `start_rpc_server()`
+///
+fn stop_rpc_server() {
+    const LOGGER_PREFIX: &'static str = "[ rpc_server - stop_rpc_server ]";
+
+    let Some(address) = RPC_SERVER_STATE.lock().unwrap().take() else {
+        nvim::print!("{LOGGER_PREFIX} Not started.");
+        return;
+    };
+
+    let _ = call_function::<_, bool>("serverstop", (address.as_str(),));
+    nvim::print!("{LOGGER_PREFIX} Stopped: {address}");
+}
+
+///
+/// Dispatch the `:Rpc {method} [args]` call to the plugin functionality
+///
+fn dispatch_rpc_method(method: &str, args: &str) {
+    const LOGGER_PREFIX: &'static str = "[ rpc_server - dispatch_rpc_method ]";
+
+    match method {
+        "dired" => {
+            let path = match call_function::<_, String>("expand", (args,)) {
+                Ok(expanded_path) if !expanded_path.is_empty() => expanded_path,
+                _ => args.to_owned(),
+            };
+            my_dired::open_directory(&path);
+        }
+        "project_command" => {
+            if !project_command::run_command(args) {
+                nvim::print!("{LOGGER_PREFIX} No project command to run.");
+            }
+        }
+        "picker" => open_registered_picker(args),
+        "action" => actions::run_action(args),
+        _ => nvim::print!("{LOGGER_PREFIX} Unknown method: '{method}'"),
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    if let Ok(address) = std::env::var(RPC_SERVER_ENV_NAME) {
+        start_rpc_server(if address.is_empty() {
+            None
+        } else {
+            Some(address.as_str())
+        });
+    }
+
+    let _ = create_user_command(
+        "RpcServerStart",
+        |args: CommandArgs| {
+            start_rpc_server(args.args.as_deref().filter(|addr| !addr.trim().is_empty()));
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Start the RPC server: ':RpcServerStart [address]'")
+            .nargs(CommandNArgs::ZeroOrOne)
+            .build(),
+    );
+
+    let _ = create_user_command(
+        "RpcServerStop",
+        |_: CommandArgs| {
+            stop_rpc_server();
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Stop the RPC server")
+            .build(),
+    );
+
+    let _ = create_user_command(
+        "Rpc",
+        |args: CommandArgs| {
+            let command_args = args.args.as_deref().unwrap_or_default().trim();
+            let (method, method_args) = command_args.split_once(' ').unwrap_or((command_args, ""));
+            dispatch_rpc_method(method, method_args.trim());
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Call the plugin functionality: ':Rpc {method} [args]'")
+            .nargs(CommandNArgs::OneOrMore)
+            .build(),
+    );
+}
+
+use crate::{actions, my_dired, picker::open_registered_picker, project_command};
+
+use std::sync::{LazyLock, Mutex};
+
+use nvim_oxi::{
+    self as nvim,
+    api::{
+        call_function, create_user_command,
+        opts::CreateCommandOpts,
+        types::{CommandArgs, CommandNArgs},
+    },
+};
@@ -0,0 +1,273 @@
+//! Named scratch buffers per filetype, e.g. markdown notes, lua playground, shell snippets:
+//!
+//! - `<leader>ss`: Pick a scratch kind to open (also in the picker registry: `<leader>pp`).
+//! - `:Scratch {name}`: Open the named scratch buffer.
+//!
+//! The scratch buffer is kept in the state (hidden when the window is closed), so reopening it
+//! returns the same contents. When the kind `persist` is `true`, the buffer is backed by the
+//! file `stdpath("data")/my_neovim_configuration/scratch/{name}.{extension}`, `:w` saves it
+//! (`BufWritePost`) and the content is loaded back after restarting Neovim.
+//!
+
+///
+/// Scratch buffer kind
+///
+#[derive(Debug, Clone)]
+struct ScratchKind {
+    name: &'static str,
+    filetype: &'static str,
+    extension: &'static str,
+    persist: bool,
+}
+
+///
+/// Define your scratch kinds here
+///
+const SCRATCH_KINDS: [ScratchKind; 3] = [
+    ScratchKind {
+        name: "notes",
+        filetype: "markdown",
+        extension: "md",
+        persist: true,
+    },
+    ScratchKind {
+        name: "lua",
+        filetype: "lua",
+        extension: "lua",
+        persist: false,
+    },
+    ScratchKind {
+        name: "shell",
+        filetype: "fish",
+        extension: "fish",
+        persist: false,
+    },
+];
+
+///
+/// Private module-scope state: the scratch buffer handle (by scratch name)
+///
+static SCRATCH_STATE: LazyLock<Mutex<HashMap<String, BufHandle>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+///
+/// Get back the persistent file path of the given scratch kind
+///
+fn get_scratch_file(kind: &ScratchKind) -> Option<PathBuf> {
+    let mut scratch_file = get_data_dir("scratch")?;
+    scratch_file.push(format!("{}.{}", kind.name, kind.extension));
+    Some(scratch_file)
+}
+
+///
+/// Create the scratch buffer of the given kind
+///
+fn create_scratch_buffer(kind: &ScratchKind) -> Option<Buffer> {
+    let scratch_file = if kind.persist {
+        get_scratch_file(kind)
+    } else {
+        None
+    };
+
+    let scratch_buffer = match &scratch_file {
+        //
+        // The file-backed buffer, load the saved content
+        //
+        Some(file) => {
+            let file_path = file.to_str()?;
+            let buffer_handle = call_function::<_, BufHandle>("bufadd", (file_path,)).ok()?;
+            let _ = call_function::<_, Object>("bufload", (buffer_handle,));
+            Buffer::from(buffer_handle)
+        }
+        None => {
+            let mut buffer = create_buf(false, true).ok()?;
+            let _ = buffer.set_name(format!("[Scratch {}]", kind.name));
+            buffer
+        }
+    };
+
+    let opts = OptionOpts::builder().buffer(scratch_buffer.clone()).build();
+    let _ = set_option_value("bufhidden", "hide", &opts);
+    let _ = set_option_value("swapfile", false, &opts);
+    let _ = set_option_value("filetype", kind.filetype, &opts);
+
+    if let Some(file) = scratch_file {
+        let name = kind.name;
+        let _ = create_autocmd(
+            vec!["BufWritePost"],
+            &CreateAutocmdOpts::builder()
+                .buffer(scratch_buffer.clone())
+                .callback(move |_| {
+                    nvim::print!("[ scratch ] '{name}' saved to: {}", file.display());
+
+                    //
+                    // Return `true` to delete the autocommand (means only run once)!!!
+                    //
+                    false
+                })
+                .build(),
+        );
+    }
+
+    Some(scratch_buffer)
+}
+
+///
+/// Open the named scratch buffer in a floating window, create it if it doesn't exist yet.
+///
+fn open_scratch(name: &str) {
+    let Some(kind) = SCRATCH_KINDS.iter().find(|kind| kind.name == name) else {
+        nvim::print!("[ scratch ] Unknown scratch: '{name}'");
+        return;
+    };
+
+    let existing_buffer = SCRATCH_STATE
+        .lock()
+        .unwrap()
+        .get(name)
+        .copied()
+        .filter(|handle| Buffer::from(*handle).is_valid());
+
+    let scratch_buffer = match existing_buffer {
+        Some(handle) => Buffer::from(handle),
+        None => {
+            let Some(buffer) = create_scratch_buffer(kind) else {
+                return;
+            };
+            SCRATCH_STATE
+                .lock()
+                .unwrap()
+                .insert(name.to_owned(), buffer.handle());
+            buffer
+        }
+    };
+
+    let _ = create_popup_window(&PopupWindowOptions {
+        border: WindowBorder::Rounded,
+        window_width_ratio: Some(0.7),
+        window_height_ratio: Some(0.7),
+        auto_width: false,
+        auto_height: false,
+        buffer: Some(scratch_buffer.handle()),
+        title: Some(format!(" Scratch: {} ", kind.name)),
+        ..Default::default()
+    });
+}
+
+///
+///
+///
+fn get_scratch_picker_items() -> Vec<PickerItem> {
+    SCRATCH_KINDS
+        .iter()
+        .map(|kind| PickerItem {
+            columns: vec![
+                PickerItemColumn::new(kind.name, Some("Function")),
+                PickerItemColumn::new(kind.filetype, Some("Comment")),
+                PickerItemColumn::new(if kind.persist { "persist" } else { "" }, Some("Special")),
+            ],
+            icon: None,
+            data: kind.name.to_string(),
+        })
+        .collect()
+}
+
+///
+/// Open the scratch buffer in the next event loop tick, as the picker windows are still
+/// closing at this moment.
+///
+fn scratch_selected_callback(selected_name: String) {
+    nvim::schedule(move |_| {
+        open_scratch(selected_name.trim());
+    });
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>ss",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("'<leader>ss': Open a scratch buffer")
+            .silent(true)
+            .callback(|_| {
+                let items = get_scratch_picker_items();
+                let _ = create_editable_picker_with_options(
+                    &mut EditablePickerOptions {
+                        title: "Scratch buffers".to_string(),
+                        window_opts: PopupWindowOptions {
+                            border: WindowBorder::Rounded,
+                            window_width_ratio: None,
+                            window_height_ratio: None,
+                            auto_width: true,
+                            auto_height: true,
+                            buffer: None,
+                            ..Default::default()
+                        },
+                        list: &items,
+                        sort: PickerSortStrategy::Frecency,
+                        source: Some("scratch".to_string()),
+                        sticky_filter: false,
+                        show_footer: false,
+                        initial_input: None,
+                        validator: None,
+                        on_delete: None,
+                        on_edit: None,
+                    },
+                    scratch_selected_callback,
+                );
+                ()
+            })
+            .build(),
+    );
+
+    let _ = create_user_command(
+        "Scratch",
+        |args: CommandArgs| {
+            open_scratch(args.args.as_deref().unwrap_or_default().trim());
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Open the named scratch buffer: ':Scratch {name}'")
+            .nargs(CommandNArgs::One)
+            .build(),
+    );
+
+    //
+    // Contribute the scratch kinds to the picker registry (`<leader>pp`)
+    //
+    register_picker_source(
+        "scratch",
+        "Scratch buffers",
+        get_scratch_picker_items,
+        scratch_selected_callback,
+    );
+}
+
+use crate::{
+    picker::{
+        EditablePickerOptions, PickerItem, PickerItemColumn, PickerSortStrategy,
+        PopupWindowOptions, create_editable_picker_with_options, create_popup_window,
+        register_picker_source,
+    },
+    utils::get_data_dir,
+};
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim, BufHandle, Object,
+    api::{
+        Buffer, call_function, create_autocmd, create_buf, create_user_command,
+        opts::{CreateAutocmdOpts, CreateCommandOpts, OptionOpts, SetKeymapOpts},
+        set_keymap, set_option_value,
+        types::{CommandArgs, CommandNArgs, Mode, WindowBorder},
+    },
+};
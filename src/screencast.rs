@@ -0,0 +1,369 @@
+//! Keystroke and command overlay for the screencasts and pairing sessions:
+//!
+//! - `:Screencast`: Toggle the float in the bottom-right corner, it shows the recent keystrokes
+//!   (the repeated key is shown as `j×3`) and the executed `:` commands and `/` searches.
+//!
+//! The keystrokes come from `vim.on_key` (bridged into Rust by `exec_lua()`), only the typed
+//! keys are shown, not the ones from the mappings. The float never gets the focus.
+//!
+
+///
+/// The namespace of the `vim.on_key` callback
+///
+const SCREENCAST_NAMESPACE: &'static str = "screencast";
+
+///
+/// The float width (without the border)
+///
+const SCREENCAST_WIDTH: usize = 36;
+
+///
+/// How many lines the float shows
+///
+const SCREENCAST_MAX_LINES: usize = 5;
+
+///
+/// Register the `vim.on_key` callback, the keys typed in the cmdline are skipped, as the whole
+/// command is recorded when leaving the cmdline.
+///
+const SCREENCAST_ON_KEY_LUA: &'static str = r#"local namespace_name, on_typed = ...
+vim.on_key(function(_, typed)
+    if typed ~= nil and typed ~= "" and vim.fn.mode() ~= "c" then
+        on_typed(vim.fn.keytrans(typed))
+    end
+end, vim.api.nvim_create_namespace(namespace_name))"#;
+
+///
+/// Private module-scope state
+///
+#[derive(Debug, Default)]
+struct ScreencastState {
+    is_enabled: bool,
+    window: Option<WinHandle>,
+
+    //
+    // The finished lines (the oldest first), and the keys with the repeat count of the current
+    // line.
+    //
+    lines: VecDeque<String>,
+    current_keys: Vec<(String, usize)>,
+
+    //
+    // The render is scheduled on the next event loop tick, the keys typed before that are
+    // rendered together.
+    //
+    is_render_pending: bool,
+}
+
+static SCREENCAST_STATE: LazyLock<Mutex<ScreencastState>> =
+    LazyLock::new(|| Mutex::new(ScreencastState::default()));
+
+///
+/// Render the keys of the line, e.g. `j×3 k <C-w> l`
+///
+fn render_keys(keys: &[(String, usize)]) -> String {
+    keys.iter()
+        .map(|(key, count)| {
+            if *count > 1 {
+                format!("{key}×{count}")
+            } else {
+                key.clone()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+///
+/// Move the current keys into the finished lines, and drop the oldest lines.
+///
+fn finish_key_line(state: &mut ScreencastState) {
+    if !state.current_keys.is_empty() {
+        let line = render_keys(&state.current_keys);
+        state.lines.push_back(line);
+        state.current_keys.clear();
+    }
+
+    while state.lines.len() >= SCREENCAST_MAX_LINES {
+        state.lines.pop_front();
+    }
+}
+
+///
+/// Render the float on the next event loop tick, as `vim.on_key` isn't allowed to change the
+/// buffers and windows.
+///
+fn schedule_render(state: &mut ScreencastState) {
+    if state.is_render_pending {
+        return;
+    }
+
+    state.is_render_pending = true;
+    nvim::schedule(|_| {
+        render_screencast_window();
+        ()
+    });
+}
+
+///
+/// Record the typed key
+///
+fn push_key(key: String) {
+    let mut locked_state = SCREENCAST_STATE.lock().unwrap();
+    if !locked_state.is_enabled {
+        return;
+    }
+
+    match locked_state.current_keys.last_mut() {
+        Some((last_key, count)) if *last_key == key => *count += 1,
+        _ => {
+            let line_width = display_width(&render_keys(&locked_state.current_keys));
+            if line_width + 1 + display_width(&key) > SCREENCAST_WIDTH {
+                finish_key_line(&mut locked_state);
+            }
+            locked_state.current_keys.push((key, 1));
+        }
+    }
+
+    schedule_render(&mut locked_state);
+}
+
+///
+/// Record the executed command (or the search), it's on its own line.
+///
+fn push_command(command_line: String) {
+    let mut locked_state = SCREENCAST_STATE.lock().unwrap();
+    if !locked_state.is_enabled {
+        return;
+    }
+
+    finish_key_line(&mut locked_state);
+    locked_state.lines.push_back(command_line);
+    schedule_render(&mut locked_state);
+}
+
+///
+/// Open the float (or move it into the current tab), and then fill the recent lines.
+///
+fn render_screencast_window() {
+    let (lines, window_handle) = {
+        let mut locked_state = SCREENCAST_STATE.lock().unwrap();
+        locked_state.is_render_pending = false;
+        if !locked_state.is_enabled {
+            return;
+        }
+
+        let mut lines = locked_state.lines.iter().cloned().collect::<Vec<String>>();
+        if !locked_state.current_keys.is_empty() {
+            lines.push(render_keys(&locked_state.current_keys));
+        }
+        (lines, locked_state.window)
+    };
+    if lines.is_empty() {
+        return;
+    }
+
+    //
+    // Keep the end of the long line, it's the latest part.
+    //
+    let lines = lines
+        .into_iter()
+        .map(|line| {
+            let chars = line.chars().collect::<Vec<char>>();
+            if chars.len() > SCREENCAST_WIDTH {
+                format!(
+                    "…{}",
+                    chars[chars.len() - SCREENCAST_WIDTH + 1..]
+                        .iter()
+                        .collect::<String>()
+                )
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<String>>();
+
+    //
+    // The float with `relative = "editor"` belongs to the tab, reopen it after switching the tab.
+    //
+    let current_tab = get_current_tabpage();
+    let window = window_handle.map(Window::from).filter(|window| {
+        window.is_valid()
+            && window
+                .get_tabpage()
+                .is_ok_and(|tabpage| tabpage == current_tab)
+    });
+    let window = match window {
+        Some(window) => window,
+        None => {
+            if let Some(old_window) = window_handle.map(Window::from)
+                && old_window.is_valid()
+            {
+                let _ = old_window.close(true);
+            }
+
+            let Some(window) = open_screencast_window() else {
+                return;
+            };
+            SCREENCAST_STATE.lock().unwrap().window = Some(window.handle());
+            window
+        }
+    };
+
+    let Ok(mut buffer) = window.get_buf() else {
+        return;
+    };
+    let _ = buffer.set_lines(.., false, lines.iter().map(|line| line.as_str()));
+
+    //
+    // Follow the screen size and the line count
+    //
+    let mut window = window;
+    let _ = window.set_config(&get_screencast_window_config(lines.len() as u32));
+}
+
+///
+/// The float config in the bottom-right corner, above the statusline and the cmdline
+///
+fn get_screencast_window_config(height: u32) -> WindowConfig {
+    let screen_size = get_screen_size();
+
+    WindowConfig::builder()
+        .relative(WindowRelativeTo::Editor)
+        .anchor(WindowAnchor::SouthEast)
+        .width(SCREENCAST_WIDTH as u32)
+        .height(height.max(1))
+        .row(screen_size.height.saturating_sub(2))
+        .col(screen_size.width)
+        .border(WindowBorder::Rounded)
+        .title(WindowTitle::SimpleString(" Keys ".into()))
+        .title_pos(WindowTitlePosition::Center)
+        .focusable(false)
+        .zindex(250)
+        .build()
+}
+
+///
+/// Open the float with a scratch buffer without entering it
+///
+fn open_screencast_window() -> Option<Window> {
+    let buffer = create_buf(false, true).ok()?;
+    let _ = set_option_value(
+        "bufhidden",
+        "wipe",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    );
+
+    open_win(&buffer, false, &get_screencast_window_config(1)).ok()
+}
+
+///
+/// `:Screencast`: Toggle the overlay
+///
+fn toggle_screencast() {
+    let (is_enabled, window_handle) = {
+        let mut locked_state = SCREENCAST_STATE.lock().unwrap();
+        locked_state.is_enabled = !locked_state.is_enabled;
+        locked_state.lines.clear();
+        locked_state.current_keys.clear();
+        (locked_state.is_enabled, locked_state.window.take())
+    };
+
+    if let Some(window) = window_handle.map(Window::from)
+        && window.is_valid()
+    {
+        let _ = window.close(true);
+    }
+
+    if !is_enabled {
+        let _ = exec_lua::<_, Object>(
+            "vim.on_key(nil, vim.api.nvim_create_namespace(...))",
+            (SCREENCAST_NAMESPACE,),
+        );
+        let _ = del_augroup_by_name("custom-screencast-group");
+        echo_message("screencast", "Off.", EchoLevel::Info);
+        return;
+    }
+
+    let _ = exec_lua::<_, Object>(
+        SCREENCAST_ON_KEY_LUA,
+        (
+            SCREENCAST_NAMESPACE,
+            Function::<String, ()>::from_fn(|key: String| push_key(key)),
+        ),
+    );
+
+    let _ = create_autocmd(
+        vec!["CmdlineLeave"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-screencast-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .patterns(vec![":", "/", "?"])
+            .callback(|args: AutocmdCallbackArgs| {
+                let is_aborted =
+                    exec_lua::<_, bool>("return vim.v.event.abort", ()).unwrap_or(false);
+                let command_line = call_function::<_, String>("getcmdline", ()).unwrap_or_default();
+                if !is_aborted && !command_line.trim().is_empty() {
+                    push_command(format!("{}{command_line}", args.file.display()));
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    echo_message(
+        "screencast",
+        "On, ':Screencast' again to turn it off.",
+        EchoLevel::Info,
+    );
+}
+
+///
+///
+///
+pub fn setup() {
+    register_user_command(
+        "Screencast",
+        UserCommandSpec {
+            desc: "Toggle the keystroke and command overlay",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| toggle_screencast(),
+    );
+}
+
+use crate::{
+    extended_api::{
+        EchoLevel, UserCommandArgs, UserCommandSpec, echo_message, exec_lua, register_user_command,
+    },
+    picker::get_screen_size,
+    utils::display_width,
+};
+
+use std::{
+    collections::VecDeque,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim, Function, Object, WinHandle,
+    api::{
+        Window, call_function, create_augroup, create_autocmd, create_buf, del_augroup_by_name,
+        get_current_tabpage, open_win,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts},
+        set_option_value,
+        types::{
+            AutocmdCallbackArgs, WindowAnchor, WindowBorder, WindowConfig, WindowRelativeTo,
+            WindowTitle, WindowTitlePosition,
+        },
+    },
+};
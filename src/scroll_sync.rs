@@ -0,0 +1,139 @@
+//! Scroll sync between two windows, e.g. to compare the generated file with its source without
+//! `:diffthis`:
+//!
+//! - `:ScrollSync`: Bind the current window and the previous one (`<C-w>p`), scrolling or moving
+//!   the cursor in one of them does the same in the other one (`scrollbind` and `cursorbind`).
+//!   Run it again to unbind.
+//!
+//! The binding is removed automatically when one of the windows is closed.
+//!
+
+///
+/// Private module-scope state: the bound window pair
+///
+static SCROLL_SYNC_STATE: LazyLock<Mutex<Option<(WinHandle, WinHandle)>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+///
+/// Set `scrollbind` and `cursorbind` of the given window
+///
+fn set_window_binding(window: &Window, enable: bool) {
+    if !window.is_valid() {
+        return;
+    }
+
+    let opts = OptionOpts::builder().win(window.clone()).build();
+    let _ = set_option_value("scrollbind", enable, &opts);
+    let _ = set_option_value("cursorbind", enable, &opts);
+}
+
+///
+/// Unbind the window pair, return `false` if there is no binding.
+///
+fn unbind_windows() -> bool {
+    let Some((first_window, second_window)) = SCROLL_SYNC_STATE.lock().unwrap().take() else {
+        return false;
+    };
+
+    set_window_binding(&Window::from(first_window), false);
+    set_window_binding(&Window::from(second_window), false);
+    true
+}
+
+///
+/// Bind the current window and the previous window
+///
+fn bind_windows() {
+    const LOGGER_PREFIX: &'static str = "[ scroll_sync ]";
+
+    let current_window = Window::current();
+    let previous_window_handle = call_function::<_, i32>(
+        "win_getid",
+        (call_function::<_, i32>("winnr", ("#",)).unwrap_or(0),),
+    )
+    .unwrap_or(0);
+
+    if previous_window_handle == 0 || previous_window_handle == current_window.handle() {
+        nvim::print!("{LOGGER_PREFIX} No previous window to bind with.");
+        return;
+    }
+
+    let previous_window = Window::from(previous_window_handle);
+    set_window_binding(&current_window, true);
+    set_window_binding(&previous_window, true);
+
+    //
+    // Align the scroll positions of the bound windows
+    //
+    let _ = command("syncbind");
+
+    *SCROLL_SYNC_STATE.lock().unwrap() = Some((current_window.handle(), previous_window_handle));
+    nvim::print!("{LOGGER_PREFIX} Bound with window {previous_window_handle}.");
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_user_command(
+        "ScrollSync",
+        |_: CommandArgs| {
+            if unbind_windows() {
+                nvim::print!("[ scroll_sync ] Unbound.");
+            } else {
+                bind_windows();
+            }
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Toggle the scroll sync between the current and the previous window")
+            .build(),
+    );
+
+    let _ = create_autocmd(
+        vec!["WinClosed"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-scroll-sync-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                //
+                // The `<amatch>` of `WinClosed` is the closed window ID
+                //
+                let closed_window_handle = args.r#match.parse::<WinHandle>().unwrap_or(0);
+                let is_bound_window =
+                    SCROLL_SYNC_STATE
+                        .lock()
+                        .unwrap()
+                        .is_some_and(|(first, second)| {
+                            first == closed_window_handle || second == closed_window_handle
+                        });
+
+                if is_bound_window {
+                    unbind_windows();
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+use std::sync::{LazyLock, Mutex};
+
+use nvim_oxi::{
+    self as nvim, WinHandle,
+    api::{
+        Window, call_function, command, create_augroup, create_autocmd, create_user_command,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, CreateCommandOpts, OptionOpts},
+        set_option_value,
+        types::{AutocmdCallbackArgs, CommandArgs},
+    },
+};
@@ -1,7 +1,259 @@
 use nvim_oxi as nvim;
 
-use nvim::api::opts::{OptionOpts, OptionScope};
-use nvim::api::set_option_value;
+use nvim::api::opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, OptionScope};
+use nvim::api::{create_augroup, create_autocmd, set_option_value};
+use std::sync::{LazyLock, Mutex};
+
+///
+/// Restore the terminal cursor (`get_terminal_cursor_style()`) when leaving or suspending
+/// Neovim, otherwise, the shell keeps the last Neovim cursor shape.
+///
+const RESTORE_TERMINAL_CURSOR_ON_EXIT: bool = true;
+
+///
+/// Cursor shape, the number is the percentage of the character cell
+///
+#[derive(Debug, Clone, Copy)]
+enum CursorShape {
+    Block,
+    VerticalBar(u8),
+    HorizontalBar(u8),
+}
+
+///
+/// Cursor blinking times in milliseconds
+///
+#[derive(Debug, Clone, Copy)]
+struct CursorBlink {
+    wait: u32,
+    on: u32,
+    off: u32,
+}
+
+///
+/// The cursor style of the given modes, it's a single part of the `guicursor` option.
+///
+#[derive(Debug, Clone, Copy)]
+struct CursorModeStyle {
+    //
+    // Mode list, e.g. "n-v-c", see `:h guicursor`
+    //
+    modes: &'static str,
+    shape: CursorShape,
+
+    //
+    // `None` means no blinking
+    //
+    blink: Option<CursorBlink>,
+
+    //
+    // Highlight groups, e.g. "Cursor1/lCursor1"
+    //
+    highlight: Option<&'static str>,
+}
+
+impl CursorModeStyle {
+    ///
+    /// Convert to the `guicursor` part, e.g. `i-ci-ve:ver25-blinkwait700-blinkon400-blinkoff250`
+    ///
+    fn to_guicursor_part(&self) -> String {
+        let mut arguments = vec![match self.shape {
+            CursorShape::Block => "block".to_string(),
+            CursorShape::VerticalBar(percentage) => format!("ver{percentage}"),
+            CursorShape::HorizontalBar(percentage) => format!("hor{percentage}"),
+        }];
+
+        match self.blink {
+            Some(blink) => arguments.push(format!(
+                "blinkwait{}-blinkon{}-blinkoff{}",
+                blink.wait, blink.on, blink.off
+            )),
+            None => arguments.push("blinkon0".to_string()),
+        }
+
+        if let Some(highlight) = self.highlight {
+            arguments.push(highlight.to_string());
+        }
+
+        format!("{}:{}", self.modes, arguments.join("-"))
+    }
+}
+
+///
+/// Define your cursor styles here:
+///
+/// 1. Normal, Visual, Command mode: block cursor with the `Cursor1` or `lCursor1` highlight
+///    group instead of the default `inverse/reverse` highlight group.
+///
+/// 2. Insert, Command line Insert, Visual with selection mode: blinking vertical bar (25% of the
+///    character width) with the `Cursor2` or `lCursor2` highlight group.
+///
+/// 3. Replace, Command line Replace mode: blinking underline (20% of the character height).
+///
+/// 4. Operator-pending mode: half-height underline.
+///
+fn get_cursor_mode_styles() -> Vec<CursorModeStyle> {
+    const CURSOR_BLINK: CursorBlink = CursorBlink {
+        wait: 700,
+        on: 400,
+        off: 250,
+    };
+
+    vec![
+        CursorModeStyle {
+            modes: "n-v-c",
+            shape: CursorShape::Block,
+            blink: None,
+            highlight: Some("Cursor1/lCursor1"),
+        },
+        CursorModeStyle {
+            modes: "i-ci-ve",
+            shape: CursorShape::VerticalBar(25),
+            blink: Some(CURSOR_BLINK),
+            highlight: Some("Cursor2/lCursor2"),
+        },
+        CursorModeStyle {
+            modes: "r-cr",
+            shape: CursorShape::HorizontalBar(20),
+            blink: Some(CURSOR_BLINK),
+            highlight: None,
+        },
+        CursorModeStyle {
+            modes: "o",
+            shape: CursorShape::HorizontalBar(50),
+            blink: None,
+            highlight: None,
+        },
+    ]
+}
+
+///
+/// The terminal cursor style after leaving Neovim
+///
+fn get_terminal_cursor_style() -> CursorModeStyle {
+    CursorModeStyle {
+        modes: "a",
+        shape: CursorShape::VerticalBar(25),
+        blink: Some(CursorBlink {
+            wait: 700,
+            on: 400,
+            off: 250,
+        }),
+        highlight: None,
+    }
+}
+
+///
+/// Join the styles into the `guicursor` option value
+///
+fn get_guicursor_value(styles: &[CursorModeStyle]) -> String {
+    styles
+        .iter()
+        .map(|style| style.to_guicursor_part())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+///
+/// Switch to the terminal cursor when leaving/suspending Neovim, and switch back when resuming.
+///
+fn setup_terminal_cursor_restoring() {
+    let Ok(cursor_group) = create_augroup(
+        "custom-terminal-cursor-group",
+        &CreateAugroupOpts::builder().clear(true).build(),
+    ) else {
+        return;
+    };
+
+    let cursor_events: Vec<(Vec<&str>, fn() -> String)> = vec![
+        (vec!["VimLeave", "VimSuspend"], || {
+            get_guicursor_value(&[get_terminal_cursor_style()])
+        }),
+        (vec!["VimResume"], || {
+            get_guicursor_value(&get_cursor_mode_styles())
+        }),
+    ];
+
+    for (events, get_value) in cursor_events {
+        let _ = create_autocmd(
+            events,
+            &CreateAutocmdOpts::builder()
+                .group(cursor_group)
+                .callback(move |_| {
+                    let _ = set_option_value(
+                        "guicursor",
+                        get_value(),
+                        &OptionOpts::builder().scope(OptionScope::Global).build(),
+                    );
+
+                    //
+                    // Return `true` to delete the autocommand (means only run once)!!!
+                    //
+                    false
+                })
+                .build(),
+        );
+    }
+}
+
+///
+/// `listchars` and `fillchars` preset
+///
+#[derive(Debug, Clone, Copy)]
+struct ListCharsPreset {
+    name: &'static str,
+    listchars: &'static str,
+    fillchars: &'static str,
+}
+
+///
+/// Define your `listchars` and `fillchars` presets here, `<leader>tl` cycles them in order, the
+/// first one is applied at startup.
+///
+const LIST_CHARS_PRESETS: [ListCharsPreset; 3] = [
+    ListCharsPreset {
+        name: "minimal",
+        listchars: "tab:  ,trail:·,nbsp:␣",
+        fillchars: "eob: ,vert:│,fold: ,foldsep: ",
+    },
+    ListCharsPreset {
+        name: "full",
+        listchars: "tab:» ,trail:·,extends:›,precedes:‹,nbsp:␣",
+        fillchars: "eob:~,vert:│,fold:·,foldsep:│,diff:╱",
+    },
+    ListCharsPreset {
+        name: "debug-whitespace",
+        listchars: "tab:>-,space:·,lead:·,trail:•,eol:↲,extends:›,precedes:‹,nbsp:␣",
+        fillchars: "eob:~,vert:│,fold:-,diff:-",
+    },
+];
+
+///
+/// Private module-scope state: the current `LIST_CHARS_PRESETS` index
+///
+static LIST_CHARS_PRESET_INDEX: LazyLock<Mutex<usize>> = LazyLock::new(|| Mutex::new(0));
+
+///
+/// Apply the `listchars` and `fillchars` preset to the global and current window values
+///
+fn apply_list_chars_preset(preset: &ListCharsPreset) {
+    let opts = OptionOpts::builder().build();
+    let _ = set_option_value("list", true, &opts);
+    let _ = set_option_value("listchars", preset.listchars, &opts);
+    let _ = set_option_value("fillchars", preset.fillchars, &opts);
+}
+
+///
+/// Cycle to the next `listchars` and `fillchars` preset
+///
+pub fn cycle_list_chars_preset() {
+    let mut locked_index = LIST_CHARS_PRESET_INDEX.lock().unwrap();
+    *locked_index = (*locked_index + 1) % LIST_CHARS_PRESETS.len();
+
+    let preset = &LIST_CHARS_PRESETS[*locked_index];
+    apply_list_chars_preset(preset);
+    nvim::print!("[ settings ] listchars/fillchars preset: {}", preset.name);
+}
 
 ///
 ///
@@ -67,6 +319,27 @@ pub fn setup() {
     // Completion options (for deoplete)
     let _ = set_option_value("completeopt", "menuone,noinsert,noselect", &opts);
 
+    //
+    // Completion popup menu: max 15 items, slightly transparent
+    //
+    let _ = set_option_value("pumheight", 15, &opts);
+    let _ = set_option_value("pumblend", 10, &opts);
+
+    //
+    // Command line completion:
+    //
+    // - Show the candidates in the popup menu (instead of the horizontal wildmenu bar) with the
+    //   fuzzy matching.
+    // - The first `<Tab>` completes the longest common text and opens the popup, the next one
+    //   selects the candidates.
+    // - `<C-j>/<C-k>` select the next/prev candidate when the popup is open, the same with the
+    //   insert mode (see `keybindings.rs`).
+    //
+    let _ = set_option_value("wildmenu", true, &opts);
+    let _ = set_option_value("wildmode", "longest:full,full", &opts);
+    let _ = set_option_value("wildoptions", "pum,fuzzy", &opts);
+    let _ = set_option_value("wildignorecase", true, &opts);
+
     // Scroll off
     let _ = set_option_value("scrolloff", 10, &opts);
 
@@ -95,24 +368,18 @@ pub fn setup() {
     let _ = set_option_value("cursorlineopt", "both", &opts);
 
     //
-    // 1. `n-v-c:block-Cursor/lCursor`
-    //
-    //     In [Normal, Visual, Command] mode, use `block ` shape cusor and `Cursor1`
-    //     or `lCursor1` highlight group instead of the default `inverse/reverse`
-    //     highlight group.
-    //
-    // 2. `i-ci-ve:ver25-Cursor2/lCursor2`
-    //
-    //     In [Insert, Command line Insert, Virual with selection ] mode, use vertical
-    //     bar shape cusor (bar width is 25% of the character width ),
-    //
+    // Mode-dependent cursor shapes, see `get_cursor_mode_styles()`
     //
     let _ = set_option_value(
         "guicursor",
-        "n-v-c:block-Cursor1/lCursor1,i-ci-ve:ver25-Cursor2/lCursor2,r-cr:hor20,o:hor50",
+        get_guicursor_value(&get_cursor_mode_styles()),
         &opts,
     );
 
+    if RESTORE_TERMINAL_CURSOR_ON_EXIT {
+        setup_terminal_cursor_restoring();
+    }
+
     //
     // Defines the default border style of floating windows. The default value
     // is empty, which is equivalent to "none". Valid values include:
@@ -131,4 +398,9 @@ pub fn setup() {
     //
     let _ = set_option_value("grepprg", "rg --no-heading --vimgrep", &opts);
     let _ = set_option_value("grepformat", "%f:%l:%c:%m", &opts);
+
+    //
+    // `listchars` and `fillchars`, `<leader>tl` to cycle the presets
+    //
+    apply_list_chars_preset(&LIST_CHARS_PRESETS[0]);
 }
@@ -0,0 +1,396 @@
+//! `<leader>qq`: Quit all with the summary of the unsaved changes, instead of the `:qa` which
+//! stops at the first modified buffer:
+//!
+//! - Quit immediately if there is no modified buffer.
+//! - Otherwise list the modified buffers in the popup window, each of them is saved or discarded
+//!   (all saved by default), `<CR>` applies them and quits.
+//! - Before quitting, the loaded session (`:mksession`) is updated, and the `VimLeavePre`
+//!   handlers (e.g. the usage stats, the remote session) flush their state as usual.
+//!
+//! It doesn't quit if any buffer fails to save (e.g. the one without a file name), the failed
+//! buffers are reported instead.
+//!
+
+///
+/// The namespace to highlight the quit dialog
+///
+const SMART_QUIT_NAMESPACE: &'static str = "smart_quit_dialog";
+
+///
+/// The modified buffer and what to do with it
+///
+#[derive(Debug, Clone)]
+struct ModifiedBuffer {
+    buffer_handle: BufHandle,
+    display_name: String,
+    is_save: bool,
+}
+
+///
+/// Get back the modified buffers which stop `:qa`, the `nofile` and terminal buffers are skipped.
+///
+fn get_modified_buffers() -> Vec<ModifiedBuffer> {
+    let Ok(buffer_infos) =
+        call_function::<_, Array>("getbufinfo", (Dictionary::from_iter([("bufmodified", 1)]),))
+    else {
+        return Vec::new();
+    };
+
+    buffer_infos
+        .into_iter()
+        .filter_map(|info| Dictionary::from_object(info).ok())
+        .filter_map(|info| {
+            let buffer_handle = info
+                .get("bufnr")
+                .and_then(|value| i64::from_object(value.clone()).ok())?
+                as BufHandle;
+            let opts = OptionOpts::builder()
+                .buffer(Buffer::from(buffer_handle))
+                .build();
+            let buffer_type = get_option_value::<NvimString>("buftype", &opts).unwrap_or_default();
+            if !buffer_type.is_empty() && buffer_type != "acwrite" {
+                return None;
+            }
+
+            let name = info
+                .get("name")
+                .and_then(|name| NvimString::from_object(name.clone()).ok())
+                .map(|name| name.to_string())
+                .unwrap_or_default();
+            let display_name = if name.is_empty() {
+                format!("[No Name] (buffer {buffer_handle})")
+            } else {
+                call_function::<_, String>("fnamemodify", (name, ":~:.")).unwrap_or_default()
+            };
+
+            Some(ModifiedBuffer {
+                buffer_handle,
+                display_name,
+                is_save: true,
+            })
+        })
+        .collect()
+}
+
+///
+/// Get back how many terminal jobs are still running, `:qa!` stops them.
+///
+fn get_running_terminal_count() -> usize {
+    list_bufs()
+        .filter(|buffer| {
+            let Ok(job_id) = buffer.get_var::<i64>("terminal_job_id") else {
+                return false;
+            };
+
+            //
+            // `jobwait()` with `0` timeout returns `-1` for the running job
+            //
+            call_function::<_, Vec<i64>>("jobwait", (Array::from_iter([job_id]), 0))
+                .is_ok_and(|results| results.first() == Some(&-1))
+        })
+        .count()
+}
+
+///
+/// Render the dialog lines, the buffer rows start from the first line.
+///
+fn render_quit_dialog_lines(
+    modified_buffers: &[ModifiedBuffer],
+    running_terminal_count: usize,
+) -> Vec<String> {
+    let mut lines = modified_buffers
+        .iter()
+        .map(|modified| {
+            format!(
+                " {}  {} ",
+                if modified.is_save {
+                    "[save]   "
+                } else {
+                    "[discard]"
+                },
+                modified.display_name
+            )
+        })
+        .collect::<Vec<String>>();
+
+    if running_terminal_count > 0 {
+        lines.push(String::new());
+        lines.push(format!(
+            " {running_terminal_count} running terminal job(s) will be stopped. "
+        ));
+    }
+
+    lines
+}
+
+///
+/// Refresh the dialog buffer and highlight the selected row
+///
+fn refresh_quit_dialog(
+    buffer: &mut Buffer,
+    modified_buffers: &[ModifiedBuffer],
+    running_terminal_count: usize,
+    selected_index: usize,
+) {
+    let lines = render_quit_dialog_lines(modified_buffers, running_terminal_count);
+    let opts = OptionOpts::builder().buffer(buffer.clone()).build();
+    let _ = set_option_value("modifiable", true, &opts);
+    let _ = buffer.set_lines(.., true, lines.iter().map(|line| line.as_str()));
+    let _ = set_option_value("modifiable", false, &opts);
+
+    let namespace_id = create_namespace(SMART_QUIT_NAMESPACE);
+    let _ = buffer.clear_namespace(namespace_id, ..);
+
+    for (index, modified) in modified_buffers.iter().enumerate() {
+        let _ = buffer.set_extmark(
+            namespace_id,
+            index,
+            1,
+            &SetExtmarkOpts::builder()
+                .end_col(10)
+                .hl_group(if modified.is_save {
+                    "DiagnosticOk"
+                } else {
+                    "DiagnosticError"
+                })
+                .build(),
+        );
+
+        if index == selected_index {
+            let _ = buffer.set_extmark(
+                namespace_id,
+                index,
+                0,
+                &SetExtmarkOpts::builder()
+                    .end_col(lines[index].len())
+                    .hl_group("PmenuSel")
+                    .build(),
+            );
+        }
+    }
+
+    if running_terminal_count > 0 {
+        let _ = buffer.set_extmark(
+            namespace_id,
+            lines.len() - 1,
+            0,
+            &SetExtmarkOpts::builder()
+                .end_col(lines[lines.len() - 1].len())
+                .hl_group("WarningMsg")
+                .build(),
+        );
+    }
+}
+
+///
+/// Show the modified buffers in the popup window and wait for the decision:
+///
+/// - `j`/`k` (or `<Down>`/`<Up>`): Select the next/previous buffer.
+/// - `<Space>`/`<Tab>`: Toggle save/discard, `s`/`d`: Save/Discard the selected one.
+/// - `S`/`D`: Save/Discard all.
+/// - `<CR>`: Apply and quit.
+/// - `q` or `<ESC>`: Cancel.
+///
+/// Return `None` if it's cancelled.
+///
+fn show_quit_dialog(
+    mut modified_buffers: Vec<ModifiedBuffer>,
+    running_terminal_count: usize,
+) -> Option<Vec<ModifiedBuffer>> {
+    let Ok(mut dialog_buffer) = create_buf(false, true) else {
+        return None;
+    };
+    let _ = set_option_value(
+        "bufhidden",
+        "wipe",
+        &OptionOpts::builder().buffer(dialog_buffer.clone()).build(),
+    );
+    refresh_quit_dialog(
+        &mut dialog_buffer,
+        &modified_buffers,
+        running_terminal_count,
+        0,
+    );
+
+    let dialog_window = create_popup_window(&PopupWindowOptions {
+        buffer: Some(dialog_buffer.handle()),
+        title: Some(format!(
+            " Quit: {} modified buffer(s) ",
+            modified_buffers.len()
+        )),
+        footer: Some(" <Space>: save/discard  S/D: all  <CR>: quit  <ESC>: cancel ".to_string()),
+        min_width: Some(60),
+        zindex: Some(100),
+        focusable: false,
+        ..Default::default()
+    })?;
+
+    let mut selected_index = 0usize;
+
+    //
+    // Block and read the keys like the confirm dialog, `getcharstr()` fails on `<C-c>`.
+    //
+    let is_confirmed = loop {
+        refresh_quit_dialog(
+            &mut dialog_buffer,
+            &modified_buffers,
+            running_terminal_count,
+            selected_index,
+        );
+        let _ = command("redraw");
+
+        let Ok(key) = call_function::<_, String>("getcharstr", ()) else {
+            break false;
+        };
+        let key = call_function::<_, String>("keytrans", (key,)).unwrap_or_default();
+
+        match key.as_str() {
+            "j" | "<Down>" => {
+                selected_index = (selected_index + 1).min(modified_buffers.len() - 1);
+            }
+            "k" | "<Up>" => {
+                selected_index = selected_index.saturating_sub(1);
+            }
+            "<Space>" | "<Tab>" => {
+                let modified = &mut modified_buffers[selected_index];
+                modified.is_save = !modified.is_save;
+            }
+            "s" | "d" => modified_buffers[selected_index].is_save = key == "s",
+            "S" | "D" => {
+                for modified in modified_buffers.iter_mut() {
+                    modified.is_save = key == "S";
+                }
+            }
+            "<CR>" => break true,
+            "q" | "<Esc>" => break false,
+            _ => {}
+        }
+    };
+
+    dialog_window.close();
+    let _ = command("redraw");
+
+    is_confirmed.then_some(modified_buffers)
+}
+
+///
+/// Save or discard the buffers, return the ones which are still modified (failed to save).
+///
+fn apply_quit_decisions(modified_buffers: &[ModifiedBuffer]) -> Vec<String> {
+    let mut failed_names = Vec::new();
+
+    for modified in modified_buffers {
+        let buffer = Buffer::from(modified.buffer_handle);
+        if !buffer.is_valid() {
+            continue;
+        }
+        let opts = OptionOpts::builder().buffer(buffer.clone()).build();
+
+        if modified.is_save {
+            let _ = buffer.call(|_| {
+                let _ = command("silent! update");
+            });
+        } else {
+            let _ = set_option_value("modified", false, &opts);
+        }
+
+        if get_option_value::<bool>("modified", &opts).unwrap_or(false) {
+            failed_names.push(modified.display_name.clone());
+        }
+    }
+
+    failed_names
+}
+
+///
+/// Update the loaded session file (`v:this_session`) and then quit, the `VimLeavePre` handlers
+/// still run with `:qa!`.
+///
+fn flush_and_quit() {
+    let session_file = call_function::<_, String>("eval", ("v:this_session",)).unwrap_or_default();
+    if !session_file.is_empty() {
+        let escaped_session_file =
+            call_function::<_, String>("fnameescape", (session_file,)).unwrap_or_default();
+        let _ = command(&format!("silent! mksession! {escaped_session_file}"));
+    }
+
+    let _ = command("qall!");
+}
+
+///
+/// `<leader>qq`: Quit all after deciding what to do with the unsaved changes
+///
+fn smart_quit_all() {
+    let modified_buffers = get_modified_buffers();
+    let running_terminal_count = get_running_terminal_count();
+    if modified_buffers.is_empty() && running_terminal_count == 0 {
+        flush_and_quit();
+        return;
+    }
+
+    if modified_buffers.is_empty() {
+        let choice = confirm(
+            "Quit",
+            &[format!(
+                "{running_terminal_count} running terminal job(s) will be stopped, quit anyway?"
+            )],
+            &["&Yes", "&No"],
+            1,
+        );
+        if choice == Some(0) {
+            flush_and_quit();
+        }
+        return;
+    }
+
+    let Some(decided_buffers) = show_quit_dialog(modified_buffers, running_terminal_count) else {
+        return;
+    };
+
+    let failed_names = apply_quit_decisions(&decided_buffers);
+    if !failed_names.is_empty() {
+        echo_message(
+            "smart_quit",
+            &format!("Failed to save, not quitting: {}", failed_names.join(", ")),
+            EchoLevel::Error,
+        );
+        return;
+    }
+
+    flush_and_quit();
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>qq",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("'<leader>qq': Quit all with the summary of the unsaved changes")
+            .silent(true)
+            .callback(|_| {
+                smart_quit_all();
+                ()
+            })
+            .build(),
+    );
+}
+
+use crate::{
+    extended_api::{EchoLevel, echo_message},
+    picker::{PopupWindowOptions, confirm, create_popup_window},
+};
+
+use nvim_oxi::{
+    Array, BufHandle, Dictionary, String as NvimString,
+    api::{
+        Buffer, call_function, command, create_buf, create_namespace, get_option_value, list_bufs,
+        opts::{OptionOpts, SetExtmarkOpts, SetKeymapOpts},
+        set_keymap, set_option_value,
+        types::Mode,
+    },
+    conversion::FromObject,
+};
@@ -0,0 +1,1133 @@
+//! The snippet engine, the snippets are loaded from `stdpath("config")/snippets/` by the
+//! filetype:
+//!
+//! - `{filetype}.json`: The VSCode snippet file.
+//! - `{filetype}.toml`: The simple TOML format, one table per snippet:
+//!
+//!   ```toml
+//!   [fn]
+//!   prefix = "fn"                   # The table name is the prefix if it's omitted
+//!   description = "Function"
+//!   body = """
+//!   fn ${1:name}(${2}) {
+//!       $0
+//!   }"""
+//!   ```
+//!
+//! - `all.json`/`all.toml`: The snippets for all filetypes.
+//!
+//! The body uses the VSCode syntax: `$1`, `${1}`, `${1:placeholder}` (can be nested),
+//! `${1|one,two|}` (the first choice is inserted) and `$0` (the final cursor position, it's at
+//! the end if omitted). `\$`, `\}` and `\\` are the literal chars. The repeated tabstop is only
+//! visited at the first place, it's NOT mirrored.
+//!
+//! - `<Tab>` in `INSERT` mode: Expand the prefix before the cursor, or jump to the next tabstop.
+//! - `<Tab>`/`<S-Tab>` in `INSERT`/`SELECT` mode: Jump to the next/prev tabstop, the placeholder
+//!   is selected in `SELECT` mode, so typing replaces it.
+//! - `:Snippets`: Pick the snippet of the filetype and insert it after the cursor.
+//! - `:SnippetsReload`: Reload the snippet files after editing them.
+//!
+//! The tabstops are tracked by the extmarks, so they follow the edits. The snippet ends after
+//! jumping to `$0`, or the cursor is moved out of it.
+//!
+
+///
+/// The sub directory of the config dir to load the snippet files
+///
+const SNIPPETS_CONFIG_SUB_DIR: &'static str = "snippets";
+
+///
+/// The snippet file name (without the extension) for all filetypes
+///
+const SNIPPETS_ALL_FILETYPES: &'static str = "all";
+
+///
+/// The namespace of the tabstop extmarks
+///
+const SNIPPETS_NAMESPACE: &'static str = "custom-snippets-namespace";
+
+///
+/// The Lua global function name to run the snippet action by `<Cmd>` from the `expr` keymap
+///
+const SNIPPET_ACTION_FUNCTION: &'static str = "MyConfigSnippetAction";
+
+///
+/// The range in the buffer: `(start_row, start_col, end_row, end_col)`, 0-based and the end is
+/// exclusive, the same with the extmark range.
+///
+type SnippetRange = (usize, usize, usize, usize);
+
+///
+/// The snippet definition, the VSCode snippet which has multiple prefixes is split into multiple
+/// ones.
+///
+#[derive(Debug, Clone)]
+struct Snippet {
+    prefix: String,
+    description: String,
+    body: String,
+}
+
+///
+/// The tabstop in the parsed body, the offsets are the byte offsets of the body text.
+///
+#[derive(Debug, Clone, Copy)]
+struct Tabstop {
+    number: u32,
+    start: usize,
+    end: usize,
+}
+
+///
+/// The expanded snippet in the buffer, the tabstop extmarks are in the jump order (`$0` is the
+/// last one).
+///
+#[derive(Debug)]
+struct SnippetSession {
+    buffer_handle: BufHandle,
+    snippet_extmark: u32,
+    tabstop_extmarks: Vec<u32>,
+    current: usize,
+}
+
+///
+/// Private module-scope state
+///
+#[derive(Debug, Default)]
+struct SnippetsState {
+    //
+    // filetype <--> snippets, they're loaded lazily
+    //
+    snippets: HashMap<String, Vec<Snippet>>,
+
+    session: Option<SnippetSession>,
+}
+
+static SNIPPETS_STATE: LazyLock<Mutex<SnippetsState>> =
+    LazyLock::new(|| Mutex::new(SnippetsState::default()));
+
+///
+/// Parse the snippet body into the text and the tabstops, the `indent` is added after the line
+/// break and the `\t` is replaced by the `tab_text`.
+///
+struct SnippetBodyParser<'a> {
+    chars: Vec<char>,
+    position: usize,
+    indent: &'a str,
+    tab_text: &'a str,
+    text: String,
+    tabstops: Vec<Tabstop>,
+}
+
+impl<'a> SnippetBodyParser<'a> {
+    fn new(body: &str, indent: &'a str, tab_text: &'a str) -> Self {
+        Self {
+            chars: body.chars().collect(),
+            position: 0,
+            indent,
+            tab_text,
+            text: String::with_capacity(body.len()),
+            tabstops: Vec::new(),
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    fn push_char(&mut self, c: char) {
+        match c {
+            '\n' => {
+                self.text.push('\n');
+                self.text.push_str(self.indent);
+            }
+            '\t' => self.text.push_str(self.tab_text),
+            _ => self.text.push(c),
+        }
+    }
+
+    fn parse_number(&mut self) -> Option<u32> {
+        let digits = self.chars[self.position..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>();
+        self.position += digits.len();
+        digits.parse::<u32>().ok()
+    }
+
+    ///
+    /// Parse until the end, or the `}` which closes the placeholder if `in_placeholder`.
+    ///
+    fn parse(&mut self, in_placeholder: bool) {
+        while let Some(c) = self.peek(0) {
+            self.position += 1;
+            match c {
+                '\\' if self.peek(0).is_some_and(|next| "$}\\".contains(next)) => {
+                    let escaped = self.peek(0).unwrap_or(c);
+                    self.position += 1;
+                    self.push_char(escaped);
+                }
+                '}' if in_placeholder => return,
+                '$' if self.peek(0).is_some_and(|next| next.is_ascii_digit()) => {
+                    let start = self.text.len();
+                    if let Some(number) = self.parse_number() {
+                        self.tabstops.push(Tabstop {
+                            number,
+                            start,
+                            end: start,
+                        });
+                    }
+                }
+                '$' if self.peek(0) == Some('{')
+                    && self.peek(1).is_some_and(|next| next.is_ascii_digit()) =>
+                {
+                    self.position += 1;
+                    self.parse_placeholder();
+                }
+                _ => self.push_char(c),
+            }
+        }
+    }
+
+    ///
+    /// Parse the rest of `${1}`, `${1:placeholder}` or `${1|one,two|}` after `${`
+    ///
+    fn parse_placeholder(&mut self) {
+        let Some(number) = self.parse_number() else {
+            return;
+        };
+        let start = self.text.len();
+
+        match self.peek(0) {
+            Some(':') => {
+                self.position += 1;
+                self.parse(true);
+            }
+            Some('|') => {
+                self.position += 1;
+                let choices = self.chars[self.position..]
+                    .iter()
+                    .take_while(|c| **c != '|')
+                    .collect::<String>();
+                self.position += choices.chars().count();
+
+                //
+                // Skip the closing `|}`
+                //
+                self.position += 2;
+                for c in choices.split(',').next().unwrap_or_default().chars() {
+                    self.push_char(c);
+                }
+            }
+            Some('}') => self.position += 1,
+            _ => {}
+        }
+
+        self.tabstops.push(Tabstop {
+            number,
+            start,
+            end: self.text.len(),
+        });
+    }
+
+    ///
+    /// Get back the text and the tabstops in the jump order, the repeated tabstop only keeps the
+    /// first place, and `$0` is added at the end if it's omitted.
+    ///
+    fn finish(mut self) -> (String, Vec<Tabstop>) {
+        //
+        // The nested placeholder is pushed before the outer one, sort by the place first.
+        //
+        self.tabstops.sort_by_key(|tabstop| tabstop.start);
+
+        let mut tabstops: Vec<Tabstop> = Vec::with_capacity(self.tabstops.len() + 1);
+        for tabstop in self.tabstops {
+            if !tabstops.iter().any(|added| added.number == tabstop.number) {
+                tabstops.push(tabstop);
+            }
+        }
+
+        if !tabstops.iter().any(|tabstop| tabstop.number == 0) {
+            tabstops.push(Tabstop {
+                number: 0,
+                start: self.text.len(),
+                end: self.text.len(),
+            });
+        }
+
+        tabstops.sort_by_key(|tabstop| {
+            if tabstop.number == 0 {
+                u32::MAX
+            } else {
+                tabstop.number
+            }
+        });
+
+        (self.text, tabstops)
+    }
+}
+
+///
+/// Get back the `(row, col)` of the byte offset in the text
+///
+fn get_text_position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    match before.rfind('\n') {
+        Some(line_break) => (before.matches('\n').count(), offset - line_break - 1),
+        None => (0, offset),
+    }
+}
+
+///
+/// Read the TOML string value which starts at the `value`, the multi-line string can continue
+/// in the following lines. Only the basic (`"`, `"""`) and the literal (`'`, `'''`) strings are
+/// supported.
+///
+fn parse_toml_string<'a>(
+    value: &str,
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+) -> std::result::Result<String, String> {
+    for quote in ["\"\"\"", "'''"] {
+        if let Some(rest) = value.strip_prefix(quote) {
+            //
+            // The line break right after the opening quotes is trimmed
+            //
+            let mut content = String::from(rest);
+            let mut is_line_break_trimmed = rest.is_empty();
+            while !content.contains(quote) {
+                let Some((_, line)) = lines.next() else {
+                    return Err(format!("unclosed {quote}"));
+                };
+                if is_line_break_trimmed {
+                    is_line_break_trimmed = false;
+                } else {
+                    content.push('\n');
+                }
+                content.push_str(line);
+            }
+
+            let end = content.find(quote).unwrap_or(content.len());
+            content.truncate(end);
+            return Ok(if quote == "'''" {
+                content
+            } else {
+                unescape_toml_basic_string(&content)
+            });
+        }
+    }
+
+    if let Some(rest) = value.strip_prefix('\'') {
+        return rest
+            .find('\'')
+            .map(|end| rest[..end].to_string())
+            .ok_or_else(|| String::from("unclosed '"));
+    }
+
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut is_escaped = false;
+        for (index, c) in rest.char_indices() {
+            match c {
+                '"' if !is_escaped => return Ok(unescape_toml_basic_string(&rest[..index])),
+                '\\' => is_escaped = !is_escaped,
+                _ => is_escaped = false,
+            }
+        }
+        return Err(String::from("unclosed \""));
+    }
+
+    Err(format!("unsupported value '{value}'"))
+}
+
+///
+/// Unescape the TOML basic string, the unknown escape (e.g. the snippet `\$`) is kept as it is,
+/// and `\\` stays escaped for the snippet body.
+///
+fn unescape_toml_basic_string(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('"') => unescaped.push('"'),
+            Some('\\') => unescaped.push_str("\\\\"),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+///
+/// The `[snippet]` table of the TOML snippet file
+///
+#[derive(Debug, Default)]
+struct TomlSnippetTable {
+    name: String,
+    prefixes: Vec<String>,
+    description: String,
+    body: Option<String>,
+}
+
+impl TomlSnippetTable {
+    ///
+    /// One snippet per prefix, the table name is the prefix if there is no `prefix`.
+    ///
+    fn into_snippets(self, file_desc: &str) -> error::Result<Vec<Snippet>> {
+        let body = self
+            .body
+            .ok_or_else(|| Error::parse(file_desc, &format!("no 'body' in [{}]", self.name)))?;
+        let prefixes = if self.prefixes.is_empty() {
+            vec![self.name]
+        } else {
+            self.prefixes
+        };
+
+        Ok(prefixes
+            .into_iter()
+            .map(|prefix| Snippet {
+                prefix,
+                description: self.description.clone(),
+                body: body.clone(),
+            })
+            .collect())
+    }
+}
+
+///
+/// Parse the simple TOML snippet file, the `prefix` can be the string or the string array.
+///
+fn parse_toml_snippets(content: &str, file_desc: &str) -> error::Result<Vec<Snippet>> {
+    let mut snippets = Vec::new();
+    let mut current_table: Option<TomlSnippetTable> = None;
+
+    let mut lines = content.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line_error =
+            |message: &str| Error::parse(file_desc, &format!("line {}: {message}", index + 1));
+
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            if let Some(table) = current_table.take() {
+                snippets.extend(table.into_snippets(file_desc)?);
+            }
+            current_table = Some(TomlSnippetTable {
+                name: name.trim().trim_matches('"').to_string(),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(line_error("expected 'key = value'"));
+        };
+        let Some(table) = current_table.as_mut() else {
+            return Err(line_error("the key is not in the [snippet] table"));
+        };
+
+        let value = value.trim();
+        let mut parse_value =
+            |value: &str| parse_toml_string(value, &mut lines).map_err(|error| line_error(&error));
+        match key.trim() {
+            "prefix" if value.starts_with('[') => {
+                let items = value
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty());
+                for item in items {
+                    table.prefixes.push(parse_value(item)?);
+                }
+            }
+            "prefix" => table.prefixes.push(parse_value(value)?),
+            "description" => table.description = parse_value(value)?,
+            "body" => table.body = Some(parse_value(value)?),
+            other => return Err(line_error(&format!("unknown key '{other}'"))),
+        }
+    }
+
+    if let Some(table) = current_table.take() {
+        snippets.extend(table.into_snippets(file_desc)?);
+    }
+
+    Ok(snippets)
+}
+
+///
+/// Parse the VSCode snippet file, the `prefix` and the `body` can be the string or the string
+/// array:
+///
+/// ```json
+/// { "Function": { "prefix": ["fn", "func"], "body": ["fn $1() {", "\t$0", "}"] } }
+/// ```
+///
+fn parse_vscode_snippets(content: &str, file_desc: &str) -> error::Result<Vec<Snippet>> {
+    let snippets_dict = call_function::<_, Dictionary>("json_decode", (content,))
+        .map_err(|error| Error::parse(file_desc, &error.to_string()))?;
+
+    let get_strings = |dict: &Dictionary, key: &str| -> Option<Vec<String>> {
+        let value = dict.get(key)?.clone();
+        match NvimString::from_object(value.clone()) {
+            Ok(text) => Some(vec![text.to_string()]),
+            Err(_) => Vec::<String>::from_object(value).ok(),
+        }
+    };
+
+    let mut snippets = Vec::new();
+    for (name, snippet) in snippets_dict {
+        let snippet_dict = Dictionary::from_object(snippet)
+            .map_err(|_| Error::parse(file_desc, &format!("'{name}' is not an object")))?;
+        let body = get_strings(&snippet_dict, "body")
+            .ok_or_else(|| Error::parse(file_desc, &format!("no 'body' in '{name}'")))?
+            .join("\n");
+        let description = get_strings(&snippet_dict, "description")
+            .map(|lines| lines.join(" "))
+            .unwrap_or_else(|| name.to_string());
+
+        for prefix in get_strings(&snippet_dict, "prefix").unwrap_or_default() {
+            snippets.push(Snippet {
+                prefix,
+                description: description.clone(),
+                body: body.clone(),
+            });
+        }
+    }
+
+    Ok(snippets)
+}
+
+///
+/// Load the snippets of the scope (the filetype or `all`) from the JSON and the TOML files
+///
+fn load_snippet_files(scope: &str) -> error::Result<Vec<Snippet>> {
+    let Ok(config_dir) = call_function::<_, String>("stdpath", ("config",)) else {
+        return Ok(Vec::new());
+    };
+
+    let mut snippets = Vec::new();
+    for extension in ["json", "toml"] {
+        let mut snippet_file = PathBuf::from(&config_dir);
+        snippet_file.push(SNIPPETS_CONFIG_SUB_DIR);
+        snippet_file.push(format!("{scope}.{extension}"));
+        if !snippet_file.exists() {
+            continue;
+        }
+
+        let file_desc = format!("the snippet file '{}'", snippet_file.display());
+        let content =
+            std::fs::read_to_string(&snippet_file).context(&format!("read {file_desc}"))?;
+        snippets.extend(match extension {
+            "json" => parse_vscode_snippets(&content, &file_desc)?,
+            _ => parse_toml_snippets(&content, &file_desc)?,
+        });
+    }
+
+    Ok(snippets)
+}
+
+///
+/// Get back the snippets of the filetype (including the `all` ones), load them if they're not
+/// loaded yet.
+///
+fn get_filetype_snippets(filetype: &str) -> Vec<Snippet> {
+    let mut scopes = vec![SNIPPETS_ALL_FILETYPES];
+    if !filetype.is_empty() {
+        scopes.push(filetype);
+    }
+
+    let mut snippets = Vec::new();
+    for scope in scopes {
+        let loaded = SNIPPETS_STATE.lock().unwrap().snippets.get(scope).cloned();
+        let scope_snippets = match loaded {
+            Some(scope_snippets) => scope_snippets,
+            None => {
+                //
+                // The broken file is reported once, it's reloaded by `:SnippetsReload`.
+                //
+                let scope_snippets =
+                    report("snippets", load_snippet_files(scope)).unwrap_or_default();
+                SNIPPETS_STATE
+                    .lock()
+                    .unwrap()
+                    .snippets
+                    .insert(scope.to_string(), scope_snippets.clone());
+                scope_snippets
+            }
+        };
+        snippets.extend(scope_snippets);
+    }
+
+    snippets
+}
+
+///
+/// Get back the extmark range, `None` if it's gone.
+///
+fn get_extmark_range(buffer: &Buffer, namespace_id: u32, extmark_id: u32) -> Option<SnippetRange> {
+    let lua_code = r#"local bufnr, namespace_id, extmark_id = ...
+local mark = vim.api.nvim_buf_get_extmark_by_id(bufnr, namespace_id, extmark_id, { details = true })
+if #mark == 0 then
+    return nil
+end
+return { mark[1], mark[2], mark[3].end_row or mark[1], mark[3].end_col or mark[2] }"#;
+
+    let range = exec_lua::<_, Vec<i64>>(
+        lua_code,
+        (
+            buffer.handle() as i64,
+            namespace_id as i64,
+            extmark_id as i64,
+        ),
+    )?;
+
+    match range.as_slice() {
+        [start_row, start_col, end_row, end_col] => Some((
+            *start_row as usize,
+            *start_col as usize,
+            *end_row as usize,
+            *end_col as usize,
+        )),
+        _ => None,
+    }
+}
+
+///
+/// Set the extmark which grows with the text typed at its both sides
+///
+fn set_range_extmark(buffer: &mut Buffer, namespace_id: u32, range: SnippetRange) -> Option<u32> {
+    buffer
+        .set_extmark(
+            namespace_id,
+            range.0,
+            range.1,
+            &SetExtmarkOpts::builder()
+                .end_row(range.2)
+                .end_col(range.3)
+                .right_gravity(false)
+                .end_right_gravity(true)
+                .build(),
+        )
+        .ok()
+}
+
+///
+/// Select the tabstop range: the empty one puts the cursor there in `INSERT` mode, the
+/// placeholder is selected in `SELECT` mode.
+///
+fn select_tabstop_range(range: SnippetRange) {
+    let (start_row, start_col, end_row, end_col) = range;
+    let is_insert_mode =
+        call_function::<_, String>("mode", ()).is_ok_and(|mode| mode.starts_with('i'));
+
+    if (start_row, start_col) == (end_row, end_col) {
+        if is_insert_mode {
+            let _ = Window::current().set_cursor(start_row + 1, start_col);
+            return;
+        }
+
+        //
+        // `cursor()` can't go beyond the last char in `NORMAL` mode, append after it instead.
+        //
+        let line_len = Buffer::current()
+            .get_lines(start_row..start_row + 1, true)
+            .ok()
+            .and_then(|mut lines| lines.next())
+            .map_or(0, |line| line.to_string().len());
+        let (col, insert_key) = if start_col > 0 && start_col >= line_len {
+            (start_col, "a")
+        } else {
+            (start_col + 1, "i")
+        };
+        feed_keys(&format!(
+            "<Esc><Cmd>call cursor({}, {col})<CR>{insert_key}",
+            start_row + 1
+        ));
+        return;
+    }
+
+    feed_keys(&format!(
+        "<Esc><Cmd>call cursor({}, {})<CR>v<Cmd>call cursor({}, {})<CR><C-g>",
+        start_row + 1,
+        start_col + 1,
+        end_row + 1,
+        end_col.max(1)
+    ));
+}
+
+///
+/// End the snippet and remove its extmarks
+///
+fn end_snippet_session() {
+    let Some(session) = SNIPPETS_STATE.lock().unwrap().session.take() else {
+        return;
+    };
+
+    let mut buffer = Buffer::from(session.buffer_handle);
+    if buffer.is_valid() {
+        let _ = buffer.clear_namespace(create_namespace(SNIPPETS_NAMESPACE), ..);
+    }
+}
+
+///
+/// Get back the tabstop index to jump to, the next (`forward`) or the prev one. `None` if the
+/// cursor isn't in the snippet (or in the other buffer), `Some(None)` if there is no tabstop to
+/// jump. It only reads the state, so it's fine under the textlock (in the `expr` keymap).
+///
+fn get_jump_target(forward: bool) -> Option<Option<usize>> {
+    let namespace_id = create_namespace(SNIPPETS_NAMESPACE);
+    let buffer = Buffer::current();
+
+    let (snippet_extmark, target) = {
+        let locked_state = SNIPPETS_STATE.lock().unwrap();
+        let session = locked_state.session.as_ref()?;
+        if session.buffer_handle != buffer.handle() {
+            return None;
+        }
+
+        let target = if forward {
+            Some(session.current + 1).filter(|next| *next < session.tabstop_extmarks.len())
+        } else {
+            session.current.checked_sub(1)
+        };
+        (session.snippet_extmark, target)
+    };
+
+    let (row, col) = Window::current().get_cursor().unwrap_or((1, 0));
+    let cursor = (row - 1, col);
+    let range = get_extmark_range(&buffer, namespace_id, snippet_extmark)?;
+    if (range.0, range.1) <= cursor && cursor <= (range.2, range.3) {
+        Some(target)
+    } else {
+        None
+    }
+}
+
+///
+/// Jump to the next (`forward`) or the prev tabstop, return `false` if the cursor isn't in the
+/// snippet or there is no tabstop to jump.
+///
+fn jump_tabstop(forward: bool) -> bool {
+    //
+    // The cursor is moved out of the snippet (or in the other buffer), the snippet ends.
+    //
+    let Some(target) = get_jump_target(forward) else {
+        end_snippet_session();
+        return false;
+    };
+    let Some(target) = target else {
+        return false;
+    };
+
+    let (extmark_id, is_last) = {
+        let mut locked_state = SNIPPETS_STATE.lock().unwrap();
+        let Some(session) = locked_state.session.as_mut() else {
+            return false;
+        };
+        session.current = target;
+        (
+            session.tabstop_extmarks[target],
+            target + 1 == session.tabstop_extmarks.len(),
+        )
+    };
+
+    let namespace_id = create_namespace(SNIPPETS_NAMESPACE);
+    if let Some(range) = get_extmark_range(&Buffer::current(), namespace_id, extmark_id) {
+        select_tabstop_range(range);
+    }
+
+    if is_last {
+        end_snippet_session();
+    }
+
+    true
+}
+
+///
+/// Get back the buffer filetype and the line
+///
+fn get_buffer_filetype_and_line(buffer: &Buffer, row: usize) -> Option<(String, String)> {
+    let filetype = get_option_value::<NvimString>(
+        "filetype",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    )
+    .map(|filetype| filetype.to_string())
+    .unwrap_or_default();
+
+    let line = buffer
+        .get_lines(row..row + 1, true)
+        .ok()
+        .and_then(|mut lines| lines.next())
+        .map(|line| line.to_string())?;
+
+    Some((filetype, line))
+}
+
+///
+/// Replace the text between `start_col` and `end_col` of the row with the snippet, and then go
+/// to the first tabstop.
+///
+fn insert_snippet(
+    buffer: &mut Buffer,
+    snippet: &Snippet,
+    row: usize,
+    start_col: usize,
+    end_col: usize,
+) {
+    let Some((_, line)) = get_buffer_filetype_and_line(buffer, row) else {
+        return;
+    };
+
+    //
+    // The body lines follow the indent of the current line, and the `\t` follows `expandtab`.
+    //
+    let indent = line
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect::<String>();
+    let buffer_opts = OptionOpts::builder().buffer(buffer.clone()).build();
+    let tab_text = if get_option_value::<bool>("expandtab", &buffer_opts).unwrap_or(false) {
+        let shift_width = call_function::<_, i64>("shiftwidth", ()).unwrap_or(4);
+        " ".repeat(shift_width.max(1) as usize)
+    } else {
+        String::from("\t")
+    };
+
+    let mut parser = SnippetBodyParser::new(&snippet.body, &indent, &tab_text);
+    parser.parse(false);
+    let (text, tabstops) = parser.finish();
+
+    let _ = exec_lua::<_, Object>(
+        r#"local bufnr, row, start_col, end_col, lines = ...
+vim.api.nvim_buf_set_text(bufnr, row, start_col, row, end_col, lines)"#,
+        (
+            buffer.handle() as i64,
+            row as i64,
+            start_col as i64,
+            end_col as i64,
+            Array::from_iter(text.split('\n')),
+        ),
+    );
+
+    //
+    // The body offset to the buffer range
+    //
+    let to_buffer_range = |start: usize, end: usize| -> SnippetRange {
+        let to_position = |offset: usize| {
+            let (text_row, text_col) = get_text_position(&text, offset);
+            if text_row == 0 {
+                (row, start_col + text_col)
+            } else {
+                (row + text_row, text_col)
+            }
+        };
+        let (start, end) = (to_position(start), to_position(end));
+        (start.0, start.1, end.0, end.1)
+    };
+
+    end_snippet_session();
+    let namespace_id = create_namespace(SNIPPETS_NAMESPACE);
+
+    let Some(snippet_extmark) =
+        set_range_extmark(buffer, namespace_id, to_buffer_range(0, text.len()))
+    else {
+        return;
+    };
+
+    let tabstop_extmarks = tabstops
+        .iter()
+        .filter_map(|tabstop| {
+            set_range_extmark(
+                buffer,
+                namespace_id,
+                to_buffer_range(tabstop.start, tabstop.end),
+            )
+        })
+        .collect::<Vec<u32>>();
+
+    SNIPPETS_STATE.lock().unwrap().session = Some(SnippetSession {
+        buffer_handle: buffer.handle(),
+        snippet_extmark,
+        tabstop_extmarks,
+        current: 0,
+    });
+
+    //
+    // Go to the first tabstop, the snippet ends at once if it's `$0`.
+    //
+    if let Some(tabstop) = tabstops.first() {
+        select_tabstop_range(to_buffer_range(tabstop.start, tabstop.end));
+    }
+    if tabstops.len() <= 1 {
+        end_snippet_session();
+    }
+}
+
+///
+/// Find the snippet which prefix is right before the cursor: (snippet, row, prefix start col,
+/// cursor col). It only reads the buffer, so it's fine under the textlock (in the `expr`
+/// keymap).
+///
+fn find_expandable_snippet() -> Option<(Snippet, usize, usize, usize)> {
+    let buffer = Buffer::current();
+    let (row, col) = Window::current().get_cursor().unwrap_or((1, 0));
+    let row = row - 1;
+    let (filetype, line) = get_buffer_filetype_and_line(&buffer, row)?;
+    let before_cursor = line.get(..col)?;
+
+    //
+    // The longest prefix wins, and it should start at the word boundary.
+    //
+    let is_keyword_char = |c: char| c.is_alphanumeric() || c == '_';
+    let snippet = get_filetype_snippets(&filetype)
+        .into_iter()
+        .filter(|snippet| {
+            !snippet.prefix.is_empty()
+                && before_cursor.ends_with(&snippet.prefix)
+                && !before_cursor[..col - snippet.prefix.len()]
+                    .chars()
+                    .next_back()
+                    .is_some_and(is_keyword_char)
+        })
+        .max_by_key(|snippet| snippet.prefix.len())?;
+
+    let start_col = col - snippet.prefix.len();
+    Some((snippet, row, start_col, col))
+}
+
+///
+/// Expand the snippet prefix before the cursor, return `false` if there is no snippet matched.
+///
+fn expand_snippet() -> bool {
+    let Some((snippet, row, start_col, end_col)) = find_expandable_snippet() else {
+        return false;
+    };
+
+    insert_snippet(&mut Buffer::current(), &snippet, row, start_col, end_col);
+    true
+}
+
+///
+/// The snippets of the current buffer filetype, the item data is the index.
+///
+fn get_snippet_picker_items() -> Vec<PickerItem> {
+    let buffer = Buffer::current();
+    let (row, _) = Window::current().get_cursor().unwrap_or((1, 0));
+    let Some((filetype, _)) = get_buffer_filetype_and_line(&buffer, row - 1) else {
+        return Vec::new();
+    };
+
+    get_filetype_snippets(&filetype)
+        .iter()
+        .enumerate()
+        .map(|(index, snippet)| PickerItem {
+            columns: vec![
+                PickerItemColumn::new(&snippet.prefix, Some("Special")),
+                PickerItemColumn::new(&snippet.description, None),
+            ],
+            icon: None,
+            data: index.to_string(),
+        })
+        .collect()
+}
+
+///
+/// Insert the selected snippet after the cursor (like `a`), the picker is gone and back to
+/// `NORMAL` mode.
+///
+fn snippet_selected_callback(selected_index: String) {
+    let Ok(index) = selected_index.trim().parse::<usize>() else {
+        return;
+    };
+
+    nvim::schedule(move |_| {
+        let mut buffer = Buffer::current();
+        let (row, col) = Window::current().get_cursor().unwrap_or((1, 0));
+        let row = row - 1;
+        let Some((filetype, line)) = get_buffer_filetype_and_line(&buffer, row) else {
+            return;
+        };
+        let Some(snippet) = get_filetype_snippets(&filetype).into_iter().nth(index) else {
+            return;
+        };
+
+        let insert_col = line[col.min(line.len())..]
+            .chars()
+            .next()
+            .map_or(line.len(), |c| col + c.len_utf8());
+        insert_snippet(&mut buffer, &snippet, row, insert_col, insert_col);
+        ()
+    });
+}
+
+///
+/// Run the snippet action of `get_tab_keys()`: `expand`, `next` or `prev`
+///
+fn run_snippet_action(action: &str) {
+    match action {
+        "expand" => {
+            expand_snippet();
+        }
+        "next" => {
+            jump_tabstop(true);
+        }
+        "prev" => {
+            jump_tabstop(false);
+        }
+        _ => {}
+    }
+}
+
+///
+/// The keys of `<Tab>` (`forward`) and `<S-Tab>` in the `expr` keymap: run the snippet action
+/// by `<Cmd>` if there is the snippet to expand (`is_expandable`) or the tabstop to jump,
+/// otherwise it's the normal key.
+///
+fn get_tab_keys(forward: bool, is_expandable: bool) -> String {
+    let jump_target = get_jump_target(forward);
+
+    //
+    // The cursor is moved out of the snippet, end it after the keymap (not under the textlock).
+    //
+    if jump_target.is_none() && SNIPPETS_STATE.lock().unwrap().session.is_some() {
+        nvim::schedule(|_| {
+            end_snippet_session();
+            ()
+        });
+    }
+
+    let action = if is_expandable && find_expandable_snippet().is_some() {
+        "expand"
+    } else if jump_target.flatten().is_some() {
+        if forward { "next" } else { "prev" }
+    } else {
+        return String::from(if forward { "<Tab>" } else { "<S-Tab>" });
+    };
+
+    format!("<Cmd>lua {SNIPPET_ACTION_FUNCTION}('{action}')<CR>")
+}
+
+///
+///
+///
+pub fn setup() {
+    //
+    // Expose the snippet action as the Lua global function, so the `expr` keymaps can run it by
+    // `<Cmd>` (the buffer can't be changed under the textlock). The builtin `<Tab>`/`<S-Tab>` are
+    // kept if it fails.
+    //
+    let registered = call_function::<_, Object>(
+        "luaeval",
+        (
+            format!("(function(f) _G.{SNIPPET_ACTION_FUNCTION} = f end)(_A)"),
+            Function::<(String,), ()>::from_fn(|(action,)| run_snippet_action(&action)),
+        ),
+    )
+    .context("register the snippet action function");
+
+    if report("snippets", registered).is_some() {
+        let snippet_keybindings: Vec<(Mode, &str, &str, fn() -> String)> = vec![
+            (
+                Mode::Insert,
+                "<Tab>",
+                "'<Tab>': Expand the snippet or jump to the next tabstop",
+                || get_tab_keys(true, true),
+            ),
+            (
+                Mode::Select,
+                "<Tab>",
+                "'<Tab>': Jump to the next snippet tabstop",
+                || get_tab_keys(true, false),
+            ),
+            (
+                Mode::Insert,
+                "<S-Tab>",
+                "'<S-Tab>': Jump to the prev snippet tabstop",
+                || get_tab_keys(false, false),
+            ),
+            (
+                Mode::Select,
+                "<S-Tab>",
+                "'<S-Tab>': Jump to the prev snippet tabstop",
+                || get_tab_keys(false, false),
+            ),
+        ];
+
+        for (mode, key, desc, callback) in snippet_keybindings {
+            set_expr_keymap(mode, key, desc, callback);
+        }
+    }
+
+    register_user_command(
+        "Snippets",
+        UserCommandSpec {
+            desc: "Pick the snippet of the filetype to insert",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| open_registered_picker("snippets"),
+    );
+
+    register_user_command(
+        "SnippetsReload",
+        UserCommandSpec {
+            desc: "Reload the snippet files",
+            ..Default::default()
+        },
+        |_: UserCommandArgs| {
+            SNIPPETS_STATE.lock().unwrap().snippets.clear();
+            notify("snippets", "Snippets reloaded.", EchoLevel::Success);
+        },
+    );
+
+    //
+    // Contribute the snippets to the picker registry (`<leader>pp`)
+    //
+    register_picker_source(
+        "snippets",
+        "Snippets",
+        get_snippet_picker_items,
+        snippet_selected_callback,
+    );
+}
+
+use crate::{
+    error::{self, Error, ResultContext, report},
+    extended_api::{
+        EchoLevel, UserCommandArgs, UserCommandSpec, exec_lua, register_user_command,
+        set_expr_keymap,
+    },
+    notify::notify,
+    picker::{PickerItem, PickerItemColumn, open_registered_picker, register_picker_source},
+    utils::feed_keys,
+};
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim, Array, BufHandle, Dictionary, Function, Object, String as NvimString,
+    api::{
+        Buffer, Window, call_function, create_namespace, get_option_value,
+        opts::{OptionOpts, SetExtmarkOpts},
+        types::Mode,
+    },
+    conversion::FromObject,
+};
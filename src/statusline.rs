@@ -0,0 +1,418 @@
+//! The `statusline` rendered by Rust, it's the `%!` expression which calls the Rust function (by
+//! the Lua global function), so the segments are rendered for each redraw:
+//!
+//! ```text
+//!  NORMAL   main src/statusline.rs [+]                     E:1 W:2 rust 42:17 35%
+//! ```
+//!
+//! - The segments and their order are defined in `STATUSLINE_LEFT_SEGMENTS` and
+//!   `STATUSLINE_RIGHT_SEGMENTS`, the highlight groups are defined in `STATUSLINE_HIGHLIGHTS`
+//!   (applied again after changing the colorscheme).
+//! - The inactive window only shows the file name, the modified flag and the cursor position.
+//! - The git branch is read from `.git/HEAD` (no `git` process), it's cached per directory and
+//!   refreshed on `FocusGained`, `ShellCmdPost` and `DirChanged`.
+//!
+
+///
+/// The Lua global function name of the `statusline` expression
+///
+const STATUSLINE_EXPR_FUNCTION: &'static str = "MyConfigStatusline";
+
+///
+/// The statusline segment
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StatuslineSegment {
+    Mode,
+    GitBranch,
+    FileName,
+    Modified,
+    Diagnostics,
+    FileType,
+    Position,
+}
+
+///
+/// Define your segments here, the left ones are aligned to the left, the right ones are aligned
+/// to the right.
+///
+const STATUSLINE_LEFT_SEGMENTS: &[StatuslineSegment] = &[
+    StatuslineSegment::Mode,
+    StatuslineSegment::GitBranch,
+    StatuslineSegment::FileName,
+    StatuslineSegment::Modified,
+];
+const STATUSLINE_RIGHT_SEGMENTS: &[StatuslineSegment] = &[
+    StatuslineSegment::Diagnostics,
+    StatuslineSegment::FileType,
+    StatuslineSegment::Position,
+];
+
+///
+/// The highlight group of the statusline, `None` means using the `StatusLine` color.
+///
+#[derive(Debug, Clone, Copy)]
+struct StatuslineHighlight {
+    name: &'static str,
+    fg: Option<&'static str>,
+    bg: Option<&'static str>,
+    bold: bool,
+}
+
+///
+/// Define your highlight groups here, the mode ones are used by the `Mode` segment.
+///
+const STATUSLINE_HIGHLIGHTS: [StatuslineHighlight; 7] = [
+    StatuslineHighlight {
+        name: "MyStatuslineNormal",
+        fg: Some("#1d2021"),
+        bg: Some("#83a598"),
+        bold: true,
+    },
+    StatuslineHighlight {
+        name: "MyStatuslineInsert",
+        fg: Some("#1d2021"),
+        bg: Some("#b8bb26"),
+        bold: true,
+    },
+    StatuslineHighlight {
+        name: "MyStatuslineVisual",
+        fg: Some("#1d2021"),
+        bg: Some("#d3869b"),
+        bold: true,
+    },
+    StatuslineHighlight {
+        name: "MyStatuslineReplace",
+        fg: Some("#1d2021"),
+        bg: Some("#fb4934"),
+        bold: true,
+    },
+    StatuslineHighlight {
+        name: "MyStatuslineCommand",
+        fg: Some("#1d2021"),
+        bg: Some("#fabd2f"),
+        bold: true,
+    },
+    StatuslineHighlight {
+        name: "MyStatuslineTerminal",
+        fg: Some("#1d2021"),
+        bg: Some("#8ec07c"),
+        bold: true,
+    },
+    StatuslineHighlight {
+        name: "MyStatuslineBranch",
+        fg: Some("#fe8019"),
+        bg: None,
+        bold: false,
+    },
+];
+
+///
+/// Private module-scope state: the git branch of the directory, `None` if it's not in a git
+/// repository.
+///
+static STATUSLINE_GIT_BRANCHES: LazyLock<Mutex<HashMap<PathBuf, Option<String>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+///
+/// Define the highlight groups, `:colorscheme` clears them, so it runs again on `ColorScheme`.
+///
+fn apply_statusline_highlights() {
+    for highlight in STATUSLINE_HIGHLIGHTS {
+        let mut definition = Dictionary::new();
+        if let Some(fg) = highlight.fg {
+            definition.insert("fg", fg);
+        }
+        if let Some(bg) = highlight.bg {
+            definition.insert("bg", bg);
+        }
+        definition.insert("bold", highlight.bold);
+
+        let _ = exec_lua::<_, Object>(
+            "local name, definition = ...; vim.api.nvim_set_hl(0, name, definition)",
+            (highlight.name, definition),
+        );
+    }
+}
+
+///
+/// The statusline text can't have the bare `%`
+///
+fn escape_statusline_text(text: &str) -> String {
+    text.replace('%', "%%")
+}
+
+///
+/// The mode name and its highlight group
+///
+fn get_mode_segment() -> String {
+    let mode = call_function::<_, String>("mode", ()).unwrap_or_default();
+    let (name, hl_group) = match mode.chars().next() {
+        Some('i') => ("INSERT", "MyStatuslineInsert"),
+        Some('v') => ("VISUAL", "MyStatuslineVisual"),
+        Some('V') => ("V-LINE", "MyStatuslineVisual"),
+        Some('\u{16}') => ("V-BLOCK", "MyStatuslineVisual"),
+        Some('s') | Some('S') | Some('\u{13}') => ("SELECT", "MyStatuslineVisual"),
+        Some('R') => ("REPLACE", "MyStatuslineReplace"),
+        Some('c') => ("COMMAND", "MyStatuslineCommand"),
+        Some('t') => ("TERMINAL", "MyStatuslineTerminal"),
+        _ => ("NORMAL", "MyStatuslineNormal"),
+    };
+
+    format!("%#{hl_group}# {name} %*")
+}
+
+///
+/// Read the branch name from `.git/HEAD` of the directory or its parents, the detached `HEAD`
+/// is shown as the short commit hash.
+///
+fn read_git_branch(dir: &Path) -> Option<String> {
+    for ancestor in dir.ancestors() {
+        let git_path = ancestor.join(".git");
+
+        //
+        // The `.git` of the worktree or the submodule is a file: `gitdir: {path}`
+        //
+        let git_dir = if git_path.is_file() {
+            let content = std::fs::read_to_string(&git_path).ok()?;
+            let git_dir = content.trim().strip_prefix("gitdir:")?.trim().to_owned();
+            ancestor.join(git_dir)
+        } else if git_path.is_dir() {
+            git_path
+        } else {
+            continue;
+        };
+
+        let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+        let head = head.trim();
+        return match head.strip_prefix("ref: refs/heads/") {
+            Some(branch) => Some(branch.to_owned()),
+            None => Some(head.chars().take(7).collect()),
+        };
+    }
+
+    None
+}
+
+///
+/// The git branch of the buffer directory (cached)
+///
+fn get_git_branch_segment(buffer: &Buffer) -> String {
+    let dir = buffer
+        .get_name()
+        .ok()
+        .and_then(|name| name.parent().map(|parent| parent.to_path_buf()))
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .or_else(|| std::env::current_dir().ok());
+    let Some(dir) = dir else {
+        return String::new();
+    };
+
+    let branch = STATUSLINE_GIT_BRANCHES
+        .lock()
+        .unwrap()
+        .entry(dir.clone())
+        .or_insert_with(|| read_git_branch(&dir))
+        .clone();
+
+    match branch {
+        Some(branch) => format!("%#MyStatuslineBranch#{}%*", escape_statusline_text(&branch)),
+        None => String::new(),
+    }
+}
+
+///
+/// The file path relative to the current working directory (or the home directory)
+///
+fn get_file_name_segment(buffer: &Buffer) -> String {
+    let name = buffer
+        .get_name()
+        .map(|name| name.display().to_string())
+        .unwrap_or_default();
+    if name.is_empty() {
+        return "[No Name]".to_string();
+    }
+
+    let name = call_function::<_, String>("fnamemodify", (name.as_str(), ":~:.")).unwrap_or(name);
+    escape_statusline_text(&name)
+}
+
+///
+/// The modified and the readonly flags
+///
+fn get_modified_segment(buffer: &Buffer) -> String {
+    let opts = OptionOpts::builder().buffer(buffer.clone()).build();
+    let mut flags = Vec::new();
+    if get_option_value::<bool>("modified", &opts).unwrap_or(false) {
+        flags.push("[+]");
+    }
+    if get_option_value::<bool>("readonly", &opts).unwrap_or(false) {
+        flags.push("[RO]");
+    }
+
+    flags.join("")
+}
+
+///
+/// The diagnostic counts by the severity, the zero ones are hidden.
+///
+fn get_diagnostics_segment(buffer: &Buffer) -> String {
+    const SEVERITIES: [(&'static str, &'static str); 4] = [
+        ("E", "DiagnosticError"),
+        ("W", "DiagnosticWarn"),
+        ("I", "DiagnosticInfo"),
+        ("H", "DiagnosticHint"),
+    ];
+
+    let counts = exec_lua::<_, Vec<i64>>(
+        r#"local counts = vim.diagnostic.count(...)
+return { counts[1] or 0, counts[2] or 0, counts[3] or 0, counts[4] or 0 }"#,
+        (buffer.handle(),),
+    )
+    .unwrap_or_default();
+
+    counts
+        .iter()
+        .zip(SEVERITIES)
+        .filter(|(count, _)| **count > 0)
+        .map(|(count, (label, hl_group))| format!("%#{hl_group}#{label}:{count}%*"))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+///
+/// The buffer filetype
+///
+fn get_file_type_segment(buffer: &Buffer) -> String {
+    get_option_value::<NvimString>(
+        "filetype",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    )
+    .map(|value| value.to_string())
+    .unwrap_or_default()
+}
+
+///
+/// Render the segment, it's empty if there is nothing to show.
+///
+fn render_segment(segment: StatuslineSegment, buffer: &Buffer, is_active: bool) -> String {
+    match segment {
+        StatuslineSegment::Mode if is_active => get_mode_segment(),
+        StatuslineSegment::GitBranch if is_active => get_git_branch_segment(buffer),
+        StatuslineSegment::FileName => get_file_name_segment(buffer),
+        StatuslineSegment::Modified => get_modified_segment(buffer),
+        StatuslineSegment::Diagnostics if is_active => get_diagnostics_segment(buffer),
+        StatuslineSegment::FileType if is_active => get_file_type_segment(buffer),
+
+        //
+        // `%l`, `%c` and `%p` are expanded by Neovim for the window of the statusline
+        //
+        StatuslineSegment::Position => "%l:%c %p%%".to_string(),
+        _ => String::new(),
+    }
+}
+
+///
+/// Render the segments of the `g:statusline_winid` window, it's the window which the statusline
+/// belongs to (the current window is always the active one).
+///
+fn render_statusline() -> String {
+    let window = get_var::<i64>("statusline_winid")
+        .map(|handle| Window::from(handle as i32))
+        .unwrap_or_else(|_| Window::current());
+    let is_active = window.handle() == Window::current().handle();
+    let Ok(buffer) = window.get_buf() else {
+        return String::new();
+    };
+
+    let render_side = |segments: &[StatuslineSegment]| {
+        segments
+            .iter()
+            .map(|segment| render_segment(*segment, &buffer, is_active))
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<String>>()
+            .join(" ")
+    };
+
+    format!(
+        "{} %<%={} ",
+        render_side(STATUSLINE_LEFT_SEGMENTS),
+        render_side(STATUSLINE_RIGHT_SEGMENTS)
+    )
+}
+
+///
+///
+///
+pub fn setup() {
+    apply_statusline_highlights();
+
+    //
+    // Expose the `statusline` expression as the Lua global function, so `v:lua` can call it.
+    //
+    let _ = exec_lua::<_, Object>(
+        "local name, f = ...; _G[name] = f",
+        (
+            STATUSLINE_EXPR_FUNCTION,
+            Function::<(), String>::from_fn(|()| render_statusline()),
+        ),
+    );
+    let _ = set_option_value(
+        "statusline",
+        format!("%!v:lua.{STATUSLINE_EXPR_FUNCTION}()"),
+        &OptionOpts::builder().scope(OptionScope::Global).build(),
+    );
+
+    let Ok(statusline_group) = create_augroup(
+        "custom-statusline-group",
+        &CreateAugroupOpts::builder().clear(true).build(),
+    ) else {
+        return;
+    };
+
+    let statusline_events: Vec<(Vec<&str>, fn())> = vec![
+        (vec!["ColorScheme"], apply_statusline_highlights),
+        (vec!["FocusGained", "ShellCmdPost", "DirChanged"], || {
+            STATUSLINE_GIT_BRANCHES.lock().unwrap().clear();
+            let _ = command("redrawstatus!");
+        }),
+        (vec!["ModeChanged", "DiagnosticChanged"], || {
+            let _ = command("redrawstatus");
+        }),
+    ];
+
+    for (events, callback) in statusline_events {
+        let _ = create_autocmd(
+            events,
+            &CreateAutocmdOpts::builder()
+                .group(statusline_group)
+                .callback(move |_| {
+                    callback();
+
+                    //
+                    // Return `true` to delete the autocommand (means only run once)!!!
+                    //
+                    false
+                })
+                .build(),
+        );
+    }
+}
+
+use crate::extended_api::exec_lua;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    Dictionary, Function, Object, String as NvimString,
+    api::{
+        Buffer, Window, call_function, command, create_augroup, create_autocmd, get_option_value,
+        get_var,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, OptionScope},
+        set_option_value,
+    },
+};
@@ -0,0 +1,346 @@
+//! Paste the clipboard text after converting it:
+//!
+//! - `json`: Pretty-print the JSON (it must be valid).
+//! - `markdown_table`: The TSV (e.g. copied from the spreadsheet) as the markdown table, the
+//!   first row is the header.
+//! - `rust_string`: The Rust string literal with the escapes, e.g. `"a\"b\n"`.
+//! - `rust_raw_string`: The Rust raw string literal, e.g. `r#"a"b"#`, it keeps the lines.
+//! - `markdown_code_block`: The text in the markdown code block.
+//!
+//! Select the transformer in the "Paste as" picker (`<leader>pp`), or `:PasteAs {transformer}`.
+//!
+
+///
+/// The indent of the pretty-printed JSON
+///
+const STRUCTURED_PASTE_JSON_INDENT: usize = 4;
+
+///
+/// The paste transformer, the `linewise` result is pasted below the current line, otherwise
+/// after the cursor.
+///
+#[derive(Debug, Clone, Copy)]
+struct PasteTransformer {
+    name: &'static str,
+    desc: &'static str,
+    linewise: bool,
+    transform: fn(&str) -> Result<Vec<String>, String>,
+}
+
+///
+/// Define your paste transformers here
+///
+const PASTE_TRANSFORMERS: [PasteTransformer; 5] = [
+    PasteTransformer {
+        name: "json",
+        desc: "Pretty-printed JSON",
+        linewise: true,
+        transform: transform_json,
+    },
+    PasteTransformer {
+        name: "markdown_table",
+        desc: "TSV as markdown table",
+        linewise: true,
+        transform: transform_markdown_table,
+    },
+    PasteTransformer {
+        name: "rust_string",
+        desc: "Rust string literal with escapes",
+        linewise: false,
+        transform: transform_rust_string,
+    },
+    PasteTransformer {
+        name: "rust_raw_string",
+        desc: "Rust raw string literal",
+        linewise: false,
+        transform: transform_rust_raw_string,
+    },
+    PasteTransformer {
+        name: "markdown_code_block",
+        desc: "Markdown code block",
+        linewise: true,
+        transform: transform_markdown_code_block,
+    },
+];
+
+///
+/// Re-indent the JSON text, the empty object and array stay in one line. The text is validated
+/// before, so only the structure characters outside of the strings matter.
+///
+fn pretty_print_json(text: &str) -> Vec<String> {
+    let mut output = String::new();
+    let mut indent = 0usize;
+    let mut in_string = false;
+    let mut is_escaped = false;
+
+    let new_line = |output: &mut String, indent: usize| {
+        output.push('\n');
+        output.push_str(&" ".repeat(indent * STRUCTURED_PASTE_JSON_INDENT));
+    };
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_string {
+            output.push(c);
+            if is_escaped {
+                is_escaped = false;
+            } else if c == '\\' {
+                is_escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                output.push(c);
+            }
+            '{' | '[' => {
+                let close = if c == '{' { '}' } else { ']' };
+                while chars.next_if(|next| next.is_whitespace()).is_some() {}
+
+                if chars.next_if_eq(&close).is_some() {
+                    output.push(c);
+                    output.push(close);
+                } else {
+                    indent += 1;
+                    output.push(c);
+                    new_line(&mut output, indent);
+                }
+            }
+            '}' | ']' => {
+                indent = indent.saturating_sub(1);
+                new_line(&mut output, indent);
+                output.push(c);
+            }
+            ',' => {
+                output.push(c);
+                new_line(&mut output, indent);
+            }
+            ':' => output.push_str(": "),
+            c if c.is_whitespace() => {}
+            c => output.push(c),
+        }
+    }
+
+    output.lines().map(|line| line.to_owned()).collect()
+}
+
+///
+/// `json`: Validate by `vim.json.decode`, and then pretty-print it.
+///
+fn transform_json(text: &str) -> Result<Vec<String>, String> {
+    let is_valid =
+        exec_lua::<_, bool>("return (pcall(vim.json.decode, ...))", (text,)).unwrap_or(false);
+    if !is_valid {
+        return Err("The clipboard text isn't valid JSON.".to_string());
+    }
+
+    Ok(pretty_print_json(text))
+}
+
+///
+/// `markdown_table`: The tab-separated rows, the columns are padded to the same width.
+///
+fn transform_markdown_table(text: &str) -> Result<Vec<String>, String> {
+    if !text.contains('\t') {
+        return Err("The clipboard text isn't tab-separated.".to_string());
+    }
+
+    let rows = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            line.split('\t')
+                .map(|cell| cell.trim().replace('|', "\\|"))
+                .collect::<Vec<String>>()
+        })
+        .collect::<Vec<Vec<String>>>();
+
+    let column_count = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let mut widths = vec![3usize; column_count];
+    for row in &rows {
+        for (index, cell) in row.iter().enumerate() {
+            widths[index] = widths[index].max(display_width(cell));
+        }
+    }
+
+    let render_row = |cells: Vec<String>| {
+        let cells = (0..column_count)
+            .map(|index| {
+                let cell = cells.get(index).map(|cell| cell.as_str()).unwrap_or("");
+                let padding = widths[index] - display_width(cell);
+                format!("{cell}{}", " ".repeat(padding))
+            })
+            .collect::<Vec<String>>();
+        format!("| {} |", cells.join(" | "))
+    };
+
+    let mut lines = Vec::new();
+    for (index, row) in rows.into_iter().enumerate() {
+        lines.push(render_row(row));
+        if index == 0 {
+            lines.push(render_row(
+                widths.iter().map(|width| "-".repeat(*width)).collect(),
+            ));
+        }
+    }
+
+    Ok(lines)
+}
+
+///
+/// `rust_string`: Escape the text into a single line string literal
+///
+fn transform_rust_string(text: &str) -> Result<Vec<String>, String> {
+    let mut literal = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            '\0' => literal.push_str("\\0"),
+            c if c.is_control() => literal.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+
+    Ok(vec![literal])
+}
+
+///
+/// `rust_raw_string`: Use enough `#` so the text can't close the literal
+///
+fn transform_rust_raw_string(text: &str) -> Result<Vec<String>, String> {
+    let mut hashes = String::new();
+    while text.contains(&format!("\"{hashes}")) {
+        hashes.push('#');
+    }
+
+    let literal = format!("r{hashes}\"{text}\"{hashes}");
+    Ok(literal.split('\n').map(|line| line.to_owned()).collect())
+}
+
+///
+/// `markdown_code_block`: Use the longer fence if the text has the code fence inside.
+///
+fn transform_markdown_code_block(text: &str) -> Result<Vec<String>, String> {
+    let fence = if text.contains("```") { "````" } else { "```" };
+
+    let mut lines = vec![fence.to_owned()];
+    lines.extend(text.lines().map(|line| line.to_owned()));
+    lines.push(fence.to_owned());
+    Ok(lines)
+}
+
+///
+/// Convert the clipboard text by the transformer, and then paste it.
+///
+fn paste_as(name: &str) {
+    let Some(transformer) = PASTE_TRANSFORMERS
+        .iter()
+        .find(|transformer| transformer.name == name)
+    else {
+        echo_message(
+            "structured_paste",
+            &format!("Unknown transformer: {name}"),
+            EchoLevel::Error,
+        );
+        return;
+    };
+
+    let text = get_register(get_clipboard_register())
+        .map(|content| content.text())
+        .unwrap_or_default();
+    let text = text.trim_end_matches('\n');
+    if text.trim().is_empty() {
+        echo_message(
+            "structured_paste",
+            "The clipboard is empty.",
+            EchoLevel::Info,
+        );
+        return;
+    }
+
+    match (transformer.transform)(text) {
+        Ok(lines) => {
+            let _ = exec_lua::<_, Object>(
+                "local lines, type = ...; vim.api.nvim_put(lines, type, true, true)",
+                (
+                    Array::from_iter(lines),
+                    if transformer.linewise { "l" } else { "c" },
+                ),
+            );
+        }
+        Err(error) => echo_message("structured_paste", &error, EchoLevel::Error),
+    }
+}
+
+///
+/// The transformers for the "Paste as" picker, the transformer name is the item data.
+///
+fn get_paste_transformer_picker_items() -> Vec<PickerItem> {
+    PASTE_TRANSFORMERS
+        .iter()
+        .map(|transformer| PickerItem {
+            columns: vec![
+                PickerItemColumn::new(transformer.name, None),
+                PickerItemColumn::new(transformer.desc, Some("Comment")),
+            ],
+            icon: None,
+            data: transformer.name.to_owned(),
+        })
+        .collect()
+}
+
+///
+///
+///
+pub fn setup() {
+    //
+    // Paste after the picker is gone and the cursor is back to the original window
+    //
+    register_picker_source(
+        "paste_as",
+        "Paste as",
+        get_paste_transformer_picker_items,
+        |selected_name: String| {
+            nvim::schedule(move |_| {
+                paste_as(selected_name.trim());
+                ()
+            });
+        },
+    );
+
+    register_user_command(
+        "PasteAs",
+        UserCommandSpec {
+            desc: "Paste the clipboard text converted by the transformer",
+            nargs: CommandNArgs::One,
+            complete: UserCommandComplete::List(|| {
+                PASTE_TRANSFORMERS
+                    .iter()
+                    .map(|transformer| transformer.name.to_owned())
+                    .collect()
+            }),
+            ..Default::default()
+        },
+        |args: UserCommandArgs| paste_as(args.arg(0).unwrap_or_default()),
+    );
+}
+
+use crate::{
+    extended_api::{
+        EchoLevel, UserCommandArgs, UserCommandComplete, UserCommandSpec, echo_message, exec_lua,
+        get_register, register_user_command,
+    },
+    picker::{PickerItem, PickerItemColumn, register_picker_source},
+    utils::{display_width, get_clipboard_register},
+};
+
+use nvim_oxi::{self as nvim, Array, Object, api::types::CommandNArgs};
@@ -0,0 +1,371 @@
+//! Tab-scoped buffer lists and buffer pinning:
+//!
+//! - Every tab page tracks the buffers entered in it, so each tab works on its own buffer set.
+//! - `:BufPin`: Toggle pinning the current buffer in the current tab. The pinned buffers come
+//!   first in the tab buffer list, and they're never auto-cleaned.
+//! - `<Tab>`/`<S-Tab>`: Cycle the current tab buffer list (pinned first) when
+//!   `TAB_SCOPED_BUFFER_CYCLING` (or `tab_buffers.scoped_cycling` of the config file) is `true`,
+//!   otherwise, it's the plain `:bnext`/`:bprevious`.
+//! - `<leader>bb` (also in the picker registry: `<leader>pp`): Pick a buffer of the current tab.
+//!
+
+///
+/// Set to `false` to cycle all listed buffers by `<Tab>`/`<S-Tab>`, it's the default of
+/// `tab_buffers.scoped_cycling` of the config file.
+///
+const TAB_SCOPED_BUFFER_CYCLING: bool = true;
+
+///
+/// Whether `<Tab>`/`<S-Tab>` cycle the current tab buffer list
+///
+fn is_tab_scoped_cycling() -> bool {
+    get_config_bool("tab_buffers.scoped_cycling", TAB_SCOPED_BUFFER_CYCLING)
+}
+
+///
+/// The buffer list of the tab page
+///
+#[derive(Debug, Default)]
+struct TabBufferList {
+    //
+    // In the entering order
+    //
+    buffers: Vec<BufHandle>,
+    pinned_buffers: Vec<BufHandle>,
+}
+
+impl TabBufferList {
+    ///
+    /// The pinned buffers first, and then follow by the rest
+    ///
+    fn ordered_buffers(&self) -> Vec<BufHandle> {
+        self.pinned_buffers
+            .iter()
+            .chain(
+                self.buffers
+                    .iter()
+                    .filter(|handle| !self.pinned_buffers.contains(handle)),
+            )
+            .copied()
+            .filter(|handle| Buffer::from(*handle).is_valid())
+            .collect()
+    }
+}
+
+///
+/// Private module-scope state: tab page handle <--> tab buffer list
+///
+static TAB_BUFFERS_STATE: LazyLock<Mutex<HashMap<i32, TabBufferList>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+///
+/// Whether the buffer is a listed normal buffer (not the terminal, quickfix, help, etc)
+///
+fn is_normal_buffer(buffer: &Buffer) -> bool {
+    let opts = OptionOpts::builder().buffer(buffer.clone()).build();
+    let buffer_type = get_option_value::<NvimString>("buftype", &opts).unwrap_or_default();
+    let is_listed = get_option_value::<bool>("buflisted", &opts).unwrap_or(false);
+
+    buffer_type.is_empty() && is_listed
+}
+
+///
+/// Get back the current tab page handle
+///
+fn current_tab_handle() -> i32 {
+    get_current_tabpage().handle()
+}
+
+///
+/// Whether the buffer is pinned in any tab page or not
+///
+pub fn is_buffer_pinned(buffer_handle: BufHandle) -> bool {
+    TAB_BUFFERS_STATE
+        .lock()
+        .unwrap()
+        .values()
+        .any(|list| list.pinned_buffers.contains(&buffer_handle))
+}
+
+///
+/// Get back the buffers of the current tab (pinned first), it's also the `<Tab>` cycling order.
+/// All the listed normal buffers if the tab scoped cycling is off.
+///
+pub fn get_current_tab_buffers() -> Vec<BufHandle> {
+    if !is_tab_scoped_cycling() {
+        return list_bufs()
+            .filter(|buffer| is_normal_buffer(buffer))
+            .map(|buffer| buffer.handle())
+            .collect();
+    }
+
+    TAB_BUFFERS_STATE
+        .lock()
+        .unwrap()
+        .get(&current_tab_handle())
+        .map(|list| list.ordered_buffers())
+        .unwrap_or_default()
+}
+
+///
+/// Toggle pinning the current buffer in the current tab
+///
+fn toggle_current_buffer_pin() {
+    const LOGGER_PREFIX: &'static str = "[ tab_buffers ]";
+
+    let buffer = Buffer::current();
+    if !is_normal_buffer(&buffer) {
+        nvim::print!("{LOGGER_PREFIX} Only the normal buffer can be pinned.");
+        return;
+    }
+
+    let buffer_handle = buffer.handle();
+    let is_pinned = {
+        let mut locked_state = TAB_BUFFERS_STATE.lock().unwrap();
+        let list = locked_state.entry(current_tab_handle()).or_default();
+
+        if list.pinned_buffers.contains(&buffer_handle) {
+            list.pinned_buffers
+                .retain(|handle| *handle != buffer_handle);
+            false
+        } else {
+            list.pinned_buffers.push(buffer_handle);
+            if !list.buffers.contains(&buffer_handle) {
+                list.buffers.push(buffer_handle);
+            }
+            true
+        }
+    };
+
+    let name = call_function::<_, String>("expand", ("%:t",)).unwrap_or_default();
+    nvim::print!(
+        "{LOGGER_PREFIX} {} '{name}'",
+        if is_pinned { "Pinned" } else { "Unpinned" }
+    );
+}
+
+///
+/// Switch to the next (`forward == true`) or the previous buffer in the current tab list
+///
+fn cycle_tab_buffers(forward: bool) {
+    let fallback_command = if forward { "bnext" } else { "bprevious" };
+    if !is_tab_scoped_cycling() {
+        let _ = command(fallback_command);
+        return;
+    }
+
+    let ordered_buffers = TAB_BUFFERS_STATE
+        .lock()
+        .unwrap()
+        .get(&current_tab_handle())
+        .map(|list| list.ordered_buffers())
+        .unwrap_or_default();
+
+    let current_handle = Buffer::current().handle();
+    let Some(current_index) = ordered_buffers
+        .iter()
+        .position(|handle| *handle == current_handle)
+    else {
+        //
+        // The current buffer is not in the tab list (e.g. the help buffer), jump to the first
+        // one, or fallback to the normal cycling if the list is empty.
+        //
+        match ordered_buffers.first() {
+            Some(first_handle) => {
+                let _ = command(&format!("buffer {first_handle}"));
+            }
+            None => {
+                let _ = command(fallback_command);
+            }
+        }
+        return;
+    };
+
+    let count = ordered_buffers.len();
+    let target_index = if forward {
+        (current_index + 1) % count
+    } else {
+        (current_index + count - 1) % count
+    };
+
+    if target_index != current_index {
+        let _ = command(&format!("buffer {}", ordered_buffers[target_index]));
+    }
+}
+
+///
+///
+///
+fn get_tab_buffer_picker_items() -> Vec<PickerItem> {
+    let (ordered_buffers, pinned_buffers) = {
+        let locked_state = TAB_BUFFERS_STATE.lock().unwrap();
+        match locked_state.get(&current_tab_handle()) {
+            Some(list) => (list.ordered_buffers(), list.pinned_buffers.clone()),
+            None => (Vec::new(), Vec::new()),
+        }
+    };
+
+    ordered_buffers
+        .into_iter()
+        .map(|handle| {
+            let name = call_function::<_, String>("bufname", (handle,)).unwrap_or_default();
+            let display_name =
+                call_function::<_, String>("fnamemodify", (name, ":~:.")).unwrap_or_default();
+
+            PickerItem {
+                columns: vec![
+                    PickerItemColumn::new(
+                        if pinned_buffers.contains(&handle) {
+                            "pinned"
+                        } else {
+                            ""
+                        },
+                        Some("Special"),
+                    ),
+                    PickerItemColumn::new(&display_name, None),
+                ],
+                icon: None,
+                data: handle.to_string(),
+            }
+        })
+        .collect()
+}
+
+///
+/// Switch to the selected buffer in the next event loop tick, as the picker windows are still
+/// closing at this moment.
+///
+fn tab_buffer_selected_callback(selected_handle: String) {
+    nvim::schedule(move |_| {
+        if let Ok(handle) = selected_handle.trim().parse::<BufHandle>() {
+            let _ = command(&format!("buffer {handle}"));
+        }
+    });
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_autocmd(
+        vec!["BufEnter", "BufWipeout"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-tab-buffers-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                let buffer_handle = args.buffer.handle();
+                let mut locked_state = TAB_BUFFERS_STATE.lock().unwrap();
+
+                if args.event == "BufWipeout" {
+                    for list in locked_state.values_mut() {
+                        list.buffers.retain(|handle| *handle != buffer_handle);
+                        list.pinned_buffers
+                            .retain(|handle| *handle != buffer_handle);
+                    }
+                } else if is_normal_buffer(&args.buffer) {
+                    //
+                    // Drop the closed tab pages, and then add the buffer to the current tab
+                    //
+                    let tab_handles = list_tabpages()
+                        .map(|tab| tab.handle())
+                        .collect::<Vec<i32>>();
+                    locked_state.retain(|tab_handle, _| tab_handles.contains(tab_handle));
+
+                    let list = locked_state.entry(current_tab_handle()).or_default();
+                    if !list.buffers.contains(&buffer_handle) {
+                        list.buffers.push(buffer_handle);
+                    }
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    let _ = create_user_command(
+        "BufPin",
+        |_: CommandArgs| {
+            toggle_current_buffer_pin();
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Toggle pinning the current buffer in the current tab")
+            .build(),
+    );
+
+    let cycle_keybindings: Vec<(&str, &str, bool)> = vec![
+        ("<Tab>", "'Tab': Switch to next buffer", true),
+        ("<S-Tab>", "'Shift + Tab': Switch to prev buffer", false),
+    ];
+
+    for (key, desc, forward) in cycle_keybindings {
+        let _ = set_keymap(
+            Mode::Normal,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(desc)
+                .silent(true)
+                .callback(move |_| {
+                    cycle_tab_buffers(forward);
+                    ()
+                })
+                .build(),
+        );
+    }
+
+    let _ = set_keymap(
+        Mode::Normal,
+        "<leader>bb",
+        "",
+        &SetKeymapOpts::builder()
+            .desc("'<leader>bb': Pick a buffer of the current tab")
+            .silent(true)
+            .callback(|_| {
+                open_registered_picker("tab_buffers");
+                ()
+            })
+            .build(),
+    );
+
+    //
+    // Contribute the current tab buffers to the picker registry (`<leader>pp`)
+    //
+    register_picker_source(
+        "tab_buffers",
+        "Tab buffers",
+        get_tab_buffer_picker_items,
+        tab_buffer_selected_callback,
+    );
+}
+
+use crate::{
+    config::get_config_bool,
+    picker::{PickerItem, PickerItemColumn, open_registered_picker, register_picker_source},
+};
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim, BufHandle, String as NvimString,
+    api::{
+        Buffer, call_function, command, create_augroup, create_autocmd, create_user_command,
+        get_current_tabpage, get_option_value, list_bufs, list_tabpages,
+        opts::{
+            CreateAugroupOpts, CreateAutocmdOpts, CreateCommandOpts, OptionOpts, SetKeymapOpts,
+        },
+        set_keymap,
+        types::{AutocmdCallbackArgs, CommandArgs, Mode},
+    },
+};
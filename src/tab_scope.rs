@@ -0,0 +1,150 @@
+//! Tab-scoped terminals and command buffers, so running the project commands in one tab page
+//! doesn't hijack the split layout of another tab page:
+//!
+//! - The project command output (the command result buffer or the `:terminal` job) is shown in
+//!   the output window of the current tab page (the right split window of it), and the source
+//!   locations are opened in the left split window of the current tab page.
+//! - Each tab page has its own floating terminal instances (`<leader>ft`, `<leader>f1..f3`),
+//!   they're wiped out when the tab page is closed.
+//!
+//! Set `TAB_SCOPED_OUTPUT` to `false` to share them across all tab pages.
+//!
+
+///
+/// Set to `false` to share the output window and the floating terminals across all tab pages
+///
+const TAB_SCOPED_OUTPUT: bool = true;
+
+///
+/// The scope of the non tab-scoped mode
+///
+const GLOBAL_SCOPE: i32 = 0;
+
+///
+/// Private module-scope state: tab page handle <--> the output window of the tab page
+///
+static TAB_SCOPE_STATE: LazyLock<Mutex<HashMap<i32, WinHandle>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+///
+/// Get back the current scope: the current tab page handle, or `GLOBAL_SCOPE` if it's not in
+/// the tab-scoped mode.
+///
+pub fn current_tab_scope() -> i32 {
+    if TAB_SCOPED_OUTPUT {
+        get_current_tabpage().handle()
+    } else {
+        GLOBAL_SCOPE
+    }
+}
+
+///
+/// Whether the tab page of the scope is still open or not
+///
+pub fn is_tab_scope_alive(scope: i32) -> bool {
+    scope == GLOBAL_SCOPE || list_tabpages().any(|tab| tab.handle() == scope)
+}
+
+///
+/// Get back the windows of the current tab page
+///
+fn get_current_tab_windows() -> Vec<Window> {
+    get_current_tabpage()
+        .list_wins()
+        .map(|windows| windows.collect())
+        .unwrap_or_default()
+}
+
+///
+/// Get back the left/right-split window of the current tab page, or of all tab pages if it's
+/// not in the tab-scoped mode.
+///
+pub fn get_tab_split_window(most_right: bool) -> Option<Window> {
+    if !TAB_SCOPED_OUTPUT {
+        return get_split_window(most_right);
+    }
+
+    find_split_window(get_current_tab_windows(), most_right)
+}
+
+///
+/// Get back the output window of the current tab page: the last one which showed the command
+/// output (if it's still in the current tab page), or the right split window.
+///
+pub fn get_tab_output_window() -> Option<Window> {
+    let output_window_handle = TAB_SCOPE_STATE
+        .lock()
+        .unwrap()
+        .get(&current_tab_scope())
+        .copied();
+
+    if let Some(handle) = output_window_handle
+        && get_current_tab_windows()
+            .iter()
+            .any(|window| window.handle() == handle)
+    {
+        return Some(Window::from(handle));
+    }
+
+    get_tab_split_window(true)
+}
+
+///
+/// Remember the output window of the current tab page
+///
+pub fn set_tab_output_window(window: &Window) {
+    TAB_SCOPE_STATE
+        .lock()
+        .unwrap()
+        .insert(current_tab_scope(), window.handle());
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_autocmd(
+        vec!["TabClosed"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-tab-scope-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|_: AutocmdCallbackArgs| {
+                TAB_SCOPE_STATE
+                    .lock()
+                    .unwrap()
+                    .retain(|scope, _| is_tab_scope_alive(*scope));
+
+                wipe_closed_tab_floating_terminals();
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+use crate::{
+    floating_terminal::wipe_closed_tab_floating_terminals,
+    utils::{find_split_window, get_split_window},
+};
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    WinHandle,
+    api::{
+        Window, create_augroup, create_autocmd, get_current_tabpage, list_tabpages,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts},
+        types::AutocmdCallbackArgs,
+    },
+};
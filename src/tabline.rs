@@ -0,0 +1,246 @@
+//! The `tabline` as the buffer line, it shows the buffers of the current tab (the same list and
+//! order of the `<Tab>`/`<S-Tab>` cycling, pinned first):
+//!
+//! ```text
+//!  1 lib.rs   2 picker/mod.rs ●   3 picker.rs                                    tab 1/2
+//! ```
+//!
+//! - The label has the index (for `<leader>1..9`), the file name (with the parent directory if
+//!   the name is not unique), the modified dot and the pin mark.
+//! - Left click switches to the buffer, middle click deletes it.
+//! - `<leader>1..9`: Switch to the buffer of the index.
+//!
+
+///
+/// The Lua global function names of the `tabline` expression and the click handler
+///
+const TABLINE_EXPR_FUNCTION: &'static str = "MyConfigTabline";
+const TABLINE_CLICK_FUNCTION: &'static str = "MyConfigTablineClick";
+
+///
+/// The marks of the modified and pinned buffer
+///
+const TABLINE_MODIFIED_MARK: &'static str = "●";
+const TABLINE_PINNED_MARK: &'static str = "📌";
+
+///
+/// The `tabline` text can't have the bare `%`
+///
+fn escape_tabline_text(text: &str) -> String {
+    text.replace('%', "%%")
+}
+
+///
+/// Get back the labels of the buffers, the parent directory is added to the duplicated file names,
+/// e.g. `picker/mod.rs` and `extended_api/mod.rs`.
+///
+fn get_buffer_labels(buffer_handles: &[BufHandle]) -> Vec<String> {
+    let names = buffer_handles
+        .iter()
+        .map(|handle| {
+            let name = call_function::<_, String>("bufname", (*handle,)).unwrap_or_default();
+            if name.is_empty() {
+                return (String::from("[No Name]"), String::from("[No Name]"));
+            }
+
+            let tail = call_function::<_, String>("fnamemodify", (name.as_str(), ":t"))
+                .unwrap_or_default();
+            let parent = call_function::<_, String>("fnamemodify", (name.as_str(), ":h:t"))
+                .unwrap_or_default();
+            (tail.clone(), format!("{parent}/{tail}"))
+        })
+        .collect::<Vec<(String, String)>>();
+
+    names
+        .iter()
+        .map(|(tail, with_parent)| {
+            let duplicated_count = names.iter().filter(|(other, _)| other == tail).count();
+            if duplicated_count > 1 {
+                with_parent.clone()
+            } else {
+                tail.clone()
+            }
+        })
+        .collect()
+}
+
+///
+/// Render the `tabline`
+///
+fn render_tabline() -> String {
+    let buffer_handles = get_current_tab_buffers();
+    let labels = get_buffer_labels(&buffer_handles);
+    let current_handle = Buffer::current().handle();
+
+    let mut tabline = String::new();
+    for (index, (handle, label)) in buffer_handles.iter().zip(labels).enumerate() {
+        let is_modified = get_option_value::<bool>(
+            "modified",
+            &OptionOpts::builder().buffer(Buffer::from(*handle)).build(),
+        )
+        .unwrap_or(false);
+
+        let mut text = format!(" {} {}", index + 1, escape_tabline_text(&label));
+        if is_modified {
+            text.push(' ');
+            text.push_str(TABLINE_MODIFIED_MARK);
+        }
+        if is_buffer_pinned(*handle) {
+            text.push(' ');
+            text.push_str(TABLINE_PINNED_MARK);
+        }
+        text.push(' ');
+
+        let highlight = if *handle == current_handle {
+            "TabLineSel"
+        } else {
+            "TabLine"
+        };
+
+        //
+        // `%{minwid}@{function}@` is the clickable region, `minwid` is the buffer handle
+        //
+        tabline.push_str(&format!(
+            "%#{highlight}#%{handle}@v:lua.{TABLINE_CLICK_FUNCTION}@{text}%X"
+        ));
+    }
+
+    tabline.push_str("%#TabLineFill#%=");
+
+    let tab_count = list_tabpages().count();
+    if tab_count > 1 {
+        let tab_number = call_function::<_, i64>("tabpagenr", ()).unwrap_or(1);
+        tabline.push_str(&format!("%#TabLine# tab {tab_number}/{tab_count} "));
+    }
+
+    tabline
+}
+
+///
+/// Click the buffer label: left click switches to it, middle click deletes it.
+///
+fn on_tabline_click(buffer_handle: BufHandle, button: &str) {
+    let buffer_command = match button {
+        "l" => "buffer",
+        "m" => "bdelete",
+        _ => return,
+    };
+
+    nvim::schedule(move |_| {
+        if Buffer::from(buffer_handle).is_valid() {
+            let _ = command(&format!("{buffer_command} {buffer_handle}"));
+        }
+        let _ = command("redrawtabline");
+        ()
+    });
+}
+
+///
+/// `<leader>1..9`: Switch to the buffer of the index (1-based)
+///
+fn switch_to_buffer_index(index: usize) {
+    let buffer_handles = get_current_tab_buffers();
+    match buffer_handles.get(index - 1) {
+        Some(handle) => {
+            let _ = command(&format!("buffer {handle}"));
+        }
+        None => echo_message(
+            "tabline",
+            &format!("No buffer {index} in the current tab."),
+            EchoLevel::Info,
+        ),
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    //
+    // Expose the `tabline` expression and the click handler as the Lua global functions, so
+    // `%!v:lua.{name}()` and `%@v:lua.{name}@` can call them, the click handler is called with
+    // `(minwid, clicks, button, modifiers)`.
+    //
+    let _ = exec_lua::<_, Object>(
+        "local name, f = ...; _G[name] = f",
+        (
+            TABLINE_EXPR_FUNCTION,
+            Function::<(), String>::from_fn(|()| render_tabline()),
+        ),
+    );
+    let _ = exec_lua::<_, Object>(
+        "local name, f = ...; _G[name] = function(minwid, clicks, button) f(minwid, button) end",
+        (
+            TABLINE_CLICK_FUNCTION,
+            Function::<(i64, String), ()>::from_fn(|(minwid, button): (i64, String)| {
+                on_tabline_click(minwid as BufHandle, &button)
+            }),
+        ),
+    );
+
+    let global_opts = OptionOpts::builder().scope(OptionScope::Global).build();
+    let _ = set_option_value("showtabline", 2, &global_opts);
+    let _ = set_option_value(
+        "tabline",
+        format!("%!v:lua.{TABLINE_EXPR_FUNCTION}()"),
+        &global_opts,
+    );
+
+    //
+    // The modified dot changes without leaving the buffer
+    //
+    let _ = create_autocmd(
+        vec!["BufModifiedSet"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-tabline-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|_| {
+                let _ = command("redrawtabline");
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    for index in 1..=9usize {
+        let _ = set_keymap(
+            Mode::Normal,
+            &format!("<leader>{index}"),
+            "",
+            &SetKeymapOpts::builder()
+                .desc(&format!(
+                    "'<leader>{index}': Switch to buffer {index} in the tabline"
+                ))
+                .silent(true)
+                .callback(move |_| {
+                    switch_to_buffer_index(index);
+                    ()
+                })
+                .build(),
+        );
+    }
+}
+
+use crate::{
+    extended_api::{EchoLevel, echo_message, exec_lua},
+    tab_buffers::{get_current_tab_buffers, is_buffer_pinned},
+};
+
+use nvim_oxi::{
+    self as nvim, BufHandle, Function, Object,
+    api::{
+        Buffer, call_function, command, create_augroup, create_autocmd, get_option_value,
+        list_tabpages,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, OptionScope, SetKeymapOpts},
+        set_keymap, set_option_value,
+        types::Mode,
+    },
+};
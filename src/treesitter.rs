@@ -0,0 +1,415 @@
+//! The treesitter setup for the languages in `TREESITTER_LANGUAGES`:
+//!
+//! - After startup (`VimEnter`), the missing parsers are installed by `:TSInstall` if
+//!   `nvim-treesitter` is there, otherwise they're listed in the warning message.
+//! - The highlighting (and the indent if `nvim-treesitter` provides it) is enabled for the buffer
+//!   of the language.
+//! - The incremental selection (only in the buffer which has the treesitter highlighting):
+//!
+//!   `<CR>` in `NORMAL` mode: Select the node under the cursor
+//!   `<CR>` in `VISUAL` mode: Expand the selection to the parent node
+//!   `<BS>` in `VISUAL` mode: Shrink the selection back to the previous node
+//!
+
+///
+/// Define your treesitter languages here (the parser names, not the filetypes)
+///
+const TREESITTER_LANGUAGES: [&'static str; 14] = [
+    "rust",
+    "c",
+    "cpp",
+    "zig",
+    "lua",
+    "vim",
+    "vimdoc",
+    "bash",
+    "fish",
+    "json",
+    "toml",
+    "yaml",
+    "markdown",
+    "markdown_inline",
+];
+
+///
+/// The node range: `(start_row, start_col, end_row, end_col)`, 0-based and the end is exclusive,
+/// the same with `TSNode:range()`.
+///
+type NodeRange = (usize, usize, usize, usize);
+
+///
+/// The selected node ranges of the incremental selection, the last one is the current selection.
+///
+#[derive(Debug, Default)]
+struct IncrementalSelection {
+    buffer_handle: i32,
+    ranges: Vec<NodeRange>,
+}
+
+///
+/// Private module-scope state
+///
+static INCREMENTAL_SELECTION_STATE: LazyLock<Mutex<IncrementalSelection>> =
+    LazyLock::new(|| Mutex::new(IncrementalSelection::default()));
+
+///
+/// Get back the configured languages which don't have the parser
+///
+fn get_missing_parsers() -> Vec<&'static str> {
+    TREESITTER_LANGUAGES
+        .iter()
+        .copied()
+        .filter(|language| {
+            let result = try_exec_lua::<_, bool>(
+                r#"local ok, added = pcall(vim.treesitter.language.add, ...)
+return ok and added == true"#,
+                (*language,),
+            )
+            .context(&format!("check the '{language}' parser"));
+            !report("treesitter", result).unwrap_or(false)
+        })
+        .collect()
+}
+
+///
+/// Install the missing parsers by `nvim-treesitter`, or tell which ones are missing
+///
+fn ensure_parsers_installed() {
+    let missing_parsers = get_missing_parsers();
+    if missing_parsers.is_empty() {
+        return;
+    }
+
+    let has_ts_install = call_function::<_, i64>("exists", (":TSInstall",)).unwrap_or(0) == 2;
+    if has_ts_install {
+        let _ = command(&format!("TSInstall {}", missing_parsers.join(" ")));
+    } else {
+        echo_message(
+            "treesitter",
+            &format!(
+                "Missing parsers: {}, install them by 'nvim-treesitter' or into 'parser/'.",
+                missing_parsers.join(", ")
+            ),
+            EchoLevel::Warning,
+        );
+    }
+}
+
+///
+/// Enable the highlighting and the indent for the buffer, return `true` if the highlighting is
+/// enabled.
+///
+fn enable_treesitter_for_buffer(buffer: &Buffer, filetype: &str) -> bool {
+    //
+    // `""` means no language, so `None` is only the failure.
+    //
+    let result = try_exec_lua::<_, String>(
+        "return vim.treesitter.language.get_lang(...) or ''",
+        (filetype,),
+    )
+    .context(&format!("get the treesitter language of '{filetype}'"));
+    let Some(language) = report("treesitter", result) else {
+        return false;
+    };
+    if !TREESITTER_LANGUAGES.contains(&language.as_str()) {
+        return false;
+    }
+
+    //
+    // `vim.treesitter` doesn't have the indent, it's only provided by `nvim-treesitter` (the
+    // `main` branch has the Lua function, the `master` branch has the Vim function).
+    //
+    let lua_code = r#"local bufnr, language = ...
+if not pcall(vim.treesitter.start, bufnr, language) then
+    return false
+end
+
+local has_ts, ts = pcall(require, "nvim-treesitter")
+if has_ts and type(ts.indentexpr) == "function" then
+    vim.bo[bufnr].indentexpr = "v:lua.require'nvim-treesitter'.indentexpr()"
+elseif vim.fn.exists("*nvim_treesitter#indent") == 1 then
+    vim.bo[bufnr].indentexpr = "nvim_treesitter#indent()"
+end
+return true"#;
+
+    let result = try_exec_lua::<_, bool>(lua_code, (buffer.handle() as i64, language.as_str()))
+        .context(&format!("start the treesitter '{language}' highlighting"));
+    report("treesitter", result).unwrap_or(false)
+}
+
+///
+/// Get back the smallest named node range which contains the given range and is larger than it,
+/// `None` if there is no parser or it's already the root node.
+///
+fn get_enclosing_node_range(range: NodeRange) -> Option<NodeRange> {
+    //
+    // The empty list means no node, so `None` of `try_exec_lua()` is only the failure.
+    //
+    let lua_code = r#"local start_row, start_col, end_row, end_col = ...
+local parser = vim.treesitter.get_parser(0, nil, { error = false })
+if not parser then
+    return {}
+end
+parser:parse()
+
+local node = parser:named_node_for_range({ start_row, start_col, end_row, end_col })
+while node do
+    local sr, sc, er, ec = node:range()
+    if sr ~= start_row or sc ~= start_col or er ~= end_row or ec ~= end_col then
+        return { sr, sc, er, ec }
+    end
+    node = node:parent()
+end
+return {}"#;
+
+    let result = try_exec_lua::<_, Vec<i64>>(
+        lua_code,
+        (
+            range.0 as i64,
+            range.1 as i64,
+            range.2 as i64,
+            range.3 as i64,
+        ),
+    )
+    .context("get the enclosing treesitter node");
+    let node_range = report("treesitter", result)?;
+
+    match node_range.as_slice() {
+        [start_row, start_col, end_row, end_col] => Some((
+            *start_row as usize,
+            *start_col as usize,
+            *end_row as usize,
+            *end_col as usize,
+        )),
+        _ => None,
+    }
+}
+
+///
+/// Get back the current `VISUAL` selection as the node range
+///
+fn get_visual_selection_range() -> Option<NodeRange> {
+    let get_position = |mark: &str| {
+        let position = call_function::<_, Vec<i64>>("getpos", (mark,)).ok()?;
+        Some((
+            (*position.get(1)? - 1).max(0) as usize,
+            (*position.get(2)? - 1).max(0) as usize,
+        ))
+    };
+
+    let (start, end) = {
+        let (visual_start, cursor) = (get_position("v")?, get_position(".")?);
+        if visual_start <= cursor {
+            (visual_start, cursor)
+        } else {
+            (cursor, visual_start)
+        }
+    };
+
+    Some((start.0, start.1, end.0, end.1 + 1))
+}
+
+///
+/// Select the node range in `VISUAL` mode
+///
+fn select_node_range(range: NodeRange) {
+    let mut buffer = Buffer::current();
+
+    //
+    // The exclusive end at the line beginning is the end of the previous line
+    //
+    let (end_row, end_col) = if range.3 == 0 && range.2 > range.0 {
+        let previous_line_len = buffer
+            .get_lines(range.2 - 1..range.2, true)
+            .ok()
+            .and_then(|mut lines| lines.next())
+            .map_or(0, |line| line.to_string().len());
+        (range.2 - 1, previous_line_len.saturating_sub(1))
+    } else {
+        (range.2, range.3.saturating_sub(1))
+    };
+
+    //
+    // Leave the current `VISUAL` mode (if any), then reselect the marks by `gv`
+    //
+    let _ = command("execute \"normal! \\<Esc>\"");
+    let _ = buffer.set_mark('<', range.0 + 1, range.1, &SetMarkOpts::default());
+    let _ = buffer.set_mark('>', end_row + 1, end_col, &SetMarkOpts::default());
+    let _ = command("normal! gv");
+}
+
+///
+/// `<CR>` in `NORMAL` mode: Select the node under the cursor
+///
+fn start_incremental_selection() {
+    let (row, col) = Window::current().get_cursor().unwrap_or((1, 0));
+    let Some(node_range) = get_enclosing_node_range((row - 1, col, row - 1, col)) else {
+        return;
+    };
+
+    {
+        let mut state = INCREMENTAL_SELECTION_STATE.lock().unwrap();
+        state.buffer_handle = Buffer::current().handle();
+        state.ranges = vec![node_range];
+    }
+
+    select_node_range(node_range);
+}
+
+///
+/// `<CR>` in `VISUAL` mode: Expand the selection to the parent node
+///
+fn expand_incremental_selection() {
+    let buffer_handle = Buffer::current().handle();
+
+    //
+    // The selection may be changed by hand, then it starts over from the current selection.
+    //
+    let current_range = {
+        let mut state = INCREMENTAL_SELECTION_STATE.lock().unwrap();
+        let visual_range = get_visual_selection_range();
+        let is_same_selection = state.buffer_handle == buffer_handle
+            && state.ranges.last().copied().is_some_and(|last| {
+                visual_range.is_some_and(|visual| visual.0 == last.0 && visual.1 == last.1)
+            });
+
+        if !is_same_selection {
+            state.buffer_handle = buffer_handle;
+            state.ranges = visual_range.into_iter().collect();
+        }
+        state.ranges.last().copied()
+    };
+
+    let Some(node_range) = current_range.and_then(get_enclosing_node_range) else {
+        return;
+    };
+
+    INCREMENTAL_SELECTION_STATE
+        .lock()
+        .unwrap()
+        .ranges
+        .push(node_range);
+
+    select_node_range(node_range);
+}
+
+///
+/// `<BS>` in `VISUAL` mode: Shrink the selection back to the previous node
+///
+fn shrink_incremental_selection() {
+    let previous_range = {
+        let mut state = INCREMENTAL_SELECTION_STATE.lock().unwrap();
+        if state.buffer_handle != Buffer::current().handle() || state.ranges.len() < 2 {
+            None
+        } else {
+            state.ranges.pop();
+            state.ranges.last().copied()
+        }
+    };
+
+    if let Some(range) = previous_range {
+        select_node_range(range);
+    }
+}
+
+///
+/// Set the incremental selection keymaps of the buffer
+///
+fn set_incremental_selection_keymaps(buffer: &mut Buffer) {
+    let selection_keybindings: Vec<(Mode, &str, &str, fn())> = vec![
+        (
+            Mode::Normal,
+            "<CR>",
+            "'<CR>': Select the treesitter node",
+            start_incremental_selection,
+        ),
+        (
+            Mode::Visual,
+            "<CR>",
+            "'<CR>': Expand the selection to the parent treesitter node",
+            expand_incremental_selection,
+        ),
+        (
+            Mode::Visual,
+            "<BS>",
+            "'<BS>': Shrink the selection to the previous treesitter node",
+            shrink_incremental_selection,
+        ),
+    ];
+
+    for (mode, key, desc, callback) in selection_keybindings {
+        let _ = buffer.set_keymap(
+            mode,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(desc)
+                .silent(true)
+                .callback(move |_| {
+                    callback();
+                    ()
+                })
+                .build(),
+        );
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    let group = create_augroup(
+        "custom-treesitter-group",
+        &CreateAugroupOpts::builder().clear(true).build(),
+    )
+    .unwrap();
+
+    //
+    // The parsers from the plugins are only available after startup
+    //
+    let _ = create_autocmd(
+        vec!["VimEnter"],
+        &CreateAutocmdOpts::builder()
+            .group(group)
+            .callback(|_| {
+                ensure_parsers_installed();
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                true
+            })
+            .build(),
+    );
+
+    let _ = create_autocmd(
+        vec!["FileType"],
+        &CreateAutocmdOpts::builder()
+            .group(group)
+            .callback(|args: AutocmdCallbackArgs| {
+                let mut buffer = args.buffer;
+                if enable_treesitter_for_buffer(&buffer, &args.r#match) {
+                    set_incremental_selection_keymaps(&mut buffer);
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+use crate::{
+    error::{ResultContext, report},
+    extended_api::{EchoLevel, echo_message, try_exec_lua},
+};
+
+use std::sync::{LazyLock, Mutex};
+
+use nvim_oxi::api::{
+    Buffer, Window, call_function, command, create_augroup, create_autocmd,
+    opts::{CreateAugroupOpts, CreateAutocmdOpts, SetKeymapOpts, SetMarkOpts},
+    types::{AutocmdCallbackArgs, Mode},
+};
@@ -0,0 +1,254 @@
+//! True color fallback for the terminals without 24-bit color support.
+//!
+//! `settings` turns `termguicolors` on, which renders the wrong colors in a 256-color terminal
+//! (e.g. the Linux console, `tmux` without `Tc`, old `screen`). If the terminal doesn't support
+//! true color (detected by `$COLORTERM`, `$TERM` and the known terminal programs), it turns
+//! `termguicolors` off, and then converts the `guifg`/`guibg`/`guisp` colors of all highlight
+//! groups to the nearest xterm 256-color (`ctermfg`/`ctermbg`/`ctermul`).
+//!
+//! The conversion runs again after every colorscheme change (`ColorScheme`).
+//!
+//! - `:TrueColorStatus`: Print the detection result.
+//!
+
+///
+/// Set to `false` to always keep `termguicolors` on
+///
+const ENABLE_TRUE_COLOR_FALLBACK: bool = true;
+
+///
+/// The xterm 256-color 6x6x6 cube channel levels (color 16 ~ 231)
+///
+const XTERM_CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+///
+/// The highlight color attributes and their 256-color counterparts
+///
+const HIGHLIGHT_COLOR_ATTRIBUTES: [(&'static str, &'static str); 3] =
+    [("fg", "ctermfg"), ("bg", "ctermbg"), ("sp", "ctermul")];
+
+///
+/// Whether the current terminal supports true color or not, and the reason.
+///
+fn detect_true_color_support() -> (bool, String) {
+    let is_gui = call_function::<_, bool>("luaeval", ("vim.g.neovide ~= nil",)).unwrap_or(false)
+        || call_function::<_, i64>("has", ("gui_running",)).unwrap_or(0) == 1
+        || list_uis().iter().any(|ui| !ui.is_tty);
+    if is_gui {
+        return (true, "GUI client".to_string());
+    }
+
+    let color_term = std::env::var("COLORTERM").unwrap_or_default();
+    if color_term == "truecolor" || color_term == "24bit" {
+        return (true, format!("$COLORTERM={color_term}"));
+    }
+
+    let term = std::env::var("TERM").unwrap_or_default();
+    if term.ends_with("-direct") || term.contains("kitty") || term.contains("alacritty") {
+        return (true, format!("$TERM={term}"));
+    }
+
+    let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+    if ["iTerm.app", "WezTerm", "ghostty", "vscode"].contains(&term_program.as_str()) {
+        return (true, format!("$TERM_PROGRAM={term_program}"));
+    }
+
+    (
+        false,
+        format!("$COLORTERM='{color_term}', $TERM='{term}', $TERM_PROGRAM='{term_program}'"),
+    )
+}
+
+///
+/// Get back the nearest xterm 256-color index of the given RGB color (`0xRRGGBB`), both the
+/// color cube and the grayscale ramp are compared.
+///
+fn rgb_to_xterm_256(rgb: u32) -> u8 {
+    let (red, green, blue) = (
+        ((rgb >> 16) & 0xFF) as i32,
+        ((rgb >> 8) & 0xFF) as i32,
+        (rgb & 0xFF) as i32,
+    );
+
+    let distance =
+        |r: i32, g: i32, b: i32| (red - r).pow(2) + (green - g).pow(2) + (blue - b).pow(2);
+
+    let nearest_cube_index = |channel: i32| {
+        XTERM_CUBE_LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, level)| (channel - **level as i32).abs())
+            .map(|(index, _)| index)
+            .unwrap_or(0)
+    };
+
+    let (red_index, green_index, blue_index) = (
+        nearest_cube_index(red),
+        nearest_cube_index(green),
+        nearest_cube_index(blue),
+    );
+    let cube_color = 16 + 36 * red_index + 6 * green_index + blue_index;
+    let cube_distance = distance(
+        XTERM_CUBE_LEVELS[red_index] as i32,
+        XTERM_CUBE_LEVELS[green_index] as i32,
+        XTERM_CUBE_LEVELS[blue_index] as i32,
+    );
+
+    //
+    // The grayscale ramp (color 232 ~ 255): 8, 18, ..., 238
+    //
+    let average = (red + green + blue) / 3;
+    let gray_index = ((average - 8).max(0) / 10).min(23);
+    let gray_level = 8 + gray_index * 10;
+    let gray_distance = distance(gray_level, gray_level, gray_level);
+
+    if gray_distance < cube_distance {
+        (232 + gray_index) as u8
+    } else {
+        cube_color as u8
+    }
+}
+
+///
+/// Convert the gui colors of all highlight groups to the 256-color ones, return the converted
+/// highlight group count.
+///
+fn degrade_highlight_colors() -> usize {
+    let Ok(highlights) = call_function::<_, Dictionary>("luaeval", ("vim.api.nvim_get_hl(0, {})",))
+    else {
+        return 0;
+    };
+
+    let mut converted_count = 0usize;
+
+    for (name, definition) in highlights {
+        let Ok(mut definition) = Dictionary::from_object(definition) else {
+            continue;
+        };
+
+        //
+        // The linked group gets the colors from the target group
+        //
+        if definition.get("link").is_some() {
+            continue;
+        }
+
+        let mut has_changes = false;
+        for (gui_attribute, cterm_attribute) in HIGHLIGHT_COLOR_ATTRIBUTES {
+            let Some(color) = definition
+                .get(gui_attribute)
+                .and_then(|color| i64::from_object(color.clone()).ok())
+            else {
+                continue;
+            };
+
+            definition.insert(cterm_attribute, rgb_to_xterm_256(color as u32) as i64);
+            has_changes = true;
+        }
+
+        if !has_changes {
+            continue;
+        }
+
+        let set_result = call_function::<_, Object>(
+            "luaeval",
+            (
+                "vim.api.nvim_set_hl(0, _A[1], _A[2])",
+                Array::from_iter([Object::from(name), Object::from(definition)]),
+            ),
+        );
+
+        if set_result.is_ok() {
+            converted_count += 1;
+        }
+    }
+
+    converted_count
+}
+
+///
+/// Turn `termguicolors` off and degrade the current colorscheme
+///
+fn apply_true_color_fallback() {
+    let opts = OptionOpts::builder().scope(OptionScope::Global).build();
+    let _ = set_option_value("termguicolors", false, &opts);
+    let _ = degrade_highlight_colors();
+}
+
+///
+///
+///
+pub fn setup() {
+    let _ = create_user_command(
+        "TrueColorStatus",
+        |_: CommandArgs| {
+            let (supported, reason) = detect_true_color_support();
+            let uis = list_uis()
+                .iter()
+                .map(|ui| {
+                    format!(
+                        "{}x{}{}{}{}",
+                        ui.width,
+                        ui.height,
+                        if ui.is_tty { " tty" } else { " gui" },
+                        if ui.rgb { " rgb" } else { "" },
+                        if ui.multigrid { " multigrid" } else { "" }
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            nvim::print!(
+                "[ true_color ] True color: {}, detected by: {reason}, UIs: [{uis}]",
+                if supported {
+                    "yes"
+                } else {
+                    "no (256-color fallback)"
+                }
+            );
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Print the true color detection result")
+            .build(),
+    );
+
+    if !ENABLE_TRUE_COLOR_FALLBACK || detect_true_color_support().0 {
+        return;
+    }
+
+    apply_true_color_fallback();
+
+    let _ = create_autocmd(
+        vec!["ColorScheme"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-true-color-fallback-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|_| {
+                apply_true_color_fallback();
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+use crate::extended_api::list_uis;
+
+use nvim_oxi::{
+    self as nvim, Array, Dictionary, Object,
+    api::{
+        call_function, create_augroup, create_autocmd, create_user_command,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, CreateCommandOpts, OptionOpts, OptionScope},
+        set_option_value,
+        types::CommandArgs,
+    },
+    conversion::FromObject,
+};
@@ -0,0 +1,445 @@
+//! Local usage statistics of my own keybindings and user commands, so the never used ones can
+//! be pruned from the Rust tables. Nothing leaves the machine, the counts are stored in
+//! `stdpath("data")/my_neovim_configuration/usage_stats/usage.json`.
+//!
+//! - The global keybindings with the description (all the keybindings of this configuration
+//!   have one) are recorded by watching the typed keys (`vim.on_key()`), the keybindings are
+//!   left untouched. The Neovim default ones (and the ones which exist before this configuration
+//!   is loaded) are skipped.
+//! - The user commands (the uppercase ones) are recorded when leaving the cmdline.
+//! - `:MyConfigUsage`: Show the ranked usage table, the never used keybindings are at the bottom.
+//!
+
+///
+/// The namespace of the `vim.on_key()` watcher
+///
+const USAGE_STATS_NAMESPACE: &'static str = "my_config_usage_stats";
+
+///
+/// The sub directory of the data dir to store the usage file
+///
+const USAGE_STATS_DATA_SUB_DIR: &'static str = "usage_stats";
+
+///
+/// Private module-scope state
+///
+#[derive(Debug, Default)]
+struct UsageStatsState {
+    //
+    // The persisted state is loaded lazily
+    //
+    is_loaded: bool,
+
+    //
+    // usage name (e.g. `n <Space>pc`, `:ProjectCommand`) <--> invoked count
+    //
+    counts: HashMap<String, u64>,
+
+    //
+    // The global keybindings exist before this configuration is loaded (the Neovim default
+    // ones): `{mode}{lhs} {desc}`, they're not tracked.
+    //
+    builtin_keybindings: Vec<String>,
+
+    //
+    // The tracked keybindings: (usage name, description)
+    //
+    keybindings: Vec<(String, String)>,
+}
+
+static USAGE_STATS_STATE: LazyLock<Mutex<UsageStatsState>> =
+    LazyLock::new(|| Mutex::new(UsageStatsState::default()));
+
+///
+/// Get back the usage file
+///
+fn get_usage_file() -> Option<PathBuf> {
+    let mut usage_file = get_data_dir(USAGE_STATS_DATA_SUB_DIR)?;
+    usage_file.push("usage.json");
+    Some(usage_file)
+}
+
+///
+/// Load the persisted counts if it's not loaded yet, the JSON file looks like this:
+///
+/// ```json
+/// { "n <Space>pc": 42, ":ProjectCommand": 3 }
+/// ```
+///
+fn ensure_usage_loaded(state: &mut UsageStatsState) {
+    if state.is_loaded {
+        return;
+    }
+    state.is_loaded = true;
+
+    let Some(content) = get_usage_file().and_then(|file| std::fs::read_to_string(file).ok()) else {
+        return;
+    };
+
+    if let Ok(usage_dict) = call_function::<_, Dictionary>("json_decode", (content,)) {
+        for (name, count) in usage_dict {
+            if let Ok(count) = i64::from_object(count) {
+                *state.counts.entry(name.to_string()).or_default() += count.max(0) as u64;
+            }
+        }
+    }
+}
+
+///
+/// Save the counts to the usage file
+///
+fn save_usage(state: &UsageStatsState) {
+    let Some(usage_file) = get_usage_file() else {
+        return;
+    };
+
+    let usage_dict = Dictionary::from_iter(
+        state
+            .counts
+            .iter()
+            .map(|(name, count)| (name.as_str(), Object::from(*count as i64))),
+    );
+
+    if let Ok(content) = call_function::<_, String>("json_encode", (usage_dict,)) {
+        let _ = std::fs::write(usage_file, content);
+    }
+}
+
+///
+/// Increase the invoked count of the given usage name
+///
+fn record_usage(name: &str) {
+    let mut locked_state = USAGE_STATS_STATE.lock().unwrap();
+    ensure_usage_loaded(&mut locked_state);
+    *locked_state.counts.entry(name.to_owned()).or_default() += 1;
+}
+
+///
+/// Get back the global keybindings which exist right now: `{mode}{lhs} {desc}`, so the ones
+/// overridden later by this configuration (e.g. `gc`) are different.
+///
+fn get_global_keybinding_keys() -> Vec<String> {
+    let lua_code = r#"local keys = {}
+for _, mode in ipairs({ "n", "i", "x", "s", "o", "t", "c" }) do
+    for _, map in ipairs(vim.api.nvim_get_keymap(mode)) do
+        table.insert(keys, map.mode .. vim.fn.keytrans(map.lhs) .. " " .. (map.desc or ""))
+    end
+end
+return keys"#;
+
+    exec_lua::<_, Vec<String>>(lua_code, Array::new()).unwrap_or_default()
+}
+
+///
+/// Track the global keybindings which have the description (except the builtin ones), the
+/// typed keys are watched by `vim.on_key()` and matched against their lhs in the current mode.
+/// The keybindings aren't touched, so the count, the typeahead order and the mapping options
+/// are all kept as is.
+///
+/// The leading count (e.g. `3n`) is skipped in `NORMAL`/`VISUAL`/`OPERATOR-PENDING` mode.
+///
+fn track_keybindings() {
+    let lua_code = r#"local builtin_keys, record, namespace = ...
+local builtin = {}
+for _, key in ipairs(builtin_keys) do
+    builtin[key] = true
+end
+
+local tracked, tracked_names = {}, {}
+local names_by_mode, prefixes_by_mode = {}, {}
+for _, mode in ipairs({ "n", "i", "x", "s", "o", "t", "c" }) do
+    names_by_mode[mode], prefixes_by_mode[mode] = {}, {}
+    for _, map in ipairs(vim.api.nvim_get_keymap(mode)) do
+        local lhs = vim.fn.keytrans(map.lhs)
+        if map.desc ~= nil and not builtin[map.mode .. lhs .. " " .. map.desc] then
+            local name = vim.trim(map.mode) .. " " .. lhs
+            if not tracked_names[name] then
+                tracked_names[name] = true
+                table.insert(tracked, { name, map.desc })
+            end
+            names_by_mode[mode][lhs] = name
+            for i = 1, #lhs - 1 do
+                prefixes_by_mode[mode][lhs:sub(1, i)] = true
+            end
+        end
+    end
+end
+
+local mode_aliases = {
+    n = "n", i = "i", R = "i", v = "x", V = "x", [""] = "x",
+    s = "s", S = "s", ["9"] = "s", c = "c", t = "t",
+}
+local pending, pending_mode = "", nil
+vim.on_key(function(_, typed)
+    if typed == nil or typed == "" then
+        return
+    end
+
+    local current_mode = vim.api.nvim_get_mode().mode
+    local mode = current_mode:sub(1, 2) == "no" and "o" or mode_aliases[current_mode:sub(1, 1)]
+    if mode ~= pending_mode then
+        pending, pending_mode = "", mode
+    end
+    if mode == nil then
+        return
+    end
+
+    local key = vim.fn.keytrans(typed)
+    for _, keys in ipairs({ pending .. key, key }) do
+        local lhs = keys
+        if mode == "n" or mode == "x" or mode == "o" then
+            lhs = keys:gsub("^[1-9]%d*", "")
+        end
+
+        if names_by_mode[mode][lhs] ~= nil then
+            pending = ""
+            record(names_by_mode[mode][lhs])
+            return
+        elseif lhs == "" or prefixes_by_mode[mode][lhs] then
+            pending = keys
+            return
+        end
+    end
+    pending = ""
+end, vim.api.nvim_create_namespace(namespace))
+
+return tracked"#;
+
+    let builtin_keybindings = USAGE_STATS_STATE
+        .lock()
+        .unwrap()
+        .builtin_keybindings
+        .clone();
+    let record_callback = Function::<(String,), ()>::from_fn(|(name,)| record_usage(&name));
+
+    let tracked = try_exec_lua::<_, Vec<Vec<String>>>(
+        lua_code,
+        (
+            Array::from_iter(builtin_keybindings),
+            record_callback,
+            USAGE_STATS_NAMESPACE,
+        ),
+    )
+    .context("track the keybindings usage");
+    let Some(tracked) = report("usage_stats", tracked) else {
+        return;
+    };
+
+    USAGE_STATS_STATE.lock().unwrap().keybindings = tracked
+        .into_iter()
+        .filter_map(|mut pair| {
+            let desc = pair.pop()?;
+            let name = pair.pop()?;
+            Some((name, desc))
+        })
+        .collect();
+}
+
+///
+/// Show the ranked usage table in the popup window
+///
+fn show_usage_table() {
+    let lines = {
+        let mut locked_state = USAGE_STATS_STATE.lock().unwrap();
+        ensure_usage_loaded(&mut locked_state);
+
+        let descriptions = locked_state
+            .keybindings
+            .iter()
+            .cloned()
+            .collect::<HashMap<String, String>>();
+
+        //
+        // The never used keybindings are listed with `0` count
+        //
+        let mut ranked_usages = locked_state
+            .counts
+            .iter()
+            .map(|(name, count)| (name.clone(), *count))
+            .chain(
+                locked_state
+                    .keybindings
+                    .iter()
+                    .filter(|(name, _)| !locked_state.counts.contains_key(name))
+                    .map(|(name, _)| (name.clone(), 0)),
+            )
+            .collect::<Vec<(String, u64)>>();
+        ranked_usages.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let name_width = ranked_usages
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        ranked_usages
+            .iter()
+            .map(|(name, count)| {
+                format!(
+                    "{count:>6}  {name:<name_width$}  {}",
+                    descriptions.get(name).map(|v| v.as_str()).unwrap_or("")
+                )
+            })
+            .collect::<Vec<String>>()
+    };
+
+    if lines.is_empty() {
+        nvim::print!("[ usage_stats ] No usage recorded yet.");
+        return;
+    }
+
+    let Ok(mut usage_buffer) = create_buf(false, true) else {
+        return;
+    };
+
+    let opts = OptionOpts::builder().buffer(usage_buffer.clone()).build();
+    let _ = set_option_value("bufhidden", "wipe", &opts);
+    let _ = usage_buffer.set_lines(.., true, lines.iter().map(|v| v.as_str()));
+    let _ = set_option_value("modifiable", false, &opts);
+
+    let Some(usage_window) = create_popup_window(&PopupWindowOptions {
+        border: WindowBorder::Rounded,
+        window_width_ratio: None,
+        window_height_ratio: Some(0.7),
+        auto_width: true,
+        auto_height: false,
+        buffer: Some(usage_buffer.handle()),
+        title: Some(" My config usage ".to_string()),
+        footer: Some(" q: close ".to_string()),
+        ..Default::default()
+    }) else {
+        return;
+    };
+
+    for key in ["q", "<ESC>"] {
+        let _ = usage_buffer.set_keymap(
+            Mode::Normal,
+            key,
+            "",
+            &SetKeymapOpts::builder()
+                .desc("My config usage: Close")
+                .callback(move |_| {
+                    usage_window.close();
+                    ()
+                })
+                .silent(true)
+                .build(),
+        );
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    //
+    // It's called before the other modules set their keybindings, so the existing ones are
+    // the Neovim default ones.
+    //
+    USAGE_STATS_STATE.lock().unwrap().builtin_keybindings = get_global_keybinding_keys();
+
+    let group = create_augroup(
+        "custom-usage-stats-group",
+        &CreateAugroupOpts::builder().clear(true).build(),
+    )
+    .unwrap();
+
+    let _ = create_autocmd(
+        vec!["VimEnter"],
+        &CreateAutocmdOpts::builder()
+            .group(group)
+            .callback(|_: AutocmdCallbackArgs| {
+                track_keybindings();
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                true
+            })
+            .build(),
+    );
+
+    let _ = create_autocmd(
+        vec!["CmdlineLeave"],
+        &CreateAutocmdOpts::builder()
+            .group(group)
+            .patterns(vec![":"])
+            .callback(|_: AutocmdCallbackArgs| {
+                let is_aborted =
+                    call_function::<_, bool>("luaeval", ("vim.v.event.abort",)).unwrap_or(true);
+
+                //
+                // Only the user commands (start with the uppercase letter) are recorded
+                //
+                if !is_aborted
+                    && let Some(command_name) =
+                        get_cmdline_context().and_then(|context| context.command)
+                    && command_name.starts_with(|c: char| c.is_ascii_uppercase())
+                {
+                    record_usage(&format!(":{command_name}"));
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    let _ = create_autocmd(
+        vec!["VimLeavePre"],
+        &CreateAutocmdOpts::builder()
+            .group(group)
+            .callback(|_: AutocmdCallbackArgs| {
+                let locked_state = USAGE_STATS_STATE.lock().unwrap();
+                if locked_state.is_loaded {
+                    save_usage(&locked_state);
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+
+    let _ = create_user_command(
+        "MyConfigUsage",
+        |_: CommandArgs| {
+            show_usage_table();
+            ()
+        },
+        &CreateCommandOpts::builder()
+            .desc("Show the usage statistics of my keybindings and commands")
+            .build(),
+    );
+}
+
+use crate::{
+    cmdline::get_cmdline_context,
+    error::{ResultContext, report},
+    extended_api::{exec_lua, try_exec_lua},
+    picker::{PopupWindowOptions, create_popup_window},
+    utils::get_data_dir,
+};
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim, Array, Dictionary, Function, Object,
+    api::{
+        call_function, create_augroup, create_autocmd, create_buf, create_user_command,
+        opts::{
+            CreateAugroupOpts, CreateAutocmdOpts, CreateCommandOpts, OptionOpts, SetKeymapOpts,
+        },
+        set_option_value,
+        types::{AutocmdCallbackArgs, CommandArgs, Mode, WindowBorder},
+    },
+    conversion::FromObject,
+};
@@ -1,23 +1,3 @@
-///
-///
-///
-pub fn open_centred_floating_terminal_window() {
-    let _ = create_popup_window(&PopupWindowOptions {
-        border: WindowBorder::Rounded,
-        window_width_ratio: Some(0.7),
-        window_height_ratio: Some(0.7),
-        auto_width: false,
-        auto_height: false,
-        buffer: None,
-    });
-
-    // Run the `:terminal` command inside the popup window's buffer
-    let command = "terminal";
-    let infos = CmdInfos::builder().cmd(command).build();
-    let opts = CmdOpts::builder().output(false).build();
-    let _ = vim_cmd(&infos, &opts);
-}
-
 ///
 /// Close all other windwos and keep the current one
 ///
@@ -33,23 +13,412 @@ pub fn kill_other_windows() {
 }
 
 ///
-/// Toggle spell checking
+/// Toggle spell checking, the project buffer uses the project spell file after turning on.
 ///
 pub fn toggle_spell_checking() {
+    if toggle_option("spell", ToggleOptionScope::Window) == Some(true) {
+        use_project_spellfile(&Buffer::current());
+    }
+}
+
+///
+/// Which option value to toggle
+///
+#[derive(Debug, Clone, Copy)]
+pub enum ToggleOptionScope {
+    Global,
+    Window,
+    Buffer,
+}
+
+impl ToggleOptionScope {
+    fn option_opts(&self) -> OptionOpts {
+        match self {
+            Self::Global => OptionOpts::builder().scope(OptionScope::Global).build(),
+            Self::Window => OptionOpts::builder().win(Window::current()).build(),
+            Self::Buffer => OptionOpts::builder().buffer(Buffer::current()).build(),
+        }
+    }
+}
+
+///
+/// Show the new option value
+///
+fn notify_toggled_option(name: &str, value: &str) {
+    let _ = notify(
+        &format!("[ toggle ] {name}: {value}"),
+        LogLevel::Info,
+        &Dictionary::new(),
+    );
+}
+
+///
+/// Toggle the boolean option, return the new value.
+///
+pub fn toggle_option(name: &str, scope: ToggleOptionScope) -> Option<bool> {
+    let opts = scope.option_opts();
+    let toggled_value = !get_option_value::<bool>(name, &opts).ok()?;
+    set_option_value(name, toggled_value, &opts).ok()?;
+
+    notify_toggled_option(name, if toggled_value { "on" } else { "off" });
+    Some(toggled_value)
+}
+
+///
+/// Toggle the string option between empty and `on_value`, e.g. `colorcolumn`, return the new
+/// value.
+///
+pub fn toggle_string_option(
+    name: &str,
+    scope: ToggleOptionScope,
+    on_value: &str,
+) -> Option<String> {
+    let opts = scope.option_opts();
+    let current_value = get_option_value::<NvimString>(name, &opts).ok()?;
+    let toggled_value = if current_value.is_empty() {
+        on_value
+    } else {
+        ""
+    };
+    set_option_value(name, toggled_value, &opts).ok()?;
+
+    notify_toggled_option(
+        name,
+        if toggled_value.is_empty() {
+            "off"
+        } else {
+            toggled_value
+        },
+    );
+    Some(toggled_value.to_owned())
+}
+
+///
+/// Toggle the diagnostics virtual text (it's not an option but the `vim.diagnostic` config)
+///
+pub fn toggle_diagnostics_virtual_text() {
+    let toggled_value = call_function::<_, bool>(
+        "luaeval",
+        (r#"(function()
+            local enabled = not vim.diagnostic.config().virtual_text
+            vim.diagnostic.config({ virtual_text = enabled })
+            return enabled
+        end)()"#,),
+    );
+
+    if let Ok(enabled) = toggled_value {
+        notify_toggled_option(
+            "diagnostics virtual text",
+            if enabled { "on" } else { "off" },
+        );
+    }
+}
+
+///
+/// Create a new split when moving beyond the last window in that direction
+///
+const SMART_WINDOW_SPLIT_AT_EDGE: bool = true;
+
+///
+/// Window movement direction
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmartWindowDirection {
+    Left,
+    Down,
+    Up,
+    Right,
+}
+
+impl SmartWindowDirection {
+    fn wincmd_key(&self) -> &'static str {
+        match self {
+            Self::Left => "h",
+            Self::Down => "j",
+            Self::Up => "k",
+            Self::Right => "l",
+        }
+    }
+
+    fn split_cmd(&self) -> &'static str {
+        match self {
+            Self::Left => "leftabove vsplit",
+            Self::Down => "rightbelow split",
+            Self::Up => "leftabove split",
+            Self::Right => "rightbelow vsplit",
+        }
+    }
+}
+
+///
+/// Move to the window in the given direction, create a new split there if it's already the last
+/// window in that direction (when `SMART_WINDOW_SPLIT_AT_EDGE` is `true`).
+///
+/// `Down`/`Up` cycle through the quickfix list instead when the quickfix window is open in the
+/// current tab, so they coexist with the quickfix cycling keybindings.
+///
+pub fn smart_window_move(direction: SmartWindowDirection) {
+    if direction == SmartWindowDirection::Down || direction == SmartWindowDirection::Up {
+        let quickfix_win_id =
+            call_function::<_, i64>("luaeval", ("vim.fn.getqflist({ winid = 0 }).winid",));
+
+        if let Ok(win_id) = quickfix_win_id
+            && win_id != 0
+        {
+            let quickfix_cmd = match direction {
+                SmartWindowDirection::Down => "silent! cnext",
+                _ => "silent! cNext",
+            };
+            let _ = command(quickfix_cmd);
+            let _ = command("normal! zz");
+            return;
+        }
+    }
+
     let current_win = Window::current();
-    let opts = OptionOpts::builder().win(current_win).build();
-    let toggled_value = !get_option_value::<bool>("spell", &opts).unwrap();
-    let _ = set_option_value("spell", toggled_value, &opts);
+    let _ = command(&format!("wincmd {}", direction.wincmd_key()));
+
+    //
+    // Still in the same window means it's the edge, the floating window is ignored.
+    //
+    let is_floating_win = call_function::<_, String>("win_gettype", ())
+        .map(|win_type| win_type == "popup")
+        .unwrap_or(false);
+
+    if SMART_WINDOW_SPLIT_AT_EDGE
+        && !is_floating_win
+        && Window::current().handle() == current_win.handle()
+    {
+        let _ = command(direction.split_cmd());
+    }
+}
+
+///
+/// Get back the text under the cursor which consists of the `is_text_char` chars. The cursor
+/// column from Neovim is the byte index, it's mapped to the char which contains that byte, so
+/// the multibyte chars (e.g. CJK, emoji) are handled correctly.
+///
+fn get_text_under_cursor(is_text_char: impl Fn(char) -> bool) -> Option<String> {
+    let line = get_current_line().ok()?.to_string();
+    let (_, cursor_col) = Window::current().get_cursor().ok()?;
+
+    let chars = line.char_indices().collect::<Vec<(usize, char)>>();
+    let cursor_index = chars
+        .iter()
+        .rposition(|(byte_index, _)| *byte_index <= cursor_col)?;
+    if !is_text_char(chars[cursor_index].1) {
+        return None;
+    }
+
+    let start_index = chars[..cursor_index]
+        .iter()
+        .rposition(|(_, c)| !is_text_char(*c))
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    let end_index = chars[cursor_index..]
+        .iter()
+        .position(|(_, c)| !is_text_char(*c))
+        .map(|index| cursor_index + index)
+        .unwrap_or(chars.len());
+
+    Some(
+        chars[start_index..end_index]
+            .iter()
+            .map(|(_, c)| c)
+            .collect(),
+    )
+}
+
+///
+/// Get back the `word` (`big_word` is `false`, letters, digits and `_`) or `WORD` (`big_word`
+/// is `true`, non-blank chars) under the cursor, `None` if the cursor isn't on it.
+///
+pub fn get_word_under_cursor(big_word: bool) -> Option<String> {
+    if big_word {
+        get_text_under_cursor(|c| !c.is_whitespace())
+    } else {
+        get_text_under_cursor(|c| c.is_alphanumeric() || c == '_')
+    }
+}
+
+///
+/// Get back the file path under the cursor, it stops at the blanks, quotes and brackets, e.g.
+/// `"src/main.rs:10:5",` -> `src/main.rs:10:5`
+///
+pub fn get_path_under_cursor() -> Option<String> {
+    let path = get_text_under_cursor(|c| !c.is_whitespace() && !"\"'`()[]{}<>,;".contains(c))?;
+    let path = path.trim_end_matches(['.', ':']).to_owned();
+
+    if path.is_empty() { None } else { Some(path) }
+}
+
+///
+/// Get back the current visual selection (charwise, linewise or blockwise), the lines are joined
+/// by `\n`. It has to be called in the visual mode, as the `'<` and `'>` marks are only updated
+/// after leaving the visual mode.
+///
+pub fn get_visual_selection() -> Option<String> {
+    let lines = call_function::<_, Vec<String>>(
+        "luaeval",
+        (r#"vim.fn.getregion(vim.fn.getpos("v"), vim.fn.getpos("."), { type = vim.fn.mode() })"#,),
+    )
+    .ok()?;
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+///
+/// Feed the keys (with the key notation, e.g. `<Left>`) as typed by the user
+///
+pub fn feed_keys(keys: &str) {
+    let _ = call_function::<_, Object>(
+        "luaeval",
+        (
+            r#"vim.api.nvim_feedkeys(vim.api.nvim_replace_termcodes(_A, true, false, true), "n", false)"#,
+            keys,
+        ),
+    );
+}
+
+///
+/// Escape the text for the `:substitute` pattern
+///
+fn escape_substitute_pattern(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\/.*$^~[]".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+///
+/// Fill the command line to replace all the words under the cursor: `:%s/\<word\>//g`
+///
+pub fn replace_word_under_cursor() {
+    if let Some(word) = get_word_under_cursor(false) {
+        let pattern = escape_substitute_pattern(&word);
+        feed_keys(&format!(":%s/\\<{pattern}\\>//g<Left><Left>"));
+    }
+}
+
+///
+/// Fill the command line to replace all the selected text (the first line only)
+///
+pub fn replace_visual_selection() {
+    if let Some(selection) = get_visual_selection() {
+        let first_line = selection.lines().next().unwrap_or_default();
+        //
+        // `feed_keys` translates the `<...>` key notation, the selected `<CR>` should be typed
+        // as is.
+        //
+        let pattern = escape_substitute_pattern(first_line).replace('<', "<lt>");
+        feed_keys(&format!("<Esc>:%s/{pattern}//g<Left><Left>"));
+    }
+}
+
+///
+/// Open the file path under the cursor, `path:line[:col]` jumps to that line. The relative path
+/// is resolved from the current file directory first, then the current working directory. If
+/// none of them exists, fall back to the builtin `gf`, which respects `'path'`, `'suffixesadd'`
+/// and `'includeexpr'`.
+///
+pub fn open_path_under_cursor() {
+    const LOGGER_PREFIX: &'static str = "[ utils - open_path_under_cursor ]";
+
+    let fallback_to_builtin_gf = || {
+        if let Err(error) = command("normal! gf") {
+            nvim::print!("{LOGGER_PREFIX} {error}");
+        }
+    };
+
+    let Some(path_under_cursor) = get_path_under_cursor() else {
+        fallback_to_builtin_gf();
+        return;
+    };
+
+    //
+    // Split the optional `:line[:col]` suffix
+    //
+    let mut parts = path_under_cursor.split(':');
+    let path = parts.next().unwrap_or_default().to_owned();
+    let line = parts.next().and_then(|line| line.parse::<usize>().ok());
+
+    let expanded_path = match path.strip_prefix("~/") {
+        Some(rest) => std::env::var("HOME")
+            .map(|home| format!("{home}/{rest}"))
+            .unwrap_or(path.clone()),
+        None => path.clone(),
+    };
+
+    let current_file_dir = Buffer::current()
+        .get_name()
+        .ok()
+        .and_then(|name| name.parent().map(|dir| dir.to_path_buf()));
+
+    let mut candidates = vec![PathBuf::from(&expanded_path)];
+    if let Some(dir) = current_file_dir {
+        candidates.insert(0, dir.join(&expanded_path));
+    }
+
+    let Some(file_path) = candidates.into_iter().find(|candidate| candidate.exists()) else {
+        fallback_to_builtin_gf();
+        return;
+    };
+
+    let Some(file_path) = file_path.to_str() else {
+        return;
+    };
+
+    let infos = CmdInfos::builder().cmd("edit").args([file_path]).build();
+    if let Err(error) = vim_cmd(&infos, &CmdOpts::builder().output(false).build()) {
+        nvim::print!("{LOGGER_PREFIX} Failed to open '{file_path}': {error}");
+        return;
+    }
+
+    if let Some(line) = line {
+        let line_count = Buffer::current().line_count().unwrap_or(1).max(1);
+        let _ = Window::current().set_cursor(line.clamp(1, line_count), 0);
+    }
+}
+
+///
+/// The register to copy the text for pasting outside of Neovim: `+` if there is a clipboard
+/// provider, otherwise the unnamed register.
+///
+pub fn get_clipboard_register() -> char {
+    if call_function::<_, i64>("has", ("clipboard",)).unwrap_or(0) == 1 {
+        '+'
+    } else {
+        '"'
+    }
 }
 
 ///
 /// Get back the left/right-split window
 ///
 pub fn get_split_window(most_right: bool) -> Option<Window> {
+    find_split_window(list_wins(), most_right)
+}
+
+///
+/// Get back the left/right-split window from the given windows, e.g. the windows of the
+/// current tab page.
+///
+pub fn find_split_window(
+    windows: impl IntoIterator<Item = Window>,
+    most_right: bool,
+) -> Option<Window> {
     let mut split_win: Option<Window> = None;
 
     let mut last_split_win_column = -1i32;
-    for win in list_wins() {
+    for win in windows {
         if let Ok((rows, cols)) = win.get_position() {
             //
             // The most right window means:
@@ -73,11 +442,316 @@ pub fn get_split_window(most_right: bool) -> Option<Window> {
     split_win
 }
 
-use crate::picker::{PopupWindowOptions, create_popup_window};
+///
+/// Get back the `stdpath("data")/my_neovim_configuration/{sub_dir}` directory to store the
+/// persistent module data, create it if it's not exists yet.
+///
+pub fn get_data_dir(sub_dir: &str) -> Option<PathBuf> {
+    let data_dir = call_function::<_, String>("stdpath", ("data",)).ok()?;
+
+    let mut dir = PathBuf::from(data_dir);
+    dir.push("my_neovim_configuration");
+    dir.push(sub_dir);
+
+    if !dir.exists() && std::fs::create_dir_all(&dir).is_err() {
+        return None;
+    }
+
+    Some(dir)
+}
+
+///
+/// Append the `jobstart` output chunks to the pending (partial) line, and return the completed
+/// lines. The first chunk continues the pending line, and the last chunk is the new pending
+/// line, see `:h channel-lines`.
+///
+fn split_job_output_chunks(pending_line: &mut String, chunks: Vec<String>) -> Vec<String> {
+    let mut lines = Vec::with_capacity(chunks.len());
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        if index == 0 {
+            pending_line.push_str(&chunk);
+        } else {
+            lines.push(std::mem::replace(pending_line, chunk));
+        }
+    }
+    lines
+}
+
+///
+/// Run the command asynchronously by `jobstart`, so the UI doesn't block while the external
+/// process runs. Return `false` if the command can't be started.
+///
+/// - `on_output`: Called with the completed output lines (both stdout and stderr) as they
+///                arrive.
+/// - `on_exit`: Called with the exit code after all output has been delivered.
+///
+/// Both callbacks run on the main thread, so they're free to call the Neovim API.
+///
+pub fn run_async<O, E>(cmd: Vec<String>, on_output: O, on_exit: E) -> bool
+where
+    O: FnMut(Vec<String>) + 'static,
+    E: FnOnce(i64) + 'static,
+{
+    run_async_in(cmd, None, &[], on_output, on_exit)
+}
+
+///
+/// Same as `run_async()`, but the command runs in the given working directory (instead of the
+/// Neovim one), and the given environment variables are added to (or override) the inherited
+/// Neovim environment.
+///
+pub fn run_async_in<O, E>(
+    cmd: Vec<String>,
+    cwd: Option<&str>,
+    env: &[(String, String)],
+    on_output: O,
+    on_exit: E,
+) -> bool
+where
+    O: FnMut(Vec<String>) + 'static,
+    E: FnOnce(i64) + 'static,
+{
+    if cmd.is_empty() {
+        return false;
+    }
+
+    let on_output = Rc::new(RefCell::new(on_output));
+
+    //
+    // The pending (partial) lines of stdout and stderr
+    //
+    let pending_lines = Rc::new(RefCell::new([String::new(), String::new()]));
+
+    let create_output_handler = |stream_index: usize| {
+        let on_output = on_output.clone();
+        let pending_lines = pending_lines.clone();
+        Function::<(i64, Object, NvimString), ()>::from_fn_mut(move |(_, data, _)| {
+            let Ok(chunks) = Vec::<String>::from_object(data) else {
+                return;
+            };
+
+            let lines =
+                split_job_output_chunks(&mut pending_lines.borrow_mut()[stream_index], chunks);
+            if !lines.is_empty() {
+                (on_output.borrow_mut())(lines);
+            }
+        })
+    };
+
+    let stdout_handler = create_output_handler(0);
+    let stderr_handler = create_output_handler(1);
+
+    let exit_handler =
+        Function::<(i64, i64, NvimString), ()>::from_fn_once(move |(_, exit_code, _)| {
+            //
+            // Flush the last lines without the trailing newline
+            //
+            let remaining_lines = pending_lines
+                .borrow_mut()
+                .iter_mut()
+                .filter(|line| !line.is_empty())
+                .map(std::mem::take)
+                .collect::<Vec<String>>();
+            if !remaining_lines.is_empty() {
+                (on_output.borrow_mut())(remaining_lines);
+            }
+
+            on_exit(exit_code);
+        });
+
+    let mut job_opts = vec![
+        ("on_stdout", Object::from(stdout_handler)),
+        ("on_stderr", Object::from(stderr_handler)),
+        ("on_exit", Object::from(exit_handler)),
+    ];
+
+    if let Some(cwd) = cwd {
+        job_opts.push(("cwd", Object::from(cwd)));
+    }
+
+    if !env.is_empty() {
+        job_opts.push((
+            "env",
+            Object::from(Dictionary::from_iter(
+                env.iter()
+                    .map(|(key, value)| (key.as_str(), Object::from(value.as_str()))),
+            )),
+        ));
+    }
+
+    matches!(
+        call_function::<_, i64>(
+            "jobstart",
+            (Array::from_iter(cmd), Dictionary::from_iter(job_opts))
+        ),
+        Ok(job_id) if job_id > 0
+    )
+}
+
+///
+/// Human-friendly file size, e.g. `512 B`, `1.5 KiB`, `23.0 MiB`
+///
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&'static str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    //
+    // Pick the unit by the rounded size, so `1048575` is `1.0 MiB` rather than `1024.0 KiB`.
+    //
+    let round_to_tenth = |size: f64| (size * 10.0).round() / 10.0;
+    let mut size = bytes as f64;
+    let mut unit_index = 0usize;
+    while round_to_tenth(size) >= 1024.0 && unit_index < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit_index += 1;
+    }
+
+    format!("{:.1} {}", round_to_tenth(size), UNITS[unit_index])
+}
+
+///
+/// Human-friendly duration, e.g. `350ms`, `2.4s`, `3m 05s`, `1h 20m`
+///
+pub fn format_duration(milliseconds: u128) -> String {
+    if milliseconds < 1000 {
+        return format!("{milliseconds}ms");
+    }
+
+    //
+    // Round first and then pick the unit, so `59950` is `1m 00s` rather than `60.0s`.
+    //
+    let tenths_of_second = (milliseconds + 50) / 100;
+    if tenths_of_second < 600 {
+        return format!("{}.{}s", tenths_of_second / 10, tenths_of_second % 10);
+    }
+
+    let seconds = (milliseconds + 500) / 1000;
+    if seconds < 3600 {
+        return format!("{}m {:02}s", seconds / 60, seconds % 60);
+    }
+
+    let minutes = (seconds + 30) / 60;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+///
+/// Human-friendly time relative to now, e.g. `just now`, `5m ago`, `3d ago`. The future time
+/// (clock skew) is treated as `just now`.
+///
+pub fn relative_time(time: SystemTime) -> String {
+    let Ok(elapsed) = SystemTime::now().duration_since(time) else {
+        return "just now".to_string();
+    };
+
+    let seconds = elapsed.as_secs();
+    match seconds {
+        0..10 => "just now".to_string(),
+        10..60 => format!("{seconds}s ago"),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86400 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+///
+/// Get back the comment prefix of the line: the leading whitespace, the comment leader of
+/// `commentstring` with the repeated leader characters (e.g. `///`, `//!`), and one space.
+///
+pub fn get_comment_prefix(line: &str) -> String {
+    let indent_len = line.len() - line.trim_start().len();
+    let (indent, rest) = line.split_at(indent_len);
+
+    let comment_string =
+        get_option_value::<NvimString>("commentstring", &OptionOpts::builder().build())
+            .map(|value| value.to_string())
+            .unwrap_or_default();
+    let leader = comment_string.split("%s").next().unwrap_or_default().trim();
+
+    if leader.is_empty() || !rest.starts_with(leader) {
+        return indent.to_owned();
+    }
+
+    let after_leader = &rest[leader.len()..];
+    let extra_len = after_leader.len()
+        - after_leader
+            .trim_start_matches(|c: char| leader.contains(c) || c == '!')
+            .len();
+    let mut prefix = format!("{indent}{leader}{}", &after_leader[..extra_len]);
+    if after_leader[extra_len..].starts_with(' ') {
+        prefix.push(' ');
+    }
+
+    prefix
+}
+
+///
+/// The display width of the text
+///
+pub fn display_width(text: &str) -> usize {
+    call_function::<_, i64>("strdisplaywidth", (text,)).unwrap_or(text.len() as i64) as usize
+}
+
+use crate::project_spell::use_project_spellfile;
+
 use nvim::api::{
-    Window, cmd as vim_cmd, get_option_value, list_wins,
-    opts::{CmdOpts, OptionOpts},
+    Buffer, Window, call_function, cmd as vim_cmd, command, get_current_line, get_option_value,
+    list_wins, notify,
+    opts::{CmdOpts, OptionOpts, OptionScope},
     set_option_value,
-    types::{CmdInfos, WindowBorder},
+    types::{CmdInfos, LogLevel},
+};
+use nvim_oxi::{
+    self as nvim, Array, Dictionary, Function, Object, String as NvimString, conversion::FromObject,
 };
-use nvim_oxi::{self as nvim};
+use std::{cell::RefCell, path::PathBuf, rc::Rc, time::SystemTime};
+
+#[cfg(test)]
+mod tests {
+    use super::{format_duration, format_size, relative_time};
+
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn format_size_picks_the_unit_after_rounding() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KiB");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(1_048_575), "1.0 MiB");
+        assert_eq!(format_size(1_048_576), "1.0 MiB");
+        assert_eq!(format_size(23 * 1024 * 1024), "23.0 MiB");
+        assert_eq!(format_size(u64::MAX), "16384.0 PiB");
+    }
+
+    #[test]
+    fn format_duration_picks_the_unit_after_rounding() {
+        assert_eq!(format_duration(0), "0ms");
+        assert_eq!(format_duration(999), "999ms");
+        assert_eq!(format_duration(1000), "1.0s");
+        assert_eq!(format_duration(2_449), "2.4s");
+        assert_eq!(format_duration(59_949), "59.9s");
+        assert_eq!(format_duration(59_950), "1m 00s");
+        assert_eq!(format_duration(185_000), "3m 05s");
+        assert_eq!(format_duration(3_599_499), "59m 59s");
+        assert_eq!(format_duration(3_599_500), "1h 00m");
+        assert_eq!(format_duration(4_800_000), "1h 20m");
+    }
+
+    #[test]
+    fn relative_time_of_the_past_and_the_future() {
+        let ago = |seconds: u64| relative_time(SystemTime::now() - Duration::from_secs(seconds));
+
+        assert_eq!(ago(0), "just now");
+        assert_eq!(ago(30), "30s ago");
+        assert_eq!(ago(5 * 60), "5m ago");
+        assert_eq!(ago(2 * 3600), "2h ago");
+        assert_eq!(ago(3 * 86400), "3d ago");
+        assert_eq!(
+            relative_time(SystemTime::now() + Duration::from_secs(60)),
+            "just now"
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! The per-window `winbar` with the breadcrumb of the file path and the symbol context:
+//!
+//! ```text
+//! src › picker › picker_item.rs › impl PickerItem › function from
+//! ```
+//!
+//! - The directory segments are clickable, click one to open it in my dired.
+//! - The symbol context is the enclosing functions/structs/impls of the cursor by treesitter (the
+//!   LSP document symbols need an asynchronous request, so they're not used), it's empty if the
+//!   buffer doesn't have a treesitter parser.
+//! - It's refreshed on `BufWinEnter` and `CursorHold`, only for the normal file windows (not the
+//!   floating windows).
+//!
+
+///
+/// The Lua global function name of the click handler
+///
+const WINBAR_CLICK_FUNCTION: &'static str = "MyConfigWinbarClick";
+
+///
+/// The separator between the segments
+///
+const WINBAR_SEPARATOR: &'static str = " › ";
+
+///
+/// The treesitter node types of the symbol context (of all languages), the symbol name is the
+/// `name` field (or the `type` field for the Rust `impl`).
+///
+const WINBAR_SYMBOL_NODE_TYPES: &[&'static str] = &[
+    "function_item",
+    "struct_item",
+    "enum_item",
+    "impl_item",
+    "trait_item",
+    "mod_item",
+    "function_definition",
+    "function_declaration",
+    "method_definition",
+    "method_declaration",
+    "class_definition",
+    "class_declaration",
+    "struct_specifier",
+];
+
+///
+/// Get back the enclosing symbols of the window cursor (the outermost first), e.g.
+/// `["impl PickerItem", "function from"]`.
+///
+const WINBAR_SYMBOLS_LUA: &'static str = r#"local winid, node_types = ...
+local bufnr = vim.api.nvim_win_get_buf(winid)
+local cursor = vim.api.nvim_win_get_cursor(winid)
+local ok, node = pcall(vim.treesitter.get_node, { bufnr = bufnr, pos = { cursor[1] - 1, cursor[2] } })
+if not ok or node == nil then
+    return {}
+end
+
+local wanted = {}
+for _, node_type in ipairs(node_types) do
+    wanted[node_type] = true
+end
+
+local symbols = {}
+while node ~= nil do
+    if wanted[node:type()] then
+        local name_node = node:field("name")[1] or node:field("type")[1]
+        if name_node ~= nil then
+            local kind = node:type():gsub("_item$", ""):gsub("_definition$", ""):gsub("_declaration$", ""):gsub("_specifier$", "")
+            local name = vim.treesitter.get_node_text(name_node, bufnr):gsub("%s+", " ")
+            table.insert(symbols, 1, kind .. " " .. name)
+        end
+    end
+    node = node:parent()
+end
+return symbols"#;
+
+///
+/// Private module-scope state: the directories of the clickable segments of the window, the
+/// click handler gets the window and the segment index from `minwid`.
+///
+static WINBAR_CLICK_TARGETS: LazyLock<Mutex<HashMap<WinHandle, Vec<String>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+///
+/// The `minwid` of the clickable segment, the index is less than 100.
+///
+fn encode_click_target(window_handle: WinHandle, index: usize) -> i64 {
+    window_handle as i64 * 100 + index as i64
+}
+
+///
+/// The `winbar` text can't have the bare `%`
+///
+fn escape_winbar_text(text: &str) -> String {
+    text.replace('%', "%%")
+}
+
+///
+/// Only the normal file window gets the `winbar`
+///
+fn is_winbar_window(window: &Window, buffer: &Buffer) -> bool {
+    let window_type =
+        call_function::<_, String>("win_gettype", (window.handle(),)).unwrap_or_default();
+    let buffer_type = get_option_value::<NvimString>(
+        "buftype",
+        &OptionOpts::builder().buffer(buffer.clone()).build(),
+    )
+    .unwrap_or_default();
+
+    window_type.is_empty() && buffer_type.is_empty()
+}
+
+///
+/// Render the `winbar` of the window, `None` if the window doesn't have a file.
+///
+fn render_winbar(window: &Window) -> Option<String> {
+    let buffer = window.get_buf().ok()?;
+    if !is_winbar_window(window, &buffer) {
+        return None;
+    }
+
+    let file_path = buffer.get_name().ok()?;
+    if file_path.as_os_str().is_empty() {
+        return None;
+    }
+    let display_path =
+        call_function::<_, String>("fnamemodify", (file_path.display().to_string(), ":~:."))
+            .ok()?;
+
+    //
+    // The display path (relative to the cwd or home) has the same trailing components with the
+    // absolute path, so the directory of the segment is the ancestor of the absolute path.
+    //
+    let path_segments = display_path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<&str>>();
+    let dir_count = path_segments.len().saturating_sub(1).min(99);
+
+    let mut click_targets = Vec::new();
+    let mut segments = Vec::new();
+    for (index, segment) in path_segments.iter().enumerate() {
+        let text = escape_winbar_text(segment);
+        if index + 1 == path_segments.len() {
+            segments.push(format!("%#WinBar#{text}"));
+            continue;
+        }
+        if index >= dir_count {
+            continue;
+        }
+
+        let Some(dir) = file_path.ancestors().nth(path_segments.len() - 1 - index) else {
+            continue;
+        };
+        segments.push(format!(
+            "%#Directory#%{}@v:lua.{WINBAR_CLICK_FUNCTION}@{text}%X",
+            encode_click_target(window.handle(), click_targets.len())
+        ));
+        click_targets.push(dir.display().to_string());
+    }
+
+    let symbols = exec_lua::<_, Vec<String>>(
+        WINBAR_SYMBOLS_LUA,
+        (
+            window.handle(),
+            Array::from_iter(WINBAR_SYMBOL_NODE_TYPES.iter().copied()),
+        ),
+    )
+    .unwrap_or_default();
+    for symbol in symbols {
+        segments.push(format!("%#Function#{}", escape_winbar_text(&symbol)));
+    }
+
+    WINBAR_CLICK_TARGETS
+        .lock()
+        .unwrap()
+        .insert(window.handle(), click_targets);
+
+    Some(segments.join(&format!("%#Comment#{WINBAR_SEPARATOR}")))
+}
+
+///
+/// Refresh the `winbar` of the current window
+///
+fn refresh_winbar() {
+    let window = Window::current();
+    let Some(winbar) = render_winbar(&window) else {
+        return;
+    };
+
+    let _ = set_option_value(
+        "winbar",
+        winbar,
+        &OptionOpts::builder()
+            .win(window)
+            .scope(OptionScope::Local)
+            .build(),
+    );
+}
+
+///
+/// Click the directory segment: open it in my dired
+///
+fn on_winbar_click(minwid: i64) {
+    let window_handle = (minwid / 100) as WinHandle;
+    let index = (minwid % 100) as usize;
+
+    let Some(dir) = WINBAR_CLICK_TARGETS
+        .lock()
+        .unwrap()
+        .get(&window_handle)
+        .and_then(|targets| targets.get(index).cloned())
+    else {
+        return;
+    };
+
+    nvim::schedule(move |_| {
+        let window = Window::from(window_handle);
+        if window.is_valid() {
+            let _ = set_current_win(&window);
+        }
+        open_directory(&dir);
+        ()
+    });
+}
+
+///
+///
+///
+pub fn setup() {
+    //
+    // Expose the click handler as the Lua global function, so `%@v:lua.{name}@` can call it with
+    // `(minwid, clicks, button, modifiers)`.
+    //
+    let _ = exec_lua::<_, Object>(
+        "local name, f = ...; _G[name] = function(minwid) f(minwid) end",
+        (
+            WINBAR_CLICK_FUNCTION,
+            Function::<i64, ()>::from_fn(|minwid: i64| on_winbar_click(minwid)),
+        ),
+    );
+
+    let _ = create_autocmd(
+        vec!["BufWinEnter", "CursorHold", "WinClosed"],
+        &CreateAutocmdOpts::builder()
+            .group(
+                create_augroup(
+                    "custom-winbar-group",
+                    &CreateAugroupOpts::builder().clear(true).build(),
+                )
+                .unwrap(),
+            )
+            .callback(|args: AutocmdCallbackArgs| {
+                if args.event == "WinClosed" {
+                    //
+                    // The pattern of `WinClosed` is the closed window handle
+                    //
+                    if let Ok(window_handle) = args.r#match.parse::<WinHandle>() {
+                        WINBAR_CLICK_TARGETS.lock().unwrap().remove(&window_handle);
+                    }
+                } else {
+                    refresh_winbar();
+                }
+
+                //
+                // Return `true` to delete the autocommand (means only run once)!!!
+                //
+                false
+            })
+            .build(),
+    );
+}
+
+use crate::{extended_api::exec_lua, my_dired::open_directory};
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use nvim_oxi::{
+    self as nvim, Array, Function, Object, String as NvimString, WinHandle,
+    api::{
+        Buffer, Window, call_function, create_augroup, create_autocmd, get_option_value,
+        opts::{CreateAugroupOpts, CreateAutocmdOpts, OptionOpts, OptionScope},
+        set_current_win, set_option_value,
+        types::AutocmdCallbackArgs,
+    },
+};
@@ -0,0 +1,621 @@
+//! Workspace-wide text rename across files by `ripgrep`, it's the non-LSP fallback when the
+//! language server isn't available (or for the non-code files):
+//!
+//! - `:WorkspaceRename`: Prompt for the regex pattern and the replacement (`$1` for the capture
+//!   group), then show the preview of all pending replacements (grouped by file).
+//! - `:WorkspaceRenameLiteral`: The same, but the pattern and the replacement are literal text.
+//!
+//! Preview buffer keybindings:
+//!
+//! - `x` or `<Tab>`: Toggle the hunk under the cursor (or all hunks of the file on the file line).
+//! - `a`: Apply all enabled hunks, and then print the summary and the undo script path.
+//! - `q` or `<ESC>`: Close without applying.
+//!
+//! The undo script restores the original files from the backup copies, run it by `sh {path}`.
+//!
+//! `rg` runs on the background thread (it may take a while in the big workspace), the preview
+//! opens on the main thread by `main_thread::run()` when it's done.
+//!
+
+///
+/// A single line replacement
+///
+#[derive(Debug, Clone)]
+struct RenameHunk {
+    //
+    // 1-based line number
+    //
+    line: usize,
+    old_text: String,
+    new_text: String,
+    enabled: bool,
+}
+
+///
+/// All replacements in a single file
+///
+#[derive(Debug, Clone)]
+struct RenameFile {
+    //
+    // Relative to the project directory
+    //
+    path: String,
+    hunks: Vec<RenameHunk>,
+}
+
+///
+/// What the preview buffer row represents
+///
+#[derive(Debug, Clone, Copy)]
+enum PreviewRow {
+    File(usize),
+    Hunk(usize, usize),
+    Other,
+}
+
+///
+/// The pending rename
+///
+#[derive(Debug)]
+struct RenamePreview {
+    project_dir: String,
+    files: Vec<RenameFile>,
+
+    //
+    // The row index (0-based) of the preview buffer to `PreviewRow`
+    //
+    rows: Vec<PreviewRow>,
+    preview_window: Option<PopupWindow>,
+}
+
+///
+/// Private module-scope state: the pending rename which is showing in the preview buffer
+///
+static WORKSPACE_RENAME_STATE: LazyLock<Mutex<Option<RenamePreview>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+///
+/// Run `rg` in the project directory, return the `(path, line, text)` list of the matched lines.
+/// The text is the replaced line when `replacement` is given.
+///
+fn run_ripgrep(
+    project_dir: &str,
+    pattern: &str,
+    replacement: Option<&str>,
+    is_literal: bool,
+) -> Result<Vec<(String, usize, String)>, String> {
+    let mut rg_command = std::process::Command::new("rg");
+    rg_command.current_dir(project_dir).args([
+        "--line-number",
+        "--with-filename",
+        "--no-heading",
+        "--null",
+        "--color",
+        "never",
+        //
+        // Keep the same output order in both runs (with and without `--replace`)
+        //
+        "--sort",
+        "path",
+    ]);
+
+    if is_literal {
+        rg_command.arg("--fixed-strings");
+    }
+
+    if let Some(replacement) = replacement {
+        rg_command.arg("--replace").arg(replacement);
+    }
+
+    rg_command.arg("--regexp").arg(pattern);
+
+    let output = rg_command
+        .output()
+        .map_err(|error| format!("Failed to run 'rg': {error}"))?;
+
+    //
+    // Exit code `1` means no match
+    //
+    if !output.status.success() && output.status.code() != Some(1) {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+
+    //
+    // Line format (with `--null`): `{path}\0{line}:{text}`
+    //
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (path, rest) = line.split_once('\0')?;
+            let (line_number, text) = rest.split_once(':')?;
+            Some((
+                path.to_owned(),
+                line_number.parse::<usize>().ok()?,
+                text.to_owned(),
+            ))
+        })
+        .collect())
+}
+
+///
+/// Collect the pending replacements grouped by file (in the `rg` output order)
+///
+fn get_rename_files(
+    project_dir: &str,
+    pattern: &str,
+    replacement: &str,
+    is_literal: bool,
+) -> Result<Vec<RenameFile>, String> {
+    //
+    // `rg --replace` treats `$` as the capture group reference, escape it for the literal text
+    //
+    let replacement = if is_literal {
+        replacement.replace('$', "$$")
+    } else {
+        replacement.to_owned()
+    };
+
+    let old_lines = run_ripgrep(project_dir, pattern, None, is_literal)?;
+    let new_lines = run_ripgrep(project_dir, pattern, Some(&replacement), is_literal)?;
+
+    let mut files: Vec<RenameFile> = Vec::new();
+    for ((path, line, old_text), (_, _, new_text)) in old_lines.into_iter().zip(new_lines) {
+        if old_text == new_text {
+            continue;
+        }
+
+        let hunk = RenameHunk {
+            line,
+            old_text,
+            new_text,
+            enabled: true,
+        };
+
+        match files.last_mut() {
+            Some(file) if file.path == path => file.hunks.push(hunk),
+            _ => files.push(RenameFile {
+                path,
+                hunks: vec![hunk],
+            }),
+        }
+    }
+
+    Ok(files)
+}
+
+///
+/// Render the preview lines and the row mapping
+///
+fn render_preview_lines(files: &[RenameFile]) -> (Vec<String>, Vec<PreviewRow>) {
+    let mut lines = Vec::new();
+    let mut rows = Vec::new();
+
+    for (file_index, file) in files.iter().enumerate() {
+        let enabled_count = file.hunks.iter().filter(|hunk| hunk.enabled).count();
+        lines.push(format!(
+            "{} ({enabled_count}/{})",
+            file.path,
+            file.hunks.len()
+        ));
+        rows.push(PreviewRow::File(file_index));
+
+        for (hunk_index, hunk) in file.hunks.iter().enumerate() {
+            let check_mark = if hunk.enabled { "x" } else { " " };
+            lines.push(format!(
+                "  [{check_mark}] {}: - {}",
+                hunk.line,
+                hunk.old_text.trim()
+            ));
+            rows.push(PreviewRow::Hunk(file_index, hunk_index));
+
+            lines.push(format!(
+                "      {}  + {}",
+                " ".repeat(hunk.line.to_string().len()),
+                hunk.new_text.trim()
+            ));
+            rows.push(PreviewRow::Hunk(file_index, hunk_index));
+        }
+
+        lines.push(String::new());
+        rows.push(PreviewRow::Other);
+    }
+
+    (lines, rows)
+}
+
+///
+/// Re-render the preview buffer from the state
+///
+fn update_preview_buffer(preview: &mut RenamePreview) {
+    let Some(preview_window) = preview.preview_window else {
+        return;
+    };
+
+    let (lines, rows) = render_preview_lines(&preview.files);
+    preview.rows = rows;
+
+    let mut preview_buffer = preview_window.buffer();
+    let opts = OptionOpts::builder().buffer(preview_buffer.clone()).build();
+    let _ = set_option_value("modifiable", true, &opts);
+    let _ = preview_buffer.set_lines(.., true, lines.iter().map(|v| v.as_str()));
+    let _ = set_option_value("modifiable", false, &opts);
+}
+
+///
+/// Toggle the hunk under the cursor, or all hunks of the file on the file line
+///
+fn toggle_hunk_under_cursor() {
+    let Ok((row, _)) = Window::current().get_cursor() else {
+        return;
+    };
+
+    let mut locked_state = WORKSPACE_RENAME_STATE.lock().unwrap();
+    let Some(preview) = locked_state.as_mut() else {
+        return;
+    };
+
+    match preview.rows.get(row - 1).copied() {
+        Some(PreviewRow::File(file_index)) => {
+            let hunks = &mut preview.files[file_index].hunks;
+            let enable_all = hunks.iter().any(|hunk| !hunk.enabled);
+            for hunk in hunks.iter_mut() {
+                hunk.enabled = enable_all;
+            }
+        }
+        Some(PreviewRow::Hunk(file_index, hunk_index)) => {
+            let hunk = &mut preview.files[file_index].hunks[hunk_index];
+            hunk.enabled = !hunk.enabled;
+        }
+        _ => return,
+    }
+
+    update_preview_buffer(preview);
+}
+
+///
+/// Quote the path for the shell script, the `'` in it is `'\''`, so the file name never breaks
+/// out of the quotes.
+///
+fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.display().to_string().replace('\'', "'\\''"))
+}
+
+///
+/// Create the backup directory of this apply, it's unique (by the nanoseconds and the suffix)
+/// as `create_dir()` fails on the existing one, so the applies never overwrite each other:
+///
+/// ```text
+/// {data_dir}/workspace_rename/{seconds}_{nanoseconds}/
+/// ├── files/        # The original files, the same layout with the project
+/// └── undo.sh
+/// ```
+///
+fn create_backup_dir() -> Option<PathBuf> {
+    let root_dir = get_data_dir("workspace_rename")?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    (0..100).find_map(|suffix| {
+        let mut dir_name = format!("{}_{:09}", now.as_secs(), now.subsec_nanos());
+        if suffix > 0 {
+            dir_name.push_str(&format!("_{suffix}"));
+        }
+
+        let backup_dir = root_dir.join(dir_name);
+        std::fs::create_dir(&backup_dir).ok().map(|_| backup_dir)
+    })
+}
+
+///
+/// Write the undo script which restores the original files from the backup copies, it's next
+/// to the `files` directory, so it never overwrites the backup of the project `undo.sh`.
+///
+fn write_undo_script(project_dir: &str, backup_dir: &Path, paths: &[String]) -> Option<PathBuf> {
+    let mut script = String::from("#!/bin/sh\n# Undo the workspace rename\n");
+    for path in paths {
+        script.push_str(&format!(
+            "cp {} {}\n",
+            shell_quote(&backup_dir.join("files").join(path)),
+            shell_quote(&Path::new(project_dir).join(path))
+        ));
+    }
+
+    let script_path = backup_dir.join("undo.sh");
+    std::fs::write(&script_path, script).ok()?;
+    Some(script_path)
+}
+
+///
+/// Apply all enabled hunks, each file is read once and written once. The line which doesn't
+/// match the preview anymore (changed after the preview) is skipped.
+///
+fn apply_rename() {
+    const LOGGER_PREFIX: &'static str = "[ workspace_rename ]";
+
+    let Some(preview) = WORKSPACE_RENAME_STATE.lock().unwrap().take() else {
+        return;
+    };
+
+    if let Some(preview_window) = preview.preview_window {
+        preview_window.close();
+    }
+
+    let Some(backup_dir) = create_backup_dir() else {
+        nvim::print!("{LOGGER_PREFIX} Failed to create the backup directory.");
+        return;
+    };
+
+    let mut replaced_count = 0usize;
+    let mut skipped_count = 0usize;
+    let mut changed_paths = Vec::new();
+
+    for file in preview.files.iter() {
+        let enabled_hunks = file
+            .hunks
+            .iter()
+            .filter(|hunk| hunk.enabled)
+            .collect::<Vec<&RenameHunk>>();
+        if enabled_hunks.is_empty() {
+            continue;
+        }
+
+        let file_path = Path::new(&preview.project_dir).join(&file.path);
+        let Ok(content) = std::fs::read_to_string(&file_path) else {
+            skipped_count += enabled_hunks.len();
+            continue;
+        };
+
+        let mut lines = content
+            .split_inclusive('\n')
+            .map(|line| line.to_owned())
+            .collect::<Vec<String>>();
+
+        let mut file_replaced_count = 0usize;
+        for hunk in enabled_hunks {
+            let Some(line) = lines.get_mut(hunk.line - 1) else {
+                skipped_count += 1;
+                continue;
+            };
+
+            let line_ending = &line[line.trim_end_matches(['\r', '\n']).len()..];
+            if line.trim_end_matches(['\r', '\n']) != hunk.old_text {
+                skipped_count += 1;
+                continue;
+            }
+
+            *line = format!("{}{line_ending}", hunk.new_text);
+            file_replaced_count += 1;
+        }
+
+        if file_replaced_count == 0 {
+            continue;
+        }
+
+        //
+        // Backup the original file for the undo script
+        //
+        let backup_path = backup_dir.join("files").join(&file.path);
+        if let Some(parent) = backup_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if std::fs::write(&backup_path, &content).is_err() {
+            skipped_count += file_replaced_count;
+            continue;
+        }
+
+        if std::fs::write(&file_path, lines.concat()).is_err() {
+            skipped_count += file_replaced_count;
+            continue;
+        }
+
+        replaced_count += file_replaced_count;
+        changed_paths.push(file.path.clone());
+    }
+
+    //
+    // Reload the open buffers of the changed files
+    //
+    let _ = command("checktime");
+
+    let undo_script = write_undo_script(&preview.project_dir, &backup_dir, &changed_paths);
+    nvim::print!(
+        "{LOGGER_PREFIX} Replaced {replaced_count} line(s) in {} file(s), skipped {skipped_count}. Undo: sh {}",
+        changed_paths.len(),
+        undo_script
+            .map(|path| path.display().to_string())
+            .unwrap_or_default()
+    );
+}
+
+///
+/// Close the preview without applying
+///
+fn close_preview() {
+    if let Some(preview) = WORKSPACE_RENAME_STATE.lock().unwrap().take()
+        && let Some(preview_window) = preview.preview_window
+    {
+        preview_window.close();
+    }
+}
+
+///
+/// Prompt for the pattern and the replacement, and then show the preview
+///
+fn workspace_rename(is_literal: bool) {
+    let pattern_prompt = if is_literal {
+        "Rename literal text: "
+    } else {
+        "Rename regex pattern: "
+    };
+    let Some(pattern) = prompt(pattern_prompt, "", None) else {
+        return;
+    };
+    if pattern.is_empty() {
+        return;
+    }
+
+    workspace_rename_pattern(&pattern, is_literal);
+}
+
+///
+/// Prompt for the replacement of the pattern, and then collect the replacements on the
+/// background thread, the preview shows when it's done.
+///
+pub fn workspace_rename_pattern(pattern: &str, is_literal: bool) {
+    let Some(replacement) = prompt(&format!("Replace '{pattern}' with: "), "", None) else {
+        return;
+    };
+
+    let Ok(project_dir) = call_function::<_, String>("getcwd", ()) else {
+        return;
+    };
+
+    let pattern = pattern.to_owned();
+    std::thread::spawn(move || {
+        let result = get_rename_files(&project_dir, &pattern, &replacement, is_literal);
+
+        main_thread::run(move || {
+            open_rename_preview(project_dir, &pattern, &replacement, result);
+        });
+    });
+}
+
+///
+/// Show the preview of the collected replacements (or the `rg` error)
+///
+fn open_rename_preview(
+    project_dir: String,
+    pattern: &str,
+    replacement: &str,
+    result: Result<Vec<RenameFile>, String>,
+) {
+    const LOGGER_PREFIX: &'static str = "[ workspace_rename ]";
+
+    let files = match result {
+        Ok(files) => files,
+        Err(error) => {
+            nvim::print!("{LOGGER_PREFIX} {error}");
+            return;
+        }
+    };
+
+    if files.is_empty() {
+        nvim::print!("{LOGGER_PREFIX} No match for '{pattern}'.");
+        return;
+    }
+
+    let (lines, rows) = render_preview_lines(&files);
+
+    let Ok(mut preview_buffer) = create_buf(false, true) else {
+        return;
+    };
+
+    let opts = OptionOpts::builder().buffer(preview_buffer.clone()).build();
+    let _ = set_option_value("bufhidden", "wipe", &opts);
+    let _ = preview_buffer.set_lines(.., true, lines.iter().map(|v| v.as_str()));
+    let _ = set_option_value("modifiable", false, &opts);
+
+    let preview_window = create_popup_window(&PopupWindowOptions {
+        border: WindowBorder::Rounded,
+        window_width_ratio: Some(0.8),
+        window_height_ratio: Some(0.8),
+        auto_width: false,
+        auto_height: false,
+        buffer: Some(preview_buffer.handle()),
+        title: Some(format!(" Rename '{pattern}' -> '{replacement}' ")),
+        footer: Some(" x: toggle  a: apply  q: close ".to_string()),
+        ..Default::default()
+    });
+
+    *WORKSPACE_RENAME_STATE.lock().unwrap() = Some(RenamePreview {
+        project_dir,
+        files,
+        rows,
+        preview_window,
+    });
+
+    let key_bindings: Vec<(&str, &str, Box<dyn Fn()>)> = vec![
+        (
+            "x",
+            "Workspace rename: Toggle the hunk",
+            Box::new(toggle_hunk_under_cursor),
+        ),
+        (
+            "<Tab>",
+            "Workspace rename: Toggle the hunk",
+            Box::new(toggle_hunk_under_cursor),
+        ),
+        ("a", "Workspace rename: Apply", Box::new(apply_rename)),
+        ("q", "Workspace rename: Close", Box::new(close_preview)),
+        ("<ESC>", "Workspace rename: Close", Box::new(close_preview)),
+    ];
+
+    for bindings in key_bindings {
+        let _ = preview_buffer.set_keymap(
+            Mode::Normal,
+            bindings.0,
+            "",
+            &SetKeymapOpts::builder()
+                .desc(bindings.1)
+                .callback(move |_| {
+                    bindings.2();
+                    ()
+                })
+                .silent(true)
+                .build(),
+        );
+    }
+}
+
+///
+///
+///
+pub fn setup() {
+    let user_commands: Vec<(&str, &str, bool)> = vec![
+        (
+            "WorkspaceRename",
+            "Rename the regex pattern across the workspace files",
+            false,
+        ),
+        (
+            "WorkspaceRenameLiteral",
+            "Rename the literal text across the workspace files",
+            true,
+        ),
+    ];
+
+    for (name, desc, is_literal) in user_commands {
+        let _ = create_user_command(
+            name,
+            move |_: CommandArgs| {
+                workspace_rename(is_literal);
+                ()
+            },
+            &CreateCommandOpts::builder().desc(desc).build(),
+        );
+    }
+}
+
+use crate::{
+    extended_api::prompt,
+    main_thread,
+    picker::{PopupWindow, PopupWindowOptions, create_popup_window},
+    utils::get_data_dir,
+};
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use nvim_oxi::{
+    self as nvim,
+    api::{
+        Window, call_function, command, create_buf, create_user_command,
+        opts::{CreateCommandOpts, OptionOpts, SetKeymapOpts},
+        set_option_value,
+        types::{CommandArgs, Mode, WindowBorder},
+    },
+};